@@ -0,0 +1,109 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{error::Error, fmt, fs::File, io, path::Path};
+
+use arrow::{error::ArrowError, ipc::writer::FileWriter, record_batch::RecordBatch};
+use npc_engine_utils::SimulationData;
+use parquet::{
+    arrow::ArrowWriter, basic::Compression, errors::ParquetError,
+    file::properties::WriterProperties,
+};
+
+use crate::{agent_metrics_batch, world_summary_batch};
+
+/// An error while exporting simulation data.
+#[derive(Debug)]
+pub enum ExportError {
+    /// A file could not be created
+    Io(io::Error),
+    /// The data could not be converted or written as Arrow
+    Arrow(ArrowError),
+    /// The data could not be written as Parquet
+    Parquet(ParquetError),
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportError::Io(error) => write!(f, "cannot create file: {error}"),
+            ExportError::Arrow(error) => write!(f, "cannot write Arrow data: {error}"),
+            ExportError::Parquet(error) => write!(f, "cannot write Parquet data: {error}"),
+        }
+    }
+}
+
+impl Error for ExportError {}
+
+impl From<io::Error> for ExportError {
+    fn from(error: io::Error) -> Self {
+        ExportError::Io(error)
+    }
+}
+
+impl From<ArrowError> for ExportError {
+    fn from(error: ArrowError) -> Self {
+        ExportError::Arrow(error)
+    }
+}
+
+impl From<ParquetError> for ExportError {
+    fn from(error: ParquetError) -> Self {
+        ExportError::Parquet(error)
+    }
+}
+
+/// Writes a record batch as a Snappy-compressed Parquet file.
+pub fn write_parquet(path: impl AsRef<Path>, batch: &RecordBatch) -> Result<(), ExportError> {
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    let mut writer = ArrowWriter::try_new(File::create(path)?, batch.schema(), Some(properties))?;
+    writer.write(batch)?;
+    writer.close()?;
+    Ok(())
+}
+
+/// Writes a record batch as an Arrow IPC file.
+pub fn write_arrow(path: impl AsRef<Path>, batch: &RecordBatch) -> Result<(), ExportError> {
+    let mut writer = FileWriter::try_new(File::create(path)?, &batch.schema())?;
+    writer.write(batch)?;
+    writer.finish()?;
+    Ok(())
+}
+
+/// Writes the tables of `data` in `dir` as `{name}-agents.parquet` and `{name}-world.parquet`.
+pub fn export_parquet(
+    data: &SimulationData,
+    dir: impl AsRef<Path>,
+    name: &str,
+) -> Result<(), ExportError> {
+    let dir = dir.as_ref();
+    write_parquet(
+        dir.join(format!("{name}-agents.parquet")),
+        &agent_metrics_batch(data)?,
+    )?;
+    write_parquet(
+        dir.join(format!("{name}-world.parquet")),
+        &world_summary_batch(data)?,
+    )
+}
+
+/// Writes the tables of `data` in `dir` as `{name}-agents.arrow` and `{name}-world.arrow`.
+pub fn export_arrow(
+    data: &SimulationData,
+    dir: impl AsRef<Path>,
+    name: &str,
+) -> Result<(), ExportError> {
+    let dir = dir.as_ref();
+    write_arrow(
+        dir.join(format!("{name}-agents.arrow")),
+        &agent_metrics_batch(data)?,
+    )?;
+    write_arrow(
+        dir.join(format!("{name}-world.arrow")),
+        &world_summary_batch(data)?,
+    )
+}
@@ -0,0 +1,16 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+//! This is an export of the simulation data of the [NPC engine](https://crates.io/crates/npc-engine-core/) as [Apache Arrow](https://arrow.apache.org) and [Parquet](https://parquet.apache.org) files.
+//!
+//! It contains the following features:
+//! - Conversions of the rows of [SimulationData](npc_engine_utils::SimulationData) into Arrow record batches, one for agent metrics ([agent_metrics_batch]) and one for world summaries ([world_summary_batch]).
+//! - Writers of these tables as Parquet files ([export_parquet]) and Arrow IPC files ([export_arrow]), to analyze batch simulations with pandas or polars.
+
+mod export;
+mod tables;
+
+pub use export::*;
+pub use tables::*;
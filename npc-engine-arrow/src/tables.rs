@@ -0,0 +1,98 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::sync::Arc;
+
+use arrow::{
+    array::{ArrayRef, Float32Array, Float64Array, StringArray, UInt32Array, UInt64Array},
+    datatypes::{DataType, Field, Schema},
+    error::ArrowError,
+    record_batch::RecordBatch,
+};
+use npc_engine_utils::SimulationData;
+
+/// Returns the columns identifying the run and tick of each row.
+fn run_columns<'a>(
+    rows: impl Iterator<Item = (&'a str, Option<u64>, u64)> + Clone,
+) -> (Vec<Field>, Vec<ArrayRef>) {
+    let fields = vec![
+        Field::new("run", DataType::Utf8, false),
+        Field::new("seed", DataType::UInt64, true),
+        Field::new("tick", DataType::UInt64, false),
+    ];
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(
+            rows.clone().map(|(run, _, _)| run),
+        )),
+        Arc::new(UInt64Array::from_iter(
+            rows.clone().map(|(_, seed, _)| seed),
+        )),
+        Arc::new(UInt64Array::from_iter_values(rows.map(|(_, _, tick)| tick))),
+    ];
+    (fields, columns)
+}
+
+/// Returns the agent metrics of `data` as a record batch, one row per planning.
+///
+/// The planning time is in microseconds, in column `planning_time_us`.
+pub fn agent_metrics_batch(data: &SimulationData) -> Result<RecordBatch, ArrowError> {
+    let rows = &data.agent_rows;
+    let (mut fields, mut columns) = run_columns(
+        rows.iter()
+            .map(|row| (row.run.as_str(), row.seed, row.tick)),
+    );
+    fields.extend([
+        Field::new("agent", DataType::UInt32, false),
+        Field::new("visits", DataType::UInt32, false),
+        Field::new("node_count", DataType::UInt64, false),
+        Field::new("edge_count", DataType::UInt64, false),
+        Field::new("planning_time_us", DataType::UInt64, false),
+        Field::new("value", DataType::Float32, false),
+        Field::new("task", DataType::Utf8, false),
+    ]);
+    columns.extend([
+        Arc::new(UInt32Array::from_iter_values(
+            rows.iter().map(|row| row.agent.0),
+        )) as ArrayRef,
+        Arc::new(UInt32Array::from_iter_values(
+            rows.iter().map(|row| row.visits),
+        )),
+        Arc::new(UInt64Array::from_iter_values(
+            rows.iter().map(|row| row.node_count as u64),
+        )),
+        Arc::new(UInt64Array::from_iter_values(
+            rows.iter().map(|row| row.edge_count as u64),
+        )),
+        Arc::new(UInt64Array::from_iter_values(
+            rows.iter().map(|row| row.planning_time.as_micros() as u64),
+        )),
+        Arc::new(Float32Array::from_iter_values(
+            rows.iter().map(|row| row.value),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            rows.iter().map(|row| row.task.as_str()),
+        )),
+    ]);
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)
+}
+
+/// Returns the world summaries of `data` as a record batch, one row per summary.
+///
+/// There is one column per value name, see [world_columns](SimulationData::world_columns),
+/// which is null in the rows not having that value.
+pub fn world_summary_batch(data: &SimulationData) -> Result<RecordBatch, ArrowError> {
+    let rows = &data.world_rows;
+    let (mut fields, mut columns) = run_columns(
+        rows.iter()
+            .map(|row| (row.run.as_str(), row.seed, row.tick)),
+    );
+    for name in data.world_columns() {
+        fields.push(Field::new(name, DataType::Float64, true));
+        columns.push(Arc::new(Float64Array::from_iter(
+            rows.iter().map(|row| row.values.get(name).copied()),
+        )));
+    }
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)
+}
@@ -0,0 +1,166 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{collections::BTreeMap, marker::PhantomData, sync::Arc};
+
+use bevy::prelude::*;
+use big_brain::prelude::*;
+use npc_engine_core::{
+    AgentId, Domain, Edges, IdleTask, MCTSConfiguration, Node, StateDiffRef, StateValueEstimator,
+};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{BevyDomain, NpcSuspended, TaskCompleted};
+
+/// A utility scorer evaluating states of the domain, the counterpart of a big-brain scorer for planning.
+///
+/// Big-brain scorers are systems scoring the ECS world, which cannot score the hypothetical states explored by the planner.
+/// A state scorer computes the same utility from the state of the domain instead, see [ScorerEstimator].
+pub trait StateScorer<D: Domain>: Send + Sync {
+    /// Returns the utility of `state` for `agent`, between 0 and 1.
+    fn score(&self, state: StateDiffRef<D>, agent: AgentId) -> f32;
+}
+
+impl<D: Domain, F> StateScorer<D> for F
+where
+    F: Fn(StateDiffRef<D>, AgentId) -> f32 + Send + Sync,
+{
+    fn score(&self, state: StateDiffRef<D>, agent: AgentId) -> f32 {
+        self(state, agent)
+    }
+}
+
+/// A state value estimator using weighted utility scorers as heuristic instead of rollouts.
+///
+/// The scores are combined with a big-brain [Measure], as the scorers of a big-brain thinker are,
+/// and scaled into the values of the domain.
+#[derive(Clone)]
+pub struct ScorerEstimator<D: Domain> {
+    scorers: Vec<(Arc<dyn StateScorer<D>>, f32)>,
+    measure: Arc<dyn Measure>,
+    scale: f32,
+}
+
+impl<D: Domain> ScorerEstimator<D> {
+    /// Creates an estimator without scorers, combining scores with `measure` and multiplying them by `scale`.
+    pub fn new(measure: impl Measure + 'static, scale: f32) -> Self {
+        Self {
+            scorers: Vec::new(),
+            measure: Arc::new(measure),
+            scale,
+        }
+    }
+
+    /// Adds a scorer with its weight in the measure.
+    pub fn with_scorer(mut self, scorer: impl StateScorer<D> + 'static, weight: f32) -> Self {
+        self.scorers.push((Arc::new(scorer), weight));
+        self
+    }
+
+    /// Returns the combined utility of `state` for `agent`, scaled.
+    pub fn utility(&self, state: StateDiffRef<D>, agent: AgentId) -> f32 {
+        let scores: Vec<_> = self
+            .scorers
+            .iter()
+            .map(|(scorer, weight)| {
+                let mut score = Score::default();
+                // Scores outside of [0, 1] are rejected by big-brain
+                score.set(scorer.score(state, agent).clamp(0., 1.));
+                (score, *weight)
+            })
+            .collect();
+        let inputs = scores
+            .iter()
+            .map(|(score, weight)| (score, *weight))
+            .collect();
+        self.measure.calculate(inputs) * self.scale
+    }
+}
+
+impl<D: Domain> StateValueEstimator<D> for ScorerEstimator<D> {
+    fn estimate(
+        &mut self,
+        _rnd: &mut ChaCha8Rng,
+        _config: &MCTSConfiguration,
+        initial_state: &D::State,
+        _start_tick: u64,
+        node: &Node<D>,
+        edges: &Edges<D>,
+        _depth: u32,
+    ) -> Option<BTreeMap<AgentId, f32>> {
+        if edges.unexpanded_count() == 0 {
+            return None;
+        }
+        let state = StateDiffRef::new(initial_state, node.diff());
+        Some(
+            node.agents()
+                .into_iter()
+                .map(|agent| (agent, self.utility(state, agent)))
+                .collect(),
+        )
+    }
+}
+
+/// A big-brain action letting the planner decide the next task of the actor.
+///
+/// The actor must have an [NpcAgent](crate::NpcAgent) and an [NpcSuspended] component while big-brain controls it.
+/// When the action is requested, [NpcSuspended] is removed so that the agent plans and executes its chosen task.
+/// The action succeeds when that task completes, fails if it fails, and the agent is then suspended again.
+#[derive(Clone, Component, Debug, ActionBuilder)]
+pub struct MctsAction;
+
+/// A plugin running the [MctsAction]s of domain `D`, to be added along the [NpcEnginePlugin](crate::NpcEnginePlugin) and big-brain's plugin.
+pub struct MctsActionPlugin<D: BevyDomain>(PhantomData<fn() -> D>);
+
+impl<D: BevyDomain> Default for MctsActionPlugin<D> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<D: BevyDomain> Plugin for MctsActionPlugin<D> {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PreUpdate,
+            mcts_action_system::<D>.in_set(BigBrainSet::Actions),
+        );
+    }
+}
+
+fn mcts_action_system<D: BevyDomain>(
+    mut commands: Commands,
+    mut completed: EventReader<TaskCompleted<D>>,
+    mut actions: Query<(&Actor, &mut ActionState), With<MctsAction>>,
+) {
+    // The outcome of the last planned task of each entity, the initial idle tasks of resumed agents being ignored
+    let outcomes: BTreeMap<Entity, bool> = completed
+        .read()
+        .filter(|event| event.active_task.task.downcast_ref::<IdleTask>().is_none())
+        .filter_map(|event| event.entity.map(|entity| (entity, event.succeeded)))
+        .collect();
+    for (Actor(actor), mut state) in &mut actions {
+        match *state {
+            ActionState::Requested => {
+                commands.entity(*actor).remove::<NpcSuspended>();
+                *state = ActionState::Executing;
+            }
+            ActionState::Executing => {
+                if let Some(succeeded) = outcomes.get(actor) {
+                    commands.entity(*actor).insert(NpcSuspended);
+                    *state = if *succeeded {
+                        ActionState::Success
+                    } else {
+                        ActionState::Failure
+                    };
+                }
+            }
+            ActionState::Cancelled => {
+                commands.entity(*actor).insert(NpcSuspended);
+                *state = ActionState::Failure;
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,29 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use bevy::prelude::Component;
+use npc_engine_core::{ActiveTask, AgentId, Domain};
+
+/// The identity of an agent of the NPC engine, making its entity plan and execute tasks.
+///
+/// The plugin adds an [NpcActiveTask] component to entities when this component is added.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NpcAgent(pub AgentId);
+
+/// Suspends the planning and execution of an agent while present, for example while another AI controls its entity.
+///
+/// The agent is removed from the executor when this component is added, and added back, planning at the next tick, when it is removed.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NpcSuspended;
+
+/// The task an agent is currently executing, updated by the plugin at every tick.
+#[derive(Component)]
+pub struct NpcActiveTask<D: Domain>(pub Option<ActiveTask<D>>);
+
+impl<D: Domain> Default for NpcActiveTask<D> {
+    fn default() -> Self {
+        Self(None)
+    }
+}
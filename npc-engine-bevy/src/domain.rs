@@ -0,0 +1,21 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use bevy::prelude::World;
+use npc_engine_utils::ExecutableDomain;
+
+/// A domain that can be executed within a Bevy app, see [NpcEnginePlugin](crate::NpcEnginePlugin).
+///
+/// The ECS world is the source of truth: at the start of each tick, its state is copied into the state of the domain,
+/// on which tasks are then executed and agents plan.
+pub trait BevyDomain: ExecutableDomain<State: Clone + Default + Send + Sync> + Send + Sync {
+    /// Updates the state of the domain from the ECS world, called at the start of each tick.
+    fn sync_state(world: &mut World, state: &mut Self::State);
+
+    /// Updates the ECS world from the state of the domain after tasks were executed, called at the end of each tick (by default does nothing).
+    ///
+    /// This allows tasks to act on the world directly, instead of through the [TaskCompleted](crate::TaskCompleted) events.
+    fn apply_state(_world: &mut World, _state: &Self::State) {}
+}
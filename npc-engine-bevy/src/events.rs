@@ -0,0 +1,27 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use bevy::prelude::{Entity, Event};
+use npc_engine_core::{ActiveTask, Domain};
+
+/// Sent when an agent starts a task, either chosen by planning or following its previous task.
+#[derive(Event)]
+pub struct TaskStarted<D: Domain> {
+    /// The entity of the agent, None if the entity was despawned
+    pub entity: Option<Entity>,
+    /// The started task
+    pub active_task: ActiveTask<D>,
+}
+
+/// Sent when a task of an agent is completed, successfully or not.
+#[derive(Event)]
+pub struct TaskCompleted<D: Domain> {
+    /// The entity of the agent, None if the entity was despawned
+    pub entity: Option<Entity>,
+    /// The completed task
+    pub active_task: ActiveTask<D>,
+    /// Whether the task was executed, false if it was invalid when it ended
+    pub succeeded: bool,
+}
@@ -0,0 +1,164 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::collections::BTreeMap;
+
+use bevy::prelude::{Entity, Resource};
+use npc_engine_core::{
+    ActiveTask, ActiveTasks, AgentId, Domain, IdleTask, MCTSConfiguration, MCTS,
+};
+use npc_engine_utils::{ExecutionQueue, ExecutorState};
+
+use crate::BevyDomain;
+
+/// The tasks started and completed during a tick, collected through the executor state callbacks.
+pub(crate) struct TaskEvents<D: Domain> {
+    pub(crate) started: Vec<ActiveTask<D>>,
+    pub(crate) completed: Vec<(ActiveTask<D>, bool)>,
+}
+
+impl<D: Domain> Default for TaskEvents<D> {
+    fn default() -> Self {
+        Self {
+            started: Vec::new(),
+            completed: Vec::new(),
+        }
+    }
+}
+
+impl<D: Domain> ExecutorState<D> for TaskEvents<D> {
+    fn on_task_chosen(&mut self, active_task: &ActiveTask<D>) {
+        self.started.push(active_task.clone());
+    }
+    fn on_task_completed(&mut self, active_task: &ActiveTask<D>) {
+        self.completed.push((active_task.clone(), true));
+    }
+    fn on_task_failed(&mut self, active_task: &ActiveTask<D>) {
+        self.completed.push((active_task.clone(), false));
+    }
+}
+
+/// The executor owned by the [NpcEnginePlugin](crate::NpcEnginePlugin), as a resource.
+///
+/// At every [step](Self::step), it executes the tasks ending at the current tick and
+/// plans for the agents that have no subsequent task, as the
+/// [SimpleExecutor](npc_engine_utils::SimpleExecutor) does, but on a state synchronized from the ECS world.
+#[derive(Resource)]
+pub struct NpcExecutor<D: BevyDomain> {
+    /// The attached MCTS configuration
+    mcts_config: MCTSConfiguration,
+    /// The current state of the domain
+    pub(crate) state: D::State,
+    /// The current queue of tasks
+    queue: ExecutionQueue<D>,
+    /// The entities of the agents
+    pub(crate) entities: BTreeMap<AgentId, Entity>,
+    /// The events of the current tick
+    pub(crate) events: TaskEvents<D>,
+    /// The current tick
+    tick: u64,
+}
+
+impl<D: BevyDomain> NpcExecutor<D> {
+    /// Creates a new executor without agents.
+    pub fn new(mcts_config: MCTSConfiguration) -> Self {
+        Self {
+            mcts_config,
+            state: Default::default(),
+            queue: ExecutionQueue::new(Default::default()),
+            entities: Default::default(),
+            events: Default::default(),
+            tick: 0,
+        }
+    }
+
+    /// Adds an agent executed by `entity`, that will plan at the next tick.
+    ///
+    /// Returns false if the agent already exists.
+    pub fn add_agent(&mut self, agent: AgentId, entity: Entity) -> bool {
+        if self.entities.insert(agent, entity).is_some() {
+            return false;
+        }
+        self.queue
+            .queue_task(self.tick, agent, Box::new(IdleTask), &self.state);
+        true
+    }
+
+    /// Removes an agent and its active task, if any, and returns that task.
+    pub fn remove_agent(&mut self, agent: AgentId) -> Option<ActiveTask<D>> {
+        self.entities.remove(&agent);
+        self.queue.remove_agent_task(agent)
+    }
+
+    /// Returns the agent executed by `entity`, if any.
+    pub fn agent_of(&self, entity: Entity) -> Option<AgentId> {
+        self.entities
+            .iter()
+            .find_map(|(agent, other)| (*other == entity).then_some(*agent))
+    }
+
+    /// Executes the tasks ending at the current tick, plans for the agents without a subsequent task, and advances the tick.
+    pub fn step(&mut self) {
+        loop {
+            match self.queue.task_queue().iter().next() {
+                Some(active_task) if active_task.end <= self.tick => {}
+                _ => break,
+            }
+            let active_task = self.queue.pop_first_task();
+            let active_agent = active_task.agent;
+            let tick = active_task.end;
+
+            // Execute the task and apply its effects
+            let (diff, new_task) =
+                self.queue
+                    .execute_task(&active_task, &self.state, &mut self.events, |_| {});
+            D::apply_diff(diff, &mut self.state);
+
+            // Continue with the next task, or plan for one
+            let new_task = new_task.unwrap_or_else(|| {
+                let mut mcts = MCTS::<D>::new_with_tasks(
+                    self.state.clone(),
+                    active_agent,
+                    tick,
+                    self.queue.task_queue().clone(),
+                    self.mcts_config.clone(),
+                    self.events.create_state_value_estimator(),
+                    None,
+                );
+                mcts.run().unwrap_or_else(|| Box::new(IdleTask))
+            });
+            self.queue.queue_chosen_task(
+                tick,
+                active_agent,
+                new_task,
+                &self.state,
+                &mut self.events,
+            );
+        }
+        self.tick += 1;
+    }
+
+    /// Returns the current tick.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Returns the current state of the domain.
+    pub fn state(&self) -> &D::State {
+        &self.state
+    }
+
+    /// Returns the active tasks of all agents.
+    pub fn active_tasks(&self) -> &ActiveTasks<D> {
+        self.queue.task_queue()
+    }
+
+    /// Returns the active task of `agent`, if any.
+    pub fn active_task(&self, agent: AgentId) -> Option<&ActiveTask<D>> {
+        self.active_tasks()
+            .iter()
+            .find(|active_task| active_task.agent == agent)
+    }
+}
@@ -0,0 +1,30 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+//! This is the [Bevy](https://bevyengine.org) integration of the [NPC engine](https://crates.io/crates/npc-engine-core/).
+//!
+//! It contains the following features:
+//! - A plugin ([NpcEnginePlugin]) owning an executor ([NpcExecutor]) that executes tasks and plans for agents at every fixed update.
+//! - A trait ([BevyDomain]) to synchronize the state of the ECS world into the state of the domain at each tick, and optionally back.
+//! - Components for the identity of agents ([NpcAgent]) and their active task ([NpcActiveTask]).
+//! - A component suspending the planning of an agent ([NpcSuspended]), to hand its entity over to another AI.
+//! - Events sent when tasks start ([TaskStarted]) and complete ([TaskCompleted]).
+//! - With feature `big-brain`, an interop with [big-brain](https://github.com/zkat/big-brain): utility scorers of domain states as planning heuristic ([ScorerEstimator]), and planning as a big-brain action ([MctsAction]).
+
+#[cfg(feature = "big-brain")]
+mod big_brain_interop;
+mod components;
+mod domain;
+mod events;
+mod executor;
+mod plugin;
+
+#[cfg(feature = "big-brain")]
+pub use big_brain_interop::*;
+pub use components::*;
+pub use domain::*;
+pub use events::*;
+pub use executor::*;
+pub use plugin::*;
@@ -0,0 +1,353 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::marker::PhantomData;
+
+use bevy::prelude::*;
+use npc_engine_core::MCTSConfiguration;
+
+use crate::{
+    BevyDomain, NpcActiveTask, NpcAgent, NpcExecutor, NpcSuspended, TaskCompleted, TaskStarted,
+};
+
+/// A plugin running the NPC engine for domain `D` at every fixed update.
+///
+/// The plugin owns an [NpcExecutor] resource, and at every fixed update:
+/// - registers the entities with a new [NpcAgent] component, and unregisters those whose component was removed,
+/// - unregisters the agents with a new [NpcSuspended] component, and registers back those whose component was removed,
+/// - synchronizes the state of the domain from the world with [BevyDomain::sync_state],
+/// - executes the finished tasks and plans for the agents that need a new task,
+/// - updates the world with [BevyDomain::apply_state],
+/// - updates the [NpcActiveTask] components and sends the [TaskStarted] and [TaskCompleted] events.
+pub struct NpcEnginePlugin<D: BevyDomain> {
+    mcts_config: MCTSConfiguration,
+    _domain: PhantomData<fn() -> D>,
+}
+
+impl<D: BevyDomain> NpcEnginePlugin<D> {
+    /// Creates a plugin planning with the given configuration.
+    pub fn new(mcts_config: MCTSConfiguration) -> Self {
+        Self {
+            mcts_config,
+            _domain: PhantomData,
+        }
+    }
+}
+
+impl<D: BevyDomain> Plugin for NpcEnginePlugin<D> {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(NpcExecutor::<D>::new(self.mcts_config.clone()))
+            .add_event::<TaskStarted<D>>()
+            .add_event::<TaskCompleted<D>>()
+            .add_systems(
+                FixedUpdate,
+                (
+                    register_agents::<D>,
+                    unregister_agents::<D>,
+                    suspend_agents::<D>,
+                    resume_agents::<D>,
+                    sync_state::<D>,
+                    step_executor::<D>,
+                    apply_state::<D>,
+                    update_active_tasks::<D>,
+                    send_task_events::<D>,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// The filter of the agents to register: new and not suspended.
+type NewAgents = (Added<NpcAgent>, Without<NpcSuspended>);
+
+fn register_agents<D: BevyDomain>(
+    mut commands: Commands,
+    mut executor: ResMut<NpcExecutor<D>>,
+    agents: Query<(Entity, &NpcAgent), NewAgents>,
+) {
+    for (entity, agent) in &agents {
+        if executor.add_agent(agent.0, entity) {
+            commands
+                .entity(entity)
+                .insert(NpcActiveTask::<D>::default());
+        } else {
+            log::warn!("{} is already executed by another entity", agent.0);
+        }
+    }
+}
+
+fn unregister_agents<D: BevyDomain>(
+    mut executor: ResMut<NpcExecutor<D>>,
+    mut removed: RemovedComponents<NpcAgent>,
+) {
+    for entity in removed.read() {
+        if let Some(agent) = executor.agent_of(entity) {
+            executor.remove_agent(agent);
+        }
+    }
+}
+
+fn suspend_agents<D: BevyDomain>(
+    mut executor: ResMut<NpcExecutor<D>>,
+    mut agents: Query<(&NpcAgent, &mut NpcActiveTask<D>), Added<NpcSuspended>>,
+) {
+    for (agent, mut active_task) in &mut agents {
+        executor.remove_agent(agent.0);
+        active_task.0 = None;
+    }
+}
+
+fn resume_agents<D: BevyDomain>(
+    mut commands: Commands,
+    mut executor: ResMut<NpcExecutor<D>>,
+    mut resumed: RemovedComponents<NpcSuspended>,
+    agents: Query<(&NpcAgent, Has<NpcActiveTask<D>>)>,
+) {
+    for entity in resumed.read() {
+        if let Ok((agent, has_active_task)) = agents.get(entity) {
+            if executor.add_agent(agent.0, entity) && !has_active_task {
+                commands
+                    .entity(entity)
+                    .insert(NpcActiveTask::<D>::default());
+            }
+        }
+    }
+}
+
+fn sync_state<D: BevyDomain>(world: &mut World) {
+    world.resource_scope(|world, mut executor: Mut<NpcExecutor<D>>| {
+        D::sync_state(world, &mut executor.state);
+    });
+}
+
+fn step_executor<D: BevyDomain>(mut executor: ResMut<NpcExecutor<D>>) {
+    executor.step();
+}
+
+fn apply_state<D: BevyDomain>(world: &mut World) {
+    world.resource_scope(|world, executor: Mut<NpcExecutor<D>>| {
+        D::apply_state(world, &executor.state);
+    });
+}
+
+fn update_active_tasks<D: BevyDomain>(
+    executor: Res<NpcExecutor<D>>,
+    mut agents: Query<(&NpcAgent, &mut NpcActiveTask<D>)>,
+) {
+    for (agent, mut active_task) in &mut agents {
+        active_task.0 = executor.active_task(agent.0).cloned();
+    }
+}
+
+fn send_task_events<D: BevyDomain>(
+    mut executor: ResMut<NpcExecutor<D>>,
+    mut started: EventWriter<TaskStarted<D>>,
+    mut completed: EventWriter<TaskCompleted<D>>,
+) {
+    let executor = &mut *executor;
+    let entities = &executor.entities;
+    // Completions happened before the starts of the next tasks
+    for (active_task, succeeded) in executor.events.completed.drain(..) {
+        completed.send(TaskCompleted {
+            entity: entities.get(&active_task.agent).copied(),
+            active_task,
+            succeeded,
+        });
+    }
+    for active_task in executor.events.started.drain(..) {
+        started.send(TaskStarted {
+            entity: entities.get(&active_task.agent).copied(),
+            active_task,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use bevy::prelude::*;
+    use npc_engine_core::{
+        impl_task_boxed_methods, AgentId, AgentValue, Behavior, Context, ContextMut, Domain,
+        MCTSConfiguration, StateDiffRef, Task, TaskDuration,
+    };
+
+    use crate::{
+        BevyDomain, NpcActiveTask, NpcAgent, NpcEnginePlugin, NpcExecutor, NpcSuspended,
+        TaskCompleted, TaskStarted,
+    };
+
+    /// A counter that agents increment, mirrored in a resource of the world
+    struct CounterDomain;
+
+    #[derive(Resource, Default)]
+    struct Counter(u32);
+
+    impl Domain for CounterDomain {
+        type State = u32;
+        type Diff = Option<u32>;
+        type DisplayAction = ();
+
+        fn list_behaviors() -> &'static [&'static dyn Behavior<Self>] {
+            &[&CounterBehavior]
+        }
+
+        fn get_current_value(
+            _tick: u64,
+            state_diff: StateDiffRef<Self>,
+            _agent: AgentId,
+        ) -> AgentValue {
+            let value = state_diff.diff.unwrap_or(*state_diff.initial_state);
+            AgentValue::new(value as f32).unwrap()
+        }
+
+        fn update_visible_agents(
+            _start_tick: u64,
+            ctx: Context<Self>,
+            agents: &mut BTreeSet<AgentId>,
+        ) {
+            agents.insert(ctx.agent);
+        }
+    }
+
+    impl BevyDomain for CounterDomain {
+        fn sync_state(world: &mut World, state: &mut u32) {
+            *state = world.resource::<Counter>().0;
+        }
+
+        fn apply_state(world: &mut World, state: &u32) {
+            world.resource_mut::<Counter>().0 = *state;
+        }
+    }
+
+    #[derive(Copy, Clone, Debug)]
+    struct CounterBehavior;
+
+    impl Behavior<CounterDomain> for CounterBehavior {
+        fn add_own_tasks(
+            &self,
+            _ctx: Context<CounterDomain>,
+            tasks: &mut Vec<Box<dyn Task<CounterDomain>>>,
+        ) {
+            tasks.push(Box::new(Increment));
+        }
+
+        fn is_valid(&self, _ctx: Context<CounterDomain>) -> bool {
+            true
+        }
+    }
+
+    #[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+    struct Increment;
+
+    impl Task<CounterDomain> for Increment {
+        fn duration(&self, _ctx: Context<CounterDomain>) -> TaskDuration {
+            1
+        }
+
+        fn is_valid(&self, _ctx: Context<CounterDomain>) -> bool {
+            true
+        }
+
+        fn execute(&self, ctx: ContextMut<CounterDomain>) -> Option<Box<dyn Task<CounterDomain>>> {
+            let value = ctx.state_diff.diff.unwrap_or(*ctx.state_diff.initial_state);
+            *ctx.state_diff.diff = Some(value + 1);
+            None
+        }
+
+        fn display_action(&self) -> <CounterDomain as Domain>::DisplayAction {}
+
+        impl_task_boxed_methods!(CounterDomain);
+    }
+
+    const CONFIG: MCTSConfiguration = MCTSConfiguration {
+        allow_invalid_tasks: false,
+        visits: 10,
+        depth: 3,
+        exploration: 1.414,
+        discount_hl: 15.,
+        seed: Some(0),
+        planning_task_duration: None,
+    };
+
+    #[derive(Resource, Default)]
+    struct EventCounts {
+        started: usize,
+        completed: usize,
+    }
+
+    fn count_events(
+        mut counts: ResMut<EventCounts>,
+        mut started: EventReader<TaskStarted<CounterDomain>>,
+        mut completed: EventReader<TaskCompleted<CounterDomain>>,
+    ) {
+        counts.started += started.read().count();
+        counts.completed += completed.read().count();
+    }
+
+    #[test]
+    fn plugin_plans_and_executes_agents() {
+        let mut app = App::new();
+        app.add_plugins(NpcEnginePlugin::<CounterDomain>::new(CONFIG))
+            .init_resource::<Counter>()
+            .init_resource::<EventCounts>()
+            .add_systems(FixedPostUpdate, count_events);
+        let entity = app.world_mut().spawn(NpcAgent(AgentId(0))).id();
+        for _ in 0..5 {
+            app.world_mut().run_schedule(FixedUpdate);
+            app.world_mut().run_schedule(FixedPostUpdate);
+        }
+
+        let executor = app.world().resource::<NpcExecutor<CounterDomain>>();
+        assert_eq!(executor.tick(), 5);
+        assert_eq!(executor.agent_of(entity), Some(AgentId(0)));
+        // The initial idle task ends at tick 1, then the agent increments once per tick
+        assert_eq!(app.world().resource::<Counter>().0, 3);
+        let active_task = app
+            .world()
+            .get::<NpcActiveTask<CounterDomain>>(entity)
+            .unwrap();
+        let task = &active_task.0.as_ref().unwrap().task;
+        assert!(task.downcast_ref::<Increment>().is_some());
+        let counts = app.world().resource::<EventCounts>();
+        assert_eq!(counts.started, 4);
+        assert_eq!(counts.completed, 4);
+
+        // Despawning the entity removes the agent
+        app.world_mut().despawn(entity);
+        app.world_mut().run_schedule(FixedUpdate);
+        let executor = app.world().resource::<NpcExecutor<CounterDomain>>();
+        assert!(executor.active_tasks().is_empty());
+    }
+
+    #[test]
+    fn suspended_agents_do_not_plan() {
+        let mut app = App::new();
+        app.add_plugins(NpcEnginePlugin::<CounterDomain>::new(CONFIG))
+            .init_resource::<Counter>();
+        let entity = app
+            .world_mut()
+            .spawn((NpcAgent(AgentId(0)), NpcSuspended))
+            .id();
+        for _ in 0..3 {
+            app.world_mut().run_schedule(FixedUpdate);
+        }
+        assert_eq!(app.world().resource::<Counter>().0, 0);
+        let executor = app.world().resource::<NpcExecutor<CounterDomain>>();
+        assert_eq!(executor.agent_of(entity), None);
+
+        // Resuming the agent makes it plan at the next tick
+        app.world_mut().entity_mut(entity).remove::<NpcSuspended>();
+        for _ in 0..3 {
+            app.world_mut().run_schedule(FixedUpdate);
+        }
+        assert_eq!(app.world().resource::<Counter>().0, 1);
+
+        app.world_mut().entity_mut(entity).insert(NpcSuspended);
+        app.world_mut().run_schedule(FixedUpdate);
+        let executor = app.world().resource::<NpcExecutor<CounterDomain>>();
+        assert!(executor.active_tasks().is_empty());
+    }
+}
@@ -0,0 +1,104 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::path::Path;
+
+use lumberjacks_web::{Lumberjack, LumberjackTask, LumberjacksDomain, State};
+use npc_engine_core::{AgentId, Domain};
+use npc_engine_utils::{Coord2D, GridMap, ReplayFile, Scenario};
+use serde::Deserialize;
+
+/// A domain whose initial state can be loaded from a file by the command-line tool.
+pub trait CliDomain: Domain
+where
+    Self::State: Clone,
+{
+    /// Loads the initial state from `path`.
+    fn load_state(path: &Path) -> Result<Self::State, String>;
+
+    /// Loads a [ReplayFile] from `path`, verifies it and returns its final state, by default replays are not supported.
+    fn load_replay_state(_path: &Path) -> Result<Self::State, String> {
+        Err("replays are not supported for this domain".into())
+    }
+
+    /// Returns the agents of `state`, the first one plans by default.
+    fn agents(state: &Self::State) -> Vec<AgentId>;
+}
+
+/// The position of a lumberjack in a [ForestScenario].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub struct ForestAgent {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// A forest of the lumberjacks of the web demo, whose map has a `T` for each tree.
+pub type ForestScenario = Scenario<ForestAgent, GridMap>;
+
+/// Returns the initial state of a forest scenario.
+pub fn forest_state(scenario: &ForestScenario) -> State {
+    let map = &scenario.map;
+    let width = map.width() as i32;
+    let height = map.height() as i32;
+    let trees = (0..height)
+        .flat_map(|y| (0..width).map(move |x| Coord2D::new(x, y)))
+        .map(|position| map.tile(position) == Some('T'))
+        .collect();
+    let lumberjacks = scenario
+        .agents
+        .iter()
+        .map(|agent| {
+            let lumberjack = Lumberjack {
+                position: Coord2D::new(agent.data.x, agent.data.y),
+                wood: 0,
+            };
+            (agent.id, lumberjack)
+        })
+        .collect();
+    State {
+        width,
+        height,
+        trees,
+        lumberjacks,
+    }
+}
+
+/// Returns the initial state of a forest scenario, if all lumberjacks stand on free cells of its map.
+pub fn validated_forest_state(scenario: &ForestScenario) -> Result<State, String> {
+    let state = forest_state(scenario);
+    let is_free = |position: Coord2D| {
+        (0..state.width).contains(&position.x)
+            && (0..state.height).contains(&position.y)
+            && !state.is_tree(position)
+    };
+    match state
+        .lumberjacks
+        .iter()
+        .find(|(_, lumberjack)| !is_free(lumberjack.position))
+    {
+        Some((agent, _)) => Err(format!("{agent} is not on a free cell of the map")),
+        None => Ok(state),
+    }
+}
+
+impl CliDomain for LumberjacksDomain {
+    fn load_state(path: &Path) -> Result<State, String> {
+        let scenario = ForestScenario::load(path).map_err(|error| error.to_string())?;
+        validated_forest_state(&scenario)
+    }
+
+    fn load_replay_state(path: &Path) -> Result<State, String> {
+        let file =
+            ReplayFile::<State, LumberjackTask>::load(path).map_err(|error| error.to_string())?;
+        file.check_domain(LumberjacksDomain::REPLAY_DOMAIN)
+            .map_err(|error| error.to_string())?;
+        file.verify::<LumberjacksDomain>()
+            .map_err(|error| error.to_string())
+    }
+
+    fn agents(state: &State) -> Vec<AgentId> {
+        state.lumberjacks.keys().copied().collect()
+    }
+}
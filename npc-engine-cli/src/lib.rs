@@ -0,0 +1,21 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+//! This is a command-line tool running and analyzing searches of the [NPC engine](https://crates.io/crates/npc-engine-core/), for scripted tuning sweeps and bug reports.
+//!
+//! It contains the following features:
+//! - Command-line options ([CliOptions]) selecting the scenario, the agent, the configuration of the search and the exports.
+//! - A trait ([CliDomain]) for domains whose initial state can be loaded from a file, implemented for the forest of lumberjacks of the web demo.
+//! - A run of a search ([run]) printing its statistics ([SearchReport]), as text or JSON, and exporting its tree and plan.
+
+mod forest;
+mod options;
+mod report;
+mod run;
+
+pub use forest::*;
+pub use options::*;
+pub use report::*;
+pub use run::*;
@@ -0,0 +1,18 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use lumberjacks_web::LumberjacksDomain;
+use npc_engine_cli::{run, CliOptions};
+
+fn main() {
+    let options = CliOptions::from_args(std::env::args_os()).unwrap_or_else(|error| error.exit());
+    match run::<LumberjacksDomain>(&options) {
+        Ok(report) => print!("{report}"),
+        Err(error) => {
+            eprintln!("error: {error}");
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,174 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{ffi::OsString, path::PathBuf, str::FromStr};
+
+use clap::{App, Arg, ArgMatches};
+use npc_engine_core::AgentId;
+use npc_engine_utils::TreeLayout;
+
+/// The options of the command-line tool.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CliOptions {
+    /// The file of the initial state
+    pub scenario: PathBuf,
+    /// Whether the file of the initial state is a replay file, planning from its final state
+    pub replay: bool,
+    /// The agent planning, the first agent of the state if None
+    pub agent: Option<AgentId>,
+    /// The file of [PlannerSettings](npc_engine_utils::PlannerSettings) to plan with, default settings if None
+    pub settings: Option<PathBuf>,
+    /// Overrides the number of visits of the settings
+    pub visits: Option<u32>,
+    /// Overrides the search depth of the settings
+    pub depth: Option<u32>,
+    /// Overrides the exploration factor of the settings
+    pub exploration: Option<f32>,
+    /// Overrides the seed of the settings
+    pub seed: Option<u64>,
+    /// Whether to print the report as JSON instead of text
+    pub json: bool,
+    /// The file to export the tree to as JSON, if any
+    pub tree: Option<PathBuf>,
+    /// The layout of the exported tree
+    pub tree_layout: TreeLayout,
+    /// The maximum depth of the exported tree
+    pub tree_depth: usize,
+    /// The file to export the tree to in Graphviz's dot format, if any
+    pub dot: Option<PathBuf>,
+    /// The file to export the plan to as JSON, if any
+    pub plan: Option<PathBuf>,
+    /// The maximum number of steps of the exported plan
+    pub plan_steps: usize,
+}
+
+fn parse<T: FromStr>(matches: &ArgMatches, name: &str) -> Result<Option<T>, clap::Error> {
+    matches
+        .value_of(name)
+        .map(|value| {
+            value.parse().map_err(|_| {
+                clap::Error::with_description(
+                    &format!("Invalid value \"{value}\" for --{name}"),
+                    clap::ErrorKind::InvalidValue,
+                )
+            })
+        })
+        .transpose()
+}
+
+fn value_arg<'a>(name: &'a str, value_name: &'a str, help: &'a str) -> Arg<'a, 'a> {
+    Arg::with_name(name)
+        .long(name)
+        .takes_value(true)
+        .value_name(value_name)
+        .help(help)
+}
+
+impl CliOptions {
+    /// Parses the options from command-line arguments, the first being the name of the program.
+    pub fn from_args<I, T>(args: I) -> Result<Self, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let matches = App::new("npc-engine-cli")
+            .version(env!("CARGO_PKG_VERSION"))
+            .about("Runs a search of the NPC engine on a scenario and reports its statistics")
+            .arg(
+                Arg::with_name("scenario")
+                    .required(true)
+                    .help("Sets the scenario file, in JSON"),
+            )
+            .arg(
+                Arg::with_name("replay")
+                    .long("replay")
+                    .short("r")
+                    .help("Reads the scenario file as a replay file, verifies it and plans from its final state"),
+            )
+            .arg(
+                value_arg(
+                    "agent",
+                    "id",
+                    "Sets the planning agent, the first one by default",
+                )
+                .short("a"),
+            )
+            .arg(
+                value_arg(
+                    "settings",
+                    "file",
+                    "Sets the planner settings file, in JSON",
+                )
+                .short("s"),
+            )
+            .arg(value_arg(
+                "visits",
+                "count",
+                "Overrides the number of visits",
+            ))
+            .arg(value_arg("depth", "ticks", "Overrides the search depth"))
+            .arg(value_arg(
+                "exploration",
+                "factor",
+                "Overrides the exploration factor",
+            ))
+            .arg(value_arg("seed", "seed", "Overrides the seed"))
+            .arg(
+                Arg::with_name("json")
+                    .long("json")
+                    .help("Prints the report as JSON"),
+            )
+            .arg(value_arg("tree", "file", "Exports the tree as JSON"))
+            .arg(
+                Arg::with_name("flat")
+                    .long("flat")
+                    .help("Exports the tree as a flat list of nodes"),
+            )
+            .arg(
+                value_arg(
+                    "tree-depth",
+                    "depth",
+                    "Sets the maximum depth of the exported tree",
+                )
+                .default_value("8"),
+            )
+            .arg(value_arg(
+                "dot",
+                "file",
+                "Exports the tree in Graphviz's dot format",
+            ))
+            .arg(value_arg("plan", "file", "Exports the plan as JSON"))
+            .arg(
+                value_arg(
+                    "plan-steps",
+                    "count",
+                    "Sets the maximum number of steps of the exported plan",
+                )
+                .default_value("16"),
+            )
+            .get_matches_from_safe(args)?;
+        Ok(Self {
+            scenario: matches.value_of("scenario").unwrap().into(),
+            replay: matches.is_present("replay"),
+            agent: parse(&matches, "agent")?.map(AgentId),
+            settings: matches.value_of("settings").map(PathBuf::from),
+            visits: parse(&matches, "visits")?,
+            depth: parse(&matches, "depth")?,
+            exploration: parse(&matches, "exploration")?,
+            seed: parse(&matches, "seed")?,
+            json: matches.is_present("json"),
+            tree: matches.value_of("tree").map(PathBuf::from),
+            tree_layout: if matches.is_present("flat") {
+                TreeLayout::Flat
+            } else {
+                TreeLayout::Nested
+            },
+            tree_depth: parse(&matches, "tree-depth")?.unwrap(),
+            dot: matches.value_of("dot").map(PathBuf::from),
+            plan: matches.value_of("plan").map(PathBuf::from),
+            plan_steps: parse(&matches, "plan-steps")?.unwrap(),
+        })
+    }
+}
@@ -0,0 +1,92 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::fmt;
+
+use npc_engine_core::{AgentId, Domain, MCTS};
+use serde::Serialize;
+
+/// A task explored at the root of a search, see [SearchReport].
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct RootTaskReport {
+    /// The task, in its debug format
+    pub task: String,
+    /// The number of visits of the task
+    pub visits: usize,
+    /// The expected value of the task for the planning agent
+    pub value: f32,
+}
+
+/// The statistics of a search.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SearchReport {
+    /// The planning agent
+    pub agent: AgentId,
+    /// The seed of the search
+    pub seed: u64,
+    /// The chosen task, in its debug format, if any
+    pub best_task: Option<String>,
+    /// The number of visits done
+    pub visits: u32,
+    /// The number of nodes of the tree
+    pub node_count: usize,
+    /// The number of edges of the tree
+    pub edge_count: usize,
+    /// The computation time in milliseconds
+    pub time_ms: f64,
+    /// The estimated value of the root for the planning agent
+    pub value: f32,
+    /// The tasks explored at the root, sorted by decreasing visits
+    pub root_tasks: Vec<RootTaskReport>,
+}
+
+impl SearchReport {
+    /// Collects the statistics of a finished search.
+    pub fn new<D: Domain>(mcts: &MCTS<D>, best_task: Option<String>) -> Self {
+        let agent = mcts.agent();
+        let mut root_tasks: Vec<_> = mcts
+            .get_edges(&mcts.root_node())
+            .into_iter()
+            .flat_map(|edges| edges.expanded_tasks())
+            .map(|(task, edge)| {
+                let edge = edge.lock().unwrap();
+                RootTaskReport {
+                    task: format!("{task:?}"),
+                    visits: edge.visits(),
+                    value: edge.q_value(agent),
+                }
+            })
+            .collect();
+        root_tasks.sort_by(|a, b| b.visits.cmp(&a.visits).then_with(|| a.task.cmp(&b.task)));
+        Self {
+            agent,
+            seed: mcts.seed(),
+            best_task,
+            visits: mcts.visits_done(),
+            node_count: mcts.node_count(),
+            edge_count: mcts.edge_count(),
+            time_ms: mcts.time().as_secs_f64() * 1000.,
+            value: mcts.q_value_at_root(agent),
+            root_tasks,
+        }
+    }
+}
+
+impl fmt::Display for SearchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let best_task = self.best_task.as_deref().unwrap_or("none");
+        writeln!(f, "{} chooses {best_task} (seed {})", self.agent, self.seed)?;
+        writeln!(
+            f,
+            "{} visits, {} nodes, {} edges in {:.2} ms, root value {:.3}",
+            self.visits, self.node_count, self.edge_count, self.time_ms, self.value
+        )?;
+        writeln!(f, "{:>8} {:>10}  task", "visits", "value")?;
+        for task in &self.root_tasks {
+            writeln!(f, "{:>8} {:>10.3}  {}", task.visits, task.value, task.task)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,263 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use npc_engine_core::{
+    graphviz::{plot_mcts_tree_with_options, PlotOptions},
+    AgentId, ConfigError, MCTSConfiguration, MCTS,
+};
+use npc_engine_utils::{
+    export_plan_json, export_tree_json, PlannerSettings, SettingsError, TreeExportOptions,
+};
+
+use crate::{CliDomain, CliOptions, SearchReport};
+
+/// An error of the command-line tool.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CliError {
+    /// The initial state could not be loaded
+    Load(String),
+    /// The settings could not be loaded
+    Settings(SettingsError),
+    /// The configuration is invalid after applying the overrides
+    InvalidConfiguration(ConfigError),
+    /// The state has no agent
+    NoAgent,
+    /// The planning agent is not in the state
+    UnknownAgent(AgentId),
+    /// An export could not be written
+    Export(String),
+}
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Load(error) => write!(f, "cannot load state: {error}"),
+            CliError::Settings(error) => write!(f, "{error}"),
+            CliError::InvalidConfiguration(error) => {
+                write!(f, "invalid MCTS configuration: {error}")
+            }
+            CliError::NoAgent => write!(f, "the state has no agent"),
+            CliError::UnknownAgent(agent) => write!(f, "agent {agent} is not in the state"),
+            CliError::Export(error) => write!(f, "cannot export: {error}"),
+        }
+    }
+}
+impl std::error::Error for CliError {}
+
+/// Returns the MCTS configuration of the settings of `options`, or of default settings, with the overrides of `options`.
+pub fn planner_config(options: &CliOptions) -> Result<MCTSConfiguration, CliError> {
+    let mut config = match &options.settings {
+        Some(path) => {
+            PlannerSettings::load(path)
+                .map_err(CliError::Settings)?
+                .mcts
+        }
+        None => MCTSConfiguration::builder()
+            .build()
+            .expect("Default configuration is valid"),
+    };
+    if let Some(visits) = options.visits {
+        config.visits = visits;
+    }
+    if let Some(depth) = options.depth {
+        config.depth = depth;
+    }
+    if let Some(exploration) = options.exploration {
+        config.exploration = exploration;
+    }
+    if let Some(seed) = options.seed {
+        config.seed = Some(seed);
+    }
+    config.validate().map_err(CliError::InvalidConfiguration)?;
+    Ok(config)
+}
+
+/// Writes an export to `path` through `write`.
+fn export(
+    path: &Path,
+    write: impl FnOnce(&mut BufWriter<File>) -> io::Result<()>,
+) -> Result<(), CliError> {
+    File::create(path)
+        .and_then(|file| {
+            let mut file = BufWriter::new(file);
+            write(&mut file)?;
+            file.flush()
+        })
+        .map_err(|error| CliError::Export(format!("{}: {error}", path.display())))
+}
+
+/// Runs a search of domain `D` as set by `options`, writes the requested exports, and returns the report to print.
+pub fn run<D>(options: &CliOptions) -> Result<String, CliError>
+where
+    D: CliDomain,
+    D::State: Clone,
+{
+    let state = if options.replay {
+        D::load_replay_state(&options.scenario)
+    } else {
+        D::load_state(&options.scenario)
+    }
+    .map_err(CliError::Load)?;
+    let agents = D::agents(&state);
+    let agent = match options.agent {
+        Some(agent) if agents.contains(&agent) => agent,
+        Some(agent) => return Err(CliError::UnknownAgent(agent)),
+        None => *agents.first().ok_or(CliError::NoAgent)?,
+    };
+    let config = planner_config(options)?;
+    let mut mcts = MCTS::<D>::new(state, agent, config);
+    let best_task = mcts.run().map(|task| format!("{task:?}"));
+
+    if let Some(path) = &options.tree {
+        let tree_options = TreeExportOptions {
+            max_depth: options.tree_depth,
+            ..Default::default()
+        };
+        let json = export_tree_json(&mcts, &tree_options, options.tree_layout);
+        export(path, |file| file.write_all(json.as_bytes()))?;
+    }
+    if let Some(path) = &options.dot {
+        let plot_options = PlotOptions {
+            max_depth: options.tree_depth,
+            ..Default::default()
+        };
+        export(path, |file| {
+            plot_mcts_tree_with_options(&mcts, plot_options, file)
+        })?;
+    }
+    if let Some(path) = &options.plan {
+        let json = export_plan_json(&mcts, options.plan_steps);
+        export(path, |file| file.write_all(json.as_bytes()))?;
+    }
+
+    let report = SearchReport::new(&mcts, best_task);
+    Ok(if options.json {
+        serde_json::to_string_pretty(&report).expect("Reports are always serializable") + "\n"
+    } else {
+        report.to_string()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use lumberjacks_web::LumberjacksDomain;
+
+    use super::*;
+
+    fn example() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/forest.json")
+    }
+
+    fn options(args: &[&str]) -> CliOptions {
+        let example = example();
+        let mut all_args = vec!["npc-engine-cli", example.to_str().unwrap()];
+        all_args.extend(args);
+        CliOptions::from_args(all_args).unwrap()
+    }
+
+    #[test]
+    fn options_are_parsed() {
+        let options = options(&["-a", "1", "--visits", "50", "--flat", "--plan-steps", "4"]);
+        assert_eq!(options.agent, Some(AgentId(1)));
+        assert_eq!(options.visits, Some(50));
+        assert_eq!(options.depth, None);
+        assert_eq!(options.tree_layout, npc_engine_utils::TreeLayout::Flat);
+        assert_eq!(options.tree_depth, 8);
+        assert_eq!(options.plan_steps, 4);
+        assert!(CliOptions::from_args(["npc-engine-cli"]).is_err());
+        assert!(CliOptions::from_args(["npc-engine-cli", "a.json", "--visits", "many"]).is_err());
+    }
+
+    #[test]
+    fn search_is_reported_and_exported() {
+        let dir = std::env::temp_dir().join(format!("npc-engine-cli-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let tree = dir.join("tree.json");
+        let plan = dir.join("plan.json");
+        let dot = dir.join("tree.dot");
+        let options = options(&[
+            "--visits",
+            "100",
+            "--seed",
+            "3",
+            "--json",
+            "--tree",
+            tree.to_str().unwrap(),
+            "--plan",
+            plan.to_str().unwrap(),
+            "--dot",
+            dot.to_str().unwrap(),
+        ]);
+        let report: serde_json::Value =
+            serde_json::from_str(&run::<LumberjacksDomain>(&options).unwrap()).unwrap();
+        assert_eq!(report["agent"], 0);
+        assert_eq!(report["seed"], 3);
+        assert_eq!(report["visits"], 100);
+        assert!(report["best_task"].is_string());
+        assert!(!report["root_tasks"].as_array().unwrap().is_empty());
+        let tree: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&tree).unwrap()).unwrap();
+        assert_eq!(tree["visits"], 100);
+        assert!(fs::read_to_string(&plan).unwrap().contains("steps"));
+        assert!(fs::read_to_string(&dot).unwrap().starts_with("digraph"));
+        fs::remove_dir_all(&dir).unwrap();
+
+        // The same seed gives the same text report, apart from the computation time
+        let text = |options: &CliOptions| {
+            let report = run::<LumberjacksDomain>(options).unwrap();
+            let lines: Vec<_> = report.lines().map(str::to_string).collect();
+            (lines[0].clone(), lines[2..].to_vec())
+        };
+        let options = self::options(&["--visits", "50", "--seed", "1"]);
+        assert_eq!(text(&options), text(&options));
+        assert!(text(&options).0.starts_with("A0 chooses"));
+    }
+
+    #[test]
+    fn replays_are_planned_from() {
+        let mut demo = lumberjacks_web::Demo::new(2);
+        for _ in 0..5 {
+            demo.step();
+        }
+        let path = std::env::temp_dir().join(format!("npc-engine-cli-{}.json", std::process::id()));
+        fs::write(&path, demo.replay_json()).unwrap();
+        let mut options = options(&["--replay", "--visits", "20", "--json"]);
+        options.scenario = path.clone();
+        let report: serde_json::Value =
+            serde_json::from_str(&run::<LumberjacksDomain>(&options).unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(report["visits"], 20);
+    }
+
+    #[test]
+    fn errors_are_reported() {
+        assert_eq!(
+            run::<LumberjacksDomain>(&options(&["-a", "7"])),
+            Err(CliError::UnknownAgent(AgentId(7)))
+        );
+        assert_eq!(
+            run::<LumberjacksDomain>(&options(&["--visits", "0"])),
+            Err(CliError::InvalidConfiguration(ConfigError::ZeroVisits))
+        );
+        assert!(matches!(
+            run::<LumberjacksDomain>(&options(&["--replay"])),
+            Err(CliError::Load(_))
+        ));
+        let mut missing = options(&[]);
+        missing.scenario = "missing.json".into();
+        assert!(matches!(
+            run::<LumberjacksDomain>(&missing),
+            Err(CliError::Load(_))
+        ));
+    }
+}
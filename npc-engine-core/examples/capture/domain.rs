@@ -19,7 +19,9 @@ use crate::{
     state::{Diff, State},
 };
 
+#[derive(Default)]
 pub enum DisplayAction {
+    #[default]
     Wait,
     Pick,
     Shoot(AgentId),
@@ -30,12 +32,6 @@ pub enum DisplayAction {
     WorldStep,
 }
 
-impl Default for DisplayAction {
-    fn default() -> Self {
-        Self::Wait
-    }
-}
-
 impl fmt::Debug for DisplayAction {
     fn fmt(&self, f: &'_ mut fmt::Formatter) -> fmt::Result {
         match &self {
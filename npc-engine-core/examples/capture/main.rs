@@ -64,8 +64,8 @@ impl ExecutorStateLocal<CaptureDomain> for CaptureGameExecutorState {
     fn init_task_queue(&self, state: &State) -> ActiveTasks<CaptureDomain> {
         state
             .agents
-            .iter()
-            .map(|(id, _)| ActiveTask::new_with_end(0, 0, *id, Box::new(IdleTask)))
+            .keys()
+            .map(|id| ActiveTask::new_with_end(0, 0, *id, Box::new(IdleTask)))
             .chain(iter::once(ActiveTask::new_with_end(
                 0,
                 0,
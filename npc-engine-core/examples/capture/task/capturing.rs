@@ -66,7 +66,7 @@ impl Task<CaptureDomain> for Capturing {
 
     fn is_valid(&self, ctx: Context<CaptureDomain>) -> bool {
         let state = CaptureDomain::get_cur_state(ctx.state_diff);
-        state.agents.get(&ctx.agent).is_some()
+        state.agents.contains_key(&ctx.agent)
             && state.capture_points[self.0 as usize] == CapturePointState::Capturing(ctx.agent)
         // note: no need to check agent location, as this task is always a follow-up of StartCapturing
     }
@@ -77,7 +77,7 @@ impl Task<CaptureDomain> for Moving {
         let state = CaptureDomain::get_cur_state(ctx.state_diff);
         // This is a follow-up of StartMoving, so as the map is static, we assume
         // that as long as the agent exists, the task is valid.
-        state.agents.get(&ctx.agent).is_some()
+        state.agents.contains_key(&ctx.agent)
     }
 
     impl_task_boxed_methods!(CaptureDomain);
@@ -55,9 +55,7 @@ impl Task<CaptureDomain> for Shoot {
                 target.map_or(false, |target| {
                     // Target must be on our location or its adjacent paths.
                     target.cur_or_last_location == location
-                        || target
-                            .next_location
-                            .map_or(false, |next_location| next_location == location)
+                        || target.next_location == Some(location)
                 })
             }
         })
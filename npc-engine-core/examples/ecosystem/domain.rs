@@ -4,6 +4,7 @@
  */
 
 use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
 
 use npc_engine_core::{
     AgentId, AgentValue, Behavior, Context, Domain, DomainWithPlanningTask, StateDiffRef,
@@ -22,7 +23,7 @@ use crate::{
     state::{Access, AgentType, Diff, GlobalState, LocalState},
 };
 
-#[derive(Debug, Default)]
+#[derive(Default)]
 pub enum DisplayAction {
     #[default]
     Idle,
@@ -34,6 +35,22 @@ pub enum DisplayAction {
     WorldStep,
 }
 
+impl fmt::Debug for DisplayAction {
+    fn fmt(&self, f: &'_ mut fmt::Formatter) -> fmt::Result {
+        match &self {
+            Self::Idle => f.write_str("Idle"),
+            Self::Plan => f.write_str("Plan"),
+            Self::Move(direction) => f.write_fmt(format_args!("Move {}", direction)),
+            Self::Jump(direction) => f.write_fmt(format_args!("Jump {}", direction)),
+            Self::EatGrass => f.write_str("EatGrass"),
+            Self::EatHerbivore(direction) => {
+                f.write_fmt(format_args!("EatHerbivore {}", direction))
+            }
+            Self::WorldStep => f.write_str("WorldStep"),
+        }
+    }
+}
+
 pub struct EcosystemDomain;
 
 impl Domain for EcosystemDomain {
@@ -140,7 +157,7 @@ fn derive_local_state_radius(
             }
         })
         .collect::<Vec<_>>();
-    agents.sort_by(|a, b| a.0.cmp(&b.0));
+    agents.sort_by_key(|a| a.0);
     let agents = agents
         .into_iter()
         .take(MAX_AGENTS_ATTENTION)
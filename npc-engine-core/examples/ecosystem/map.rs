@@ -115,7 +115,8 @@ pub enum ParseGridError<T: FromStr> {
     TileError(T::Err),
     InconsistentLines,
 }
-fn parse_map_str<T: FromStr>(s: &str) -> Result<Box<[Box<[T]>]>, ParseGridError<T>> {
+type Grid<T> = Box<[Box<[T]>]>;
+fn parse_map_str<T: FromStr>(s: &str) -> Result<Grid<T>, ParseGridError<T>> {
     let mut map = Vec::new();
     let mut previous_length: Option<usize> = None;
     for line in s.split('\n') {
@@ -10,20 +10,15 @@ use crate::{behavior::DefaultBehaviour, state::State};
 
 pub type Diff = Option<State>; // if Some, use this diff, otherwise use initial state
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub enum DisplayAction {
+    #[default]
     Wait,
     Collect,
     Left,
     Right,
 }
 
-impl Default for DisplayAction {
-    fn default() -> Self {
-        Self::Wait
-    }
-}
-
 pub struct LearnDomain;
 
 impl Domain for LearnDomain {
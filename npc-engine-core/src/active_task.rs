@@ -4,10 +4,10 @@
  */
 
 use crate::{AgentId, Context, Domain, IdleTask, Task};
-use std::cmp::Ordering;
-use std::collections::BTreeSet;
-use std::hash::{Hash, Hasher};
-use std::{fmt, mem};
+use alloc::{boxed::Box, collections::BTreeSet};
+use core::cmp::Ordering;
+use core::hash::{Hash, Hasher};
+use core::{fmt, mem};
 
 /// A task associated to an agent and that is being processed by the planner.
 pub struct ActiveTask<D: Domain> {
@@ -79,8 +79,8 @@ impl<D: Domain> fmt::Debug for ActiveTask<D> {
     }
 }
 
-impl<D: Domain> std::fmt::Display for ActiveTask<D> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl<D: Domain> fmt::Display for ActiveTask<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{} {:?} ends T{}", self.agent, self.task, self.end)
     }
 }
@@ -0,0 +1,310 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use alloc::vec::{self, Vec};
+use core::{fmt, iter::FromIterator, slice};
+
+use crate::AgentId;
+
+/// The map from agents to values used in the hot path of the search, such as the current values of nodes.
+///
+/// With the default feature `agent-vec-map`, its entries are stored in an [AgentVecMap], faster for the few agents typically visible in a node.
+/// Otherwise, they are stored in a [BTreeMap](alloc::collections::BTreeMap). Either way the type and its methods are the same.
+#[derive(Clone, PartialEq, Eq)]
+pub struct AgentMap<V>(Entries<V>);
+
+#[cfg(feature = "agent-vec-map")]
+type Entries<V> = AgentVecMap<V>;
+#[cfg(not(feature = "agent-vec-map"))]
+type Entries<V> = alloc::collections::BTreeMap<AgentId, V>;
+
+#[cfg(feature = "agent-vec-map")]
+type EntriesIter<'a, V> = AgentVecMapIter<'a, V>;
+#[cfg(not(feature = "agent-vec-map"))]
+type EntriesIter<'a, V> = alloc::collections::btree_map::Iter<'a, AgentId, V>;
+
+#[cfg(feature = "agent-vec-map")]
+type EntriesIntoIter<V> = vec::IntoIter<(AgentId, V)>;
+#[cfg(not(feature = "agent-vec-map"))]
+type EntriesIntoIter<V> = alloc::collections::btree_map::IntoIter<AgentId, V>;
+
+impl<V> Default for AgentMap<V> {
+    fn default() -> Self {
+        Self(Entries::default())
+    }
+}
+
+impl<V: fmt::Debug> fmt::Debug for AgentMap<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// An iterator over the entries of an [AgentMap], ordered by agent.
+pub struct AgentMapIter<'a, V>(EntriesIter<'a, V>);
+
+impl<'a, V> Iterator for AgentMapIter<'a, V> {
+    type Item = (&'a AgentId, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+/// An owning iterator over the entries of an [AgentMap], ordered by agent.
+pub struct AgentMapIntoIter<V>(EntriesIntoIter<V>);
+
+impl<V> Iterator for AgentMapIntoIter<V> {
+    type Item = (AgentId, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<V> AgentMap<V> {
+    /// Creates an empty map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of entries.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns whether the map is empty.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Removes all entries.
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    /// Returns whether the map contains an entry for `agent`.
+    pub fn contains_key(&self, agent: &AgentId) -> bool {
+        self.0.contains_key(agent)
+    }
+
+    /// Returns the value of `agent`, if any.
+    pub fn get(&self, agent: &AgentId) -> Option<&V> {
+        self.0.get(agent)
+    }
+
+    /// Returns the value of `agent` mutably, if any.
+    pub fn get_mut(&mut self, agent: &AgentId) -> Option<&mut V> {
+        self.0.get_mut(agent)
+    }
+
+    /// Sets the value of `agent`, returns the previous value if any.
+    pub fn insert(&mut self, agent: AgentId, value: V) -> Option<V> {
+        self.0.insert(agent, value)
+    }
+
+    /// Removes the value of `agent`, if any.
+    pub fn remove(&mut self, agent: &AgentId) -> Option<V> {
+        self.0.remove(agent)
+    }
+
+    /// Returns an iterator over the entries, ordered by agent.
+    pub fn iter(&self) -> AgentMapIter<'_, V> {
+        AgentMapIter(self.0.iter())
+    }
+
+    /// Returns an iterator over the agents, in order.
+    pub fn keys(&self) -> impl Iterator<Item = &AgentId> {
+        self.0.keys()
+    }
+
+    /// Returns an iterator over the values, ordered by agent.
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.0.values()
+    }
+}
+
+impl<V> Extend<(AgentId, V)> for AgentMap<V> {
+    fn extend<I: IntoIterator<Item = (AgentId, V)>>(&mut self, iter: I) {
+        self.0.extend(iter);
+    }
+}
+
+impl<V> FromIterator<(AgentId, V)> for AgentMap<V> {
+    fn from_iter<I: IntoIterator<Item = (AgentId, V)>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl<V> IntoIterator for AgentMap<V> {
+    type Item = (AgentId, V);
+    type IntoIter = AgentMapIntoIter<V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        AgentMapIntoIter(self.0.into_iter())
+    }
+}
+
+impl<'a, V> IntoIterator for &'a AgentMap<V> {
+    type Item = (&'a AgentId, &'a V);
+    type IntoIter = AgentMapIter<'a, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// A map from agents to values stored as a vector sorted by agent.
+///
+/// Lookups are binary searches within a single allocation, which for small maps are faster
+/// than the pointer chasing of a [BTreeMap](alloc::collections::BTreeMap), while iteration stays ordered.
+#[derive(Clone, PartialEq, Eq)]
+pub struct AgentVecMap<V>(Vec<(AgentId, V)>);
+
+impl<V> Default for AgentVecMap<V> {
+    fn default() -> Self {
+        Self(Vec::new())
+    }
+}
+
+impl<V: fmt::Debug> fmt::Debug for AgentVecMap<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// An iterator over the entries of an [AgentVecMap], ordered by agent.
+pub struct AgentVecMapIter<'a, V>(slice::Iter<'a, (AgentId, V)>);
+
+impl<'a, V> Iterator for AgentVecMapIter<'a, V> {
+    type Item = (&'a AgentId, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(agent, value)| (agent, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<V> AgentVecMap<V> {
+    /// Creates an empty map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of entries.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns whether the map is empty.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Removes all entries.
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    /// Returns whether the map contains an entry for `agent`.
+    pub fn contains_key(&self, agent: &AgentId) -> bool {
+        self.position(agent).is_ok()
+    }
+
+    /// Returns the value of `agent`, if any.
+    pub fn get(&self, agent: &AgentId) -> Option<&V> {
+        self.position(agent).ok().map(|index| &self.0[index].1)
+    }
+
+    /// Returns the value of `agent` mutably, if any.
+    pub fn get_mut(&mut self, agent: &AgentId) -> Option<&mut V> {
+        self.position(agent).ok().map(|index| &mut self.0[index].1)
+    }
+
+    /// Sets the value of `agent`, returns the previous value if any.
+    pub fn insert(&mut self, agent: AgentId, value: V) -> Option<V> {
+        match self.position(&agent) {
+            Ok(index) => Some(core::mem::replace(&mut self.0[index].1, value)),
+            Err(index) => {
+                self.0.insert(index, (agent, value));
+                None
+            }
+        }
+    }
+
+    /// Removes the value of `agent`, if any.
+    pub fn remove(&mut self, agent: &AgentId) -> Option<V> {
+        self.position(agent)
+            .ok()
+            .map(|index| self.0.remove(index).1)
+    }
+
+    /// Returns an iterator over the entries, ordered by agent.
+    pub fn iter(&self) -> AgentVecMapIter<'_, V> {
+        AgentVecMapIter(self.0.iter())
+    }
+
+    /// Returns an iterator over the agents, in order.
+    pub fn keys(&self) -> impl Iterator<Item = &AgentId> {
+        self.0.iter().map(|(agent, _)| agent)
+    }
+
+    /// Returns an iterator over the values, ordered by agent.
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.0.iter().map(|(_, value)| value)
+    }
+
+    fn position(&self, agent: &AgentId) -> Result<usize, usize> {
+        self.0.binary_search_by(|(other, _)| other.cmp(agent))
+    }
+}
+
+impl<V> Extend<(AgentId, V)> for AgentVecMap<V> {
+    fn extend<I: IntoIterator<Item = (AgentId, V)>>(&mut self, iter: I) {
+        for (agent, value) in iter {
+            self.insert(agent, value);
+        }
+    }
+}
+
+impl<V> FromIterator<(AgentId, V)> for AgentVecMap<V> {
+    fn from_iter<I: IntoIterator<Item = (AgentId, V)>>(iter: I) -> Self {
+        let mut entries: Vec<_> = iter.into_iter().collect();
+        // Keep the last value of duplicated agents, as a BTreeMap would
+        entries.reverse();
+        entries.sort_by_key(|(agent, _)| *agent);
+        entries.dedup_by_key(|(agent, _)| *agent);
+        Self(entries)
+    }
+}
+
+impl<V> IntoIterator for AgentVecMap<V> {
+    type Item = (AgentId, V);
+    type IntoIter = vec::IntoIter<(AgentId, V)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a, V> IntoIterator for &'a AgentVecMap<V> {
+    type Item = (&'a AgentId, &'a V);
+    type IntoIter = AgentVecMapIter<'a, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
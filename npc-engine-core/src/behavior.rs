@@ -3,6 +3,8 @@
  *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
  */
 
+use alloc::{boxed::Box, vec::Vec};
+
 use crate::{Context, Domain, Task};
 
 /// A possibly-recursive set of possible tasks.
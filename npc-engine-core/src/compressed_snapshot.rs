@@ -0,0 +1,108 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::io::{BufReader, Read, Write};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{NodeSnapshot, SnapshotDomain, TreeSnapshot, MCTS};
+
+impl<D: SnapshotDomain> MCTS<D>
+where
+    D::Diff: Serialize,
+{
+    /// Writes a compressed binary snapshot of the search tree, one node at a time.
+    ///
+    /// Nodes are converted, encoded with bincode and compressed with zstd at the given `level`
+    /// (0 meaning the default level) as they are written, so the whole [TreeSnapshot] is never built in memory.
+    /// The dump can be read back with [CompressedSnapshotReader] or [read_compressed_snapshot].
+    pub fn dump_compressed<W: Write>(&self, writer: W, level: i32) -> bincode::Result<()> {
+        let mut encoder = zstd::Encoder::new(writer, level)?;
+        let (order, indices) = self.number_nodes();
+        bincode::serialize_into(&mut encoder, &self.snapshot_without_nodes())?;
+        bincode::serialize_into(&mut encoder, &(order.len() as u64))?;
+        for node in &order {
+            bincode::serialize_into(&mut encoder, &self.snapshot_node(node, &indices))?;
+        }
+        encoder.finish()?;
+        Ok(())
+    }
+}
+
+/// A reader of a dump written by [MCTS::dump_compressed], iterating over its nodes one at a time.
+///
+/// This allows to analyse trees that do not fit in memory.
+pub struct CompressedSnapshotReader<R: Read, Df, T> {
+    decoder: zstd::Decoder<'static, BufReader<R>>,
+    header: TreeSnapshot<Df, T>,
+    remaining_nodes: u64,
+}
+impl<R, Df, T> CompressedSnapshotReader<R, Df, T>
+where
+    R: Read,
+    Df: DeserializeOwned,
+    T: DeserializeOwned,
+{
+    /// Starts reading a dump, reading everything but the nodes.
+    pub fn new(reader: R) -> bincode::Result<Self> {
+        let mut decoder = zstd::Decoder::new(reader)?;
+        let header = bincode::deserialize_from(&mut decoder)?;
+        let remaining_nodes = bincode::deserialize_from(&mut decoder)?;
+        Ok(Self {
+            decoder,
+            header,
+            remaining_nodes,
+        })
+    }
+
+    /// Returns the snapshot of the tree without its nodes.
+    pub fn header(&self) -> &TreeSnapshot<Df, T> {
+        &self.header
+    }
+
+    /// Returns the number of nodes not read yet.
+    pub fn remaining_nodes(&self) -> u64 {
+        self.remaining_nodes
+    }
+
+    /// Reads the remaining nodes and returns the whole snapshot.
+    pub fn into_snapshot(mut self) -> bincode::Result<TreeSnapshot<Df, T>> {
+        let mut nodes = Vec::with_capacity(self.remaining_nodes as usize);
+        for node in &mut self {
+            nodes.push(node?);
+        }
+        Ok(TreeSnapshot {
+            nodes,
+            ..self.header
+        })
+    }
+}
+
+impl<R, Df, T> Iterator for CompressedSnapshotReader<R, Df, T>
+where
+    R: Read,
+    Df: DeserializeOwned,
+    T: DeserializeOwned,
+{
+    type Item = bincode::Result<NodeSnapshot<Df, T>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining_nodes == 0 {
+            return None;
+        }
+        self.remaining_nodes -= 1;
+        Some(bincode::deserialize_from(&mut self.decoder))
+    }
+}
+
+/// Reads a whole dump written by [MCTS::dump_compressed].
+pub fn read_compressed_snapshot<R, Df, T>(reader: R) -> bincode::Result<TreeSnapshot<Df, T>>
+where
+    R: Read,
+    Df: DeserializeOwned,
+    T: DeserializeOwned,
+{
+    CompressedSnapshotReader::new(reader)?.into_snapshot()
+}
@@ -3,13 +3,36 @@
  *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
  */
 
-use std::num::NonZeroU64;
+use core::{fmt, num::NonZeroU64, time::Duration};
 
 /// A functor that returns whether the planner must do an early stop.
 pub type EarlyStopCondition = dyn Fn(u32) -> bool + Send;
 
+/// A slice of computation granted to an incremental search, see [MCTS::run_with_budget](crate::MCTS::run_with_budget).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlanningBudget {
+    /// at most this number of visits
+    Visits(u32),
+    /// as many visits as fit in this wall-clock duration, at least one
+    Time(Duration),
+}
+
+/// How many children are created when a search reaches a node that is not fully expanded,
+/// see [MCTS::set_expansion_mode](crate::MCTS::set_expansion_mode).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ExpansionMode {
+    /// one new child per visit
+    #[default]
+    Single,
+    /// all remaining children at once, estimated together by [StateValueEstimator::estimate_batch](crate::StateValueEstimator::estimate_batch),
+    /// each counting as a visit, so a batch stops early rather than exceeding the visits of the search
+    Batch,
+}
+
 /// The configuration of an MCTS instance.
-#[derive(Clone, Debug, Default)]
+///
+/// It can be built with validation using [builder](Self::builder).
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
 pub struct MCTSConfiguration {
     /// if true, invalid tasks do not abort expansion or rollout, but trigger re-planning
     pub allow_invalid_tasks: bool,
@@ -26,3 +49,140 @@ pub struct MCTSConfiguration {
     /// optionally, a user-given seed
     pub seed: Option<u64>,
 }
+
+/// An invalid combination of parameters of an [MCTSConfiguration].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConfigError {
+    /// The planner must do at least one visit
+    ZeroVisits,
+    /// The search must be at least one tick deep
+    ZeroDepth,
+    /// The exploration factor must be finite and non-negative
+    InvalidExploration(f32),
+    /// The discount half life must be positive, possibly infinite
+    InvalidDiscountHalfLife(f32),
+    /// The planning task must last at least one tick
+    ZeroPlanningTaskDuration,
+}
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::ZeroVisits => write!(f, "visits must be positive"),
+            ConfigError::ZeroDepth => write!(f, "depth must be positive"),
+            ConfigError::InvalidExploration(exploration) => write!(
+                f,
+                "exploration must be finite and non-negative, got {exploration}"
+            ),
+            ConfigError::InvalidDiscountHalfLife(discount_hl) => {
+                write!(f, "discount half life must be positive, got {discount_hl}")
+            }
+            ConfigError::ZeroPlanningTaskDuration => {
+                write!(f, "planning task duration must be positive")
+            }
+        }
+    }
+}
+#[cfg(feature = "std")]
+impl std::error::Error for ConfigError {}
+
+impl MCTSConfiguration {
+    /// Returns a builder starting from reasonable defaults: 1000 visits, a depth of 10, an exploration of √2,
+    /// a discount half life of 15, no planning task and no seed.
+    pub fn builder() -> MCTSConfigurationBuilder {
+        MCTSConfigurationBuilder::default()
+    }
+
+    /// Checks that the parameters make sense, for example that the planner does at least one visit.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.visits == 0 {
+            return Err(ConfigError::ZeroVisits);
+        }
+        if self.depth == 0 {
+            return Err(ConfigError::ZeroDepth);
+        }
+        if !(self.exploration.is_finite() && self.exploration >= 0.) {
+            return Err(ConfigError::InvalidExploration(self.exploration));
+        }
+        if self.discount_hl.is_nan() || self.discount_hl <= 0. {
+            return Err(ConfigError::InvalidDiscountHalfLife(self.discount_hl));
+        }
+        Ok(())
+    }
+}
+
+/// A builder of [MCTSConfiguration] validating its parameters, see [MCTSConfiguration::builder].
+#[derive(Clone, Debug)]
+pub struct MCTSConfigurationBuilder {
+    config: MCTSConfiguration,
+    planning_task_duration: Option<u64>,
+}
+impl Default for MCTSConfigurationBuilder {
+    fn default() -> Self {
+        Self {
+            config: MCTSConfiguration {
+                allow_invalid_tasks: false,
+                visits: 1000,
+                depth: 10,
+                exploration: 1.414,
+                discount_hl: 15.,
+                planning_task_duration: None,
+                seed: None,
+            },
+            planning_task_duration: None,
+        }
+    }
+}
+impl MCTSConfigurationBuilder {
+    /// Sets whether invalid tasks trigger re-planning instead of aborting expansion or rollout.
+    pub fn allow_invalid_tasks(mut self, allow_invalid_tasks: bool) -> Self {
+        self.config.allow_invalid_tasks = allow_invalid_tasks;
+        self
+    }
+
+    /// Sets the maximum number of visits per run.
+    pub fn visits(mut self, visits: u32) -> Self {
+        self.config.visits = visits;
+        self
+    }
+
+    /// Sets the maximum tree depth per run in tick.
+    pub fn depth(mut self, depth: u32) -> Self {
+        self.config.depth = depth;
+        self
+    }
+
+    /// Sets the exploration factor of UCT.
+    pub fn exploration(mut self, exploration: f32) -> Self {
+        self.config.exploration = exploration;
+        self
+    }
+
+    /// Sets the discount factor for later reward, in half life.
+    pub fn discount_hl(mut self, discount_hl: f32) -> Self {
+        self.config.discount_hl = discount_hl;
+        self
+    }
+
+    /// Sets the duration of the planning task, in tick.
+    pub fn planning_task_duration(mut self, duration: u64) -> Self {
+        self.planning_task_duration = Some(duration);
+        self
+    }
+
+    /// Sets the seed of the planner.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.config.seed = Some(seed);
+        self
+    }
+
+    /// Validates the parameters and returns the configuration.
+    pub fn build(self) -> Result<MCTSConfiguration, ConfigError> {
+        let mut config = self.config;
+        if let Some(duration) = self.planning_task_duration {
+            config.planning_task_duration =
+                Some(NonZeroU64::new(duration).ok_or(ConfigError::ZeroPlanningTaskDuration)?);
+        }
+        config.validate()?;
+        Ok(config)
+    }
+}
@@ -0,0 +1,78 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use alloc::{string::String, vec::Vec};
+use core::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Domain, Node, StateDiffRef};
+
+/// A change of a displayed entry between two diffs, see [Domain::display_diff].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DiffChange {
+    /// An entry present only in the later diff
+    Added { key: String, value: String },
+    /// An entry whose value differs
+    Changed {
+        key: String,
+        from: String,
+        to: String,
+    },
+    /// An entry present only in the earlier diff
+    Removed { key: String, value: String },
+}
+
+impl fmt::Display for DiffChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiffChange::Added { key, value } => write!(f, "+ {key}: {value}"),
+            DiffChange::Changed { key, from, to } => write!(f, "~ {key}: {from} -> {to}"),
+            DiffChange::Removed { key, value } => write!(f, "- {key}: {value}"),
+        }
+    }
+}
+
+/// Returns the changes of the displayed entries of the diff of `to` compared to the diff of `from`, ordered by key.
+///
+/// Domains that do not implement [Domain::display_diff] have no changes.
+pub fn diff_changes<D: Domain>(
+    initial_state: &D::State,
+    from: &Node<D>,
+    to: &Node<D>,
+) -> Vec<DiffChange> {
+    let mut from = D::display_diff(StateDiffRef::new(initial_state, from.diff()));
+    let to = D::display_diff(StateDiffRef::new(initial_state, to.diff()));
+    let mut changes = Vec::new();
+    for (key, value) in to {
+        match from.remove(&key) {
+            None => changes.push(DiffChange::Added { key, value }),
+            Some(previous) if previous != value => changes.push(DiffChange::Changed {
+                key,
+                from: previous,
+                to: value,
+            }),
+            Some(_) => {}
+        }
+    }
+    changes.extend(
+        from.into_iter()
+            .map(|(key, value)| DiffChange::Removed { key, value }),
+    );
+    changes.sort_by(|a, b| a.key().cmp(b.key()));
+    changes
+}
+
+impl DiffChange {
+    /// Returns the key of the changed entry.
+    pub fn key(&self) -> &str {
+        match self {
+            DiffChange::Added { key, .. }
+            | DiffChange::Changed { key, .. }
+            | DiffChange::Removed { key, .. } => key,
+        }
+    }
+}
@@ -0,0 +1,66 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use alloc::vec::Vec;
+
+/// A per-search pool of diff buffers, re-used by rollouts instead of allocating a new diff each time.
+///
+/// This is a free list of whole diffs rather than an arena: each diff keeps its own allocations, and the pool
+/// only holds on to the diffs given back. Taken buffers are overwritten with [Domain::reset_diff](crate::Domain::reset_diff),
+/// which domains implement to keep the allocations of the buffer, for example the capacity of vectors and maps,
+/// so that domains with small but frequent diffs put less pressure on the allocator. The buffers are freed when the search is dropped.
+#[derive(Debug)]
+pub struct DiffPool<T> {
+    free: Vec<T>,
+    reused: usize,
+    allocated: usize,
+}
+
+impl<T> Default for DiffPool<T> {
+    fn default() -> Self {
+        Self {
+            free: Vec::new(),
+            reused: 0,
+            allocated: 0,
+        }
+    }
+}
+
+impl<T: Clone> DiffPool<T> {
+    /// Returns a copy of `diff`, re-using a free buffer overwritten by `reset` if any.
+    pub fn clone_of(&mut self, diff: &T, reset: impl FnOnce(&mut T, &T)) -> T {
+        match self.free.pop() {
+            Some(mut buffer) => {
+                self.reused += 1;
+                reset(&mut buffer, diff);
+                buffer
+            }
+            None => {
+                self.allocated += 1;
+                diff.clone()
+            }
+        }
+    }
+
+    /// Gives back a buffer for later re-use.
+    pub fn recycle(&mut self, diff: T) {
+        self.free.push(diff);
+    }
+
+    /// Returns the number of copies that re-used a free buffer.
+    pub fn reused(&self) -> usize {
+        self.reused
+    }
+
+    /// Returns the number of copies that needed a new buffer.
+    pub fn allocated(&self) -> usize {
+        self.allocated
+    }
+
+    /// Returns the number of free buffers.
+    pub fn free_count(&self) -> usize {
+        self.free.len()
+    }
+}
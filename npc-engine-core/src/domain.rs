@@ -3,8 +3,14 @@
  *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
  */
 
-use std::collections::{BTreeMap, BTreeSet};
-use std::hash::Hash;
+use alloc::{
+    boxed::Box,
+    collections::{BTreeMap, BTreeSet},
+    string::String,
+    vec,
+    vec::Vec,
+};
+use core::hash::Hash;
 
 use ordered_float::NotNan;
 use rand_chacha::ChaCha8Rng;
@@ -19,12 +25,12 @@ pub type AgentValue = NotNan<f32>;
 /// A domain on which the MCTS planner can plan.
 pub trait Domain: Sized + 'static {
     /// The state the MCTS plans on.
-    type State: std::fmt::Debug + Sized;
+    type State: core::fmt::Debug + Sized;
     /// A compact set of changes towards a `State` that are accumulated throughout planning.
-    type Diff: std::fmt::Debug + Default + Clone + Hash + Eq;
+    type Diff: core::fmt::Debug + Default + Clone + Hash + Eq;
     /// A representation of a display action that can be fetched from a task.
     /// We need Default trait for creating the DisplayAction for the idle placeholder task.
-    type DisplayAction: std::fmt::Debug + Default;
+    type DisplayAction: core::fmt::Debug + Default;
 
     /// Returns all behaviors available for this domain.
     fn list_behaviors() -> &'static [&'static dyn Behavior<Self>];
@@ -52,6 +58,28 @@ pub trait Domain: Sized + 'static {
         String::new()
     }
 
+    /// Gets the entries of a diff as readable key-value pairs, for example one entry per changed agent property.
+    /// This will be used to show the state changes between nodes in tree exports and explanations, see [diff_changes](crate::diff_changes).
+    fn display_diff(_state_diff: StateDiffRef<Self>) -> BTreeMap<String, String> {
+        BTreeMap::new()
+    }
+
+    /// Returns a hash of everything [update_visible_agents](Self::update_visible_agents) reads from the tick and the world state, besides the given agents.
+    /// If some, the visible agents of nodes with the same hash and the same agents with tasks are computed only once per search,
+    /// see [VisibilityCache](crate::VisibilityCache). By default, returns None, and the visible agents are always computed.
+    fn visibility_hash(_start_tick: u64, _ctx: Context<Self>) -> Option<u64> {
+        None
+    }
+
+    /// Overwrites `diff` with a copy of `source`, re-using the allocations of `diff`, used by the [DiffPool](crate::DiffPool) with feature `diff-pool`.
+    ///
+    /// By default, this calls [Clone::clone_from], but derived [Clone] implementations do not override it and allocate a new diff anyway.
+    /// For the pool to save allocations, implement this by calling `clone_from` on the fields of the diff that own buffers, such as vectors and maps.
+    #[cfg(feature = "diff-pool")]
+    fn reset_diff(diff: &mut Self::Diff, source: &Self::Diff) {
+        diff.clone_from(source);
+    }
+
     /// Gets the new agents present in a diff but not in a state.
     fn get_new_agents(_state_diff: StateDiffRef<Self>) -> Vec<AgentId> {
         vec![]
@@ -84,6 +112,44 @@ pub trait StateValueEstimator<D: Domain>: Send {
         edges: &Edges<D>,
         depth: u32,
     ) -> Option<BTreeMap<AgentId, f32>>;
+
+    /// Estimates as [estimate](Self::estimate), with a pool of diff buffers that the estimation can re-use.
+    ///
+    /// This is called instead of [estimate](Self::estimate) with feature `diff-pool`, by default it ignores the pool.
+    #[cfg(feature = "diff-pool")]
+    #[allow(clippy::too_many_arguments)]
+    fn estimate_with_pool(
+        &mut self,
+        rnd: &mut ChaCha8Rng,
+        config: &MCTSConfiguration,
+        initial_state: &D::State,
+        start_tick: u64,
+        node: &Node<D>,
+        edges: &Edges<D>,
+        depth: u32,
+        _pool: &mut crate::DiffPool<D::Diff>,
+    ) -> Option<BTreeMap<AgentId, f32>> {
+        self.estimate(rnd, config, initial_state, start_tick, node, edges, depth)
+    }
+
+    /// Estimates the values of several nodes at once, with their edges and depth, used by [ExpansionMode::Batch](crate::ExpansionMode::Batch).
+    ///
+    /// This allows to amortize or vectorize the estimation, by default it calls [estimate](Self::estimate) for each node.
+    fn estimate_batch(
+        &mut self,
+        rnd: &mut ChaCha8Rng,
+        config: &MCTSConfiguration,
+        initial_state: &D::State,
+        start_tick: u64,
+        nodes: &[(&Node<D>, &Edges<D>, u32)],
+    ) -> Vec<Option<BTreeMap<AgentId, f32>>> {
+        nodes
+            .iter()
+            .map(|(node, edges, depth)| {
+                self.estimate(rnd, config, initial_state, start_tick, node, edges, *depth)
+            })
+            .collect()
+    }
 }
 
 /// Domains who want to use planning tasks must implement this.
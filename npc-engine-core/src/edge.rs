@@ -3,15 +3,16 @@
  *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
  */
 
-use std::{
-    collections::BTreeSet,
-    fmt, mem,
-    ops::Range,
-    sync::{Arc, Mutex},
-};
+use alloc::{boxed::Box, collections::BTreeSet, sync::Arc, vec, vec::Vec};
+use core::{fmt, mem, ops::Range};
 
-use crate::{AgentId, AgentValue, Context, Domain, Node, SeededHashMap, Task, WeakNode};
+use crate::{
+    hash_map, timed_hook, AgentId, AgentValue, Context, Domain, Mutex, Node, SeededHashMap, Task,
+    WeakNode,
+};
 
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
 use rand::distributions::WeightedIndex;
 
 /// The tasks left to expand in a given node.
@@ -35,7 +36,7 @@ impl<D: Domain> fmt::Debug for Edges<D> {
 
 impl<'a, D: Domain> IntoIterator for &'a Edges<D> {
     type Item = (&'a Box<dyn Task<D>>, &'a Edge<D>);
-    type IntoIter = std::collections::hash_map::Iter<'a, Box<dyn Task<D>>, Edge<D>>;
+    type IntoIter = hash_map::Iter<'a, Box<dyn Task<D>>, Edge<D>>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.expanded_tasks.iter()
@@ -52,7 +53,7 @@ impl<D: Domain> Edges<D> {
         let ctx =
             Context::with_state_and_diff(node.tick, initial_state, &node.diff, node.active_agent);
         let unexpanded_tasks = match next_task {
-            Some(task) if task.is_valid(ctx) => {
+            Some(task) if timed_hook!(is_valid, task.is_valid(ctx)) => {
                 let weights = WeightedIndex::new([1.].iter().map(Clone::clone)).unwrap();
 
                 // Set existing child weights, only option
@@ -103,17 +104,50 @@ impl<D: Domain> Edges<D> {
         exploration: f32,
         range: Range<AgentValue>,
     ) -> Option<Box<dyn Task<D>>> {
-        let visits = self.child_visits();
-        self.expanded_tasks
-            .iter()
-            .max_by(|(_, a), (_, b)| {
-                let a = a.lock().unwrap();
-                let b = b.lock().unwrap();
-                a.uct(agent, visits, exploration, range.clone())
-                    .partial_cmp(&b.uct(agent, visits, exploration, range.clone()))
-                    .unwrap()
-            })
-            .map(|(k, _)| k.clone())
+        self.best_task_filtered(agent, exploration, range, |_| true)
+    }
+
+    /// Finds the best task as [best_task](Self::best_task), among the edges for which `keep` returns true.
+    ///
+    /// The visits of all edges are counted for exploration.
+    pub(crate) fn best_task_filtered(
+        &self,
+        agent: AgentId,
+        exploration: f32,
+        range: Range<AgentValue>,
+        keep: impl Fn(&Edge<D>) -> bool,
+    ) -> Option<Box<dyn Task<D>>> {
+        // Gather the statistics of children in contiguous arrays, so that scoring them can be vectorized
+        let count = self.expanded_tasks.len();
+        let mut tasks = Vec::with_capacity(count);
+        let mut kept = Vec::with_capacity(count);
+        let mut q_values = Vec::with_capacity(count);
+        let mut visits = Vec::with_capacity(count);
+        let mut missing = Vec::new();
+        let mut parent_child_visits = 0;
+        for (index, (task, edge)) in self.expanded_tasks.iter().enumerate() {
+            kept.push(keep(edge));
+            let edge = edge.lock().unwrap();
+            tasks.push(task);
+            match edge.q_values.get(&agent) {
+                Some(q_value) => q_values.push(*q_value),
+                None => {
+                    q_values.push(0.);
+                    missing.push(index);
+                }
+            }
+            visits.push(edge.visits as f32);
+            parent_child_visits += edge.visits;
+        }
+        let mut scores = uct_scores(&q_values, &visits, parent_child_visits, exploration, range);
+        // If parent is not present, this node is being reused and the parent leaves the horizon. Score doesn't matter
+        for index in missing {
+            scores[index] = 0.;
+        }
+        (0..count)
+            .filter(|index| kept[*index])
+            .max_by(|a, b| scores[*a].partial_cmp(&scores[*b]).unwrap())
+            .map(|index| tasks[index].clone())
     }
 
     /// Returns the weighted average q value of all child edges.
@@ -156,6 +190,11 @@ impl<D: Domain> Edges<D> {
         self.expanded_count() + self.unexpanded_count()
     }
 
+    /// Returns an iterator over the expanded tasks and their edges, in arbitrary order.
+    pub fn expanded_tasks(&self) -> impl Iterator<Item = (&Box<dyn Task<D>>, &Edge<D>)> {
+        self.expanded_tasks.iter()
+    }
+
     /// Returns the expanded edge associated to a task, None if it does not exist.
     #[allow(clippy::borrowed_box)]
     pub fn get_edge(&self, task: &Box<dyn Task<D>>) -> Option<Edge<D>> {
@@ -183,6 +222,31 @@ impl<D: Domain> Edges<D> {
     }
 }
 
+/// Computes the UCT values of children from their q-values and visits, see [EdgeInner::uct].
+///
+/// Each value only depends on the same index of the input slices, which allows the loop to be vectorized.
+pub fn uct_scores(
+    q_values: &[f32],
+    visits: &[f32],
+    parent_child_visits: usize,
+    exploration: f32,
+    range: Range<AgentValue>,
+) -> Vec<f32> {
+    let start = *range.start;
+    let width = (*(range.end - range.start)).max(f32::EPSILON);
+    let parent_ln = (parent_child_visits as f32).ln();
+    q_values
+        .iter()
+        .zip(visits)
+        .map(|(q_value, visits)| {
+            // Normalize the exploitation factor so it doesn't overshadow the exploration
+            let exploitation_value = (q_value - start) / width;
+            let exploration_value = (parent_ln / visits.max(f32::EPSILON)).sqrt();
+            exploitation_value + exploration * exploration_value
+        })
+        .collect()
+}
+
 /// Strong atomic reference counted edge.
 pub type Edge<D> = Arc<Mutex<EdgeInner<D>>>;
 
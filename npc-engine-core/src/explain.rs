@@ -0,0 +1,199 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use alloc::{boxed::Box, collections::BTreeMap, vec, vec::Vec};
+use core::fmt;
+
+use crate::{diff_changes, AgentId, DiffChange, Domain, Edge, Node, Task, MCTS};
+
+/// A task considered at the root of a search, with its statistics.
+#[derive(Clone, Debug)]
+pub struct ConsideredTask<D: Domain> {
+    /// The task
+    pub task: Box<dyn Task<D>>,
+    /// The number of visits of the task
+    pub visits: usize,
+    /// The q-value of the task for the agent of the search
+    pub value: f32,
+    /// The q-values of the task for all agents
+    pub q_values: BTreeMap<AgentId, f32>,
+    /// The state changes done by the task, see [Domain::display_diff]
+    pub changes: Vec<DiffChange>,
+}
+
+/// A step of the most visited branch beneath the chosen task.
+#[derive(Clone, Debug)]
+pub struct BranchStep<D: Domain> {
+    /// The agent doing the task
+    pub agent: AgentId,
+    /// The tick at which the task is chosen
+    pub tick: u64,
+    /// The task
+    pub task: Box<dyn Task<D>>,
+    /// The number of visits of the task
+    pub visits: usize,
+    /// The q-value of the task for its agent
+    pub value: f32,
+    /// The state changes done by the task, see [Domain::display_diff]
+    pub changes: Vec<DiffChange>,
+}
+
+/// The components of the value of the chosen task for the agent of the search.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ValueComponents {
+    /// The change of current value of the agent when the task ends
+    pub immediate: f32,
+    /// The discounted value expected in the rest of the search horizon
+    pub future: f32,
+    /// The difference of value with the best alternative, if any
+    pub margin: Option<f32>,
+}
+
+/// Why a search chose its best task, see [MCTS::explain_best_task].
+#[derive(Clone, Debug)]
+pub struct TaskExplanation<D: Domain> {
+    /// The agent of the search
+    pub agent: AgentId,
+    /// The tick at the root of the search
+    pub tick: u64,
+    /// The chosen task
+    pub chosen: ConsideredTask<D>,
+    /// The other expanded tasks, the best first
+    pub alternatives: Vec<ConsideredTask<D>>,
+    /// The most visited branch following the chosen task
+    pub dominant_branch: Vec<BranchStep<D>>,
+    /// The components of the value of the chosen task
+    pub value_components: ValueComponents,
+}
+
+impl<D: Domain> fmt::Display for TaskExplanation<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} at T{} chose {:?} (value {:.3}, {} visits)",
+            self.agent, self.tick, self.chosen.task, self.chosen.value, self.chosen.visits
+        )?;
+        write_changes(f, &self.chosen.changes)?;
+        let components = &self.value_components;
+        write!(
+            f,
+            "  immediate {:.3}, future {:.3}",
+            components.immediate, components.future
+        )?;
+        match components.margin {
+            Some(margin) => writeln!(f, ", {margin:.3} better than the best alternative")?,
+            None => writeln!(f, ", no alternative")?,
+        }
+        for alternative in &self.alternatives {
+            writeln!(
+                f,
+                "  instead of {:?} (value {:.3}, {} visits)",
+                alternative.task, alternative.value, alternative.visits
+            )?;
+        }
+        for step in &self.dominant_branch {
+            writeln!(
+                f,
+                "  then T{} {} {:?} (value {:.3}, {} visits)",
+                step.tick, step.agent, step.task, step.value, step.visits
+            )?;
+            write_changes(f, &step.changes)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_changes(f: &mut fmt::Formatter<'_>, changes: &[DiffChange]) -> fmt::Result {
+    for change in changes {
+        writeln!(f, "    {change}")?;
+    }
+    Ok(())
+}
+
+impl<D: Domain> MCTS<D> {
+    /// Explains the choice of the best task at the root, if any task was expanded.
+    ///
+    /// The explanation lists the competing tasks, the most visited branch beneath the chosen task,
+    /// and splits its value into the immediate change of current value and the expected future value.
+    pub fn explain_best_task(&self) -> Option<TaskExplanation<D>> {
+        let agent = self.agent();
+        let root = self.root_node();
+        let edges = self.get_edges(&root)?;
+        let task = edges.best_task(agent, 0., self.min_max_range(agent))?;
+        let considered = |task: Box<dyn Task<D>>, edge: &Edge<D>| {
+            let edge = edge.lock().unwrap();
+            ConsideredTask {
+                task,
+                visits: edge.visits,
+                value: edge.q_value(agent),
+                q_values: edge
+                    .q_values
+                    .iter()
+                    .map(|(agent, value)| (*agent, *value))
+                    .collect(),
+                changes: diff_changes(self.initial_state(), &root, &edge.child()),
+            }
+        };
+        let chosen_edge = edges.get_edge(&task)?;
+        let chosen = considered(task.clone(), &chosen_edge);
+        let mut alternatives: Vec<_> = edges
+            .expanded_tasks()
+            .filter(|(other, _)| *other != &task)
+            .map(|(task, edge)| considered(task.clone(), edge))
+            .collect();
+        alternatives.sort_by(|a, b| b.value.total_cmp(&a.value));
+
+        let child = chosen_edge.lock().unwrap().child();
+        let immediate = *child.current_value_or_compute(agent, self.initial_state())
+            - *root.current_value_or_compute(agent, self.initial_state());
+        let value_components = ValueComponents {
+            immediate,
+            future: chosen.value - immediate,
+            margin: alternatives
+                .first()
+                .map(|alternative| chosen.value - alternative.value),
+        };
+
+        Some(TaskExplanation {
+            agent,
+            tick: self.start_tick(),
+            chosen,
+            alternatives,
+            dominant_branch: self.dominant_branch(child),
+            value_components,
+        })
+    }
+
+    /// Follows the most visited edges from `node`, until a leaf or a node already visited.
+    fn dominant_branch(&self, mut node: Node<D>) -> Vec<BranchStep<D>> {
+        let mut path = vec![self.root_node()];
+        let mut steps = Vec::new();
+        while !path.contains(&node) {
+            let edges = match self.get_edges(&node) {
+                Some(edges) => edges,
+                None => break,
+            };
+            let best = edges
+                .expanded_tasks()
+                .max_by_key(|(_, edge)| edge.lock().unwrap().visits);
+            let (task, edge) = match best {
+                Some(best) => best,
+                None => break,
+            };
+            let edge = edge.lock().unwrap();
+            steps.push(BranchStep {
+                agent: node.active_agent,
+                tick: node.tick,
+                task: task.clone(),
+                visits: edge.visits,
+                value: edge.q_value(node.active_agent),
+                changes: diff_changes(self.initial_state(), &node, &edge.child()),
+            });
+            path.push(node);
+            node = edge.child();
+        }
+        steps
+    }
+}
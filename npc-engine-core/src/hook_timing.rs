@@ -0,0 +1,70 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{cell::RefCell, time::Duration};
+
+/// The number of calls and the total time spent in a domain hook.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HookTiming {
+    /// The number of calls
+    pub calls: u64,
+    /// The total time spent in these calls
+    pub time: Duration,
+}
+impl HookTiming {
+    fn add(&mut self, other: &Self) {
+        self.calls += other.calls;
+        self.time += other.time;
+    }
+}
+
+/// The time spent in domain hooks during a search, see [MCTS::hook_timings](crate::MCTS::hook_timings).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HookTimings {
+    /// Calls to [Domain::get_current_value](crate::Domain::get_current_value)
+    pub get_current_value: HookTiming,
+    /// Calls to [Domain::update_visible_agents](crate::Domain::update_visible_agents)
+    pub update_visible_agents: HookTiming,
+    /// Calls to [Task::execute](crate::Task::execute)
+    pub execute: HookTiming,
+    /// Calls to [Task::is_valid](crate::Task::is_valid)
+    pub is_valid: HookTiming,
+}
+impl HookTimings {
+    /// Returns the total time spent in all hooks.
+    pub fn total(&self) -> Duration {
+        self.get_current_value.time
+            + self.update_visible_agents.time
+            + self.execute.time
+            + self.is_valid.time
+    }
+
+    pub(crate) fn add(&mut self, other: &Self) {
+        self.get_current_value.add(&other.get_current_value);
+        self.update_visible_agents.add(&other.update_visible_agents);
+        self.execute.add(&other.execute);
+        self.is_valid.add(&other.is_valid);
+    }
+}
+
+thread_local! {
+    /// The timings of the hooks called on this thread since the last call to `take`.
+    static TIMINGS: RefCell<HookTimings> = RefCell::new(HookTimings::default());
+}
+
+/// Records a call to a hook on this thread.
+pub(crate) fn record(hook: fn(&mut HookTimings) -> &mut HookTiming, time: Duration) {
+    TIMINGS.with(|timings| {
+        let mut timings = timings.borrow_mut();
+        let timing = hook(&mut timings);
+        timing.calls += 1;
+        timing.time += time;
+    });
+}
+
+/// Returns the timings recorded on this thread and resets them.
+pub(crate) fn take() -> HookTimings {
+    TIMINGS.with(|timings| std::mem::take(&mut *timings.borrow_mut()))
+}
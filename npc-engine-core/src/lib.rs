@@ -12,9 +12,16 @@
 //! The core of the planner is the [MCTS] struct, which holds the state of the planner.
 //! It has two constructors, a simplified one, [new](MCTS::new), and a complete one, [new_with_tasks](MCTS::new_with_tasks).
 //! Once constructed, the [run](MCTS::run) method performs the search and returns the best task.
-//! After a search, the resulting tree can be inspected, starting from the [root node](MCTS::root_node).
+//! After a search, the resulting tree can be inspected, starting from the [root node](MCTS::root_node),
+//! and the choice of the best task explained using [explain_best_task](MCTS::explain_best_task).
+//! Long searches can report their progress, and be stopped, through a [progress callback](MCTS::set_progress_callback).
+//! Other threads, such as a user interface, can read the statistics of a running search without blocking it through [live statistics](MCTS::live_statistics).
+//! The search can also [expand all children](MCTS::set_expansion_mode) of a node at once, to estimate them in a batch with [StateValueEstimator::estimate_batch].
+//! Domains whose visibility of agents is expensive to compute can memoize it within a search by implementing [visibility_hash](Domain::visibility_hash).
+//! A search can [profile itself](MCTS::set_profiling), reporting the time spent in selection, expansion, estimation, backpropagation and domain callbacks.
+//! For strictly competitive two-agent domains, the search can [prune branches](MCTS::set_bounds_pruning) using value bounds proven from terminal nodes.
 //!
-//! The planner's search parameters are described by the [MCTSConfiguration] struct.
+//! The planner's search parameters are described by the [MCTSConfiguration] struct, which can be validated when built using [MCTSConfiguration::builder].
 //!
 //! The [MCTS] struct is generic over a [Domain], which you have to implement to describe your own planning domain.
 //! You need to implement at least these three methods:
@@ -22,35 +29,91 @@
 //! * [get_current_value](Domain::get_current_value) returns the instantaneous (not discounted) value of an agent in a given state.
 //! * [update_visible_agents](Domain::update_visible_agents) lists all agents visible from a given agent in a given state.
 //!
-//! The `graphviz` feature enables to output the search tree in the Graphviz's dot format using the [plot_mcts_tree](graphviz::plot_mcts_tree) function.
+//! Optionally, [display_diff](Domain::display_diff) makes the state changes between nodes readable, see [diff_changes].
+//!
+//! The `graphviz` feature enables to output the search tree in the Graphviz's dot format using the [plot_mcts_tree](graphviz::plot_mcts_tree) function, or [plot_mcts_tree_with_options](graphviz::plot_mcts_tree_with_options) to filter large trees by depth and visits.
+//! The `snapshot` feature enables to take serializable snapshots of the whole search tree, including diffs and tasks, using the `MCTS::snapshot` method.
+//! The `compressed-snapshot` feature additionally enables to dump very large trees to a compressed binary format node by node, using the `MCTS::dump_compressed` method.
+//! The `tracing` feature instruments the search with [tracing](https://docs.rs/tracing) spans for each planning and for the selection, expansion, rollout and backpropagation phases, and events when nodes are expanded or expansions are pruned.
+//! The `hook-timing` feature counts and times the calls to the domain hooks `get_current_value`, `update_visible_agents`, and tasks' `execute` and `is_valid` during each search, available through the `MCTS::hook_timings` method.
+//! The `agent-vec-map` feature, enabled by default as its lookups are faster for up to 32 agents in the `map_get` benchmarks of `benches/mcts.rs`, stores the per-agent values of the hot path of the search, such as the current values of nodes, in sorted vectors instead of B-trees, see [AgentMap]. It only changes how [AgentMap] stores its entries, not its API.
+//! The `diff-pool` feature makes rollouts re-use diff buffers from a per-search free list instead of allocating new diffs, see `MCTS::diff_pool`; domains keep the allocations of the buffers by implementing `Domain::reset_diff`.
+//!
+//! The `std` feature, enabled by default, can be disabled to use the planner in `no_std` environments with `alloc`, such as consoles or embedded targets.
+//! Hash maps then come from hashbrown and float functions from libm, edges are protected by a spin lock,
+//! searches are timed with the clock given to `set_clock`, and domain callbacks are not profiled.
+//! The `graphviz`, `snapshot`, `compressed-snapshot`, `tracing` and `hook-timing` features require `std`.
 //!
 //! Additional features and utilites such as execution loops are available in the [`npc-engine-utils`](https://crates.io/crates/npc-engine-utils/) crate.
 //! You might want to use them in your project as they make the planner significantly simpler to use.
 //! Most [examples](https://github.com/ethz-gtc/npc-engine/tree/main/npc-engine-core/examples) use them.
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
 mod active_task;
+mod agent_map;
 mod behavior;
+#[cfg(feature = "compressed-snapshot")]
+mod compressed_snapshot;
 mod config;
 mod context;
+mod diff_display;
+#[cfg(feature = "diff-pool")]
+mod diff_pool;
 mod domain;
 mod edge;
+mod explain;
+#[cfg(feature = "hook-timing")]
+mod hook_timing;
+mod live_statistics;
 mod mcts;
 mod node;
+mod profile;
+mod progress;
+#[cfg(feature = "snapshot")]
+mod snapshot;
 mod state_diff;
+mod sync;
 mod task;
+mod time;
 mod util;
+mod value_bounds;
+mod visibility_cache;
 
 pub use active_task::*;
+pub use agent_map::*;
 pub use behavior::*;
+#[cfg(feature = "compressed-snapshot")]
+pub use compressed_snapshot::*;
 pub use config::*;
 pub use context::*;
+pub use diff_display::*;
+#[cfg(feature = "diff-pool")]
+pub use diff_pool::*;
 pub use domain::*;
 pub use edge::*;
+pub use explain::*;
+#[cfg(feature = "hook-timing")]
+pub use hook_timing::*;
+pub use live_statistics::*;
 pub use mcts::*;
 pub use node::*;
+pub use profile::*;
+pub use progress::*;
+#[cfg(feature = "snapshot")]
+pub use snapshot::*;
 pub use state_diff::*;
+#[cfg(not(feature = "std"))]
+pub use sync::*;
+#[cfg(feature = "std")]
+use sync::*;
 pub use task::*;
+pub use time::*;
 use util::*;
+pub use value_bounds::*;
+pub use visibility_cache::*;
 
 /// The identifier of an agent, essentially a u32.
 #[derive(
@@ -61,8 +124,8 @@ pub struct AgentId(
     /// The internal identifier
     pub u32,
 );
-impl std::fmt::Display for AgentId {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Display for AgentId {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "A{}", self.0)
     }
 }
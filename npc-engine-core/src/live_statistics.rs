@@ -0,0 +1,142 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
+use core::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+
+use crate::{AgentId, Domain, Edges, SeededHashMap, Task};
+
+/// The statistics of a root task read from [LiveStatistics].
+#[derive(Clone, Debug)]
+pub struct LiveTaskStatistics<D: Domain> {
+    /// The task
+    pub task: Box<dyn Task<D>>,
+    /// The number of visits of the task
+    pub visits: usize,
+    /// The q-value of the task for the agent of the search
+    pub value: f32,
+}
+
+struct TaskSlot<D: Domain> {
+    task: Box<dyn Task<D>>,
+    visits: AtomicUsize,
+    value: AtomicU32,
+}
+
+struct LiveStatisticsInner<D: Domain> {
+    visits_done: AtomicU32,
+    node_count: AtomicUsize,
+    edge_count: AtomicUsize,
+    finished: AtomicBool,
+    slots: Vec<TaskSlot<D>>,
+    slot_of_task: SeededHashMap<Box<dyn Task<D>>, usize>,
+}
+
+/// A handle to the statistics of a search, readable from other threads while the search runs.
+///
+/// The statistics are atomics updated by the search after each visit,
+/// so that reading them neither blocks the search nor clones its tree.
+/// See [MCTS::live_statistics](crate::MCTS::live_statistics).
+pub struct LiveStatistics<D: Domain>(Arc<LiveStatisticsInner<D>>);
+
+impl<D: Domain> Clone for LiveStatistics<D> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<D: Domain> LiveStatistics<D> {
+    pub(crate) fn new(root_tasks: Vec<Box<dyn Task<D>>>) -> Self {
+        let slot_of_task = root_tasks
+            .iter()
+            .enumerate()
+            .map(|(index, task)| (task.clone(), index))
+            .collect();
+        let slots = root_tasks
+            .into_iter()
+            .map(|task| TaskSlot {
+                task,
+                visits: AtomicUsize::new(0),
+                value: AtomicU32::new(0f32.to_bits()),
+            })
+            .collect();
+        Self(Arc::new(LiveStatisticsInner {
+            visits_done: AtomicU32::new(0),
+            node_count: AtomicUsize::new(0),
+            edge_count: AtomicUsize::new(0),
+            finished: AtomicBool::new(false),
+            slots,
+            slot_of_task,
+        }))
+    }
+
+    /// Publishes the statistics of the search, called by the search thread.
+    pub(crate) fn publish(
+        &self,
+        visits_done: u32,
+        node_count: usize,
+        edge_count: usize,
+        finished: bool,
+        root_edges: &Edges<D>,
+        agent: AgentId,
+    ) {
+        let inner = &self.0;
+        for (task, edge) in root_edges.expanded_tasks() {
+            if let Some(slot) = inner.slot_of_task.get(task) {
+                let edge = edge.lock().unwrap();
+                let slot = &inner.slots[*slot];
+                slot.visits.store(edge.visits(), Ordering::Relaxed);
+                slot.value
+                    .store(edge.q_value(agent).to_bits(), Ordering::Relaxed);
+            }
+        }
+        inner.node_count.store(node_count, Ordering::Relaxed);
+        inner.edge_count.store(edge_count, Ordering::Relaxed);
+        inner.finished.store(finished, Ordering::Relaxed);
+        inner.visits_done.store(visits_done, Ordering::Release);
+    }
+
+    /// Returns the number of visits done so far.
+    pub fn visits_done(&self) -> u32 {
+        self.0.visits_done.load(Ordering::Acquire)
+    }
+
+    /// Returns the number of nodes of the search tree.
+    pub fn node_count(&self) -> usize {
+        self.0.node_count.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of edges of the search tree.
+    pub fn edge_count(&self) -> usize {
+        self.0.edge_count.load(Ordering::Relaxed)
+    }
+
+    /// Returns whether the search is finished.
+    pub fn is_finished(&self) -> bool {
+        self.0.finished.load(Ordering::Relaxed)
+    }
+
+    /// Returns the statistics of the tasks at the root, including not-yet visited ones.
+    pub fn root_tasks(&self) -> Vec<LiveTaskStatistics<D>> {
+        self.0
+            .slots
+            .iter()
+            .map(|slot| LiveTaskStatistics {
+                task: slot.task.clone(),
+                visits: slot.visits.load(Ordering::Relaxed),
+                value: f32::from_bits(slot.value.load(Ordering::Relaxed)),
+            })
+            .collect()
+    }
+
+    /// Returns the visited root task with the highest value so far, if any.
+    pub fn best_task(&self) -> Option<Box<dyn Task<D>>> {
+        self.root_tasks()
+            .into_iter()
+            .filter(|task| task.visits > 0)
+            .max_by(|a, b| a.value.total_cmp(&b.value))
+            .map(|task| task.task)
+    }
+}
@@ -3,16 +3,20 @@
  *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
  */
 
-use std::collections::HashMap;
-use std::collections::{btree_map::Entry, BTreeMap, BTreeSet, HashSet};
-use std::f32;
-use std::mem;
-use std::ops::Range;
-use std::time::{Duration, Instant};
-
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::{boxed::Box, vec::Vec};
+use core::hash::BuildHasher;
+use core::mem;
+use core::ops::Range;
+use core::time::Duration;
+
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+#[cfg(feature = "std")]
+use rand::prelude::{thread_rng, RngCore};
 use rand::{
     distributions::WeightedIndex,
-    prelude::{thread_rng, Distribution, RngCore, SeedableRng},
+    prelude::{Distribution, SeedableRng},
     Rng,
 };
 use rand_chacha::ChaCha8Rng;
@@ -26,30 +30,61 @@ use crate::*;
 // SAFETY: 0 is not NaN
 const VALUE_ZERO: AgentValue = unsafe { AgentValue::new_unchecked(0.) };
 // SAFETY: INFINITY is not NaN
-const VALUE_INFINITE: AgentValue = unsafe { AgentValue::new_unchecked(std::f32::INFINITY) };
+const VALUE_INFINITE: AgentValue = unsafe { AgentValue::new_unchecked(f32::INFINITY) };
 // SAFETY: NEG_INFINITY is not NaN
-const VALUE_NEG_INFINITE: AgentValue = unsafe { AgentValue::new_unchecked(std::f32::NEG_INFINITY) };
+const VALUE_NEG_INFINITE: AgentValue = unsafe { AgentValue::new_unchecked(f32::NEG_INFINITY) };
+
+/// Returns a seed for searches without a configured seed.
+#[cfg(feature = "std")]
+fn random_seed() -> u64 {
+    thread_rng().next_u64()
+}
+
+/// Returns a seed for searches without a configured seed.
+///
+/// Without an entropy source, successive searches use successive seeds.
+#[cfg(not(feature = "std"))]
+fn random_seed() -> u64 {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    static SEARCHES: AtomicUsize = AtomicUsize::new(0);
+    SEARCHES.fetch_add(1, Ordering::Relaxed) as u64
+}
 
 /// The state of a running planner instance.
 pub struct MCTS<D: Domain> {
     // Statistics
     time: Duration,
+    visits_done: u32,
+    pub(crate) early_stopped: bool,
+    // Whether the tree was restored from a snapshot, the next search continuing it
+    resumed: bool,
+    #[cfg(feature = "hook-timing")]
+    hook_timings: HookTimings,
+    profile: Option<SearchProfile>,
 
     // Config
-    config: MCTSConfiguration,
+    pub(crate) config: MCTSConfiguration,
     state_value_estimator: Box<dyn StateValueEstimator<D> + Send>,
     early_stop_condition: Option<Box<EarlyStopCondition>>,
+    progress_callback: Option<(u32, Box<ProgressCallback<D>>)>,
+    live_statistics: Option<LiveStatistics<D>>,
+    expansion_mode: ExpansionMode,
+    visibility_cache: VisibilityCache,
+    #[cfg(feature = "diff-pool")]
+    diff_pool: DiffPool<D::Diff>,
+    pub(crate) node_bounds: Option<NodeBounds<D>>,
 
     // Run-specific parameters
     root_agent: AgentId,
     seed: u64,
+    intentions: BTreeMap<AgentId, Box<dyn Task<D>>>,
 
     // Nodes
     root: Node<D>,
     nodes: SeededHashMap<Node<D>, Edges<D>>,
 
     // Globals
-    q_value_ranges: BTreeMap<AgentId, Range<AgentValue>>,
+    pub(crate) q_value_ranges: AgentMap<Range<AgentValue>>,
 
     // State before planning
     initial_state: D::State,
@@ -62,9 +97,10 @@ pub struct MCTS<D: Domain> {
 /// The possible outcomes from a tree policy pass.
 enum TreePolicyOutcome<D: Domain> {
     NodeCreated(u32, Node<D>, Vec<Edge<D>>), // depth, new node, path
-    NoValidTask(u32, Vec<Edge<D>>),          // depth, path
-    NoChildNode(u32, Node<D>, Vec<Edge<D>>), // depth, node, path
-    DepthLimitReached(u32, Node<D>, Vec<Edge<D>>), // depth, new node, path
+    NodesCreated(Vec<(u32, Node<D>, Vec<Edge<D>>)>), // depth, new node, path for each new node
+    NoValidTask(Vec<Edge<D>>),               // path
+    NoChildNode(Vec<Edge<D>>),               // path
+    DepthLimitReached(Vec<Edge<D>>),         // path
 }
 
 impl<D: Domain> MCTS<D> {
@@ -97,13 +133,15 @@ impl<D: Domain> MCTS<D> {
             get_task_for_agent(&tasks, root_agent).map(|active_task| active_task.task.clone());
 
         // Create new root node
-        let root = Node::new(NodeInner::new(
+        let mut visibility_cache = VisibilityCache::default();
+        let root = Node::new(NodeInner::new_with_visibility_cache(
             &initial_state,
             start_tick,
             Default::default(),
             root_agent,
             start_tick,
             tasks,
+            &mut visibility_cache,
         ));
 
         // Prepare nodes, reserve the maximum amount we could need
@@ -117,15 +155,29 @@ impl<D: Domain> MCTS<D> {
         nodes.insert(root.clone(), root_edges);
 
         // Compute seed
-        let cur_seed = config.seed.unwrap_or_else(|| thread_rng().next_u64());
+        let cur_seed = config.seed.unwrap_or_else(random_seed);
 
         MCTS {
             time: Duration::default(),
+            visits_done: 0,
+            early_stopped: false,
+            resumed: false,
+            #[cfg(feature = "hook-timing")]
+            hook_timings: Default::default(),
+            profile: None,
             config,
             state_value_estimator,
             early_stop_condition,
+            progress_callback: None,
+            live_statistics: None,
+            expansion_mode: ExpansionMode::default(),
+            visibility_cache,
+            #[cfg(feature = "diff-pool")]
+            diff_pool: Default::default(),
+            node_bounds: None,
             seed: cur_seed,
             root_agent,
+            intentions: Default::default(),
             root,
             nodes,
             q_value_ranges: Default::default(),
@@ -135,6 +187,170 @@ impl<D: Domain> MCTS<D> {
         }
     }
 
+    /// Sets the intentions of other agents, the tasks they are expected to choose at their first decision.
+    ///
+    /// When an agent has to decide for the first time in the search tree, its intention is used as a fixed move
+    /// if it is valid, instead of searching over its possible tasks. This makes plans mutually predictable
+    /// and searches cheaper. Rollouts are not affected. This must be called before running the search.
+    pub fn set_intentions(&mut self, intentions: BTreeMap<AgentId, Box<dyn Task<D>>>) {
+        self.intentions = intentions;
+        self.intentions.remove(&self.root_agent);
+    }
+
+    /// Returns the tasks available at the root, expanded or not.
+    pub fn root_tasks(&self) -> Vec<Box<dyn Task<D>>> {
+        let edges = self.nodes.get(&self.root).unwrap();
+        edges
+            .unexpanded_tasks
+            .iter()
+            .flat_map(|(_, tasks)| tasks.iter())
+            .chain(edges.expanded_tasks.keys())
+            .cloned()
+            .collect()
+    }
+
+    /// Keeps only the unexpanded tasks at the root for which `keep` returns true, given their index and the task.
+    ///
+    /// This restricts the search to some root children, for example to explore them on different threads.
+    /// This must be called before running the search, and returns the number of kept tasks.
+    pub fn retain_root_tasks(
+        &mut self,
+        mut keep: impl FnMut(usize, &dyn Task<D>) -> bool,
+    ) -> usize {
+        let ctx = Context::with_state_and_diff(
+            self.root.tick,
+            &self.initial_state,
+            &self.root.diff,
+            self.root_agent,
+        );
+        let edges = self.nodes.get_mut(&self.root).unwrap();
+        let tasks = match edges.unexpanded_tasks.take() {
+            Some((_, tasks)) => tasks,
+            None => return 0,
+        };
+        let tasks: Vec<_> = tasks
+            .into_iter()
+            .enumerate()
+            .filter(|(index, task)| keep(*index, &**task))
+            .map(|(_, task)| task)
+            .collect();
+        let count = tasks.len();
+        edges.unexpanded_tasks = WeightedIndex::new(tasks.iter().map(|task| task.weight(ctx)))
+            .ok()
+            .map(|weights| (weights, tasks));
+        count
+    }
+
+    /// Sets a callback called every `every` visits with the running statistics of the search.
+    ///
+    /// This allows to show the progress of long searches, and to stop them with custom rules
+    /// by returning [ProgressControl::Stop].
+    pub fn set_progress_callback(
+        &mut self,
+        every: u32,
+        callback: impl FnMut(&SearchProgress<D>) -> ProgressControl + Send + 'static,
+    ) {
+        assert!(every > 0, "Progress must be reported at least every visit");
+        self.progress_callback = Some((every, Box::new(callback)));
+    }
+
+    /// Returns the running statistics of the current search, `elapsed` being the time spent so far.
+    fn progress(&self, elapsed: Duration) -> SearchProgress<D> {
+        let agent = self.root_agent;
+        SearchProgress {
+            visits_done: self.visits_done,
+            visits: self.config.visits,
+            best_task: self
+                .nodes
+                .get(&self.root)
+                .and_then(|edges| edges.best_task(agent, 0., self.min_max_range(agent))),
+            value: self.q_value_at_root(agent),
+            node_count: self.node_count(),
+            edge_count: self.edge_count(),
+            elapsed,
+        }
+    }
+
+    /// Sets how many children are created when the search reaches a node that is not fully expanded.
+    pub fn set_expansion_mode(&mut self, expansion_mode: ExpansionMode) {
+        self.expansion_mode = expansion_mode;
+    }
+
+    /// Returns a handle to the statistics of the search, to read them from another thread while the search runs.
+    ///
+    /// After the first call, the search publishes its statistics to the handle after each visit.
+    pub fn live_statistics(&mut self) -> LiveStatistics<D> {
+        if self.live_statistics.is_none() {
+            let statistics = LiveStatistics::new(self.root_tasks());
+            self.publish_live_statistics_to(&statistics);
+            self.live_statistics = Some(statistics);
+        }
+        self.live_statistics.clone().unwrap()
+    }
+
+    fn publish_live_statistics_to(&self, statistics: &LiveStatistics<D>) {
+        statistics.publish(
+            self.visits_done,
+            self.node_count(),
+            self.edge_count(),
+            self.is_search_finished(),
+            self.nodes.get(&self.root).unwrap(),
+            self.root_agent,
+        );
+    }
+
+    /// Calls the progress callback if it is due, and stops the search if it asks so.
+    fn report_progress(&mut self, slice_start: Instant) {
+        let every = match &self.progress_callback {
+            Some((every, _)) => *every,
+            None => return,
+        };
+        if self.visits_done % every != 0 {
+            return;
+        }
+        let progress = self.progress(self.time + slice_start.elapsed());
+        let (_, callback) = self.progress_callback.as_mut().unwrap();
+        if callback(&progress) == ProgressControl::Stop {
+            log::info!(
+                "{:?} stops planning after {} visits, as told by its progress callback",
+                self.agent(),
+                self.visits_done
+            );
+            self.early_stopped = true;
+        }
+    }
+
+    /// Replaces the search tree and the statistics of the search, used when restoring a snapshot.
+    #[cfg(feature = "snapshot")]
+    pub(crate) fn restore_tree(
+        &mut self,
+        root: Node<D>,
+        nodes: SeededHashMap<Node<D>, Edges<D>>,
+        q_value_ranges: AgentMap<Range<AgentValue>>,
+        visits_done: u32,
+        seed: u64,
+    ) {
+        self.root = root;
+        self.nodes = nodes;
+        self.q_value_ranges = q_value_ranges;
+        self.visits_done = visits_done;
+        self.seed = seed;
+        self.rng = ChaCha8Rng::seed_from_u64(seed);
+        self.resumed = true;
+    }
+
+    /// Returns the intention of the agent of `finished_task`, if that agent has to decide for the first time after it.
+    ///
+    /// This is the case after its active task at the root, or after the planning task following it.
+    fn intention_after(&self, finished_task: &ActiveTask<D>) -> Option<Box<dyn Task<D>>> {
+        let intention = self.intentions.get(&finished_task.agent)?;
+        let root_task = get_task_for_agent(&self.root.tasks, finished_task.agent)?;
+        let is_first_decision = finished_task.end == root_task.end
+            || (finished_task.start == root_task.end
+                && finished_task.task.downcast_ref::<PlanningTask>().is_some());
+        is_first_decision.then(|| intention.clone())
+    }
+
     /// Returns the best task, using exploration value of 0.
     pub fn best_task_at_root(&mut self) -> Option<Box<dyn Task<D>>> {
         let range = self.min_max_range(self.root_agent);
@@ -156,9 +372,9 @@ impl<D: Domain> MCTS<D> {
     }
 
     /// Returns the best task, following a given recent task history, in case planning tasks are used.
-    pub fn best_task_with_history(
+    pub fn best_task_with_history<S: BuildHasher>(
         &self,
-        task_history: &HashMap<AgentId, ActiveTask<D>>,
+        task_history: &HashMap<AgentId, ActiveTask<D>, S>,
     ) -> Box<dyn Task<D>>
     where
         D: DomainWithPlanningTask,
@@ -242,57 +458,240 @@ impl<D: Domain> MCTS<D> {
 
     /// Executes the MCTS search.
     ///
+    /// After restoring a snapshot, continues its search until [visits](MCTSConfiguration::visits)
+    /// visits are done in total; otherwise does that many new visits.
     /// Returns the current best task, if there is at least one task for the root node.
     pub fn run(&mut self) -> Option<Box<dyn Task<D>>> {
-        // Reset globals
-        self.q_value_ranges.clear();
+        #[cfg(feature = "tracing")]
+        let _span = self.planning_span().entered();
+
+        // Reset globals, unless continuing a restored search
+        if !mem::take(&mut self.resumed) {
+            self.q_value_ranges.clear();
+            self.visibility_cache.clear();
+            if let Some(bounds) = &mut self.node_bounds {
+                bounds.clear();
+            }
+            self.visits_done = 0;
+        }
+        self.early_stopped = false;
+        self.time = Duration::default();
+        #[cfg(feature = "hook-timing")]
+        {
+            self.hook_timings = Default::default();
+            hook_timing::take();
+        }
+        if let Some(profile) = &mut self.profile {
+            *profile = Default::default();
+            profile::start();
+        }
 
         let start = Instant::now();
-        let max_visits = self.config.visits;
-        for i in 0..max_visits {
-            // Execute tree policy, if expansion resulted in no node, do nothing
-            let tree_policy_outcome = self.tree_policy();
-
-            // Only if the tree policy resulted in a node expansion, we execute the default policy,
-            // but in any case we update the visit count.
-            let (path, rollout_values) = match tree_policy_outcome {
-                TreePolicyOutcome::NodeCreated(depth, leaf, path) => {
-                    // Execute default policy
-                    let edges = self.nodes.get(&leaf).unwrap();
-                    let rollout_values = self.state_value_estimator.estimate(
-                        &mut self.rng,
-                        &self.config,
-                        &self.initial_state,
-                        self.start_tick,
-                        &leaf,
-                        edges,
-                        depth,
-                    );
-                    (path, rollout_values)
+        while self.visit(start) {}
+        self.time = start.elapsed();
+        #[cfg(feature = "hook-timing")]
+        self.hook_timings.add(&hook_timing::take());
+        if let Some(profile) = &mut self.profile {
+            profile::stop(profile);
+        }
+
+        self.best_task_at_root()
+    }
+
+    /// Continues the MCTS search for at most the given budget.
+    ///
+    /// This allows to spread a search over several frames,
+    /// the search being finished once [visits](MCTSConfiguration::visits)
+    /// visits have been done or the early stop condition holds.
+    /// Returns whether the search is finished.
+    pub fn run_with_budget(&mut self, budget: PlanningBudget) -> bool {
+        #[cfg(feature = "tracing")]
+        let _span = self.planning_span().entered();
+
+        let start = Instant::now();
+        self.resumed = false;
+        if self.visits_done == 0 {
+            // First slice, reset globals
+            self.q_value_ranges.clear();
+            self.visibility_cache.clear();
+            if let Some(bounds) = &mut self.node_bounds {
+                bounds.clear();
+            }
+            self.time = Duration::default();
+            #[cfg(feature = "hook-timing")]
+            {
+                self.hook_timings = Default::default();
+            }
+            if let Some(profile) = &mut self.profile {
+                *profile = Default::default();
+            }
+        }
+        #[cfg(feature = "hook-timing")]
+        hook_timing::take();
+        if self.profile.is_some() {
+            profile::start();
+        }
+        match budget {
+            PlanningBudget::Visits(visits) => {
+                for _ in 0..visits {
+                    if !self.visit(start) {
+                        break;
+                    }
                 }
-                TreePolicyOutcome::NoValidTask(_, path) => (path, None),
-                TreePolicyOutcome::NoChildNode(_, _, path) => (path, None),
-                TreePolicyOutcome::DepthLimitReached(_, _, path) => (path, None),
-            };
+            }
+            PlanningBudget::Time(duration) => {
+                while self.visit(start) && start.elapsed() < duration {}
+            }
+        }
+        self.time += start.elapsed();
+        #[cfg(feature = "hook-timing")]
+        self.hook_timings.add(&hook_timing::take());
+        if let Some(profile) = &mut self.profile {
+            profile::stop(profile);
+        }
+        self.is_search_finished()
+    }
 
-            // Backpropagate results
-            self.backpropagation(path, rollout_values);
+    /// Returns whether the current search is finished.
+    pub fn is_search_finished(&self) -> bool {
+        self.early_stopped || self.visits_done >= self.config.visits
+    }
 
-            // Early stopping if told so by some user-defined condition
-            if let Some(early_stop_condition) = &self.early_stop_condition {
-                if early_stop_condition(i) {
-                    log::info!("{:?} early stops planning after {} visits", self.agent(), i);
-                    break;
+    /// Returns the number of visits done in the current search.
+    pub fn visits_done(&self) -> u32 {
+        self.visits_done
+    }
+
+    /// Returns the span of a planning of the root agent.
+    #[cfg(feature = "tracing")]
+    fn planning_span(&self) -> tracing::Span {
+        tracing::debug_span!(
+            "planning",
+            agent = %self.root_agent,
+            tick = self.start_tick,
+            visits_done = self.visits_done
+        )
+    }
+
+    /// Does one visit of the search, if not finished, returns whether the search continues.
+    ///
+    /// `slice_start` is the time at which the current run or slice started.
+    fn visit(&mut self, slice_start: Instant) -> bool {
+        if self.is_search_finished() {
+            return false;
+        }
+        let i = self.visits_done;
+        self.visits_done += 1;
+
+        // Execute tree policy, if expansion resulted in no node, do nothing
+        let phase_start = self.profile_phase_start();
+        let expansion_before = self.profile.map(|profile| profile.expansion);
+        let tree_policy_outcome = self.tree_policy();
+        if let (Some(profile), Some(start), Some(expansion_before)) =
+            (&mut self.profile, phase_start, expansion_before)
+        {
+            // Expansion is timed within the tree policy, the rest is selection
+            let expansion = profile.expansion - expansion_before;
+            profile.selection += start.elapsed().saturating_sub(expansion);
+        }
+        let phase_start = self.profile_phase_start();
+
+        // Only if the tree policy resulted in a node expansion, we execute the default policy,
+        // but in any case we update the visit count.
+        let (path, rollout_values) = match tree_policy_outcome {
+            TreePolicyOutcome::NodesCreated(leaves) => {
+                // Estimate all new children at once, each counting as a visit
+                #[cfg(feature = "tracing")]
+                let _span = tracing::trace_span!("rollout", leaves = leaves.len()).entered();
+                let batch: Vec<_> = leaves
+                    .iter()
+                    .map(|(depth, leaf, _)| (leaf, self.nodes.get(leaf).unwrap(), *depth))
+                    .collect();
+                let rollout_values = self.state_value_estimator.estimate_batch(
+                    &mut self.rng,
+                    &self.config,
+                    &self.initial_state,
+                    self.start_tick,
+                    &batch,
+                );
+                self.visits_done += leaves.len() as u32 - 1;
+                self.profile_phase_end(|profile| &mut profile.estimation, phase_start);
+                let phase_start = self.profile_phase_start();
+                let mut leaves = leaves
+                    .into_iter()
+                    .zip(rollout_values)
+                    .map(|((_, _, path), rollout_values)| (path, rollout_values));
+                let last = leaves.next_back().unwrap();
+                for (path, rollout_values) in leaves {
+                    self.backpropagation(path, rollout_values);
                 }
+                self.profile_phase_end(|profile| &mut profile.backpropagation, phase_start);
+                last
+            }
+            TreePolicyOutcome::NodeCreated(depth, leaf, path) => {
+                // Execute default policy
+                #[cfg(feature = "tracing")]
+                let _span = tracing::trace_span!("rollout", depth).entered();
+                let edges = self.nodes.get(&leaf).unwrap();
+                #[cfg(not(feature = "diff-pool"))]
+                let rollout_values = self.state_value_estimator.estimate(
+                    &mut self.rng,
+                    &self.config,
+                    &self.initial_state,
+                    self.start_tick,
+                    &leaf,
+                    edges,
+                    depth,
+                );
+                #[cfg(feature = "diff-pool")]
+                let rollout_values = self.state_value_estimator.estimate_with_pool(
+                    &mut self.rng,
+                    &self.config,
+                    &self.initial_state,
+                    self.start_tick,
+                    &leaf,
+                    edges,
+                    depth,
+                    &mut self.diff_pool,
+                );
+                self.profile_phase_end(|profile| &mut profile.estimation, phase_start);
+                (path, rollout_values)
             }
+            TreePolicyOutcome::NoValidTask(path) => (path, None),
+            TreePolicyOutcome::NoChildNode(path) => (path, None),
+            TreePolicyOutcome::DepthLimitReached(path) => (path, None),
+        };
+
+        // Backpropagate results
+        {
+            #[cfg(feature = "tracing")]
+            let _span = tracing::trace_span!("backpropagation", length = path.len()).entered();
+            let phase_start = self.profile_phase_start();
+            self.backpropagation(path, rollout_values);
+            self.profile_phase_end(|profile| &mut profile.backpropagation, phase_start);
         }
-        self.time = start.elapsed();
 
-        self.best_task_at_root()
+        // Early stopping if told so by some user-defined condition
+        if let Some(early_stop_condition) = &self.early_stop_condition {
+            if early_stop_condition(i) {
+                log::info!("{:?} early stops planning after {} visits", self.agent(), i);
+                #[cfg(feature = "tracing")]
+                tracing::debug!(visits = i, "early stop");
+                self.early_stopped = true;
+            }
+        }
+        self.report_progress(slice_start);
+        if let Some(statistics) = &self.live_statistics {
+            self.publish_live_statistics_to(statistics);
+        }
+        !self.is_search_finished()
     }
 
     /// MCTS tree policy. Executes the `selection` and `expansion` phases.
     fn tree_policy(&mut self) -> TreePolicyOutcome<D> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!("selection").entered();
+
         let agents = self.root.agents();
 
         let mut node = self.root.clone();
@@ -303,162 +702,52 @@ impl<D: Domain> MCTS<D> {
         // Execute selection until at most `depth`, expressed as number of ticks
         let mut depth = 0;
         while depth < self.config.depth {
-            let mut edges = self.nodes.get_mut(&node).unwrap();
+            let edges = self.nodes.get_mut(&node).unwrap();
 
             // -------------------------
             // Expansion
             // -------------------------
             // If weights are non-empty, the node has not been fully expanded
-            if let Some((weights, tasks)) = edges.unexpanded_tasks.as_mut() {
-                // Clone a new diff from the current one to be used for the newly expanded node
-                let mut diff = node.diff.clone();
-
-                // Select expansion task randomly
-                let idx = weights.sample(&mut self.rng);
-                let task = tasks[idx].clone();
-                let ctx = Context::with_state_and_diff(
-                    node.tick,
-                    &self.initial_state,
-                    &diff,
-                    node.active_agent,
-                );
-                debug_assert!(task.is_valid(ctx));
-                log::debug!(
-                    "T{}\t{:?} - Expand task: {:?}",
-                    node.tick,
-                    node.active_agent,
-                    task
-                );
-
-                // Set weight of chosen task to zero to mark it as expanded.
-                // As updating weights returns an error if all weights are zero,
-                // we have to handle this by setting unexpanded_tasks to None if we get an error.
-                if weights.update_weights(&[(idx, &0.)]).is_err() {
-                    // All weights being zero implies the node is fully expanded
-                    edges.unexpanded_tasks = None;
-                }
-
-                // Clone active tasks for child node, removing task of active agent
-                let mut child_tasks = node
-                    .tasks
-                    .iter()
-                    .filter(|task| task.agent != node.active_agent)
-                    .cloned()
-                    .collect::<BTreeSet<_>>();
-                // Create and insert new active task for the active agent and the selected task
-                let active_task = ActiveTask::new(task.clone(), ctx);
-                child_tasks.insert(active_task);
-                log::trace!("\tActive Tasks ({}):", child_tasks.len());
-                for active_task in &child_tasks {
-                    log::trace!(
-                        "\t  {:?}: {:?} ends T{}",
-                        active_task.agent,
-                        active_task.task,
-                        active_task.end
-                    );
-                }
-
-                // Get task that finishes in the next node
-                let next_active_task = child_tasks.iter().next().unwrap().clone();
-                log::trace!(
-                    "\tNext Active Task: {:?}: {:?} ends T{}",
-                    next_active_task.agent,
-                    next_active_task.task,
-                    next_active_task.end
-                );
-
-                // If it is not valid, abort this expansion
-                let next_ctx =
-                    ctx.replace_tick_and_agent(next_active_task.end, next_active_task.agent);
-                let is_task_valid = next_active_task.task.is_valid(next_ctx);
-                if !is_task_valid && !self.config.allow_invalid_tasks {
-                    log::debug!("T{}\tNext active task {:?} is invalid and that is not allowed, aborting expansion", next_active_task.end, next_active_task.task);
-                    return TreePolicyOutcome::NoValidTask(depth, path);
-                }
-                // Execute the task which finishes in the next node
-                let after_next_task = if is_task_valid {
-                    let next_ctx_mut = ContextMut::with_rest_and_state_and_diff(
-                        next_ctx.drop_state_diff(),
-                        &self.initial_state,
-                        &mut diff,
-                    );
-                    next_active_task.task.execute(next_ctx_mut)
-                } else {
-                    None
-                };
-
-                // If we do not have a forced follow-up task...
-                let after_next_task = if after_next_task.is_none() {
-                    // And we have a forced planning task, handle it
-                    if let Some(planning_task_duration) = self.config.planning_task_duration {
-                        if next_active_task
-                            .task
-                            .downcast_ref::<PlanningTask>()
-                            .is_none()
-                        {
-                            // the incoming task was not planning, so the next one should be
-                            let task: Box<dyn Task<D>> =
-                                Box::new(PlanningTask(planning_task_duration));
-                            Some(task)
-                        } else {
-                            None
+            if edges.unexpanded_tasks.is_some() {
+                #[cfg(feature = "tracing")]
+                let _span = tracing::trace_span!("expansion", tick = node.tick).entered();
+
+                if self.expansion_mode == ExpansionMode::Batch {
+                    // Expand all remaining tasks, each new child being a leaf of its own path,
+                    // but no more than the visits left, counting the current one
+                    let visits_left = self.config.visits.saturating_sub(self.visits_done) + 1;
+                    let mut leaves = Vec::new();
+                    while leaves.len() < visits_left as usize
+                        && self.nodes.get(&node).unwrap().unexpanded_tasks.is_some()
+                    {
+                        if let Some((child_node, edge)) = self.profiled_expand(&node, &agents) {
+                            let mut path = path.clone();
+                            path.push(edge);
+                            let depth = depth + (child_node.tick - node.tick) as u32;
+                            leaves.push((depth, child_node, path));
                         }
-                    } else {
-                        None
                     }
-                } else {
-                    after_next_task
+                    if leaves.is_empty() {
+                        return TreePolicyOutcome::NoValidTask(path);
+                    }
+                    return TreePolicyOutcome::NodesCreated(leaves);
+                }
+                return match self.profiled_expand(&node, &agents) {
+                    Some((child_node, edge)) => {
+                        path.push(edge);
+                        depth += (child_node.tick - node.tick) as u32;
+                        TreePolicyOutcome::NodeCreated(depth, child_node, path)
+                    }
+                    None => TreePolicyOutcome::NoValidTask(path),
                 };
-
-                // Create expanded node state
-                // let was_planning = task.downcast_ref::<Plan>().is_some();
-                let child_state = NodeInner::new(
-                    &self.initial_state,
-                    self.start_tick,
-                    diff,
-                    next_active_task.agent,
-                    next_active_task.end,
-                    child_tasks,
-                );
-
-                // Check if child node exists already
-                let child_node =
-                    if let Some((existing_node, _)) = self.nodes.get_key_value(&child_state) {
-                        // Link existing child node
-                        log::trace!("\tLinking to existing node {:?}", existing_node);
-                        existing_node.clone()
-                    } else {
-                        // Create and insert new child node
-                        log::trace!("\tCreating new node {:?}", child_state);
-                        let child_node = Node::new(child_state);
-                        self.nodes.insert(
-                            child_node.clone(),
-                            Edges::new(&child_node, &self.initial_state, after_next_task),
-                        );
-                        child_node
-                    };
-
-                // Create edge from parent to child
-                let edge = new_edge(&node, &child_node, &agents);
-                let edges = self.nodes.get_mut(&node).unwrap();
-                edges.expanded_tasks.insert(task, edge.clone());
-
-                // Push edge to path
-                path.push(edge);
-
-                depth += (child_node.tick - node.tick) as u32;
-                log::debug!(
-                    "T{}\tExpansion successful, node created with incoming task {:?}",
-                    child_node.tick,
-                    next_active_task.task
-                );
-                return TreePolicyOutcome::NodeCreated(depth, child_node, path);
             }
 
             // There is no child to this node, still return last node to ensure increase of visit count for this path
             if edges.child_visits() == 0 {
                 log::debug!("T{}\tNode has no children, aborting expansion", node.tick);
-                return TreePolicyOutcome::NoChildNode(depth, node, path);
+                #[cfg(feature = "tracing")]
+                tracing::trace!(tick = node.tick, "expansion pruned, no children");
+                return TreePolicyOutcome::NoChildNode(path);
             }
 
             // -------------------------
@@ -466,10 +755,14 @@ impl<D: Domain> MCTS<D> {
             // -------------------------
             // Node is fully expanded, perform selection
             let range = self.min_max_range(node.active_agent);
-            let edges = self.nodes.get_mut(&node).unwrap();
-            let task = edges
-                .best_task(node.active_agent, self.config.exploration, range)
+            let task = self
+                .best_unpruned_task(&node, self.config.exploration, range.clone())
+                .unwrap_or_else(|| {
+                    let edges = self.nodes.get(&node).unwrap();
+                    edges.best_task(node.active_agent, self.config.exploration, range)
+                })
                 .expect("No valid task!");
+            let edges = self.nodes.get(&node).unwrap();
             log::trace!(
                 "T{}\t{:?} - Select task: {:?}",
                 node.tick,
@@ -492,12 +785,203 @@ impl<D: Domain> MCTS<D> {
         }
 
         // We reached maximum depth, still return last node to ensure increase of visit count for this path
+        #[cfg(feature = "tracing")]
+        tracing::trace!(
+            tick = node.tick,
+            depth,
+            "expansion pruned, depth limit reached"
+        );
         log::debug!(
             "T{}\tReached maximum depth {}, aborting expansion",
             node.tick,
             depth
         );
-        TreePolicyOutcome::DepthLimitReached(depth, node, path)
+        TreePolicyOutcome::DepthLimitReached(path)
+    }
+
+    /// Expands `node` as [expand](Self::expand), recording the time spent if profiling.
+    fn profiled_expand(
+        &mut self,
+        node: &Node<D>,
+        agents: &BTreeSet<AgentId>,
+    ) -> Option<(Node<D>, Edge<D>)> {
+        let start = self.profile_phase_start();
+        let expanded = self.expand(node, agents);
+        self.profile_phase_end(|profile| &mut profile.expansion, start);
+        expanded
+    }
+
+    /// Returns the start time of a phase, if profiling.
+    fn profile_phase_start(&self) -> Option<Instant> {
+        self.profile.is_some().then(Instant::now)
+    }
+
+    /// Records the time spent in a phase started at `start`, if profiling.
+    fn profile_phase_end(
+        &mut self,
+        phase: fn(&mut SearchProfile) -> &mut Duration,
+        start: Option<Instant>,
+    ) {
+        if let (Some(profile), Some(start)) = (&mut self.profile, start) {
+            *phase(profile) += start.elapsed();
+        }
+    }
+
+    /// Expands a randomly-chosen unexpanded task of `node`, returns the new child and its edge.
+    ///
+    /// Returns None if the expansion is aborted because the next task to finish is invalid.
+    fn expand(&mut self, node: &Node<D>, agents: &BTreeSet<AgentId>) -> Option<(Node<D>, Edge<D>)> {
+        // Clone a new diff from the current one to be used for the newly expanded node
+        let mut diff = node.diff.clone();
+
+        // Select expansion task randomly
+        let edges = self.nodes.get_mut(node).unwrap();
+        let (weights, tasks) = edges.unexpanded_tasks.as_mut().unwrap();
+        let idx = weights.sample(&mut self.rng);
+        let task = tasks[idx].clone();
+        let ctx =
+            Context::with_state_and_diff(node.tick, &self.initial_state, &diff, node.active_agent);
+        debug_assert!(task.is_valid(ctx));
+        log::debug!(
+            "T{}\t{:?} - Expand task: {:?}",
+            node.tick,
+            node.active_agent,
+            task
+        );
+
+        // Set weight of chosen task to zero to mark it as expanded.
+        // As updating weights returns an error if all weights are zero,
+        // we have to handle this by setting unexpanded_tasks to None if we get an error.
+        if weights.update_weights(&[(idx, &0.)]).is_err() {
+            // All weights being zero implies the node is fully expanded
+            edges.unexpanded_tasks = None;
+        }
+
+        // Clone active tasks for child node, removing task of active agent
+        let mut child_tasks = node
+            .tasks
+            .iter()
+            .filter(|task| task.agent != node.active_agent)
+            .cloned()
+            .collect::<BTreeSet<_>>();
+        // Create and insert new active task for the active agent and the selected task
+        let active_task = ActiveTask::new(task.clone(), ctx);
+        child_tasks.insert(active_task);
+        log::trace!("\tActive Tasks ({}):", child_tasks.len());
+        for active_task in &child_tasks {
+            log::trace!(
+                "\t  {:?}: {:?} ends T{}",
+                active_task.agent,
+                active_task.task,
+                active_task.end
+            );
+        }
+
+        // Get task that finishes in the next node
+        let next_active_task = child_tasks.iter().next().unwrap().clone();
+        log::trace!(
+            "\tNext Active Task: {:?}: {:?} ends T{}",
+            next_active_task.agent,
+            next_active_task.task,
+            next_active_task.end
+        );
+
+        // If it is not valid, abort this expansion
+        let next_ctx = ctx.replace_tick_and_agent(next_active_task.end, next_active_task.agent);
+        let is_task_valid = timed_hook!(is_valid, next_active_task.task.is_valid(next_ctx));
+        if !is_task_valid && !self.config.allow_invalid_tasks {
+            log::debug!(
+                "T{}\tNext active task {:?} is invalid and that is not allowed, aborting expansion",
+                next_active_task.end,
+                next_active_task.task
+            );
+            #[cfg(feature = "tracing")]
+            tracing::trace!(
+                tick = next_active_task.end,
+                task = ?next_active_task.task,
+                "expansion pruned, invalid task"
+            );
+            return None;
+        }
+        // Execute the task which finishes in the next node
+        let after_next_task = if is_task_valid {
+            let next_ctx_mut = ContextMut::with_rest_and_state_and_diff(
+                next_ctx.drop_state_diff(),
+                &self.initial_state,
+                &mut diff,
+            );
+            timed_hook!(execute, next_active_task.task.execute(next_ctx_mut))
+        } else {
+            None
+        };
+
+        // If we do not have a forced follow-up task...
+        let after_next_task = if after_next_task.is_none() {
+            // And we have a forced planning task, handle it
+            if let Some(planning_task_duration) = self.config.planning_task_duration {
+                if next_active_task
+                    .task
+                    .downcast_ref::<PlanningTask>()
+                    .is_none()
+                {
+                    // the incoming task was not planning, so the next one should be
+                    let task: Box<dyn Task<D>> = Box::new(PlanningTask(planning_task_duration));
+                    Some(task)
+                } else {
+                    None
+                }
+            } else {
+                None
+            }
+        } else {
+            after_next_task
+        };
+        // If the agent has to decide for the first time, use its intention if any
+        let after_next_task = after_next_task.or_else(|| self.intention_after(&next_active_task));
+
+        // Create expanded node state
+        // let was_planning = task.downcast_ref::<Plan>().is_some();
+        let child_state = NodeInner::new_with_visibility_cache(
+            &self.initial_state,
+            self.start_tick,
+            diff,
+            next_active_task.agent,
+            next_active_task.end,
+            child_tasks,
+            &mut self.visibility_cache,
+        );
+
+        // Check if child node exists already
+        let child_node = if let Some((existing_node, _)) = self.nodes.get_key_value(&child_state) {
+            // Link existing child node
+            log::trace!("\tLinking to existing node {:?}", existing_node);
+            #[cfg(feature = "tracing")]
+            tracing::trace!(tick = existing_node.tick, agent = %existing_node.active_agent, "node linked");
+            existing_node.clone()
+        } else {
+            // Create and insert new child node
+            log::trace!("\tCreating new node {:?}", child_state);
+            let child_node = Node::new(child_state);
+            #[cfg(feature = "tracing")]
+            tracing::trace!(tick = child_node.tick, agent = %child_node.active_agent, "node expanded");
+            self.nodes.insert(
+                child_node.clone(),
+                Edges::new(&child_node, &self.initial_state, after_next_task),
+            );
+            child_node
+        };
+
+        // Create edge from parent to child
+        let edge = new_edge(node, &child_node, agents);
+        let edges = self.nodes.get_mut(node).unwrap();
+        edges.expanded_tasks.insert(task, edge.clone());
+
+        log::debug!(
+            "T{}\tExpansion successful, node created with incoming task {:?}",
+            child_node.tick,
+            next_active_task.task
+        );
+        Some((child_node, edge))
     }
 
     /// MCTS backpropagation phase. If rollout values are None, just increment the visits.
@@ -506,6 +990,8 @@ impl<D: Domain> MCTS<D> {
         mut path: Vec<Edge<D>>,
         rollout_values: Option<BTreeMap<AgentId, f32>>,
     ) {
+        self.update_node_bounds(&path);
+
         // Backtracking
         path.drain(..).rev().for_each(|edge| {
             // Increment child node visit count
@@ -548,13 +1034,16 @@ impl<D: Domain> MCTS<D> {
                     // Update global q value range for agent
                     let q_value_range = self
                         .q_value_ranges
-                        .entry(parent_node.active_agent)
-                        .or_insert_with(|| Range {
+                        .get(&parent_node.active_agent)
+                        .cloned()
+                        .unwrap_or(Range {
                             start: VALUE_INFINITE,
                             end: VALUE_NEG_INFINITE,
                         });
-                    q_value_range.start = q_value_range.start.min(q_value);
-                    q_value_range.end = q_value_range.end.max(q_value);
+                    self.q_value_ranges.insert(
+                        parent_node.active_agent,
+                        q_value_range.start.min(q_value)..q_value_range.end.max(q_value),
+                    );
                 });
             }
         });
@@ -564,7 +1053,7 @@ impl<D: Domain> MCTS<D> {
     ///
     /// This basically calculates a half-life decay factor for the given duration.
     /// This means the discount factor will be 0.5 if the given ticks are equal to the configured half-life in the MCTS.
-    fn discount_factor(duration: u64, config: &MCTSConfiguration) -> f32 {
+    pub(crate) fn discount_factor(duration: u64, config: &MCTSConfiguration) -> f32 {
         2f64.powf((-(duration as f64)) / (config.discount_hl as f64)) as f32
     }
 
@@ -611,6 +1100,11 @@ impl<D: Domain> MCTS<D> {
         self.seed
     }
 
+    /// Returns the configuration of the search.
+    pub fn config(&self) -> &MCTSConfiguration {
+        &self.config
+    }
+
     /// Returns the number of nodes.
     pub fn node_count(&self) -> usize {
         self.nodes.len()
@@ -624,11 +1118,44 @@ impl<D: Domain> MCTS<D> {
             .sum()
     }
 
-    /// Returns the duration of the last run.
+    /// Returns the duration of the last run, or the accumulated duration of the slices of the current incremental search.
     pub fn time(&self) -> Duration {
         self.time
     }
 
+    /// Returns the pool of diff buffers used by the rollouts of this search.
+    #[cfg(feature = "diff-pool")]
+    pub fn diff_pool(&self) -> &DiffPool<D::Diff> {
+        &self.diff_pool
+    }
+
+    /// Returns the memoization of the visible agents of the current search, see [Domain::visibility_hash].
+    pub fn visibility_cache(&self) -> &VisibilityCache {
+        &self.visibility_cache
+    }
+
+    /// Returns the number of calls and the time spent in domain hooks during the last run,
+    /// or accumulated over the slices of the current incremental search.
+    #[cfg(feature = "hook-timing")]
+    pub fn hook_timings(&self) -> &HookTimings {
+        &self.hook_timings
+    }
+
+    /// Enables or disables the profiling of the phases of the search, disabled by default.
+    ///
+    /// When enabled, the search measures the time spent in selection, expansion, estimation and backpropagation,
+    /// and in domain callbacks, available through [profile](Self::profile).
+    /// This must be called before running the search.
+    pub fn set_profiling(&mut self, enabled: bool) {
+        self.profile = enabled.then(SearchProfile::default);
+    }
+
+    /// Returns the time spent in the phases of the last run, or accumulated over the slices of the
+    /// current incremental search, if profiling is enabled.
+    pub fn profile(&self) -> Option<&SearchProfile> {
+        self.profile.as_ref()
+    }
+
     /// Returns an estimation of the memory footprint of the MCTS struct.
     pub fn size(&self, task_size: fn(&dyn Task<D>) -> usize) -> usize {
         let mut size = 0;
@@ -660,219 +1187,272 @@ impl<D: Domain> StateValueEstimator<D> for DefaultPolicyEstimator {
         depth: u32,
     ) -> Option<BTreeMap<AgentId, f32>> {
         let mut diff = node.diff.clone();
-        log::debug!(
-            "T{}\tStarting rollout with cur. values: {:?}",
-            node.tick,
-            node.current_values()
+        rollout(
+            rng,
+            config,
+            initial_state,
+            start_tick,
+            node,
+            edges,
+            depth,
+            &mut diff,
+        )
+    }
+
+    #[cfg(feature = "diff-pool")]
+    fn estimate_with_pool(
+        &mut self,
+        rng: &mut ChaCha8Rng,
+        config: &MCTSConfiguration,
+        initial_state: &D::State,
+        start_tick: u64,
+        node: &Node<D>,
+        edges: &Edges<D>,
+        depth: u32,
+        pool: &mut DiffPool<D::Diff>,
+    ) -> Option<BTreeMap<AgentId, f32>> {
+        let mut diff = pool.clone_of(&node.diff, D::reset_diff);
+        let values = rollout(
+            rng,
+            config,
+            initial_state,
+            start_tick,
+            node,
+            edges,
+            depth,
+            &mut diff,
         );
+        pool.recycle(diff);
+        values
+    }
+}
 
-        // In this map we collect at the same time both:
-        // - the current value (measured from state and replaced in the course of simulation)
-        // - the Q value (initially 0, updated in the course of simulation)
-        let mut values: BTreeMap<AgentId, (AgentValue, f32)> = node
-            .current_values()
-            .iter()
-            .map(|(&agent, &current_value)| (agent, (current_value, 0f32)))
-            .collect::<BTreeMap<_, _>>();
+/// Simulates random tasks from `node`, using `diff`, initially the diff of `node`, as buffer.
+#[allow(clippy::too_many_arguments)]
+fn rollout<D: Domain>(
+    rng: &mut ChaCha8Rng,
+    config: &MCTSConfiguration,
+    initial_state: &D::State,
+    start_tick: u64,
+    node: &Node<D>,
+    edges: &Edges<D>,
+    depth: u32,
+    diff: &mut D::Diff,
+) -> Option<BTreeMap<AgentId, f32>> {
+    log::debug!(
+        "T{}\tStarting rollout with cur. values: {:?}",
+        node.tick,
+        node.current_values()
+    );
+
+    // In this map we collect at the same time both:
+    // - the current value (measured from state and replaced in the course of simulation)
+    // - the Q value (initially 0, updated in the course of simulation)
+    let mut values: AgentMap<(AgentValue, f32)> = node
+        .current_values()
+        .iter()
+        .map(|(&agent, &current_value)| (agent, (current_value, 0f32)))
+        .collect();
+
+    // Clone active tasks for child node, removing task of active agent
+    let mut tasks = node
+        .tasks
+        .iter()
+        .filter(|task| task.agent != node.active_agent)
+        .cloned()
+        .collect::<BTreeSet<_>>();
+
+    // Sample a task for the node's unexpanded list, and put it in the queue
+    let task = {
+        if let Some((weights, tasks)) = edges.unexpanded_tasks.as_ref() {
+            // Select task randomly
+            let idx = weights.sample(rng);
+            tasks[idx].clone()
+        } else {
+            // No unexpanded edges, q values are 0
+            log::debug!(
+                "T{}\tNo unexpanded edges in node passed to rollout",
+                node.tick
+            );
+            return None;
+        }
+    };
+    let ctx = Context::with_state_and_diff(node.tick, initial_state, diff, node.active_agent);
+    let new_active_task = ActiveTask::new(task, ctx);
+    tasks.insert(new_active_task);
+    let mut agents_with_tasks = tasks
+        .iter()
+        .map(|task| task.agent)
+        .collect::<BTreeSet<AgentId>>();
+    let mut agents = agents_with_tasks.iter().copied().collect();
+
+    // Create the state we need to perform the simulation
+    let rollout_start_tick = node.tick;
+    let mut tick = node.tick;
+    let mut depth = depth;
+    while depth < config.depth {
+        let state_diff = StateDiffRef::new(initial_state, diff);
+
+        // If there is no more task to do, return what we have so far
+        if tasks.is_empty() {
+            log::debug!(
+                "! T{} No more task to do in state\n{}",
+                tick,
+                D::get_state_description(state_diff)
+            );
+            break;
+        }
 
-        // Clone active tasks for child node, removing task of active agent
-        let mut tasks = node
-            .tasks
-            .iter()
-            .filter(|task| task.agent != node.active_agent)
-            .cloned()
-            .collect::<BTreeSet<_>>();
+        // Pop first task that is completed
+        let active_task = tasks.iter().next().unwrap().clone();
+        tasks.remove(&active_task);
+        let active_agent = active_task.agent;
+        agents_with_tasks.remove(&active_agent);
+
+        // Compute elapsed time and update tick
+        let elapsed = active_task.end - tick;
+        tick = active_task.end;
+
+        // If task is invalid, stop rollout
+        let ctx = Context::new(tick, state_diff, active_agent);
+        let is_task_valid = timed_hook!(is_valid, active_task.task.is_valid(ctx));
+        if !is_task_valid && !config.allow_invalid_tasks {
+            log::debug!(
+                "! T{} Not allowed invalid task {:?} by {:?} in state\n{}",
+                tick,
+                active_task.task,
+                active_agent,
+                D::get_state_description(state_diff)
+            );
+            break;
+        } else if is_task_valid {
+            log::trace!(
+                "✓ T{} Valid task {:?} by {:?} in state\n{}",
+                tick,
+                active_task.task,
+                active_agent,
+                D::get_state_description(state_diff)
+            );
+        } else {
+            log::trace!(
+                "✓ T{} Skipping invalid task {:?} by {:?} in state\n{}",
+                tick,
+                active_task.task,
+                active_agent,
+                D::get_state_description(state_diff)
+            );
+        }
 
-        // Sample a task for the node's unexpanded list, and put it in the queue
-        let task = {
-            if let Some((weights, tasks)) = edges.unexpanded_tasks.as_ref() {
-                // Select task randomly
-                let idx = weights.sample(rng);
-                tasks[idx].clone()
-            } else {
-                // No unexpanded edges, q values are 0
-                log::debug!(
-                    "T{}\tNo unexpanded edges in node passed to rollout",
-                    node.tick
-                );
-                return None;
-            }
+        // Execute the task
+        let new_task = if is_task_valid {
+            let ctx_mut = ContextMut::with_rest_and_state_and_diff(
+                ctx.drop_state_diff(),
+                initial_state,
+                diff,
+            );
+            timed_hook!(execute, active_task.task.execute(ctx_mut))
+        } else {
+            None
         };
-        let ctx = Context::with_state_and_diff(node.tick, initial_state, &diff, node.active_agent);
-        let new_active_task = ActiveTask::new(task, ctx);
-        tasks.insert(new_active_task);
-        let mut agents_with_tasks = tasks
-            .iter()
-            .map(|task| task.agent)
-            .collect::<HashSet<AgentId>>();
-        let mut agents = agents_with_tasks.iter().copied().collect();
-
-        // Create the state we need to perform the simulation
-        let rollout_start_tick = node.tick;
-        let mut tick = node.tick;
-        let mut depth = depth;
-        while depth < config.depth {
-            let state_diff = StateDiffRef::new(initial_state, &diff);
-
-            // If there is no more task to do, return what we have so far
-            if tasks.is_empty() {
-                log::debug!(
-                    "! T{} No more task to do in state\n{}",
-                    tick,
-                    D::get_state_description(state_diff)
-                );
-                break;
-            }
-
-            // Pop first task that is completed
-            let active_task = tasks.iter().next().unwrap().clone();
-            tasks.remove(&active_task);
-            let active_agent = active_task.agent;
-            agents_with_tasks.remove(&active_agent);
-
-            // Compute elapsed time and update tick
-            let elapsed = active_task.end - tick;
-            tick = active_task.end;
-
-            // If task is invalid, stop rollout
-            let ctx = Context::new(tick, state_diff, active_agent);
-            let is_task_valid = active_task.task.is_valid(ctx);
-            if !is_task_valid && !config.allow_invalid_tasks {
-                log::debug!(
-                    "! T{} Not allowed invalid task {:?} by {:?} in state\n{}",
-                    tick,
-                    active_task.task,
-                    active_agent,
-                    D::get_state_description(state_diff)
-                );
-                break;
-            } else if is_task_valid {
-                log::trace!(
-                    "✓ T{} Valid task {:?} by {:?} in state\n{}",
-                    tick,
-                    active_task.task,
-                    active_agent,
-                    D::get_state_description(state_diff)
-                );
-            } else {
-                log::trace!(
-                    "✓ T{} Skipping invalid task {:?} by {:?} in state\n{}",
-                    tick,
-                    active_task.task,
-                    active_agent,
-                    D::get_state_description(state_diff)
-                );
-            }
-
-            // Execute the task
-            let new_task = if is_task_valid {
-                let ctx_mut = ContextMut::with_rest_and_state_and_diff(
-                    ctx.drop_state_diff(),
-                    initial_state,
-                    &mut diff,
-                );
-                active_task.task.execute(ctx_mut)
-            } else {
-                None
-            };
-            let new_ctx = Context::with_state_and_diff(tick, initial_state, &diff, active_agent);
-
-            // If we do not have a forced follow-up task...
-            let new_task = if new_task.is_none() {
-                // And we have a forced planning task, handle it
-                if let Some(planning_task_duration) = config.planning_task_duration {
-                    if active_task.task.downcast_ref::<PlanningTask>().is_none() {
-                        // the incoming task was not planning, so the next one should be
-                        let task: Box<dyn Task<D>> = Box::new(PlanningTask(planning_task_duration));
-                        Some(task)
-                    } else {
-                        None
-                    }
+        let new_ctx = Context::with_state_and_diff(tick, initial_state, diff, active_agent);
+
+        // If we do not have a forced follow-up task...
+        let new_task = if new_task.is_none() {
+            // And we have a forced planning task, handle it
+            if let Some(planning_task_duration) = config.planning_task_duration {
+                if active_task.task.downcast_ref::<PlanningTask>().is_none() {
+                    // the incoming task was not planning, so the next one should be
+                    let task: Box<dyn Task<D>> = Box::new(PlanningTask(planning_task_duration));
+                    Some(task)
                 } else {
                     None
                 }
             } else {
-                new_task
-            };
+                None
+            }
+        } else {
+            new_task
+        };
 
-            // if the values for the agent executing the task are being tracked, update them
-            if let Entry::Occupied(mut entry) = values.entry(active_agent) {
-                let (current_value, estimated_value) = entry.get_mut();
+        // if the values for the agent executing the task are being tracked, update them
+        if let Some((current_value, estimated_value)) = values.get_mut(&active_agent) {
+            // Compute discount
+            let discount = MCTS::<D>::discount_factor(active_task.end - rollout_start_tick, config);
 
-                // Compute discount
-                let discount =
-                    MCTS::<D>::discount_factor(active_task.end - rollout_start_tick, config);
+            // Update estimated value with discounted difference in current values
+            let new_current_value = timed_hook!(
+                get_current_value,
+                D::get_current_value(tick, new_ctx.state_diff, active_agent)
+            );
+            *estimated_value += *(new_current_value - *current_value) * discount;
+            *current_value = new_current_value;
+        }
 
-                // Update estimated value with discounted difference in current values
-                let new_current_value =
-                    D::get_current_value(tick, new_ctx.state_diff, active_agent);
-                *estimated_value += *(new_current_value - *current_value) * discount;
-                *current_value = new_current_value;
+        // Update the list of tasks, only considering visible agents,
+        // excluding the active agent (a new task for it will be added later)
+        timed_hook!(
+            update_visible_agents,
+            D::update_visible_agents(start_tick, new_ctx, &mut agents)
+        );
+        for agent in agents.iter() {
+            if *agent != active_agent && !agents_with_tasks.contains(agent) {
+                tasks.insert(ActiveTask::new_idle(tick, *agent, active_agent));
+                agents_with_tasks.insert(*agent);
             }
+        }
 
-            // Update the list of tasks, only considering visible agents,
-            // excluding the active agent (a new task for it will be added later)
-            D::update_visible_agents(start_tick, new_ctx, &mut agents);
-            for agent in agents.iter() {
-                if *agent != active_agent && !agents_with_tasks.contains(agent) {
-                    tasks.insert(ActiveTask::new_idle(tick, *agent, active_agent));
-                    agents_with_tasks.insert(*agent);
+        // If active agent is visible, insert its next task, otherwise we forget about it
+        if agents.contains(&active_agent) {
+            // If no new task is available, select one randomly
+            let new_task = new_task.or_else(|| {
+                // Get possible tasks
+                let tasks = D::get_tasks(new_ctx);
+                if tasks.is_empty() {
+                    return None;
                 }
-            }
-
-            // If active agent is visible, insert its next task, otherwise we forget about it
-            if agents.contains(&active_agent) {
-                // If no new task is available, select one randomly
-                let new_task = new_task.or_else(|| {
-                    // Get possible tasks
-                    let tasks = D::get_tasks(new_ctx);
-                    if tasks.is_empty() {
-                        return None;
-                    }
-                    // Safety-check that all tasks are valid
-                    for task in &tasks {
-                        debug_assert!(task.is_valid(new_ctx));
-                    }
-                    // Get the weight for each task
-                    let weights =
-                        WeightedIndex::new(tasks.iter().map(|task| task.weight(new_ctx))).unwrap();
-                    // Select task randomly
-                    let idx = weights.sample(rng);
-                    Some(tasks[idx].clone())
-                });
-
-                // If still none is available, stop caring about this agent
-                if let Some(new_task) = new_task {
-                    // Insert new task
-                    let new_active_task = ActiveTask::new(new_task, new_ctx);
-                    tasks.insert(new_active_task);
-                    agents_with_tasks.insert(active_agent);
+                // Safety-check that all tasks are valid
+                for task in &tasks {
+                    debug_assert!(task.is_valid(new_ctx));
                 }
-            }
+                // Get the weight for each task
+                let weights =
+                    WeightedIndex::new(tasks.iter().map(|task| task.weight(new_ctx))).unwrap();
+                // Select task randomly
+                let idx = weights.sample(rng);
+                Some(tasks[idx].clone())
+            });
 
-            // Make sure we do not keep track of the agents outside of the horizon
-            if agents_with_tasks.len() > agents.len() {
-                agents_with_tasks.retain(|id| agents.contains(id));
+            // If still none is available, stop caring about this agent
+            if let Some(new_task) = new_task {
+                // Insert new task
+                let new_active_task = ActiveTask::new(new_task, new_ctx);
+                tasks.insert(new_active_task);
+                agents_with_tasks.insert(active_agent);
             }
+        }
 
-            // Update depth
-            depth += elapsed as u32;
+        // Make sure we do not keep track of the agents outside of the horizon
+        if agents_with_tasks.len() > agents.len() {
+            agents_with_tasks.retain(|id| agents.contains(id));
         }
 
-        let q_values = values
-            .iter()
-            .map(|(agent, (_, q_value))| (*agent, *q_value))
-            .collect();
+        // Update depth
+        depth += elapsed as u32;
+    }
 
-        log::debug!(
-            "T{}\tRollout to T{}: q values: {:?}",
-            node.tick,
-            depth,
-            q_values
-        );
+    let q_values = values
+        .iter()
+        .map(|(agent, (_, q_value))| (*agent, *q_value))
+        .collect();
 
-        Some(q_values)
-    }
+    log::debug!(
+        "T{}\tRollout to T{}: q values: {:?}",
+        node.tick,
+        depth,
+        q_values
+    );
+
+    Some(q_values)
 }
 
 /// When `graphviz` feature is enabled, provides plotting of the search tree.
@@ -888,9 +1468,44 @@ pub mod graphviz {
 
     use dot::{Arrow, Edges, GraphWalk, Id, Kind, LabelText, Labeller, Nodes, Style};
 
-    /// Renders the search tree as graphviz's dot format.
+    /// Renders the search tree as graphviz's dot format, up to the [graph output depth](set_graph_output_depth).
     pub fn plot_mcts_tree<D: Domain, W: Write>(mcts: &MCTS<D>, w: &mut W) -> io::Result<()> {
-        dot::render(mcts, w)
+        plot_mcts_tree_with_options(mcts, PlotOptions::default(), w)
+    }
+
+    /// Renders the part of the search tree selected by `options` as graphviz's dot format.
+    ///
+    /// Nodes are labeled by their active agent, tick, visits and values, and edges by the display action of their task.
+    pub fn plot_mcts_tree_with_options<D: Domain, W: Write>(
+        mcts: &MCTS<D>,
+        options: PlotOptions,
+        w: &mut W,
+    ) -> io::Result<()> {
+        dot::render(&TreePlot { mcts, options }, w)
+    }
+
+    /// Options selecting the part of the search tree to plot, so that large trees produce readable graphs.
+    #[derive(Clone, Copy, Debug)]
+    pub struct PlotOptions {
+        /// The depth of the graph to plot, in number of nodes
+        pub max_depth: usize,
+        /// The minimum number of visits of an edge for it and its child to be plotted
+        pub min_visits: usize,
+    }
+    impl Default for PlotOptions {
+        /// Uses the [graph output depth](get_graph_output_depth) and plots all expanded edges.
+        fn default() -> Self {
+            Self {
+                max_depth: get_graph_output_depth(),
+                min_visits: 0,
+            }
+        }
+    }
+
+    /// A search tree with the options to plot it.
+    struct TreePlot<'a, D: Domain> {
+        mcts: &'a MCTS<D>,
+        options: PlotOptions,
     }
 
     fn agent_color_hsv(agent: AgentId) -> (f32, f32, f32) {
@@ -946,22 +1561,25 @@ pub mod graphviz {
         GRAPH_OUTPUT_DEPTH.load(std::sync::atomic::Ordering::Relaxed)
     }
 
-    impl<D: Domain> MCTS<D> {
+    impl<'a, D: Domain> TreePlot<'a, D> {
         fn add_relevant_nodes(
             &self,
             nodes: &mut SeededHashSet<Node<D>>,
             node: &Node<D>,
             depth: usize,
         ) {
-            if depth >= GRAPH_OUTPUT_DEPTH.load(std::sync::atomic::Ordering::Relaxed) {
+            if depth >= self.options.max_depth {
                 return;
             }
 
             nodes.insert(node.clone());
 
-            let edges = self.nodes.get(node).unwrap();
+            let edges = self.mcts.nodes.get(node).unwrap();
             for edge in edges.expanded_tasks.values() {
                 if let Ok(edge) = edge.try_lock() {
+                    if edge.visits < self.options.min_visits {
+                        continue;
+                    }
                     // Prevent recursion
                     if let Some(child) = edge.child.upgrade() {
                         // TODO: Priority queue
@@ -972,24 +1590,24 @@ pub mod graphviz {
         }
     }
 
-    impl<'a, D: Domain> GraphWalk<'a, Node<D>, Edge<D>> for MCTS<D> {
+    impl<'a, D: Domain> GraphWalk<'a, Node<D>, Edge<D>> for TreePlot<'_, D> {
         fn nodes(&'a self) -> Nodes<'a, Node<D>> {
             let mut nodes = SeededHashSet::default();
-            self.add_relevant_nodes(&mut nodes, &self.root, 0);
+            self.add_relevant_nodes(&mut nodes, &self.mcts.root, 0);
 
             Nodes::Owned(nodes.iter().cloned().collect::<Vec<_>>())
         }
 
         fn edges(&'a self) -> Edges<'a, Edge<D>> {
             let mut nodes = SeededHashSet::default();
-            self.add_relevant_nodes(&mut nodes, &self.root, 0);
+            self.add_relevant_nodes(&mut nodes, &self.mcts.root, 0);
 
             let mut edge_vec = Vec::new();
             nodes.iter().for_each(|node| {
-                let edges = self.nodes.get(node).unwrap();
+                let edges = self.mcts.nodes.get(node).unwrap();
 
                 if !edges.expanded_tasks.is_empty() {
-                    let range = self.min_max_range(self.root_agent);
+                    let range = self.mcts.min_max_range(self.mcts.root_agent);
                     let best_task = edges
                         .best_task(node.active_agent, 0., range.clone())
                         .unwrap();
@@ -1000,7 +1618,7 @@ pub mod graphviz {
                         let parent = edge.parent();
                         let child = edge.child();
 
-                        if nodes.contains(&child) {
+                        if nodes.contains(&child) && edge.visits >= self.options.min_visits {
                             let child_value = child.current_value(node.active_agent);
                             let parent_value = parent.current_value(node.active_agent);
                             let reward = child_value - parent_value;
@@ -1014,7 +1632,7 @@ pub mod graphviz {
                                 uct: edge.uct(
                                     node.active_agent,
                                     visits,
-                                    self.config.exploration,
+                                    self.mcts.config.exploration,
                                     range.clone(),
                                 ),
                                 uct_0: edge.uct(node.active_agent, visits, 0., range.clone()),
@@ -1037,9 +1655,9 @@ pub mod graphviz {
         }
     }
 
-    impl<'a, D: Domain> Labeller<'a, Node<D>, Edge<D>> for MCTS<D> {
+    impl<'a, D: Domain> Labeller<'a, Node<D>, Edge<D>> for TreePlot<'_, D> {
         fn graph_id(&'a self) -> Id<'a> {
-            Id::new(format!("agent_{}", self.root_agent.0)).unwrap()
+            Id::new(format!("agent_{}", self.mcts.root_agent.0)).unwrap()
         }
 
         fn node_id(&'a self, n: &Node<D>) -> Id<'a> {
@@ -1047,18 +1665,19 @@ pub mod graphviz {
         }
 
         fn node_label(&'a self, n: &Node<D>) -> LabelText<'a> {
-            let edges = self.nodes.get(n).unwrap();
+            let edges = self.mcts.nodes.get(n).unwrap();
             let q_v = edges.q_value((0, 0.), n.active_agent);
-            let state_diff = StateDiffRef::new(&self.initial_state, &n.diff);
+            let state_diff = StateDiffRef::new(&self.mcts.initial_state, &n.diff);
             let mut state = D::get_state_description(state_diff);
             if !state.is_empty() {
                 state = state.replace('\n', "<br/>");
                 state = format!("<br/><font point-size='10'>{state}</font>");
             }
             LabelText::HtmlStr(Cow::Owned(format!(
-                "Agent {}<br/>T: {}, Q: {}<br/>V: {:?}{state}",
+                "Agent {}<br/>T: {}, N: {}, Q: {}<br/>V: {:?}{state}",
                 n.active_agent.0,
                 n.tick,
+                edges.child_visits(),
                 q_v.map(|q_v| format!("{:.2}", q_v))
                     .unwrap_or_else(|| "None".to_owned()),
                 n.current_values()
@@ -1069,7 +1688,7 @@ pub mod graphviz {
         }
 
         fn node_style(&'a self, node: &Node<D>) -> Style {
-            if *node == self.root {
+            if *node == self.mcts.root {
                 Style::Bold
             } else {
                 Style::Filled
@@ -1077,10 +1696,10 @@ pub mod graphviz {
         }
 
         fn node_color(&'a self, node: &Node<D>) -> Option<LabelText<'a>> {
-            let root_visits = self.nodes.get(&self.root).unwrap().child_visits();
-            let visits = self.nodes.get(node).unwrap().child_visits();
+            let root_visits = self.mcts.nodes.get(&self.mcts.root).unwrap().child_visits();
+            let visits = self.mcts.nodes.get(node).unwrap().child_visits();
 
-            if *node == self.root {
+            if *node == self.mcts.root {
                 Some(LabelText::LabelStr(Cow::Borrowed("red")))
             } else {
                 let (h, s, _v) = agent_color_hsv(node.active_agent);
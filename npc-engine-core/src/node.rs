@@ -3,17 +3,20 @@
  *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
  */
 
-use std::{
-    collections::{BTreeMap, BTreeSet},
+use alloc::{
+    collections::BTreeSet,
+    sync::{Arc, Weak},
+};
+use core::{
     fmt,
     hash::{Hash, Hasher},
     mem,
-    sync::{Arc, Weak},
 };
 
 use crate::{
     active_task::{ActiveTask, ActiveTasks},
-    get_task_for_agent, AgentId, AgentValue, Context, Domain, StateDiffRef, Task,
+    get_task_for_agent, timed_hook, AgentId, AgentMap, AgentValue, Context, Domain, StateDiffRef,
+    Task, VisibilityCache,
 };
 
 /// Strong atomic reference counted node.
@@ -28,7 +31,7 @@ pub struct NodeInner<D: Domain> {
     pub(crate) active_agent: AgentId,
     pub(crate) tick: u64,
     pub(crate) tasks: ActiveTasks<D>,
-    current_values: BTreeMap<AgentId, AgentValue>, // pre-computed current values
+    current_values: AgentMap<AgentValue>, // pre-computed current values
 }
 
 impl<D: Domain> fmt::Debug for NodeInner<D> {
@@ -53,11 +56,32 @@ impl<D: Domain> NodeInner<D> {
         active_agent: AgentId,
         tick: u64,
         tasks: BTreeSet<ActiveTask<D>>,
+    ) -> Self {
+        Self::new_with_visibility_cache(
+            initial_state,
+            start_tick,
+            diff,
+            active_agent,
+            tick,
+            tasks,
+            &mut VisibilityCache::default(),
+        )
+    }
+
+    /// Create a new node as [new](Self::new), memoizing the visible agents in `visibility_cache`.
+    pub(crate) fn new_with_visibility_cache(
+        initial_state: &D::State,
+        start_tick: u64,
+        diff: D::Diff,
+        active_agent: AgentId,
+        tick: u64,
+        tasks: BTreeSet<ActiveTask<D>>,
+        visibility_cache: &mut VisibilityCache,
     ) -> Self {
         let ctx = Context::with_state_and_diff(tick, initial_state, &diff, active_agent);
         // Get list of agents we consider in planning
         let mut agents = tasks.iter().map(|task| task.agent).collect();
-        D::update_visible_agents(start_tick, ctx, &mut agents);
+        visibility_cache.update_visible_agents(start_tick, ctx, &mut agents);
 
         // Assign idle tasks to agents without a task
         let (tasks, current_values): (ActiveTasks<D>, _) = agents
@@ -73,7 +97,13 @@ impl<D: Domain> NodeInner<D> {
                 let agent = task.agent;
                 (
                     task,
-                    (agent, D::get_current_value(ctx.tick, ctx.state_diff, agent)),
+                    (
+                        agent,
+                        timed_hook!(
+                            get_current_value,
+                            D::get_current_value(ctx.tick, ctx.state_diff, agent)
+                        ),
+                    ),
                 )
             })
             .unzip();
@@ -87,6 +117,24 @@ impl<D: Domain> NodeInner<D> {
         }
     }
 
+    /// Creates a node from its parts, used when restoring a snapshot.
+    #[cfg(feature = "snapshot")]
+    pub(crate) fn from_parts(
+        diff: D::Diff,
+        active_agent: AgentId,
+        tick: u64,
+        tasks: ActiveTasks<D>,
+        current_values: AgentMap<AgentValue>,
+    ) -> Self {
+        NodeInner {
+            active_agent,
+            diff,
+            tick,
+            tasks,
+            current_values,
+        }
+    }
+
     /// Returns the agent who owns the node
     pub fn agent(&self) -> AgentId {
         self.active_agent
@@ -102,6 +150,11 @@ impl<D: Domain> NodeInner<D> {
         self.tasks.iter().map(|task| task.agent).collect()
     }
 
+    /// Returns the active tasks of all agents considered by this node
+    pub fn active_tasks(&self) -> &ActiveTasks<D> {
+        &self.tasks
+    }
+
     /// Returns the diff of current node.
     pub fn diff(&self) -> &D::Diff {
         &self.diff
@@ -118,16 +171,19 @@ impl<D: Domain> NodeInner<D> {
     /// Returns the current value from an agent, compute if not present in the node
     pub fn current_value_or_compute(&self, agent: AgentId, initial_state: &D::State) -> AgentValue {
         self.current_values.get(&agent).copied().unwrap_or_else(|| {
-            D::get_current_value(
-                self.tick,
-                StateDiffRef::new(initial_state, &self.diff),
-                agent,
+            timed_hook!(
+                get_current_value,
+                D::get_current_value(
+                    self.tick,
+                    StateDiffRef::new(initial_state, &self.diff),
+                    agent,
+                )
             )
         })
     }
 
     /// Returns all current values
-    pub fn current_values(&self) -> &BTreeMap<AgentId, AgentValue> {
+    pub fn current_values(&self) -> &AgentMap<AgentValue> {
         &self.current_values
     }
 
@@ -0,0 +1,113 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+#[cfg(feature = "std")]
+use core::cell::Cell;
+use core::{fmt, time::Duration};
+
+use crate::Instant;
+
+/// The time spent in the phases of a search, see [MCTS::set_profiling](crate::MCTS::set_profiling).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SearchProfile {
+    /// Time spent descending the tree
+    pub selection: Duration,
+    /// Time spent creating new nodes
+    pub expansion: Duration,
+    /// Time spent estimating the value of new nodes, typically in rollouts
+    pub estimation: Duration,
+    /// Time spent propagating values back up the tree
+    pub backpropagation: Duration,
+    /// Time spent in domain callbacks, also counted in the phases calling them
+    pub domain_callbacks: Duration,
+    /// The number of calls to domain callbacks
+    pub domain_callback_calls: u64,
+}
+
+impl SearchProfile {
+    /// Returns the total time spent in the phases.
+    pub fn total(&self) -> Duration {
+        self.selection + self.expansion + self.estimation + self.backpropagation
+    }
+
+    /// Returns the time spent in the phases but outside domain callbacks, that is in the planner itself.
+    pub fn engine(&self) -> Duration {
+        self.total().saturating_sub(self.domain_callbacks)
+    }
+}
+
+impl fmt::Display for SearchProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total = self.total().as_secs_f64().max(f64::MIN_POSITIVE);
+        let percent = |duration: Duration| 100. * duration.as_secs_f64() / total;
+        write!(
+            f,
+            "selection {:.1}%, expansion {:.1}%, estimation {:.1}%, backpropagation {:.1}%, domain callbacks {:.1}% ({} calls)",
+            percent(self.selection),
+            percent(self.expansion),
+            percent(self.estimation),
+            percent(self.backpropagation),
+            percent(self.domain_callbacks),
+            self.domain_callback_calls
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+thread_local! {
+    /// Whether a search profiles the domain callbacks on this thread.
+    static PROFILING: Cell<bool> = const { Cell::new(false) };
+    /// The number of calls and the time spent in domain callbacks since profiling started on this thread.
+    static CALLBACKS: Cell<(u64, Duration)> = const { Cell::new((0, Duration::ZERO)) };
+}
+
+/// Starts timing a domain callback, if profiling on this thread.
+#[cfg(feature = "std")]
+pub(crate) fn callback_start() -> Option<Instant> {
+    PROFILING.with(Cell::get).then(Instant::now)
+}
+
+/// Records the end of a domain callback started with [callback_start].
+#[cfg(feature = "std")]
+pub(crate) fn callback_end(start: Option<Instant>) {
+    if let Some(start) = start {
+        CALLBACKS.with(|callbacks| {
+            let (calls, time) = callbacks.get();
+            callbacks.set((calls + 1, time + start.elapsed()));
+        });
+    }
+}
+
+/// Starts profiling the domain callbacks on this thread.
+#[cfg(feature = "std")]
+pub(crate) fn start() {
+    PROFILING.with(|profiling| profiling.set(true));
+    CALLBACKS.with(|callbacks| callbacks.set((0, Duration::ZERO)));
+}
+
+/// Stops profiling the domain callbacks on this thread and adds them to `profile`.
+#[cfg(feature = "std")]
+pub(crate) fn stop(profile: &mut SearchProfile) {
+    PROFILING.with(|profiling| profiling.set(false));
+    let (calls, time) = CALLBACKS.with(Cell::get);
+    profile.domain_callback_calls += calls;
+    profile.domain_callbacks += time;
+}
+
+// Without std, there are no thread-local variables, so domain callbacks are not profiled
+
+#[cfg(not(feature = "std"))]
+pub(crate) fn callback_start() -> Option<Instant> {
+    None
+}
+
+#[cfg(not(feature = "std"))]
+pub(crate) fn callback_end(_start: Option<Instant>) {}
+
+#[cfg(not(feature = "std"))]
+pub(crate) fn start() {}
+
+#[cfg(not(feature = "std"))]
+pub(crate) fn stop(_profile: &mut SearchProfile) {}
@@ -0,0 +1,40 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use alloc::boxed::Box;
+use core::time::Duration;
+
+use crate::{Domain, Task};
+
+/// Running statistics of a search, passed to its progress callback, see [MCTS::set_progress_callback](crate::MCTS::set_progress_callback).
+#[derive(Debug)]
+pub struct SearchProgress<D: Domain> {
+    /// The number of visits done so far
+    pub visits_done: u32,
+    /// The maximum number of visits of the search
+    pub visits: u32,
+    /// The best task at the root so far, if any task was expanded
+    pub best_task: Option<Box<dyn Task<D>>>,
+    /// The estimated value of the root for the agent of the search
+    pub value: f32,
+    /// The number of nodes of the search tree
+    pub node_count: usize,
+    /// The number of edges of the search tree
+    pub edge_count: usize,
+    /// The computation time spent in the search so far
+    pub elapsed: Duration,
+}
+
+/// Whether a search continues after its progress callback returns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProgressControl {
+    /// The search continues
+    Continue,
+    /// The search stops, as with an early stop
+    Stop,
+}
+
+/// A functor called periodically with the progress of a search.
+pub type ProgressCallback<D> = dyn FnMut(&SearchProgress<D>) -> ProgressControl + Send;
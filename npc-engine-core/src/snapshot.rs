@@ -0,0 +1,358 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    num::NonZeroU64,
+    sync::{Arc, Mutex},
+};
+
+use rand::distributions::WeightedIndex;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    ActiveTask, AgentId, AgentValue, Context, Domain, EarlyStopCondition, EdgeInner, Edges,
+    IdleTask, MCTSConfiguration, Node, NodeInner, PlanningTask, SeededHashMap, StateValueEstimator,
+    Task, MCTS,
+};
+
+/// A domain whose tasks can be converted to and from a serializable form.
+///
+/// This is needed to save search trees, see [TreeSnapshot], and to save executors in `npc-engine-utils`.
+/// The built-in [IdleTask] and [PlanningTask] are handled separately
+/// and never passed to these functions.
+pub trait SnapshotDomain: Domain {
+    /// The serializable form of a task of this domain.
+    type TaskSnapshot: Serialize + DeserializeOwned;
+
+    /// Converts a task of this domain to its serializable form.
+    fn snapshot_task(task: &dyn Task<Self>) -> Self::TaskSnapshot;
+
+    /// Re-creates a task of this domain from its serializable form.
+    fn restore_task(snapshot: Self::TaskSnapshot) -> Box<dyn Task<Self>>;
+}
+
+/// The serializable form of a task.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TaskSnapshot<T> {
+    /// An [IdleTask]
+    Idle,
+    /// A [PlanningTask] of the given duration
+    Planning(NonZeroU64),
+    /// A task of the domain
+    Domain(T),
+}
+impl<T> TaskSnapshot<T> {
+    /// Converts a task to its serializable form.
+    pub fn new<D: SnapshotDomain<TaskSnapshot = T>>(task: &dyn Task<D>) -> Self {
+        if task.downcast_ref::<IdleTask>().is_some() {
+            TaskSnapshot::Idle
+        } else if let Some(planning_task) = task.downcast_ref::<PlanningTask>() {
+            TaskSnapshot::Planning(planning_task.0)
+        } else {
+            TaskSnapshot::Domain(D::snapshot_task(task))
+        }
+    }
+
+    /// Re-creates the task.
+    pub fn restore<D: SnapshotDomain<TaskSnapshot = T>>(self) -> Box<dyn Task<D>> {
+        match self {
+            TaskSnapshot::Idle => Box::new(IdleTask),
+            TaskSnapshot::Planning(duration) => Box::new(PlanningTask(duration)),
+            TaskSnapshot::Domain(snapshot) => D::restore_task(snapshot),
+        }
+    }
+}
+
+/// The serializable form of an [ActiveTask].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ActiveTaskSnapshot<T> {
+    pub start: u64,
+    pub end: u64,
+    pub agent: AgentId,
+    pub task: TaskSnapshot<T>,
+}
+impl<T> ActiveTaskSnapshot<T> {
+    /// Converts an active task to its serializable form.
+    pub fn new<D: SnapshotDomain<TaskSnapshot = T>>(active_task: &ActiveTask<D>) -> Self {
+        Self {
+            start: active_task.start,
+            end: active_task.end,
+            agent: active_task.agent,
+            task: TaskSnapshot::new(&*active_task.task),
+        }
+    }
+
+    /// Re-creates the active task.
+    pub fn restore<D: SnapshotDomain<TaskSnapshot = T>>(self) -> ActiveTask<D> {
+        ActiveTask::new_with_end(self.start, self.end, self.agent, self.task.restore())
+    }
+}
+
+/// The serializable form of an expanded edge of a search tree.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EdgeSnapshot<T> {
+    /// The task leading to the child node
+    pub task: TaskSnapshot<T>,
+    /// The index of the child node in [TreeSnapshot::nodes]
+    pub child: usize,
+    /// The number of visits of this edge
+    pub visits: usize,
+    /// The q-values of the agents
+    pub q_values: BTreeMap<AgentId, f32>,
+}
+
+/// The serializable form of a node of a search tree, with its outgoing edges.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NodeSnapshot<Df, T> {
+    /// The agent who decides in this node
+    pub agent: AgentId,
+    /// The tick of this node
+    pub tick: u64,
+    /// The difference to the initial state of the search
+    pub diff: Df,
+    /// The active tasks of all agents considered in this node
+    pub tasks: Vec<ActiveTaskSnapshot<T>>,
+    /// The current values of these agents
+    pub current_values: BTreeMap<AgentId, f32>,
+    /// The expanded edges
+    pub edges: Vec<EdgeSnapshot<T>>,
+    /// The tasks not expanded yet
+    pub unexpanded_tasks: Vec<TaskSnapshot<T>>,
+}
+
+/// The serializable form of a whole search tree, including diffs and tasks.
+///
+/// It allows to dump a search from a running game and load it into offline analysis tools.
+/// The initial state of the search is not part of the snapshot, as it is typically large and known by the caller.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TreeSnapshot<Df, T> {
+    /// The agent for which the search was done
+    pub root_agent: AgentId,
+    /// The tick at which the search started
+    pub start_tick: u64,
+    /// The configuration of the search
+    pub config: MCTSConfiguration,
+    /// The seed of the search
+    pub seed: u64,
+    /// The number of visits done
+    pub visits_done: u32,
+    /// The range of q-values of each agent, used to normalize exploitation
+    pub q_value_ranges: BTreeMap<AgentId, (f32, f32)>,
+    /// The nodes of the tree, the root first, each with its outgoing edges
+    pub nodes: Vec<NodeSnapshot<Df, T>>,
+}
+
+impl<D: SnapshotDomain> MCTS<D> {
+    /// Takes a serializable snapshot of the search tree, numbering nodes in breadth-first order from the root.
+    pub fn snapshot(&self) -> TreeSnapshot<D::Diff, D::TaskSnapshot> {
+        let (order, indices) = self.number_nodes();
+        let nodes = order
+            .iter()
+            .map(|node| self.snapshot_node(node, &indices))
+            .collect();
+        TreeSnapshot {
+            nodes,
+            ..self.snapshot_without_nodes()
+        }
+    }
+
+    /// Re-creates a search from a snapshot taken by [snapshot](Self::snapshot) and the initial state of the search.
+    ///
+    /// The search can then be continued, [run](Self::run) and [run_with_budget](Self::run_with_budget)
+    /// both continuing until [visits](MCTSConfiguration::visits) visits are done in total.
+    /// To continue a finished search, increase the visits in the configuration of the snapshot.
+    /// The random number generator is re-seeded from the seed of the snapshot,
+    /// so a resumed search does not exactly match an uninterrupted one.
+    pub fn from_snapshot(
+        snapshot: TreeSnapshot<D::Diff, D::TaskSnapshot>,
+        initial_state: D::State,
+        state_value_estimator: Box<dyn StateValueEstimator<D> + Send>,
+        early_stop_condition: Option<Box<EarlyStopCondition>>,
+    ) -> Self {
+        // Create all nodes, then link them with edges
+        let mut snapshot_nodes = Vec::with_capacity(snapshot.nodes.len());
+        let nodes: Vec<Node<D>> = snapshot
+            .nodes
+            .into_iter()
+            .map(|node| {
+                let inner = NodeInner::from_parts(
+                    node.diff,
+                    node.agent,
+                    node.tick,
+                    node.tasks
+                        .into_iter()
+                        .map(ActiveTaskSnapshot::restore)
+                        .collect(),
+                    node.current_values
+                        .into_iter()
+                        .map(|(agent, value)| (agent, AgentValue::new(value).unwrap()))
+                        .collect(),
+                );
+                snapshot_nodes.push((node.edges, node.unexpanded_tasks));
+                Node::new(inner)
+            })
+            .collect();
+        let mut tree = SeededHashMap::with_capacity_and_hasher(nodes.len(), Default::default());
+        for (node, (edges, unexpanded_tasks)) in nodes.iter().zip(snapshot_nodes) {
+            let expanded_tasks = edges
+                .into_iter()
+                .map(|edge| {
+                    let child = &nodes[edge.child];
+                    let edge_inner = EdgeInner {
+                        parent: Node::downgrade(node),
+                        child: Node::downgrade(child),
+                        visits: edge.visits,
+                        q_values: edge.q_values.into_iter().collect(),
+                    };
+                    (edge.task.restore(), Arc::new(Mutex::new(edge_inner)))
+                })
+                .collect();
+            let unexpanded_tasks: Vec<Box<dyn Task<D>>> = unexpanded_tasks
+                .into_iter()
+                .map(TaskSnapshot::restore)
+                .collect();
+            let ctx = Context::with_state_and_diff(
+                node.tick,
+                &initial_state,
+                &node.diff,
+                node.active_agent,
+            );
+            let unexpanded_tasks =
+                WeightedIndex::new(unexpanded_tasks.iter().map(|task| task.weight(ctx)))
+                    .ok()
+                    .map(|weights| (weights, unexpanded_tasks));
+            tree.insert(
+                node.clone(),
+                Edges {
+                    unexpanded_tasks,
+                    expanded_tasks,
+                },
+            );
+        }
+        let root = nodes
+            .first()
+            .expect("A tree snapshot must contain at least its root")
+            .clone();
+
+        let mut mcts = Self::new_with_tasks(
+            initial_state,
+            snapshot.root_agent,
+            snapshot.start_tick,
+            root.tasks.clone(),
+            snapshot.config,
+            state_value_estimator,
+            early_stop_condition,
+        );
+        let q_value_ranges = snapshot
+            .q_value_ranges
+            .into_iter()
+            .map(|(agent, (start, end))| {
+                (
+                    agent,
+                    AgentValue::new(start).unwrap()..AgentValue::new(end).unwrap(),
+                )
+            })
+            .collect();
+        mcts.restore_tree(
+            root,
+            tree,
+            q_value_ranges,
+            snapshot.visits_done,
+            snapshot.seed,
+        );
+        mcts
+    }
+
+    /// Numbers nodes in breadth-first order, then adds the ones not reachable from the root, if any.
+    pub(crate) fn number_nodes(&self) -> (Vec<Node<D>>, SeededHashMap<Node<D>, usize>) {
+        let mut indices: SeededHashMap<Node<D>, usize> = Default::default();
+        let mut order = Vec::with_capacity(self.node_count());
+        let mut queue = VecDeque::from([self.root_node()]);
+        while let Some(node) = queue.pop_front() {
+            if indices.contains_key(&node) {
+                continue;
+            }
+            indices.insert(node.clone(), order.len());
+            order.push(node.clone());
+            if let Some(edges) = self.get_edges(&node) {
+                queue.extend(
+                    edges
+                        .expanded_tasks()
+                        .map(|(_, edge)| edge.lock().unwrap().child()),
+                );
+            }
+        }
+        for (node, _) in self.nodes() {
+            if !indices.contains_key(node) {
+                indices.insert(node.clone(), order.len());
+                order.push(node.clone());
+            }
+        }
+        (order, indices)
+    }
+
+    /// Takes a snapshot of a node and its edges, given the numbering of nodes.
+    pub(crate) fn snapshot_node(
+        &self,
+        node: &Node<D>,
+        indices: &SeededHashMap<Node<D>, usize>,
+    ) -> NodeSnapshot<D::Diff, D::TaskSnapshot> {
+        let edges = self.get_edges(node).unwrap();
+        NodeSnapshot {
+            agent: node.agent(),
+            tick: node.tick(),
+            diff: node.diff().clone(),
+            tasks: node.tasks.iter().map(ActiveTaskSnapshot::new).collect(),
+            current_values: node
+                .current_values()
+                .iter()
+                .map(|(agent, value)| (*agent, **value))
+                .collect(),
+            edges: edges
+                .expanded_tasks()
+                .map(|(task, edge)| {
+                    let edge = edge.lock().unwrap();
+                    EdgeSnapshot {
+                        task: TaskSnapshot::new(&**task),
+                        child: indices[&edge.child()],
+                        visits: edge.visits(),
+                        q_values: edge
+                            .q_values
+                            .iter()
+                            .map(|(agent, value)| (*agent, *value))
+                            .collect(),
+                    }
+                })
+                .collect(),
+            // Expanded tasks are kept with a zero weight, skip them
+            unexpanded_tasks: edges
+                .unexpanded_tasks
+                .iter()
+                .flat_map(|(_, tasks)| tasks)
+                .filter(|task| !edges.expanded_tasks.contains_key(*task))
+                .map(|task| TaskSnapshot::new(&**task))
+                .collect(),
+        }
+    }
+
+    /// Takes a snapshot of everything but the nodes.
+    pub(crate) fn snapshot_without_nodes(&self) -> TreeSnapshot<D::Diff, D::TaskSnapshot> {
+        TreeSnapshot {
+            root_agent: self.agent(),
+            start_tick: self.start_tick(),
+            config: self.config.clone(),
+            seed: self.seed(),
+            visits_done: self.visits_done(),
+            q_value_ranges: self
+                .q_value_ranges
+                .iter()
+                .map(|(agent, range)| (*agent, (*range.start, *range.end)))
+                .collect(),
+            nodes: Vec::new(),
+        }
+    }
+}
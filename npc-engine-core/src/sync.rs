@@ -0,0 +1,124 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+/// The mutex protecting the edges of the search tree.
+///
+/// This is [std::sync::Mutex], except without the `std` feature, where a spin lock is used instead.
+#[cfg(feature = "std")]
+pub(crate) use std::sync::Mutex;
+
+#[cfg(not(feature = "std"))]
+pub use spin::{Mutex, MutexGuard, WouldBlock};
+
+#[cfg(not(feature = "std"))]
+mod spin {
+    use core::{
+        cell::UnsafeCell,
+        convert::Infallible,
+        fmt,
+        marker::PhantomData,
+        ops::{Deref, DerefMut},
+        sync::atomic::{AtomicBool, Ordering},
+    };
+
+    /// A mutex spinning on an atomic flag, with the API subset of [std::sync::Mutex] used by the engine.
+    ///
+    /// As it cannot be poisoned, [lock](Self::lock) never fails.
+    pub struct Mutex<T: ?Sized> {
+        locked: AtomicBool,
+        value: UnsafeCell<T>,
+    }
+
+    // Safety: the flag ensures that only one thread accesses the value at a time
+    unsafe impl<T: ?Sized + Send> Send for Mutex<T> {}
+    unsafe impl<T: ?Sized + Send> Sync for Mutex<T> {}
+
+    /// The error returned by [Mutex::try_lock] when the mutex is already locked.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct WouldBlock;
+
+    impl<T> Mutex<T> {
+        /// Creates a new unlocked mutex.
+        pub const fn new(value: T) -> Self {
+            Self {
+                locked: AtomicBool::new(false),
+                value: UnsafeCell::new(value),
+            }
+        }
+
+        /// Consumes the mutex and returns its value.
+        pub fn into_inner(self) -> Result<T, Infallible> {
+            Ok(self.value.into_inner())
+        }
+    }
+
+    impl<T: ?Sized> Mutex<T> {
+        /// Locks the mutex, spinning until it is available.
+        pub fn lock(&self) -> Result<MutexGuard<'_, T>, Infallible> {
+            loop {
+                if let Ok(guard) = self.try_lock() {
+                    return Ok(guard);
+                }
+                while self.locked.load(Ordering::Relaxed) {
+                    core::hint::spin_loop();
+                }
+            }
+        }
+
+        /// Locks the mutex if it is available.
+        pub fn try_lock(&self) -> Result<MutexGuard<'_, T>, WouldBlock> {
+            self.locked
+                .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+                .map(|_| MutexGuard {
+                    mutex: self,
+                    _marker: PhantomData,
+                })
+                .map_err(|_| WouldBlock)
+        }
+
+        /// Returns the value mutably, no locking is needed as the mutex is borrowed mutably.
+        pub fn get_mut(&mut self) -> Result<&mut T, Infallible> {
+            Ok(self.value.get_mut())
+        }
+    }
+
+    impl<T: ?Sized + fmt::Debug> fmt::Debug for Mutex<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self.try_lock() {
+                Ok(guard) => f.debug_struct("Mutex").field("data", &&*guard).finish(),
+                Err(_) => f.write_str("Mutex { <locked> }"),
+            }
+        }
+    }
+
+    /// A locked [Mutex], unlocked when dropped.
+    pub struct MutexGuard<'a, T: ?Sized> {
+        mutex: &'a Mutex<T>,
+        // Shares the value across threads only if it is Sync, as a reference would
+        _marker: PhantomData<&'a mut T>,
+    }
+
+    impl<T: ?Sized> Deref for MutexGuard<'_, T> {
+        type Target = T;
+
+        fn deref(&self) -> &T {
+            // Safety: the guard holds the lock
+            unsafe { &*self.mutex.value.get() }
+        }
+    }
+
+    impl<T: ?Sized> DerefMut for MutexGuard<'_, T> {
+        fn deref_mut(&mut self) -> &mut T {
+            // Safety: the guard holds the lock
+            unsafe { &mut *self.mutex.value.get() }
+        }
+    }
+
+    impl<T: ?Sized> Drop for MutexGuard<'_, T> {
+        fn drop(&mut self) {
+            self.mutex.locked.store(false, Ordering::Release);
+        }
+    }
+}
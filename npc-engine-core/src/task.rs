@@ -3,7 +3,8 @@
  *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
  */
 
-use std::{
+use alloc::{boxed::Box, string::String};
+use core::{
     hash::{Hash, Hasher},
     num::NonZeroU64,
 };
@@ -28,7 +29,7 @@ pub fn debug_name_to_filename_safe(debug_name: &str) -> String {
 ///
 /// It is illegal to have a task of both 0-duration and not modifying the state,
 /// as this would lead to self-looping nodes in the planner.
-pub trait Task<D: Domain>: std::fmt::Debug + Downcast + Send + Sync {
+pub trait Task<D: Domain>: core::fmt::Debug + Downcast + Send + Sync {
     /// Returns the relative weight of the task for the given agent in the given tick and world state, by default weight is 1.0.
     fn weight(&self, _ctx: Context<D>) -> f32 {
         1.0
@@ -159,8 +160,8 @@ macro_rules! impl_task_boxed_methods {
             Box::new(self.clone())
         }
 
-        fn box_hash(&self, mut state: &mut dyn std::hash::Hasher) {
-            use std::hash::Hash;
+        fn box_hash(&self, mut state: &mut dyn ::core::hash::Hasher) {
+            use ::core::hash::Hash;
             self.hash(&mut state)
         }
 
@@ -0,0 +1,84 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+/// A measurement of a monotonic clock, used to time searches.
+///
+/// This is [std::time::Instant], except in browsers (`wasm32-unknown-unknown`),
+/// where the standard clock is not available and the clock of JavaScript is used instead,
+/// and without the `std` feature, where the clock given to `set_clock` is used.
+#[cfg(all(
+    feature = "std",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+pub use std::time::Instant;
+
+#[cfg(all(feature = "std", target_arch = "wasm32", target_os = "unknown"))]
+pub use wasm::Instant;
+
+#[cfg(not(feature = "std"))]
+pub use clock::{set_clock, Instant};
+
+#[cfg(all(feature = "std", target_arch = "wasm32", target_os = "unknown"))]
+mod wasm {
+    use std::time::Duration;
+
+    /// A measurement of the clock of JavaScript, in milliseconds, with the API subset of [std::time::Instant] used by the engine.
+    #[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+    pub struct Instant(f64);
+
+    impl Instant {
+        /// Returns the current time.
+        pub fn now() -> Self {
+            Self(js_sys::Date::now())
+        }
+
+        /// Returns the time elapsed since `earlier`, zero if `earlier` is later.
+        pub fn duration_since(&self, earlier: Instant) -> Duration {
+            Duration::from_secs_f64((self.0 - earlier.0).max(0.) / 1000.)
+        }
+
+        /// Returns the time elapsed since this instant.
+        pub fn elapsed(&self) -> Duration {
+            Self::now().duration_since(*self)
+        }
+    }
+}
+
+#[cfg(not(feature = "std"))]
+mod clock {
+    use core::time::Duration;
+
+    use crate::Mutex;
+
+    static CLOCK: Mutex<Option<fn() -> Duration>> = Mutex::new(None);
+
+    /// Sets the monotonic clock of the platform, returning the time since an arbitrary origin, used to time searches without the `std` feature.
+    ///
+    /// Until it is set, time does not advance, so searches are only bounded by their visits.
+    pub fn set_clock(now: fn() -> Duration) {
+        *CLOCK.lock().unwrap() = Some(now);
+    }
+
+    /// A measurement of the clock given to [set_clock], with the API subset of `std::time::Instant` used by the engine.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+    pub struct Instant(Duration);
+
+    impl Instant {
+        /// Returns the current time.
+        pub fn now() -> Self {
+            Self(CLOCK.lock().unwrap().map_or(Duration::ZERO, |now| now()))
+        }
+
+        /// Returns the time elapsed since `earlier`, zero if `earlier` is later.
+        pub fn duration_since(&self, earlier: Instant) -> Duration {
+            self.0.saturating_sub(earlier.0)
+        }
+
+        /// Returns the time elapsed since this instant.
+        pub fn elapsed(&self) -> Duration {
+            Self::now().duration_since(*self)
+        }
+    }
+}
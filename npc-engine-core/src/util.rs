@@ -4,7 +4,7 @@
  */
 
 #[allow(deprecated)]
-use std::hash::BuildHasher;
+use core::hash::BuildHasher;
 
 use rustc_hash::FxHasher;
 
@@ -32,8 +32,29 @@ impl BuildHasher for SeededRandomState {
     }
 }
 
+#[cfg(not(feature = "std"))]
+pub(crate) use hashbrown::{hash_map, HashMap};
+/// Hash maps and their iterators, from hashbrown without std.
+#[cfg(feature = "std")]
+pub(crate) use std::collections::{hash_map, HashMap};
+
 /// An `HashMap` with a defined seed.
-pub(crate) type SeededHashMap<K, V> = std::collections::HashMap<K, V, SeededRandomState>;
+pub(crate) type SeededHashMap<K, V> = HashMap<K, V, SeededRandomState>;
 /// An `HashSet` with a defined seed.
 #[cfg(feature = "graphviz")]
 pub(crate) type SeededHashSet<V> = std::collections::HashSet<V, SeededRandomState>;
+
+/// Evaluates the call of a domain hook, recording its duration with feature `hook-timing` and when profiling the search.
+macro_rules! timed_hook {
+    ($hook:ident, $call:expr) => {{
+        #[cfg(feature = "hook-timing")]
+        let start = crate::Instant::now();
+        let profile_start = crate::profile::callback_start();
+        let result = $call;
+        crate::profile::callback_end(profile_start);
+        #[cfg(feature = "hook-timing")]
+        crate::hook_timing::record(|timings| &mut timings.$hook, start.elapsed());
+        result
+    }};
+}
+pub(crate) use timed_hook;
@@ -0,0 +1,196 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use alloc::boxed::Box;
+use core::{fmt, ops::Range};
+
+use crate::{AgentValue, Domain, Edge, Edges, Node, SeededHashMap, Task, MCTS};
+
+/// Proven bounds of the value of a node or edge for the agent of the search, see [MCTS::set_bounds_pruning].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ValueBounds {
+    /// The value is at least this, possibly negative infinity
+    pub lower: f32,
+    /// The value is at most this, possibly infinity
+    pub upper: f32,
+}
+
+impl ValueBounds {
+    /// Bounds of a value about which nothing is known.
+    pub const UNKNOWN: Self = Self {
+        lower: f32::NEG_INFINITY,
+        upper: f32::INFINITY,
+    };
+
+    /// Bounds of an exactly-known value.
+    pub fn exact(value: f32) -> Self {
+        Self {
+            lower: value,
+            upper: value,
+        }
+    }
+
+    /// Returns whether the value is exactly known.
+    pub fn is_exact(&self) -> bool {
+        self.lower == self.upper
+    }
+}
+
+impl fmt::Display for ValueBounds {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}, {}]", self.lower, self.upper)
+    }
+}
+
+/// The bounds of the nodes of a search using bounds pruning.
+pub(crate) type NodeBounds<D> = SeededHashMap<Node<D>, ValueBounds>;
+
+impl<D: Domain> MCTS<D> {
+    /// Enables or disables the pruning of branches using proven value bounds, disabled by default.
+    ///
+    /// This is meant for strictly competitive two-agent domains, in which the other agent minimizes the value of the agent of the search.
+    /// Nodes without any task are terminal, and their exact values are propagated up the tree with the
+    /// current values and the discount, the agent of the search maximizing and the other agent minimizing.
+    /// Selection then skips the branches whose bounds prove they cannot change the choice at their parent,
+    /// and the search stops early once the value of the root is exactly known.
+    /// This must be called before running the search.
+    /// It has no effect when the agent of the search sees no other agent at the root, as there is no opponent to minimize.
+    pub fn set_bounds_pruning(&mut self, enabled: bool) {
+        if enabled && self.root_node().agents().len() < 2 {
+            log::warn!(
+                "{:?} does not prune with bounds, it is the only agent of the search",
+                self.agent()
+            );
+            self.node_bounds = None;
+            return;
+        }
+        self.node_bounds = if enabled {
+            Some(Default::default())
+        } else {
+            None
+        };
+    }
+
+    /// Returns the proven bounds of the value of `node` for the agent of the search, if bounds pruning is enabled.
+    pub fn value_bounds(&self, node: &Node<D>) -> Option<ValueBounds> {
+        self.node_bounds
+            .as_ref()
+            .map(|bounds| bounds.get(node).copied().unwrap_or(ValueBounds::UNKNOWN))
+    }
+
+    /// Returns the proven bounds of the value of the edge for the agent of the search, given the bounds of its child.
+    fn edge_bounds(&self, bounds: &NodeBounds<D>, edge: &Edge<D>) -> ValueBounds {
+        let (parent, child) = {
+            let edge = edge.lock().unwrap();
+            (edge.parent(), edge.child())
+        };
+        let child_bounds = bounds.get(&child).copied().unwrap_or(ValueBounds::UNKNOWN);
+        let agent = self.agent();
+        let delta = *(child.current_value_or_compute(agent, self.initial_state())
+            - parent.current_value_or_compute(agent, self.initial_state()));
+        let discount = Self::discount_factor(child.tick - parent.tick, &self.config);
+        ValueBounds {
+            lower: delta + discount * child_bounds.lower,
+            upper: delta + discount * child_bounds.upper,
+        }
+    }
+
+    /// Computes the bounds of `node` from the bounds of its children.
+    fn compute_node_bounds(
+        &self,
+        bounds: &NodeBounds<D>,
+        node: &Node<D>,
+        edges: &Edges<D>,
+    ) -> ValueBounds {
+        if edges.branching_factor() == 0 {
+            // Terminal node, no value will be added
+            return ValueBounds::exact(0.);
+        }
+        let fully_expanded = edges.unexpanded_count() == 0;
+        let children = edges
+            .expanded_tasks()
+            .map(|(_, edge)| self.edge_bounds(bounds, edge));
+        if node.active_agent == self.agent() {
+            // The agent of the search takes the best child, which is at least as good as any known child
+            let (lower, upper) = children.fold(
+                (f32::NEG_INFINITY, f32::NEG_INFINITY),
+                |(lower, upper), child| (lower.max(child.lower), upper.max(child.upper)),
+            );
+            ValueBounds {
+                lower,
+                upper: if fully_expanded { upper } else { f32::INFINITY },
+            }
+        } else {
+            // The other agent takes the worst child for the agent of the search
+            let (lower, upper) = children
+                .fold((f32::INFINITY, f32::INFINITY), |(lower, upper), child| {
+                    (lower.min(child.lower), upper.min(child.upper))
+                });
+            ValueBounds {
+                lower: if fully_expanded {
+                    lower
+                } else {
+                    f32::NEG_INFINITY
+                },
+                upper,
+            }
+        }
+    }
+
+    /// Updates the bounds of the nodes along `path`, from the leaf to the root.
+    pub(crate) fn update_node_bounds(&mut self, path: &[Edge<D>]) {
+        let mut bounds = match self.node_bounds.take() {
+            Some(bounds) => bounds,
+            None => return,
+        };
+        let nodes = path
+            .iter()
+            .rev()
+            .map(|edge| edge.lock().unwrap().child())
+            .chain(core::iter::once(self.root_node()));
+        for node in nodes {
+            let edges = self.get_edges(&node).unwrap();
+            let node_bounds = self.compute_node_bounds(&bounds, &node, edges);
+            bounds.insert(node, node_bounds);
+        }
+        let root_bounds = bounds[&self.root_node()];
+        self.node_bounds = Some(bounds);
+        if root_bounds.is_exact() && !self.early_stopped {
+            log::info!(
+                "{:?} stops planning after {} visits, the root value is proven to be {}",
+                self.agent(),
+                self.visits_done(),
+                root_bounds.lower
+            );
+            self.early_stopped = true;
+        }
+    }
+
+    /// Returns the best task at `node` among the children not pruned by their bounds, if bounds pruning is enabled.
+    pub(crate) fn best_unpruned_task(
+        &self,
+        node: &Node<D>,
+        exploration: f32,
+        range: Range<AgentValue>,
+    ) -> Option<Option<Box<dyn Task<D>>>> {
+        let bounds = self.node_bounds.as_ref()?;
+        let node_bounds = bounds.get(node).copied().unwrap_or(ValueBounds::UNKNOWN);
+        let maximizing = node.active_agent == self.agent();
+        let edges = self.get_edges(node).unwrap();
+        let task = edges
+            .best_task_filtered(node.active_agent, exploration, range.clone(), |edge| {
+                let edge_bounds = self.edge_bounds(bounds, edge);
+                // Keep the children that could still change the value of the node
+                if maximizing {
+                    edge_bounds.upper > node_bounds.lower
+                } else {
+                    edge_bounds.lower < node_bounds.upper
+                }
+            })
+            // All children are pruned if the node is solved
+            .or_else(|| edges.best_task(node.active_agent, exploration, range));
+        Some(task)
+    }
+}
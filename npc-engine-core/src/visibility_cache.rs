@@ -0,0 +1,76 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use alloc::collections::BTreeSet;
+
+use crate::{timed_hook, AgentId, Context, Domain, SeededHashMap};
+
+/// A memoization of the visible agents of nodes, keyed on [Domain::visibility_hash].
+///
+/// See [MCTS::visibility_cache](crate::MCTS::visibility_cache).
+#[derive(Debug, Default)]
+pub struct VisibilityCache {
+    entries: SeededHashMap<(u64, BTreeSet<AgentId>), BTreeSet<AgentId>>,
+    hits: usize,
+    misses: usize,
+}
+
+impl VisibilityCache {
+    /// Updates `agents` as [Domain::update_visible_agents], re-using a previous result if the visibility hash is the same.
+    pub(crate) fn update_visible_agents<D: Domain>(
+        &mut self,
+        start_tick: u64,
+        ctx: Context<D>,
+        agents: &mut BTreeSet<AgentId>,
+    ) {
+        let hash = match D::visibility_hash(start_tick, ctx) {
+            Some(hash) => hash,
+            None => {
+                timed_hook!(
+                    update_visible_agents,
+                    D::update_visible_agents(start_tick, ctx, agents)
+                );
+                return;
+            }
+        };
+        let key = (hash, agents.clone());
+        if let Some(visible_agents) = self.entries.get(&key) {
+            self.hits += 1;
+            agents.clone_from(visible_agents);
+            return;
+        }
+        self.misses += 1;
+        timed_hook!(
+            update_visible_agents,
+            D::update_visible_agents(start_tick, ctx, agents)
+        );
+        self.entries.insert(key, agents.clone());
+    }
+
+    /// Removes all memoized entries and resets the statistics.
+    pub(crate) fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Returns the number of times a memoized result was used.
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// Returns the number of times the visible agents had to be computed despite a visibility hash.
+    pub fn misses(&self) -> usize {
+        self.misses
+    }
+
+    /// Returns the number of memoized entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns whether there are no memoized entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
@@ -0,0 +1,44 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use npc_engine_core::{AgentId, AgentMap, AgentVecMap};
+
+#[test]
+fn agent_vec_map_behaves_like_an_ordered_map() {
+    let mut map: AgentVecMap<u32> = [(AgentId(3), 30), (AgentId(1), 10), (AgentId(3), 31)]
+        .into_iter()
+        .collect();
+    assert_eq!(map.len(), 2);
+    // The last value of a duplicated agent is kept
+    assert_eq!(map.get(&AgentId(3)), Some(&31));
+    assert_eq!(map.insert(AgentId(2), 20), None);
+    assert_eq!(map.insert(AgentId(1), 11), Some(10));
+    *map.get_mut(&AgentId(2)).unwrap() += 1;
+    assert!(!map.contains_key(&AgentId(0)));
+    let entries: Vec<_> = map.iter().map(|(agent, value)| (agent.0, *value)).collect();
+    assert_eq!(entries, vec![(1, 11), (2, 21), (3, 31)]);
+    assert_eq!(map.remove(&AgentId(2)), Some(21));
+    assert_eq!(
+        map.keys().copied().collect::<Vec<_>>(),
+        vec![AgentId(1), AgentId(3)]
+    );
+    map.clear();
+    assert!(map.is_empty());
+}
+
+#[test]
+fn agent_map_is_ordered_whatever_its_storage() {
+    let mut map: AgentMap<u32> = [(AgentId(2), 20), (AgentId(0), 0)].into_iter().collect();
+    map.extend([(AgentId(1), 10), (AgentId(2), 21)]);
+    assert_eq!(
+        format!("{:?}", map),
+        "{AgentId(0): 0, AgentId(1): 10, AgentId(2): 21}"
+    );
+    assert_eq!(map.values().copied().collect::<Vec<_>>(), vec![0, 10, 21]);
+    assert_eq!(
+        map.into_iter().collect::<Vec<_>>(),
+        vec![(AgentId(0), 0), (AgentId(1), 10), (AgentId(2), 21)]
+    );
+}
@@ -0,0 +1,56 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::num::NonZeroU64;
+
+use npc_engine_core::{ConfigError, MCTSConfiguration};
+
+#[test]
+fn builder_validates_parameters() {
+    let config = MCTSConfiguration::builder()
+        .visits(50)
+        .depth(4)
+        .planning_task_duration(3)
+        .seed(7)
+        .build()
+        .unwrap();
+    assert_eq!(config.visits, 50);
+    assert_eq!(config.depth, 4);
+    assert_eq!(config.planning_task_duration, NonZeroU64::new(3));
+    assert_eq!(config.seed, Some(7));
+    assert!(!config.allow_invalid_tasks);
+
+    let error = |builder: npc_engine_core::MCTSConfigurationBuilder| builder.build().unwrap_err();
+    assert_eq!(
+        error(MCTSConfiguration::builder().visits(0)),
+        ConfigError::ZeroVisits
+    );
+    assert_eq!(
+        error(MCTSConfiguration::builder().depth(0)),
+        ConfigError::ZeroDepth
+    );
+    assert_eq!(
+        error(MCTSConfiguration::builder().exploration(-1.)),
+        ConfigError::InvalidExploration(-1.)
+    );
+    assert!(matches!(
+        error(MCTSConfiguration::builder().discount_hl(f32::NAN)),
+        ConfigError::InvalidDiscountHalfLife(_)
+    ));
+    assert_eq!(
+        error(MCTSConfiguration::builder().planning_task_duration(0)),
+        ConfigError::ZeroPlanningTaskDuration
+    );
+
+    // No discount is a valid choice
+    assert!(MCTSConfiguration::builder()
+        .discount_hl(f32::INFINITY)
+        .build()
+        .is_ok());
+    assert_eq!(
+        MCTSConfiguration::default().validate(),
+        Err(ConfigError::ZeroVisits)
+    );
+}
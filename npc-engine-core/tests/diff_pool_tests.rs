@@ -0,0 +1,132 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{
+    collections::BTreeSet,
+    hash::Hash,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use npc_engine_core::{
+    impl_task_boxed_methods, AgentId, AgentValue, Behavior, Context, ContextMut, DiffPool, Domain,
+    MCTSConfiguration, StateDiffRef, Task, TaskDuration, MCTS,
+};
+
+struct TestEngine;
+
+#[derive(Debug, Default, Eq, Hash, Clone, PartialEq)]
+struct Diff(Vec<u16>);
+
+impl Domain for TestEngine {
+    type State = u16;
+    type Diff = Diff;
+    type DisplayAction = ();
+
+    fn list_behaviors() -> &'static [&'static dyn Behavior<Self>] {
+        &[&TestBehavior]
+    }
+
+    fn get_current_value(
+        _tick: u64,
+        state_diff: StateDiffRef<Self>,
+        _agent: AgentId,
+    ) -> AgentValue {
+        (*state_diff.initial_state + state_diff.diff.0.iter().sum::<u16>()).into()
+    }
+
+    fn update_visible_agents(
+        _start_tick: u64,
+        _ctx: Context<TestEngine>,
+        agents: &mut BTreeSet<AgentId>,
+    ) {
+        agents.extend([AgentId(0), AgentId(1)]);
+    }
+
+    fn reset_diff(diff: &mut Diff, source: &Diff) {
+        RESETS.fetch_add(1, Ordering::Relaxed);
+        // The derived Clone does not keep the vector on clone_from, its field does
+        diff.0.clone_from(&source.0);
+    }
+}
+
+static RESETS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Copy, Clone, Debug)]
+struct TestBehavior;
+
+impl Behavior<TestEngine> for TestBehavior {
+    fn add_own_tasks(&self, _ctx: Context<TestEngine>, tasks: &mut Vec<Box<dyn Task<TestEngine>>>) {
+        tasks.push(Box::new(TestTask(1)));
+        tasks.push(Box::new(TestTask(2)));
+    }
+
+    fn is_valid(&self, _ctx: Context<TestEngine>) -> bool {
+        true
+    }
+}
+
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+struct TestTask(u16);
+
+impl Task<TestEngine> for TestTask {
+    fn weight(&self, _ctx: Context<TestEngine>) -> f32 {
+        1.
+    }
+
+    fn duration(&self, _ctx: Context<TestEngine>) -> TaskDuration {
+        1
+    }
+
+    fn is_valid(&self, _ctx: Context<TestEngine>) -> bool {
+        true
+    }
+
+    fn execute(&self, ctx: ContextMut<TestEngine>) -> Option<Box<dyn Task<TestEngine>>> {
+        ctx.state_diff.diff.0.push(self.0);
+        None
+    }
+
+    fn display_action(&self) -> <TestEngine as Domain>::DisplayAction {}
+
+    impl_task_boxed_methods!(TestEngine);
+}
+
+#[test]
+fn rollouts_reuse_diff_buffers() {
+    const CONFIG: MCTSConfiguration = MCTSConfiguration {
+        allow_invalid_tasks: false,
+        visits: 100,
+        depth: 6,
+        exploration: 1.414,
+        discount_hl: 15.,
+        seed: Some(0),
+        planning_task_duration: None,
+    };
+    let mut mcts = MCTS::<TestEngine>::new(0, AgentId(0), CONFIG);
+    let best = mcts.run().unwrap();
+    // Adding 2 is better than adding 1
+    assert_eq!(best.downcast_ref::<TestTask>().unwrap().0, 2);
+    // Rollouts are sequential, so a single buffer is allocated and re-used by all others
+    let pool = mcts.diff_pool();
+    assert_eq!(pool.allocated(), 1);
+    assert!(pool.reused() > 0);
+    assert_eq!(pool.free_count(), 1);
+    // Re-used buffers are overwritten by the domain
+    assert!(RESETS.load(Ordering::Relaxed) > 0);
+}
+
+#[test]
+fn reused_buffers_keep_their_allocation() {
+    let mut pool = DiffPool::default();
+    let buffer = Diff(Vec::with_capacity(64));
+    let pointer = buffer.0.as_ptr();
+    pool.recycle(buffer);
+    let copy = pool.clone_of(&Diff(vec![1, 2, 3]), TestEngine::reset_diff);
+    assert_eq!(copy, Diff(vec![1, 2, 3]));
+    assert_eq!(copy.0.as_ptr(), pointer);
+    assert!(copy.0.capacity() >= 64);
+    assert_eq!(pool.reused(), 1);
+    assert_eq!(pool.allocated(), 0);
+}
@@ -0,0 +1,215 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    hash::Hash,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use npc_engine_core::{
+    impl_task_boxed_methods, ActiveTask, AgentId, AgentValue, Behavior, Context, ContextMut,
+    DefaultPolicyEstimator, Domain, Edges, ExpansionMode, MCTSConfiguration, Node, StateDiffRef,
+    StateValueEstimator, Task, TaskDuration, MCTS,
+};
+
+struct TestEngine;
+
+#[derive(Debug, Default, Eq, Hash, Clone, PartialEq)]
+struct Diff(u16);
+
+impl Domain for TestEngine {
+    type State = u16;
+    type Diff = Diff;
+    type DisplayAction = ();
+
+    fn list_behaviors() -> &'static [&'static dyn Behavior<Self>] {
+        &[&TestBehavior]
+    }
+
+    fn get_current_value(
+        _tick: u64,
+        state_diff: StateDiffRef<Self>,
+        _agent: AgentId,
+    ) -> AgentValue {
+        (*state_diff.initial_state + state_diff.diff.0).into()
+    }
+
+    fn update_visible_agents(
+        _start_tick: u64,
+        _ctx: Context<TestEngine>,
+        agents: &mut BTreeSet<AgentId>,
+    ) {
+        agents.extend([AgentId(0), AgentId(1)]);
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct TestBehavior;
+
+impl Behavior<TestEngine> for TestBehavior {
+    fn add_own_tasks(&self, _ctx: Context<TestEngine>, tasks: &mut Vec<Box<dyn Task<TestEngine>>>) {
+        tasks.push(Box::new(TestTask(1)));
+        tasks.push(Box::new(TestTask(2)));
+    }
+
+    fn is_valid(&self, _ctx: Context<TestEngine>) -> bool {
+        true
+    }
+}
+
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+struct TestTask(u16);
+
+impl Task<TestEngine> for TestTask {
+    fn weight(&self, _ctx: Context<TestEngine>) -> f32 {
+        1.
+    }
+
+    fn duration(&self, _ctx: Context<TestEngine>) -> TaskDuration {
+        1
+    }
+
+    fn is_valid(&self, _ctx: Context<TestEngine>) -> bool {
+        true
+    }
+
+    fn execute(&self, ctx: ContextMut<TestEngine>) -> Option<Box<dyn Task<TestEngine>>> {
+        ctx.state_diff.diff.0 += self.0;
+        None
+    }
+
+    fn display_action(&self) -> <TestEngine as Domain>::DisplayAction {}
+
+    impl_task_boxed_methods!(TestEngine);
+}
+
+const CONFIG: MCTSConfiguration = MCTSConfiguration {
+    allow_invalid_tasks: false,
+    visits: 100,
+    depth: 6,
+    exploration: 1.414,
+    discount_hl: 15.,
+    seed: Some(0),
+    planning_task_duration: None,
+};
+
+/// Counts the batches and nodes estimated, delegating to the default policy
+struct CountingEstimator {
+    batches: Arc<AtomicUsize>,
+    nodes: Arc<AtomicUsize>,
+}
+
+impl StateValueEstimator<TestEngine> for CountingEstimator {
+    fn estimate(
+        &mut self,
+        rnd: &mut rand_chacha::ChaCha8Rng,
+        config: &MCTSConfiguration,
+        initial_state: &u16,
+        start_tick: u64,
+        node: &Node<TestEngine>,
+        edges: &Edges<TestEngine>,
+        depth: u32,
+    ) -> Option<BTreeMap<AgentId, f32>> {
+        DefaultPolicyEstimator {}.estimate(
+            rnd,
+            config,
+            initial_state,
+            start_tick,
+            node,
+            edges,
+            depth,
+        )
+    }
+
+    fn estimate_batch(
+        &mut self,
+        rnd: &mut rand_chacha::ChaCha8Rng,
+        config: &MCTSConfiguration,
+        initial_state: &u16,
+        start_tick: u64,
+        nodes: &[(&Node<TestEngine>, &Edges<TestEngine>, u32)],
+    ) -> Vec<Option<BTreeMap<AgentId, f32>>> {
+        self.batches.fetch_add(1, Ordering::Relaxed);
+        self.nodes.fetch_add(nodes.len(), Ordering::Relaxed);
+        nodes
+            .iter()
+            .map(|(node, edges, depth)| {
+                self.estimate(rnd, config, initial_state, start_tick, node, edges, *depth)
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn batch_expansion_creates_all_children_at_once() {
+    let batches = Arc::new(AtomicUsize::new(0));
+    let nodes = Arc::new(AtomicUsize::new(0));
+    let estimator = CountingEstimator {
+        batches: batches.clone(),
+        nodes: nodes.clone(),
+    };
+    let mut mcts = MCTS::<TestEngine>::new_with_tasks(
+        0,
+        AgentId(0),
+        0,
+        BTreeSet::<ActiveTask<TestEngine>>::new(),
+        CONFIG,
+        Box::new(estimator),
+        None,
+    );
+    mcts.set_expansion_mode(ExpansionMode::Batch);
+    let best = mcts.run().unwrap();
+    // Adding 2 is better than adding 1
+    assert_eq!(best.downcast_ref::<TestTask>().unwrap().0, 2);
+    let root_edges = mcts.get_edges(&mcts.root_node()).unwrap();
+    assert_eq!(root_edges.expanded_count(), 2);
+    // Each node has two tasks, so each batch estimates two nodes, each counting as a visit
+    assert!(batches.load(Ordering::Relaxed) > 0);
+    assert_eq!(
+        nodes.load(Ordering::Relaxed),
+        2 * batches.load(Ordering::Relaxed)
+    );
+    assert_eq!(mcts.visits_done(), CONFIG.visits);
+    assert!(root_edges.child_visits() as u32 <= mcts.visits_done());
+}
+
+#[test]
+fn single_expansion_creates_one_child_per_visit() {
+    let mut mcts = MCTS::<TestEngine>::new(0, AgentId(0), CONFIG);
+    mcts.run_with_budget(npc_engine_core::PlanningBudget::Visits(1));
+    assert_eq!(
+        mcts.get_edges(&mcts.root_node()).unwrap().expanded_count(),
+        1
+    );
+    let mut mcts = MCTS::<TestEngine>::new(0, AgentId(0), CONFIG);
+    mcts.set_expansion_mode(ExpansionMode::Batch);
+    mcts.run_with_budget(npc_engine_core::PlanningBudget::Visits(1));
+    assert_eq!(
+        mcts.get_edges(&mcts.root_node()).unwrap().expanded_count(),
+        2
+    );
+    assert_eq!(mcts.visits_done(), 2);
+}
+
+#[test]
+fn batch_expansion_stops_at_the_visits_of_the_search() {
+    // The root expands two children, which leaves one visit for a batch of two
+    let mut mcts = MCTS::<TestEngine>::new(
+        0,
+        AgentId(0),
+        MCTSConfiguration {
+            visits: 3,
+            ..CONFIG
+        },
+    );
+    mcts.set_expansion_mode(ExpansionMode::Batch);
+    mcts.run().unwrap();
+    assert_eq!(mcts.visits_done(), 3);
+    assert_eq!(mcts.node_count(), 4);
+}
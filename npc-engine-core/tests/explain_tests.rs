@@ -0,0 +1,144 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    hash::Hash,
+};
+
+use npc_engine_core::{
+    impl_task_boxed_methods, AgentId, AgentValue, Behavior, Context, ContextMut, DiffChange,
+    Domain, MCTSConfiguration, StateDiffRef, Task, TaskDuration, MCTS,
+};
+
+struct TestEngine;
+
+#[derive(Debug, Default, Eq, Hash, Clone, PartialEq)]
+struct Diff(u16);
+
+impl Domain for TestEngine {
+    type State = u16;
+    type Diff = Diff;
+    type DisplayAction = ();
+
+    fn list_behaviors() -> &'static [&'static dyn Behavior<Self>] {
+        &[&TestBehavior]
+    }
+
+    fn get_current_value(
+        _tick: u64,
+        state_diff: StateDiffRef<Self>,
+        _agent: AgentId,
+    ) -> AgentValue {
+        (*state_diff.initial_state + state_diff.diff.0).into()
+    }
+
+    fn update_visible_agents(
+        _start_tick: u64,
+        _ctx: Context<TestEngine>,
+        agents: &mut BTreeSet<AgentId>,
+    ) {
+        agents.extend([AgentId(0), AgentId(1)]);
+    }
+
+    fn display_diff(state_diff: StateDiffRef<Self>) -> BTreeMap<String, String> {
+        if state_diff.diff.0 == 0 {
+            BTreeMap::new()
+        } else {
+            BTreeMap::from([("sum".to_string(), state_diff.diff.0.to_string())])
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct TestBehavior;
+
+impl Behavior<TestEngine> for TestBehavior {
+    fn add_own_tasks(&self, _ctx: Context<TestEngine>, tasks: &mut Vec<Box<dyn Task<TestEngine>>>) {
+        tasks.push(Box::new(TestTask(1)));
+        tasks.push(Box::new(TestTask(2)));
+    }
+
+    fn is_valid(&self, _ctx: Context<TestEngine>) -> bool {
+        true
+    }
+}
+
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+struct TestTask(u16);
+
+impl Task<TestEngine> for TestTask {
+    fn weight(&self, _ctx: Context<TestEngine>) -> f32 {
+        1.
+    }
+
+    fn duration(&self, _ctx: Context<TestEngine>) -> TaskDuration {
+        1
+    }
+
+    fn is_valid(&self, _ctx: Context<TestEngine>) -> bool {
+        true
+    }
+
+    fn execute(&self, ctx: ContextMut<TestEngine>) -> Option<Box<dyn Task<TestEngine>>> {
+        ctx.state_diff.diff.0 += self.0;
+        None
+    }
+
+    fn display_action(&self) -> <TestEngine as Domain>::DisplayAction {}
+
+    impl_task_boxed_methods!(TestEngine);
+}
+
+#[test]
+fn explanation_describes_best_task() {
+    const CONFIG: MCTSConfiguration = MCTSConfiguration {
+        allow_invalid_tasks: false,
+        visits: 100,
+        depth: 6,
+        exploration: 1.414,
+        discount_hl: 15.,
+        seed: Some(0),
+        planning_task_duration: None,
+    };
+    let mut mcts = MCTS::<TestEngine>::new(0, AgentId(0), CONFIG);
+    assert!(mcts.explain_best_task().is_none());
+    let best = mcts.run().unwrap();
+    let explanation = mcts.explain_best_task().unwrap();
+    assert_eq!(explanation.agent, AgentId(0));
+    assert!(explanation.chosen.task == best);
+    // Adding 2 is better than adding 1
+    assert!(
+        explanation
+            .chosen
+            .task
+            .downcast_ref::<TestTask>()
+            .unwrap()
+            .0
+            == 2
+    );
+    assert_eq!(explanation.alternatives.len(), 1);
+    let components = explanation.value_components;
+    assert!((components.immediate + components.future - explanation.chosen.value).abs() < 1e-4);
+    assert!(components.margin.unwrap() > 0.);
+    assert_eq!(
+        explanation.chosen.visits + explanation.alternatives[0].visits,
+        mcts.get_edges(&mcts.root_node()).unwrap().child_visits()
+    );
+    assert!(!explanation.dominant_branch.is_empty());
+    assert_eq!(explanation.dominant_branch[0].agent, AgentId(1));
+    // Tasks execute when they end, so the changes appear along the branch
+    let changes: Vec<_> = std::iter::once(&explanation.chosen.changes)
+        .chain(explanation.dominant_branch.iter().map(|step| &step.changes))
+        .flatten()
+        .collect();
+    assert!(matches!(changes[0], DiffChange::Added { key, .. } if key == "sum"));
+    assert!(changes[1..]
+        .iter()
+        .all(|change| matches!(change, DiffChange::Changed { key, .. } if key == "sum")));
+    let text = explanation.to_string();
+    assert!(text.contains("chose TestTask(2)"));
+    assert!(text.contains("+ sum: "));
+}
@@ -0,0 +1,111 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{collections::BTreeSet, hash::Hash};
+
+use npc_engine_core::{
+    impl_task_boxed_methods, AgentId, AgentValue, Behavior, Context, ContextMut, Domain,
+    MCTSConfiguration, PlanningBudget, StateDiffRef, Task, TaskDuration, MCTS,
+};
+
+struct TestEngine;
+
+#[derive(Debug, Default, Eq, Hash, Clone, PartialEq)]
+struct Diff(u16);
+
+impl Domain for TestEngine {
+    type State = u16;
+    type Diff = Diff;
+    type DisplayAction = ();
+
+    fn list_behaviors() -> &'static [&'static dyn Behavior<Self>] {
+        &[&TestBehavior]
+    }
+
+    fn get_current_value(
+        _tick: u64,
+        state_diff: StateDiffRef<Self>,
+        _agent: AgentId,
+    ) -> AgentValue {
+        (*state_diff.initial_state + state_diff.diff.0).into()
+    }
+
+    fn update_visible_agents(
+        _start_tick: u64,
+        _ctx: Context<TestEngine>,
+        agents: &mut BTreeSet<AgentId>,
+    ) {
+        agents.extend([AgentId(0), AgentId(1)]);
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct TestBehavior;
+
+impl Behavior<TestEngine> for TestBehavior {
+    fn add_own_tasks(&self, _ctx: Context<TestEngine>, tasks: &mut Vec<Box<dyn Task<TestEngine>>>) {
+        tasks.push(Box::new(TestTask(1)));
+        tasks.push(Box::new(TestTask(2)));
+    }
+
+    fn is_valid(&self, _ctx: Context<TestEngine>) -> bool {
+        true
+    }
+}
+
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+struct TestTask(u16);
+
+impl Task<TestEngine> for TestTask {
+    fn weight(&self, _ctx: Context<TestEngine>) -> f32 {
+        1.
+    }
+
+    fn duration(&self, _ctx: Context<TestEngine>) -> TaskDuration {
+        1
+    }
+
+    fn is_valid(&self, _ctx: Context<TestEngine>) -> bool {
+        true
+    }
+
+    fn execute(&self, ctx: ContextMut<TestEngine>) -> Option<Box<dyn Task<TestEngine>>> {
+        ctx.state_diff.diff.0 += self.0;
+        None
+    }
+
+    fn display_action(&self) -> <TestEngine as Domain>::DisplayAction {}
+
+    impl_task_boxed_methods!(TestEngine);
+}
+
+#[test]
+fn hooks_are_timed() {
+    const CONFIG: MCTSConfiguration = MCTSConfiguration {
+        allow_invalid_tasks: false,
+        visits: 20,
+        depth: 5,
+        exploration: 1.414,
+        discount_hl: 15.,
+        seed: Some(0),
+        planning_task_duration: None,
+    };
+    let mut mcts = MCTS::<TestEngine>::new(0, AgentId(0), CONFIG);
+    mcts.run();
+    let timings = *mcts.hook_timings();
+    assert!(timings.execute.calls >= 20);
+    assert!(timings.is_valid.calls >= timings.execute.calls);
+    assert!(timings.get_current_value.calls > 0);
+    assert!(timings.update_visible_agents.calls > 0);
+    assert!(timings.total() <= mcts.time());
+
+    // Timings are accumulated over the slices of an incremental search
+    let mut incremental = MCTS::<TestEngine>::new(0, AgentId(0), CONFIG);
+    while !incremental.run_with_budget(PlanningBudget::Visits(5)) {}
+    assert_eq!(
+        incremental.hook_timings().execute.calls,
+        timings.execute.calls
+    );
+}
@@ -0,0 +1,147 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    hash::Hash,
+};
+
+use npc_engine_core::{
+    impl_task_boxed_methods, ActiveTask, ActiveTasks, AgentId, AgentValue, Behavior, Context,
+    ContextMut, Domain, MCTSConfiguration, StateDiffRef, Task, TaskDuration, MCTS,
+};
+
+struct TestEngine;
+
+#[derive(Debug, Default, Eq, Hash, Clone, PartialEq)]
+struct Diff(u16);
+
+impl Domain for TestEngine {
+    type State = u16;
+    type Diff = Diff;
+    type DisplayAction = ();
+
+    fn list_behaviors() -> &'static [&'static dyn Behavior<Self>] {
+        &[&TestBehavior]
+    }
+
+    fn get_current_value(
+        _tick: u64,
+        state_diff: StateDiffRef<Self>,
+        _agent: AgentId,
+    ) -> AgentValue {
+        (*state_diff.initial_state + state_diff.diff.0).into()
+    }
+
+    fn update_visible_agents(
+        _start_tick: u64,
+        _ctx: Context<TestEngine>,
+        agents: &mut BTreeSet<AgentId>,
+    ) {
+        agents.extend([AgentId(0), AgentId(1)]);
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct TestBehavior;
+
+impl Behavior<TestEngine> for TestBehavior {
+    fn add_own_tasks(&self, _ctx: Context<TestEngine>, tasks: &mut Vec<Box<dyn Task<TestEngine>>>) {
+        tasks.push(Box::new(TestTask(1)));
+        tasks.push(Box::new(TestTask(2)));
+    }
+
+    fn is_valid(&self, _ctx: Context<TestEngine>) -> bool {
+        true
+    }
+}
+
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+struct TestTask(u16);
+
+impl Task<TestEngine> for TestTask {
+    fn weight(&self, _ctx: Context<TestEngine>) -> f32 {
+        1.
+    }
+
+    fn duration(&self, _ctx: Context<TestEngine>) -> TaskDuration {
+        1
+    }
+
+    fn is_valid(&self, _ctx: Context<TestEngine>) -> bool {
+        true
+    }
+
+    fn execute(&self, ctx: ContextMut<TestEngine>) -> Option<Box<dyn Task<TestEngine>>> {
+        ctx.state_diff.diff.0 += self.0;
+        None
+    }
+
+    fn display_action(&self) -> <TestEngine as Domain>::DisplayAction {}
+
+    impl_task_boxed_methods!(TestEngine);
+}
+
+#[test]
+fn intentions_are_fixed_first_moves() {
+    const CONFIG: MCTSConfiguration = MCTSConfiguration {
+        allow_invalid_tasks: false,
+        visits: 200,
+        depth: 4,
+        exploration: 1.414,
+        discount_hl: 15.,
+        seed: Some(0),
+        planning_task_duration: None,
+    };
+    let other_agent = AgentId(1);
+    let tasks: ActiveTasks<TestEngine> = [ActiveTask::new_with_end(
+        0,
+        1,
+        other_agent,
+        Box::new(TestTask(1)),
+    )]
+    .into_iter()
+    .collect();
+    let intention: Box<dyn Task<TestEngine>> = Box::new(TestTask(2));
+    let tasks_of_other_agent = |intentions: BTreeMap<_, _>| {
+        let mut mcts = MCTS::<TestEngine>::new_with_tasks(
+            0,
+            AgentId(0),
+            0,
+            tasks.clone(),
+            CONFIG,
+            Box::new(npc_engine_core::DefaultPolicyEstimator {}),
+            None,
+        );
+        mcts.set_intentions(intentions);
+        mcts.run();
+        // Returns the tasks of the other agent at its first decision, and at the later ones
+        let mut first_tasks = BTreeSet::new();
+        let mut later_tasks = BTreeSet::new();
+        for (node, edges) in mcts.nodes() {
+            if node.agent() != other_agent {
+                continue;
+            }
+            let tasks = if node.tick() == 1 {
+                &mut first_tasks
+            } else {
+                &mut later_tasks
+            };
+            for (task, _) in edges.expanded_tasks() {
+                tasks.insert(task.downcast_ref::<TestTask>().unwrap().0);
+            }
+        }
+        (first_tasks, later_tasks)
+    };
+
+    let (first_tasks, later_tasks) = tasks_of_other_agent(BTreeMap::new());
+    assert_eq!(first_tasks, BTreeSet::from([1, 2]));
+    assert_eq!(later_tasks, BTreeSet::from([1, 2]));
+
+    let intentions = BTreeMap::from([(other_agent, intention)]);
+    let (first_tasks, later_tasks) = tasks_of_other_agent(intentions);
+    assert_eq!(first_tasks, BTreeSet::from([2]));
+    assert_eq!(later_tasks, BTreeSet::from([1, 2]));
+}
@@ -0,0 +1,126 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{collections::BTreeSet, hash::Hash, thread};
+
+use npc_engine_core::{
+    impl_task_boxed_methods, AgentId, AgentValue, Behavior, Context, ContextMut, Domain,
+    MCTSConfiguration, StateDiffRef, Task, TaskDuration, MCTS,
+};
+
+struct TestEngine;
+
+#[derive(Debug, Default, Eq, Hash, Clone, PartialEq)]
+struct Diff(u16);
+
+impl Domain for TestEngine {
+    type State = u16;
+    type Diff = Diff;
+    type DisplayAction = ();
+
+    fn list_behaviors() -> &'static [&'static dyn Behavior<Self>] {
+        &[&TestBehavior]
+    }
+
+    fn get_current_value(
+        _tick: u64,
+        state_diff: StateDiffRef<Self>,
+        _agent: AgentId,
+    ) -> AgentValue {
+        (*state_diff.initial_state + state_diff.diff.0).into()
+    }
+
+    fn update_visible_agents(
+        _start_tick: u64,
+        _ctx: Context<TestEngine>,
+        agents: &mut BTreeSet<AgentId>,
+    ) {
+        agents.extend([AgentId(0), AgentId(1)]);
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct TestBehavior;
+
+impl Behavior<TestEngine> for TestBehavior {
+    fn add_own_tasks(&self, _ctx: Context<TestEngine>, tasks: &mut Vec<Box<dyn Task<TestEngine>>>) {
+        tasks.push(Box::new(TestTask(1)));
+        tasks.push(Box::new(TestTask(2)));
+    }
+
+    fn is_valid(&self, _ctx: Context<TestEngine>) -> bool {
+        true
+    }
+}
+
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+struct TestTask(u16);
+
+impl Task<TestEngine> for TestTask {
+    fn weight(&self, _ctx: Context<TestEngine>) -> f32 {
+        1.
+    }
+
+    fn duration(&self, _ctx: Context<TestEngine>) -> TaskDuration {
+        1
+    }
+
+    fn is_valid(&self, _ctx: Context<TestEngine>) -> bool {
+        true
+    }
+
+    fn execute(&self, ctx: ContextMut<TestEngine>) -> Option<Box<dyn Task<TestEngine>>> {
+        ctx.state_diff.diff.0 += self.0;
+        None
+    }
+
+    fn display_action(&self) -> <TestEngine as Domain>::DisplayAction {}
+
+    impl_task_boxed_methods!(TestEngine);
+}
+
+#[test]
+fn live_statistics_are_readable_during_search() {
+    const CONFIG: MCTSConfiguration = MCTSConfiguration {
+        allow_invalid_tasks: false,
+        visits: 2000,
+        depth: 6,
+        exploration: 1.414,
+        discount_hl: 15.,
+        seed: Some(0),
+        planning_task_duration: None,
+    };
+    let mut mcts = MCTS::<TestEngine>::new(0, AgentId(0), CONFIG);
+    let statistics = mcts.live_statistics();
+    assert_eq!(statistics.visits_done(), 0);
+    assert_eq!(statistics.root_tasks().len(), 2);
+    assert!(statistics.best_task().is_none());
+
+    let reader = {
+        let statistics = statistics.clone();
+        thread::spawn(move || {
+            let mut last_visits = 0;
+            while !statistics.is_finished() {
+                let visits = statistics.visits_done();
+                assert!(visits >= last_visits);
+                last_visits = visits;
+                let task_visits: usize = statistics.root_tasks().iter().map(|t| t.visits).sum();
+                assert!(task_visits <= CONFIG.visits as usize);
+                thread::yield_now();
+            }
+        })
+    };
+    let best = mcts.run().unwrap();
+    reader.join().unwrap();
+
+    assert!(statistics.is_finished());
+    assert_eq!(statistics.visits_done(), mcts.visits_done());
+    assert_eq!(statistics.node_count(), mcts.node_count());
+    assert_eq!(statistics.edge_count(), mcts.edge_count());
+    assert!(statistics.best_task().unwrap() == best);
+    let root_edges = mcts.get_edges(&mcts.root_node()).unwrap();
+    let task_visits: usize = statistics.root_tasks().iter().map(|t| t.visits).sum();
+    assert_eq!(task_visits, root_edges.child_visits());
+}
@@ -0,0 +1,128 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{
+    collections::BTreeSet,
+    hash::Hash,
+    sync::{Arc, Mutex},
+};
+
+use npc_engine_core::{
+    impl_task_boxed_methods, AgentId, AgentValue, Behavior, Context, ContextMut, Domain,
+    MCTSConfiguration, ProgressControl, StateDiffRef, Task, TaskDuration, MCTS,
+};
+
+struct TestEngine;
+
+#[derive(Debug, Default, Eq, Hash, Clone, PartialEq)]
+struct Diff(u16);
+
+impl Domain for TestEngine {
+    type State = u16;
+    type Diff = Diff;
+    type DisplayAction = ();
+
+    fn list_behaviors() -> &'static [&'static dyn Behavior<Self>] {
+        &[&TestBehavior]
+    }
+
+    fn get_current_value(
+        _tick: u64,
+        state_diff: StateDiffRef<Self>,
+        _agent: AgentId,
+    ) -> AgentValue {
+        (*state_diff.initial_state + state_diff.diff.0).into()
+    }
+
+    fn update_visible_agents(
+        _start_tick: u64,
+        _ctx: Context<TestEngine>,
+        agents: &mut BTreeSet<AgentId>,
+    ) {
+        agents.extend([AgentId(0), AgentId(1)]);
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct TestBehavior;
+
+impl Behavior<TestEngine> for TestBehavior {
+    fn add_own_tasks(&self, _ctx: Context<TestEngine>, tasks: &mut Vec<Box<dyn Task<TestEngine>>>) {
+        tasks.push(Box::new(TestTask(1)));
+        tasks.push(Box::new(TestTask(2)));
+    }
+
+    fn is_valid(&self, _ctx: Context<TestEngine>) -> bool {
+        true
+    }
+}
+
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+struct TestTask(u16);
+
+impl Task<TestEngine> for TestTask {
+    fn weight(&self, _ctx: Context<TestEngine>) -> f32 {
+        1.
+    }
+
+    fn duration(&self, _ctx: Context<TestEngine>) -> TaskDuration {
+        1
+    }
+
+    fn is_valid(&self, _ctx: Context<TestEngine>) -> bool {
+        true
+    }
+
+    fn execute(&self, ctx: ContextMut<TestEngine>) -> Option<Box<dyn Task<TestEngine>>> {
+        ctx.state_diff.diff.0 += self.0;
+        None
+    }
+
+    fn display_action(&self) -> <TestEngine as Domain>::DisplayAction {}
+
+    impl_task_boxed_methods!(TestEngine);
+}
+
+#[test]
+fn progress_is_reported_and_can_stop_the_search() {
+    const CONFIG: MCTSConfiguration = MCTSConfiguration {
+        allow_invalid_tasks: false,
+        visits: 100,
+        depth: 5,
+        exploration: 1.414,
+        discount_hl: 15.,
+        seed: Some(0),
+        planning_task_duration: None,
+    };
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let mut mcts = MCTS::<TestEngine>::new(0, AgentId(0), CONFIG);
+    let recorded = reports.clone();
+    mcts.set_progress_callback(10, move |progress| {
+        recorded.lock().unwrap().push((
+            progress.visits_done,
+            progress.node_count,
+            progress.best_task.is_some(),
+        ));
+        ProgressControl::Continue
+    });
+    mcts.run();
+    let reports = reports.lock().unwrap();
+    assert_eq!(reports.len(), 10);
+    assert_eq!(reports[0].0, 10);
+    assert_eq!(reports[9], (100, mcts.node_count(), true));
+    assert!(reports.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+
+    // A callback can stop the search
+    let mut mcts = MCTS::<TestEngine>::new(0, AgentId(0), CONFIG);
+    mcts.set_progress_callback(1, |progress| {
+        if progress.visits_done >= 25 {
+            ProgressControl::Stop
+        } else {
+            ProgressControl::Continue
+        }
+    });
+    assert!(mcts.run().is_some());
+    assert_eq!(mcts.visits_done(), 25);
+}
@@ -7,7 +7,7 @@ use std::{collections::BTreeSet, fmt, hash::Hash};
 
 use npc_engine_core::{
     impl_task_boxed_methods, AgentId, AgentValue, Behavior, Context, ContextMut, Domain,
-    MCTSConfiguration, StateDiffRef, Task, TaskDuration, MCTS,
+    MCTSConfiguration, PlanningBudget, StateDiffRef, Task, TaskDuration, MCTS,
 };
 use rand::{thread_rng, RngCore};
 
@@ -84,7 +84,7 @@ impl Task<TestEngine> for TestTask {
         true
     }
 
-    fn execute(&self, mut ctx: ContextMut<TestEngine>) -> Option<Box<dyn Task<TestEngine>>> {
+    fn execute(&self, ctx: ContextMut<TestEngine>) -> Option<Box<dyn Task<TestEngine>>> {
         ctx.state_diff.diff.0 += self.0.min(1);
         None
     }
@@ -123,3 +123,30 @@ fn seed() {
         }
     }
 }
+
+#[test]
+fn incremental_search_matches_full_search() {
+    let agent = AgentId(0);
+    let config = MCTSConfiguration {
+        allow_invalid_tasks: false,
+        visits: 1000,
+        depth: 10,
+        exploration: 1.414,
+        discount_hl: 15.,
+        seed: Some(thread_rng().next_u64()),
+        planning_task_duration: None,
+    };
+    let state = State(Default::default());
+    let mut mcts = MCTS::<TestEngine>::new(state, agent, config.clone());
+    let result = mcts.run();
+
+    let mut mcts = MCTS::<TestEngine>::new(state, agent, config);
+    let mut slices = 0;
+    while !mcts.run_with_budget(PlanningBudget::Visits(64)) {
+        slices += 1;
+    }
+    assert_eq!(slices, 15);
+    assert_eq!(mcts.visits_done(), 1000);
+    assert!(mcts.run_with_budget(PlanningBudget::Visits(64)));
+    assert!(result == mcts.best_task_at_root());
+}
@@ -0,0 +1,265 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{collections::BTreeSet, hash::Hash};
+
+use npc_engine_core::{
+    impl_task_boxed_methods, AgentId, AgentValue, Behavior, Context, ContextMut,
+    DefaultPolicyEstimator, Domain, MCTSConfiguration, PlanningBudget, SnapshotDomain,
+    StateDiffRef, Task, TaskDuration, TaskSnapshot, TreeSnapshot, MCTS,
+};
+use serde::{Deserialize, Serialize};
+
+struct TestEngine;
+
+#[derive(Debug, Default, Eq, Hash, Clone, PartialEq, Serialize, Deserialize)]
+struct Diff(u16);
+
+impl Domain for TestEngine {
+    type State = u16;
+    type Diff = Diff;
+    type DisplayAction = ();
+
+    fn list_behaviors() -> &'static [&'static dyn Behavior<Self>] {
+        &[&TestBehavior]
+    }
+
+    fn get_current_value(
+        _tick: u64,
+        state_diff: StateDiffRef<Self>,
+        _agent: AgentId,
+    ) -> AgentValue {
+        (*state_diff.initial_state + state_diff.diff.0).into()
+    }
+
+    fn update_visible_agents(
+        _start_tick: u64,
+        _ctx: Context<TestEngine>,
+        agents: &mut BTreeSet<AgentId>,
+    ) {
+        agents.extend([AgentId(0), AgentId(1)]);
+    }
+}
+
+impl SnapshotDomain for TestEngine {
+    type TaskSnapshot = u16;
+
+    fn snapshot_task(task: &dyn Task<Self>) -> Self::TaskSnapshot {
+        task.downcast_ref::<TestTask>().unwrap().0
+    }
+
+    fn restore_task(snapshot: Self::TaskSnapshot) -> Box<dyn Task<Self>> {
+        Box::new(TestTask(snapshot))
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct TestBehavior;
+
+impl Behavior<TestEngine> for TestBehavior {
+    fn add_own_tasks(&self, _ctx: Context<TestEngine>, tasks: &mut Vec<Box<dyn Task<TestEngine>>>) {
+        tasks.push(Box::new(TestTask(1)));
+        tasks.push(Box::new(TestTask(2)));
+    }
+
+    fn is_valid(&self, _ctx: Context<TestEngine>) -> bool {
+        true
+    }
+}
+
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+struct TestTask(u16);
+
+impl Task<TestEngine> for TestTask {
+    fn weight(&self, _ctx: Context<TestEngine>) -> f32 {
+        1.
+    }
+
+    fn duration(&self, _ctx: Context<TestEngine>) -> TaskDuration {
+        1
+    }
+
+    fn is_valid(&self, _ctx: Context<TestEngine>) -> bool {
+        true
+    }
+
+    fn execute(&self, ctx: ContextMut<TestEngine>) -> Option<Box<dyn Task<TestEngine>>> {
+        ctx.state_diff.diff.0 += self.0;
+        None
+    }
+
+    fn display_action(&self) -> <TestEngine as Domain>::DisplayAction {}
+
+    impl_task_boxed_methods!(TestEngine);
+}
+
+#[test]
+fn tree_snapshot_contains_the_whole_tree() {
+    const CONFIG: MCTSConfiguration = MCTSConfiguration {
+        allow_invalid_tasks: false,
+        visits: 50,
+        depth: 4,
+        exploration: 1.414,
+        discount_hl: 15.,
+        seed: Some(0),
+        planning_task_duration: None,
+    };
+    let mut mcts = MCTS::<TestEngine>::new(0, AgentId(0), CONFIG);
+    mcts.run();
+    let snapshot = mcts.snapshot();
+    assert_eq!(snapshot.root_agent, AgentId(0));
+    assert_eq!(snapshot.visits_done, 50);
+    assert_eq!(snapshot.nodes.len(), mcts.node_count());
+    let edge_count: usize = snapshot.nodes.iter().map(|node| node.edges.len()).sum();
+    assert_eq!(edge_count, mcts.edge_count());
+    let root = &snapshot.nodes[0];
+    assert_eq!(root.agent, AgentId(0));
+    assert_eq!(root.tick, 0);
+    assert_eq!(root.tasks.len(), 2);
+    assert!(matches!(root.tasks[0].task, TaskSnapshot::Idle));
+    let root_visits: usize = root.edges.iter().map(|edge| edge.visits).sum();
+    assert_eq!(
+        root_visits,
+        mcts.get_edges(&mcts.root_node()).unwrap().child_visits()
+    );
+    for node in &snapshot.nodes {
+        for edge in &node.edges {
+            assert!(edge.child < snapshot.nodes.len());
+            assert!(matches!(edge.task, TaskSnapshot::Domain(1 | 2)));
+        }
+    }
+
+    // The snapshot survives a round trip through JSON
+    let json = serde_json::to_string(&snapshot).unwrap();
+    let restored: TreeSnapshot<Diff, u16> = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.nodes.len(), snapshot.nodes.len());
+    assert_eq!(serde_json::to_string(&restored).unwrap(), json);
+}
+
+#[test]
+fn search_resumes_from_snapshot() {
+    let config = MCTSConfiguration {
+        allow_invalid_tasks: false,
+        visits: 30,
+        depth: 4,
+        exploration: 1.414,
+        discount_hl: 15.,
+        seed: Some(2),
+        planning_task_duration: None,
+    };
+    let mut mcts = MCTS::<TestEngine>::new(0, AgentId(0), config);
+    mcts.run();
+    let json = serde_json::to_string(&mcts.snapshot()).unwrap();
+
+    let mut snapshot: TreeSnapshot<Diff, u16> = serde_json::from_str(&json).unwrap();
+    snapshot.config.visits = 80;
+    let mut resumed =
+        MCTS::<TestEngine>::from_snapshot(snapshot, 0, Box::new(DefaultPolicyEstimator {}), None);
+    assert_eq!(resumed.node_count(), mcts.node_count());
+    assert_eq!(resumed.edge_count(), mcts.edge_count());
+    assert_eq!(resumed.visits_done(), 30);
+    assert_eq!(
+        resumed
+            .get_edges(&resumed.root_node())
+            .unwrap()
+            .child_visits(),
+        mcts.get_edges(&mcts.root_node()).unwrap().child_visits()
+    );
+    assert!(!resumed.is_search_finished());
+
+    // The search continues where it left off
+    assert!(resumed.run_with_budget(PlanningBudget::Visits(100)));
+    assert_eq!(resumed.visits_done(), 80);
+    assert!(resumed.node_count() > mcts.node_count());
+    assert_eq!(
+        resumed
+            .get_edges(&resumed.root_node())
+            .unwrap()
+            .child_visits(),
+        80
+    );
+    assert!(resumed.best_task_at_root().is_some());
+}
+
+#[test]
+fn run_continues_restored_search() {
+    let config = MCTSConfiguration {
+        allow_invalid_tasks: false,
+        visits: 30,
+        depth: 4,
+        exploration: 1.414,
+        discount_hl: 15.,
+        seed: Some(3),
+        planning_task_duration: None,
+    };
+    let mut mcts = MCTS::<TestEngine>::new(0, AgentId(0), config);
+    mcts.run();
+    let agents = [AgentId(0), AgentId(1)];
+    let ranges = agents.map(|agent| mcts.min_max_range(agent));
+
+    let mut snapshot = mcts.snapshot();
+    snapshot.config.visits = 80;
+    let mut resumed =
+        MCTS::<TestEngine>::from_snapshot(snapshot, 0, Box::new(DefaultPolicyEstimator {}), None);
+    assert_eq!(agents.map(|agent| resumed.min_max_range(agent)), ranges);
+
+    // The ranges are kept, and only widen as the search continues up to the total visits
+    assert!(resumed.run().is_some());
+    assert_eq!(resumed.visits_done(), 80);
+    assert_eq!(
+        resumed
+            .get_edges(&resumed.root_node())
+            .unwrap()
+            .child_visits(),
+        80
+    );
+    for (agent, range) in agents.into_iter().zip(ranges) {
+        let resumed_range = resumed.min_max_range(agent);
+        assert!(resumed_range.start <= range.start);
+        assert!(resumed_range.end >= range.end);
+    }
+
+    // A new search on the same tree starts over
+    resumed.run();
+    assert_eq!(resumed.visits_done(), 80);
+    assert_eq!(
+        resumed
+            .get_edges(&resumed.root_node())
+            .unwrap()
+            .child_visits(),
+        160
+    );
+}
+
+#[cfg(feature = "compressed-snapshot")]
+#[test]
+fn compressed_dump_round_trips() {
+    use npc_engine_core::{read_compressed_snapshot, CompressedSnapshotReader};
+    let config = MCTSConfiguration {
+        allow_invalid_tasks: false,
+        visits: 50,
+        depth: 4,
+        exploration: 1.414,
+        discount_hl: 15.,
+        seed: Some(1),
+        planning_task_duration: None,
+    };
+    let mut mcts = MCTS::<TestEngine>::new(0, AgentId(0), config);
+    mcts.run();
+    let mut dump = Vec::new();
+    mcts.dump_compressed(&mut dump, 0).unwrap();
+
+    let reader = CompressedSnapshotReader::<_, Diff, u16>::new(dump.as_slice()).unwrap();
+    assert_eq!(reader.header().visits_done, 50);
+    assert!(reader.header().nodes.is_empty());
+    assert_eq!(reader.remaining_nodes(), mcts.node_count() as u64);
+    assert_eq!(reader.count(), mcts.node_count());
+
+    let restored: TreeSnapshot<Diff, u16> = read_compressed_snapshot(dump.as_slice()).unwrap();
+    assert_eq!(
+        serde_json::to_string(&restored).unwrap(),
+        serde_json::to_string(&mcts.snapshot()).unwrap()
+    );
+}
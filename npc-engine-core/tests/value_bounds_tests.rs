@@ -0,0 +1,206 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{collections::BTreeSet, hash::Hash};
+
+use npc_engine_core::{
+    impl_task_boxed_methods, AgentId, AgentValue, Behavior, Context, ContextMut, Domain,
+    MCTSConfiguration, StateDiffRef, Task, TaskDuration, MCTS,
+};
+
+/// Two agents take turns adding 1 or 2 to their own score, until the sum of the scores reaches 6
+struct RaceEngine;
+
+const END: u16 = 6;
+
+#[derive(Debug, Default, Eq, Hash, Clone, PartialEq)]
+struct Diff([u16; 2]);
+
+fn scores(state_diff: StateDiffRef<RaceEngine>) -> [u16; 2] {
+    let [a, b] = state_diff.diff.0;
+    [
+        state_diff.initial_state[0] + a,
+        state_diff.initial_state[1] + b,
+    ]
+}
+
+impl Domain for RaceEngine {
+    type State = [u16; 2];
+    type Diff = Diff;
+    type DisplayAction = ();
+
+    fn list_behaviors() -> &'static [&'static dyn Behavior<Self>] {
+        &[&RaceBehavior]
+    }
+
+    fn get_current_value(_tick: u64, state_diff: StateDiffRef<Self>, agent: AgentId) -> AgentValue {
+        // Strictly competitive, the value of one agent is the opposite of the other's
+        let [a, b] = scores(state_diff);
+        let value = a as f32 - b as f32;
+        let value = if agent == AgentId(0) { value } else { -value };
+        AgentValue::new(value).unwrap()
+    }
+
+    fn update_visible_agents(
+        _start_tick: u64,
+        _ctx: Context<RaceEngine>,
+        agents: &mut BTreeSet<AgentId>,
+    ) {
+        agents.extend([AgentId(0), AgentId(1)]);
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct RaceBehavior;
+
+impl Behavior<RaceEngine> for RaceBehavior {
+    fn add_own_tasks(&self, _ctx: Context<RaceEngine>, tasks: &mut Vec<Box<dyn Task<RaceEngine>>>) {
+        tasks.push(Box::new(Add(1)));
+        tasks.push(Box::new(Add(2)));
+    }
+
+    fn is_valid(&self, ctx: Context<RaceEngine>) -> bool {
+        let [a, b] = scores(ctx.state_diff);
+        a + b < END
+    }
+}
+
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+struct Add(u16);
+
+impl Task<RaceEngine> for Add {
+    fn weight(&self, _ctx: Context<RaceEngine>) -> f32 {
+        1.
+    }
+
+    fn duration(&self, _ctx: Context<RaceEngine>) -> TaskDuration {
+        1
+    }
+
+    fn is_valid(&self, _ctx: Context<RaceEngine>) -> bool {
+        true
+    }
+
+    fn execute(&self, ctx: ContextMut<RaceEngine>) -> Option<Box<dyn Task<RaceEngine>>> {
+        ctx.state_diff.diff.0[ctx.agent.0 as usize] += self.0;
+        None
+    }
+
+    fn display_action(&self) -> <RaceEngine as Domain>::DisplayAction {}
+
+    impl_task_boxed_methods!(RaceEngine);
+}
+
+const CONFIG: MCTSConfiguration = MCTSConfiguration {
+    allow_invalid_tasks: false,
+    visits: 5000,
+    depth: 20,
+    exploration: 1.414,
+    discount_hl: 15.,
+    seed: Some(0),
+    planning_task_duration: None,
+};
+
+#[test]
+fn bounds_pruning_solves_small_games() {
+    let mut mcts = MCTS::<RaceEngine>::new([0, 0], AgentId(0), CONFIG);
+    assert!(mcts.value_bounds(&mcts.root_node()).is_none());
+    let best = mcts.run().unwrap();
+    assert_eq!(best.downcast_ref::<Add>().unwrap().0, 2);
+    assert_eq!(mcts.visits_done(), CONFIG.visits);
+
+    let mut pruned = MCTS::<RaceEngine>::new([0, 0], AgentId(0), CONFIG);
+    pruned.set_bounds_pruning(true);
+    let pruned_best = pruned.run().unwrap();
+    assert!(pruned_best == best);
+    // The root value is proven before using all visits
+    let bounds = pruned.value_bounds(&pruned.root_node()).unwrap();
+    assert!(bounds.is_exact());
+    assert!(pruned.visits_done() < CONFIG.visits);
+    // The bounds of the root come from the solved best child
+    let root_edges = pruned.get_edges(&pruned.root_node()).unwrap();
+    let best_edge = root_edges.get_edge(&pruned_best).unwrap();
+    let child = best_edge.lock().unwrap().child();
+    let child_bounds = pruned.value_bounds(&child).unwrap();
+    assert!(child_bounds.is_exact());
+}
+
+/// A single agent adds 1 or 2 to its score, until it reaches 6
+struct SoloEngine;
+
+#[derive(Debug, Default, Eq, Hash, Clone, PartialEq)]
+struct SoloDiff(u16);
+
+impl Domain for SoloEngine {
+    type State = u16;
+    type Diff = SoloDiff;
+    type DisplayAction = ();
+
+    fn list_behaviors() -> &'static [&'static dyn Behavior<Self>] {
+        &[&SoloBehavior]
+    }
+
+    fn get_current_value(
+        _tick: u64,
+        state_diff: StateDiffRef<Self>,
+        _agent: AgentId,
+    ) -> AgentValue {
+        AgentValue::new((*state_diff.initial_state + state_diff.diff.0) as f32).unwrap()
+    }
+
+    fn update_visible_agents(
+        _start_tick: u64,
+        ctx: Context<SoloEngine>,
+        agents: &mut BTreeSet<AgentId>,
+    ) {
+        agents.insert(ctx.agent);
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct SoloBehavior;
+
+impl Behavior<SoloEngine> for SoloBehavior {
+    fn add_own_tasks(&self, _ctx: Context<SoloEngine>, tasks: &mut Vec<Box<dyn Task<SoloEngine>>>) {
+        tasks.push(Box::new(SoloAdd(1)));
+        tasks.push(Box::new(SoloAdd(2)));
+    }
+
+    fn is_valid(&self, ctx: Context<SoloEngine>) -> bool {
+        *ctx.state_diff.initial_state + ctx.state_diff.diff.0 < END
+    }
+}
+
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+struct SoloAdd(u16);
+
+impl Task<SoloEngine> for SoloAdd {
+    fn duration(&self, _ctx: Context<SoloEngine>) -> TaskDuration {
+        1
+    }
+
+    fn is_valid(&self, _ctx: Context<SoloEngine>) -> bool {
+        true
+    }
+
+    fn execute(&self, ctx: ContextMut<SoloEngine>) -> Option<Box<dyn Task<SoloEngine>>> {
+        ctx.state_diff.diff.0 += self.0;
+        None
+    }
+
+    fn display_action(&self) -> <SoloEngine as Domain>::DisplayAction {}
+
+    impl_task_boxed_methods!(SoloEngine);
+}
+
+#[test]
+fn bounds_pruning_is_ignored_without_an_opponent() {
+    let mut mcts = MCTS::<SoloEngine>::new(0, AgentId(0), CONFIG);
+    mcts.set_bounds_pruning(true);
+    assert!(mcts.value_bounds(&mcts.root_node()).is_none());
+    assert!(mcts.run().is_some());
+    // The search is a plain one, using all its visits
+    assert_eq!(mcts.visits_done(), CONFIG.visits);
+}
@@ -77,7 +77,7 @@ impl Task<TestEngine> for TestTask {
         true
     }
 
-    fn execute(&self, mut ctx: ContextMut<TestEngine>) -> Option<Box<dyn Task<TestEngine>>> {
+    fn execute(&self, ctx: ContextMut<TestEngine>) -> Option<Box<dyn Task<TestEngine>>> {
         ctx.state_diff.diff.0 += 1;
         None
     }
@@ -0,0 +1,130 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{
+    collections::BTreeSet,
+    hash::Hash,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+use npc_engine_core::{
+    impl_task_boxed_methods, AgentId, AgentValue, Behavior, Context, ContextMut, Domain,
+    MCTSConfiguration, StateDiffRef, Task, TaskDuration, MCTS,
+};
+
+static MEMOIZE: AtomicBool = AtomicBool::new(false);
+static VISIBILITY_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+struct TestEngine;
+
+#[derive(Debug, Default, Eq, Hash, Clone, PartialEq)]
+struct Diff(u16);
+
+impl Domain for TestEngine {
+    type State = u16;
+    type Diff = Diff;
+    type DisplayAction = ();
+
+    fn list_behaviors() -> &'static [&'static dyn Behavior<Self>] {
+        &[&TestBehavior]
+    }
+
+    fn get_current_value(
+        _tick: u64,
+        state_diff: StateDiffRef<Self>,
+        _agent: AgentId,
+    ) -> AgentValue {
+        (*state_diff.initial_state + state_diff.diff.0).into()
+    }
+
+    fn update_visible_agents(
+        _start_tick: u64,
+        _ctx: Context<TestEngine>,
+        agents: &mut BTreeSet<AgentId>,
+    ) {
+        VISIBILITY_CALLS.fetch_add(1, Ordering::Relaxed);
+        agents.extend([AgentId(0), AgentId(1)]);
+    }
+
+    fn visibility_hash(_start_tick: u64, _ctx: Context<Self>) -> Option<u64> {
+        // Visibility does not depend on the state
+        MEMOIZE.load(Ordering::Relaxed).then_some(0)
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct TestBehavior;
+
+impl Behavior<TestEngine> for TestBehavior {
+    fn add_own_tasks(&self, _ctx: Context<TestEngine>, tasks: &mut Vec<Box<dyn Task<TestEngine>>>) {
+        tasks.push(Box::new(TestTask(1)));
+        tasks.push(Box::new(TestTask(2)));
+    }
+
+    fn is_valid(&self, _ctx: Context<TestEngine>) -> bool {
+        true
+    }
+}
+
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+struct TestTask(u16);
+
+impl Task<TestEngine> for TestTask {
+    fn weight(&self, _ctx: Context<TestEngine>) -> f32 {
+        1.
+    }
+
+    fn duration(&self, _ctx: Context<TestEngine>) -> TaskDuration {
+        1
+    }
+
+    fn is_valid(&self, _ctx: Context<TestEngine>) -> bool {
+        true
+    }
+
+    fn execute(&self, ctx: ContextMut<TestEngine>) -> Option<Box<dyn Task<TestEngine>>> {
+        ctx.state_diff.diff.0 += self.0;
+        None
+    }
+
+    fn display_action(&self) -> <TestEngine as Domain>::DisplayAction {}
+
+    impl_task_boxed_methods!(TestEngine);
+}
+
+#[test]
+fn visible_agents_are_memoized() {
+    const CONFIG: MCTSConfiguration = MCTSConfiguration {
+        allow_invalid_tasks: false,
+        visits: 100,
+        depth: 6,
+        exploration: 1.414,
+        discount_hl: 15.,
+        seed: Some(0),
+        planning_task_duration: None,
+    };
+    let run = |memoize| {
+        MEMOIZE.store(memoize, Ordering::Relaxed);
+        VISIBILITY_CALLS.store(0, Ordering::Relaxed);
+        let mut mcts = MCTS::<TestEngine>::new(0, AgentId(0), CONFIG);
+        let best = mcts.run().unwrap();
+        (
+            best,
+            mcts.node_count(),
+            VISIBILITY_CALLS.load(Ordering::Relaxed),
+            mcts.visibility_cache().hits(),
+            mcts.visibility_cache().misses(),
+        )
+    };
+    let (best, node_count, calls, hits, misses) = run(false);
+    assert_eq!((hits, misses), (0, 0));
+    let (memoized_best, memoized_node_count, memoized_calls, hits, misses) = run(true);
+    // The search is the same, with fewer calls to update_visible_agents
+    assert!(memoized_best == best);
+    assert_eq!(memoized_node_count, node_count);
+    assert!(memoized_calls < calls);
+    assert!(hits > 0);
+    assert_eq!(misses, 1);
+}
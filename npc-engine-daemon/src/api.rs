@@ -0,0 +1,384 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::collections::BTreeMap;
+
+use npc_engine_core::{AgentId, MCTSConfiguration};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{DaemonDomain, Simulation};
+
+/// The largest number of ticks a single step request can advance a simulation by.
+pub const MAX_STEP_TICKS: u64 = 10_000;
+
+/// A response of the API: an HTTP status code and a JSON body.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Response {
+    /// The HTTP status code
+    pub status: u16,
+    /// The body
+    pub body: Value,
+}
+
+impl Response {
+    fn ok(body: Value) -> Self {
+        Self { status: 200, body }
+    }
+
+    fn created(body: Value) -> Self {
+        Self { status: 201, body }
+    }
+
+    fn error(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            body: json!({ "error": message.into() }),
+        }
+    }
+
+    /// Returns the reason phrase of the status code.
+    pub fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            201 => "Created",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            _ => "Internal Server Error",
+        }
+    }
+}
+
+/// The body of a request creating a simulation, whose MCTS configuration overrides the defaults.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NewSimulation {
+    scenario: String,
+    visits: Option<u32>,
+    depth: Option<u32>,
+    exploration: Option<f32>,
+    discount_hl: Option<f32>,
+    seed: Option<u64>,
+}
+
+impl NewSimulation {
+    fn mcts_config(&self) -> MCTSConfiguration {
+        let mut config = MCTSConfiguration::builder()
+            .build()
+            .expect("Default configuration is valid");
+        if let Some(visits) = self.visits {
+            config.visits = visits;
+        }
+        if let Some(depth) = self.depth {
+            config.depth = depth;
+        }
+        if let Some(exploration) = self.exploration {
+            config.exploration = exploration;
+        }
+        if let Some(discount_hl) = self.discount_hl {
+            config.discount_hl = discount_hl;
+        }
+        config.seed = self.seed;
+        config
+    }
+}
+
+/// The body of a step request, advancing by one tick by default.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StepRequest {
+    ticks: Option<u64>,
+}
+
+/// The registered scenarios and the running simulations of the daemon, with the handling of the requests of the REST API.
+///
+/// The routes are:
+/// - `GET /scenarios`: the names of the registered scenarios,
+/// - `POST /scenarios`: registers or replaces a scenario, given in the body,
+/// - `GET /simulations`: the ids of the running simulations,
+/// - `POST /simulations`: creates a simulation of `{"scenario": name}`, optionally with `visits`, `depth`, `exploration`, `discount_hl` and `seed`,
+/// - `GET /simulations/{id}`: the tick and state of a simulation,
+/// - `POST /simulations/{id}/step`: advances a simulation by `{"ticks": n}`, 1 by default, and returns the decisions taken,
+/// - `GET /simulations/{id}/decisions`: the decisions of a simulation, optionally filtered by `?agent=` and `?since=` a tick,
+/// - `DELETE /simulations/{id}`: stops a simulation.
+pub struct Registry<D: DaemonDomain>
+where
+    D::State: Clone,
+{
+    scenarios: BTreeMap<String, D::State>,
+    simulations: BTreeMap<u64, Simulation<D>>,
+    next_id: u64,
+}
+
+impl<D: DaemonDomain> Default for Registry<D>
+where
+    D::State: Clone,
+{
+    fn default() -> Self {
+        Self {
+            scenarios: BTreeMap::new(),
+            simulations: BTreeMap::new(),
+            next_id: 0,
+        }
+    }
+}
+
+impl<D: DaemonDomain> Registry<D>
+where
+    D::State: Clone,
+{
+    /// Registers the scenario `name` with its initial state, replacing any scenario of the same name.
+    pub fn register_scenario(&mut self, name: impl Into<String>, state: D::State) {
+        self.scenarios.insert(name.into(), state);
+    }
+
+    /// Returns the simulation `id`, if it is running.
+    pub fn simulation(&self, id: u64) -> Option<&Simulation<D>> {
+        self.simulations.get(&id)
+    }
+
+    /// Handles a request of the REST API, with the query string, if any, in `path`.
+    pub fn handle(&mut self, method: &str, path: &str, body: &str) -> Response {
+        let (path, query) = path.split_once('?').unwrap_or((path, ""));
+        let segments: Vec<_> = path
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .collect();
+        match (method, segments.as_slice()) {
+            ("GET", ["scenarios"]) => Response::ok(json!({
+                "scenarios": self.scenarios.keys().collect::<Vec<_>>()
+            })),
+            ("POST", ["scenarios"]) => self.post_scenario(body),
+            ("GET", ["simulations"]) => Response::ok(json!({
+                "simulations": self.simulations.keys().collect::<Vec<_>>()
+            })),
+            ("POST", ["simulations"]) => self.post_simulation(body),
+            (method, ["simulations", id, rest @ ..]) => {
+                let id = match id.parse() {
+                    Ok(id) if self.simulations.contains_key(&id) => id,
+                    _ => return Response::error(404, format!("no simulation {id}")),
+                };
+                match (method, rest) {
+                    ("GET", []) => Response::ok(self.summary(id)),
+                    ("DELETE", []) => {
+                        self.simulations.remove(&id);
+                        Response::ok(json!({ "deleted": id }))
+                    }
+                    ("POST", ["step"]) => self.post_step(id, body),
+                    ("GET", ["decisions"]) => self.get_decisions(id, query),
+                    (_, [] | ["step"] | ["decisions"]) => {
+                        Response::error(405, format!("{method} is not allowed on {path}"))
+                    }
+                    _ => Response::error(404, format!("no route {path}")),
+                }
+            }
+            (_, ["scenarios"] | ["simulations"]) => {
+                Response::error(405, format!("{method} is not allowed on {path}"))
+            }
+            _ => Response::error(404, format!("no route {path}")),
+        }
+    }
+
+    fn post_scenario(&mut self, body: &str) -> Response {
+        match D::parse_scenario(body) {
+            Ok((name, state)) => {
+                let agents = D::agents(&state);
+                self.register_scenario(name.clone(), state);
+                Response::created(json!({ "name": name, "agents": agents }))
+            }
+            Err(error) => Response::error(400, error),
+        }
+    }
+
+    fn post_simulation(&mut self, body: &str) -> Response {
+        let request: NewSimulation = match serde_json::from_str(body) {
+            Ok(request) => request,
+            Err(error) => return Response::error(400, format!("invalid simulation: {error}")),
+        };
+        let state = match self.scenarios.get(&request.scenario) {
+            Some(state) => state.clone(),
+            None => return Response::error(404, format!("no scenario {}", request.scenario)),
+        };
+        let mcts_config = request.mcts_config();
+        if let Err(error) = mcts_config.validate() {
+            return Response::error(400, format!("invalid MCTS configuration: {error}"));
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        self.simulations
+            .insert(id, Simulation::new(request.scenario, state, mcts_config));
+        Response::created(self.summary(id))
+    }
+
+    fn post_step(&mut self, id: u64, body: &str) -> Response {
+        let request: StepRequest = if body.trim().is_empty() {
+            StepRequest::default()
+        } else {
+            match serde_json::from_str(body) {
+                Ok(request) => request,
+                Err(error) => return Response::error(400, format!("invalid step: {error}")),
+            }
+        };
+        let ticks = request.ticks.unwrap_or(1);
+        if ticks > MAX_STEP_TICKS {
+            return Response::error(400, format!("cannot step more than {MAX_STEP_TICKS} ticks"));
+        }
+        let simulation = self.simulations.get_mut(&id).unwrap();
+        let first_decision = simulation.decisions().len();
+        for _ in 0..ticks {
+            simulation.step();
+        }
+        let decisions = &simulation.decisions()[first_decision..];
+        let decisions = json!(decisions);
+        let mut body = self.summary(id);
+        body["decisions"] = decisions;
+        Response::ok(body)
+    }
+
+    fn get_decisions(&self, id: u64, query: &str) -> Response {
+        let mut agent = None;
+        let mut since = 0;
+        for (key, value) in query.split('&').filter_map(|pair| pair.split_once('=')) {
+            let parsed = match key {
+                "agent" => value.parse().map(|value| agent = Some(AgentId(value))),
+                "since" => value.parse().map(|value| since = value),
+                _ => return Response::error(400, format!("unknown parameter {key}")),
+            };
+            if parsed.is_err() {
+                return Response::error(400, format!("invalid value of {key}: {value}"));
+            }
+        }
+        let decisions: Vec<_> = self.simulations[&id]
+            .decisions()
+            .iter()
+            .filter(|decision| agent.map_or(true, |agent| decision.agent == agent))
+            .filter(|decision| decision.tick >= since)
+            .collect();
+        Response::ok(json!({ "decisions": decisions }))
+    }
+
+    /// Returns the id, scenario, tick, configuration and state of the simulation `id`.
+    fn summary(&self, id: u64) -> Value {
+        let simulation = &self.simulations[&id];
+        json!({
+            "id": id,
+            "scenario": simulation.scenario(),
+            "tick": simulation.tick(),
+            "mcts": simulation.mcts_config(),
+            "state": D::state_json(simulation.state()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lumberjacks_web::LumberjacksDomain;
+
+    use super::*;
+
+    const FOREST: &str = r#"{
+        "name": "glade",
+        "agents": [{ "id": 0, "data": { "x": 0, "y": 0 } }, { "id": 1, "data": { "x": 1, "y": 2 } }],
+        "map": { "rows": ["..T", "...", "T.."] }
+    }"#;
+
+    fn registry() -> Registry<LumberjacksDomain> {
+        let mut registry = Registry::default();
+        let response = registry.handle("POST", "/scenarios", FOREST);
+        assert_eq!(response.status, 201);
+        assert_eq!(response.body, json!({ "name": "glade", "agents": [0, 1] }));
+        registry
+    }
+
+    #[test]
+    fn simulations_are_created_stepped_and_deleted() {
+        let mut registry = registry();
+        assert_eq!(
+            registry.handle("GET", "/scenarios", "").body,
+            json!({ "scenarios": ["glade"] })
+        );
+        let response = registry.handle(
+            "POST",
+            "/simulations",
+            r#"{ "scenario": "glade", "visits": 50, "seed": 3 }"#,
+        );
+        assert_eq!(response.status, 201);
+        assert_eq!(response.body["id"], 0);
+        assert_eq!(response.body["mcts"]["visits"], 50);
+        assert_eq!(response.body["state"]["rows"], json!(["..T", "...", "T.."]));
+
+        let response = registry.handle("POST", "/simulations/0/step", r#"{ "ticks": 3 }"#);
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body["tick"], 3);
+        let decisions = response.body["decisions"].as_array().unwrap();
+        assert!(!decisions.is_empty());
+        assert_eq!(
+            registry.handle("POST", "/simulations/0/step", "").body["tick"],
+            4
+        );
+
+        let all = registry.simulation(0).unwrap().decisions().len();
+        let response = registry.handle("GET", "/simulations/0/decisions?agent=1&since=1", "");
+        let decisions = response.body["decisions"].as_array().unwrap();
+        assert!(decisions.len() < all);
+        assert!(decisions
+            .iter()
+            .all(|decision| decision["agent"] == 1 && decision["tick"].as_u64().unwrap() >= 1));
+
+        assert_eq!(registry.handle("DELETE", "/simulations/0", "").status, 200);
+        assert_eq!(registry.handle("GET", "/simulations/0", "").status, 404);
+    }
+
+    #[test]
+    fn invalid_requests_are_rejected() {
+        let mut registry = registry();
+        let status = |registry: &mut Registry<_>, method, path, body| {
+            registry.handle(method, path, body).status
+        };
+        assert_eq!(
+            status(&mut registry, "POST", "/scenarios", r#"{ "name": "x" }"#),
+            400
+        );
+        assert_eq!(
+            status(
+                &mut registry,
+                "POST",
+                "/simulations",
+                r#"{ "scenario": "none" }"#
+            ),
+            404
+        );
+        assert_eq!(
+            status(
+                &mut registry,
+                "POST",
+                "/simulations",
+                r#"{ "scenario": "glade", "visits": 0 }"#
+            ),
+            400
+        );
+        assert_eq!(
+            status(&mut registry, "POST", "/simulations/7/step", ""),
+            404
+        );
+        assert_eq!(status(&mut registry, "PUT", "/scenarios", ""), 405);
+        assert_eq!(status(&mut registry, "GET", "/agents", ""), 404);
+        registry.handle("POST", "/simulations", r#"{ "scenario": "glade" }"#);
+        assert_eq!(
+            status(
+                &mut registry,
+                "POST",
+                "/simulations/0/step",
+                r#"{ "ticks": 100000 }"#
+            ),
+            400
+        );
+        assert_eq!(
+            status(&mut registry, "GET", "/simulations/0/decisions?agent=x", ""),
+            400
+        );
+    }
+}
@@ -0,0 +1,70 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use lumberjacks_web::{LumberjacksDomain, State};
+use npc_engine_cli::{validated_forest_state, ForestScenario};
+use npc_engine_core::AgentId;
+use npc_engine_utils::{Coord2D, ExecutableDomain};
+use serde_json::{json, Value};
+
+/// A domain whose scenarios can be registered and whose simulations can be observed through the daemon.
+pub trait DaemonDomain: ExecutableDomain
+where
+    Self::State: Clone,
+{
+    /// Parses a scenario from JSON, and returns its name and initial state.
+    fn parse_scenario(json: &str) -> Result<(String, Self::State), String>;
+
+    /// Returns the agents of `state`.
+    fn agents(state: &Self::State) -> Vec<AgentId>;
+
+    /// Returns `state` as JSON, for the observations of clients.
+    fn state_json(state: &Self::State) -> Value;
+}
+
+impl DaemonDomain for LumberjacksDomain {
+    fn parse_scenario(json: &str) -> Result<(String, State), String> {
+        let scenario = ForestScenario::from_json_str(json).map_err(|error| error.to_string())?;
+        let state = validated_forest_state(&scenario)?;
+        Ok((scenario.name, state))
+    }
+
+    fn agents(state: &State) -> Vec<AgentId> {
+        state.lumberjacks.keys().copied().collect()
+    }
+
+    fn state_json(state: &State) -> Value {
+        let rows: Vec<String> = (0..state.height)
+            .map(|y| {
+                (0..state.width)
+                    .map(|x| {
+                        if state.is_tree(Coord2D::new(x, y)) {
+                            'T'
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        let lumberjacks: Vec<_> = state
+            .lumberjacks
+            .iter()
+            .map(|(agent, lumberjack)| {
+                json!({
+                    "agent": agent,
+                    "x": lumberjack.position.x,
+                    "y": lumberjack.position.y,
+                    "wood": lumberjack.wood,
+                })
+            })
+            .collect();
+        json!({
+            "rows": rows,
+            "trees": state.tree_count(),
+            "lumberjacks": lumberjacks,
+        })
+    }
+}
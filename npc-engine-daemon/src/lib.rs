@@ -0,0 +1,22 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+//! This is a headless daemon driving simulations of the [NPC engine](https://crates.io/crates/npc-engine-core/) through a REST API, so that non-Rust pipelines such as reinforcement-learning training loops can use the engine as a service.
+//!
+//! It contains the following features:
+//! - A trait ([DaemonDomain]) for domains whose scenarios can be parsed from JSON and whose states can be observed as JSON, implemented for the forest of lumberjacks of the web demo.
+//! - Simulations ([Simulation]) stepped on demand, recording the decisions of their agents ([Decision]).
+//! - A registry of scenarios and simulations ([Registry]) handling the requests of the REST API, see [Registry::handle] for the routes.
+//! - An HTTP server ([DaemonServer]) serving the API of a registry.
+
+mod api;
+mod domain;
+mod server;
+mod simulation;
+
+pub use api::*;
+pub use domain::*;
+pub use server::*;
+pub use simulation::*;
@@ -0,0 +1,25 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use lumberjacks_web::LumberjacksDomain;
+use npc_engine_daemon::{DaemonServer, Registry};
+
+fn main() {
+    let addr = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:8080".to_string());
+    let server = match DaemonServer::<LumberjacksDomain>::bind(&addr, Registry::default()) {
+        Ok(server) => server,
+        Err(error) => {
+            eprintln!("error: cannot listen on {addr}: {error}");
+            std::process::exit(1);
+        }
+    };
+    println!("Listening on http://{}", server.local_addr());
+    // Requests are served on a background thread until the process is stopped
+    loop {
+        std::thread::park();
+    }
+}
@@ -0,0 +1,214 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::{DaemonDomain, Registry};
+
+/// The largest accepted size of the body of a request, in bytes.
+pub const MAX_BODY_SIZE: usize = 1 << 20;
+
+/// An HTTP server exposing the REST API of a [Registry], see [Registry::handle] for the routes.
+///
+/// Requests are handled one at a time on a background thread, and the server stops when dropped.
+pub struct DaemonServer<D: DaemonDomain>
+where
+    D::State: Clone,
+{
+    registry: Arc<Mutex<Registry<D>>>,
+    running: Arc<AtomicBool>,
+    local_addr: SocketAddr,
+}
+
+impl<D> DaemonServer<D>
+where
+    D: DaemonDomain + 'static,
+    D::State: Clone + Send,
+{
+    /// Starts a server listening on the given address, serving the requests of `registry` on a background thread.
+    pub fn bind(addr: impl ToSocketAddrs, registry: Registry<D>) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        let local_addr = listener.local_addr()?;
+        let registry = Arc::new(Mutex::new(registry));
+        let running = Arc::new(AtomicBool::new(true));
+        let thread_registry = registry.clone();
+        let thread_running = running.clone();
+        thread::Builder::new()
+            .name("daemon-server".into())
+            .spawn(move || {
+                while thread_running.load(Ordering::Relaxed) {
+                    match listener.accept() {
+                        Ok((stream, _)) => {
+                            if let Err(error) = handle_request(stream, &thread_registry) {
+                                log::debug!("Daemon connection failed: {error}");
+                            }
+                        }
+                        Err(error) if error.kind() == io::ErrorKind::WouldBlock => {
+                            thread::sleep(Duration::from_millis(5));
+                        }
+                        Err(error) => log::warn!("Daemon cannot accept: {error}"),
+                    }
+                }
+            })?;
+        Ok(Self {
+            registry,
+            running,
+            local_addr,
+        })
+    }
+}
+
+impl<D: DaemonDomain> DaemonServer<D>
+where
+    D::State: Clone,
+{
+    /// Returns the address the server listens on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Returns the registry served, for example to register scenarios from Rust.
+    pub fn registry(&self) -> &Arc<Mutex<Registry<D>>> {
+        &self.registry
+    }
+}
+
+impl<D: DaemonDomain> Drop for DaemonServer<D>
+where
+    D::State: Clone,
+{
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+    }
+}
+
+fn invalid_request(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Reads a request and returns its method, its path and its body.
+fn read_request(stream: &mut TcpStream) -> io::Result<(String, String, String)> {
+    let mut request = Vec::new();
+    let mut buffer = [0; 4096];
+    let head_len = loop {
+        if let Some(end) = request.windows(4).position(|end| end == b"\r\n\r\n") {
+            break end + 4;
+        }
+        let len = stream.read(&mut buffer)?;
+        if len == 0 || request.len() > 8192 {
+            return Err(invalid_request("malformed request"));
+        }
+        request.extend_from_slice(&buffer[..len]);
+    };
+    let head = String::from_utf8_lossy(&request[..head_len]).into_owned();
+    let mut words = head.split_whitespace();
+    let method = words.next().unwrap_or_default().to_string();
+    let path = words.next().unwrap_or("/").to_string();
+    let content_length = head
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+        .map_or(Ok(0), |(_, value)| value.trim().parse())
+        .map_err(|_| invalid_request("invalid content length"))?;
+    if content_length > MAX_BODY_SIZE {
+        return Err(invalid_request("body too large"));
+    }
+    let mut body = request.split_off(head_len);
+    body.truncate(content_length);
+    if body.len() < content_length {
+        let start = body.len();
+        body.resize(content_length, 0);
+        stream.read_exact(&mut body[start..])?;
+    }
+    let body = String::from_utf8(body).map_err(|_| invalid_request("body is not UTF-8"))?;
+    Ok((method, path, body))
+}
+
+fn handle_request<D: DaemonDomain>(
+    mut stream: TcpStream,
+    registry: &Mutex<Registry<D>>,
+) -> io::Result<()>
+where
+    D::State: Clone,
+{
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    stream.set_write_timeout(Some(Duration::from_secs(5)))?;
+    let (method, path, body) = read_request(&mut stream)?;
+    let response = registry.lock().unwrap().handle(&method, &path, &body);
+    let body = response.body.to_string();
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        response.status,
+        response.reason(),
+        body.len()
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use lumberjacks_web::LumberjacksDomain;
+    use serde_json::Value;
+
+    use super::*;
+
+    fn request(addr: SocketAddr, method: &str, path: &str, body: &str) -> (String, Value) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "{method} {path} HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.lines().next().unwrap().to_string();
+        (status, serde_json::from_str(body).unwrap())
+    }
+
+    #[test]
+    fn server_answers_requests() {
+        let server =
+            DaemonServer::<LumberjacksDomain>::bind("127.0.0.1:0", Registry::default()).unwrap();
+        let addr = server.local_addr();
+        let scenario = r#"{ "name": "clearing", "agents": [{ "id": 0, "data": { "x": 1, "y": 0 } }], "map": { "rows": ["T.T"] } }"#;
+        let (status, body) = request(addr, "POST", "/scenarios", scenario);
+        assert_eq!(status, "HTTP/1.1 201 Created");
+        assert_eq!(body["name"], "clearing");
+
+        let (status, _) = request(addr, "POST", "/simulations", r#"{"scenario":"clearing"}"#);
+        assert_eq!(status, "HTTP/1.1 201 Created");
+        let (status, body) = request(addr, "POST", "/simulations/0/step", r#"{"ticks":2}"#);
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert_eq!(body["tick"], 2);
+        assert_eq!(
+            server
+                .registry()
+                .lock()
+                .unwrap()
+                .simulation(0)
+                .unwrap()
+                .tick(),
+            2
+        );
+
+        let (status, body) = request(addr, "GET", "/nowhere", "");
+        assert_eq!(status, "HTTP/1.1 404 Not Found");
+        assert!(body["error"].is_string());
+    }
+}
@@ -0,0 +1,139 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use npc_engine_core::{ActiveTask, AgentId, IdleTask, MCTSConfiguration, MCTS};
+use npc_engine_utils::{ExecutionQueue, ExecutorState};
+use serde::Serialize;
+
+use crate::DaemonDomain;
+
+/// A task chosen by an agent, as reported to clients.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Decision {
+    /// The tick at which the task was chosen
+    pub tick: u64,
+    /// The tick at which the task ends
+    pub end: u64,
+    /// The agent that chose the task
+    pub agent: AgentId,
+    /// The task, in its debug format
+    pub task: String,
+}
+
+/// Records the decisions of the agents while stepping.
+#[derive(Default)]
+struct DecisionRecorder {
+    decisions: Vec<Decision>,
+}
+impl<D: DaemonDomain> ExecutorState<D> for DecisionRecorder
+where
+    D::State: Clone,
+{
+    fn on_task_chosen(&mut self, active_task: &ActiveTask<D>) {
+        self.decisions.push(Decision {
+            tick: active_task.start,
+            end: active_task.end,
+            agent: active_task.agent,
+            task: format!("{:?}", active_task.task),
+        });
+    }
+}
+
+/// A simulation of a scenario, stepped on demand by clients.
+pub struct Simulation<D: DaemonDomain>
+where
+    D::State: Clone,
+{
+    scenario: String,
+    mcts_config: MCTSConfiguration,
+    state: D::State,
+    queue: ExecutionQueue<D>,
+    tick: u64,
+    decisions: Vec<Decision>,
+}
+
+impl<D: DaemonDomain> Simulation<D>
+where
+    D::State: Clone,
+{
+    /// Creates a simulation of the scenario named `scenario` from its initial state, with all agents idle.
+    pub fn new(
+        scenario: impl Into<String>,
+        state: D::State,
+        mcts_config: MCTSConfiguration,
+    ) -> Self {
+        let mut queue = ExecutionQueue::new(Default::default());
+        for agent in D::agents(&state) {
+            queue.queue_task(0, agent, Box::new(IdleTask), &state);
+        }
+        Self {
+            scenario: scenario.into(),
+            mcts_config,
+            state,
+            queue,
+            tick: 0,
+            decisions: Vec::new(),
+        }
+    }
+
+    /// Executes the tasks ending at the current tick, plans for the agents without a subsequent task, and advances the tick.
+    pub fn step(&mut self) {
+        let mut recorder = DecisionRecorder::default();
+        loop {
+            match self.queue.task_queue().iter().next() {
+                Some(active_task) if active_task.end <= self.tick => {}
+                _ => break,
+            }
+            let active_task = self.queue.pop_first_task();
+            let active_agent = active_task.agent;
+            let tick = active_task.end;
+            let (diff, new_task) =
+                self.queue
+                    .execute_task(&active_task, &self.state, &mut recorder, |_| {});
+            D::apply_diff(diff, &mut self.state);
+            let new_task = new_task.unwrap_or_else(|| {
+                let mut mcts = MCTS::<D>::new_with_tasks(
+                    self.state.clone(),
+                    active_agent,
+                    tick,
+                    self.queue.task_queue().clone(),
+                    self.mcts_config.clone(),
+                    ExecutorState::<D>::create_state_value_estimator(&recorder),
+                    None,
+                );
+                mcts.run().unwrap_or_else(|| Box::new(IdleTask))
+            });
+            self.queue
+                .queue_chosen_task(tick, active_agent, new_task, &self.state, &mut recorder);
+        }
+        self.decisions.extend(recorder.decisions);
+        self.tick += 1;
+    }
+
+    /// Returns the name of the scenario of this simulation.
+    pub fn scenario(&self) -> &str {
+        &self.scenario
+    }
+
+    /// Returns the current tick.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Returns the current state.
+    pub fn state(&self) -> &D::State {
+        &self.state
+    }
+
+    /// Returns the MCTS configuration of the agents.
+    pub fn mcts_config(&self) -> &MCTSConfiguration {
+        &self.mcts_config
+    }
+
+    /// Returns all decisions taken so far, in order.
+    pub fn decisions(&self) -> &[Decision] {
+        &self.decisions
+    }
+}
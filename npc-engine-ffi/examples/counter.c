@@ -0,0 +1,64 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+/* A counter domain: the state is an int32_t, task 1 adds one and task 2 adds two. */
+
+#include <stdio.h>
+#include <string.h>
+
+#include "npc_engine.h"
+
+static int32_t counter(const uint8_t *state) {
+    int32_t value;
+    memcpy(&value, state, sizeof(value));
+    return value;
+}
+
+static float value(void *user_data, uint64_t tick, const uint8_t *state, size_t state_len, uint32_t agent) {
+    return (float)counter(state);
+}
+
+static size_t tasks(void *user_data, uint64_t tick, const uint8_t *state, size_t state_len, uint32_t agent, uint64_t *tasks, size_t capacity) {
+    for (size_t i = 0; i < 2 && i < capacity; ++i)
+        tasks[i] = i + 1;
+    return 2;
+}
+
+static bool execute(void *user_data, uint64_t tick, const uint8_t *state, size_t state_len, uint32_t agent, uint64_t task, NpcBuffer *new_state) {
+    int32_t value = counter(state) + (int32_t)task;
+    npc_buffer_set(new_state, (const uint8_t *)&value, sizeof(value));
+    return true;
+}
+
+int main(void) {
+    if (npc_ffi_version() != NPC_FFI_VERSION) {
+        fprintf(stderr, "Header and library versions differ\n");
+        return 1;
+    }
+    NpcDomainCallbacks callbacks = { value, tasks, execute, NULL, NULL, NULL, NULL };
+    NpcDomain *domain = npc_domain_new(&callbacks, NULL);
+    NpcConfig config = npc_config_default();
+    config.visits = 100;
+    config.depth = 5;
+
+    int32_t state = 0;
+    NpcPlanner *planner = npc_planner_new(domain, (const uint8_t *)&state, sizeof(state), 0, &config);
+    uint64_t task;
+    if (npc_planner_run(planner, &task))
+        printf("Best task: %llu\n", (unsigned long long)task);
+    npc_planner_free(planner);
+
+    uint32_t agent = 0;
+    NpcExecutor *executor = npc_executor_new(domain, (const uint8_t *)&state, sizeof(state), &agent, 1, &config);
+    for (int i = 0; i < 10; ++i)
+        npc_executor_step(executor);
+    size_t len;
+    const uint8_t *data = npc_executor_state(executor, &len);
+    printf("Counter after %llu ticks: %d\n", (unsigned long long)npc_executor_tick(executor), counter(data));
+    npc_executor_free(executor);
+
+    npc_domain_free(domain);
+    return 0;
+}
@@ -0,0 +1,87 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+/* The C interface of the NPC engine, see npc-engine-ffi/src/lib.rs for the documentation of each function. */
+
+#ifndef NPC_ENGINE_H
+#define NPC_ENGINE_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* The version of the interface, compare with npc_ffi_version() */
+#define NPC_FFI_VERSION 1
+
+/* Opaque handles */
+typedef struct NpcBuffer NpcBuffer;
+typedef struct NpcDomain NpcDomain;
+typedef struct NpcPlanner NpcPlanner;
+typedef struct NpcExecutor NpcExecutor;
+
+/* The callbacks of a domain, which must be thread-safe; optional ones can be NULL */
+typedef struct NpcDomainCallbacks {
+    /* Returns the current value of agent in state */
+    float (*value)(void *user_data, uint64_t tick, const uint8_t *state, size_t state_len, uint32_t agent);
+    /* Writes up to capacity tasks in tasks, returns the number of available tasks */
+    size_t (*tasks)(void *user_data, uint64_t tick, const uint8_t *state, size_t state_len, uint32_t agent, uint64_t *tasks, size_t capacity);
+    /* Writes the new state with npc_buffer_set, returns whether the state changed */
+    bool (*execute)(void *user_data, uint64_t tick, const uint8_t *state, size_t state_len, uint32_t agent, uint64_t task, NpcBuffer *new_state);
+    /* Optional, the duration of task in ticks, 1 by default */
+    uint64_t (*duration)(void *user_data, uint64_t tick, const uint8_t *state, size_t state_len, uint32_t agent, uint64_t task);
+    /* Optional, whether task can be executed, true by default */
+    bool (*is_valid)(void *user_data, uint64_t tick, const uint8_t *state, size_t state_len, uint32_t agent, uint64_t task);
+    /* Optional, the weight of task in rollouts, 1 by default */
+    float (*weight)(void *user_data, uint64_t tick, const uint8_t *state, size_t state_len, uint32_t agent, uint64_t task);
+    /* Optional, writes up to capacity visible agents in agents, returns their number; only the agent itself by default */
+    size_t (*visible_agents)(void *user_data, uint64_t tick, const uint8_t *state, size_t state_len, uint32_t agent, uint32_t *agents, size_t capacity);
+} NpcDomainCallbacks;
+
+/* The parameters of a search */
+typedef struct NpcConfig {
+    uint32_t visits;
+    uint32_t depth;
+    float exploration;
+    float discount_hl;
+    bool has_seed;
+    uint64_t seed;
+} NpcConfig;
+
+/* The statistics of a root task of a search */
+typedef struct NpcTaskStatistics {
+    uint64_t task;
+    size_t visits;
+    float value;
+} NpcTaskStatistics;
+
+uint32_t npc_ffi_version(void);
+NpcConfig npc_config_default(void);
+void npc_buffer_set(NpcBuffer *buffer, const uint8_t *data, size_t len);
+
+NpcDomain *npc_domain_new(const NpcDomainCallbacks *callbacks, void *user_data);
+void npc_domain_free(NpcDomain *domain);
+
+NpcPlanner *npc_planner_new(const NpcDomain *domain, const uint8_t *state, size_t state_len, uint32_t agent, const NpcConfig *config);
+void npc_planner_free(NpcPlanner *planner);
+bool npc_planner_run(NpcPlanner *planner, uint64_t *task);
+uint32_t npc_planner_visits_done(const NpcPlanner *planner);
+size_t npc_planner_node_count(const NpcPlanner *planner);
+size_t npc_planner_root_tasks(const NpcPlanner *planner, NpcTaskStatistics *tasks, size_t capacity);
+
+NpcExecutor *npc_executor_new(const NpcDomain *domain, const uint8_t *state, size_t state_len, const uint32_t *agents, size_t agent_count, const NpcConfig *config);
+void npc_executor_free(NpcExecutor *executor);
+bool npc_executor_step(NpcExecutor *executor);
+const uint8_t *npc_executor_state(const NpcExecutor *executor, size_t *state_len);
+uint64_t npc_executor_tick(const NpcExecutor *executor);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* NPC_ENGINE_H */
@@ -0,0 +1,392 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{
+    collections::BTreeSet,
+    ffi::c_void,
+    fmt,
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+use npc_engine_core::{
+    impl_task_boxed_methods, AgentId, AgentValue, Behavior, Context, ContextMut, Domain,
+    StateDiffRef, Task, TaskDuration,
+};
+use npc_engine_utils::OptionDiffDomain;
+
+use crate::{ffi_guard, NpcBuffer};
+
+/// The callbacks of a domain implemented in C, see [npc_domain_new].
+///
+/// States are byte buffers serialized by the C side, compared and hashed byte-wise,
+/// and tasks are 64-bit identifiers chosen by the C side.
+/// Every callback receives the `user_data` given to [npc_domain_new] as first parameter.
+/// Optional callbacks can be NULL, in which case their default is used.
+/// As the executor and the planner may call them from several threads, the callbacks must be thread-safe.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct NpcDomainCallbacks {
+    /// Returns the current value of `agent` in `state`.
+    pub value: extern "C" fn(
+        user_data: *mut c_void,
+        tick: u64,
+        state: *const u8,
+        state_len: usize,
+        agent: u32,
+    ) -> f32,
+    /// Writes up to `capacity` tasks available to `agent` in `tasks`, and returns the number of available tasks.
+    ///
+    /// If more than `capacity` tasks are available, it is called again with a large enough buffer.
+    pub tasks: extern "C" fn(
+        user_data: *mut c_void,
+        tick: u64,
+        state: *const u8,
+        state_len: usize,
+        agent: u32,
+        tasks: *mut u64,
+        capacity: usize,
+    ) -> usize,
+    /// Executes `task` for `agent`, writes the new state in `new_state` with [npc_buffer_set](crate::npc_buffer_set),
+    /// and returns whether the state changed.
+    pub execute: extern "C" fn(
+        user_data: *mut c_void,
+        tick: u64,
+        state: *const u8,
+        state_len: usize,
+        agent: u32,
+        task: u64,
+        new_state: *mut NpcBuffer,
+    ) -> bool,
+    /// Optionally, returns the duration of `task` in ticks, 1 by default.
+    pub duration: Option<
+        extern "C" fn(
+            user_data: *mut c_void,
+            tick: u64,
+            state: *const u8,
+            state_len: usize,
+            agent: u32,
+            task: u64,
+        ) -> u64,
+    >,
+    /// Optionally, returns whether `task` can be executed, true by default.
+    pub is_valid: Option<
+        extern "C" fn(
+            user_data: *mut c_void,
+            tick: u64,
+            state: *const u8,
+            state_len: usize,
+            agent: u32,
+            task: u64,
+        ) -> bool,
+    >,
+    /// Optionally, returns the weight of `task` in rollouts, 1 by default.
+    pub weight: Option<
+        extern "C" fn(
+            user_data: *mut c_void,
+            tick: u64,
+            state: *const u8,
+            state_len: usize,
+            agent: u32,
+            task: u64,
+        ) -> f32,
+    >,
+    /// Optionally, writes up to `capacity` agents visible by `agent` in `agents`, and returns the number of visible agents.
+    ///
+    /// By default, only the agent itself is visible.
+    pub visible_agents: Option<
+        extern "C" fn(
+            user_data: *mut c_void,
+            tick: u64,
+            state: *const u8,
+            state_len: usize,
+            agent: u32,
+            agents: *mut u32,
+            capacity: usize,
+        ) -> usize,
+    >,
+}
+
+/// A domain implemented in C, created by [npc_domain_new].
+pub struct NpcDomain {
+    callbacks: NpcDomainCallbacks,
+    user_data: *mut c_void,
+}
+
+// Safety: the callbacks are required to be thread-safe
+unsafe impl Send for NpcDomain {}
+unsafe impl Sync for NpcDomain {}
+
+impl NpcDomain {
+    /// Calls a callback listing values, growing the buffer until they all fit.
+    fn list<T: Copy + Default>(&self, mut list: impl FnMut(*mut T, usize) -> usize) -> Vec<T> {
+        let mut values = vec![T::default(); 16];
+        loop {
+            let count = list(values.as_mut_ptr(), values.len());
+            if count <= values.len() {
+                values.truncate(count);
+                return values;
+            }
+            values.resize(count, T::default());
+        }
+    }
+}
+
+/// Creates a domain from its callbacks, or returns NULL if `callbacks` is NULL.
+///
+/// The domain must be freed with [npc_domain_free], after the planners and executors using it.
+///
+/// # Safety
+///
+/// `callbacks` must be NULL or point to valid callbacks, which are copied.
+#[no_mangle]
+pub unsafe extern "C" fn npc_domain_new(
+    callbacks: *const NpcDomainCallbacks,
+    user_data: *mut c_void,
+) -> *mut NpcDomain {
+    match callbacks.as_ref() {
+        Some(callbacks) => Box::into_raw(Box::new(NpcDomain {
+            callbacks: *callbacks,
+            user_data,
+        })),
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// Frees a domain created by [npc_domain_new], does nothing if `domain` is NULL.
+///
+/// # Safety
+///
+/// `domain` must be NULL or a domain created by [npc_domain_new] and not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn npc_domain_free(domain: *mut NpcDomain) {
+    if !domain.is_null() {
+        ffi_guard((), || drop(Box::from_raw(domain)));
+    }
+}
+
+/// The state of a domain implemented in C: the domain and the serialized state.
+#[derive(Clone)]
+pub struct CState {
+    pub(crate) domain: DomainRef,
+    pub(crate) data: Arc<[u8]>,
+}
+
+/// A reference to a domain owned by the C side.
+#[derive(Clone, Copy)]
+pub(crate) struct DomainRef(pub(crate) *const NpcDomain);
+
+// Safety: the domain is Send and Sync, and outlives the planners and executors using it
+unsafe impl Send for DomainRef {}
+unsafe impl Sync for DomainRef {}
+
+impl CState {
+    /// Creates a state of `domain` from a serialized state.
+    ///
+    /// # Safety
+    ///
+    /// `domain` must be valid as long as the state is used, and `data` must point to `len` readable bytes.
+    pub(crate) unsafe fn new(domain: *const NpcDomain, data: *const u8, len: usize) -> Self {
+        Self {
+            domain: DomainRef(domain),
+            data: bytes(data, len).into(),
+        }
+    }
+
+    /// Returns the domain of this state.
+    fn domain(&self) -> &NpcDomain {
+        // Safety: the domain outlives the states, see [CState::new]
+        unsafe { &*self.domain.0 }
+    }
+}
+
+/// Returns the `len` bytes at `data`, which can be NULL if `len` is 0.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes, unless `len` is 0.
+pub(crate) unsafe fn bytes<'a>(data: *const u8, len: usize) -> &'a [u8] {
+    if len == 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(data, len)
+    }
+}
+
+impl fmt::Debug for CState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CState({} bytes)", self.data.len())
+    }
+}
+
+impl Hash for CState {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.data.hash(state);
+    }
+}
+
+impl PartialEq for CState {
+    fn eq(&self, other: &Self) -> bool {
+        self.data == other.data
+    }
+}
+
+impl Eq for CState {}
+
+/// A domain whose callbacks are implemented in C, see [NpcDomainCallbacks].
+pub struct CDomain;
+
+impl Domain for CDomain {
+    type State = CState;
+    type Diff = Option<CState>;
+    type DisplayAction = u64;
+
+    fn list_behaviors() -> &'static [&'static dyn Behavior<Self>] {
+        &[&CBehavior]
+    }
+
+    fn get_current_value(tick: u64, state_diff: StateDiffRef<Self>, agent: AgentId) -> AgentValue {
+        let state = CDomain::get_cur_state(state_diff);
+        let domain = state.domain();
+        let value = (domain.callbacks.value)(
+            domain.user_data,
+            tick,
+            state.data.as_ptr(),
+            state.data.len(),
+            agent.0,
+        );
+        // A NaN value is treated as no value
+        AgentValue::new(value).unwrap_or_else(|_| AgentValue::new(0.).unwrap())
+    }
+
+    fn update_visible_agents(start_tick: u64, ctx: Context<Self>, agents: &mut BTreeSet<AgentId>) {
+        agents.insert(ctx.agent);
+        let state = CDomain::get_cur_state(ctx.state_diff);
+        let domain = state.domain();
+        if let Some(visible_agents) = domain.callbacks.visible_agents {
+            let visible = domain.list(|agents, capacity| {
+                visible_agents(
+                    domain.user_data,
+                    start_tick,
+                    state.data.as_ptr(),
+                    state.data.len(),
+                    ctx.agent.0,
+                    agents,
+                    capacity,
+                )
+            });
+            agents.extend(visible.into_iter().map(AgentId));
+        }
+    }
+}
+
+/// The only behavior of a C domain, listing the tasks given by its `tasks` callback.
+#[derive(Copy, Clone, Debug)]
+struct CBehavior;
+
+impl Behavior<CDomain> for CBehavior {
+    fn add_own_tasks(&self, ctx: Context<CDomain>, tasks: &mut Vec<Box<dyn Task<CDomain>>>) {
+        let state = CDomain::get_cur_state(ctx.state_diff);
+        let domain = state.domain();
+        let ids = domain.list(|ids, capacity| {
+            (domain.callbacks.tasks)(
+                domain.user_data,
+                ctx.tick,
+                state.data.as_ptr(),
+                state.data.len(),
+                ctx.agent.0,
+                ids,
+                capacity,
+            )
+        });
+        tasks.extend(
+            ids.into_iter()
+                .map(|id| Box::new(CTask(id)) as Box<dyn Task<CDomain>>),
+        );
+    }
+
+    fn is_valid(&self, _ctx: Context<CDomain>) -> bool {
+        true
+    }
+}
+
+/// A task of a C domain, identified by a 64-bit integer.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub struct CTask(pub u64);
+
+impl Task<CDomain> for CTask {
+    fn weight(&self, ctx: Context<CDomain>) -> f32 {
+        let state = CDomain::get_cur_state(ctx.state_diff);
+        let domain = state.domain();
+        domain.callbacks.weight.map_or(1., |weight| {
+            weight(
+                domain.user_data,
+                ctx.tick,
+                state.data.as_ptr(),
+                state.data.len(),
+                ctx.agent.0,
+                self.0,
+            )
+        })
+    }
+
+    fn duration(&self, ctx: Context<CDomain>) -> TaskDuration {
+        let state = CDomain::get_cur_state(ctx.state_diff);
+        let domain = state.domain();
+        domain.callbacks.duration.map_or(1, |duration| {
+            duration(
+                domain.user_data,
+                ctx.tick,
+                state.data.as_ptr(),
+                state.data.len(),
+                ctx.agent.0,
+                self.0,
+            )
+        })
+    }
+
+    fn execute(&self, ctx: ContextMut<CDomain>) -> Option<Box<dyn Task<CDomain>>> {
+        let state = CDomain::get_cur_state(*ctx.state_diff).clone();
+        let domain = state.domain();
+        let mut new_state = NpcBuffer::default();
+        let changed = (domain.callbacks.execute)(
+            domain.user_data,
+            ctx.tick,
+            state.data.as_ptr(),
+            state.data.len(),
+            ctx.agent.0,
+            self.0,
+            &mut new_state,
+        );
+        if changed {
+            *ctx.state_diff.diff = Some(CState {
+                domain: state.domain,
+                data: new_state.0.into(),
+            });
+        }
+        None
+    }
+
+    fn is_valid(&self, ctx: Context<CDomain>) -> bool {
+        let state = CDomain::get_cur_state(ctx.state_diff);
+        let domain = state.domain();
+        domain.callbacks.is_valid.map_or(true, |is_valid| {
+            is_valid(
+                domain.user_data,
+                ctx.tick,
+                state.data.as_ptr(),
+                state.data.len(),
+                ctx.agent.0,
+                self.0,
+            )
+        })
+    }
+
+    fn display_action(&self) -> u64 {
+        self.0
+    }
+
+    impl_task_boxed_methods!(CDomain);
+}
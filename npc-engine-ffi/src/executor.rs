@@ -0,0 +1,163 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use npc_engine_core::{AgentId, IdleTask, MCTSConfiguration, MCTS};
+use npc_engine_utils::{ExecutableDomain, ExecutionQueue, ExecutorState};
+
+use crate::{ffi_guard, CDomain, CState, NpcConfig, NpcDomain};
+
+struct CExecutorState;
+impl ExecutorState<CDomain> for CExecutorState {}
+
+/// An execution loop for a domain implemented in C, created by [npc_executor_new].
+///
+/// It executes tasks and plans for agents as the [SimpleExecutor](npc_engine_utils::SimpleExecutor) does.
+pub struct NpcExecutor {
+    mcts_config: MCTSConfiguration,
+    state: CState,
+    queue: ExecutionQueue<CDomain>,
+    tick: u64,
+}
+
+impl NpcExecutor {
+    fn step(&mut self) {
+        let mut executor_state = CExecutorState;
+        loop {
+            match self.queue.task_queue().iter().next() {
+                Some(active_task) if active_task.end <= self.tick => {}
+                _ => break,
+            }
+            let active_task = self.queue.pop_first_task();
+            let active_agent = active_task.agent;
+            let tick = active_task.end;
+            let (diff, new_task) =
+                self.queue
+                    .execute_task(&active_task, &self.state, &mut executor_state, |_| {});
+            CDomain::apply_diff(diff, &mut self.state);
+            let new_task = new_task.unwrap_or_else(|| {
+                let mut mcts = MCTS::<CDomain>::new_with_tasks(
+                    self.state.clone(),
+                    active_agent,
+                    tick,
+                    self.queue.task_queue().clone(),
+                    self.mcts_config.clone(),
+                    executor_state.create_state_value_estimator(),
+                    None,
+                );
+                mcts.run().unwrap_or_else(|| Box::new(IdleTask))
+            });
+            self.queue.queue_chosen_task(
+                tick,
+                active_agent,
+                new_task,
+                &self.state,
+                &mut executor_state,
+            );
+        }
+        self.tick += 1;
+    }
+}
+
+/// Creates an executor for `agent_count` agents in `state`, or returns NULL if `domain` or `config` is NULL.
+///
+/// The executor must be freed with [npc_executor_free].
+///
+/// # Safety
+///
+/// `domain` must be NULL or a valid domain that outlives the executor, `state` must point to `state_len` readable bytes,
+/// `agents` must point to `agent_count` readable agents, unless `agent_count` is 0, and `config` must be NULL or point to a valid configuration.
+#[no_mangle]
+pub unsafe extern "C" fn npc_executor_new(
+    domain: *const NpcDomain,
+    state: *const u8,
+    state_len: usize,
+    agents: *const u32,
+    agent_count: usize,
+    config: *const NpcConfig,
+) -> *mut NpcExecutor {
+    let config = match (domain.is_null(), config.as_ref()) {
+        (false, Some(config)) => config,
+        _ => return std::ptr::null_mut(),
+    };
+    let agents: &[u32] = if agent_count == 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(agents, agent_count)
+    };
+    ffi_guard(std::ptr::null_mut(), || {
+        let state = CState::new(domain, state, state_len);
+        let mut queue = ExecutionQueue::new(Default::default());
+        for agent in agents {
+            queue.queue_task(0, AgentId(*agent), Box::new(IdleTask), &state);
+        }
+        Box::into_raw(Box::new(NpcExecutor {
+            mcts_config: config.into(),
+            state,
+            queue,
+            tick: 0,
+        }))
+    })
+}
+
+/// Frees an executor created by [npc_executor_new], does nothing if `executor` is NULL.
+///
+/// # Safety
+///
+/// `executor` must be NULL or an executor created by [npc_executor_new] and not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn npc_executor_free(executor: *mut NpcExecutor) {
+    if !executor.is_null() {
+        ffi_guard((), || drop(Box::from_raw(executor)));
+    }
+}
+
+/// Executes the tasks ending at the current tick, plans for the agents without a subsequent task, and advances the tick.
+///
+/// Returns false if `executor` is NULL or if a panic occurred.
+///
+/// # Safety
+///
+/// `executor` must be NULL or a valid executor.
+#[no_mangle]
+pub unsafe extern "C" fn npc_executor_step(executor: *mut NpcExecutor) -> bool {
+    match executor.as_mut() {
+        Some(executor) => ffi_guard(false, || {
+            executor.step();
+            true
+        }),
+        None => false,
+    }
+}
+
+/// Returns the current state and writes its length in `state_len`, NULL if `executor` is NULL.
+///
+/// The state is valid until the next call to [npc_executor_step] or [npc_executor_free].
+///
+/// # Safety
+///
+/// `executor` must be NULL or a valid executor, and `state_len` must point to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn npc_executor_state(
+    executor: *const NpcExecutor,
+    state_len: *mut usize,
+) -> *const u8 {
+    match executor.as_ref() {
+        Some(executor) => {
+            *state_len = executor.state.data.len();
+            executor.state.data.as_ptr()
+        }
+        None => std::ptr::null(),
+    }
+}
+
+/// Returns the current tick, 0 if `executor` is NULL.
+///
+/// # Safety
+///
+/// `executor` must be NULL or a valid executor.
+#[no_mangle]
+pub unsafe extern "C" fn npc_executor_tick(executor: *const NpcExecutor) -> u64 {
+    executor.as_ref().map_or(0, |executor| executor.tick)
+}
@@ -0,0 +1,242 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+//! This is the C interface of the [NPC engine](https://crates.io/crates/npc-engine-core/), to embed the planner in engines written in C or C++.
+//!
+//! The interface is declared in `include/npc_engine.h`, and uses opaque handles:
+//! - A domain ([NpcDomain]) is a set of callbacks ([NpcDomainCallbacks]) operating on states serialized as bytes, with tasks identified by 64-bit integers.
+//! - A planner ([NpcPlanner]) runs a single search for an agent, and exposes the statistics of the root tasks.
+//! - An executor ([NpcExecutor]) executes tasks and plans for agents in a loop.
+//!
+//! All functions catch Rust panics, returning NULL or zero values instead, so that they never unwind into C.
+//! The interface is stable within a major [version](npc_ffi_version).
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+mod domain;
+mod executor;
+mod planner;
+
+pub use domain::*;
+pub use executor::*;
+pub use planner::*;
+
+/// The version of the C interface, incremented on incompatible changes.
+pub const NPC_FFI_VERSION: u32 = 1;
+
+/// Returns the version of the C interface, to check that the header matches the library.
+#[no_mangle]
+pub extern "C" fn npc_ffi_version() -> u32 {
+    NPC_FFI_VERSION
+}
+
+/// Runs `f`, returning `default` if it panics, as panics must not unwind into C.
+pub(crate) fn ffi_guard<T>(default: T, f: impl FnOnce() -> T) -> T {
+    catch_unwind(AssertUnwindSafe(f)).unwrap_or(default)
+}
+
+/// A byte buffer owned by the engine, into which callbacks write states with [npc_buffer_set].
+#[derive(Default)]
+pub struct NpcBuffer(pub(crate) Vec<u8>);
+
+/// Copies `len` bytes from `data` into `buffer`, replacing its content.
+///
+/// # Safety
+///
+/// `buffer` must be a buffer given to a callback, and `data` must point to `len` readable bytes, unless `len` is 0.
+#[no_mangle]
+pub unsafe extern "C" fn npc_buffer_set(buffer: *mut NpcBuffer, data: *const u8, len: usize) {
+    if let Some(buffer) = buffer.as_mut() {
+        buffer.0.clear();
+        buffer.0.extend_from_slice(bytes(data, len));
+    }
+}
+
+/// The parameters of a search, see [MCTSConfiguration](npc_engine_core::MCTSConfiguration).
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct NpcConfig {
+    /// The number of visits of a search
+    pub visits: u32,
+    /// The maximum depth of a search, in ticks
+    pub depth: u32,
+    /// The exploration factor of UCT
+    pub exploration: f32,
+    /// The discount of later rewards, as a half life in ticks
+    pub discount_hl: f32,
+    /// Whether `seed` is used, otherwise the seed is random
+    pub has_seed: bool,
+    /// The seed of the search
+    pub seed: u64,
+}
+
+impl From<&NpcConfig> for npc_engine_core::MCTSConfiguration {
+    fn from(config: &NpcConfig) -> Self {
+        npc_engine_core::MCTSConfiguration {
+            allow_invalid_tasks: false,
+            visits: config.visits,
+            depth: config.depth,
+            exploration: config.exploration,
+            discount_hl: config.discount_hl,
+            seed: config.has_seed.then_some(config.seed),
+            planning_task_duration: None,
+        }
+    }
+}
+
+/// Returns the default parameters of a search: 1000 visits, a depth of 10, an exploration of 1.414, a discount half life of 15 and no seed.
+#[no_mangle]
+pub extern "C" fn npc_config_default() -> NpcConfig {
+    NpcConfig {
+        visits: 1000,
+        depth: 10,
+        exploration: 1.414,
+        discount_hl: 15.,
+        has_seed: false,
+        seed: 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ffi::c_void, ptr};
+
+    use super::*;
+
+    // A counter per agent, stored as a little-endian i32 at the agent's index;
+    // task 1 adds one, task 2 adds two.
+
+    fn counter(state: *const u8, state_len: usize, agent: u32) -> i32 {
+        let state = unsafe { bytes(state, state_len) };
+        let index = agent as usize * 4;
+        i32::from_le_bytes(state[index..index + 4].try_into().unwrap())
+    }
+
+    extern "C" fn value(
+        _user_data: *mut c_void,
+        _tick: u64,
+        state: *const u8,
+        state_len: usize,
+        agent: u32,
+    ) -> f32 {
+        counter(state, state_len, agent) as f32
+    }
+
+    extern "C" fn tasks(
+        _user_data: *mut c_void,
+        _tick: u64,
+        _state: *const u8,
+        _state_len: usize,
+        _agent: u32,
+        tasks: *mut u64,
+        capacity: usize,
+    ) -> usize {
+        for (index, task) in [1, 2].into_iter().take(capacity).enumerate() {
+            unsafe { *tasks.add(index) = task };
+        }
+        2
+    }
+
+    extern "C" fn execute(
+        _user_data: *mut c_void,
+        _tick: u64,
+        state: *const u8,
+        state_len: usize,
+        agent: u32,
+        task: u64,
+        new_state: *mut NpcBuffer,
+    ) -> bool {
+        let mut data = unsafe { bytes(state, state_len) }.to_vec();
+        let index = agent as usize * 4;
+        let value = counter(state, state_len, agent) + task as i32;
+        data[index..index + 4].copy_from_slice(&value.to_le_bytes());
+        unsafe { npc_buffer_set(new_state, data.as_ptr(), data.len()) };
+        true
+    }
+
+    fn domain() -> *mut NpcDomain {
+        let callbacks = NpcDomainCallbacks {
+            value,
+            tasks,
+            execute,
+            duration: None,
+            is_valid: None,
+            weight: None,
+            visible_agents: None,
+        };
+        unsafe { npc_domain_new(&callbacks, ptr::null_mut()) }
+    }
+
+    fn config() -> NpcConfig {
+        NpcConfig {
+            visits: 100,
+            depth: 5,
+            has_seed: true,
+            ..npc_config_default()
+        }
+    }
+
+    #[test]
+    fn planner_chooses_larger_addition() {
+        let domain = domain();
+        let state = 0i32.to_le_bytes();
+        unsafe {
+            let planner = npc_planner_new(domain, state.as_ptr(), state.len(), 0, &config());
+            assert!(!planner.is_null());
+            let mut task = 0;
+            assert!(npc_planner_run(planner, &mut task));
+            assert_eq!(task, 2);
+            assert_eq!(npc_planner_visits_done(planner), 100);
+            assert!(npc_planner_node_count(planner) > 1);
+            let mut statistics = [NpcTaskStatistics::default(); 1];
+            let count = npc_planner_root_tasks(planner, statistics.as_mut_ptr(), 1);
+            assert_eq!(count, 2);
+            assert_eq!(statistics[0].task, 2);
+            npc_planner_free(planner);
+            npc_domain_free(domain);
+        }
+    }
+
+    #[test]
+    fn executor_steps_agents() {
+        let domain = domain();
+        let state: Vec<u8> = [0i32, 10].iter().flat_map(|v| v.to_le_bytes()).collect();
+        let agents = [0, 1];
+        unsafe {
+            let executor = npc_executor_new(
+                domain,
+                state.as_ptr(),
+                state.len(),
+                agents.as_ptr(),
+                agents.len(),
+                &config(),
+            );
+            assert!(!executor.is_null());
+            for _ in 0..5 {
+                assert!(npc_executor_step(executor));
+            }
+            assert_eq!(npc_executor_tick(executor), 5);
+            let mut len = 0;
+            let data = npc_executor_state(executor, &mut len);
+            assert_eq!(len, 8);
+            assert!(counter(data, len, 0) > 0);
+            assert!(counter(data, len, 1) > 10);
+            npc_executor_free(executor);
+            npc_domain_free(domain);
+        }
+    }
+
+    #[test]
+    fn null_handles_are_rejected() {
+        unsafe {
+            assert!(npc_domain_new(ptr::null(), ptr::null_mut()).is_null());
+            let planner = npc_planner_new(ptr::null(), ptr::null(), 0, 0, &config());
+            assert!(planner.is_null());
+            assert!(!npc_executor_step(ptr::null_mut()));
+            assert_eq!(npc_planner_visits_done(ptr::null()), 0);
+        }
+        assert_eq!(npc_ffi_version(), NPC_FFI_VERSION);
+    }
+}
@@ -0,0 +1,151 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use npc_engine_core::{AgentId, MCTS};
+
+use crate::{ffi_guard, CDomain, CState, CTask, NpcConfig, NpcDomain};
+
+/// A search for the best task of an agent, created by [npc_planner_new].
+pub struct NpcPlanner(MCTS<CDomain>);
+
+/// The statistics of a task at the root of a search, see [npc_planner_root_tasks].
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NpcTaskStatistics {
+    /// The task
+    pub task: u64,
+    /// The number of visits of the task
+    pub visits: usize,
+    /// The q-value of the task for the agent of the search
+    pub value: f32,
+}
+
+/// Creates a search for `agent` in `state`, or returns NULL if `domain` or `config` is NULL.
+///
+/// The planner must be freed with [npc_planner_free].
+///
+/// # Safety
+///
+/// `domain` must be NULL or a valid domain that outlives the planner,
+/// `state` must point to `state_len` readable bytes, and `config` must be NULL or point to a valid configuration.
+#[no_mangle]
+pub unsafe extern "C" fn npc_planner_new(
+    domain: *const NpcDomain,
+    state: *const u8,
+    state_len: usize,
+    agent: u32,
+    config: *const NpcConfig,
+) -> *mut NpcPlanner {
+    let config = match (domain.is_null(), config.as_ref()) {
+        (false, Some(config)) => config,
+        _ => return std::ptr::null_mut(),
+    };
+    ffi_guard(std::ptr::null_mut(), || {
+        let state = CState::new(domain, state, state_len);
+        let mcts = MCTS::new(state, AgentId(agent), config.into());
+        Box::into_raw(Box::new(NpcPlanner(mcts)))
+    })
+}
+
+/// Frees a planner created by [npc_planner_new], does nothing if `planner` is NULL.
+///
+/// # Safety
+///
+/// `planner` must be NULL or a planner created by [npc_planner_new] and not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn npc_planner_free(planner: *mut NpcPlanner) {
+    if !planner.is_null() {
+        ffi_guard((), || drop(Box::from_raw(planner)));
+    }
+}
+
+/// Runs the search, writes the best task in `task`, and returns whether there is a best task.
+///
+/// # Safety
+///
+/// `planner` must be a valid planner and `task` must point to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn npc_planner_run(planner: *mut NpcPlanner, task: *mut u64) -> bool {
+    let planner = match planner.as_mut() {
+        Some(planner) => planner,
+        None => return false,
+    };
+    ffi_guard(false, || {
+        match planner
+            .0
+            .run()
+            .and_then(|best| best.downcast_ref::<CTask>().copied())
+        {
+            Some(best) => {
+                *task = best.0;
+                true
+            }
+            // The best task can be the idle task, which is not a task of the domain
+            None => false,
+        }
+    })
+}
+
+/// Returns the number of visits done by the search, 0 if `planner` is NULL.
+///
+/// # Safety
+///
+/// `planner` must be NULL or a valid planner.
+#[no_mangle]
+pub unsafe extern "C" fn npc_planner_visits_done(planner: *const NpcPlanner) -> u32 {
+    planner
+        .as_ref()
+        .map_or(0, |planner| planner.0.visits_done())
+}
+
+/// Returns the number of nodes in the search tree, 0 if `planner` is NULL.
+///
+/// # Safety
+///
+/// `planner` must be NULL or a valid planner.
+#[no_mangle]
+pub unsafe extern "C" fn npc_planner_node_count(planner: *const NpcPlanner) -> usize {
+    planner.as_ref().map_or(0, |planner| planner.0.node_count())
+}
+
+/// Writes up to `capacity` statistics of the expanded root tasks in `tasks`, the most visited first,
+/// and returns the number of expanded root tasks.
+///
+/// # Safety
+///
+/// `planner` must be NULL or a valid planner, and `tasks` must point to `capacity` writable statistics, unless `capacity` is 0.
+#[no_mangle]
+pub unsafe extern "C" fn npc_planner_root_tasks(
+    planner: *const NpcPlanner,
+    tasks: *mut NpcTaskStatistics,
+    capacity: usize,
+) -> usize {
+    let mcts = match planner.as_ref() {
+        Some(planner) => &planner.0,
+        None => return 0,
+    };
+    ffi_guard(0, || {
+        let agent = mcts.agent();
+        let mut statistics: Vec<_> = match mcts.get_edges(&mcts.root_node()) {
+            Some(edges) => edges
+                .expanded_tasks()
+                .filter_map(|(task, edge)| {
+                    let edge = edge.lock().unwrap();
+                    task.downcast_ref::<CTask>().map(|task| NpcTaskStatistics {
+                        task: task.0,
+                        visits: edge.visits(),
+                        value: edge.q_value(agent),
+                    })
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+        statistics.sort_by(|a, b| b.visits.cmp(&a.visits).then(a.task.cmp(&b.task)));
+        for (index, task) in statistics.iter().take(capacity).enumerate() {
+            *tasks.add(index) = *task;
+        }
+        statistics.len()
+    })
+}
@@ -0,0 +1,65 @@
+# SPDX-License-Identifier: Apache-2.0 OR MIT
+# © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+
+# Two agents increment their own counter, by one or two, without letting the total exceed a limit.
+extends Node
+
+const LIMIT := 10
+
+var executor: NpcExecutor
+
+
+func total(state: Dictionary) -> int:
+	var sum := 0
+	for counter in state["counters"].values():
+		sum += counter
+	return sum
+
+
+func value(_tick: int, state: Dictionary, agent: int) -> float:
+	return float(state["counters"][agent])
+
+
+func amounts(_tick: int, state: Dictionary, _agent: int) -> Array:
+	return [1, 2].filter(func(amount): return total(state) + amount <= LIMIT)
+
+
+func add(_tick: int, state: Dictionary, agent: int, amount: int) -> Dictionary:
+	var new_state := state.duplicate(true)
+	new_state["counters"][agent] += amount
+	return new_state
+
+
+func visible_agents(_tick: int, state: Dictionary, _agent: int) -> Array:
+	return state["counters"].keys()
+
+
+func _ready() -> void:
+	var add_task := NpcTask.new()
+	add_task.name = "add"
+	add_task.execute = add
+	add_task.arguments = amounts
+	var domain := NpcDomain.new()
+	domain.tasks = [add_task]
+	domain.value = value
+	domain.visible_agents = visible_agents
+
+	var config := NpcConfig.new()
+	config.visits = 500
+	config.seed = 0
+	var state := {"counters": {0: 0, 1: 0}}
+
+	# Analyze a single search
+	var planner := NpcPlanner.create(domain, state, 0, config)
+	print("best task: ", planner.run())
+	for task in planner.root_tasks():
+		print("  %s(%s): %d visits, value %.2f" % [task.name, task.argument, task.visits, task.value])
+
+	executor = NpcExecutor.create(domain, state, PackedInt64Array([0, 1]), config)
+
+
+func _physics_process(_delta: float) -> void:
+	if total(executor.state()) >= LIMIT or executor.tick() >= 50:
+		return
+	for task in executor.step():
+		print("tick %d: agent %d starts %s(%s)" % [executor.tick(), task.agent, task.name, task.argument])
@@ -0,0 +1,55 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use godot::prelude::*;
+use npc_engine_core::MCTSConfiguration;
+
+/// The parameters of a search, see [MCTSConfiguration].
+#[derive(GodotClass)]
+#[class(init, base=Resource)]
+pub struct NpcConfig {
+    /// The number of visits of a search
+    #[export]
+    #[init(val = 1000)]
+    visits: i64,
+    /// The maximum depth of a search, in ticks
+    #[export]
+    #[init(val = 10)]
+    depth: i64,
+    /// The exploration factor of UCT
+    #[export]
+    #[init(val = 1.414)]
+    exploration: f64,
+    /// The discount of later rewards, as a half life in ticks
+    #[export]
+    #[init(val = 15.)]
+    discount_hl: f64,
+    /// The seed of the search, random if negative
+    #[export]
+    #[init(val = -1)]
+    seed: i64,
+}
+
+impl From<&NpcConfig> for MCTSConfiguration {
+    fn from(config: &NpcConfig) -> Self {
+        MCTSConfiguration {
+            allow_invalid_tasks: false,
+            visits: config.visits.max(1) as u32,
+            depth: config.depth.max(1) as u32,
+            exploration: config.exploration as f32,
+            discount_hl: config.discount_hl as f32,
+            seed: (config.seed >= 0).then_some(config.seed as u64),
+            planning_task_duration: None,
+        }
+    }
+}
+
+/// Returns the search parameters of `config`, the defaults of [NpcConfig] if None.
+pub(crate) fn mcts_config(config: Option<Gd<NpcConfig>>) -> MCTSConfiguration {
+    match config {
+        Some(config) => MCTSConfiguration::from(&*config.bind()),
+        None => MCTSConfiguration::from(&*NpcConfig::new_gd().bind()),
+    }
+}
@@ -0,0 +1,284 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{
+    collections::BTreeSet,
+    fmt,
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+use godot::{
+    global::{bytes_to_var, var_to_bytes},
+    prelude::*,
+};
+use npc_engine_core::{
+    impl_task_boxed_methods, AgentId, AgentValue, Behavior, Context, ContextMut, Domain,
+    StateDiffRef, Task, TaskDuration,
+};
+use npc_engine_utils::OptionDiffDomain;
+
+/// A domain, as a resource holding its tasks and its callables.
+#[derive(GodotClass)]
+#[class(init, base=Resource)]
+pub struct NpcDomain {
+    /// The tasks of the domain
+    #[export]
+    tasks: Array<Gd<NpcTask>>,
+    /// Returns the current value of an agent: `(tick, state, agent) -> float`
+    #[var]
+    #[init(val = Callable::invalid())]
+    value: Callable,
+    /// Optionally, returns the agents visible by an agent: `(tick, state, agent) -> Array[int]`
+    #[var]
+    #[init(val = Callable::invalid())]
+    visible_agents: Callable,
+}
+
+/// A task of a domain, as a resource holding its callables.
+#[derive(GodotClass)]
+#[class(init, base=Resource)]
+pub struct NpcTask {
+    /// The name of the task, as seen in planning results
+    #[export]
+    name: GString,
+    /// The duration of the task, in ticks
+    #[export]
+    #[init(val = 1)]
+    duration: i64,
+    /// The weight of the task in rollouts
+    #[export]
+    #[init(val = 1.)]
+    weight: f64,
+    /// Executes the task and returns the new state, null if unchanged: `(tick, state, agent, argument) -> Dictionary`
+    #[var]
+    #[init(val = Callable::invalid())]
+    execute: Callable,
+    /// Optionally, returns whether the task can be executed: `(tick, state, agent, argument) -> bool`
+    #[var]
+    #[init(val = Callable::invalid())]
+    is_valid: Callable,
+    /// Optionally, returns the arguments for which the task is available: `(tick, state, agent) -> Array`,
+    /// by default the task is available once with a null argument
+    #[var]
+    #[init(val = Callable::invalid())]
+    arguments: Callable,
+}
+
+/// Calls `callable` with the tick, the state and the agent followed by `extra`, None if the callable is not set.
+fn call(
+    callable: &Callable,
+    tick: u64,
+    state: &GdState,
+    agent: AgentId,
+    extra: Option<Variant>,
+) -> Option<Variant> {
+    if !callable.is_valid() {
+        return None;
+    }
+    let mut args = varray![tick as i64, state.to_dictionary(), agent.0 as i64];
+    if let Some(extra) = extra {
+        args.push(&extra);
+    }
+    Some(callable.callv(&args))
+}
+
+/// Converts a variant to a float, accepting integers as well.
+fn to_f64(value: &Variant) -> Option<f64> {
+    value
+        .try_to::<f64>()
+        .ok()
+        .or_else(|| value.try_to::<i64>().ok().map(|value| value as f64))
+}
+
+/// The state of a domain described in Godot: the domain resource and the state dictionary, serialized.
+///
+/// States are compared and hashed through their serialization with `var_to_bytes`, so the order of keys matters.
+#[derive(Clone)]
+pub struct GdState {
+    pub(crate) domain: Gd<NpcDomain>,
+    pub(crate) data: Arc<[u8]>,
+}
+
+impl GdState {
+    /// Creates a state of `domain` from a dictionary.
+    pub(crate) fn new(domain: Gd<NpcDomain>, state: &Dictionary) -> Self {
+        Self {
+            domain,
+            data: var_to_bytes(&state.to_variant()).to_vec().into(),
+        }
+    }
+
+    /// Returns the state as a dictionary.
+    pub(crate) fn to_dictionary(&self) -> Dictionary {
+        bytes_to_var(&PackedByteArray::from(&self.data[..]))
+            .try_to()
+            .unwrap_or_default()
+    }
+}
+
+impl fmt::Debug for GdState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_dictionary())
+    }
+}
+
+impl Hash for GdState {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.data.hash(state);
+    }
+}
+
+impl PartialEq for GdState {
+    fn eq(&self, other: &Self) -> bool {
+        self.data == other.data
+    }
+}
+
+impl Eq for GdState {}
+
+/// A domain described by an [NpcDomain] resource, see the crate documentation.
+pub struct GodotDomain;
+
+impl Domain for GodotDomain {
+    type State = GdState;
+    type Diff = Option<GdState>;
+    type DisplayAction = String;
+
+    fn list_behaviors() -> &'static [&'static dyn Behavior<Self>] {
+        &[&GodotBehavior]
+    }
+
+    fn get_current_value(tick: u64, state_diff: StateDiffRef<Self>, agent: AgentId) -> AgentValue {
+        let state = GodotDomain::get_cur_state(state_diff);
+        let value = state.domain.bind().value.clone();
+        let value =
+            call(&value, tick, state, agent, None).expect("the domain must have a value callable");
+        let value = to_f64(&value).expect("the value must be a number");
+        AgentValue::new(value as f32).expect("the value must not be NaN")
+    }
+
+    fn update_visible_agents(start_tick: u64, ctx: Context<Self>, agents: &mut BTreeSet<AgentId>) {
+        let state = GodotDomain::get_cur_state(ctx.state_diff);
+        agents.insert(ctx.agent);
+        let visible_agents = state.domain.bind().visible_agents.clone();
+        if let Some(visible) = call(&visible_agents, start_tick, state, ctx.agent, None) {
+            let visible: VariantArray = visible
+                .try_to()
+                .expect("visible_agents must return an array");
+            agents.extend(visible.iter_shared().filter_map(|agent| {
+                agent
+                    .try_to::<i64>()
+                    .ok()
+                    .map(|agent| AgentId(agent as u32))
+            }));
+        }
+    }
+}
+
+/// The only behavior of a Godot domain, listing the tasks of its resource with their arguments.
+#[derive(Copy, Clone, Debug)]
+struct GodotBehavior;
+
+impl Behavior<GodotDomain> for GodotBehavior {
+    fn add_own_tasks(
+        &self,
+        ctx: Context<GodotDomain>,
+        tasks: &mut Vec<Box<dyn Task<GodotDomain>>>,
+    ) {
+        let state = GodotDomain::get_cur_state(ctx.state_diff);
+        let resources = state.domain.bind().tasks.clone();
+        for (index, resource) in resources.iter_shared().enumerate() {
+            let (name, arguments) = {
+                let resource = resource.bind();
+                (resource.name.to_string(), resource.arguments.clone())
+            };
+            let arguments: VariantArray = match call(&arguments, ctx.tick, state, ctx.agent, None) {
+                Some(arguments) => arguments
+                    .try_to()
+                    .unwrap_or_else(|_| panic!("arguments of task {name} must return an array")),
+                None => varray![Variant::nil()],
+            };
+            let name: Arc<str> = name.into();
+            tasks.extend(arguments.iter_shared().map(|argument| {
+                Box::new(GdTask {
+                    index,
+                    name: name.clone(),
+                    argument: var_to_bytes(&argument).to_vec().into(),
+                }) as Box<dyn Task<GodotDomain>>
+            }));
+        }
+    }
+
+    fn is_valid(&self, _ctx: Context<GodotDomain>) -> bool {
+        true
+    }
+}
+
+/// A task of a Godot domain: the index of its resource in the domain, its name and its argument, serialized.
+#[derive(Clone, Hash, PartialEq, Eq)]
+pub struct GdTask {
+    pub(crate) index: usize,
+    pub(crate) name: Arc<str>,
+    pub(crate) argument: Arc<[u8]>,
+}
+
+impl GdTask {
+    /// Returns the argument of the task.
+    pub(crate) fn argument(&self) -> Variant {
+        bytes_to_var(&PackedByteArray::from(&self.argument[..]))
+    }
+
+    /// Returns the resource of the task in the domain of `state`.
+    fn resource(&self, state: &GdState) -> Gd<NpcTask> {
+        state.domain.bind().tasks.at(self.index)
+    }
+}
+
+impl fmt::Debug for GdTask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}({})", self.name, self.argument())
+    }
+}
+
+impl Task<GodotDomain> for GdTask {
+    fn weight(&self, ctx: Context<GodotDomain>) -> f32 {
+        let state = GodotDomain::get_cur_state(ctx.state_diff);
+        self.resource(state).bind().weight as f32
+    }
+
+    fn duration(&self, ctx: Context<GodotDomain>) -> TaskDuration {
+        let state = GodotDomain::get_cur_state(ctx.state_diff);
+        self.resource(state).bind().duration.max(0) as TaskDuration
+    }
+
+    fn execute(&self, ctx: ContextMut<GodotDomain>) -> Option<Box<dyn Task<GodotDomain>>> {
+        let state = GodotDomain::get_cur_state(*ctx.state_diff).clone();
+        let execute = self.resource(&state).bind().execute.clone();
+        let new_state = call(&execute, ctx.tick, &state, ctx.agent, Some(self.argument()))
+            .unwrap_or_else(|| panic!("task {} must have an execute callable", self.name));
+        // A null new state means that the task did not change the state
+        if !new_state.is_nil() {
+            let new_state: Dictionary = new_state.try_to().unwrap_or_else(|_| {
+                panic!("execute of task {} must return a dictionary", self.name)
+            });
+            *ctx.state_diff.diff = Some(GdState::new(state.domain.clone(), &new_state));
+        }
+        None
+    }
+
+    fn is_valid(&self, ctx: Context<GodotDomain>) -> bool {
+        let state = GodotDomain::get_cur_state(ctx.state_diff);
+        let is_valid = self.resource(state).bind().is_valid.clone();
+        call(&is_valid, ctx.tick, state, ctx.agent, Some(self.argument()))
+            .map_or(true, |valid| valid.try_to::<bool>().unwrap_or(false))
+    }
+
+    fn display_action(&self) -> String {
+        format!("{self:?}")
+    }
+
+    impl_task_boxed_methods!(GodotDomain);
+}
@@ -0,0 +1,114 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use godot::prelude::*;
+use npc_engine_core::{ActiveTask, AgentId, IdleTask, MCTSConfiguration, MCTS};
+use npc_engine_utils::{ExecutableDomain, ExecutionQueue, ExecutorState};
+
+use crate::{mcts_config, task_to_dictionary, GdState, GodotDomain, NpcConfig, NpcDomain};
+
+/// Collects the tasks chosen during a step.
+#[derive(Default)]
+struct ChosenTasks(Vec<ActiveTask<GodotDomain>>);
+
+impl ExecutorState<GodotDomain> for ChosenTasks {
+    fn on_task_chosen(&mut self, active_task: &ActiveTask<GodotDomain>) {
+        self.0.push(active_task.clone());
+    }
+}
+
+/// An execution loop for a domain, executing tasks and planning for agents as the
+/// [SimpleExecutor](npc_engine_utils::SimpleExecutor) does.
+///
+/// It is typically stepped from `_physics_process`.
+#[derive(GodotClass)]
+#[class(no_init, base=RefCounted)]
+pub struct NpcExecutor {
+    mcts_config: MCTSConfiguration,
+    state: GdState,
+    queue: ExecutionQueue<GodotDomain>,
+    tick: u64,
+}
+
+#[godot_api]
+impl NpcExecutor {
+    /// Creates an execution loop for `agents` in `state`, with the default parameters if `config` is null.
+    #[func]
+    fn create(
+        domain: Gd<NpcDomain>,
+        state: Dictionary,
+        agents: PackedInt64Array,
+        config: Option<Gd<NpcConfig>>,
+    ) -> Gd<Self> {
+        let state = GdState::new(domain, &state);
+        let mut queue = ExecutionQueue::new(Default::default());
+        for agent in agents.as_slice() {
+            queue.queue_task(0, AgentId(*agent as u32), Box::new(IdleTask), &state);
+        }
+        Gd::from_object(Self {
+            mcts_config: mcts_config(config),
+            state,
+            queue,
+            tick: 0,
+        })
+    }
+
+    /// Executes the tasks ending at the current tick, plans for the agents without a subsequent task, and advances the tick.
+    ///
+    /// Returns the tasks chosen during this step, as `{agent, name, argument}` dictionaries.
+    #[func]
+    fn step(&mut self) -> Array<Dictionary> {
+        let mut chosen = ChosenTasks::default();
+        loop {
+            match self.queue.task_queue().iter().next() {
+                Some(active_task) if active_task.end <= self.tick => {}
+                _ => break,
+            }
+            let active_task = self.queue.pop_first_task();
+            let active_agent = active_task.agent;
+            let tick = active_task.end;
+            let (diff, new_task) =
+                self.queue
+                    .execute_task(&active_task, &self.state, &mut chosen, |_| {});
+            GodotDomain::apply_diff(diff, &mut self.state);
+            let new_task = new_task.unwrap_or_else(|| {
+                let mut mcts = MCTS::<GodotDomain>::new_with_tasks(
+                    self.state.clone(),
+                    active_agent,
+                    tick,
+                    self.queue.task_queue().clone(),
+                    self.mcts_config.clone(),
+                    chosen.create_state_value_estimator(),
+                    None,
+                );
+                mcts.run().unwrap_or_else(|| Box::new(IdleTask))
+            });
+            self.queue
+                .queue_chosen_task(tick, active_agent, new_task, &self.state, &mut chosen);
+        }
+        self.tick += 1;
+        chosen
+            .0
+            .iter()
+            .map(|active_task| {
+                let mut task = task_to_dictionary(&*active_task.task);
+                task.set("agent", active_task.agent.0 as i64);
+                task
+            })
+            .collect()
+    }
+
+    /// Returns the current state.
+    #[func]
+    fn state(&self) -> Dictionary {
+        self.state.to_dictionary()
+    }
+
+    /// Returns the current tick.
+    #[func]
+    fn tick(&self) -> i64 {
+        self.tick as i64
+    }
+}
@@ -0,0 +1,38 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+//! This is the [Godot](https://godotengine.org) GDExtension of the [NPC engine](https://crates.io/crates/npc-engine-core/), to use the planner from GDScript.
+//!
+//! A domain is described by Godot resources, with states being dictionaries and tasks calling GDScript callables:
+//! - [NpcDomain] holds the tasks of the domain, a `value` callable returning the current value of an agent,
+//!   and optionally a `visible_agents` callable returning the agents visible by an agent (by default, only itself).
+//! - [NpcTask] holds the `execute` callable of a task, returning the new state or null if it is unchanged,
+//!   and optionally an `arguments` callable listing its available arguments, an `is_valid` callable, its duration and its weight.
+//!
+//! Callables receive the tick, the state and the agent, followed by the argument for task callables.
+//! It also contains the following classes:
+//! - [NpcConfig] is a resource holding the parameters of a search.
+//! - [NpcPlanner] runs a search and exposes the statistics of the root tasks.
+//! - [NpcExecutor] executes tasks and plans for agents in a loop.
+
+// The functions generated by godot-rust 0.2 return its large CallError
+#![allow(clippy::result_large_err)]
+
+use godot::prelude::*;
+
+mod config;
+mod domain;
+mod executor;
+mod planner;
+
+pub use config::*;
+pub use domain::*;
+pub use executor::*;
+pub use planner::*;
+
+struct NpcEngineExtension;
+
+#[gdextension]
+unsafe impl ExtensionLibrary for NpcEngineExtension {}
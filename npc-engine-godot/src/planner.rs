@@ -0,0 +1,95 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::cmp::Reverse;
+
+use godot::prelude::*;
+use npc_engine_core::{AgentId, Task, MCTS};
+
+use crate::{mcts_config, GdState, GdTask, GodotDomain, NpcConfig, NpcDomain};
+
+/// A search for the best task of an agent in a state of a domain, whose root tasks can be inspected after running.
+#[derive(GodotClass)]
+#[class(no_init, base=RefCounted)]
+pub struct NpcPlanner {
+    mcts: MCTS<GodotDomain>,
+}
+
+#[godot_api]
+impl NpcPlanner {
+    /// Creates a search for `agent` in `state`, with the default parameters if `config` is null.
+    #[func]
+    fn create(
+        domain: Gd<NpcDomain>,
+        state: Dictionary,
+        agent: i64,
+        config: Option<Gd<NpcConfig>>,
+    ) -> Gd<Self> {
+        let state = GdState::new(domain, &state);
+        Gd::from_object(Self {
+            mcts: MCTS::new(state, AgentId(agent as u32), mcts_config(config)),
+        })
+    }
+
+    /// Runs the search and returns the best task as a `{name, argument}` dictionary, empty if the agent has no task.
+    #[func]
+    fn run(&mut self) -> Dictionary {
+        match self.mcts.run() {
+            Some(task) => task_to_dictionary(&*task),
+            None => Dictionary::new(),
+        }
+    }
+
+    /// Returns the expanded tasks at the root as `{name, argument, visits, value}` dictionaries, the most visited first.
+    #[func]
+    fn root_tasks(&self) -> Array<Dictionary> {
+        let agent = self.mcts.agent();
+        let edges = match self.mcts.get_edges(&self.mcts.root_node()) {
+            Some(edges) => edges,
+            None => return Array::new(),
+        };
+        let mut tasks: Vec<_> = edges
+            .expanded_tasks()
+            .map(|(task, edge)| {
+                let edge = edge.lock().unwrap();
+                (
+                    task_to_dictionary(&**task),
+                    edge.visits(),
+                    edge.q_value(agent),
+                )
+            })
+            .collect();
+        tasks.sort_by_key(|task| Reverse(task.1));
+        tasks
+            .into_iter()
+            .map(|(mut task, visits, value)| {
+                task.set("visits", visits as i64);
+                task.set("value", value);
+                task
+            })
+            .collect()
+    }
+
+    /// Returns the number of visits done by the search.
+    #[func]
+    fn visits_done(&self) -> i64 {
+        self.mcts.visits_done() as i64
+    }
+
+    /// Returns the number of nodes of the search tree.
+    #[func]
+    fn node_count(&self) -> i64 {
+        self.mcts.node_count() as i64
+    }
+}
+
+/// Returns a task as a `{name, argument}` dictionary.
+pub(crate) fn task_to_dictionary(task: &dyn Task<GodotDomain>) -> Dictionary {
+    match task.downcast_ref::<GdTask>() {
+        Some(task) => dict! { "name": task.name.as_ref(), "argument": task.argument() },
+        // Idle and planning tasks are not tasks of the domain
+        None => dict! { "name": "idle", "argument": Variant::nil() },
+    }
+}
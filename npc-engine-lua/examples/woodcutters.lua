@@ -0,0 +1,50 @@
+-- SPDX-License-Identifier: Apache-2.0 OR MIT
+-- © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+
+-- Woodcutters chop the trees of a shared forest, chopping taking longer as the forest thins out.
+-- Edit this file while the example runs to see the woodcutters change their minds.
+
+local domain = {}
+
+function domain.value(tick, state, agent)
+  return state.wood[tostring(agent)]
+end
+
+function domain.visible_agents(tick, state, agent)
+  local agents = {}
+  for id, _ in pairs(state.wood) do
+    table.insert(agents, tonumber(id))
+  end
+  return agents
+end
+
+domain.behaviors = {
+  {
+    name = "woodcutter",
+    add_own_tasks = function(tick, state, agent)
+      return { { "chop", { amount = 1 } }, { "chop", { amount = 3 } }, { "rest" } }
+    end,
+  },
+}
+
+domain.tasks = {
+  chop = {
+    is_valid = function(tick, state, agent, args)
+      return state.trees >= args.amount
+    end,
+    execute = function(tick, state, agent, args)
+      state.trees = state.trees - args.amount
+      local id = tostring(agent)
+      state.wood[id] = state.wood[id] + args.amount
+    end,
+    duration = function(tick, state, agent, args)
+      return args.amount * 2
+    end,
+  },
+  rest = {
+    weight = 0.1,
+    execute = function(tick, state, agent, args) end,
+  },
+}
+
+return domain
@@ -0,0 +1,45 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+//! Plans for two woodcutters scripted in `examples/woodcutters.lua`, reloading the script before each planning.
+
+use std::{thread, time::Duration};
+
+use npc_engine_core::{AgentId, MCTSConfiguration, MCTS};
+use npc_engine_lua::{LuaDomain, LuaScript, LuaState, LuaTask};
+
+fn main() -> mlua::Result<()> {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/woodcutters.lua");
+    let script = LuaScript::load(path)?;
+    let state = LuaState::new(
+        script.clone(),
+        &serde_json::json!({ "trees": 10, "wood": { "0": 0, "1": 0 } }),
+    );
+    let config = MCTSConfiguration {
+        allow_invalid_tasks: false,
+        visits: 1000,
+        depth: 20,
+        exploration: 1.414,
+        discount_hl: 15.,
+        seed: Some(0),
+        planning_task_duration: None,
+    };
+    loop {
+        // Keep the previous script if the file has errors
+        if let Err(err) = script.reload() {
+            println!("Cannot reload the script: {err}");
+        }
+        let mut mcts = MCTS::<LuaDomain>::new(state.clone(), AgentId(0), config.clone());
+        let best = mcts.run();
+        match best
+            .as_ref()
+            .and_then(|task| task.downcast_ref::<LuaTask>())
+        {
+            Some(task) => println!("Woodcutter 0 chooses {} {}", task.name(), task.arguments()),
+            None => println!("Woodcutter 0 has nothing to do"),
+        }
+        thread::sleep(Duration::from_secs(2));
+    }
+}
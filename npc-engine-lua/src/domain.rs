@@ -0,0 +1,299 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{
+    collections::BTreeSet,
+    fmt,
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+use mlua::{FromLua, Function, IntoLuaMulti, Lua, LuaSerdeExt, Table, Value};
+use npc_engine_core::{
+    impl_task_boxed_methods, AgentId, AgentValue, Behavior, Context, ContextMut, Domain,
+    StateDiffRef, Task, TaskDuration,
+};
+use npc_engine_utils::OptionDiffDomain;
+
+use crate::LuaScript;
+
+/// Converts a JSON string to a Lua value.
+fn json_to_lua(lua: &Lua, json: &str) -> mlua::Result<Value> {
+    let value: serde_json::Value = serde_json::from_str(json).map_err(mlua::Error::external)?;
+    lua.to_value(&value)
+}
+
+/// Converts a Lua value to canonical JSON, so that equal values have equal strings.
+fn lua_to_json(lua: &Lua, value: Value) -> mlua::Result<Arc<str>> {
+    // JSON objects are ordered by key, making the JSON canonical
+    let value: serde_json::Value = lua.from_value(value)?;
+    Ok(value.to_string().into())
+}
+
+/// Returns `value` as a number, calling it with `args` if it is a function, None if it is nil.
+fn number_or_call(lua: &Lua, value: Value, args: impl IntoLuaMulti) -> mlua::Result<Option<f64>> {
+    match value {
+        Value::Nil => Ok(None),
+        Value::Function(function) => function.call(args).map(Some),
+        value => f64::from_lua(value, lua).map(Some),
+    }
+}
+
+/// The state of a domain scripted in Lua: its script and the state itself, as JSON.
+///
+/// States are compared and hashed through their canonical JSON.
+#[derive(Clone)]
+pub struct LuaState {
+    pub(crate) script: Arc<LuaScript>,
+    pub(crate) data: Arc<str>,
+}
+
+impl LuaState {
+    /// Creates a state of `script` from a JSON value.
+    pub fn new(script: Arc<LuaScript>, state: &serde_json::Value) -> Self {
+        Self {
+            script,
+            data: state.to_string().into(),
+        }
+    }
+
+    /// Returns the state as a JSON value.
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::from_str(&self.data).expect("States are always valid JSON")
+    }
+
+    /// Returns the script of this state.
+    pub fn script(&self) -> &Arc<LuaScript> {
+        &self.script
+    }
+
+    /// Returns the state as a Lua table.
+    fn to_lua(&self, lua: &Lua) -> mlua::Result<Value> {
+        json_to_lua(lua, &self.data)
+    }
+}
+
+impl fmt::Debug for LuaState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.data)
+    }
+}
+
+impl Hash for LuaState {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.data.hash(state);
+    }
+}
+
+impl PartialEq for LuaState {
+    fn eq(&self, other: &Self) -> bool {
+        self.data == other.data
+    }
+}
+
+impl Eq for LuaState {}
+
+/// A domain whose value, behaviors and tasks are scripted in Lua, see the crate documentation.
+pub struct LuaDomain;
+
+impl Domain for LuaDomain {
+    type State = LuaState;
+    type Diff = Option<LuaState>;
+    type DisplayAction = String;
+
+    fn list_behaviors() -> &'static [&'static dyn Behavior<Self>] {
+        &[&LuaBehaviors]
+    }
+
+    fn get_current_value(tick: u64, state_diff: StateDiffRef<Self>, agent: AgentId) -> AgentValue {
+        let state = LuaDomain::get_cur_state(state_diff);
+        let value = state.script.with_domain("value", |lua, domain| {
+            let value: Function = domain.get("value")?;
+            value.call::<f32>((tick, state.to_lua(lua)?, agent.0))
+        });
+        AgentValue::new(value).expect("the value must not be NaN")
+    }
+
+    fn update_visible_agents(start_tick: u64, ctx: Context<Self>, agents: &mut BTreeSet<AgentId>) {
+        let state = LuaDomain::get_cur_state(ctx.state_diff);
+        agents.insert(ctx.agent);
+        let visible = state.script.with_domain("visible_agents", |lua, domain| {
+            match domain.get::<Option<Function>>("visible_agents")? {
+                Some(visible_agents) => {
+                    visible_agents.call::<Vec<u32>>((start_tick, state.to_lua(lua)?, ctx.agent.0))
+                }
+                None => Ok(Vec::new()),
+            }
+        });
+        agents.extend(visible.into_iter().map(AgentId));
+    }
+}
+
+/// The behaviors of a Lua domain, listing the tasks of each valid behavior of its `behaviors` list.
+#[derive(Copy, Clone, Debug)]
+struct LuaBehaviors;
+
+impl Behavior<LuaDomain> for LuaBehaviors {
+    fn add_own_tasks(&self, ctx: Context<LuaDomain>, tasks: &mut Vec<Box<dyn Task<LuaDomain>>>) {
+        let state = LuaDomain::get_cur_state(ctx.state_diff);
+        let new_tasks = state.script.with_domain("add_own_tasks", |lua, domain| {
+            let state_table = state.to_lua(lua)?;
+            let mut new_tasks = Vec::new();
+            let behaviors: Table = domain.get("behaviors")?;
+            for behavior in behaviors.sequence_values::<Table>() {
+                let behavior = behavior?;
+                let args = (ctx.tick, state_table.clone(), ctx.agent.0);
+                if let Some(is_valid) = behavior.get::<Option<Function>>("is_valid")? {
+                    if !is_valid.call::<bool>(args.clone())? {
+                        continue;
+                    }
+                }
+                let add_own_tasks: Function = behavior.get("add_own_tasks")?;
+                let behavior_tasks: Table = add_own_tasks.call(args)?;
+                for task in behavior_tasks.sequence_values::<Table>() {
+                    let task = task?;
+                    let name: String = task.get(1)?;
+                    let arguments: Value = task.get(2)?;
+                    new_tasks.push(LuaTask {
+                        name: name.into(),
+                        args: lua_to_json(lua, arguments)?,
+                    });
+                }
+            }
+            Ok(new_tasks)
+        });
+        tasks.extend(
+            new_tasks
+                .into_iter()
+                .map(|task| Box::new(task) as Box<dyn Task<LuaDomain>>),
+        );
+    }
+
+    fn is_valid(&self, _ctx: Context<LuaDomain>) -> bool {
+        // The validity of each Lua behavior is checked when listing tasks
+        true
+    }
+}
+
+/// A task of a Lua domain: its name in the `tasks` table and its arguments, as JSON.
+#[derive(Clone, Hash, PartialEq, Eq)]
+pub struct LuaTask {
+    pub(crate) name: Arc<str>,
+    pub(crate) args: Arc<str>,
+}
+
+impl LuaTask {
+    /// Returns the name of the task.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the arguments of the task, as JSON.
+    pub fn arguments(&self) -> serde_json::Value {
+        serde_json::from_str(&self.args).expect("Task arguments are always valid JSON")
+    }
+
+    /// Runs `f` with the table of this task in the `tasks` table of the domain.
+    fn with_task<R>(
+        &self,
+        state: &LuaState,
+        what: &str,
+        f: impl FnOnce(&Lua, Table) -> mlua::Result<R>,
+    ) -> R {
+        state.script.with_domain(what, |lua, domain| {
+            let tasks: Table = domain.get("tasks")?;
+            let task: Table = tasks.get(&*self.name).map_err(|err| {
+                mlua::Error::external(format!("unknown task {}: {err}", self.name))
+            })?;
+            f(lua, task)
+        })
+    }
+
+    /// Returns the tick, the state, the agent and the arguments, to pass them to a function of the task.
+    fn callback_args(
+        &self,
+        lua: &Lua,
+        tick: u64,
+        state: &LuaState,
+        agent: AgentId,
+    ) -> mlua::Result<(u64, Value, u32, Value)> {
+        Ok((
+            tick,
+            state.to_lua(lua)?,
+            agent.0,
+            json_to_lua(lua, &self.args)?,
+        ))
+    }
+}
+
+impl fmt::Debug for LuaTask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}({})", self.name, self.args)
+    }
+}
+
+impl Task<LuaDomain> for LuaTask {
+    fn weight(&self, ctx: Context<LuaDomain>) -> f32 {
+        let state = LuaDomain::get_cur_state(ctx.state_diff);
+        self.with_task(state, "weight", |lua, task| {
+            let args = self.callback_args(lua, ctx.tick, state, ctx.agent)?;
+            number_or_call(lua, task.get("weight")?, args)
+        })
+        .map_or(1., |weight| weight as f32)
+    }
+
+    fn duration(&self, ctx: Context<LuaDomain>) -> TaskDuration {
+        let state = LuaDomain::get_cur_state(ctx.state_diff);
+        self.with_task(state, "duration", |lua, task| {
+            let args = self.callback_args(lua, ctx.tick, state, ctx.agent)?;
+            number_or_call(lua, task.get("duration")?, args)
+        })
+        .map_or(1, |duration| duration.max(0.) as TaskDuration)
+    }
+
+    fn execute(&self, ctx: ContextMut<LuaDomain>) -> Option<Box<dyn Task<LuaDomain>>> {
+        let state = LuaDomain::get_cur_state(*ctx.state_diff).clone();
+        let new_state = self.with_task(&state, "execute", |lua, task| {
+            let execute: Function = task.get("execute")?;
+            let (tick, table, agent, args) =
+                self.callback_args(lua, ctx.tick, &state, ctx.agent)?;
+            // The function either changes the state in place or returns a new one
+            let new_table: Value = execute.call((tick, table.clone(), agent, args))?;
+            let new_table = if new_table.is_nil() { table } else { new_table };
+            lua_to_json(lua, new_table)
+        });
+        if new_state != state.data {
+            *ctx.state_diff.diff = Some(LuaState {
+                script: state.script.clone(),
+                data: new_state,
+            });
+        }
+        None
+    }
+
+    fn is_valid(&self, ctx: Context<LuaDomain>) -> bool {
+        let state = LuaDomain::get_cur_state(ctx.state_diff);
+        state.script.with_domain("is_valid", |lua, domain| {
+            // Tasks removed by reloading the script are no longer valid
+            let tasks: Table = domain.get("tasks")?;
+            let task = match tasks.get::<Option<Table>>(&*self.name)? {
+                Some(task) => task,
+                None => return Ok(false),
+            };
+            match task.get::<Option<Function>>("is_valid")? {
+                Some(is_valid) => {
+                    is_valid.call(self.callback_args(lua, ctx.tick, state, ctx.agent)?)
+                }
+                None => Ok(true),
+            }
+        })
+    }
+
+    fn display_action(&self) -> String {
+        format!("{self:?}")
+    }
+
+    impl_task_boxed_methods!(LuaDomain);
+}
@@ -0,0 +1,25 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+//! This is a [Lua](https://www.lua.org) bridge for the [NPC engine](https://crates.io/crates/npc-engine-core/), to write tasks and behaviors in Lua,
+//! so that designers can iterate on the abilities of NPCs without recompiling.
+//!
+//! A [LuaScript] returns a table describing the domain, whose functions receive the tick, the state as a table and the agent:
+//! - `value(tick, state, agent)` returns the current value of an agent.
+//! - `behaviors` is a list of behaviors, each with an `add_own_tasks(tick, state, agent)` function returning the tasks of the behavior
+//!   as `{name, arguments}` pairs, and optionally an `is_valid(tick, state, agent)` function (true by default).
+//! - `tasks` maps task names to tables, each with an `execute(tick, state, agent, arguments)` function changing the state in place
+//!   or returning a new one, and optionally `is_valid` (true by default), `weight` (1 by default) and `duration` (1 by default),
+//!   the last two being either numbers or functions with the same parameters as `execute`.
+//! - Optionally, `visible_agents(tick, state, agent)` returns the agents visible by an agent (by default, only itself).
+//!
+//! States are JSON values, seen from Lua as tables, and are compared and hashed through their canonical JSON, see [LuaState].
+//! Scripts can be reloaded while the game runs with [LuaScript::reload], plannings started afterwards using the new script.
+
+mod domain;
+mod script;
+
+pub use domain::*;
+pub use script::*;
@@ -0,0 +1,85 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+use mlua::{Lua, Table};
+
+/// The key of the domain table in the Lua registry.
+const DOMAIN_KEY: &str = "npc_engine_domain";
+
+/// A Lua script describing a domain, see the crate documentation.
+///
+/// The script is shared by all states of a [LuaDomain](crate::LuaDomain), and its functions are called one at a time.
+pub struct LuaScript {
+    lua: RwLock<Lua>,
+    path: Option<PathBuf>,
+}
+
+impl LuaScript {
+    /// Creates a script from its source, which must return the domain table.
+    pub fn new(source: &str) -> mlua::Result<Arc<Self>> {
+        Ok(Arc::new(Self {
+            lua: RwLock::new(Self::create_lua(source, "domain")?),
+            path: None,
+        }))
+    }
+
+    /// Creates a script from a file, which can later be reloaded with [reload](Self::reload).
+    pub fn load(path: impl AsRef<Path>) -> mlua::Result<Arc<Self>> {
+        let path = path.as_ref().to_path_buf();
+        let lua = Self::create_lua(
+            &fs::read_to_string(&path).map_err(mlua::Error::external)?,
+            &path.to_string_lossy(),
+        )?;
+        Ok(Arc::new(Self {
+            lua: RwLock::new(lua),
+            path: Some(path),
+        }))
+    }
+
+    /// Reloads the script from its file, does nothing for scripts created from source.
+    ///
+    /// If the new script fails to load, the error is returned and the previous script is kept.
+    pub fn reload(&self) -> mlua::Result<()> {
+        if let Some(path) = &self.path {
+            let lua = Self::create_lua(
+                &fs::read_to_string(path).map_err(mlua::Error::external)?,
+                &path.to_string_lossy(),
+            )?;
+            *self.lua.write().unwrap() = lua;
+        }
+        Ok(())
+    }
+
+    /// Returns the file of the script, if loaded from one.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Creates a Lua state running `source`, and stores the domain table it returns.
+    fn create_lua(source: &str, name: &str) -> mlua::Result<Lua> {
+        let lua = Lua::new();
+        let domain: Table = lua.load(source).set_name(name).eval()?;
+        lua.set_named_registry_value(DOMAIN_KEY, domain)?;
+        Ok(lua)
+    }
+
+    /// Runs `f` with the domain table, panicking if it fails, as domain callbacks cannot fail.
+    pub(crate) fn with_domain<R>(
+        &self,
+        what: &str,
+        f: impl FnOnce(&Lua, Table) -> mlua::Result<R>,
+    ) -> R {
+        let lua = self.lua.read().unwrap();
+        lua.named_registry_value(DOMAIN_KEY)
+            .and_then(|domain| f(&lua, domain))
+            .unwrap_or_else(|err| panic!("Lua {what} failed: {err}"))
+    }
+}
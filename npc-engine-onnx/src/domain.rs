@@ -0,0 +1,20 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use npc_engine_core::{AgentId, Domain, StateDiffRef};
+
+/// A domain whose states can be encoded as features for an [OnnxValueModel](crate::OnnxValueModel).
+pub trait OnnxDomain: Domain {
+    /// Returns the number of features of a state, which must match the input of the model.
+    fn feature_count() -> usize;
+
+    /// Writes the features of the state seen by `agent` in `features`, of [feature_count](Self::feature_count) elements.
+    fn encode_features(
+        tick: u64,
+        state_diff: StateDiffRef<Self>,
+        agent: AgentId,
+        features: &mut [f32],
+    );
+}
@@ -0,0 +1,65 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::collections::BTreeMap;
+
+use npc_engine_core::{AgentId, Edges, MCTSConfiguration, Node, StateDiffRef, StateValueEstimator};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{OnnxDomain, OnnxValueModel};
+
+/// A state value estimator using an [OnnxValueModel] instead of rollouts.
+///
+/// The features of all agents of a leaf are encoded by [OnnxDomain::encode_features] and evaluated in a single batch.
+pub struct OnnxEstimator {
+    model: OnnxValueModel,
+    features: Vec<f32>,
+}
+
+impl OnnxEstimator {
+    /// Creates an estimator using `model`, which must take [OnnxDomain::feature_count] features.
+    pub fn new(model: OnnxValueModel) -> Self {
+        Self {
+            model,
+            features: Vec::new(),
+        }
+    }
+}
+
+impl<D: OnnxDomain> StateValueEstimator<D> for OnnxEstimator {
+    fn estimate(
+        &mut self,
+        _rnd: &mut ChaCha8Rng,
+        _config: &MCTSConfiguration,
+        initial_state: &D::State,
+        _start_tick: u64,
+        node: &Node<D>,
+        edges: &Edges<D>,
+        _depth: u32,
+    ) -> Option<BTreeMap<AgentId, f32>> {
+        if edges.unexpanded_count() == 0 {
+            return None;
+        }
+        let feature_count = D::feature_count();
+        assert_eq!(
+            feature_count,
+            self.model.feature_count(),
+            "the model must take the features of the domain"
+        );
+        let agents = node.agents();
+        // Re-use the buffer across leaves, as estimation runs for every expansion
+        self.features.clear();
+        self.features.resize(agents.len() * feature_count, 0.);
+        let state_diff = StateDiffRef::new(initial_state, node.diff());
+        for (agent, features) in agents.iter().zip(self.features.chunks_mut(feature_count)) {
+            D::encode_features(node.tick(), state_diff, *agent, features);
+        }
+        let values = self
+            .model
+            .evaluate_batch(&self.features)
+            .unwrap_or_else(|err| panic!("the value model failed: {err}"));
+        Some(agents.into_iter().zip(values).collect())
+    }
+}
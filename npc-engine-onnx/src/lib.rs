@@ -0,0 +1,19 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+//! This is a learned leaf evaluation backend for the [NPC engine](https://crates.io/crates/npc-engine-core/), running [ONNX](https://onnx.ai) value models with [tract](https://github.com/sonos/tract).
+//!
+//! It contains the following features:
+//! - A hook for domains to encode the state seen by an agent as features ([OnnxDomain]).
+//! - A value model ([OnnxValueModel]) loaded from an ONNX file, evaluating batches of feature rows.
+//! - A state value estimator ([OnnxEstimator]) using the model instead of rollouts, evaluating all agents of a leaf in a single batch.
+
+mod domain;
+mod estimator;
+mod model;
+
+pub use domain::*;
+pub use estimator::*;
+pub use model::*;
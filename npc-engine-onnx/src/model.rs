@@ -0,0 +1,71 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{path::Path, sync::Arc};
+
+use tract_onnx::prelude::*;
+use tract_onnx::tract_core::internal::{bail, dims};
+
+/// A value model loaded from an ONNX file, mapping rows of features to values.
+///
+/// The model must take a single `[batch, features]` input of 32-bit floats, with a dynamic batch dimension,
+/// and return a `[batch]` or `[batch, 1]` output.
+/// Cloning the model shares it.
+#[derive(Clone)]
+pub struct OnnxValueModel {
+    plan: Arc<TypedRunnableModel<TypedModel>>,
+    feature_count: usize,
+}
+
+impl OnnxValueModel {
+    /// Loads and optimizes the model in the ONNX file at `path`, taking `feature_count` features per row.
+    pub fn load(path: impl AsRef<Path>, feature_count: usize) -> TractResult<Self> {
+        let model = tract_onnx::onnx().model_for_path(path)?;
+        Self::new(model, feature_count)
+    }
+
+    /// Loads and optimizes a model from the bytes of an ONNX file, taking `feature_count` features per row.
+    pub fn from_bytes(mut bytes: &[u8], feature_count: usize) -> TractResult<Self> {
+        let model = tract_onnx::onnx().model_for_read(&mut bytes)?;
+        Self::new(model, feature_count)
+    }
+
+    fn new(model: InferenceModel, feature_count: usize) -> TractResult<Self> {
+        let batch = model.symbols.sym("batch");
+        let plan = model
+            .with_input_fact(0, f32::fact(dims!(batch, feature_count)).into())?
+            .into_optimized()?
+            .into_runnable()?;
+        Ok(Self {
+            plan: Arc::new(plan),
+            feature_count,
+        })
+    }
+
+    /// Returns the number of features per row.
+    pub fn feature_count(&self) -> usize {
+        self.feature_count
+    }
+
+    /// Evaluates rows of features, concatenated in `features`, and returns one value per row.
+    pub fn evaluate_batch(&self, features: &[f32]) -> TractResult<Vec<f32>> {
+        if self.feature_count == 0 || features.len() % self.feature_count != 0 {
+            bail!(
+                "{} features is not a multiple of {} features per row",
+                features.len(),
+                self.feature_count
+            );
+        }
+        let rows = features.len() / self.feature_count;
+        let input =
+            tract_ndarray::Array2::from_shape_vec((rows, self.feature_count), features.to_vec())?;
+        let outputs = self.plan.run(tvec!(input.into_tensor().into()))?;
+        let values: Vec<f32> = outputs[0].to_array_view::<f32>()?.iter().copied().collect();
+        if values.len() != rows {
+            bail!("the model returned {} values for {rows} rows", values.len());
+        }
+        Ok(values)
+    }
+}
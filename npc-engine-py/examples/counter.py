@@ -0,0 +1,47 @@
+# SPDX-License-Identifier: Apache-2.0 OR MIT
+# © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+
+"""Two agents increment their own counter, by one or two, without letting the total exceed a limit."""
+
+import npc_engine
+
+LIMIT = 10
+
+
+class CounterDomain:
+    def value(self, tick, state, agent):
+        return float(state["counters"][str(agent)])
+
+    def tasks(self, tick, state, agent):
+        total = sum(state["counters"].values())
+        return [("add", amount) for amount in (1, 2) if total + amount <= LIMIT]
+
+    def execute(self, tick, state, agent, name, amount):
+        state["counters"][str(agent)] += amount
+        return state
+
+    def duration(self, tick, state, agent, name, amount):
+        return amount
+
+    def visible_agents(self, tick, state, agent):
+        return [int(other) for other in state["counters"]]
+
+
+if __name__ == "__main__":
+    domain = CounterDomain()
+    state = {"counters": {"0": 0, "1": 0}}
+    config = npc_engine.MCTSConfiguration(visits=500, depth=10, seed=0)
+
+    # Analyze a single search
+    planner = npc_engine.Planner(domain, state, 0, config)
+    print("best task:", planner.run())
+    for name, amount, visits, value in planner.root_tasks():
+        print(f"  {name}({amount}): {visits} visits, value {value:.2f}")
+    print(planner.explain())
+
+    # Run both agents until no task is left
+    executor = npc_engine.Executor(domain, state, [0, 1], config)
+    while sum(executor.state["counters"].values()) < LIMIT and executor.tick < 50:
+        for agent, name, amount in executor.step():
+            print(f"tick {executor.tick}: agent {agent} starts {name}({amount})")
+    print("final state:", executor.state)
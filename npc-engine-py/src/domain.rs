@@ -0,0 +1,255 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{
+    collections::BTreeSet,
+    fmt,
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+use npc_engine_core::{
+    impl_task_boxed_methods, AgentId, AgentValue, Behavior, Context, ContextMut, Domain,
+    StateDiffRef, Task, TaskDuration,
+};
+use npc_engine_utils::OptionDiffDomain;
+use pyo3::{
+    prelude::*,
+    types::{IntoPyDict, PyTuple},
+};
+
+/// Converts a Python value to canonical JSON, so that equal values have equal strings.
+pub(crate) fn to_json(py: Python<'_>, value: &Bound<'_, PyAny>) -> PyResult<Arc<str>> {
+    let kwargs = [("sort_keys", true)].into_py_dict_bound(py);
+    let json: String = py
+        .import_bound("json")?
+        .call_method("dumps", (value,), Some(&kwargs))?
+        .extract()?;
+    Ok(json.into())
+}
+
+/// Converts JSON back to a Python value.
+pub(crate) fn from_json<'py>(py: Python<'py>, json: &str) -> PyResult<Bound<'py, PyAny>> {
+    py.import_bound("json")?.call_method1("loads", (json,))
+}
+
+/// The state of a domain defined in Python: the Python object implementing the domain callbacks and the state itself, as JSON.
+///
+/// States are compared and hashed through their canonical JSON, so they must be JSON-serializable.
+#[derive(Clone)]
+pub struct PyState {
+    pub(crate) domain: Arc<PyObject>,
+    pub(crate) data: Arc<str>,
+}
+
+impl PyState {
+    /// Creates a state of `domain` from a Python value.
+    pub(crate) fn new(domain: Arc<PyObject>, value: &Bound<'_, PyAny>) -> PyResult<Self> {
+        Ok(Self {
+            domain,
+            data: to_json(value.py(), value)?,
+        })
+    }
+
+    /// Returns the state as a Python value.
+    pub(crate) fn to_python<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        from_json(py, &self.data)
+    }
+
+    /// Calls the method `name` of the domain with the tick, the state and the agent followed by `args`, if the domain has it.
+    fn call<T, A, F>(&self, name: &str, tick: u64, agent: AgentId, args: A, extract: F) -> Option<T>
+    where
+        A: IntoPy<Py<PyTuple>>,
+        F: FnOnce(&Bound<'_, PyAny>) -> PyResult<T>,
+    {
+        Python::with_gil(|py| {
+            let domain = self.domain.bind(py);
+            let result = || -> PyResult<Option<T>> {
+                if !domain.hasattr(name)? {
+                    return Ok(None);
+                }
+                let state = self.to_python(py)?;
+                let args = args.into_py(py);
+                let args: Vec<PyObject> = [tick.into_py(py), state.unbind(), agent.0.into_py(py)]
+                    .into_iter()
+                    .chain(args.bind(py).iter().map(Bound::unbind))
+                    .collect();
+                let args = PyTuple::new_bound(py, args);
+                extract(&domain.call_method1(name, args)?).map(Some)
+            };
+            // Domain callbacks cannot fail, so a Python exception aborts the search
+            result().unwrap_or_else(|err| panic!("domain callback {name} raised {err}"))
+        })
+    }
+}
+
+impl fmt::Debug for PyState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.data)
+    }
+}
+
+impl Hash for PyState {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.data.hash(state);
+    }
+}
+
+impl PartialEq for PyState {
+    fn eq(&self, other: &Self) -> bool {
+        self.data == other.data
+    }
+}
+
+impl Eq for PyState {}
+
+/// A domain whose callbacks are the methods of a Python object, see the crate documentation.
+pub struct PythonDomain;
+
+impl Domain for PythonDomain {
+    type State = PyState;
+    type Diff = Option<PyState>;
+    type DisplayAction = String;
+
+    fn list_behaviors() -> &'static [&'static dyn Behavior<Self>] {
+        &[&PythonBehavior]
+    }
+
+    fn get_current_value(tick: u64, state_diff: StateDiffRef<Self>, agent: AgentId) -> AgentValue {
+        let state = PythonDomain::get_cur_state(state_diff);
+        let value = state
+            .call("value", tick, agent, (), |value| value.extract::<f32>())
+            .expect("the domain must have a value method");
+        AgentValue::new(value).expect("the value must not be NaN")
+    }
+
+    fn update_visible_agents(start_tick: u64, ctx: Context<Self>, agents: &mut BTreeSet<AgentId>) {
+        let state = PythonDomain::get_cur_state(ctx.state_diff);
+        agents.insert(ctx.agent);
+        if let Some(visible) = state.call("visible_agents", start_tick, ctx.agent, (), |agents| {
+            agents.extract::<Vec<u32>>()
+        }) {
+            agents.extend(visible.into_iter().map(AgentId));
+        }
+    }
+}
+
+/// The only behavior of a Python domain, listing the tasks given by its `tasks` method.
+#[derive(Copy, Clone, Debug)]
+struct PythonBehavior;
+
+impl Behavior<PythonDomain> for PythonBehavior {
+    fn add_own_tasks(
+        &self,
+        ctx: Context<PythonDomain>,
+        tasks: &mut Vec<Box<dyn Task<PythonDomain>>>,
+    ) {
+        let state = PythonDomain::get_cur_state(ctx.state_diff);
+        let new_tasks = state
+            .call("tasks", ctx.tick, ctx.agent, (), |tasks| {
+                let py = tasks.py();
+                tasks
+                    .iter()?
+                    .map(|task| {
+                        let (name, args): (String, Bound<'_, PyAny>) = task?.extract()?;
+                        Ok(PyTask {
+                            name: name.into(),
+                            args: to_json(py, &args)?,
+                        })
+                    })
+                    .collect::<PyResult<Vec<_>>>()
+            })
+            .expect("the domain must have a tasks method");
+        tasks.extend(
+            new_tasks
+                .into_iter()
+                .map(|task| Box::new(task) as Box<dyn Task<PythonDomain>>),
+        );
+    }
+
+    fn is_valid(&self, _ctx: Context<PythonDomain>) -> bool {
+        true
+    }
+}
+
+/// A task of a Python domain: its name and its arguments, as JSON.
+#[derive(Clone, Hash, PartialEq, Eq)]
+pub struct PyTask {
+    pub(crate) name: Arc<str>,
+    pub(crate) args: Arc<str>,
+}
+
+impl PyTask {
+    /// Returns the name and the arguments of the task as Python values.
+    pub(crate) fn to_python(&self, py: Python<'_>) -> PyResult<(String, PyObject)> {
+        Ok((self.name.to_string(), from_json(py, &self.args)?.unbind()))
+    }
+
+    /// Returns the name and the arguments of the task, to pass them to the domain.
+    fn callback_args(&self) -> (String, PyObject) {
+        Python::with_gil(|py| self.to_python(py))
+            .unwrap_or_else(|err| panic!("invalid task arguments: {err}"))
+    }
+
+    /// Calls the method `name` of the domain for this task, passing its name and its arguments.
+    fn call<T, F>(&self, name: &str, ctx: Context<PythonDomain>, extract: F) -> Option<T>
+    where
+        F: FnOnce(&Bound<'_, PyAny>) -> PyResult<T>,
+    {
+        let state = PythonDomain::get_cur_state(ctx.state_diff);
+        state.call(name, ctx.tick, ctx.agent, self.callback_args(), extract)
+    }
+}
+
+impl fmt::Debug for PyTask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}({})", self.name, self.args)
+    }
+}
+
+impl Task<PythonDomain> for PyTask {
+    fn weight(&self, ctx: Context<PythonDomain>) -> f32 {
+        self.call("weight", ctx, |weight| weight.extract())
+            .unwrap_or(1.0)
+    }
+
+    fn duration(&self, ctx: Context<PythonDomain>) -> TaskDuration {
+        self.call("duration", ctx, |duration| duration.extract())
+            .unwrap_or(1)
+    }
+
+    fn execute(&self, ctx: ContextMut<PythonDomain>) -> Option<Box<dyn Task<PythonDomain>>> {
+        let state = PythonDomain::get_cur_state(*ctx.state_diff).clone();
+        let new_state = state.call(
+            "execute",
+            ctx.tick,
+            ctx.agent,
+            self.callback_args(),
+            |new_state| {
+                if new_state.is_none() {
+                    Ok(None)
+                } else {
+                    PyState::new(state.domain.clone(), new_state).map(Some)
+                }
+            },
+        );
+        // No new state means that the task did not change the state
+        if let Some(Some(new_state)) = new_state {
+            *ctx.state_diff.diff = Some(new_state);
+        }
+        None
+    }
+
+    fn is_valid(&self, ctx: Context<PythonDomain>) -> bool {
+        self.call("is_valid", ctx, |valid| valid.extract())
+            .unwrap_or(true)
+    }
+
+    fn display_action(&self) -> String {
+        format!("{self:?}")
+    }
+
+    impl_task_boxed_methods!(PythonDomain);
+}
@@ -0,0 +1,110 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::sync::Arc;
+
+use npc_engine_core::{ActiveTask, AgentId, IdleTask, MCTSConfiguration, MCTS};
+use npc_engine_utils::{ExecutableDomain, ExecutionQueue, ExecutorState};
+use pyo3::prelude::*;
+
+use crate::{task_to_python, PyMCTSConfiguration, PyState, PyTask, PythonDomain};
+
+/// Collects the tasks chosen during a step.
+#[derive(Default)]
+struct ChosenTasks(Vec<ActiveTask<PythonDomain>>);
+
+impl ExecutorState<PythonDomain> for ChosenTasks {
+    fn on_task_chosen(&mut self, active_task: &ActiveTask<PythonDomain>) {
+        self.0.push(active_task.clone());
+    }
+}
+
+/// An execution loop for a Python domain, executing tasks and planning for agents as the
+/// [SimpleExecutor](npc_engine_utils::SimpleExecutor) does.
+#[pyclass(unsendable)]
+pub struct Executor {
+    mcts_config: MCTSConfiguration,
+    state: PyState,
+    queue: ExecutionQueue<PythonDomain>,
+    tick: u64,
+}
+
+#[pymethods]
+impl Executor {
+    #[new]
+    fn new(
+        domain: PyObject,
+        state: &Bound<'_, PyAny>,
+        agents: Vec<u32>,
+        config: &PyMCTSConfiguration,
+    ) -> PyResult<Self> {
+        let state = PyState::new(Arc::new(domain), state)?;
+        let mut queue = ExecutionQueue::new(Default::default());
+        for agent in agents {
+            queue.queue_task(0, AgentId(agent), Box::new(IdleTask), &state);
+        }
+        Ok(Self {
+            mcts_config: config.into(),
+            state,
+            queue,
+            tick: 0,
+        })
+    }
+
+    /// Executes the tasks ending at the current tick, plans for the agents without a subsequent task, and advances the tick.
+    ///
+    /// Returns the tasks chosen during this step, as (agent, name, arguments) tuples.
+    fn step(&mut self, py: Python<'_>) -> PyResult<Vec<(u32, String, PyObject)>> {
+        let mut chosen = ChosenTasks::default();
+        loop {
+            match self.queue.task_queue().iter().next() {
+                Some(active_task) if active_task.end <= self.tick => {}
+                _ => break,
+            }
+            let active_task = self.queue.pop_first_task();
+            let active_agent = active_task.agent;
+            let tick = active_task.end;
+            let (diff, new_task) =
+                self.queue
+                    .execute_task(&active_task, &self.state, &mut chosen, |_| {});
+            PythonDomain::apply_diff(diff, &mut self.state);
+            let new_task = new_task.unwrap_or_else(|| {
+                let mut mcts = MCTS::<PythonDomain>::new_with_tasks(
+                    self.state.clone(),
+                    active_agent,
+                    tick,
+                    self.queue.task_queue().clone(),
+                    self.mcts_config.clone(),
+                    chosen.create_state_value_estimator(),
+                    None,
+                );
+                mcts.run().unwrap_or_else(|| Box::new(IdleTask))
+            });
+            self.queue
+                .queue_chosen_task(tick, active_agent, new_task, &self.state, &mut chosen);
+        }
+        self.tick += 1;
+        chosen
+            .0
+            .iter()
+            .map(|active_task| {
+                let (name, args) = task_to_python(py, active_task.task.downcast_ref::<PyTask>())?;
+                Ok((active_task.agent.0, name, args))
+            })
+            .collect()
+    }
+
+    /// The current state
+    #[getter]
+    fn state(&self, py: Python<'_>) -> PyResult<PyObject> {
+        self.state.to_python(py).map(Bound::unbind)
+    }
+
+    /// The current tick
+    #[getter]
+    fn tick(&self) -> u64 {
+        self.tick
+    }
+}
@@ -0,0 +1,40 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+//! These are the Python bindings of the [NPC engine](https://crates.io/crates/npc-engine-core/), built as the `npc_engine` Python module.
+//!
+//! A domain is a Python object whose methods are called by the planner, with states exchanged as JSON-serializable values:
+//! - `value(tick, state, agent)` returns the current value of an agent, as a float.
+//! - `tasks(tick, state, agent)` returns the tasks available to an agent, as (name, arguments) tuples.
+//! - `execute(tick, state, agent, name, arguments)` returns the state after executing a task, None if it is unchanged.
+//! - Optionally, `duration`, `is_valid` and `weight`, with the same parameters, return the duration (1 by default),
+//!   the validity (true by default) and the weight (1 by default) of a task.
+//! - Optionally, `visible_agents(tick, state, agent)` returns the agents visible by an agent (by default, only itself).
+//!
+//! It contains the following classes:
+//! - [MCTSConfiguration](PyMCTSConfiguration) holds the parameters of a search.
+//! - [Planner] runs a search and exposes its tree for analysis, for example from notebooks.
+//! - [Executor] executes tasks and plans for agents in a loop.
+
+// The #[pymethods] of PyO3 0.22 convert the error of a returned PyResult into itself
+#![allow(clippy::useless_conversion)]
+
+use pyo3::prelude::*;
+
+mod domain;
+mod executor;
+mod planner;
+
+pub use domain::*;
+pub use executor::*;
+pub use planner::*;
+
+#[pymodule]
+fn npc_engine(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyMCTSConfiguration>()?;
+    m.add_class::<Planner>()?;
+    m.add_class::<Executor>()?;
+    Ok(())
+}
@@ -0,0 +1,145 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{cmp::Reverse, sync::Arc};
+
+use npc_engine_core::{AgentId, MCTSConfiguration, MCTS};
+use pyo3::prelude::*;
+
+use crate::{PyState, PyTask, PythonDomain};
+
+/// The parameters of a search, see [MCTSConfiguration].
+#[pyclass(name = "MCTSConfiguration")]
+#[derive(Clone, Debug)]
+pub struct PyMCTSConfiguration {
+    /// The number of visits of a search
+    #[pyo3(get, set)]
+    pub visits: u32,
+    /// The maximum depth of a search, in ticks
+    #[pyo3(get, set)]
+    pub depth: u32,
+    /// The exploration factor of UCT
+    #[pyo3(get, set)]
+    pub exploration: f32,
+    /// The discount of later rewards, as a half life in ticks
+    #[pyo3(get, set)]
+    pub discount_hl: f32,
+    /// The seed of the search, random if None
+    #[pyo3(get, set)]
+    pub seed: Option<u64>,
+}
+
+#[pymethods]
+impl PyMCTSConfiguration {
+    #[new]
+    #[pyo3(signature = (visits = 1000, depth = 10, exploration = 1.414, discount_hl = 15.0, seed = None))]
+    fn new(visits: u32, depth: u32, exploration: f32, discount_hl: f32, seed: Option<u64>) -> Self {
+        Self {
+            visits,
+            depth,
+            exploration,
+            discount_hl,
+            seed,
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{self:?}")
+    }
+}
+
+impl From<&PyMCTSConfiguration> for MCTSConfiguration {
+    fn from(config: &PyMCTSConfiguration) -> Self {
+        MCTSConfiguration {
+            allow_invalid_tasks: false,
+            visits: config.visits,
+            depth: config.depth,
+            exploration: config.exploration,
+            discount_hl: config.discount_hl,
+            seed: config.seed,
+            planning_task_duration: None,
+        }
+    }
+}
+
+/// A search for the best task of an agent in a state of a Python domain, whose tree can be inspected after running.
+#[pyclass(unsendable)]
+pub struct Planner {
+    mcts: MCTS<PythonDomain>,
+}
+
+#[pymethods]
+impl Planner {
+    #[new]
+    fn new(
+        domain: PyObject,
+        state: &Bound<'_, PyAny>,
+        agent: u32,
+        config: &PyMCTSConfiguration,
+    ) -> PyResult<Self> {
+        let state = PyState::new(Arc::new(domain), state)?;
+        Ok(Self {
+            mcts: MCTS::new(state, AgentId(agent), config.into()),
+        })
+    }
+
+    /// Runs the search and returns the best task as a (name, arguments) tuple, None if the agent has no task.
+    fn run(&mut self, py: Python<'_>) -> PyResult<Option<(String, PyObject)>> {
+        match self.mcts.run() {
+            Some(task) => task_to_python(py, task.downcast_ref::<PyTask>()).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the expanded tasks at the root as (name, arguments, visits, value) tuples, the most visited first.
+    fn root_tasks(&self, py: Python<'_>) -> PyResult<Vec<(String, PyObject, usize, f32)>> {
+        let agent = self.mcts.agent();
+        let edges = match self.mcts.get_edges(&self.mcts.root_node()) {
+            Some(edges) => edges,
+            None => return Ok(Vec::new()),
+        };
+        let mut tasks = edges
+            .expanded_tasks()
+            .map(|(task, edge)| {
+                let edge = edge.lock().unwrap();
+                let (name, args) = task_to_python(py, task.downcast_ref::<PyTask>())?;
+                Ok((name, args, edge.visits(), edge.q_value(agent)))
+            })
+            .collect::<PyResult<Vec<_>>>()?;
+        tasks.sort_by_key(|task| Reverse(task.2));
+        Ok(tasks)
+    }
+
+    /// Returns a human-readable explanation of the choice of the best task, None if no task was expanded.
+    fn explain(&self) -> Option<String> {
+        self.mcts
+            .explain_best_task()
+            .map(|explanation| explanation.to_string())
+    }
+
+    /// The number of visits done by the search
+    #[getter]
+    fn visits_done(&self) -> u32 {
+        self.mcts.visits_done()
+    }
+
+    /// The number of nodes of the search tree
+    #[getter]
+    fn node_count(&self) -> usize {
+        self.mcts.node_count()
+    }
+}
+
+/// Returns a task of a Python domain as a (name, arguments) tuple.
+pub(crate) fn task_to_python(
+    py: Python<'_>,
+    task: Option<&PyTask>,
+) -> PyResult<(String, PyObject)> {
+    match task {
+        Some(task) => task.to_python(py),
+        // Idle and planning tasks are not Python tasks
+        None => Ok(("idle".into(), py.None())),
+    }
+}
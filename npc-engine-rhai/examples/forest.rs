@@ -0,0 +1,136 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+//! A woodcutter whose value function and heuristic are scripted in `examples/forest.rhai`, reloaded before each planning.
+
+use std::{
+    collections::BTreeSet,
+    sync::{Arc, OnceLock},
+    thread,
+    time::Duration,
+};
+
+use npc_engine_core::{
+    impl_task_boxed_methods, AgentId, AgentValue, Behavior, Context, ContextMut, Domain,
+    MCTSConfiguration, StateDiffRef, Task, TaskDuration, MCTS,
+};
+use npc_engine_rhai::{RhaiEstimator, RhaiValueScript};
+use npc_engine_utils::OptionDiffDomain;
+use serde::Serialize;
+
+static SCRIPT: OnceLock<Arc<RhaiValueScript>> = OnceLock::new();
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize)]
+struct State {
+    trees: u32,
+    wood: Vec<u32>,
+}
+
+struct ForestDomain;
+
+impl Domain for ForestDomain {
+    type State = State;
+    type Diff = Option<State>;
+    type DisplayAction = ();
+
+    fn list_behaviors() -> &'static [&'static dyn Behavior<Self>] {
+        &[&Woodcutter]
+    }
+
+    fn get_current_value(_tick: u64, state_diff: StateDiffRef<Self>, agent: AgentId) -> AgentValue {
+        let state = ForestDomain::get_cur_state(state_diff);
+        SCRIPT.get().unwrap().value(state, agent)
+    }
+
+    fn update_visible_agents(_start_tick: u64, ctx: Context<Self>, agents: &mut BTreeSet<AgentId>) {
+        agents.insert(ctx.agent);
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Woodcutter;
+
+impl Behavior<ForestDomain> for Woodcutter {
+    fn add_own_tasks(
+        &self,
+        _ctx: Context<ForestDomain>,
+        tasks: &mut Vec<Box<dyn Task<ForestDomain>>>,
+    ) {
+        tasks.push(Box::new(Chop));
+    }
+
+    fn is_valid(&self, _ctx: Context<ForestDomain>) -> bool {
+        true
+    }
+}
+
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+struct Chop;
+
+impl Task<ForestDomain> for Chop {
+    fn duration(&self, _ctx: Context<ForestDomain>) -> TaskDuration {
+        1
+    }
+
+    fn execute(&self, ctx: ContextMut<ForestDomain>) -> Option<Box<dyn Task<ForestDomain>>> {
+        let state = ForestDomain::get_cur_state_mut(ctx.state_diff);
+        state.trees -= 1;
+        state.wood[ctx.agent.0 as usize] += 1;
+        None
+    }
+
+    fn is_valid(&self, ctx: Context<ForestDomain>) -> bool {
+        ForestDomain::get_cur_state(ctx.state_diff).trees > 0
+    }
+
+    fn display_action(&self) -> <ForestDomain as Domain>::DisplayAction {}
+
+    impl_task_boxed_methods!(ForestDomain);
+}
+
+fn main() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/forest.rhai");
+    let script = Arc::new(RhaiValueScript::load(path).expect("the script must compile"));
+    SCRIPT.set(script.clone()).ok().unwrap();
+    let state = State {
+        trees: 5,
+        wood: vec![0],
+    };
+    let config = MCTSConfiguration {
+        allow_invalid_tasks: false,
+        visits: 1000,
+        depth: 10,
+        exploration: 1.414,
+        discount_hl: 15.,
+        seed: Some(0),
+        planning_task_duration: None,
+    };
+    loop {
+        // Keep the previous script if the file has errors
+        if let Err(err) = script.reload() {
+            println!("Cannot reload the script: {err}");
+        }
+        let estimator = RhaiEstimator::new(script.clone(), |state_diff| {
+            ForestDomain::get_cur_state(state_diff).clone()
+        });
+        let mut mcts = MCTS::<ForestDomain>::new_with_tasks(
+            state.clone(),
+            AgentId(0),
+            0,
+            Default::default(),
+            config.clone(),
+            Box::new(estimator),
+            None,
+        );
+        let best = mcts.run();
+        let stats = script.cache_stats();
+        println!(
+            "Best task {best:?}, cache hit rate {:.0}% ({} values)",
+            100. * stats.hit_rate(),
+            stats.len
+        );
+        thread::sleep(Duration::from_secs(2));
+    }
+}
@@ -0,0 +1,99 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{
+    collections::HashMap,
+    hash::{BuildHasherDefault, Hash, Hasher},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+use rustc_hash::FxHasher;
+
+/// The statistics of the cache of a [RhaiValueScript](crate::RhaiValueScript).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// The number of values found in the cache
+    pub hits: u64,
+    /// The number of values computed by the script
+    pub misses: u64,
+    /// The number of values currently in the cache
+    pub len: usize,
+}
+
+impl CacheStats {
+    /// Returns the ratio of values found in the cache, 0 if no value was requested.
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+/// A bounded cache of script results, keyed by hash.
+///
+/// When full, the cache is cleared, which is cheap and keeps the states of the current searches.
+pub(crate) struct ValueCache {
+    values: Mutex<HashMap<u64, f32, BuildHasherDefault<FxHasher>>>,
+    capacity: usize,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl ValueCache {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            values: Mutex::new(HashMap::default()),
+            capacity,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the key of a value.
+    pub(crate) fn key(parts: impl Hash) -> u64 {
+        let mut hasher = FxHasher::default();
+        parts.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Returns the cached value for `key`, computing it with `compute` if missing or if the cache is disabled.
+    pub(crate) fn get_or_compute(&self, key: u64, compute: impl FnOnce() -> f32) -> f32 {
+        if self.capacity == 0 {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            return compute();
+        }
+        if let Some(value) = self.values.lock().unwrap().get(&key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return *value;
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        // Compute without holding the lock, so that searches on other threads are not blocked
+        let value = compute();
+        let mut values = self.values.lock().unwrap();
+        if values.len() >= self.capacity {
+            values.clear();
+        }
+        values.insert(key, value);
+        value
+    }
+
+    pub(crate) fn clear(&self) {
+        self.values.lock().unwrap().clear();
+    }
+
+    pub(crate) fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            len: self.values.lock().unwrap().len(),
+        }
+    }
+}
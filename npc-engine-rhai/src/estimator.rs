@@ -0,0 +1,70 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{collections::BTreeMap, hash::Hash, marker::PhantomData, sync::Arc};
+
+use npc_engine_core::{
+    AgentId, Domain, Edges, MCTSConfiguration, Node, StateDiffRef, StateValueEstimator,
+};
+use rand_chacha::ChaCha8Rng;
+use serde::Serialize;
+
+use crate::RhaiValueScript;
+
+/// A state value estimator using the heuristic of a [RhaiValueScript] instead of rollouts.
+///
+/// The estimator evaluates the state of a node through `view`, which returns the serializable state seen by the script,
+/// typically the current state for domains whose diffs are copies of the state.
+pub struct RhaiEstimator<D, F> {
+    script: Arc<RhaiValueScript>,
+    view: F,
+    _domain: PhantomData<fn() -> D>,
+}
+
+impl<D, F> RhaiEstimator<D, F> {
+    /// Creates an estimator from `script`, seeing states through `view`.
+    ///
+    /// The bound on `view` lets closures be inferred for any lifetime of the state they see.
+    pub fn new<S>(script: Arc<RhaiValueScript>, view: F) -> Self
+    where
+        D: Domain,
+        F: Fn(StateDiffRef<D>) -> S,
+    {
+        Self {
+            script,
+            view,
+            _domain: PhantomData,
+        }
+    }
+}
+
+impl<D, F, S> StateValueEstimator<D> for RhaiEstimator<D, F>
+where
+    D: Domain,
+    F: Fn(StateDiffRef<D>) -> S + Send,
+    S: Serialize + Hash,
+{
+    fn estimate(
+        &mut self,
+        _rnd: &mut ChaCha8Rng,
+        _config: &MCTSConfiguration,
+        initial_state: &D::State,
+        _start_tick: u64,
+        node: &Node<D>,
+        edges: &Edges<D>,
+        _depth: u32,
+    ) -> Option<BTreeMap<AgentId, f32>> {
+        if edges.unexpanded_count() == 0 {
+            return None;
+        }
+        let state = (self.view)(StateDiffRef::new(initial_state, node.diff()));
+        Some(
+            node.agents()
+                .into_iter()
+                .map(|agent| (agent, self.script.heuristic(&state, agent)))
+                .collect(),
+        )
+    }
+}
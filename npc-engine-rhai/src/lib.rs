@@ -0,0 +1,24 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+//! This is a [Rhai](https://rhai.rs) integration for the [NPC engine](https://crates.io/crates/npc-engine-core/), to express value functions and heuristics as scripts.
+//!
+//! A [RhaiValueScript] defines `value(state, agent)`, returning the current value of an agent, and optionally `heuristic(state, agent)`,
+//! returning the estimated value of an agent in a newly expanded node, states being passed as Rhai object maps through [serde].
+//! The script can be reloaded while the game runs with [RhaiValueScript::reload], for example when its file changes.
+//!
+//! It contains the following features:
+//! - [RhaiValueScript::value], to call from [get_current_value](npc_engine_core::Domain::get_current_value).
+//! - A state value estimator ([RhaiEstimator]) using the heuristic instead of rollouts.
+//! - A cache of the values computed by the script ([CacheStats]), keyed by the hash of the state and the agent,
+//!   as the same states are evaluated many times during a search.
+
+mod cache;
+mod estimator;
+mod script;
+
+pub use cache::*;
+pub use estimator::*;
+pub use script::*;
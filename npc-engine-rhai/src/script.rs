@@ -0,0 +1,125 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{
+    fs,
+    hash::Hash,
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+
+use npc_engine_core::{AgentId, AgentValue};
+use rhai::{Dynamic, Engine, EvalAltResult, Scope, AST};
+use serde::Serialize;
+
+use crate::{CacheStats, ValueCache};
+
+/// The default number of values kept in the cache of a script.
+pub const DEFAULT_CACHE_CAPACITY: usize = 100_000;
+
+/// A Rhai script defining the value function and optionally the heuristic of a domain, see the crate documentation.
+///
+/// Values are functions of the state and the agent only, which allows caching them.
+pub struct RhaiValueScript {
+    engine: Engine,
+    ast: RwLock<AST>,
+    path: Option<PathBuf>,
+    cache: ValueCache,
+}
+
+impl RhaiValueScript {
+    /// Creates a script from its source.
+    pub fn new(source: &str) -> Result<Self, Box<EvalAltResult>> {
+        let engine = Engine::new();
+        let ast = engine.compile(source)?;
+        Ok(Self {
+            engine,
+            ast: RwLock::new(ast),
+            path: None,
+            cache: ValueCache::new(DEFAULT_CACHE_CAPACITY),
+        })
+    }
+
+    /// Creates a script from a file, which can later be reloaded with [reload](Self::reload).
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<EvalAltResult>> {
+        let path = path.as_ref().to_path_buf();
+        let mut script = Self::new(&read(&path)?)?;
+        script.path = Some(path);
+        Ok(script)
+    }
+
+    /// Reloads the script from its file and clears the cache, does nothing for scripts created from source.
+    ///
+    /// If the new script fails to compile, the error is returned and the previous script is kept.
+    pub fn reload(&self) -> Result<(), Box<EvalAltResult>> {
+        if let Some(path) = &self.path {
+            let ast = self.engine.compile(read(path)?)?;
+            *self.ast.write().unwrap() = ast;
+            self.cache.clear();
+        }
+        Ok(())
+    }
+
+    /// Sets the number of values kept in the cache, 0 disabling it, and clears the cache.
+    pub fn set_cache_capacity(&mut self, capacity: usize) {
+        self.cache = ValueCache::new(capacity);
+    }
+
+    /// Returns the statistics of the cache.
+    pub fn cache_stats(&self) -> CacheStats {
+        self.cache.stats()
+    }
+
+    /// Returns the value of `agent` in `state`, computed by the `value` function of the script.
+    ///
+    /// Panics if the script fails, as domain callbacks cannot fail.
+    pub fn value<S: Serialize + Hash>(&self, state: &S, agent: AgentId) -> AgentValue {
+        let key = ValueCache::key(("value", state, agent));
+        let value = self
+            .cache
+            .get_or_compute(key, || self.call("value", state, agent));
+        AgentValue::new(value).expect("the value must not be NaN")
+    }
+
+    /// Returns the estimated value of `agent` in `state`, computed by the `heuristic` function of the script,
+    /// or by its `value` function if it has no heuristic.
+    ///
+    /// Panics if the script fails.
+    pub fn heuristic<S: Serialize + Hash>(&self, state: &S, agent: AgentId) -> f32 {
+        let key = ValueCache::key(("heuristic", state, agent));
+        self.cache.get_or_compute(key, || {
+            let has_heuristic = self
+                .ast
+                .read()
+                .unwrap()
+                .iter_functions()
+                .any(|function| function.name == "heuristic" && function.params.len() == 2);
+            let name = if has_heuristic { "heuristic" } else { "value" };
+            self.call(name, state, agent)
+        })
+    }
+
+    /// Calls the function `name` of the script with the state and the agent.
+    fn call<S: Serialize>(&self, name: &str, state: &S, agent: AgentId) -> f32 {
+        let result = || -> Result<f32, Box<EvalAltResult>> {
+            let state = rhai::serde::to_dynamic(state)?;
+            let ast = self.ast.read().unwrap();
+            let value: Dynamic =
+                self.engine
+                    .call_fn(&mut Scope::new(), &ast, name, (state, agent.0 as i64))?;
+            // Accept integer values as well, as scripts often count things
+            let value = value
+                .as_float()
+                .or_else(|_| value.as_int().map(|value| value as f64))
+                .map_err(|type_name| format!("{name} returned a {type_name}, not a number"))?;
+            Ok(value as f32)
+        };
+        result().unwrap_or_else(|err| panic!("Rhai function {name} failed: {err}"))
+    }
+}
+
+fn read(path: &Path) -> Result<String, Box<EvalAltResult>> {
+    fs::read_to_string(path).map_err(|err| format!("cannot read {}: {err}", path.display()).into())
+}
@@ -0,0 +1,13 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Builds with the protoc shipped by protoc-bin-vendored, unless another one is given
+    if std::env::var_os("PROTOC").is_none() {
+        std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+    }
+    tonic_build::compile_protos("proto/planner.proto")?;
+    Ok(())
+}
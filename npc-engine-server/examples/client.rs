@@ -0,0 +1,52 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+//! A client planning for agent 0 of the demo `counter` domain, on the server at the address given as argument.
+
+use npc_engine_server::proto::{
+    plan_response::Event, planner_client::PlannerClient, Config, ListDomainsRequest, PlanRequest,
+};
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let addr = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "http://[::1]:50051".into());
+    let mut client = PlannerClient::connect(addr).await?;
+    let domains = client
+        .list_domains(ListDomainsRequest {})
+        .await?
+        .into_inner();
+    println!("Domains: {:?}", domains.domains);
+
+    let request = PlanRequest {
+        domain: "counter".into(),
+        state: r#"{"limit": 10, "counters": {"0": 0, "1": 0}}"#.into(),
+        agent: 0,
+        tick: 0,
+        config: Some(Config {
+            visits: 500,
+            depth: 10,
+            exploration: 1.414,
+            discount_hl: 15.,
+            seed: Some(0),
+        }),
+        max_plan_steps: 8,
+    };
+    let mut responses = client.plan(request).await?.into_inner();
+    while let Some(response) = responses.message().await? {
+        match response.event {
+            Some(Event::Chosen(chosen)) => {
+                println!("Chosen task: {} ({} visits)", chosen.task, chosen.visits)
+            }
+            Some(Event::Step(step)) => println!(
+                "  tick {}: agent {} does {} for {} ticks, expected value {:.2}",
+                step.tick, step.agent, step.task, step.duration, step.expected_value
+            ),
+            None => {}
+        }
+    }
+    Ok(())
+}
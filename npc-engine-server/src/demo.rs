@@ -0,0 +1,112 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+//! A demo domain, in which agents increment their own counter by one or two, without letting the total exceed a limit.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use npc_engine_core::{
+    impl_task_boxed_methods, AgentId, AgentValue, Behavior, Context, ContextMut, Domain,
+    SnapshotDomain, StateDiffRef, Task, TaskDuration,
+};
+use npc_engine_utils::OptionDiffDomain;
+use serde::{Deserialize, Serialize};
+
+/// The counters of the agents and their limit, for example `{"limit": 10, "counters": {"0": 0, "1": 0}}`.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct State {
+    pub limit: u32,
+    pub counters: BTreeMap<u32, u32>,
+}
+
+impl State {
+    fn total(&self) -> u32 {
+        self.counters.values().sum()
+    }
+}
+
+pub struct CounterDomain;
+
+impl Domain for CounterDomain {
+    type State = State;
+    type Diff = Option<State>;
+    type DisplayAction = ();
+
+    fn list_behaviors() -> &'static [&'static dyn Behavior<Self>] {
+        &[&CounterBehavior]
+    }
+
+    fn get_current_value(_tick: u64, state_diff: StateDiffRef<Self>, agent: AgentId) -> AgentValue {
+        let state = CounterDomain::get_cur_state(state_diff);
+        let counter = state.counters.get(&agent.0).copied().unwrap_or(0);
+        AgentValue::new(counter as f32).unwrap()
+    }
+
+    fn update_visible_agents(_start_tick: u64, ctx: Context<Self>, agents: &mut BTreeSet<AgentId>) {
+        let state = CounterDomain::get_cur_state(ctx.state_diff);
+        agents.extend(state.counters.keys().copied().map(AgentId));
+    }
+}
+
+impl SnapshotDomain for CounterDomain {
+    type TaskSnapshot = u32;
+
+    fn snapshot_task(task: &dyn Task<Self>) -> u32 {
+        task.downcast_ref::<Add>().unwrap().0
+    }
+
+    fn restore_task(amount: u32) -> Box<dyn Task<Self>> {
+        Box::new(Add(amount))
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct CounterBehavior;
+
+impl Behavior<CounterDomain> for CounterBehavior {
+    fn add_own_tasks(
+        &self,
+        ctx: Context<CounterDomain>,
+        tasks: &mut Vec<Box<dyn Task<CounterDomain>>>,
+    ) {
+        for amount in [1, 2] {
+            let task = Add(amount);
+            if task.is_valid(ctx) {
+                tasks.push(Box::new(task));
+            }
+        }
+    }
+
+    fn is_valid(&self, ctx: Context<CounterDomain>) -> bool {
+        CounterDomain::get_cur_state(ctx.state_diff)
+            .counters
+            .contains_key(&ctx.agent.0)
+    }
+}
+
+/// Adds an amount to the counter of the agent, taking as many ticks.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+struct Add(u32);
+
+impl Task<CounterDomain> for Add {
+    fn duration(&self, _ctx: Context<CounterDomain>) -> TaskDuration {
+        self.0 as TaskDuration
+    }
+
+    fn execute(&self, ctx: ContextMut<CounterDomain>) -> Option<Box<dyn Task<CounterDomain>>> {
+        let state = CounterDomain::get_cur_state_mut(ctx.state_diff);
+        *state.counters.get_mut(&ctx.agent.0).unwrap() += self.0;
+        None
+    }
+
+    fn is_valid(&self, ctx: Context<CounterDomain>) -> bool {
+        let state = CounterDomain::get_cur_state(ctx.state_diff);
+        state.total() + self.0 <= state.limit
+    }
+
+    fn display_action(&self) -> <CounterDomain as Domain>::DisplayAction {}
+
+    impl_task_boxed_methods!(CounterDomain);
+}
@@ -0,0 +1,24 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+//! This is an out-of-process planning server for the [NPC engine](https://crates.io/crates/npc-engine-core/), serving plannings over [gRPC](https://grpc.io).
+//!
+//! Clients submit a state serialized as JSON, an agent and a configuration, the server plans, possibly on a more powerful machine,
+//! and streams back the chosen task followed by the steps of the plan, tasks being serialized as JSON [TaskSnapshots](npc_engine_core::TaskSnapshot).
+//! This allows thin game clients and centralized AI for many clients.
+//!
+//! The protocol is defined in `proto/planner.proto`, whose generated code is in [proto].
+//! A [PlanningService] serves the domains registered in it, which must implement [SnapshotDomain](npc_engine_core::SnapshotDomain)
+//! and have a deserializable state.
+//! The `npc-planning-server` binary serves a demo domain, servers for games are built the same way with their own domains.
+
+mod service;
+
+pub use service::*;
+
+/// The code generated from `proto/planner.proto`, including the server and the client.
+pub mod proto {
+    tonic::include_proto!("npc_engine");
+}
@@ -0,0 +1,39 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+//! A planning server serving the demo `counter` domain, listening on the address given as argument, `[::1]:50051` by default.
+
+use npc_engine_core::MCTSConfiguration;
+use npc_engine_server::PlanningService;
+
+mod demo;
+
+const DEFAULT_ADDR: &str = "[::1]:50051";
+const MAX_VISITS: u32 = 100_000;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let addr = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| DEFAULT_ADDR.into())
+        .parse()?;
+    let mut service = PlanningService::new(MCTSConfiguration {
+        allow_invalid_tasks: false,
+        visits: 1000,
+        depth: 10,
+        exploration: 1.414,
+        discount_hl: 15.,
+        seed: None,
+        planning_task_duration: None,
+    });
+    service.register_domain::<demo::CounterDomain>("counter");
+    service.set_max_visits(Some(MAX_VISITS));
+    println!("Serving plannings on {addr}");
+    tonic::transport::Server::builder()
+        .add_service(service.into_server())
+        .serve(addr)
+        .await?;
+    Ok(())
+}
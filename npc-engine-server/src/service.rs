@@ -0,0 +1,210 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{collections::BTreeMap, marker::PhantomData, sync::Arc};
+
+use npc_engine_core::{
+    AgentId, DefaultPolicyEstimator, IdleTask, MCTSConfiguration, SnapshotDomain, Task,
+    TaskSnapshot, MCTS,
+};
+use npc_engine_utils::export_plan;
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+
+use crate::proto::{
+    plan_response::Event,
+    planner_server::{Planner, PlannerServer},
+    ChosenTask, Config, ListDomainsRequest, ListDomainsResponse, PlanRequest, PlanResponse,
+    PlanStep,
+};
+
+/// The number of responses buffered per planning before the server waits for the client.
+const RESPONSE_BUFFER: usize = 16;
+
+type ResponseSender = mpsc::Sender<Result<PlanResponse, Status>>;
+
+/// A domain registered in a [PlanningService], with its type erased.
+trait ServedDomain: Send + Sync {
+    /// Plans for the request and sends the responses, stopping early if the client is gone.
+    // The error is sent back to the client as is, like the ones of tonic
+    #[allow(clippy::result_large_err)]
+    fn plan(
+        &self,
+        request: &PlanRequest,
+        config: MCTSConfiguration,
+        sender: &ResponseSender,
+    ) -> Result<(), Status>;
+}
+
+struct Served<D>(PhantomData<fn() -> D>);
+
+impl<D> ServedDomain for Served<D>
+where
+    D: SnapshotDomain,
+    D::State: DeserializeOwned,
+    D::TaskSnapshot: Serialize,
+{
+    fn plan(
+        &self,
+        request: &PlanRequest,
+        config: MCTSConfiguration,
+        sender: &ResponseSender,
+    ) -> Result<(), Status> {
+        let state: D::State = serde_json::from_str(&request.state)
+            .map_err(|err| Status::invalid_argument(format!("invalid state: {err}")))?;
+        let mut mcts = MCTS::<D>::new_with_tasks(
+            state,
+            AgentId(request.agent),
+            request.tick,
+            Default::default(),
+            config,
+            Box::new(DefaultPolicyEstimator {}),
+            None,
+        );
+        let task = mcts.run().unwrap_or_else(|| Box::new(IdleTask));
+        let chosen = ChosenTask {
+            task: task_json(&*task),
+            visits: mcts.visits_done(),
+        };
+        // Send the chosen task before exporting the plan, so that clients can act on it right away
+        if sender
+            .blocking_send(Ok(response(Event::Chosen(chosen))))
+            .is_err()
+        {
+            return Ok(());
+        }
+        let plan = export_plan(&mcts, request.max_plan_steps as usize, task_json::<D>);
+        for step in plan.steps {
+            let step = PlanStep {
+                agent: step.agent.0,
+                tick: step.tick,
+                task: step.task,
+                duration: step.duration,
+                visits: step.visits as u64,
+                expected_value: step.expected_value,
+            };
+            if sender
+                .blocking_send(Ok(response(Event::Step(step))))
+                .is_err()
+            {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn task_json<D>(task: &dyn Task<D>) -> String
+where
+    D: SnapshotDomain,
+    D::TaskSnapshot: Serialize,
+{
+    serde_json::to_string(&TaskSnapshot::new(task)).expect("Task snapshots are always serializable")
+}
+
+fn response(event: Event) -> PlanResponse {
+    PlanResponse { event: Some(event) }
+}
+
+/// A gRPC service planning for the domains registered in it, see the crate documentation.
+///
+/// Each planning runs on the blocking thread pool of tokio, so that plannings for many clients run in parallel.
+pub struct PlanningService {
+    domains: BTreeMap<String, Arc<dyn ServedDomain>>,
+    default_config: MCTSConfiguration,
+    max_visits: Option<u32>,
+}
+
+impl PlanningService {
+    /// Creates a service without domains, using `default_config` for requests without configuration.
+    pub fn new(default_config: MCTSConfiguration) -> Self {
+        Self {
+            domains: BTreeMap::new(),
+            default_config,
+            max_visits: None,
+        }
+    }
+
+    /// Registers domain `D` under `name`, replacing any domain with the same name.
+    pub fn register_domain<D>(&mut self, name: impl Into<String>)
+    where
+        D: SnapshotDomain,
+        D::State: DeserializeOwned,
+        D::TaskSnapshot: Serialize,
+    {
+        self.domains
+            .insert(name.into(), Arc::new(Served::<D>(PhantomData)));
+    }
+
+    /// Limits the number of visits of a planning, to protect the server from costly requests.
+    pub fn set_max_visits(&mut self, max_visits: Option<u32>) {
+        self.max_visits = max_visits;
+    }
+
+    /// Returns the names of the registered domains, in order.
+    pub fn domains(&self) -> impl Iterator<Item = &str> {
+        self.domains.keys().map(String::as_str)
+    }
+
+    /// Returns the configuration of a planning for a request.
+    fn config(&self, config: Option<&Config>) -> MCTSConfiguration {
+        let mut mcts_config = self.default_config.clone();
+        if let Some(config) = config {
+            mcts_config.visits = config.visits;
+            mcts_config.depth = config.depth;
+            mcts_config.exploration = config.exploration;
+            mcts_config.discount_hl = config.discount_hl;
+            mcts_config.seed = config.seed;
+        }
+        if let Some(max_visits) = self.max_visits {
+            mcts_config.visits = mcts_config.visits.min(max_visits);
+        }
+        mcts_config
+    }
+
+    /// Wraps the service into a server to add to a [tonic] router.
+    pub fn into_server(self) -> PlannerServer<Self> {
+        PlannerServer::new(self)
+    }
+}
+
+#[tonic::async_trait]
+impl Planner for PlanningService {
+    type PlanStream = ReceiverStream<Result<PlanResponse, Status>>;
+
+    async fn plan(
+        &self,
+        request: Request<PlanRequest>,
+    ) -> Result<Response<Self::PlanStream>, Status> {
+        let request = request.into_inner();
+        let domain = self
+            .domains
+            .get(&request.domain)
+            .ok_or_else(|| Status::not_found(format!("unknown domain {}", request.domain)))?
+            .clone();
+        let config = self.config(request.config.as_ref());
+        if config.visits == 0 {
+            return Err(Status::invalid_argument("visits must be positive"));
+        }
+        let (sender, receiver) = mpsc::channel(RESPONSE_BUFFER);
+        tokio::task::spawn_blocking(move || {
+            if let Err(status) = domain.plan(&request, config, &sender) {
+                let _ = sender.blocking_send(Err(status));
+            }
+        });
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+
+    async fn list_domains(
+        &self,
+        _request: Request<ListDomainsRequest>,
+    ) -> Result<Response<ListDomainsResponse>, Status> {
+        Ok(Response::new(ListDomainsResponse {
+            domains: self.domains().map(String::from).collect(),
+        }))
+    }
+}
@@ -0,0 +1,250 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use npc_engine_core::{
+    ActiveTasks, AgentId, Domain, DomainWithPlanningTask, EarlyStopCondition, MCTSConfiguration,
+    MCTS,
+};
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::{runtime::Handle, sync::oneshot};
+
+use crate::{
+    executor_core::{impl_executor_methods, ExecutorCore, Planners},
+    ExecutorState, ExecutorStateGlobal, GlobalDomain, TuningHandle,
+};
+
+/// An asynchronous generic executor, running planning on a [tokio] runtime.
+///
+/// It follows the same logic as the [ThreadedExecutor](crate::ThreadedExecutor),
+/// but each agent's planning runs as a blocking task on the runtime,
+/// and its result is delivered back to the executor through a channel.
+/// As [step](Self::step) is `async`, a game server can drive the simulation
+/// from its own event loop without blocking it while agents plan.
+/// The helper function [run_async_executor] can create and run it for you.
+pub struct AsyncExecutor<'a, D, S>
+where
+    D: DomainWithPlanningTask + GlobalDomain,
+    D::State: Clone + Send,
+    D::Diff: Send + Sync,
+    S: ExecutorState<D> + ExecutorStateGlobal<D>,
+{
+    /// The state and logic shared with the other executors
+    core: ExecutorCore<'a, D, S>,
+    /// The ongoing planning tasks
+    planners: AsyncPlanners<D>,
+}
+impl<'a, D, S> AsyncExecutor<'a, D, S>
+where
+    D: DomainWithPlanningTask + GlobalDomain,
+    D::State: Clone + Send,
+    D::Diff: Send + Sync,
+    S: ExecutorState<D> + ExecutorStateGlobal<D>,
+{
+    /// Creates a new executor planning on the current runtime, initializes state and task queue from the S trait.
+    ///
+    /// Panics if not called from within a [tokio] runtime.
+    pub fn new(mcts_config: MCTSConfiguration, executor_state: &'a mut S) -> Self {
+        Self::new_with_handle(mcts_config, executor_state, Handle::current())
+    }
+
+    /// Creates a new executor planning on the runtime of `runtime`, initializes state and task queue from the S trait.
+    pub fn new_with_handle(
+        mcts_config: MCTSConfiguration,
+        executor_state: &'a mut S,
+        runtime: Handle,
+    ) -> Self {
+        Self {
+            core: ExecutorCore::new(mcts_config, executor_state),
+            planners: AsyncPlanners {
+                receivers: Default::default(),
+                tick: Arc::new(AtomicU64::new(0)),
+                runtime,
+            },
+        }
+    }
+
+    /// Waits for all planning tasks which should have finished in the current tick and adds the
+    /// resulting best tasks to the queue.
+    ///
+    /// Returns the agents which committed a plan.
+    async fn await_planning(&mut self, tick: u64) -> BTreeSet<AgentId> {
+        let mut planned_agents = BTreeSet::new();
+        for active_task in self.core.due_planning_tasks(tick) {
+            let active_agent = active_task.agent;
+
+            // Wait for the result of the planning of the current agent
+            let receiver = self.planners.receivers.remove(&active_agent).unwrap_or_else(|| {
+                panic!("There is no planning task for {active_agent} even though there is an active_task for it.")
+            });
+            let mcts = receiver.await.unwrap_or_else(|_| {
+                panic!("Could not receive planning result of {active_agent}! Probably it panicked!")
+            });
+
+            // Override the planning task in the queue with the best task we got from the planning
+            self.core.commit_planning(tick, &active_task, &mcts);
+            planned_agents.insert(active_agent);
+        }
+        planned_agents
+    }
+
+    impl_executor_methods!();
+
+    /// Returns a handle to change the MCTS configuration while the executor runs, see [TuningHandle].
+    pub fn tuning_handle(&mut self) -> TuningHandle {
+        self.core.tuning_handle(None, None)
+    }
+
+    /// Executes all tasks finishing at the current tick and then increments it.
+    ///
+    /// Waits without blocking for the planning results needed at that tick.
+    /// Returns whether execution should continue.
+    pub async fn step(&mut self) -> bool {
+        if self.core.queue.is_empty() {
+            return false;
+        }
+
+        self.core.apply_tuning();
+        let tick = self.planners.tick.load(Ordering::Relaxed);
+        if !self.core.executor_state.keep_execution(
+            tick,
+            self.core.queue.task_queue(),
+            &self.core.state,
+        ) {
+            return false;
+        }
+        self.core.monitor_tasks(tick);
+        self.core.replan_dirty_agents(tick, &mut self.planners);
+        let planned_agents = self.await_planning(tick).await;
+        self.core.coordinate_plans(tick, &planned_agents);
+        self.core.replan_dirty_agents(tick, &mut self.planners);
+        self.core.execute_finished_tasks(tick, &mut self.planners);
+        self.core
+            .executor_state
+            .post_step_hook(tick, &mut self.core.state);
+
+        self.planners.tick.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+
+    /// Makes all planning tasks stop and wait for them to finish.
+    pub async fn stop(&mut self) {
+        // Set tick to maximum value
+        self.planners.tick.store(u64::MAX, Ordering::Relaxed);
+        // Wait for planning tasks to finish
+        for (_, receiver) in self.planners.receivers.drain() {
+            let _ = receiver.await;
+        }
+    }
+
+    /// Gets the current tick.
+    fn tick(&self) -> u64 {
+        self.planners.tick.load(Ordering::Relaxed)
+    }
+
+    /// Gets the global state, read-only.
+    pub fn state(&self) -> &D::GlobalState {
+        &self.core.state
+    }
+
+    /// Gets the active tasks, read-only.
+    pub fn active_tasks(&self) -> &ActiveTasks<D> {
+        self.core.queue.task_queue()
+    }
+
+    /// Gets the number of active agents in the execution queue.
+    pub fn agents_count(&self) -> usize {
+        self.core.queue.size()
+    }
+}
+
+/// The planning tasks of an [AsyncExecutor], running on a [tokio] runtime.
+struct AsyncPlanners<D: Domain> {
+    /// Channels receiving the results of the ongoing planning tasks
+    receivers: HashMap<AgentId, oneshot::Receiver<MCTS<D>>>,
+    /// The global tick of the simulation, shared with the plannings to stop them early
+    tick: Arc<AtomicU64>,
+    /// The runtime on which planning tasks are spawned
+    runtime: Handle,
+}
+impl<D, S> Planners<D, S> for AsyncPlanners<D>
+where
+    D: DomainWithPlanningTask + GlobalDomain,
+    D::State: Clone + Send,
+    D::Diff: Send + Sync,
+    S: ExecutorState<D> + ExecutorStateGlobal<D>,
+{
+    fn start(
+        &mut self,
+        core: &mut ExecutorCore<'_, D, S>,
+        tick: u64,
+        active_agent: AgentId,
+        end_tick: u64,
+    ) {
+        core.executor_state.on_plan_started(tick, active_agent);
+        log::info!("T{tick} - {active_agent} starts planning until T{end_tick}.");
+        let tick_atomic = self.tick.clone();
+        let early_stop_condition: Option<Box<EarlyStopCondition>> = Some(Box::new(move |visits| {
+            visits >= S::MINIMUM_VISITS && tick_atomic.load(Ordering::Relaxed) >= end_tick - 1
+        }));
+        let mut mcts = core.new_mcts(tick, active_agent, early_stop_condition);
+        let (sender, receiver) = oneshot::channel();
+        self.runtime.spawn_blocking(move || {
+            mcts.run();
+            // The receiver is gone if the executor was dropped or the agent replanned, the result is not needed then
+            let _ = sender.send(mcts);
+        });
+        self.receivers.insert(active_agent, receiver);
+    }
+
+    fn drop_planning(&mut self, agent: AgentId) {
+        self.receivers.remove(&agent);
+    }
+}
+
+/// Creates and runs an asynchronous executor on the current runtime, initializes state and task queue from the `S` trait.
+///
+/// Parameter `step_duration` defines how long a logical step lasts in wall time.
+pub async fn run_async_executor<D, S>(
+    mcts_config: &MCTSConfiguration,
+    executor_state: &mut S,
+    step_duration: Duration,
+) where
+    D: DomainWithPlanningTask + GlobalDomain,
+    D::State: Clone + Send,
+    D::Diff: Send + Sync,
+    S: ExecutorState<D> + ExecutorStateGlobal<D>,
+{
+    let mut executor = AsyncExecutor::<D, S>::new(mcts_config.clone(), executor_state);
+    while executor.step().await {
+        tokio::time::sleep(step_duration).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::tests::{trivial_mcts_config, TrivialExecutorState};
+
+    #[tokio::test]
+    async fn async_executor_trivial_domain() {
+        let mut executor_state = TrivialExecutorState(4);
+        let mut executor = AsyncExecutor::new(trivial_mcts_config(), &mut executor_state);
+        let mut steps = 0;
+        while executor.step().await {
+            steps += 1;
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        executor.stop().await;
+        assert_eq!(steps, 25);
+        assert_eq!(executor.agents_count(), 4);
+    }
+}
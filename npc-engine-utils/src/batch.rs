@@ -0,0 +1,345 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{fmt, time::Duration};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+};
+
+use npc_engine_core::{Instant, MCTSConfiguration};
+use serde::Serialize;
+
+use crate::{DebugStep, ExecutableDomain, ExecutorState, ExecutorStateLocal, SimpleExecutor};
+
+/// A simulation of a batch, see [run_batch].
+#[derive(Clone, Debug)]
+pub struct BatchRun {
+    /// The label of the configuration, runs with the same label are aggregated together in the report
+    pub label: String,
+    /// The MCTS configuration of this run
+    pub mcts_config: MCTSConfiguration,
+}
+impl BatchRun {
+    /// Creates a run with a given configuration.
+    pub fn new(label: impl Into<String>, mcts_config: MCTSConfiguration) -> Self {
+        Self {
+            label: label.into(),
+            mcts_config,
+        }
+    }
+
+    /// Creates one run per seed, all with the same label and otherwise the same configuration.
+    pub fn with_seeds(
+        label: impl Into<String>,
+        mcts_config: &MCTSConfiguration,
+        seeds: impl IntoIterator<Item = u64>,
+    ) -> Vec<Self> {
+        let label = label.into();
+        seeds
+            .into_iter()
+            .map(|seed| {
+                let mut mcts_config = mcts_config.clone();
+                mcts_config.seed = Some(seed);
+                Self::new(label.clone(), mcts_config)
+            })
+            .collect()
+    }
+}
+
+/// The statistics of a simulation of a batch.
+#[derive(Clone, Debug, Serialize)]
+pub struct RunStatistics {
+    /// The label of the run
+    pub label: String,
+    /// The seed of the run, if any
+    pub seed: Option<u64>,
+    /// The number of executed steps
+    pub steps: u64,
+    /// The number of plannings
+    pub plannings: u64,
+    /// The total number of visits of all plannings
+    pub visits: u64,
+    /// The total computation time spent planning
+    pub planning_time: Duration,
+    /// The wall-clock duration of the whole simulation
+    pub wall_time: Duration,
+    /// The score of the final state
+    pub score: f64,
+}
+
+/// The aggregated statistics of the runs sharing a label.
+#[derive(Clone, Debug, Serialize)]
+pub struct RunSummary {
+    /// The label of the runs
+    pub label: String,
+    /// The number of runs
+    pub runs: usize,
+    /// The mean score
+    pub mean_score: f64,
+    /// The standard deviation of the score
+    pub score_std_dev: f64,
+    /// The minimum score
+    pub min_score: f64,
+    /// The maximum score
+    pub max_score: f64,
+    /// The mean number of steps
+    pub mean_steps: f64,
+    /// The mean computation time spent planning
+    pub mean_planning_time: Duration,
+}
+
+/// The result of [run_batch], containing the statistics of all runs.
+#[derive(Clone, Debug, Serialize)]
+pub struct BatchReport {
+    /// The statistics of the runs, in the order of the runs passed to [run_batch]
+    pub runs: Vec<RunStatistics>,
+}
+impl BatchReport {
+    /// Returns the labels of the runs, in order of first appearance.
+    pub fn labels(&self) -> Vec<&str> {
+        let mut labels = Vec::new();
+        for run in &self.runs {
+            if !labels.contains(&run.label.as_str()) {
+                labels.push(run.label.as_str());
+            }
+        }
+        labels
+    }
+
+    /// Aggregates the statistics of the runs with a given label, returns None if there are none.
+    pub fn summary(&self, label: &str) -> Option<RunSummary> {
+        let runs: Vec<_> = self.runs.iter().filter(|run| run.label == label).collect();
+        if runs.is_empty() {
+            return None;
+        }
+        let count = runs.len() as f64;
+        let scores = runs.iter().map(|run| run.score);
+        let mean_score = scores.clone().sum::<f64>() / count;
+        let variance = scores
+            .clone()
+            .map(|score| (score - mean_score).powi(2))
+            .sum::<f64>()
+            / count;
+        Some(RunSummary {
+            label: label.into(),
+            runs: runs.len(),
+            mean_score,
+            score_std_dev: variance.sqrt(),
+            min_score: scores.clone().fold(f64::INFINITY, f64::min),
+            max_score: scores.fold(f64::NEG_INFINITY, f64::max),
+            mean_steps: runs.iter().map(|run| run.steps as f64).sum::<f64>() / count,
+            mean_planning_time: runs.iter().map(|run| run.planning_time).sum::<Duration>()
+                / runs.len() as u32,
+        })
+    }
+
+    /// Aggregates the statistics of the runs for all labels.
+    pub fn summaries(&self) -> Vec<RunSummary> {
+        self.labels()
+            .into_iter()
+            .filter_map(|label| self.summary(label))
+            .collect()
+    }
+}
+
+impl fmt::Display for BatchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<20} {:>5} {:>10} {:>10} {:>10} {:>10} {:>10} {:>12}",
+            "label", "runs", "mean", "std dev", "min", "max", "steps", "planning"
+        )?;
+        for summary in self.summaries() {
+            writeln!(
+                f,
+                "{:<20} {:>5} {:>10.3} {:>10.3} {:>10.3} {:>10.3} {:>10.1} {:>12?}",
+                summary.label,
+                summary.runs,
+                summary.mean_score,
+                summary.score_std_dev,
+                summary.min_score,
+                summary.max_score,
+                summary.mean_steps,
+                summary.mean_planning_time
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs a full simulation for each of `runs` using a [SimpleExecutor], in parallel on `threads` threads, or in turn in browsers.
+///
+/// For each run, `create_executor_state` creates the executor state and the simulation runs
+/// until the executor stops or `max_steps` steps are executed.
+/// Then, `score` evaluates the final state.
+/// The statistics of the runs are returned in a [BatchReport], that can be printed as a table.
+pub fn run_batch<D, S, C, E>(
+    runs: &[BatchRun],
+    max_steps: u64,
+    threads: usize,
+    create_executor_state: C,
+    score: E,
+) -> BatchReport
+where
+    D: ExecutableDomain + 'static,
+    D::State: Clone,
+    S: ExecutorState<D> + ExecutorStateLocal<D>,
+    C: Fn(&BatchRun) -> S + Send + Sync + 'static,
+    E: Fn(&D::State) -> f64 + Send + Sync + 'static,
+{
+    // Threads cannot be spawned in browsers, so simulations run in turn there
+    #[cfg(not(target_arch = "wasm32"))]
+    let runs = run_in_threads(runs, max_steps, threads, create_executor_state, score);
+    #[cfg(target_arch = "wasm32")]
+    let runs = {
+        let _ = threads;
+        runs.iter()
+            .map(|run| simulate(run, max_steps, &create_executor_state, &score))
+            .collect()
+    };
+    BatchReport { runs }
+}
+
+/// Runs the simulations of a batch on `threads` threads, returns their statistics in the order of `runs`.
+#[cfg(not(target_arch = "wasm32"))]
+fn run_in_threads<D, S, C, E>(
+    runs: &[BatchRun],
+    max_steps: u64,
+    threads: usize,
+    create_executor_state: C,
+    score: E,
+) -> Vec<RunStatistics>
+where
+    D: ExecutableDomain + 'static,
+    D::State: Clone,
+    S: ExecutorState<D> + ExecutorStateLocal<D>,
+    C: Fn(&BatchRun) -> S + Send + Sync + 'static,
+    E: Fn(&D::State) -> f64 + Send + Sync + 'static,
+{
+    let runs = Arc::new(runs.to_vec());
+    let create_executor_state = Arc::new(create_executor_state);
+    let score = Arc::new(score);
+    let next_run = Arc::new(AtomicUsize::new(0));
+    let (sender, receiver) = mpsc::channel();
+    let workers: Vec<_> = (0..threads.max(1))
+        .map(|worker| {
+            let runs = runs.clone();
+            let create_executor_state = create_executor_state.clone();
+            let score = score.clone();
+            let next_run = next_run.clone();
+            let sender = sender.clone();
+            thread::Builder::new()
+                .name(format!("batch-{worker}"))
+                .spawn(move || loop {
+                    let index = next_run.fetch_add(1, Ordering::Relaxed);
+                    let run = match runs.get(index) {
+                        Some(run) => run,
+                        None => break,
+                    };
+                    let statistics = simulate(run, max_steps, &*create_executor_state, &*score);
+                    sender.send((index, statistics)).unwrap();
+                })
+                .unwrap()
+        })
+        .collect();
+    drop(sender);
+    let mut results: Vec<_> = receiver.iter().collect();
+    for worker in workers {
+        worker.join().expect("A simulation of the batch panicked");
+    }
+    results.sort_by_key(|(index, _)| *index);
+    results
+        .into_iter()
+        .map(|(_, statistics)| statistics)
+        .collect()
+}
+
+/// Runs one simulation of a batch and collects its statistics.
+fn simulate<D, S, C, E>(
+    run: &BatchRun,
+    max_steps: u64,
+    create_executor_state: &C,
+    score: &E,
+) -> RunStatistics
+where
+    D: ExecutableDomain,
+    D::State: Clone,
+    S: ExecutorState<D> + ExecutorStateLocal<D>,
+    C: Fn(&BatchRun) -> S,
+    E: Fn(&D::State) -> f64,
+{
+    let start_time = Instant::now();
+    let mut executor_state = create_executor_state(run);
+    let mut executor = SimpleExecutor::new(run.mcts_config.clone(), &mut executor_state);
+    let mut statistics = RunStatistics {
+        label: run.label.clone(),
+        seed: run.mcts_config.seed,
+        steps: 0,
+        plannings: 0,
+        visits: 0,
+        planning_time: Duration::ZERO,
+        wall_time: Duration::ZERO,
+        score: 0.,
+    };
+    while statistics.steps < max_steps {
+        match executor.step_until_decision() {
+            DebugStep::Finished => break,
+            DebugStep::Executed => {}
+            DebugStep::Decision(decision) => {
+                statistics.plannings += 1;
+                statistics.visits += decision.mcts().visits_done() as u64;
+                statistics.planning_time += decision.mcts().time();
+                executor.commit_decision(*decision);
+            }
+        }
+        statistics.steps += 1;
+    }
+    statistics.score = score(executor.state());
+    statistics.wall_time = start_time.elapsed();
+    statistics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::tests::{counter_mcts_config, CounterDomain, CounterExecutorState};
+
+    #[test]
+    fn batch_runs_and_aggregates_simulations() {
+        let mut runs = BatchRun::with_seeds("deep", &counter_mcts_config(), 0..3);
+        let mut shallow_config = counter_mcts_config();
+        shallow_config.depth = 1;
+        runs.extend(BatchRun::with_seeds("shallow", &shallow_config, 0..3));
+        let run_batch = || {
+            run_batch::<CounterDomain, _, _, _>(
+                &runs,
+                20,
+                4,
+                |_| CounterExecutorState(0),
+                |state| *state as f64,
+            )
+        };
+        let report = run_batch();
+        assert_eq!(report.runs.len(), 6);
+        assert_eq!(report.runs[4].seed, Some(1));
+        assert_eq!(report.labels(), ["deep", "shallow"]);
+        let summary = report.summary("deep").unwrap();
+        assert_eq!(summary.runs, 3);
+        assert_eq!(summary.mean_steps, 20.);
+        assert!(summary.min_score > 0.);
+        assert!(summary.min_score <= summary.mean_score && summary.mean_score <= summary.max_score);
+        assert!(report.summary("unknown").is_none());
+        assert!(report.to_string().contains("shallow"));
+        // Runs are seeded, hence reproducible
+        let scores =
+            |report: &BatchReport| -> Vec<_> { report.runs.iter().map(|run| run.score).collect() };
+        assert_eq!(scores(&report), scores(&run_batch()));
+    }
+}
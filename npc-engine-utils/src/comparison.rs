@@ -0,0 +1,262 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::{
+    run_batch, BatchReport, BatchRun, ExecutableDomain, ExecutorState, ExecutorStateLocal,
+    RunSummary,
+};
+
+/// The comparison of two configurations simulated on the same seeds, see [compare_runs].
+#[derive(Clone, Debug, Serialize)]
+pub struct ComparisonReport {
+    /// The summary of the runs of the first configuration
+    pub a: RunSummary,
+    /// The summary of the runs of the second configuration
+    pub b: RunSummary,
+    /// The number of seeds for which the second configuration scored higher
+    pub wins_b: usize,
+    /// The number of seeds for which the first configuration scored higher
+    pub wins_a: usize,
+    /// The number of seeds for which both configurations scored the same
+    pub ties: usize,
+    /// The rate of wins of the second configuration, ties counting as half wins
+    pub win_rate_b: f64,
+    /// The mean difference of score of the second configuration compared to the first
+    pub mean_score_difference: f64,
+    /// The t statistic of the paired t-test of the scores
+    pub t_statistic: f64,
+    /// The two-sided p-value of the paired t-test, the probability of such a difference if both configurations were equivalent
+    pub p_value: f64,
+    /// The statistics of all runs
+    pub batch: BatchReport,
+}
+
+impl ComparisonReport {
+    /// Returns whether the difference of score is significant at the given level, for example 0.05.
+    pub fn is_significant(&self, level: f64) -> bool {
+        self.p_value < level
+    }
+}
+
+impl fmt::Display for ComparisonReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.batch)?;
+        writeln!(
+            f,
+            "{} vs {}: {} wins, {} losses, {} ties (win rate {:.1}%)",
+            self.b.label,
+            self.a.label,
+            self.wins_b,
+            self.wins_a,
+            self.ties,
+            self.win_rate_b * 100.
+        )?;
+        writeln!(
+            f,
+            "mean score difference {:+.3}, t = {:.3}, p = {:.4}",
+            self.mean_score_difference, self.t_statistic, self.p_value
+        )?;
+        writeln!(
+            f,
+            "mean planning time {:?} vs {:?}",
+            self.b.mean_planning_time, self.a.mean_planning_time
+        )
+    }
+}
+
+/// Simulates two configurations on the same seeds using [run_batch], and compares their scores seed by seed.
+///
+/// The labels of `a` and `b` must differ; `create_executor_state` receives the run and can use its label to compare two domain variants.
+/// The significance of the difference of scores is assessed with a paired t-test.
+pub fn compare_runs<D, S, C, E>(
+    a: &BatchRun,
+    b: &BatchRun,
+    seeds: &[u64],
+    max_steps: u64,
+    threads: usize,
+    create_executor_state: C,
+    score: E,
+) -> ComparisonReport
+where
+    D: ExecutableDomain + 'static,
+    D::State: Clone,
+    S: ExecutorState<D> + ExecutorStateLocal<D>,
+    C: Fn(&BatchRun) -> S + Send + Sync + 'static,
+    E: Fn(&D::State) -> f64 + Send + Sync + 'static,
+{
+    assert_ne!(a.label, b.label, "Compared runs must have different labels");
+    assert!(!seeds.is_empty(), "At least one seed is needed");
+    let mut runs = BatchRun::with_seeds(a.label.clone(), &a.mcts_config, seeds.iter().copied());
+    runs.extend(BatchRun::with_seeds(
+        b.label.clone(),
+        &b.mcts_config,
+        seeds.iter().copied(),
+    ));
+    let batch = run_batch(&runs, max_steps, threads, create_executor_state, score);
+
+    // Runs are in order, the ones of a then the ones of b, both following the seeds
+    let (runs_a, runs_b) = batch.runs.split_at(seeds.len());
+    let differences: Vec<_> = runs_a
+        .iter()
+        .zip(runs_b)
+        .map(|(a, b)| b.score - a.score)
+        .collect();
+    let wins_b = differences
+        .iter()
+        .filter(|difference| **difference > 0.)
+        .count();
+    let wins_a = differences
+        .iter()
+        .filter(|difference| **difference < 0.)
+        .count();
+    let ties = differences.len() - wins_a - wins_b;
+    let (mean_score_difference, t_statistic, p_value) = paired_t_test(&differences);
+    ComparisonReport {
+        a: batch.summary(&a.label).unwrap(),
+        b: batch.summary(&b.label).unwrap(),
+        wins_b,
+        wins_a,
+        ties,
+        win_rate_b: (wins_b as f64 + 0.5 * ties as f64) / differences.len() as f64,
+        mean_score_difference,
+        t_statistic,
+        p_value,
+        batch,
+    }
+}
+
+/// Returns the mean, the t statistic and the two-sided p-value of a paired t-test on `differences`.
+fn paired_t_test(differences: &[f64]) -> (f64, f64, f64) {
+    let count = differences.len() as f64;
+    let mean = differences.iter().sum::<f64>() / count;
+    if differences.len() < 2 {
+        return (mean, 0., 1.);
+    }
+    let variance = differences
+        .iter()
+        .map(|difference| (difference - mean).powi(2))
+        .sum::<f64>()
+        / (count - 1.);
+    if variance == 0. {
+        return if mean == 0. {
+            (mean, 0., 1.)
+        } else {
+            (mean, mean.signum() * f64::INFINITY, 0.)
+        };
+    }
+    let t = mean / (variance / count).sqrt();
+    let degrees = count - 1.;
+    let p = regularized_incomplete_beta(degrees / (degrees + t * t), degrees / 2., 0.5);
+    (mean, t, p)
+}
+
+/// The natural logarithm of the gamma function, using the Lanczos approximation.
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 6] = [
+        76.180_091_729_471_46,
+        -86.505_320_329_416_77,
+        24.014_098_240_830_91,
+        -1.231_739_572_450_155,
+        0.001_208_650_973_866_179,
+        -0.000_005_395_239_384_953,
+    ];
+    let tmp = x + 5.5;
+    let tmp = tmp - (x + 0.5) * tmp.ln();
+    let series = COEFFICIENTS
+        .iter()
+        .enumerate()
+        .fold(1.000_000_000_190_015, |sum, (index, coefficient)| {
+            sum + coefficient / (x + 1. + index as f64)
+        });
+    -tmp + (2.506_628_274_631_000_5 * series / x).ln()
+}
+
+/// The regularized incomplete beta function I_x(a, b), using its continued fraction.
+fn regularized_incomplete_beta(x: f64, a: f64, b: f64) -> f64 {
+    if x <= 0. {
+        return 0.;
+    }
+    if x >= 1. {
+        return 1.;
+    }
+    let front =
+        (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1. - x).ln()).exp();
+    // The continued fraction converges quickly for x < (a + 1) / (a + b + 2)
+    if x < (a + 1.) / (a + b + 2.) {
+        front * beta_continued_fraction(x, a, b) / a
+    } else {
+        1. - front * beta_continued_fraction(1. - x, b, a) / b
+    }
+}
+
+/// Evaluates the continued fraction of the incomplete beta function with the modified Lentz's method.
+fn beta_continued_fraction(x: f64, a: f64, b: f64) -> f64 {
+    const TINY: f64 = 1e-30;
+    let mut c = 1.;
+    let mut d = 1. - (a + b) * x / (a + 1.);
+    d = 1. / if d.abs() < TINY { TINY } else { d };
+    let mut result = d;
+    for m in 1..200 {
+        let m = m as f64;
+        let even = m * (b - m) * x / ((a + 2. * m - 1.) * (a + 2. * m));
+        let odd = -(a + m) * (a + b + m) * x / ((a + 2. * m) * (a + 2. * m + 1.));
+        for coefficient in [even, odd] {
+            d = 1. + coefficient * d;
+            d = 1. / if d.abs() < TINY { TINY } else { d };
+            c = 1. + coefficient / c;
+            if c.abs() < TINY {
+                c = TINY;
+            }
+            result *= d * c;
+        }
+        if (d * c - 1.).abs() < 1e-12 {
+            break;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::tests::{counter_mcts_config, CounterDomain, CounterExecutorState};
+
+    #[test]
+    fn t_test_matches_known_values() {
+        // t = 2.228 with 10 degrees of freedom is the 97.5% quantile
+        let p = regularized_incomplete_beta(10. / (10. + 2.228 * 2.228), 5., 0.5);
+        assert!((p - 0.05).abs() < 1e-3);
+        assert_eq!(paired_t_test(&[0., 0., 0.]), (0., 0., 1.));
+        let (mean, t, p) = paired_t_test(&[1., 2., 3., 4.]);
+        assert_eq!(mean, 2.5);
+        assert!((t - 3.873).abs() < 1e-3);
+        assert!((p - 0.0305).abs() < 1e-3);
+    }
+
+    #[test]
+    fn comparison_finds_the_better_configuration() {
+        let mut shallow_config = counter_mcts_config();
+        shallow_config.depth = 1;
+        shallow_config.visits = 2;
+        let report = compare_runs::<CounterDomain, _, _, _>(
+            &BatchRun::new("shallow", shallow_config),
+            &BatchRun::new("deep", counter_mcts_config()),
+            &[0, 1, 2, 3, 4, 5],
+            20,
+            4,
+            |_| CounterExecutorState(0),
+            |state| *state as f64,
+        );
+        assert_eq!(report.batch.runs.len(), 12);
+        assert_eq!(report.wins_a + report.wins_b + report.ties, 6);
+        assert!(report.mean_score_difference >= 0.);
+        assert!((0. ..=1.).contains(&report.p_value));
+        assert!(report.to_string().contains("deep vs shallow"));
+    }
+}
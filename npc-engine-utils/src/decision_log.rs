@@ -0,0 +1,147 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufWriter, Write},
+    path::Path,
+};
+
+use npc_engine_core::{AgentId, Domain, MCTS};
+use serde::{Deserialize, Serialize};
+
+/// A task considered by a decision but not chosen, see [DecisionRecord].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DecisionAlternative {
+    /// The task, as displayed by its `Debug` implementation
+    pub task: String,
+    /// The q-value of the task for the deciding agent
+    pub value: f32,
+    /// The number of visits of the task
+    pub visits: usize,
+}
+
+/// One decision of an agent, a line of a [DecisionLog].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DecisionRecord {
+    /// The tick of the decision
+    pub tick: u64,
+    /// The deciding agent
+    pub agent: AgentId,
+    /// The chosen task, as displayed by its `Debug` implementation
+    pub task: String,
+    /// The q-value of the chosen task
+    pub value: f32,
+    /// The number of visits of the search
+    pub visits: u32,
+    /// The other expanded tasks, the best first
+    pub alternatives: Vec<DecisionAlternative>,
+}
+
+/// An append-only log of decisions in the JSON Lines format, one [DecisionRecord] per line.
+///
+/// Call [record](Self::record) from [post_mcts_run_hook](crate::ExecutorState::post_mcts_run_hook)
+/// to log all decisions of a session for offline analysis, and read it back with [read_decision_log].
+pub struct DecisionLog<W: Write> {
+    writer: W,
+}
+
+impl DecisionLog<BufWriter<File>> {
+    /// Opens a log file, appending to it if it exists.
+    pub fn append_to(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self::new(BufWriter::new(file)))
+    }
+}
+
+impl<W: Write> DecisionLog<W> {
+    /// Creates a log writing to `writer`.
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    /// Logs the decision of a finished search, does nothing if no task was expanded.
+    ///
+    /// Each record is flushed, so that the log is complete even if the session crashes.
+    pub fn record<D: Domain>(&mut self, mcts: &MCTS<D>) -> io::Result<()> {
+        let explanation = match mcts.explain_best_task() {
+            Some(explanation) => explanation,
+            None => return Ok(()),
+        };
+        let record = DecisionRecord {
+            tick: explanation.tick,
+            agent: explanation.agent,
+            task: format!("{:?}", explanation.chosen.task),
+            value: explanation.chosen.value,
+            visits: mcts.visits_done(),
+            alternatives: explanation
+                .alternatives
+                .iter()
+                .map(|alternative| DecisionAlternative {
+                    task: format!("{:?}", alternative.task),
+                    value: alternative.value,
+                    visits: alternative.visits,
+                })
+                .collect(),
+        };
+        serde_json::to_writer(&mut self.writer, &record)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()
+    }
+
+    /// Consumes the log and returns its writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Reads the records of a decision log, skipping empty lines.
+pub fn read_decision_log(reader: impl BufRead) -> io::Result<Vec<DecisionRecord>> {
+    reader
+        .lines()
+        .filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()))
+        .map(|line| Ok(serde_json::from_str(&line?)?))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::tests::{counter_mcts_config, CounterDomain};
+
+    #[test]
+    fn decisions_are_logged_as_json_lines() {
+        let mut log = DecisionLog::new(Vec::new());
+        let mut mcts = MCTS::<CounterDomain>::new(0, AgentId(1), counter_mcts_config());
+        log.record(&mcts).unwrap();
+        mcts.run();
+        log.record(&mcts).unwrap();
+        let mut mcts = MCTS::<CounterDomain>::new(5, AgentId(1), counter_mcts_config());
+        mcts.run();
+        log.record(&mcts).unwrap();
+
+        let bytes = log.into_inner();
+        assert_eq!(bytes.iter().filter(|byte| **byte == b'\n').count(), 2);
+        let records = read_decision_log(&bytes[..]).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].agent, AgentId(1));
+        assert_eq!(records[0].task, "Add(2)");
+        assert_eq!(records[0].visits, counter_mcts_config().visits);
+        assert_eq!(records[0].alternatives.len(), 1);
+        assert!(records[0].alternatives[0].value < records[0].value);
+
+        let path = std::env::temp_dir().join("npc_engine_decision_log_test.jsonl");
+        let _ = std::fs::remove_file(&path);
+        for _ in 0..2 {
+            DecisionLog::append_to(&path)
+                .unwrap()
+                .record(&mcts)
+                .unwrap();
+        }
+        let records = read_decision_log(io::BufReader::new(File::open(&path).unwrap())).unwrap();
+        assert_eq!(records.len(), 2);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,203 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{
+    collections::{BTreeSet, HashSet},
+    fmt,
+    hash::Hash,
+};
+
+use npc_engine_core::{ActiveTask, AgentId, Context, ContextMut, Node, Task, MCTS};
+
+use crate::{stable_state_hash, ExecutableDomain};
+
+/// A node whose diff disagrees with the state obtained by re-executing its path, see [validate_diffs].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DiffMismatch {
+    /// The tick of the node
+    pub tick: u64,
+    /// The agent of the node
+    pub agent: AgentId,
+    /// The tasks chosen from the root to the node
+    pub path: Vec<String>,
+    /// The hash of the state obtained by re-executing the path
+    pub expected: u64,
+    /// The hash of the initial state with the diff of the node applied
+    pub actual: u64,
+}
+impl fmt::Display for DiffMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "diff of node at T{} ({}) after [{}] gives state hash {:#018x} instead of {:#018x}",
+            self.tick,
+            self.agent,
+            self.path.join(", "),
+            self.actual,
+            self.expected
+        )
+    }
+}
+impl std::error::Error for DiffMismatch {}
+
+/// Re-executes the task finishing after `task` is chosen in `node`, on a full state instead of a diff.
+fn replay_step<D: ExecutableDomain>(node: &Node<D>, task: &dyn Task<D>, state: &mut D::State) {
+    let empty_diff = D::Diff::default();
+    let ctx = Context::with_state_and_diff(node.tick(), state, &empty_diff, node.agent());
+    let mut tasks: BTreeSet<_> = node
+        .active_tasks()
+        .iter()
+        .filter(|active_task| active_task.agent != node.agent())
+        .cloned()
+        .collect();
+    tasks.insert(ActiveTask::new(task.box_clone(), ctx));
+    let next = tasks.into_iter().next().unwrap();
+    let mut diff = D::Diff::default();
+    let ctx = Context::with_state_and_diff(next.end, state, &diff, next.agent);
+    if next.task.is_valid(ctx) {
+        let ctx = ContextMut::with_state_and_diff(next.end, state, &mut diff, next.agent);
+        next.task.execute(ctx);
+    }
+    D::apply_diff(diff, state);
+}
+
+/// Checks the diffs of a search tree by re-deriving the state of every `every`-th node,
+/// replaying the path from the root on a full state and comparing its hash with the one of the diff of the node applied to the initial state.
+///
+/// This catches domains whose tasks read or write diffs inconsistently, or whose [apply_diff](ExecutableDomain::apply_diff) loses changes.
+/// It is expensive and meant for debug builds, for example called from
+/// [post_mcts_run_hook](crate::ExecutorState::post_mcts_run_hook).
+/// Returns the number of checked nodes, or the first mismatch.
+pub fn validate_diffs<D>(mcts: &MCTS<D>, every: usize) -> Result<usize, DiffMismatch>
+where
+    D: ExecutableDomain,
+    D::State: Clone + Hash,
+{
+    assert!(every > 0, "At least every node must be checked");
+    let root = mcts.root_node();
+    let mut seen = HashSet::new();
+    let mut to_visit = vec![(root, mcts.initial_state().clone(), Vec::new())];
+    let mut checked = 0;
+    while let Some((node, state, path)) = to_visit.pop() {
+        if !seen.insert(node.clone()) {
+            continue;
+        }
+        if (seen.len() - 1) % every == 0 {
+            let mut diff_state = mcts.initial_state().clone();
+            D::apply_diff(node.diff().clone(), &mut diff_state);
+            let expected = stable_state_hash(&state);
+            let actual = stable_state_hash(&diff_state);
+            if expected != actual {
+                return Err(DiffMismatch {
+                    tick: node.tick(),
+                    agent: node.agent(),
+                    path,
+                    expected,
+                    actual,
+                });
+            }
+            checked += 1;
+        }
+        if let Some(edges) = mcts.get_edges(&node) {
+            for (task, edge) in edges.expanded_tasks() {
+                let child = edge.lock().unwrap().child();
+                let mut child_state = state.clone();
+                replay_step(&node, &**task, &mut child_state);
+                let mut child_path = path.clone();
+                child_path.push(format!("{task:?}"));
+                to_visit.push((child, child_state, child_path));
+            }
+        }
+    }
+    Ok(checked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::tests::{counter_mcts_config, CounterDomain};
+    use npc_engine_core::{
+        impl_task_boxed_methods, AgentValue, Behavior, Domain, StateDiffRef, TaskDuration,
+    };
+
+    /// A domain whose task forgets the diff when updating the second counter.
+    struct FlawedDomain;
+    impl Domain for FlawedDomain {
+        type State = (u32, u32);
+        type Diff = Option<(u32, u32)>;
+        type DisplayAction = ();
+
+        fn list_behaviors() -> &'static [&'static dyn Behavior<Self>] {
+            &[&FlawedBehavior]
+        }
+
+        fn get_current_value(
+            _tick: u64,
+            state_diff: StateDiffRef<Self>,
+            _agent: AgentId,
+        ) -> AgentValue {
+            let (a, b) = state_diff.diff.unwrap_or(*state_diff.initial_state);
+            AgentValue::new((a + b) as f32).unwrap()
+        }
+
+        fn update_visible_agents(
+            _start_tick: u64,
+            ctx: Context<Self>,
+            agents: &mut BTreeSet<AgentId>,
+        ) {
+            agents.insert(ctx.agent);
+        }
+    }
+
+    #[derive(Copy, Clone, Debug)]
+    struct FlawedBehavior;
+    impl Behavior<FlawedDomain> for FlawedBehavior {
+        fn add_own_tasks(
+            &self,
+            _ctx: Context<FlawedDomain>,
+            tasks: &mut Vec<Box<dyn Task<FlawedDomain>>>,
+        ) {
+            tasks.push(Box::new(IncrementBoth));
+        }
+
+        fn is_valid(&self, _ctx: Context<FlawedDomain>) -> bool {
+            true
+        }
+    }
+
+    #[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+    struct IncrementBoth;
+    impl Task<FlawedDomain> for IncrementBoth {
+        fn duration(&self, _ctx: Context<FlawedDomain>) -> TaskDuration {
+            1
+        }
+        fn execute(&self, ctx: ContextMut<FlawedDomain>) -> Option<Box<dyn Task<FlawedDomain>>> {
+            let (a, _) = ctx.state_diff.diff.unwrap_or(*ctx.state_diff.initial_state);
+            *ctx.state_diff.diff = Some((a + 1, ctx.state_diff.initial_state.1 + 1));
+            None
+        }
+        fn is_valid(&self, _ctx: Context<FlawedDomain>) -> bool {
+            true
+        }
+        fn display_action(&self) {}
+        impl_task_boxed_methods!(FlawedDomain);
+    }
+
+    #[test]
+    fn consistent_diffs_pass_and_flawed_ones_are_caught() {
+        let mut mcts = MCTS::<CounterDomain>::new(0, AgentId(0), counter_mcts_config());
+        mcts.run();
+        assert_eq!(validate_diffs(&mcts, 1), Ok(mcts.node_count()));
+        assert!(validate_diffs(&mcts, 3).unwrap() < mcts.node_count());
+
+        let mut mcts = MCTS::<FlawedDomain>::new((0, 0), AgentId(0), counter_mcts_config());
+        mcts.run();
+        let mismatch = validate_diffs(&mcts, 1).unwrap_err();
+        assert_eq!(mismatch.path.len(), 2);
+        assert!(mismatch
+            .to_string()
+            .contains("IncrementBoth, IncrementBoth"));
+    }
+}
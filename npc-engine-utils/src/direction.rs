@@ -3,7 +3,7 @@
  *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
  */
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::{fmt, marker::PhantomData};
 
 use crate::Coord2D;
@@ -42,7 +42,7 @@ impl YUpDown for YDown {
 ///
 /// Directions can be applied to [Coord2D] through the help of a [DirectionConverter],
 /// which decides whether up is positive or negative y.
-#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, Serialize)]
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub enum Direction {
     Up,
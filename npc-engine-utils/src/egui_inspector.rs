@@ -0,0 +1,200 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::collections::BTreeMap;
+
+use npc_engine_core::{AgentId, Domain, MCTS};
+
+use crate::{export_plan, export_tree_nested, NestedTreeNode, PlanExport, TreeExportOptions};
+
+/// The last recorded search of an agent.
+struct InspectedSearch {
+    tick: u64,
+    tree: NestedTreeNode,
+    plan: PlanExport<String>,
+}
+
+/// An [egui](https://docs.rs/egui) widget showing the search tree, the per-edge statistics and the plan of a selected agent.
+///
+/// Searches are recorded with [record](Self::record), typically from
+/// [post_mcts_run_hook](crate::ExecutorState::post_mcts_run_hook),
+/// and shown by calling [ui](Self::ui) in a debug overlay.
+pub struct PlannerInspector {
+    searches: BTreeMap<AgentId, InspectedSearch>,
+    selected: Option<AgentId>,
+    options: TreeExportOptions,
+    max_plan_steps: usize,
+}
+
+impl Default for PlannerInspector {
+    fn default() -> Self {
+        Self {
+            searches: Default::default(),
+            selected: None,
+            options: TreeExportOptions {
+                max_depth: 4,
+                min_visits: 1,
+            },
+            max_plan_steps: 16,
+        }
+    }
+}
+
+impl PlannerInspector {
+    /// Sets the part of the trees to record, see [TreeExportOptions].
+    pub fn set_export_options(&mut self, options: TreeExportOptions) {
+        self.options = options;
+    }
+
+    /// Sets the maximum number of steps of recorded plans.
+    pub fn set_max_plan_steps(&mut self, max_plan_steps: usize) {
+        self.max_plan_steps = max_plan_steps;
+    }
+
+    /// Records a search, replacing the previous one of its agent.
+    pub fn record<D: Domain>(&mut self, mcts: &MCTS<D>) {
+        let search = InspectedSearch {
+            tick: mcts.start_tick(),
+            tree: export_tree_nested(mcts, &self.options),
+            plan: export_plan(mcts, self.max_plan_steps, |task| format!("{task:?}")),
+        };
+        self.searches.insert(mcts.agent(), search);
+        self.selected.get_or_insert(mcts.agent());
+    }
+
+    /// Forgets the search of an agent, for example when it leaves the simulation.
+    pub fn forget(&mut self, agent: AgentId) {
+        self.searches.remove(&agent);
+        if self.selected == Some(agent) {
+            self.selected = self.searches.keys().next().copied();
+        }
+    }
+
+    /// Returns the agent whose search is shown.
+    pub fn selected(&self) -> Option<AgentId> {
+        self.selected
+    }
+
+    /// Selects the agent whose search is shown.
+    pub fn select(&mut self, agent: AgentId) {
+        self.selected = Some(agent);
+    }
+
+    /// Shows the widget.
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        let selected_text = self
+            .selected
+            .map_or_else(|| "none".to_string(), |agent| agent.to_string());
+        egui::ComboBox::from_label("Agent")
+            .selected_text(selected_text)
+            .show_ui(ui, |ui| {
+                for agent in self.searches.keys() {
+                    ui.selectable_value(&mut self.selected, Some(*agent), agent.to_string());
+                }
+            });
+        let search = match self.selected.and_then(|agent| self.searches.get(&agent)) {
+            Some(search) => search,
+            None => {
+                ui.label("No search recorded");
+                return;
+            }
+        };
+        ui.label(format!(
+            "Search at T{}, {} visits",
+            search.tick, search.tree.stats.visits
+        ));
+        egui::CollapsingHeader::new("Plan")
+            .default_open(true)
+            .show(ui, |ui| plan_ui(ui, &search.plan));
+        egui::CollapsingHeader::new("Tree")
+            .default_open(true)
+            .show(ui, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    tree_ui(ui, &search.tree, &mut vec![0]);
+                })
+            });
+    }
+}
+
+fn format_value(value: Option<f32>) -> String {
+    value.map_or_else(|| "-".to_string(), |value| format!("{value:.3}"))
+}
+
+fn plan_ui(ui: &mut egui::Ui, plan: &PlanExport<String>) {
+    egui::Grid::new("planner_inspector_plan")
+        .striped(true)
+        .show(ui, |ui| {
+            for header in ["Tick", "Agent", "Task", "Duration", "Visits", "Value"] {
+                ui.strong(header);
+            }
+            ui.end_row();
+            for step in &plan.steps {
+                ui.label(format!("T{}", step.tick));
+                ui.label(step.agent.to_string());
+                ui.label(&step.task);
+                ui.label(step.duration.to_string());
+                ui.label(step.visits.to_string());
+                ui.label(format_value(Some(step.expected_value)));
+                ui.end_row();
+            }
+        });
+}
+
+/// Shows a node and its children, `path` giving a unique id to each node.
+fn tree_ui(ui: &mut egui::Ui, node: &NestedTreeNode, path: &mut Vec<usize>) {
+    let stats = &node.stats;
+    let text = format!(
+        "{} — {} T{}, N {}, Q {}, V {}",
+        stats.task.as_deref().unwrap_or("root"),
+        stats.agent,
+        stats.tick,
+        stats.visits,
+        format_value(stats.task_value),
+        format_value(stats.value)
+    );
+    if node.children.is_empty() {
+        ui.label(text);
+        return;
+    }
+    egui::CollapsingHeader::new(text)
+        .id_salt(&*path)
+        .default_open(path.len() == 1)
+        .show(ui, |ui| {
+            for (index, child) in node.children.iter().enumerate() {
+                path.push(index);
+                tree_ui(ui, child, path);
+                path.pop();
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::tests::{counter_mcts_config, CounterDomain};
+
+    #[test]
+    fn inspector_shows_recorded_searches() {
+        let mut inspector = PlannerInspector::default();
+        let ctx = egui::Context::default();
+        let show = |inspector: &mut PlannerInspector| {
+            let _ = ctx.run(egui::RawInput::default(), |ctx| {
+                egui::CentralPanel::default().show(ctx, |ui| inspector.ui(ui));
+            });
+        };
+        show(&mut inspector);
+        assert_eq!(inspector.selected(), None);
+
+        let mut mcts = MCTS::<CounterDomain>::new(0, AgentId(3), counter_mcts_config());
+        mcts.run();
+        inspector.record(&mcts);
+        assert_eq!(inspector.selected(), Some(AgentId(3)));
+        show(&mut inspector);
+        show(&mut inspector);
+
+        inspector.forget(AgentId(3));
+        assert_eq!(inspector.selected(), None);
+    }
+}
@@ -6,12 +6,27 @@
 use ansi_term::Style;
 use npc_engine_core::{
     ActiveTask, ActiveTasks, AgentId, Context, ContextMut, DefaultPolicyEstimator, Domain,
-    DomainWithPlanningTask, EarlyStopCondition, IdleTask, MCTSConfiguration, PlanningTask,
-    StateDiffRef, StateValueEstimator, Task, MCTS,
+    IdleTask, MCTSConfiguration, PlanningTask, StateDiffRef, StateValueEstimator, Task, MCTS,
 };
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, BTreeSet, HashMap},
     hash::Hash,
+};
+
+use crate::{GlobalDomain, PlanningMetrics};
+
+// The threaded executor is not available in browsers, where threads cannot be spawned
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{
+    executor_core::{impl_executor_methods, ExecutorCore, Planners},
+    ExecutorSnapshot, SnapshotDomain, TuningHandle,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use npc_engine_core::{DomainWithPlanningTask, EarlyStopCondition};
+#[cfg(feature = "rayon")]
+use std::sync::mpsc;
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
     sync::{
         atomic::{AtomicU64, Ordering},
         Arc,
@@ -20,8 +35,6 @@ use std::{
     time::Duration,
 };
 
-use crate::GlobalDomain;
-
 fn highlight_style() -> Style {
     ansi_term::Style::new().bold().fg(ansi_term::Colour::Green)
 }
@@ -89,6 +102,23 @@ pub trait ExecutorState<D: Domain> {
     }
     /// Method called after MCTS has run, to perform tasks such as printing the search tree (by default does nothing).
     fn post_mcts_run_hook(&mut self, _mcts: &MCTS<D>, _last_active_task: &ActiveTask<D>) {}
+    /// Returns whether the planning of an agent starting at a given tick profiles the phases of its search,
+    /// reported in [PlanningMetrics::profile] (by default returns false).
+    fn profile_planning(&self, _tick: u64, _agent: AgentId) -> bool {
+        false
+    }
+    /// Method called when planning is finished, with its metrics (by default does nothing).
+    fn on_planning_metrics(&mut self, _metrics: &PlanningMetrics<D>) {}
+    /// Method called when an agent starts planning, at a given tick (by default does nothing).
+    fn on_plan_started(&mut self, _tick: u64, _agent: AgentId) {}
+    /// Method called when planning has chosen a task for an agent, after it was queued (by default does nothing).
+    fn on_task_chosen(&mut self, _active_task: &ActiveTask<D>) {}
+    /// Method called when a task was successfully executed (by default does nothing).
+    fn on_task_completed(&mut self, _active_task: &ActiveTask<D>) {}
+    /// Method called when a task could not be executed because it was not valid any more (by default does nothing).
+    fn on_task_failed(&mut self, _active_task: &ActiveTask<D>) {}
+    /// Method called when planning found nothing to do for an agent, which will then idle (by default does nothing).
+    fn on_agent_idle(&mut self, _tick: u64, _agent: AgentId) {}
 }
 
 /// User-defined methods for the executor,
@@ -131,8 +161,45 @@ pub trait ExecutorStateGlobal<D: GlobalDomain> {
     fn keep_execution(&self, _tick: u64, _queue: &ActiveTasks<D>, _state: &D::GlobalState) -> bool {
         true
     }
+    /// Returns whether an ongoing task, which is still valid, should be continued, when task monitoring is enabled (by default returns true).
+    ///
+    /// This allows to abort tasks whose justification disappeared, for example because their expected value dropped.
+    fn keep_task(&self, _tick: u64, _state: &D::GlobalState, _active_task: &ActiveTask<D>) -> bool {
+        true
+    }
     /// Method called from [ThreadedExecutor::step] after all tasks have been executed at a given step (by default does nothing).
     fn post_step_hook(&mut self, _tick: u64, _state: &mut D::GlobalState) {}
+    /// Returns the number of visits the planning of an agent starting at a given tick aims at (by default returns `visits`, the one of the MCTS configuration).
+    ///
+    /// This allows to give different iteration budgets to different agents, for example to plan less for agents far away from the player.
+    fn planning_visits(
+        &self,
+        _tick: u64,
+        _state: &D::GlobalState,
+        _agent: AgentId,
+        visits: u32,
+    ) -> u32 {
+        visits
+    }
+    /// Returns whether two tasks conflict, when coordination is enabled (by default returns false).
+    ///
+    /// For example, two agents harvesting the same resource. When a newly planned task conflicts with another one,
+    /// the agent having the lowest [priority](Self::agent_priority) plans again.
+    fn tasks_conflict(
+        &self,
+        _tick: u64,
+        _state: &D::GlobalState,
+        _first: &ActiveTask<D>,
+        _second: &ActiveTask<D>,
+    ) -> bool {
+        false
+    }
+    /// Returns the priority of an agent when resolving conflicting tasks (by default returns 0).
+    ///
+    /// With equal priorities, the task planned last is dropped, and amongst newly planned ones, the one of the agent with the highest id.
+    fn agent_priority(&self, _tick: u64, _state: &D::GlobalState, _agent: AgentId) -> i32 {
+        0
+    }
 }
 
 /// The state of tasks undergoing execution.
@@ -169,6 +236,11 @@ where
         active_task
     }
 
+    /// Removes a task from the queue, returns whether it was present.
+    pub fn remove_task(&mut self, active_task: &ActiveTask<D>) -> bool {
+        self.task_queue.remove(active_task)
+    }
+
     pub fn execute_task<S, C>(
         &mut self,
         active_task: &ActiveTask<D>,
@@ -224,9 +296,11 @@ where
                 active_task,
                 &mut self.task_queue,
             );
+            executor_state.on_task_completed(active_task);
             (diff, new_task)
         } else {
             log::info!("Invalid task!");
+            executor_state.on_task_failed(active_task);
             (diff, None)
         }
     }
@@ -254,11 +328,120 @@ where
         new_active_task
     }
 
+    /// Returns whether an active task is still valid at a given tick in a given state.
+    pub fn is_task_still_valid(tick: u64, active_task: &ActiveTask<D>, state: &D::State) -> bool {
+        let diff = D::Diff::default();
+        let ctx = Context::with_state_and_diff(tick, state, &diff, active_task.agent);
+        active_task.task.is_valid(ctx)
+    }
+
+    /// Removes the active task of an agent from the queue, if any, and returns it.
+    pub fn remove_agent_task(&mut self, agent: AgentId) -> Option<ActiveTask<D>> {
+        let active_task = self
+            .task_queue
+            .iter()
+            .find(|active_task| active_task.agent == agent)?
+            .clone();
+        self.task_queue.remove(&active_task);
+        Some(active_task)
+    }
+
+    /// Queues a task chosen by planning, and notifies the executor state.
+    pub fn queue_chosen_task<S>(
+        &mut self,
+        tick: u64,
+        active_agent: AgentId,
+        new_task: Box<dyn Task<D>>,
+        state: &D::State,
+        executor_state: &mut S,
+    ) -> ActiveTask<D>
+    where
+        S: ExecutorState<D>,
+    {
+        let is_idle = new_task.downcast_ref::<IdleTask>().is_some();
+        let new_active_task = self.queue_task(tick, active_agent, new_task, state);
+        if is_idle {
+            executor_state.on_agent_idle(tick, active_agent);
+        }
+        executor_state.on_task_chosen(&new_active_task);
+        new_active_task
+    }
+
     /// Get the active task, read only.
     pub fn task_queue(&self) -> &ActiveTasks<D> {
         &self.task_queue
     }
 }
+impl<D> ExecutionQueue<D>
+where
+    D: GlobalDomain,
+{
+    /// Returns the agents that must plan again so that the tasks just planned by `planned_agents` do not conflict with other tasks.
+    ///
+    /// Conflicts are detected by [ExecutorStateGlobal::tasks_conflict] and resolved using [ExecutorStateGlobal::agent_priority].
+    pub fn conflicting_agents<S>(
+        &self,
+        tick: u64,
+        planned_agents: &BTreeSet<AgentId>,
+        state: &D::GlobalState,
+        executor_state: &S,
+    ) -> BTreeSet<AgentId>
+    where
+        S: ExecutorStateGlobal<D>,
+    {
+        let tasks: Vec<_> = self
+            .task_queue
+            .iter()
+            .filter(|task| task.task.downcast_ref::<PlanningTask>().is_none())
+            .collect();
+        let mut losers = BTreeSet::new();
+        let is_new = |task: &ActiveTask<D>| planned_agents.contains(&task.agent);
+        for new_task in tasks.iter().filter(|task| is_new(task)) {
+            for other_task in &tasks {
+                if losers.contains(&new_task.agent) {
+                    break;
+                }
+                let other_is_new = is_new(other_task);
+                // Each pair of new tasks is only considered once
+                if other_task.agent == new_task.agent
+                    || losers.contains(&other_task.agent)
+                    || (other_is_new && other_task.agent < new_task.agent)
+                    || !executor_state.tasks_conflict(tick, state, new_task, other_task)
+                {
+                    continue;
+                }
+                let new_priority = executor_state.agent_priority(tick, state, new_task.agent);
+                let other_priority = executor_state.agent_priority(tick, state, other_task.agent);
+                // If both tasks are new, the other agent has the higher id, so it loses on equal priorities
+                let loser = if other_priority < new_priority
+                    || (other_priority == new_priority && other_is_new)
+                {
+                    other_task.agent
+                } else {
+                    new_task.agent
+                };
+                losers.insert(loser);
+            }
+        }
+        losers
+    }
+}
+
+/// Returns the last tasks chosen by planning for agents other than `agent`, to be used as their intentions.
+pub(crate) fn intentions_from_history<D: Domain>(
+    task_history: &HashMap<AgentId, ActiveTask<D>>,
+    agent: AgentId,
+) -> BTreeMap<AgentId, Box<dyn Task<D>>> {
+    task_history
+        .values()
+        .filter(|active_task| {
+            active_task.agent != agent
+                && active_task.task.downcast_ref::<IdleTask>().is_none()
+                && active_task.task.downcast_ref::<PlanningTask>().is_none()
+        })
+        .map(|active_task| (active_task.agent, active_task.task.clone()))
+        .collect()
+}
 
 /// A single-threaded generic executor.
 ///
@@ -302,8 +485,23 @@ where
 
     /// Executes one task, returns whether there are still tasks in the queue.
     pub fn step(&mut self) -> bool {
+        match self.step_until_decision() {
+            DebugStep::Finished => false,
+            DebugStep::Executed => true,
+            DebugStep::Decision(decision) => {
+                self.commit_decision(*decision);
+                true
+            }
+        }
+    }
+
+    /// Executes one task, and if its agent needs to plan, plans but does not commit the chosen task.
+    ///
+    /// This allows to debug decisions: the returned [PendingDecision] can be inspected,
+    /// its task can be overridden, and it must then be passed to [commit_decision](Self::commit_decision).
+    pub fn step_until_decision(&mut self) -> DebugStep<D> {
         if self.queue.is_empty() {
-            return false;
+            return DebugStep::Finished;
         }
 
         // Pop first task that is completed
@@ -316,7 +514,7 @@ where
             .executor_state
             .keep_agent(tick, &self.state, active_agent)
         {
-            return true;
+            return DebugStep::Executed;
         }
 
         // Execute the task and queue the new agents
@@ -325,24 +523,66 @@ where
                 .execute_task(&active_task, &self.state, self.executor_state, |_| {});
         D::apply_diff(diff, &mut self.state);
 
-        // If no next task, plan and get the task for this agent
-        let new_task = new_task.unwrap_or_else(|| {
-            log::info!("No subsequent task, planning!");
-            let mut mcts = self.new_mcts(tick, active_agent);
-            let new_task = mcts.run().unwrap_or_else(|| Box::new(IdleTask));
-            self.executor_state.post_mcts_run_hook(&mcts, &active_task);
-            new_task
-        });
+        // If there is a next task, add it to queue
+        if let Some(new_task) = new_task {
+            self.queue
+                .queue_task(tick, active_agent, new_task, &self.state);
+            return DebugStep::Executed;
+        }
 
-        // Add new task to queue
-        self.queue
-            .queue_task(tick, active_agent, new_task, &self.state);
+        // Otherwise plan
+        log::info!("No subsequent task, planning!");
+        self.executor_state.on_plan_started(tick, active_agent);
+        let mut mcts = self.new_mcts(tick, active_agent);
+        let task = mcts.run().unwrap_or_else(|| Box::new(IdleTask));
+        self.executor_state.post_mcts_run_hook(&mcts, &active_task);
+        DebugStep::Decision(Box::new(PendingDecision {
+            tick,
+            agent: active_agent,
+            mcts,
+            task,
+        }))
+    }
 
-        true
+    /// Adds the task of a decision returned by [step_until_decision](Self::step_until_decision) to the queue.
+    pub fn commit_decision(&mut self, decision: PendingDecision<D>) {
+        self.executor_state
+            .on_planning_metrics(&PlanningMetrics::new(
+                decision.tick,
+                &decision.mcts,
+                decision.task.clone(),
+            ));
+        self.queue.queue_chosen_task(
+            decision.tick,
+            decision.agent,
+            decision.task,
+            &self.state,
+            self.executor_state,
+        );
+    }
+
+    /// Gets the current state, read-only.
+    pub fn state(&self) -> &D::State {
+        &self.state
+    }
+
+    /// Gets the active tasks, read-only.
+    pub fn active_tasks(&self) -> &ActiveTasks<D> {
+        self.queue.task_queue()
+    }
+
+    /// Gets the MCTS configuration of new plannings.
+    pub fn mcts_config(&self) -> &MCTSConfiguration {
+        &self.mcts_config
+    }
+
+    /// Sets the MCTS configuration of new plannings.
+    pub fn set_mcts_config(&mut self, mcts_config: MCTSConfiguration) {
+        self.mcts_config = mcts_config;
     }
 
     fn new_mcts(&self, tick: u64, active_agent: AgentId) -> MCTS<D> {
-        MCTS::<D>::new_with_tasks(
+        let mut mcts = MCTS::<D>::new_with_tasks(
             self.state.clone(),
             active_agent,
             tick,
@@ -350,7 +590,73 @@ where
             self.mcts_config.clone(),
             self.executor_state.create_state_value_estimator(),
             None,
-        )
+        );
+        mcts.set_profiling(self.executor_state.profile_planning(tick, active_agent));
+        mcts
+    }
+}
+
+/// The outcome of [SimpleExecutor::step_until_decision].
+pub enum DebugStep<D: Domain> {
+    /// There are no more tasks to execute
+    Finished,
+    /// A task was executed and no planning was needed
+    Executed,
+    /// A task was executed and its agent planned, the decision must be committed
+    Decision(Box<PendingDecision<D>>),
+}
+
+/// A choice of task by the planner, not yet committed to the execution queue.
+pub struct PendingDecision<D: Domain> {
+    tick: u64,
+    agent: AgentId,
+    mcts: MCTS<D>,
+    task: Box<dyn Task<D>>,
+}
+impl<D: Domain> PendingDecision<D> {
+    /// The tick at which the decision was taken.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// The agent that took the decision.
+    pub fn agent(&self) -> AgentId {
+        self.agent
+    }
+
+    /// The search tree that led to the decision.
+    pub fn mcts(&self) -> &MCTS<D> {
+        &self.mcts
+    }
+
+    /// The task that will be committed.
+    pub fn task(&self) -> &dyn Task<D> {
+        &*self.task
+    }
+
+    /// Replaces the task that will be committed.
+    pub fn override_task(&mut self, task: Box<dyn Task<D>>) {
+        self.task = task;
+    }
+
+    /// Returns the tasks explored at the root of the search tree, with their visit count and
+    /// the q-value of the deciding agent, sorted by decreasing visits.
+    pub fn options(&self) -> Vec<(Box<dyn Task<D>>, usize, f32)> {
+        let root = self.mcts.root_node();
+        let edges = self.mcts.get_edges(&root).unwrap();
+        let mut options: Vec<_> = edges
+            .expanded_tasks()
+            .map(|(task, edge)| {
+                let edge = edge.lock().unwrap();
+                (task.clone(), edge.visits(), edge.q_value(self.agent))
+            })
+            .collect();
+        options.sort_by(|(a_task, a_visits, _), (b_task, b_visits, _)| {
+            b_visits
+                .cmp(a_visits)
+                .then_with(|| format!("{a_task:?}").cmp(&format!("{b_task:?}")))
+        });
+        options
     }
 }
 
@@ -370,13 +676,141 @@ where
     }
 }
 
+/// A planning running in the background.
+#[cfg(not(target_arch = "wasm32"))]
+enum PlanningHandle<D: Domain> {
+    /// Planning in a dedicated thread
+    Thread(JoinHandle<MCTS<D>>),
+    /// Planning in a shared thread pool, result delivered through a channel
+    #[cfg(feature = "rayon")]
+    Pool(mpsc::Receiver<MCTS<D>>),
+}
+#[cfg(not(target_arch = "wasm32"))]
+impl<D: Domain> PlanningHandle<D> {
+    /// Blocks until planning is done, returns None if planning panicked.
+    fn join(self) -> Option<MCTS<D>> {
+        match self {
+            PlanningHandle::Thread(handle) => handle.join().ok(),
+            #[cfg(feature = "rayon")]
+            PlanningHandle::Pool(receiver) => receiver.recv().ok(),
+        }
+    }
+}
+
+/// The plannings of a [ThreadedExecutor], each running in a thread.
+#[cfg(not(target_arch = "wasm32"))]
+struct ThreadedPlanners<D: Domain> {
+    /// Stores all ongoing plannings
+    threads: HashMap<AgentId, PlanningHandle<D>>,
+    /// The global tick of the simulation, shared with the plannings to stop them early
+    tick: Arc<AtomicU64>,
+    /// The thread pool to plan on, if any
+    #[cfg(feature = "rayon")]
+    thread_pool: Option<Arc<rayon::ThreadPool>>,
+}
+#[cfg(not(target_arch = "wasm32"))]
+impl<D> ThreadedPlanners<D>
+where
+    D: Domain,
+    D::State: Send,
+    D::Diff: Send + Sync,
+{
+    fn new(tick: u64) -> Self {
+        Self {
+            threads: Default::default(),
+            tick: Arc::new(AtomicU64::new(tick)),
+            #[cfg(feature = "rayon")]
+            thread_pool: None,
+        }
+    }
+
+    /// Runs a planning in the background, either in the thread pool or in a new thread.
+    fn spawn_planning<F>(&self, agent: AgentId, planning: F) -> PlanningHandle<D>
+    where
+        F: FnOnce() -> MCTS<D> + Send + 'static,
+    {
+        #[cfg(feature = "rayon")]
+        if let Some(thread_pool) = &self.thread_pool {
+            let (sender, receiver) = mpsc::channel();
+            thread_pool.spawn(move || {
+                // The receiver is gone if the executor was dropped, the result is not needed then
+                let _ = sender.send(planning());
+            });
+            return PlanningHandle::Pool(receiver);
+        }
+        let handle = thread::Builder::new()
+            .name(format!("plan-{}", agent.0))
+            .spawn(planning)
+            .unwrap();
+        PlanningHandle::Thread(handle)
+    }
+}
+#[cfg(not(target_arch = "wasm32"))]
+impl<D, S> Planners<D, S> for ThreadedPlanners<D>
+where
+    D: DomainWithPlanningTask + GlobalDomain,
+    D::State: Clone + Send,
+    D::Diff: Send + Sync,
+    S: ExecutorState<D> + ExecutorStateGlobal<D>,
+{
+    fn start(
+        &mut self,
+        core: &mut ExecutorCore<'_, D, S>,
+        tick: u64,
+        active_agent: AgentId,
+        end_tick: u64,
+    ) {
+        core.executor_state.on_plan_started(tick, active_agent);
+        let tick_atomic = self.tick.clone();
+        let early_stop_condition: Option<Box<EarlyStopCondition>> = Some(Box::new(move |visits| {
+            visits >= S::MINIMUM_VISITS && tick_atomic.load(Ordering::Relaxed) >= end_tick - 1
+        }));
+        let mut mcts = core.new_mcts(tick, active_agent, early_stop_condition);
+        if log::log_enabled!(log::Level::Info) {
+            log::info!(
+                "{} - {} starts planning until {}.",
+                highlight_tick(tick),
+                active_agent,
+                highlight_tick(end_tick)
+            );
+            log::trace!("Active Tasks:");
+            for active_task in core.queue.task_queue() {
+                log::trace!(
+                    "{}: {} {:?}",
+                    active_task.agent,
+                    highlight_tick(active_task.end),
+                    active_task.task
+                );
+            }
+        }
+        let handle = self.spawn_planning(active_agent, move || {
+            // Initialize MCTS instance for planning
+            // We update it outside the planning thread such that we don't need to pass the state into the thread
+            mcts.run();
+            mcts
+        });
+        self.threads.insert(active_agent, handle);
+    }
+
+    fn drop_planning(&mut self, agent: AgentId) {
+        self.threads.remove(&agent);
+    }
+}
+
 /// A multi-threaded generic executor.
 ///
 /// It maintains a [D::GlobalState](GlobalDomain::GlobalState) out of which a
 /// [D::State](Domain::State) is [derived](GlobalDomain::derive_local_state) for planning.
 /// This allows to simulate a large world with many agents, each of them planning on a small
 /// subset of that world.
+/// By default, each planning runs in its own thread.
+/// With feature `rayon`, [new_with_thread_pool](Self::new_with_thread_pool) creates an executor
+/// that plans all agents concurrently on a shared thread pool instead, which scales to many agents.
+/// In both cases, the number of visits per agent can be set through [ExecutorStateGlobal::planning_visits].
+/// Each agent can also be given its own level of detail, see [set_planning_detail](Self::set_planning_detail).
 /// The helper function [run_threaded_executor] can create and run it for you.
+/// It is not available in browsers (`wasm32`), where threads cannot be spawned.
+#[cfg(not(target_arch = "wasm32"))]
 pub struct ThreadedExecutor<'a, D, S>
 where
     D: DomainWithPlanningTask + GlobalDomain,
@@ -384,24 +818,14 @@ where
     D::Diff: Send + Sync,
     S: ExecutorState<D> + ExecutorStateGlobal<D>,
 {
-    /// The attached MCTS configuration
-    mcts_config: MCTSConfiguration,
-    /// The state of this executor
-    executor_state: &'a mut S,
-    /// The current state of the world
-    state: D::GlobalState,
-    /// The current queue of tasks
-    queue: ExecutionQueue<D>,
-    /// The last tasks which were executed
-    /// Assuming that planning tasks all take the same time, we need to only
-    /// keep one ActiveTask per AgentId, because after the first task choice,
-    /// either it is planning or it is a forced task, which we do not put in the history.
-    task_history: HashMap<AgentId, ActiveTask<D>>,
-    /// Stores all planning threads
-    threads: HashMap<AgentId, JoinHandle<MCTS<D>>>,
-    /// The global tick of the simulation
-    tick: Arc<AtomicU64>,
+    /// The state and logic shared with the other executors
+    core: ExecutorCore<'a, D, S>,
+    /// Stores all ongoing plannings
+    planners: ThreadedPlanners<D>,
+    /// Whether execution is paused
+    paused: bool,
 }
+#[cfg(not(target_arch = "wasm32"))]
 impl<'a, D, S> ThreadedExecutor<'a, D, S>
 where
     D: DomainWithPlanningTask + GlobalDomain,
@@ -411,61 +835,64 @@ where
 {
     /// Creates a new executor, initializes state and task queue from the S trait.
     pub fn new(mcts_config: MCTSConfiguration, executor_state: &'a mut S) -> Self {
-        let state = executor_state.create_initial_state();
-        let task_queue = executor_state.init_task_queue(&state);
-        let task_history = task_queue
-            .iter()
-            .map(|active_task| (active_task.agent, active_task.clone()))
-            .collect();
-        let queue = ExecutionQueue::new(task_queue);
         Self {
-            mcts_config,
-            state,
-            queue,
-            task_history,
-            threads: Default::default(),
-            tick: Arc::new(AtomicU64::new(0)),
-            executor_state,
+            core: ExecutorCore::new(mcts_config, executor_state),
+            planners: ThreadedPlanners::new(0),
+            paused: false,
         }
     }
 
-    fn new_mcts(&self, tick: u64, active_agent: AgentId) -> MCTS<D> {
-        let planning_task_duration = self
-            .mcts_config
-            .planning_task_duration
-            .expect("Planning task must have non-zero duration for threaded executor");
-        let tick_atomic = self.tick.clone();
-        let early_stop_condition: Option<Box<EarlyStopCondition>> = Some(Box::new(move |visits| {
-            visits >= S::MINIMUM_VISITS
-                && tick_atomic.load(Ordering::Relaxed) >= tick + planning_task_duration.get() - 1
-        }));
-        MCTS::<D>::new_with_tasks(
-            D::derive_local_state(&self.state, active_agent),
-            active_agent,
-            tick,
-            self.queue.task_queue.clone(),
-            self.mcts_config.clone(),
-            self.executor_state.create_state_value_estimator(),
-            early_stop_condition,
-        )
+    /// Creates a new executor planning on a shared thread pool, initializes state and task queue from the S trait.
+    #[cfg(feature = "rayon")]
+    pub fn new_with_thread_pool(
+        mcts_config: MCTSConfiguration,
+        executor_state: &'a mut S,
+        thread_pool: Arc<rayon::ThreadPool>,
+    ) -> Self {
+        let mut executor = Self::new(mcts_config, executor_state);
+        executor.planners.thread_pool = Some(thread_pool);
+        executor
+    }
+
+    /// Creates a new executor from a snapshot taken by [snapshot](Self::snapshot), restarting ongoing plannings.
+    pub fn from_snapshot(
+        mcts_config: MCTSConfiguration,
+        executor_state: &'a mut S,
+        snapshot: ExecutorSnapshot<D::GlobalState, D::TaskSnapshot>,
+    ) -> Self
+    where
+        D: SnapshotDomain,
+    {
+        let (tick, state, task_queue, task_history) = snapshot.restore();
+        let mut executor = Self {
+            core: ExecutorCore::with_tasks(
+                mcts_config,
+                executor_state,
+                state,
+                task_queue,
+                task_history,
+            ),
+            planners: ThreadedPlanners::new(tick),
+            paused: false,
+        };
+        executor
+            .core
+            .restart_plannings(tick, &mut executor.planners);
+        executor
     }
 
     /// Blocks on all planning threads which should have finished in the current tick and adds the
     /// resulting best tasks to the `active_tasks`.
-    fn block_on_planning(&mut self, tick: u64) {
+    ///
+    /// Returns the agents which committed a plan.
+    fn block_on_planning(&mut self, tick: u64) -> BTreeSet<AgentId> {
+        let mut planned_agents = BTreeSet::new();
         // Iterate over all planning tasks that should have finished by now
-        let active_tasks = self.queue.task_queue.clone();
-        for active_task in active_tasks
-            .iter()
-            .filter(|task| task.end <= tick && task.task.downcast_ref::<PlanningTask>().is_some())
-        {
+        for active_task in self.core.due_planning_tasks(tick) {
             let active_agent = active_task.agent;
-            debug_assert!(active_task.end == tick,
-                "Processing an active planning task at tick {tick} but it should have been processed at tick {}.", active_task.end
-            );
 
             // Try to get the planning thread of the current agent
-            let thread = self.threads.remove(&active_agent);
+            let thread = self.planners.threads.remove(&active_agent);
             assert!(thread.is_some(),
                 "There is no planning thread for {active_agent} even though there is an active_task for it."
             );
@@ -474,168 +901,123 @@ where
             // Block on it to retrieve the result
             let mcts = thread.join();
             assert!(
-                mcts.is_ok(),
+                mcts.is_some(),
                 "Could not join planning thread of {active_agent}! Probably it panicked!"
             );
             let mcts = mcts.unwrap();
-            self.executor_state.post_mcts_run_hook(&mcts, active_task);
 
             // Override the planning task in active_tasks with the best_task we got from the planning
-            if log::log_enabled!(log::Level::Info) {
-                log::info!(
-                    "{} - {} finished planning. Looking for best task...",
-                    highlight_tick(tick),
-                    highlight_agent(active_agent)
-                );
-            }
-            let best_task = mcts.best_task_with_history(&self.task_history);
-            log::info!("Best Task: {best_task:?}");
-
-            self.queue.task_queue.remove(active_task);
-            let local_state = D::derive_local_state(&self.state, active_agent);
-            let new_active_task =
-                self.queue
-                    .queue_task(tick, active_agent, best_task.clone(), &local_state);
-            self.task_history.insert(active_agent, new_active_task);
+            self.core.commit_planning(tick, &active_task, &mcts);
+            planned_agents.insert(active_agent);
         }
+        planned_agents
     }
 
-    /// Executes all task which are due at the current game tick and starts new planning threads for those agents.
-    fn execute_finished_tasks(&mut self, tick: u64) {
-        let active_tasks = self.queue.task_queue.clone();
-        for active_task in active_tasks.iter().filter(|task| task.end <= tick) {
-            // Pop task as it is completed
-            self.queue.task_queue.remove(active_task);
-            let active_agent = active_task.agent;
-            debug_assert!(
-                active_task.end == tick,
-                "Processing an active task at tick {tick} but it ended at tick {}.",
-                active_task.end
-            );
-
-            // Should we continue considering that agent?
-            if !self
-                .executor_state
-                .keep_agent(tick, &self.state, active_agent)
-            {
-                continue;
-            }
-
-            // Execute the task, queue the new agents
-            let local_state = D::derive_local_state(&self.state, active_agent);
-            let (diff, new_task) = self.queue.execute_task(
-                active_task,
-                &local_state,
-                self.executor_state,
-                |new_agents_tasks| {
-                    for new_task in new_agents_tasks.iter() {
-                        debug_assert!(!self.task_history.contains_key(&new_task.agent));
-                        self.task_history.insert(new_task.agent, new_task.clone());
-                    }
-                },
-            );
-            D::apply(&mut self.state, &local_state, &diff);
-            let local_state = D::derive_local_state(&self.state, active_agent);
-
-            // If no next task, spawn a plan task and an associated thread
-            let new_task = new_task.unwrap_or_else(|| {
-                Box::new(PlanningTask(
-                    self.mcts_config.planning_task_duration.unwrap(),
-                ))
-            });
+    impl_executor_methods!();
 
-            // Add new task to queue
-            let end_tick = self
-                .queue
-                .queue_task(tick, active_agent, new_task.clone(), &local_state)
-                .end;
-
-            // Deploy new planning thread for this agent if needed
-            if new_task.downcast_ref::<PlanningTask>().is_some() {
-                let mut mcts = self.new_mcts(tick, active_agent);
-                if log::log_enabled!(log::Level::Info) {
-                    log::info!(
-                        "{} - {} starts planning until {}.",
-                        highlight_tick(tick),
-                        active_agent,
-                        highlight_tick(end_tick)
-                    );
-                    log::trace!("Active Tasks:");
-                    for active_task in &self.queue.task_queue {
-                        log::trace!(
-                            "{}: {} {:?}",
-                            active_task.agent,
-                            highlight_tick(active_task.end),
-                            active_task.task
-                        );
-                    }
-                }
-                let handle = thread::Builder::new()
-                    .name(format!("plan-{}", active_agent.0))
-                    .spawn(move || {
-                        // Initialize MCTS instance for planning
-                        // We update it outside the planning thread such that we don't need to pass the state into the thread
-                        mcts.run();
-                        mcts
-                    })
-                    .unwrap();
-                self.threads.insert(active_task.agent, handle);
-            }
-        }
+    /// Returns a handle to change the MCTS configuration while the executor runs, see [TuningHandle].
+    pub fn tuning_handle(&mut self) -> TuningHandle {
+        self.core.tuning_handle(None, None)
     }
 
     /// Executes all tasks finishing at the current tick and then increments it.
     ///
     /// Returns whether execution should continue.
     pub fn step(&mut self) -> bool {
-        if self.queue.is_empty() {
+        if self.core.queue.is_empty() {
             return false;
         }
+        if self.paused {
+            return true;
+        }
 
-        let tick = self.tick.load(Ordering::Relaxed);
-        if !self
-            .executor_state
-            .keep_execution(tick, &self.queue.task_queue, &self.state)
-        {
+        self.core.apply_tuning();
+        let tick = self.planners.tick.load(Ordering::Relaxed);
+        if !self.core.executor_state.keep_execution(
+            tick,
+            &self.core.queue.task_queue,
+            &self.core.state,
+        ) {
             return false;
         }
-        self.block_on_planning(tick);
-        self.execute_finished_tasks(tick);
-        self.executor_state.post_step_hook(tick, &mut self.state);
+        self.core.monitor_tasks(tick);
+        self.core.replan_dirty_agents(tick, &mut self.planners);
+        let planned_agents = self.block_on_planning(tick);
+        self.core.coordinate_plans(tick, &planned_agents);
+        self.core.replan_dirty_agents(tick, &mut self.planners);
+        self.core.execute_finished_tasks(tick, &mut self.planners);
+        self.core
+            .executor_state
+            .post_step_hook(tick, &mut self.core.state);
 
-        self.tick.fetch_add(1, Ordering::Relaxed);
+        self.planners.tick.fetch_add(1, Ordering::Relaxed);
         true
     }
 
+    /// Pauses execution: until [resume](Self::resume) is called, [step](Self::step) does nothing.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Resumes execution after [pause](Self::pause).
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    /// Returns whether execution is paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Takes a serializable snapshot of the execution, to be restored with [from_snapshot](Self::from_snapshot).
+    pub fn snapshot(&self) -> ExecutorSnapshot<D::GlobalState, D::TaskSnapshot>
+    where
+        D: SnapshotDomain,
+        D::GlobalState: Clone,
+    {
+        ExecutorSnapshot::new(
+            self.planners.tick.load(Ordering::Relaxed),
+            self.core.state.clone(),
+            self.core.queue.task_queue(),
+            &self.core.task_history,
+        )
+    }
+
     /// Makes all planning threads stop and wait for them to finish.
     pub fn stop(&mut self) {
         // Set tick to maximum value
-        self.tick.store(u64::MAX, Ordering::Relaxed);
+        self.planners.tick.store(u64::MAX, Ordering::Relaxed);
         // Wait for planning threads to finish
-        self.threads.drain().for_each(|(_, thread)| {
+        self.planners.threads.drain().for_each(|(_, thread)| {
             let _ = thread.join();
         });
     }
 
+    /// Gets the current tick.
+    fn tick(&self) -> u64 {
+        self.planners.tick.load(Ordering::Relaxed)
+    }
+
     /// Gets the global state, read-only.
     pub fn state(&self) -> &D::GlobalState {
-        &self.state
+        &self.core.state
     }
 
     // Get the active tasks, read only.
     pub fn active_tasks(&self) -> &ActiveTasks<D> {
-        self.queue.task_queue()
+        self.core.queue.task_queue()
     }
 
     /// Gets the number of active agents in the execution queue.
     pub fn agents_count(&self) -> usize {
-        self.queue.size()
+        self.core.queue.size()
     }
 }
 
 /// Creates and runs a multi-threaded executor, initializes state and task queue from the `S` trait.
 ///
 /// Parameter `step_duration` defines how long a logical step lasts in wall time.
+#[cfg(not(target_arch = "wasm32"))]
 pub fn run_threaded_executor<D, S>(
     mcts_config: &MCTSConfiguration,
     executor_state: &mut S,
@@ -657,7 +1039,7 @@ pub fn run_threaded_executor<D, S>(
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
     use crate::*;
     use core::time;
@@ -667,101 +1049,101 @@ mod tests {
     };
     use std::{collections::BTreeSet, num::NonZeroU64, thread};
 
-    #[test]
-    fn threaded_executor_trivial_domain() {
-        #[derive(Debug)]
-        enum DisplayAction {
-            // #[default] // TODO: use derive(Default) on Rust 1.62 onwards
-            Idle,
-            Plan,
-        }
-        impl Default for DisplayAction {
-            fn default() -> Self {
-                Self::Idle
-            }
-        }
+    #[derive(Debug, Default)]
+    pub(crate) enum DisplayAction {
+        #[default]
+        Idle,
+        Plan,
+    }
 
-        struct TrivialDomain;
-        impl Domain for TrivialDomain {
-            type State = ();
-            type Diff = ();
-            type DisplayAction = DisplayAction;
+    pub(crate) struct TrivialDomain;
+    impl Domain for TrivialDomain {
+        type State = ();
+        type Diff = ();
+        type DisplayAction = DisplayAction;
 
-            fn list_behaviors() -> &'static [&'static dyn Behavior<Self>] {
-                &[&TrivialBehavior]
-            }
+        fn list_behaviors() -> &'static [&'static dyn Behavior<Self>] {
+            &[&TrivialBehavior]
+        }
 
-            fn get_current_value(
-                _tick: u64,
-                _state_diff: StateDiffRef<Self>,
-                _agent: AgentId,
-            ) -> AgentValue {
-                AgentValue::new(0.).unwrap()
-            }
+        fn get_current_value(
+            _tick: u64,
+            _state_diff: StateDiffRef<Self>,
+            _agent: AgentId,
+        ) -> AgentValue {
+            AgentValue::new(0.).unwrap()
+        }
 
-            fn update_visible_agents(
-                _start_tick: u64,
-                ctx: Context<Self>,
-                agents: &mut BTreeSet<AgentId>,
-            ) {
-                agents.insert(ctx.agent);
-            }
+        fn update_visible_agents(
+            _start_tick: u64,
+            ctx: Context<Self>,
+            agents: &mut BTreeSet<AgentId>,
+        ) {
+            agents.insert(ctx.agent);
+        }
 
-            fn display_action_task_planning() -> Self::DisplayAction {
-                DisplayAction::Plan
-            }
+        fn display_action_task_planning() -> Self::DisplayAction {
+            DisplayAction::Plan
         }
-        impl GlobalDomain for TrivialDomain {
-            type GlobalState = ();
-            fn derive_local_state(
-                _global_state: &Self::GlobalState,
-                _agent: AgentId,
-            ) -> Self::State {
-            }
-            fn apply(
-                _global_state: &mut Self::GlobalState,
-                _local_state: &Self::State,
-                _diff: &Self::Diff,
-            ) {
-            }
+    }
+    impl GlobalDomain for TrivialDomain {
+        type GlobalState = ();
+        fn derive_local_state(_global_state: &Self::GlobalState, _agent: AgentId) -> Self::State {}
+        fn apply(
+            _global_state: &mut Self::GlobalState,
+            _local_state: &Self::State,
+            _diff: &Self::Diff,
+        ) {
         }
-        impl DomainWithPlanningTask for TrivialDomain {}
-
-        #[derive(Copy, Clone, Debug)]
-        struct TrivialBehavior;
-        impl Behavior<TrivialDomain> for TrivialBehavior {
-            fn add_own_tasks(
-                &self,
-                _ctx: Context<TrivialDomain>,
-                tasks: &mut Vec<Box<dyn Task<TrivialDomain>>>,
-            ) {
-                tasks.push(Box::new(IdleTask));
-            }
+    }
+    impl DomainWithPlanningTask for TrivialDomain {}
+    impl SnapshotDomain for TrivialDomain {
+        type TaskSnapshot = ();
+        fn snapshot_task(_task: &dyn Task<Self>) -> Self::TaskSnapshot {
+            unreachable!("Trivial domain only has idle tasks")
+        }
+        fn restore_task(_snapshot: Self::TaskSnapshot) -> Box<dyn Task<Self>> {
+            unreachable!("Trivial domain only has idle tasks")
+        }
+    }
 
-            fn is_valid(&self, _ctx: Context<TrivialDomain>) -> bool {
-                true
-            }
+    #[derive(Copy, Clone, Debug)]
+    struct TrivialBehavior;
+    impl Behavior<TrivialDomain> for TrivialBehavior {
+        fn add_own_tasks(
+            &self,
+            _ctx: Context<TrivialDomain>,
+            tasks: &mut Vec<Box<dyn Task<TrivialDomain>>>,
+        ) {
+            tasks.push(Box::new(IdleTask));
         }
 
-        struct TrivialExecutorState;
-        impl ExecutorStateGlobal<TrivialDomain> for TrivialExecutorState {
-            const MINIMUM_VISITS: u32 = 0;
-            fn create_initial_state(&self) {}
-            fn init_task_queue(&self, _: &()) -> ActiveTasks<TrivialDomain> {
-                vec![ActiveTask::new_with_end(
-                    0,
-                    0,
-                    AgentId(0),
-                    Box::new(IdleTask),
-                )]
-                .into_iter()
+        fn is_valid(&self, _ctx: Context<TrivialDomain>) -> bool {
+            true
+        }
+    }
+
+    /// Executor state for the trivial domain, with the given number of agents
+    pub(crate) struct TrivialExecutorState(pub u32);
+    impl ExecutorStateGlobal<TrivialDomain> for TrivialExecutorState {
+        const MINIMUM_VISITS: u32 = 0;
+        fn create_initial_state(&self) {}
+        fn init_task_queue(&self, _: &()) -> ActiveTasks<TrivialDomain> {
+            (0..self.0)
+                .map(|agent| ActiveTask::new_with_end(0, 0, AgentId(agent), Box::new(IdleTask)))
                 .collect()
-            }
         }
-        impl ExecutorState<TrivialDomain> for TrivialExecutorState {}
+        fn keep_execution(&self, tick: u64, _: &ActiveTasks<TrivialDomain>, _: &()) -> bool {
+            tick < 25
+        }
+        fn planning_visits(&self, _tick: u64, _state: &(), agent: AgentId, visits: u32) -> u32 {
+            visits + agent.0
+        }
+    }
+    impl ExecutorState<TrivialDomain> for TrivialExecutorState {}
 
-        env_logger::init();
-        let mcts_config = MCTSConfiguration {
+    pub(crate) fn trivial_mcts_config() -> MCTSConfiguration {
+        MCTSConfiguration {
             allow_invalid_tasks: false,
             visits: 5,
             depth: 100,
@@ -769,13 +1151,38 @@ mod tests {
             discount_hl: 30.,
             seed: None,
             planning_task_duration: Some(NonZeroU64::new(10).unwrap()),
-        };
-        let mut executor_state = TrivialExecutorState;
-        let mut executor = ThreadedExecutor::new(mcts_config, &mut executor_state);
+        }
+    }
+
+    #[test]
+    fn threaded_executor_trivial_domain() {
+        env_logger::init();
+        let mut executor_state = TrivialExecutorState(1);
+        let mut executor = ThreadedExecutor::new(trivial_mcts_config(), &mut executor_state);
         let one_millis = time::Duration::from_millis(1);
         for _ in 0..5 {
             executor.step();
             thread::sleep(one_millis);
         }
     }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn thread_pool_executor_trivial_domain() {
+        let thread_pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .build()
+            .unwrap();
+        let mut executor_state = TrivialExecutorState(16);
+        let mut executor = ThreadedExecutor::new_with_thread_pool(
+            trivial_mcts_config(),
+            &mut executor_state,
+            Arc::new(thread_pool),
+        );
+        for _ in 0..25 {
+            assert!(executor.step());
+        }
+        executor.stop();
+        assert_eq!(executor.agents_count(), 16);
+    }
 }
@@ -0,0 +1,778 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use npc_engine_core::{
+    ActiveTask, ActiveTasks, AgentId, DomainWithPlanningTask, EarlyStopCondition,
+    MCTSConfiguration, PlanningBudget, PlanningTask, Task, MCTS,
+};
+use std::{
+    collections::{BTreeSet, HashMap},
+    mem,
+};
+
+use crate::{
+    intentions_from_history, ExecutionQueue, ExecutorState, ExecutorStateGlobal, GlobalDomain,
+    PlanningDetail, PlanningDetails, PlanningMetrics, Tuning, TuningHandle, TuningListener,
+};
+
+/// The ongoing plannings of an executor, which decides where and when they run.
+pub(crate) trait Planners<D, S>
+where
+    D: DomainWithPlanningTask + GlobalDomain,
+    S: ExecutorState<D> + ExecutorStateGlobal<D>,
+{
+    /// Starts planning for an agent whose planning task was queued until `end_tick`.
+    fn start(
+        &mut self,
+        core: &mut ExecutorCore<'_, D, S>,
+        tick: u64,
+        active_agent: AgentId,
+        end_tick: u64,
+    );
+
+    /// Drops the ongoing planning of an agent, if any, without waiting for it.
+    fn drop_planning(&mut self, agent: AgentId);
+}
+
+/// The state and the logic shared by the executors planning agents of a [GlobalDomain] during their planning tasks.
+pub(crate) struct ExecutorCore<'a, D, S>
+where
+    D: DomainWithPlanningTask + GlobalDomain,
+    S: ExecutorState<D> + ExecutorStateGlobal<D>,
+{
+    /// The attached MCTS configuration
+    pub(crate) mcts_config: MCTSConfiguration,
+    /// The state of this executor
+    pub(crate) executor_state: &'a mut S,
+    /// The current state of the world
+    pub(crate) state: D::GlobalState,
+    /// The current queue of tasks
+    pub(crate) queue: ExecutionQueue<D>,
+    /// The last tasks which were executed
+    /// Assuming that planning tasks all take the same time, we need to only
+    /// keep one ActiveTask per AgentId, because after the first task choice,
+    /// either it is planning or it is a forced task, which we do not put in the history.
+    pub(crate) task_history: HashMap<AgentId, ActiveTask<D>>,
+    /// The agents to replan at the next step
+    pub(crate) dirty_agents: BTreeSet<AgentId>,
+    /// The planning details of agents not using the MCTS configuration
+    pub(crate) planning_details: PlanningDetails,
+    /// Whether to check the ongoing tasks at every step
+    pub(crate) task_monitoring: bool,
+    /// Whether to resolve conflicts between planned tasks
+    pub(crate) coordination: bool,
+    /// Whether agents use the last tasks of others as their intentions when planning
+    pub(crate) intention_sharing: bool,
+    /// The handle through which parameters are changed at runtime, if any
+    pub(crate) tuning: Option<TuningListener>,
+}
+impl<'a, D, S> ExecutorCore<'a, D, S>
+where
+    D: DomainWithPlanningTask + GlobalDomain,
+    S: ExecutorState<D> + ExecutorStateGlobal<D>,
+{
+    /// Creates a new core, initializes state and task queue from the S trait.
+    pub(crate) fn new(mcts_config: MCTSConfiguration, executor_state: &'a mut S) -> Self {
+        let state = executor_state.create_initial_state();
+        let task_queue = executor_state.init_task_queue(&state);
+        let task_history = task_queue
+            .iter()
+            .map(|active_task| (active_task.agent, active_task.clone()))
+            .collect();
+        Self::with_tasks(mcts_config, executor_state, state, task_queue, task_history)
+    }
+
+    /// Creates a new core from a given state, task queue and history, for example restored from a snapshot.
+    pub(crate) fn with_tasks(
+        mcts_config: MCTSConfiguration,
+        executor_state: &'a mut S,
+        state: D::GlobalState,
+        task_queue: ActiveTasks<D>,
+        task_history: HashMap<AgentId, ActiveTask<D>>,
+    ) -> Self {
+        Self {
+            mcts_config,
+            executor_state,
+            state,
+            queue: ExecutionQueue::new(task_queue),
+            task_history,
+            dirty_agents: Default::default(),
+            planning_details: Default::default(),
+            task_monitoring: false,
+            coordination: false,
+            intention_sharing: false,
+            tuning: None,
+        }
+    }
+
+    /// Creates the search of an agent starting to plan at `tick`.
+    pub(crate) fn new_mcts(
+        &self,
+        tick: u64,
+        active_agent: AgentId,
+        early_stop_condition: Option<Box<EarlyStopCondition>>,
+    ) -> MCTS<D> {
+        let detail = self.planning_details.get(active_agent, &self.mcts_config);
+        let mut mcts_config = self.mcts_config.clone();
+        mcts_config.visits =
+            self.executor_state
+                .planning_visits(tick, &self.state, active_agent, detail.visits);
+        mcts_config.planning_task_duration = Some(detail.planning_task_duration);
+        let mut mcts = MCTS::<D>::new_with_tasks(
+            D::derive_local_state(&self.state, active_agent),
+            active_agent,
+            tick,
+            self.queue.task_queue().clone(),
+            mcts_config,
+            self.executor_state.create_state_value_estimator(),
+            early_stop_condition,
+        );
+        if self.intention_sharing {
+            mcts.set_intentions(intentions_from_history(&self.task_history, active_agent));
+        }
+        mcts.set_profiling(self.executor_state.profile_planning(tick, active_agent));
+        mcts
+    }
+
+    /// Sets the planning detail of all agents of the queue as returned by `classifier`.
+    pub(crate) fn set_planning_details<F>(&mut self, classifier: F)
+    where
+        F: Fn(AgentId, &D::GlobalState) -> Option<PlanningDetail>,
+    {
+        for active_task in self.queue.task_queue() {
+            let agent = active_task.agent;
+            self.planning_details
+                .set(agent, classifier(agent, &self.state));
+        }
+    }
+
+    /// Creates a planning task for an agent, lasting as specified by its planning detail.
+    pub(crate) fn planning_task(&self, agent: AgentId) -> Box<dyn Task<D>> {
+        let detail = self.planning_details.get(agent, &self.mcts_config);
+        Box::new(PlanningTask(detail.planning_task_duration))
+    }
+
+    /// Starts planning for all agents having a planning task in the queue.
+    pub(crate) fn restart_plannings(&mut self, tick: u64, planners: &mut impl Planners<D, S>) {
+        let planning_tasks: Vec<_> = self
+            .queue
+            .task_queue()
+            .iter()
+            .filter(|task| task.task.downcast_ref::<PlanningTask>().is_some())
+            .map(|task| (task.agent, task.end))
+            .collect();
+        for (active_agent, end_tick) in planning_tasks {
+            planners.start(self, tick, active_agent, end_tick);
+        }
+    }
+
+    /// Returns the planning tasks ending at `tick`, whose plannings must be committed.
+    pub(crate) fn due_planning_tasks(&self, tick: u64) -> Vec<ActiveTask<D>> {
+        self.queue
+            .task_queue()
+            .iter()
+            .filter(|task| task.end <= tick && task.task.downcast_ref::<PlanningTask>().is_some())
+            .inspect(|task| {
+                debug_assert!(task.end == tick,
+                    "Processing an active planning task at tick {tick} but it should have been processed at tick {}.", task.end
+                );
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Replaces the planning task of an agent with the best task found by its finished planning.
+    pub(crate) fn commit_planning(
+        &mut self,
+        tick: u64,
+        planning_task: &ActiveTask<D>,
+        mcts: &MCTS<D>,
+    ) {
+        let active_agent = planning_task.agent;
+        self.executor_state.post_mcts_run_hook(mcts, planning_task);
+        log::info!(
+            "T{tick} - {active_agent} finished planning after {} visits. Looking for best task...",
+            mcts.visits_done()
+        );
+        let best_task = mcts.best_task_with_history(&self.task_history);
+        log::info!("Best Task: {best_task:?}");
+        self.executor_state
+            .on_planning_metrics(&PlanningMetrics::new(tick, mcts, best_task.clone()));
+
+        self.queue.remove_task(planning_task);
+        let local_state = D::derive_local_state(&self.state, active_agent);
+        let new_active_task = self.queue.queue_chosen_task(
+            tick,
+            active_agent,
+            best_task,
+            &local_state,
+            self.executor_state,
+        );
+        self.task_history.insert(active_agent, new_active_task);
+    }
+
+    /// Executes all tasks which are due at `tick` and starts planning for the agents left without a next task.
+    pub(crate) fn execute_finished_tasks(&mut self, tick: u64, planners: &mut impl Planners<D, S>) {
+        let active_tasks = self.queue.task_queue().clone();
+        for active_task in active_tasks.iter().filter(|task| task.end <= tick) {
+            // Pop task as it is completed
+            self.queue.remove_task(active_task);
+            let active_agent = active_task.agent;
+            debug_assert!(
+                active_task.end == tick,
+                "Processing an active task at tick {tick} but it ended at tick {}.",
+                active_task.end
+            );
+
+            // Should we continue considering that agent?
+            if !self
+                .executor_state
+                .keep_agent(tick, &self.state, active_agent)
+            {
+                continue;
+            }
+
+            // Execute the task, queue the new agents
+            let local_state = D::derive_local_state(&self.state, active_agent);
+            let task_history = &mut self.task_history;
+            let (diff, new_task) = self.queue.execute_task(
+                active_task,
+                &local_state,
+                self.executor_state,
+                |new_agents_tasks| {
+                    for new_task in new_agents_tasks.iter() {
+                        debug_assert!(!task_history.contains_key(&new_task.agent));
+                        task_history.insert(new_task.agent, new_task.clone());
+                    }
+                },
+            );
+            D::apply(&mut self.state, &local_state, &diff);
+            let local_state = D::derive_local_state(&self.state, active_agent);
+
+            // If no next task, queue a plan task
+            let new_task = new_task.unwrap_or_else(|| self.planning_task(active_agent));
+            let end_tick = self
+                .queue
+                .queue_task(tick, active_agent, new_task.clone(), &local_state)
+                .end;
+
+            // Start the associated planning if needed
+            if new_task.downcast_ref::<PlanningTask>().is_some() {
+                planners.start(self, tick, active_agent, end_tick);
+            }
+        }
+    }
+
+    /// Queues a planning task for an agent and starts its planning, unless it already has an active task.
+    pub(crate) fn add_agent(
+        &mut self,
+        tick: u64,
+        agent: AgentId,
+        planners: &mut impl Planners<D, S>,
+    ) -> bool {
+        if self
+            .queue
+            .task_queue()
+            .iter()
+            .any(|task| task.agent == agent)
+        {
+            return false;
+        }
+        log::info!("T{tick} - {agent} is added.");
+        let local_state = D::derive_local_state(&self.state, agent);
+        let planning_task = self.planning_task(agent);
+        let active_task = self
+            .queue
+            .queue_task(tick, agent, planning_task, &local_state);
+        let end_tick = active_task.end;
+        self.task_history.insert(agent, active_task);
+        planners.start(self, tick, agent, end_tick);
+        true
+    }
+
+    /// Forgets everything about an agent and returns its active task if any.
+    pub(crate) fn remove_agent(
+        &mut self,
+        tick: u64,
+        agent: AgentId,
+        planners: &mut impl Planners<D, S>,
+    ) -> Option<ActiveTask<D>> {
+        log::info!("T{tick} - {agent} is removed.");
+        planners.drop_planning(agent);
+        self.dirty_agents.remove(&agent);
+        self.task_history.remove(&agent);
+        self.planning_details.set(agent, None);
+        self.queue.remove_agent_task(agent)
+    }
+
+    /// Marks as dirty all agents of the queue for which `predicate` holds.
+    pub(crate) fn mark_dirty_where<F>(&mut self, predicate: F)
+    where
+        F: Fn(AgentId, &D::GlobalState) -> bool,
+    {
+        let state = &self.state;
+        self.dirty_agents.extend(
+            self.queue
+                .task_queue()
+                .iter()
+                .map(|active_task| active_task.agent)
+                .filter(|agent| predicate(*agent, state)),
+        );
+    }
+
+    /// Drops the tasks and plannings of dirty agents and makes them plan again.
+    pub(crate) fn replan_dirty_agents(&mut self, tick: u64, planners: &mut impl Planners<D, S>) {
+        for active_agent in mem::take(&mut self.dirty_agents) {
+            if self.queue.remove_agent_task(active_agent).is_none() {
+                continue;
+            }
+            planners.drop_planning(active_agent);
+            log::info!("T{tick} - {active_agent} is dirty, replanning.");
+            let local_state = D::derive_local_state(&self.state, active_agent);
+            let planning_task = self.planning_task(active_agent);
+            let end_tick = self
+                .queue
+                .queue_task(tick, active_agent, planning_task, &local_state)
+                .end;
+            planners.start(self, tick, active_agent, end_tick);
+        }
+    }
+
+    /// Returns a handle to change the MCTS configuration, and the given budgets if any, while the executor runs.
+    pub(crate) fn tuning_handle(
+        &mut self,
+        budget: Option<PlanningBudget>,
+        step_budget: Option<PlanningBudget>,
+    ) -> TuningHandle {
+        let mcts_config = &self.mcts_config;
+        TuningListener::handle(&mut self.tuning, || Tuning {
+            mcts_config: mcts_config.clone(),
+            budget,
+            step_budget,
+        })
+    }
+
+    /// Applies the MCTS configuration changed through the tuning handle, returns the changed parameters if any.
+    pub(crate) fn apply_tuning(&mut self) -> Option<Tuning> {
+        let tuning = self.tuning.as_mut().and_then(TuningListener::poll)?;
+        self.mcts_config = tuning.mcts_config.clone();
+        Some(tuning)
+    }
+
+    /// Marks as dirty the agents whose ongoing task is not valid or not justified any more.
+    pub(crate) fn monitor_tasks(&mut self, tick: u64) {
+        if !self.task_monitoring {
+            return;
+        }
+        let ongoing_tasks: Vec<_> = self
+            .queue
+            .task_queue()
+            .iter()
+            .filter(|task| task.end > tick && task.task.downcast_ref::<PlanningTask>().is_none())
+            .cloned()
+            .collect();
+        for active_task in ongoing_tasks {
+            let active_agent = active_task.agent;
+            let local_state = D::derive_local_state(&self.state, active_agent);
+            if !ExecutionQueue::is_task_still_valid(tick, &active_task, &local_state)
+                || !self
+                    .executor_state
+                    .keep_task(tick, &self.state, &active_task)
+            {
+                log::info!("T{tick} - {active_agent} aborts {:?}.", active_task.task);
+                self.executor_state.on_task_failed(&active_task);
+                self.dirty_agents.insert(active_agent);
+            }
+        }
+    }
+
+    /// Marks as dirty the agents whose task conflicts with a newly planned one, so that they plan again.
+    pub(crate) fn coordinate_plans(&mut self, tick: u64, planned_agents: &BTreeSet<AgentId>) {
+        if !self.coordination {
+            return;
+        }
+        let conflicting_agents =
+            self.queue
+                .conflicting_agents(tick, planned_agents, &self.state, &*self.executor_state);
+        for active_agent in conflicting_agents {
+            let active_task = self
+                .queue
+                .task_queue()
+                .iter()
+                .find(|task| task.agent == active_agent)
+                .cloned();
+            if let Some(active_task) = active_task {
+                log::info!(
+                    "T{tick} - {active_agent} drops conflicting {:?}.",
+                    active_task.task
+                );
+                self.executor_state.on_task_failed(&active_task);
+            }
+            self.dirty_agents.insert(active_agent);
+        }
+    }
+}
+
+/// Implements the public methods shared by the executors built on an [ExecutorCore].
+///
+/// The executor must have a `core` field, a `planners` field implementing [Planners] and a `tick` method.
+macro_rules! impl_executor_methods {
+    () => {
+        /// Adds an agent to the simulation, for example a newly spawned animal, and makes it plan at once.
+        ///
+        /// The agent must already exist in the global state, as its local state is derived from it.
+        /// Returns false and does nothing if the agent already has an active task.
+        pub fn add_agent(&mut self, agent: ::npc_engine_core::AgentId) -> bool {
+            let tick = self.tick();
+            self.core.add_agent(tick, agent, &mut self.planners)
+        }
+
+        /// Removes an agent from the simulation, for example a dead lumberjack, and returns its active task if any.
+        ///
+        /// Its ongoing planning, if any, is dropped without being waited for, as well as its planning detail and history.
+        /// The agent itself is not removed from the global state, this is left to the domain.
+        pub fn remove_agent(
+            &mut self,
+            agent: ::npc_engine_core::AgentId,
+        ) -> Option<::npc_engine_core::ActiveTask<D>> {
+            let tick = self.tick();
+            self.core.remove_agent(tick, agent, &mut self.planners)
+        }
+
+        /// Marks an agent as dirty, because the world changed in a way that affects it.
+        ///
+        /// At the next [step](Self::step), its active task and ongoing planning are dropped and it plans anew.
+        pub fn mark_agent_dirty(&mut self, agent: ::npc_engine_core::AgentId) {
+            self.core.dirty_agents.insert(agent);
+        }
+
+        /// Marks as dirty all agents of the queue for which `predicate` holds, for example those close to a changed region of the world.
+        ///
+        /// See [mark_agent_dirty](Self::mark_agent_dirty).
+        pub fn mark_dirty_where<F>(&mut self, predicate: F)
+        where
+            F: Fn(::npc_engine_core::AgentId, &D::GlobalState) -> bool,
+        {
+            self.core.mark_dirty_where(predicate);
+        }
+
+        /// Sets the [PlanningDetail](crate::PlanningDetail) of an agent, or resets it to the MCTS configuration if `None`.
+        ///
+        /// It takes effect at the next planning of the agent.
+        pub fn set_planning_detail(
+            &mut self,
+            agent: ::npc_engine_core::AgentId,
+            detail: Option<crate::PlanningDetail>,
+        ) {
+            self.core.planning_details.set(agent, detail);
+        }
+
+        /// Sets the [PlanningDetail](crate::PlanningDetail) of all agents of the queue as returned by `classifier`, for example depending on their distance to the player.
+        ///
+        /// See [set_planning_detail](Self::set_planning_detail).
+        pub fn set_planning_details<F>(&mut self, classifier: F)
+        where
+            F: Fn(::npc_engine_core::AgentId, &D::GlobalState) -> Option<crate::PlanningDetail>,
+        {
+            self.core.set_planning_details(classifier);
+        }
+
+        /// Enables or disables intention sharing (disabled by default).
+        ///
+        /// When enabled, each planning agent expects the other agents to choose again the task they last committed to,
+        /// and uses it as their fixed first move, see [MCTS::set_intentions](npc_engine_core::MCTS::set_intentions).
+        pub fn set_intention_sharing(&mut self, enabled: bool) {
+            self.core.intention_sharing = enabled;
+        }
+
+        /// Enables or disables coordination of plans (disabled by default).
+        ///
+        /// When enabled, after agents have planned, their new tasks are checked against the other tasks
+        /// with [ExecutorStateGlobal::tasks_conflict](crate::ExecutorStateGlobal::tasks_conflict),
+        /// and in each conflict the agent with the lower priority plans again.
+        pub fn set_coordination(&mut self, enabled: bool) {
+            self.core.coordination = enabled;
+        }
+
+        /// Enables or disables task monitoring (disabled by default).
+        ///
+        /// When enabled, at every step the active tasks of all agents are checked with [Task::is_valid](npc_engine_core::Task::is_valid)
+        /// and [ExecutorStateGlobal::keep_task](crate::ExecutorStateGlobal::keep_task), and agents whose task fails these checks are replanned.
+        pub fn set_task_monitoring(&mut self, enabled: bool) {
+            self.core.task_monitoring = enabled;
+        }
+    };
+}
+pub(crate) use impl_executor_methods;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use npc_engine_core::{
+        impl_task_boxed_methods, AgentValue, Behavior, Context, ContextMut, Domain, StateDiffRef,
+        TaskDuration,
+    };
+    use std::num::NonZeroU64;
+
+    /// Two lumberjacks chop the trees of a small forest, each of them valuing the wood it chopped
+    struct ForestDomain;
+
+    #[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+    struct Forest {
+        /// The wood left in each tree
+        trees: [u32; 3],
+        /// The wood chopped by each agent
+        wood: [u32; 2],
+    }
+
+    fn forest<'a>(state_diff: StateDiffRef<'a, ForestDomain>) -> &'a Forest {
+        state_diff.diff.as_ref().unwrap_or(state_diff.initial_state)
+    }
+
+    impl Domain for ForestDomain {
+        type State = Forest;
+        type Diff = Option<Forest>;
+        type DisplayAction = ();
+
+        fn list_behaviors() -> &'static [&'static dyn Behavior<Self>] {
+            &[&Lumberjack]
+        }
+
+        fn get_current_value(
+            _tick: u64,
+            state_diff: StateDiffRef<Self>,
+            agent: AgentId,
+        ) -> AgentValue {
+            AgentValue::new(forest(state_diff).wood[agent.0 as usize] as f32).unwrap()
+        }
+
+        fn update_visible_agents(
+            _start_tick: u64,
+            _ctx: Context<Self>,
+            agents: &mut BTreeSet<AgentId>,
+        ) {
+            agents.extend([AgentId(0), AgentId(1)]);
+        }
+    }
+    impl GlobalDomain for ForestDomain {
+        type GlobalState = Forest;
+        fn derive_local_state(global_state: &Forest, _agent: AgentId) -> Forest {
+            global_state.clone()
+        }
+        fn apply(global_state: &mut Forest, _local_state: &Forest, diff: &Option<Forest>) {
+            if let Some(diff) = diff {
+                *global_state = diff.clone();
+            }
+        }
+    }
+    impl DomainWithPlanningTask for ForestDomain {}
+
+    #[derive(Copy, Clone, Debug)]
+    struct Lumberjack;
+    impl Behavior<ForestDomain> for Lumberjack {
+        fn add_own_tasks(
+            &self,
+            ctx: Context<ForestDomain>,
+            tasks: &mut Vec<Box<dyn Task<ForestDomain>>>,
+        ) {
+            for (tree, wood) in forest(ctx.state_diff).trees.iter().enumerate() {
+                if *wood > 0 {
+                    tasks.push(Box::new(Chop(tree)));
+                }
+            }
+        }
+
+        fn is_valid(&self, _ctx: Context<ForestDomain>) -> bool {
+            true
+        }
+    }
+
+    #[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+    struct Chop(usize);
+    impl Task<ForestDomain> for Chop {
+        fn duration(&self, _ctx: Context<ForestDomain>) -> TaskDuration {
+            1
+        }
+        fn execute(&self, ctx: ContextMut<ForestDomain>) -> Option<Box<dyn Task<ForestDomain>>> {
+            let mut forest = ctx
+                .state_diff
+                .diff
+                .clone()
+                .unwrap_or_else(|| ctx.state_diff.initial_state.clone());
+            forest.trees[self.0] -= 1;
+            forest.wood[ctx.agent.0 as usize] += 1;
+            *ctx.state_diff.diff = Some(forest);
+            None
+        }
+        fn is_valid(&self, ctx: Context<ForestDomain>) -> bool {
+            forest(ctx.state_diff).trees[self.0] > 0
+        }
+        fn display_action(&self) -> <ForestDomain as Domain>::DisplayAction {}
+        impl_task_boxed_methods!(ForestDomain);
+    }
+
+    /// Two lumberjacks chopping the same tree conflict
+    #[derive(Default)]
+    struct ForestState {
+        failed_agents: Vec<AgentId>,
+    }
+    impl ExecutorStateGlobal<ForestDomain> for ForestState {
+        const MINIMUM_VISITS: u32 = 0;
+        fn create_initial_state(&self) -> Forest {
+            Forest {
+                trees: [5; 3],
+                wood: [0; 2],
+            }
+        }
+        fn init_task_queue(&self, _: &Forest) -> ActiveTasks<ForestDomain> {
+            Default::default()
+        }
+        fn tasks_conflict(
+            &self,
+            _: u64,
+            _: &Forest,
+            first: &ActiveTask<ForestDomain>,
+            second: &ActiveTask<ForestDomain>,
+        ) -> bool {
+            first.task.downcast_ref::<Chop>() == second.task.downcast_ref::<Chop>()
+        }
+    }
+    impl ExecutorState<ForestDomain> for ForestState {
+        fn on_task_failed(&mut self, active_task: &ActiveTask<ForestDomain>) {
+            self.failed_agents.push(active_task.agent);
+        }
+    }
+
+    fn mcts_config() -> MCTSConfiguration {
+        MCTSConfiguration {
+            allow_invalid_tasks: false,
+            visits: 200,
+            depth: 4,
+            exploration: 1.414,
+            discount_hl: 15.,
+            seed: Some(0),
+            planning_task_duration: Some(NonZeroU64::new(1).unwrap()),
+        }
+    }
+
+    // The agents chop the given trees, their tasks ending at tick 1
+    fn chopping(trees: [usize; 2]) -> ActiveTasks<ForestDomain> {
+        trees
+            .iter()
+            .enumerate()
+            .map(|(agent, tree)| {
+                ActiveTask::new_with_end(0, 1, AgentId(agent as u32), Box::new(Chop(*tree)))
+            })
+            .collect()
+    }
+
+    /// Records the plannings started, without running them
+    #[derive(Default)]
+    struct RecordingPlanners(Vec<(AgentId, u64)>);
+    impl Planners<ForestDomain, ForestState> for RecordingPlanners {
+        fn start(
+            &mut self,
+            _core: &mut ExecutorCore<'_, ForestDomain, ForestState>,
+            _tick: u64,
+            active_agent: AgentId,
+            end_tick: u64,
+        ) {
+            self.0.push((active_agent, end_tick));
+        }
+        fn drop_planning(&mut self, _agent: AgentId) {}
+    }
+
+    fn core_with_tasks(
+        executor_state: &mut ForestState,
+        tasks: ActiveTasks<ForestDomain>,
+    ) -> ExecutorCore<'_, ForestDomain, ForestState> {
+        let state = executor_state.create_initial_state();
+        let task_history = tasks
+            .iter()
+            .map(|active_task| (active_task.agent, active_task.clone()))
+            .collect();
+        ExecutorCore::with_tasks(mcts_config(), executor_state, state, tasks, task_history)
+    }
+
+    #[test]
+    fn monitoring_replans_agents_whose_tree_is_gone() {
+        for task_monitoring in [false, true] {
+            let mut executor_state = ForestState::default();
+            let mut core = core_with_tasks(&mut executor_state, chopping([0, 1]));
+            core.task_monitoring = task_monitoring;
+            // Someone else chopped the whole first tree meanwhile
+            core.state.trees[0] = 0;
+            core.monitor_tasks(0);
+            let dirty_agents = if task_monitoring {
+                BTreeSet::from([AgentId(0)])
+            } else {
+                BTreeSet::new()
+            };
+            assert_eq!(core.dirty_agents, dirty_agents);
+            assert_eq!(
+                executor_state.failed_agents,
+                dirty_agents.into_iter().collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn coordination_replans_one_of_the_agents_chopping_the_same_tree() {
+        for (coordination, trees, dirty_agent) in [
+            (false, [0, 0], None),
+            (true, [0, 1], None),
+            (true, [0, 0], Some(AgentId(1))),
+        ] {
+            let mut executor_state = ForestState::default();
+            let mut core = core_with_tasks(&mut executor_state, chopping(trees));
+            core.coordination = coordination;
+            core.coordinate_plans(0, &BTreeSet::from([AgentId(0), AgentId(1)]));
+            assert_eq!(core.dirty_agents, dirty_agent.into_iter().collect());
+            assert_eq!(
+                executor_state.failed_agents,
+                dirty_agent.into_iter().collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn intention_sharing_fixes_the_next_task_of_the_others() {
+        // The trees chopped by the other agent at its first decision in the search of the first agent,
+        // after its current task ends at tick 1 and it plans for a tick
+        let first_trees_of_other_agent = |intention_sharing: bool| {
+            let mut executor_state = ForestState::default();
+            let tasks: ActiveTasks<ForestDomain> = chopping([0, 2])
+                .into_iter()
+                .filter(|active_task| active_task.agent == AgentId(1))
+                .collect();
+            let mut core = core_with_tasks(&mut executor_state, tasks);
+            core.intention_sharing = intention_sharing;
+            let mut mcts = core.new_mcts(0, AgentId(0), None);
+            mcts.run();
+            mcts.nodes()
+                .filter(|(node, _)| node.agent() == AgentId(1) && node.tick() == 2)
+                .flat_map(|(_, edges)| edges.expanded_tasks())
+                .filter_map(|(task, _)| task.downcast_ref::<Chop>().map(|chop| chop.0))
+                .collect::<BTreeSet<_>>()
+        };
+        assert_eq!(first_trees_of_other_agent(false), BTreeSet::from([0, 1, 2]));
+        // The other agent keeps chopping the tree it chopped last
+        assert_eq!(first_trees_of_other_agent(true), BTreeSet::from([2]));
+    }
+
+    #[test]
+    fn finished_tasks_are_executed_and_their_agents_plan_again() {
+        let mut executor_state = ForestState::default();
+        let mut core = core_with_tasks(&mut executor_state, chopping([0, 1]));
+        let mut planners = RecordingPlanners::default();
+        core.execute_finished_tasks(1, &mut planners);
+        assert_eq!(core.state.trees, [4, 4, 5]);
+        assert_eq!(core.state.wood, [1, 1]);
+        // Both agents plan until their planning task ends at the next tick
+        assert_eq!(planners.0, vec![(AgentId(0), 2), (AgentId(1), 2)]);
+        assert!(core.due_planning_tasks(1).is_empty());
+        assert_eq!(core.due_planning_tasks(2).len(), 2);
+    }
+}
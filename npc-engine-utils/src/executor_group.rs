@@ -0,0 +1,181 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use npc_engine_core::DomainWithPlanningTask;
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::ThreadedExecutor;
+use crate::{ExecutorState, ExecutorStateGlobal, GlobalDomain, IncrementalExecutor};
+
+/// An executor that can be stepped as part of an [ExecutorGroup], regardless of its domain.
+pub trait GroupedExecutor {
+    /// Executes one step, returns whether execution should continue.
+    fn step(&mut self) -> bool;
+
+    /// Stops execution, waiting for background work if any.
+    fn stop(&mut self) {}
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<'a, D, S> GroupedExecutor for ThreadedExecutor<'a, D, S>
+where
+    D: DomainWithPlanningTask + GlobalDomain,
+    D::State: Clone + Send,
+    D::Diff: Send + Sync,
+    S: ExecutorState<D> + ExecutorStateGlobal<D>,
+{
+    fn step(&mut self) -> bool {
+        ThreadedExecutor::step(self)
+    }
+
+    fn stop(&mut self) {
+        ThreadedExecutor::stop(self)
+    }
+}
+
+impl<'a, D, S> GroupedExecutor for IncrementalExecutor<'a, D, S>
+where
+    D: DomainWithPlanningTask + GlobalDomain,
+    D::State: Clone,
+    S: ExecutorState<D> + ExecutorStateGlobal<D>,
+{
+    fn step(&mut self) -> bool {
+        IncrementalExecutor::step(self)
+    }
+}
+
+struct GroupMember<'a> {
+    name: String,
+    executor: Box<dyn GroupedExecutor + 'a>,
+    running: bool,
+}
+
+/// Several executors, possibly of unrelated domains, stepped together.
+///
+/// Each executor keeps its own world, agent ids and schedule, and all advance by one step
+/// when the group is [stepped](Self::step).
+/// This allows for example a village economy and a combat squad to be simulated side by side
+/// from a single update loop.
+#[derive(Default)]
+pub struct ExecutorGroup<'a> {
+    members: Vec<GroupMember<'a>>,
+}
+impl<'a> ExecutorGroup<'a> {
+    /// Creates an empty group.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an executor to the group under a given name, panics if the name is already taken.
+    pub fn add<E: GroupedExecutor + 'a>(&mut self, name: impl Into<String>, executor: E) {
+        let name = name.into();
+        assert!(
+            self.members.iter().all(|member| member.name != name),
+            "An executor named {name} is already in the group"
+        );
+        self.members.push(GroupMember {
+            name,
+            executor: Box::new(executor),
+            running: true,
+        });
+    }
+
+    /// Steps all running executors in the order they were added, stopping those which finish.
+    ///
+    /// Returns whether at least one executor is still running.
+    pub fn step(&mut self) -> bool {
+        for member in self.members.iter_mut().filter(|member| member.running) {
+            if !member.executor.step() {
+                log::info!("Executor {} finished.", member.name);
+                member.executor.stop();
+                member.running = false;
+            }
+        }
+        self.is_running()
+    }
+
+    /// Stops all running executors.
+    pub fn stop(&mut self) {
+        for member in self.members.iter_mut().filter(|member| member.running) {
+            member.executor.stop();
+            member.running = false;
+        }
+    }
+
+    /// Returns whether at least one executor is still running.
+    pub fn is_running(&self) -> bool {
+        self.members.iter().any(|member| member.running)
+    }
+
+    /// Returns whether the executor with a given name is still running, or None if there is no such executor.
+    pub fn is_member_running(&self, name: &str) -> Option<bool> {
+        self.members
+            .iter()
+            .find(|member| member.name == name)
+            .map(|member| member.running)
+    }
+
+    /// Gets the names of the executors, in the order they were added.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.members.iter().map(|member| member.name.as_str())
+    }
+}
+impl<'a> Drop for ExecutorGroup<'a> {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use npc_engine_core::{ActiveTasks, PlanningBudget};
+
+    use super::*;
+    use crate::executor::tests::{trivial_mcts_config, TrivialDomain, TrivialExecutorState};
+
+    /// Like [TrivialExecutorState] but stops earlier
+    struct ShortExecutorState;
+    impl ExecutorStateGlobal<TrivialDomain> for ShortExecutorState {
+        const MINIMUM_VISITS: u32 = 0;
+        fn create_initial_state(&self) {}
+        fn init_task_queue(&self, state: &()) -> ActiveTasks<TrivialDomain> {
+            TrivialExecutorState(2).init_task_queue(state)
+        }
+        fn keep_execution(&self, tick: u64, _: &ActiveTasks<TrivialDomain>, _: &()) -> bool {
+            tick < 10
+        }
+    }
+    impl ExecutorState<TrivialDomain> for ShortExecutorState {}
+
+    #[test]
+    fn executor_group_steps_members_independently() {
+        let mut village_state = TrivialExecutorState(3);
+        let mut squad_state = ShortExecutorState;
+        let mut group = ExecutorGroup::new();
+        group.add(
+            "village",
+            ThreadedExecutor::new(trivial_mcts_config(), &mut village_state),
+        );
+        group.add(
+            "squad",
+            IncrementalExecutor::new(
+                trivial_mcts_config(),
+                PlanningBudget::Visits(1),
+                &mut squad_state,
+            ),
+        );
+        assert_eq!(group.names().collect::<Vec<_>>(), ["village", "squad"]);
+        let mut steps = 0;
+        while group.step() {
+            steps += 1;
+            if steps == 15 {
+                assert_eq!(group.is_member_running("village"), Some(true));
+                assert_eq!(group.is_member_running("squad"), Some(false));
+            }
+        }
+        assert_eq!(steps, 25);
+        assert_eq!(group.is_member_running("unknown"), None);
+    }
+}
@@ -0,0 +1,205 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::fmt::Write;
+#[cfg(feature = "png")]
+use std::{fs::File, io, io::BufWriter, path::Path};
+
+use npc_engine_core::{Context, Domain, Task, MCTS};
+
+use crate::Coord2D;
+
+/// A domain whose agents move on a 2-D grid, allowing to map tasks to the cells they lead to.
+pub trait GridDomain: Domain {
+    /// Returns the cell reached by the agent of `ctx` when doing `task`, or None if it is not a movement task.
+    fn task_target(ctx: Context<Self>, task: &dyn Task<Self>) -> Option<Coord2D>;
+}
+
+/// The values of the movement tasks at the root of a search, mapped onto the cells they lead to, see [export_value_heatmap].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValueHeatmap {
+    /// The cell of the lowest coordinates of the map
+    pub origin: Coord2D,
+    /// The number of columns
+    pub width: usize,
+    /// The number of rows
+    pub height: usize,
+    /// The values of the cells, row by row, None for cells no task leads to
+    pub values: Vec<Option<f32>>,
+}
+
+impl ValueHeatmap {
+    /// Returns the value of a cell, if any task leads to it.
+    pub fn value(&self, coord: Coord2D) -> Option<f32> {
+        let x = usize::try_from(coord.x - self.origin.x).ok()?;
+        let y = usize::try_from(coord.y - self.origin.y).ok()?;
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        self.values[y * self.width + x]
+    }
+
+    /// Returns the range of values, if any.
+    pub fn value_range(&self) -> Option<(f32, f32)> {
+        self.values.iter().flatten().fold(None, |range, value| {
+            Some(range.map_or((*value, *value), |(min, max): (f32, f32)| {
+                (min.min(*value), max.max(*value))
+            }))
+        })
+    }
+
+    /// Returns the heatmap in CSV, with a header row of x coordinates and a first column of y coordinates.
+    /// Cells no task leads to are empty.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("y\\x");
+        for x in 0..self.width {
+            write!(csv, ",{}", self.origin.x + x as i32).unwrap();
+        }
+        csv.push('\n');
+        for y in 0..self.height {
+            write!(csv, "{}", self.origin.y + y as i32).unwrap();
+            for value in &self.values[y * self.width..(y + 1) * self.width] {
+                csv.push(',');
+                if let Some(value) = value {
+                    write!(csv, "{value}").unwrap();
+                }
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+
+    /// Writes the heatmap as a PNG image with `scale` pixels per cell, from blue for the lowest value to red for the highest.
+    /// Cells no task leads to are transparent.
+    #[cfg(feature = "png")]
+    pub fn write_png(&self, path: impl AsRef<Path>, scale: u32) -> io::Result<()> {
+        let to_io_error = |error: png::EncodingError| io::Error::new(io::ErrorKind::Other, error);
+        let scale = scale.max(1) as usize;
+        let (min, max) = self.value_range().unwrap_or((0., 0.));
+        let (width, height) = (self.width * scale, self.height * scale);
+        let mut pixels = vec![0u8; width * height * 4];
+        for (index, value) in self.values.iter().enumerate() {
+            let value = match value {
+                Some(value) => value,
+                None => continue,
+            };
+            let ratio = if max > min {
+                (value - min) / (max - min)
+            } else {
+                0.5
+            };
+            let color = [(255. * ratio) as u8, 0, (255. * (1. - ratio)) as u8, 255];
+            let (x, y) = (index % self.width, index / self.width);
+            for row in y * scale..(y + 1) * scale {
+                for column in x * scale..(x + 1) * scale {
+                    let offset = (row * width + column) * 4;
+                    pixels[offset..offset + 4].copy_from_slice(&color);
+                }
+            }
+        }
+        let mut encoder = png::Encoder::new(
+            BufWriter::new(File::create(path)?),
+            width as u32,
+            height as u32,
+        );
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(to_io_error)?;
+        writer.write_image_data(&pixels).map_err(to_io_error)
+    }
+}
+
+/// Maps the q-values of the expanded movement tasks at the root of a search onto the cells they lead to.
+///
+/// When several tasks lead to the same cell, the best value is kept.
+/// The map covers the bounding box of the reached cells, and is empty if no movement task was expanded.
+pub fn export_value_heatmap<D: GridDomain>(mcts: &MCTS<D>) -> ValueHeatmap {
+    let root = mcts.root_node();
+    let agent = mcts.agent();
+    let ctx = Context::with_state_and_diff(root.tick(), mcts.initial_state(), root.diff(), agent);
+    let targets: Vec<_> = mcts
+        .get_edges(&root)
+        .into_iter()
+        .flat_map(|edges| edges.expanded_tasks())
+        .filter_map(|(task, edge)| {
+            D::task_target(ctx, &**task).map(|target| (target, edge.lock().unwrap().q_value(agent)))
+        })
+        .collect();
+    let (origin, end) = match targets.first() {
+        Some((first, _)) => targets
+            .iter()
+            .fold((*first, *first), |(min, max), (target, _)| {
+                (min.min_per_comp(*target), max.max_per_comp(*target))
+            }),
+        None => {
+            return ValueHeatmap {
+                origin: Coord2D::default(),
+                width: 0,
+                height: 0,
+                values: Vec::new(),
+            }
+        }
+    };
+    let width = (end.x - origin.x) as usize + 1;
+    let height = (end.y - origin.y) as usize + 1;
+    let mut values: Vec<Option<f32>> = vec![None; width * height];
+    for (target, value) in targets {
+        let index = (target.y - origin.y) as usize * width + (target.x - origin.x) as usize;
+        let cell = &mut values[index];
+        *cell = Some(cell.map_or(value, |previous| previous.max(value)));
+    }
+    ValueHeatmap {
+        origin,
+        width,
+        height,
+        values,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::tests::{counter_mcts_config, Add, CounterDomain};
+    use npc_engine_core::AgentId;
+
+    /// Adding n moves the counter n cells to the right.
+    impl GridDomain for CounterDomain {
+        fn task_target(ctx: Context<Self>, task: &dyn Task<Self>) -> Option<Coord2D> {
+            let counter = ctx.state_diff.diff.unwrap_or(*ctx.state_diff.initial_state);
+            task.downcast_ref::<Add>()
+                .map(|add| Coord2D::new((counter + add.0) as i32, 0))
+        }
+    }
+
+    #[test]
+    fn heatmap_maps_root_values_to_cells() {
+        let mut mcts = MCTS::<CounterDomain>::new(3, AgentId(0), counter_mcts_config());
+        let empty = export_value_heatmap(&mcts);
+        assert_eq!((empty.width, empty.height), (0, 0));
+        assert_eq!(empty.value_range(), None);
+
+        mcts.run();
+        let heatmap = export_value_heatmap(&mcts);
+        assert_eq!(heatmap.origin, Coord2D::new(4, 0));
+        assert_eq!((heatmap.width, heatmap.height), (2, 1));
+        let add_one = heatmap.value(Coord2D::new(4, 0)).unwrap();
+        let add_two = heatmap.value(Coord2D::new(5, 0)).unwrap();
+        assert!(add_two > add_one);
+        assert_eq!(heatmap.value(Coord2D::new(6, 0)), None);
+        assert_eq!(heatmap.value_range(), Some((add_one, add_two)));
+        assert_eq!(
+            heatmap.to_csv(),
+            format!("y\\x,4,5\n0,{add_one},{add_two}\n")
+        );
+
+        #[cfg(feature = "png")]
+        {
+            let path = std::env::temp_dir().join("npc_engine_heatmap_test.png");
+            heatmap.write_png(&path, 4).unwrap();
+            assert!(std::fs::metadata(&path).unwrap().len() > 0);
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+}
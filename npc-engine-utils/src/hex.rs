@@ -0,0 +1,297 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::fmt;
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+use serde::{Deserialize, Serialize};
+
+/// An axial coordinate on a grid of pointy-top hexagons.
+///
+/// `q` grows towards the east and `r` towards the south-east, rows being along `q`.
+/// The third cube coordinate `s` is implied, the three of them summing to zero.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct HexCoord {
+    pub q: i32,
+    pub r: i32,
+}
+impl HexCoord {
+    pub const fn new(q: i32, r: i32) -> Self {
+        HexCoord { q, r }
+    }
+    /// Creates a coordinate from cube coordinates, panics if they do not sum to zero.
+    pub fn from_cube(q: i32, r: i32, s: i32) -> Self {
+        assert_eq!(q + r + s, 0, "cube coordinates must sum to zero");
+        Self::new(q, r)
+    }
+    /// Returns the third cube coordinate.
+    pub const fn s(&self) -> i32 {
+        -self.q - self.r
+    }
+    /// Returns the cube coordinates `(q, r, s)`.
+    pub const fn cube(&self) -> (i32, i32, i32) {
+        (self.q, self.r, self.s())
+    }
+    /// Rounds fractional axial coordinates to the hexagon containing them.
+    pub fn round(q: f32, r: f32) -> Self {
+        let s = -q - r;
+        let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+        let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+        // The coordinate that moved the most is recomputed from the two others
+        if dq > dr && dq > ds {
+            rq = -rr - rs;
+        } else if dr > ds {
+            rr = -rq - rs;
+        }
+        Self::new(rq as i32, rr as i32)
+    }
+    /// Converts from offset coordinates in which odd rows are shifted half a hexagon to the east.
+    pub const fn from_odd_r(col: i32, row: i32) -> Self {
+        Self::new(col - (row - (row & 1)) / 2, row)
+    }
+    /// Converts to offset coordinates in which odd rows are shifted half a hexagon to the east.
+    pub const fn to_odd_r(&self) -> (i32, i32) {
+        (self.q + (self.r - (self.r & 1)) / 2, self.r)
+    }
+    /// Returns the adjacent coordinate in `direction`.
+    pub fn neighbor(&self, direction: HexDirection) -> Self {
+        *self + direction.offset()
+    }
+    /// Returns the six adjacent coordinates, in the order of [HEX_DIRECTIONS].
+    pub fn neighbors(&self) -> [Self; 6] {
+        HEX_DIRECTIONS.map(|direction| self.neighbor(direction))
+    }
+    /// Number of steps between self and other.
+    pub fn distance(&self, other: HexCoord) -> i32 {
+        let (q, r, s) = (*self - other).cube();
+        q.abs().max(r.abs()).max(s.abs())
+    }
+    /// Gets the direction from self to other, if they are adjacent.
+    pub fn direction_to(&self, other: HexCoord) -> Option<HexDirection> {
+        let offset = other - *self;
+        HEX_DIRECTIONS
+            .into_iter()
+            .find(|direction| direction.offset() == offset)
+    }
+    /// Returns the coordinates at exactly `radius` steps, going counter-clockwise from the
+    /// south-west corner of the ring, or only self if `radius` is zero.
+    pub fn ring(&self, radius: u32) -> Vec<Self> {
+        if radius == 0 {
+            return vec![*self];
+        }
+        let mut ring = Vec::with_capacity(6 * radius as usize);
+        let mut coord = *self + HexDirection::SouthWest.offset() * radius as i32;
+        for direction in HEX_DIRECTIONS {
+            for _ in 0..radius {
+                ring.push(coord);
+                coord = coord.neighbor(direction);
+            }
+        }
+        ring
+    }
+    /// Returns the coordinates at most `radius` steps away, ring by ring from self outwards.
+    pub fn spiral(&self, radius: u32) -> Vec<Self> {
+        (0..=radius).flat_map(|radius| self.ring(radius)).collect()
+    }
+}
+
+impl fmt::Display for HexCoord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}, {}]", self.q, self.r)
+    }
+}
+
+impl Ord for HexCoord {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.r.cmp(&other.r).then(self.q.cmp(&other.q))
+    }
+}
+impl PartialOrd for HexCoord {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Add for HexCoord {
+    type Output = HexCoord;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        HexCoord::new(self.q + rhs.q, self.r + rhs.r)
+    }
+}
+
+impl Sub for HexCoord {
+    type Output = HexCoord;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        HexCoord::new(self.q - rhs.q, self.r - rhs.r)
+    }
+}
+
+impl AddAssign for HexCoord {
+    fn add_assign(&mut self, rhs: Self) {
+        self.q += rhs.q;
+        self.r += rhs.r;
+    }
+}
+
+impl SubAssign for HexCoord {
+    fn sub_assign(&mut self, rhs: Self) {
+        self.q -= rhs.q;
+        self.r -= rhs.r;
+    }
+}
+
+impl Mul<i32> for HexCoord {
+    type Output = HexCoord;
+
+    fn mul(self, rhs: i32) -> Self::Output {
+        HexCoord::new(self.q * rhs, self.r * rhs)
+    }
+}
+
+impl Neg for HexCoord {
+    type Output = HexCoord;
+
+    fn neg(self) -> Self::Output {
+        HexCoord::new(-self.q, -self.r)
+    }
+}
+
+/// One of the six directions of a grid of pointy-top hexagons, see [HexCoord].
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HexDirection {
+    East,
+    NorthEast,
+    NorthWest,
+    West,
+    SouthWest,
+    SouthEast,
+}
+
+impl HexDirection {
+    /// Returns the axial offset of a step in this direction.
+    pub const fn offset(&self) -> HexCoord {
+        match self {
+            HexDirection::East => HexCoord::new(1, 0),
+            HexDirection::NorthEast => HexCoord::new(1, -1),
+            HexDirection::NorthWest => HexCoord::new(0, -1),
+            HexDirection::West => HexCoord::new(-1, 0),
+            HexDirection::SouthWest => HexCoord::new(-1, 1),
+            HexDirection::SouthEast => HexCoord::new(0, 1),
+        }
+    }
+    /// Returns the direction pointing the other way.
+    pub const fn opposite(&self) -> Self {
+        self.rotated(3)
+    }
+    /// Returns this direction turned counter-clockwise by `steps` sixths of a turn,
+    /// clockwise if negative.
+    pub const fn rotated(&self, steps: i32) -> Self {
+        let index = (*self as i32 + steps).rem_euclid(6);
+        HEX_DIRECTIONS[index as usize]
+    }
+}
+
+impl fmt::Display for HexDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HexDirection::East => write!(f, "East"),
+            HexDirection::NorthEast => write!(f, "NorthEast"),
+            HexDirection::NorthWest => write!(f, "NorthWest"),
+            HexDirection::West => write!(f, "West"),
+            HexDirection::SouthWest => write!(f, "SouthWest"),
+            HexDirection::SouthEast => write!(f, "SouthEast"),
+        }
+    }
+}
+
+/// All hex directions, counter-clockwise from the east.
+pub const HEX_DIRECTIONS: [HexDirection; 6] = [
+    HexDirection::East,
+    HexDirection::NorthEast,
+    HexDirection::NorthWest,
+    HexDirection::West,
+    HexDirection::SouthWest,
+    HexDirection::SouthEast,
+];
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn cube() {
+        assert_eq!(HexCoord::new(2, -3).cube(), (2, -3, 1));
+        assert_eq!(HexCoord::from_cube(2, -3, 1), HexCoord::new(2, -3));
+    }
+
+    #[test]
+    fn distance() {
+        let origin = HexCoord::new(0, 0);
+        assert_eq!(origin.distance(origin), 0);
+        for neighbor in origin.neighbors() {
+            assert_eq!(origin.distance(neighbor), 1);
+        }
+        assert_eq!(origin.distance(HexCoord::new(3, -1)), 3);
+        assert_eq!(HexCoord::new(-1, 2).distance(HexCoord::new(2, -2)), 4);
+    }
+
+    #[test]
+    fn directions() {
+        let coord = HexCoord::new(1, 1);
+        for direction in HEX_DIRECTIONS {
+            assert_eq!(
+                coord.direction_to(coord.neighbor(direction)),
+                Some(direction)
+            );
+            assert_eq!(direction.opposite().opposite(), direction);
+            assert_eq!(
+                coord.neighbor(direction).neighbor(direction.opposite()),
+                coord
+            );
+        }
+        assert_eq!(coord.direction_to(HexCoord::new(3, 1)), None);
+        assert_eq!(HexDirection::East.rotated(1), HexDirection::NorthEast);
+        assert_eq!(HexDirection::East.rotated(-1), HexDirection::SouthEast);
+    }
+
+    #[test]
+    fn ring_and_spiral() {
+        let center = HexCoord::new(2, -1);
+        assert_eq!(center.ring(0), vec![center]);
+        for radius in 1..4 {
+            let ring = center.ring(radius);
+            assert_eq!(ring.len(), 6 * radius as usize);
+            assert!(ring
+                .iter()
+                .all(|coord| center.distance(*coord) == radius as i32));
+        }
+        let mut spiral = center.spiral(2);
+        assert_eq!(spiral.len(), 19);
+        spiral.sort();
+        spiral.dedup();
+        assert_eq!(spiral.len(), 19);
+    }
+
+    #[test]
+    fn round() {
+        assert_eq!(HexCoord::round(0.1, -0.1), HexCoord::new(0, 0));
+        assert_eq!(HexCoord::round(1.4, 0.4), HexCoord::new(1, 1));
+        assert_eq!(HexCoord::round(-0.6, 0.2), HexCoord::new(-1, 0));
+    }
+
+    #[test]
+    fn odd_r() {
+        for row in -3..3 {
+            for col in -3..3 {
+                assert_eq!(HexCoord::from_odd_r(col, row).to_odd_r(), (col, row));
+            }
+        }
+        assert_eq!(HexCoord::from_odd_r(1, 1), HexCoord::new(1, 1));
+        assert_eq!(HexCoord::from_odd_r(1, 2), HexCoord::new(0, 2));
+    }
+}
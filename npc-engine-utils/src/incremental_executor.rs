@@ -0,0 +1,596 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use npc_engine_core::{
+    ActiveTasks, AgentId, DomainWithPlanningTask, Instant, MCTSConfiguration, PlanningBudget,
+    PlanningTask, MCTS,
+};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    executor_core::{impl_executor_methods, ExecutorCore, Planners},
+    ExecutorSnapshot, ExecutorState, ExecutorStateGlobal, GlobalDomain, PlanningRequest,
+    SchedulingPolicy, SnapshotDomain, TuningHandle, UrgentFirst,
+};
+
+/// A single-threaded generic executor that spreads planning over frames.
+///
+/// Like the [ThreadedExecutor](crate::ThreadedExecutor), agents plan during a
+/// [PlanningTask] while the world continues to be simulated, but instead of planning
+/// in the background, each [step](Self::step) grants every ongoing planning a slice of
+/// computation defined by a [PlanningBudget].
+/// A plan is committed, meaning its best task replaces the planning task, as soon as its search is finished.
+/// If the planning task ends before, the search is completed up to
+/// [MINIMUM_VISITS](ExecutorStateGlobal::MINIMUM_VISITS) visits and committed.
+/// This allows to keep planning within a fixed frame time, for example
+/// by calling [step](Self::step) once per frame with a budget of 2 ms.
+/// With many agents, the computation of a whole step can be limited as well,
+/// see [set_step_budget](Self::set_step_budget).
+pub struct IncrementalExecutor<'a, D, S>
+where
+    D: DomainWithPlanningTask + GlobalDomain,
+    D::State: Clone,
+    S: ExecutorState<D> + ExecutorStateGlobal<D>,
+{
+    /// The state and logic shared with the other executors
+    core: ExecutorCore<'a, D, S>,
+    /// The computation granted to each planning per step
+    budget: PlanningBudget,
+    /// The computation shared by all plannings per step, if limited
+    step_budget: Option<PlanningBudget>,
+    /// The order in which agents are planned
+    scheduling_policy: Box<dyn SchedulingPolicy<D>>,
+    /// The ongoing plannings, ordered by agent for determinism
+    planners: BTreeMap<AgentId, MCTS<D>>,
+    /// Whether execution is paused
+    paused: bool,
+    /// The global tick of the simulation
+    tick: u64,
+}
+impl<'a, D, S> IncrementalExecutor<'a, D, S>
+where
+    D: DomainWithPlanningTask + GlobalDomain,
+    D::State: Clone,
+    S: ExecutorState<D> + ExecutorStateGlobal<D>,
+{
+    /// Creates a new executor, initializes state and task queue from the S trait.
+    pub fn new(
+        mcts_config: MCTSConfiguration,
+        budget: PlanningBudget,
+        executor_state: &'a mut S,
+    ) -> Self {
+        Self {
+            core: ExecutorCore::new(mcts_config, executor_state),
+            budget,
+            step_budget: None,
+            scheduling_policy: Box::new(UrgentFirst),
+            planners: Default::default(),
+            paused: false,
+            tick: 0,
+        }
+    }
+
+    /// Creates a new executor from a snapshot taken by [snapshot](Self::snapshot), restarting ongoing plannings.
+    pub fn from_snapshot(
+        mcts_config: MCTSConfiguration,
+        budget: PlanningBudget,
+        executor_state: &'a mut S,
+        snapshot: ExecutorSnapshot<D::GlobalState, D::TaskSnapshot>,
+    ) -> Self
+    where
+        D: SnapshotDomain,
+    {
+        let (tick, state, task_queue, task_history) = snapshot.restore();
+        let mut executor = Self {
+            core: ExecutorCore::with_tasks(
+                mcts_config,
+                executor_state,
+                state,
+                task_queue,
+                task_history,
+            ),
+            budget,
+            step_budget: None,
+            scheduling_policy: Box::new(UrgentFirst),
+            planners: Default::default(),
+            paused: false,
+            tick,
+        };
+        executor
+            .core
+            .restart_plannings(tick, &mut executor.planners);
+        executor
+    }
+
+    /// Runs a slice of all ongoing plannings, and commits the finished ones or those whose planning task ends.
+    ///
+    /// Returns the agents which committed a plan.
+    fn plan(&mut self, tick: u64) -> BTreeSet<AgentId> {
+        let mut planned_agents = BTreeSet::new();
+        let planning_tasks: BTreeMap<_, _> = self
+            .core
+            .queue
+            .task_queue()
+            .iter()
+            .filter(|task| task.task.downcast_ref::<PlanningTask>().is_some())
+            .map(|task| (task.agent, task.clone()))
+            .collect();
+        let mut requests: Vec<_> = self
+            .planners
+            .iter()
+            .map(|(&agent, mcts)| {
+                let planning_task = planning_tasks.get(&agent).unwrap_or_else(|| {
+                    panic!("There is a planner for {agent} but no planning task for it.")
+                });
+                PlanningRequest {
+                    agent,
+                    start_tick: mcts.start_tick(),
+                    end_tick: planning_task.end,
+                    visits_done: mcts.visits_done(),
+                }
+            })
+            .collect();
+        // Stable sort, so that agents of equal priority are planned in the order of their ids
+        requests.sort_by(|a, b| {
+            let priority = |request| {
+                self.scheduling_policy
+                    .priority(tick, &self.core.state, request)
+            };
+            priority(b).total_cmp(&priority(a))
+        });
+        let start_time = Instant::now();
+        let mut visits_done = 0;
+        for request in requests {
+            let active_agent = request.agent;
+            let planning_task = &planning_tasks[&active_agent];
+            let has_budget = match self.step_budget {
+                None => true,
+                Some(PlanningBudget::Visits(visits)) => visits_done < visits,
+                Some(PlanningBudget::Time(duration)) => start_time.elapsed() < duration,
+            };
+            let mcts = self.planners.get_mut(&active_agent).unwrap();
+            let finished = if has_budget {
+                let finished = mcts.run_with_budget(self.budget);
+                visits_done += mcts.visits_done() - request.visits_done;
+                finished
+            } else {
+                mcts.is_search_finished()
+            };
+            if !finished && planning_task.end > tick {
+                continue;
+            }
+            if !finished {
+                // Planning task is over, ensure the minimum number of visits
+                let missing_visits = S::MINIMUM_VISITS.saturating_sub(mcts.visits_done());
+                mcts.run_with_budget(PlanningBudget::Visits(missing_visits));
+            }
+            let mcts = self.planners.remove(&active_agent).unwrap();
+
+            // Replace the planning task with the best task we got from the planning
+            self.core.commit_planning(tick, planning_task, &mcts);
+            planned_agents.insert(active_agent);
+        }
+        planned_agents
+    }
+
+    /// Sets the computation granted to each planning at every step.
+    pub fn set_budget(&mut self, budget: PlanningBudget) {
+        self.budget = budget;
+    }
+
+    /// Limits the computation shared by all plannings at each step, or removes the limit if `None` (the default).
+    ///
+    /// Plannings are granted their slice of computation in the order given by the
+    /// [scheduling policy](Self::set_scheduling_policy), until the budget of the step is spent.
+    /// As slices are not interrupted, the last one can exceed this budget.
+    /// Plannings whose planning task ends are committed even if they got no computation.
+    pub fn set_step_budget(&mut self, step_budget: Option<PlanningBudget>) {
+        self.step_budget = step_budget;
+    }
+
+    /// Sets the policy deciding which agents are planned first, by default [UrgentFirst].
+    pub fn set_scheduling_policy<P>(&mut self, policy: P)
+    where
+        P: SchedulingPolicy<D> + 'static,
+    {
+        self.scheduling_policy = Box::new(policy);
+    }
+
+    impl_executor_methods!();
+
+    /// Returns a handle to change the MCTS configuration and the budgets while the executor runs, see [TuningHandle].
+    pub fn tuning_handle(&mut self) -> TuningHandle {
+        self.core.tuning_handle(Some(self.budget), self.step_budget)
+    }
+
+    /// Applies the parameters changed through the [tuning handle](Self::tuning_handle).
+    fn apply_tuning(&mut self) {
+        if let Some(tuning) = self.core.apply_tuning() {
+            if let Some(budget) = tuning.budget {
+                self.budget = budget;
+            }
+            self.step_budget = tuning.step_budget;
+        }
+    }
+
+    /// Runs a slice of all ongoing plannings, executes all tasks finishing at the current tick and then increments it.
+    ///
+    /// Returns whether execution should continue.
+    pub fn step(&mut self) -> bool {
+        if self.core.queue.is_empty() {
+            return false;
+        }
+        if self.paused {
+            return true;
+        }
+
+        self.apply_tuning();
+        let tick = self.tick;
+        if !self.core.executor_state.keep_execution(
+            tick,
+            self.core.queue.task_queue(),
+            &self.core.state,
+        ) {
+            return false;
+        }
+        self.core.monitor_tasks(tick);
+        self.core.replan_dirty_agents(tick, &mut self.planners);
+        let planned_agents = self.plan(tick);
+        self.core.coordinate_plans(tick, &planned_agents);
+        self.core.replan_dirty_agents(tick, &mut self.planners);
+        self.core.execute_finished_tasks(tick, &mut self.planners);
+        self.core
+            .executor_state
+            .post_step_hook(tick, &mut self.core.state);
+
+        self.tick += 1;
+        true
+    }
+
+    /// Pauses execution: until [resume](Self::resume) is called, [step](Self::step) does nothing.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Resumes execution after [pause](Self::pause).
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    /// Returns whether execution is paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Takes a serializable snapshot of the execution, to be restored with [from_snapshot](Self::from_snapshot).
+    pub fn snapshot(&self) -> ExecutorSnapshot<D::GlobalState, D::TaskSnapshot>
+    where
+        D: SnapshotDomain,
+        D::GlobalState: Clone,
+    {
+        ExecutorSnapshot::new(
+            self.tick,
+            self.core.state.clone(),
+            self.core.queue.task_queue(),
+            &self.core.task_history,
+        )
+    }
+
+    /// Gets the current tick.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Gets the global state, read-only.
+    pub fn state(&self) -> &D::GlobalState {
+        &self.core.state
+    }
+
+    /// Gets the active tasks, read-only.
+    pub fn active_tasks(&self) -> &ActiveTasks<D> {
+        self.core.queue.task_queue()
+    }
+
+    /// Gets the number of active agents in the execution queue.
+    pub fn agents_count(&self) -> usize {
+        self.core.queue.size()
+    }
+
+    /// Gets the number of agents currently planning.
+    pub fn planning_agents_count(&self) -> usize {
+        self.planners.len()
+    }
+}
+
+impl<D, S> Planners<D, S> for BTreeMap<AgentId, MCTS<D>>
+where
+    D: DomainWithPlanningTask + GlobalDomain,
+    D::State: Clone,
+    S: ExecutorState<D> + ExecutorStateGlobal<D>,
+{
+    fn start(
+        &mut self,
+        core: &mut ExecutorCore<'_, D, S>,
+        tick: u64,
+        active_agent: AgentId,
+        end_tick: u64,
+    ) {
+        core.executor_state.on_plan_started(tick, active_agent);
+        log::info!("T{tick} - {active_agent} starts planning until at most T{end_tick}.");
+        let mcts = core.new_mcts(tick, active_agent, None);
+        self.insert(active_agent, mcts);
+    }
+
+    fn drop_planning(&mut self, agent: AgentId) {
+        self.remove(&agent);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::tests::{trivial_mcts_config, TrivialDomain, TrivialExecutorState};
+    use crate::{ImportantFirst, LongestWaitingFirst};
+    use npc_engine_core::{ActiveTask, IdleTask};
+
+    #[test]
+    fn incremental_executor_trivial_domain() {
+        let mut executor_state = TrivialExecutorState(4);
+        let mut executor = IncrementalExecutor::new(
+            trivial_mcts_config(),
+            PlanningBudget::Visits(2),
+            &mut executor_state,
+        );
+        // All agents idle at tick 0 and then plan
+        assert!(executor.step());
+        assert_eq!(executor.planning_agents_count(), 4);
+        // Agent plans with 5 + agent id visits, hence agents 0 and 1 finish after 3 slices
+        for _ in 0..3 {
+            assert!(executor.step());
+        }
+        assert_eq!(executor.planning_agents_count(), 2);
+        while executor.step() {}
+        assert_eq!(executor.tick(), 25);
+        assert_eq!(executor.agents_count(), 4);
+    }
+
+    #[test]
+    fn incremental_executor_schedules_plannings() {
+        let mut executor_state = TrivialExecutorState(3);
+        let mut executor = IncrementalExecutor::new(
+            trivial_mcts_config(),
+            PlanningBudget::Visits(1),
+            &mut executor_state,
+        );
+        executor.set_step_budget(Some(PlanningBudget::Visits(1)));
+        executor.set_scheduling_policy(ImportantFirst(|agent: AgentId, _: &()| agent.0 as f32));
+        let visits = |executor: &IncrementalExecutor<_, _>| -> Vec<_> {
+            executor.planners.values().map(MCTS::visits_done).collect()
+        };
+        // Idle at tick 0 then start planning
+        executor.step();
+        executor.step();
+        assert_eq!(visits(&executor), [0, 0, 1]);
+        // All agents waited the same time, so the first one is planned
+        executor.set_scheduling_policy(LongestWaitingFirst);
+        executor.step();
+        assert_eq!(visits(&executor), [1, 0, 1]);
+    }
+
+    #[derive(Default)]
+    struct EventCounter {
+        plans_started: u32,
+        tasks_chosen: u32,
+        tasks_completed: u32,
+        tasks_failed: u32,
+        agents_idle: u32,
+        first_task_end: u64,
+        keep_tasks: bool,
+    }
+    impl ExecutorStateGlobal<TrivialDomain> for EventCounter {
+        const MINIMUM_VISITS: u32 = 0;
+        fn create_initial_state(&self) {}
+        fn init_task_queue(&self, _: &()) -> ActiveTasks<TrivialDomain> {
+            let task =
+                ActiveTask::new_with_end(0, self.first_task_end, AgentId(0), Box::new(IdleTask));
+            [task].into_iter().collect()
+        }
+        fn keep_task(&self, _: u64, _: &(), _: &ActiveTask<TrivialDomain>) -> bool {
+            self.keep_tasks
+        }
+    }
+    impl ExecutorState<TrivialDomain> for EventCounter {
+        fn on_plan_started(&mut self, _tick: u64, _agent: AgentId) {
+            self.plans_started += 1;
+        }
+        fn on_task_chosen(&mut self, _active_task: &ActiveTask<TrivialDomain>) {
+            self.tasks_chosen += 1;
+        }
+        fn on_task_completed(&mut self, _active_task: &ActiveTask<TrivialDomain>) {
+            self.tasks_completed += 1;
+        }
+        fn on_task_failed(&mut self, _active_task: &ActiveTask<TrivialDomain>) {
+            self.tasks_failed += 1;
+        }
+        fn on_agent_idle(&mut self, _tick: u64, _agent: AgentId) {
+            self.agents_idle += 1;
+        }
+    }
+
+    #[test]
+    fn incremental_executor_lifecycle_hooks() {
+        let mut event_counter = EventCounter::default();
+        let mut executor = IncrementalExecutor::new(
+            trivial_mcts_config(),
+            PlanningBudget::Visits(5),
+            &mut event_counter,
+        );
+        // Idle at tick 0, plan at tick 1, idle again at tick 2, and so on
+        for _ in 0..6 {
+            executor.step();
+        }
+        assert_eq!(event_counter.plans_started, 3);
+        assert_eq!(event_counter.tasks_chosen, 3);
+        assert_eq!(event_counter.agents_idle, 3);
+        assert_eq!(event_counter.tasks_completed, 3);
+    }
+
+    #[test]
+    fn incremental_executor_replans_dirty_agents() {
+        let mut event_counter = EventCounter::default();
+        let mut executor = IncrementalExecutor::new(
+            trivial_mcts_config(),
+            PlanningBudget::Visits(3),
+            &mut event_counter,
+        );
+        // Idle at tick 0 then start planning
+        executor.step();
+        // Make some progress in planning, then replan
+        executor.step();
+        executor.mark_dirty_where(|agent, _| agent == AgentId(0));
+        executor.step();
+        assert_eq!(executor.planning_agents_count(), 1);
+        executor.step();
+        assert_eq!(executor.planning_agents_count(), 0);
+        assert_eq!(event_counter.plans_started, 2);
+        assert_eq!(event_counter.tasks_chosen, 1);
+    }
+
+    #[test]
+    fn incremental_executor_monitors_tasks() {
+        let mut event_counter = EventCounter {
+            first_task_end: 5,
+            keep_tasks: true,
+            ..Default::default()
+        };
+        let mut executor = IncrementalExecutor::new(
+            trivial_mcts_config(),
+            PlanningBudget::Visits(1),
+            &mut event_counter,
+        );
+        executor.set_task_monitoring(true);
+        executor.step();
+        assert_eq!(executor.planning_agents_count(), 0);
+        executor.core.executor_state.keep_tasks = false;
+        executor.step();
+        assert_eq!(executor.planning_agents_count(), 1);
+        assert_eq!(event_counter.tasks_failed, 1);
+        assert_eq!(event_counter.plans_started, 1);
+    }
+
+    /// Two agents whose tasks always conflict
+    struct ConflictingAgents {
+        prioritize_last_agent: bool,
+        tasks_failed: u32,
+    }
+    impl ExecutorStateGlobal<TrivialDomain> for ConflictingAgents {
+        const MINIMUM_VISITS: u32 = 0;
+        fn create_initial_state(&self) {}
+        fn init_task_queue(&self, _: &()) -> ActiveTasks<TrivialDomain> {
+            (0..2)
+                .map(|agent| ActiveTask::new_with_end(0, 0, AgentId(agent), Box::new(IdleTask)))
+                .collect()
+        }
+        fn tasks_conflict(
+            &self,
+            _: u64,
+            _: &(),
+            _: &ActiveTask<TrivialDomain>,
+            _: &ActiveTask<TrivialDomain>,
+        ) -> bool {
+            true
+        }
+        fn agent_priority(&self, _: u64, _: &(), agent: AgentId) -> i32 {
+            if self.prioritize_last_agent {
+                agent.0 as i32
+            } else {
+                0
+            }
+        }
+    }
+    impl ExecutorState<TrivialDomain> for ConflictingAgents {
+        fn on_task_failed(&mut self, _active_task: &ActiveTask<TrivialDomain>) {
+            self.tasks_failed += 1;
+        }
+    }
+
+    #[test]
+    fn incremental_executor_coordinates_plans() {
+        for (prioritize_last_agent, replanning_agent) in [(false, AgentId(1)), (true, AgentId(0))] {
+            let mut executor_state = ConflictingAgents {
+                prioritize_last_agent,
+                tasks_failed: 0,
+            };
+            let mut executor = IncrementalExecutor::new(
+                trivial_mcts_config(),
+                PlanningBudget::Visits(5),
+                &mut executor_state,
+            );
+            executor.set_coordination(true);
+            // Idle at tick 0, then both agents plan and commit conflicting tasks at tick 1
+            executor.step();
+            executor.step();
+            assert_eq!(executor.planning_agents_count(), 1);
+            assert!(executor.planners.contains_key(&replanning_agent));
+            assert_eq!(executor_state.tasks_failed, 1);
+        }
+    }
+
+    #[test]
+    fn incremental_executor_snapshot() {
+        let mut executor_state = TrivialExecutorState(3);
+        let mut executor = IncrementalExecutor::new(
+            trivial_mcts_config(),
+            PlanningBudget::Visits(1),
+            &mut executor_state,
+        );
+        executor.step();
+        executor.step();
+        executor.pause();
+        assert!(executor.step());
+        assert_eq!(executor.tick(), 2);
+        let snapshot = serde_json::to_string(&executor.snapshot()).unwrap();
+        let snapshot = serde_json::from_str(&snapshot).unwrap();
+        let mut executor_state = TrivialExecutorState(3);
+        let mut executor = IncrementalExecutor::from_snapshot(
+            trivial_mcts_config(),
+            PlanningBudget::Visits(1),
+            &mut executor_state,
+            snapshot,
+        );
+        assert_eq!(executor.tick(), 2);
+        assert_eq!(executor.agents_count(), 3);
+        assert_eq!(executor.planning_agents_count(), 3);
+        while executor.step() {}
+        assert_eq!(executor.tick(), 25);
+    }
+
+    #[test]
+    fn incremental_executor_adds_and_removes_agents() {
+        let mut executor_state = TrivialExecutorState(2);
+        let mut executor = IncrementalExecutor::new(
+            trivial_mcts_config(),
+            PlanningBudget::Visits(1),
+            &mut executor_state,
+        );
+        executor.step();
+        assert_eq!(executor.planning_agents_count(), 2);
+        assert!(!executor.add_agent(AgentId(1)));
+        assert!(executor.add_agent(AgentId(5)));
+        assert_eq!(executor.agents_count(), 3);
+        assert_eq!(executor.planning_agents_count(), 3);
+        let removed = executor.remove_agent(AgentId(0)).unwrap();
+        assert!(removed.task.downcast_ref::<PlanningTask>().is_some());
+        assert!(executor.remove_agent(AgentId(0)).is_none());
+        assert_eq!(executor.agents_count(), 2);
+        assert_eq!(executor.planning_agents_count(), 2);
+        while executor.step() {}
+        assert!(executor
+            .active_tasks()
+            .iter()
+            .all(|task| task.agent != AgentId(0)));
+        assert_eq!(executor.agents_count(), 2);
+    }
+}
@@ -7,29 +7,157 @@
 //!
 //! It contains the following features:
 //! - A helper trait [OptionDiffDomain] that can be used when [Diffs](Domain::Diff) are just copies of the [State](Domain::State).
+//! - A shared view of an external world such as an ECS world ([WorldView]), usable as the state of a domain without copying the world for each planning, along with diffs recording the changes of tasks on top of it ([WorldDiff]).
 //! - Two executors (update loops), [SimpleExecutor] and [ThreadedExecutor], that implement the execution logic of a [Domain] beyond planning itself, and related abstractions.
+//! - A step-debugging mode of [SimpleExecutor] ([SimpleExecutor::step_until_decision]) to inspect and override decisions one at a time, and an interactive debugger built on it ([run_step_debugger]).
+//! - An executor spreading planning over frames with a fixed computation budget per frame ([IncrementalExecutor]), to keep planning within the frame time of a game.
+//! - A group of executors ([ExecutorGroup]) stepping several independent domains, each with its own agents and schedule, from a single update loop.
+//! - Policies deciding which agents plan first when the computation per frame is limited ([SchedulingPolicy]).
+//! - With feature `rayon`, a mode of [ThreadedExecutor] that plans all agents on a shared thread pool ([ThreadedExecutor::new_with_thread_pool]).
+//! - With feature `rayon`, a root-parallel search ([run_root_parallel]) exploring the children of the root in independent subtrees on a thread pool and merging their statistics, with a deterministic mode ([run_root_parallel_deterministic]) for lockstep simulations.
+//! - With feature `tokio`, an asynchronous executor ([AsyncExecutor]) that plans on a [tokio](https://tokio.rs) runtime without blocking the caller.
+//! - With feature `tracing`, [tracing](https://docs.rs/tracing) instrumentation of the planners, see the `tracing` feature of [npc_engine_core].
+//! - An optional coordination pass in the executors, replanning agents whose planned tasks conflict ([ExecutorStateGlobal::tasks_conflict]).
+//! - Optional intention sharing in the executors, using the last tasks of other agents as their fixed first moves when planning ([ThreadedExecutor::set_intention_sharing]).
+//! - Per-agent metrics of each planning ([PlanningMetrics]), reported by all executors through [ExecutorState::on_planning_metrics].
+//! - An append-only structured log of decisions ([DecisionLog]) in JSON Lines, with the chosen task, its alternatives, its value and the number of visits.
+//! - With feature `metrics-export`, an exporter of planner health metrics ([MetricsExporter]) in the Prometheus format, with an HTTP endpoint ([MetricsServer]) for production dashboards.
+//! - With feature `hook-timing`, the calls and time spent in domain hooks in these metrics, see the `hook-timing` feature of [npc_engine_core].
+//! - Per-agent levels of detail of planning ([PlanningDetail]), setting how often and how much each agent plans, so that large worlds scale.
+//! - Runtime tuning of the MCTS configuration and budgets of the executors through a shared handle ([TuningHandle]), to adjust AI quality like graphics settings.
+//! - Planner settings ([PlannerSettings]) loaded and validated from JSON or, with feature `toml`, TOML files, so that tuning does not require recompiling.
+//! - Generic scenario descriptions ([Scenario]) with initial agents, map data such as a [GridMap] and value weights, loaded from JSON or, with feature `ron`, RON files.
+//! - A headless batch runner ([run_batch]) executing many simulations with different seeds and configurations in parallel, and aggregating their statistics in a [BatchReport].
+//! - Tables of per-tick agent metrics and world summaries of simulations ([SimulationData]), written as Arrow and Parquet files by the `npc-engine-arrow` crate for analysis with pandas or polars.
+//! - A test-support harness simulating a domain over many seeds ([run_regression]) and asserting aggregate properties such as the mean value, the absence of panics and of starvation ([RegressionReport]).
+//! - A comparison of two configurations or domain variants on the same seeds ([compare_runs]), with win rates and a paired t-test of their scores ([ComparisonReport]).
+//! - Serializable snapshots of executors ([ExecutorSnapshot]) for domains implementing [SnapshotDomain], to save and restore simulations.
+//! - A recorder of executed tasks ([ReplayRecorder]) and a player re-executing them while verifying the resulting states ([play_replay]).
+//! - A versioned interchange format of replays ([ReplayFile]) with keyframes and metadata, in JSON or, with feature `bincode`, binary, shared by games, the command-line tool, the web demo and tests.
+//! - A debug check of the diffs of search trees ([validate_diffs]), re-deriving node states by replaying their paths on full states.
+//! - Capture of plannings that panic or produce NaN values into repro files ([run_capturing_repro]), that can be re-played deterministically in unit tests ([PlanningRepro::replay]).
+//! - A recorder of the tasks of all agents ([TimelineRecorder]) into a Gantt-style [Timeline], exported as JSON or SVG, to spot idle gaps and coordination failures.
+//! - Manifests of runs ([RunManifest]), recording the versions, configuration, seed and time of a run alongside its results.
 //! - A simple implementation of feed-forward leaky ReLU neurons ([Neuron]) and corresponding simple networks ([NeuralNetwork]), providing learning based on back-propagation ([NeuralNetwork::train]).
 //! - Simple 2-D coordinates ([Coord2D]) and direction ([Direction]) implementations.
+//! - Pathfinding over closures giving the steps from a node: A* ([astar], with [astar_grid] and [astar_hex] for grids), flow fields ([FlowField]) and a cache of paths ([PathCache]), to back tasks walking somewhere.
+//! - Axial coordinates of hex grids ([HexCoord]) and their six directions ([HexDirection]), with distances, neighbors, rings and conversions from cube and offset coordinates.
+//! - For domains on grids ([GridDomain]), a heatmap of the values of movement tasks at the root of a search ([export_value_heatmap]), exported as CSV or, with feature `png`, PNG images.
 //! - Helper functions to plot search trees: [plot_tree_in_tmp] and [plot_tree_in_tmp_with_task_name].
+//! - Charts of search statistics ([plot_root_tasks], [plot_tree_depths]) and of values over time ([plot_values]) as [Svg] images, shown inline in [evcxr](https://github.com/evcxr/evcxr) notebooks.
+//! - A compact JSON export of search trees for web-based viewers ([export_tree_json]), nested or flat, with depth and visit filters.
+//! - An export of the principal variation of a search ([export_plan]), with durations and expected values of tasks, for external tools and scripting systems.
+//! - An export of replays as [PDDL](https://planning.wiki) problems and plans ([PddlExport]) for domains describing their states and tasks in PDDL ([PddlDomain]), to check and compare plans with classical planners.
+//! - With feature `tree-server`, an HTTP and WebSocket server ([TreeServer]) with a bundled web page, to watch the latest trees and the decisions of agents live.
+//! - With feature `live-control`, a WebSocket server ([LiveControlServer]) with a small JSON protocol ([LiveCommand]) for external tools to pause execution, request the tree of an agent, override its decision or change the configuration live.
+//! - With feature `message-protocol`, a transport-agnostic JSON message protocol ([MessageProtocol]) of plan requests, decisions and streamed plan steps, designed for C# clients such as Unity games, with a reference TCP host ([ProtocolHost]).
+//! - With feature `egui`, an [egui](https://docs.rs/egui) widget ([PlannerInspector]) showing the tree, the edge statistics and the plan of a selected agent, for debug overlays.
 //! - Helper functions to simplify functional programming with tuples: [keep_first] and [keep_second], and their mutable versions [keep_first_mut] and [keep_second_mut].
 
 #[cfg(doc)]
 use npc_engine_core::Domain;
 
+#[cfg(feature = "tokio")]
+mod async_executor;
+mod batch;
+mod comparison;
 mod coord2d;
+mod decision_log;
+mod diff_validation;
 mod direction;
+#[cfg(feature = "egui")]
+mod egui_inspector;
 mod executor;
+mod executor_core;
+mod executor_group;
 mod functional;
 mod global_domain;
 mod graphs;
+mod heatmap;
+mod hex;
+mod incremental_executor;
+#[cfg(feature = "live-control")]
+mod live_control;
+mod manifest;
+#[cfg(feature = "message-protocol")]
+mod message_protocol;
+mod metrics;
+#[cfg(feature = "metrics-export")]
+mod metrics_export;
 mod neuron;
 mod option_state_diff;
+mod pathfinding;
+mod pddl;
+mod plan_export;
+mod planning_detail;
+mod plots;
+mod regression;
+mod replay;
+mod replay_format;
+mod repro;
+#[cfg(feature = "rayon")]
+mod root_parallel;
+mod scenario;
+mod scheduling;
+mod settings;
+mod simulation_data;
+mod snapshot;
+mod step_debugger;
+mod timeline;
+mod tree_export;
+#[cfg(feature = "tree-server")]
+mod tree_server;
+mod tuning;
+mod world_view;
 
+#[cfg(feature = "tokio")]
+pub use async_executor::*;
+pub use batch::*;
+pub use comparison::*;
 pub use coord2d::*;
+pub use decision_log::*;
+pub use diff_validation::*;
 pub use direction::*;
+#[cfg(feature = "egui")]
+pub use egui_inspector::*;
 pub use executor::*;
+pub use executor_group::*;
 pub use functional::*;
 pub use global_domain::*;
 pub use graphs::*;
+pub use heatmap::*;
+pub use hex::*;
+pub use incremental_executor::*;
+#[cfg(feature = "live-control")]
+pub use live_control::*;
+pub use manifest::*;
+#[cfg(feature = "message-protocol")]
+pub use message_protocol::*;
+pub use metrics::*;
+#[cfg(feature = "metrics-export")]
+pub use metrics_export::*;
 pub use neuron::*;
 pub use option_state_diff::*;
+pub use pathfinding::*;
+pub use pddl::*;
+pub use plan_export::*;
+pub use planning_detail::*;
+pub use plots::*;
+pub use regression::*;
+pub use replay::*;
+pub use replay_format::*;
+pub use repro::*;
+#[cfg(feature = "rayon")]
+pub use root_parallel::*;
+pub use scenario::*;
+pub use scheduling::*;
+pub use settings::*;
+pub use simulation_data::*;
+pub use snapshot::*;
+pub use step_debugger::*;
+pub use timeline::*;
+pub use tree_export::*;
+#[cfg(feature = "tree-server")]
+pub use tree_server::*;
+pub use tuning::*;
+pub use world_view::*;
@@ -0,0 +1,498 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io,
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use npc_engine_core::{AgentId, Domain, MCTSConfiguration, MCTS};
+use serde::{Deserialize, Serialize};
+use tungstenite::{Message, WebSocket};
+
+use crate::{
+    export_tree_nested, DebugStep, ExecutableDomain, ExecutorState, ExecutorStateLocal,
+    NestedTreeNode, PendingDecision, SimpleExecutor, TreeExportOptions, Tuning, TuningHandle,
+};
+
+/// A command sent by a client of a [LiveControlServer], as a JSON object tagged by `type`.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LiveCommand {
+    /// Pauses execution, `{"type": "pause"}`
+    Pause,
+    /// Resumes execution, `{"type": "resume"}`
+    Resume,
+    /// Requests the tree of the next planning of an agent, `{"type": "request_tree", "agent": 0}`
+    RequestTree { agent: AgentId },
+    /// Replaces the next decision of an agent by the root task whose debug name is `task`,
+    /// `{"type": "override", "agent": 0, "task": "Move(Up)"}`
+    Override { agent: AgentId, task: String },
+    /// Changes the MCTS configuration of new plannings, `{"type": "set_config", "visits": 100}`
+    SetConfig(ConfigChange),
+}
+
+/// A change of some parameters of an [MCTSConfiguration], the others being kept.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigChange {
+    /// The maximum number of visits per run
+    pub visits: Option<u32>,
+    /// The maximum tree depth per run in ticks
+    pub depth: Option<u32>,
+    /// The exploration factor of UCT
+    pub exploration: Option<f32>,
+    /// The discount factor for later reward, in half life
+    pub discount_hl: Option<f32>,
+    /// The seed of new plannings
+    pub seed: Option<u64>,
+}
+
+impl ConfigChange {
+    /// Applies the change to `config`.
+    pub fn apply(&self, config: &mut MCTSConfiguration) {
+        if let Some(visits) = self.visits {
+            config.visits = visits;
+        }
+        if let Some(depth) = self.depth {
+            config.depth = depth;
+        }
+        if let Some(exploration) = self.exploration {
+            config.exploration = exploration;
+        }
+        if let Some(discount_hl) = self.discount_hl {
+            config.discount_hl = discount_hl;
+        }
+        if let Some(seed) = self.seed {
+            config.seed = Some(seed);
+        }
+    }
+}
+
+/// A message sent to the clients of a [LiveControlServer].
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum LiveReply<'a> {
+    Ok,
+    Error {
+        message: &'a str,
+    },
+    Tree {
+        agent: AgentId,
+        tick: u64,
+        tree: NestedTreeNode,
+    },
+    Overridden {
+        agent: AgentId,
+        tick: u64,
+        task: &'a str,
+    },
+}
+
+impl LiveReply<'_> {
+    fn to_message(&self) -> Message {
+        Message::text(serde_json::to_string(self).expect("Live replies are always serializable"))
+    }
+}
+
+#[derive(Default)]
+struct ControlState {
+    running: AtomicBool,
+    paused: AtomicBool,
+    clients: Mutex<Vec<WebSocket<TcpStream>>>,
+    requested_trees: Mutex<BTreeSet<AgentId>>,
+    overrides: Mutex<BTreeMap<AgentId, String>>,
+    /// The handle to the parameters of the controlled executor, and the version last applied by [LiveControlServer::step]
+    tuning: Mutex<Option<(TuningHandle, u64)>>,
+    options: Mutex<TreeExportOptions>,
+}
+
+impl ControlState {
+    fn broadcast(&self, reply: &LiveReply) {
+        let message = reply.to_message();
+        // Forget clients that cannot be written to
+        self.clients
+            .lock()
+            .unwrap()
+            .retain_mut(|client| is_alive(client.send(message.clone())));
+    }
+
+    /// Handles a command received as text, and returns the reply to its sender.
+    fn handle(&self, text: &str) -> Message {
+        let command = match serde_json::from_str(text) {
+            Ok(command) => command,
+            Err(error) => {
+                let message = format!("invalid command: {error}");
+                return LiveReply::Error { message: &message }.to_message();
+            }
+        };
+        match command {
+            LiveCommand::Pause => self.paused.store(true, Ordering::Relaxed),
+            LiveCommand::Resume => self.paused.store(false, Ordering::Relaxed),
+            LiveCommand::RequestTree { agent } => {
+                self.requested_trees.lock().unwrap().insert(agent);
+            }
+            LiveCommand::Override { agent, task } => {
+                self.overrides.lock().unwrap().insert(agent, task);
+            }
+            LiveCommand::SetConfig(change) => {
+                if let Err(message) = self.set_config(change) {
+                    return LiveReply::Error { message: &message }.to_message();
+                }
+            }
+        }
+        LiveReply::Ok.to_message()
+    }
+
+    fn set_config(&self, change: ConfigChange) -> Result<(), String> {
+        let tuning = self.tuning.lock().unwrap();
+        let (handle, _) = tuning.as_ref().ok_or("no executor is controlled")?;
+        let mut mcts_config = handle.get().mcts_config;
+        change.apply(&mut mcts_config);
+        mcts_config
+            .validate()
+            .map_err(|error| format!("invalid configuration: {error:?}"))?;
+        handle.update(|tuning| tuning.mcts_config = mcts_config);
+        Ok(())
+    }
+
+    /// Reads the pending commands of all clients and answers them, returns whether any was read.
+    fn poll_clients(&self) -> bool {
+        let mut any_read = false;
+        self.clients.lock().unwrap().retain_mut(|client| loop {
+            match client.read() {
+                Ok(Message::Text(text)) => {
+                    any_read = true;
+                    let reply = self.handle(&text);
+                    if !is_alive(client.send(reply)) {
+                        return false;
+                    }
+                }
+                Ok(Message::Close(_)) => return false,
+                Ok(_) => {}
+                Err(tungstenite::Error::Io(error)) if error.kind() == io::ErrorKind::WouldBlock => {
+                    return true
+                }
+                Err(_) => return false,
+            }
+        });
+        any_read
+    }
+}
+
+/// Returns whether a client is still usable after a write, a full send buffer being flushed later.
+fn is_alive(result: tungstenite::Result<()>) -> bool {
+    match result {
+        Ok(()) => true,
+        Err(tungstenite::Error::Io(error)) => error.kind() == io::ErrorKind::WouldBlock,
+        Err(_) => false,
+    }
+}
+
+/// A WebSocket server to control an executor live, for example from an external tool while playing the game.
+///
+/// Clients connect to any path and exchange JSON messages tagged by `type`.
+/// They send [LiveCommand]s, each answered by `{"type": "ok"}` or `{"type": "error", "message": ...}`, to:
+/// - pause and resume execution,
+/// - request the tree of the next planning of an agent, sent to all clients as `{"type": "tree", "agent", "tick", "tree"}`
+///   in the format of [export_tree_nested],
+/// - override the next decision of an agent by one of the tasks at the root of its tree, notified as
+///   `{"type": "overridden", "agent", "tick", "task"}`,
+/// - change the MCTS configuration of new plannings.
+///
+/// A [SimpleExecutor] is controlled by stepping it through [step](Self::step).
+/// Other executors are controlled by attaching their [TuningHandle] with [set_tuning_handle](Self::set_tuning_handle),
+/// following [is_paused](Self::is_paused), and calling [publish_tree](Self::publish_tree) from
+/// [post_mcts_run_hook](crate::ExecutorState::post_mcts_run_hook); decisions then cannot be overridden.
+/// The server stops when dropped.
+pub struct LiveControlServer {
+    state: Arc<ControlState>,
+    local_addr: SocketAddr,
+}
+
+impl LiveControlServer {
+    /// Starts a server listening on the given address, serving clients on a background thread.
+    pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        let local_addr = listener.local_addr()?;
+        let state = Arc::new(ControlState::default());
+        state.running.store(true, Ordering::Relaxed);
+        let thread_state = state.clone();
+        thread::Builder::new()
+            .name("live-control".into())
+            .spawn(move || {
+                while thread_state.running.load(Ordering::Relaxed) {
+                    let accepted = match listener.accept() {
+                        Ok((stream, _)) => {
+                            if let Err(error) = accept_client(stream, &thread_state) {
+                                log::debug!("Live control connection failed: {error}");
+                            }
+                            true
+                        }
+                        Err(error) if error.kind() == io::ErrorKind::WouldBlock => false,
+                        Err(error) => {
+                            log::warn!("Live control cannot accept: {error}");
+                            false
+                        }
+                    };
+                    if !thread_state.poll_clients() && !accepted {
+                        thread::sleep(Duration::from_millis(10));
+                    }
+                }
+            })?;
+        Ok(Self { state, local_addr })
+    }
+
+    /// Returns the address the server listens on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Returns the number of connected clients.
+    pub fn client_count(&self) -> usize {
+        self.state.clients.lock().unwrap().len()
+    }
+
+    /// Sets the part of the trees to send, see [TreeExportOptions].
+    pub fn set_export_options(&self, options: TreeExportOptions) {
+        *self.state.options.lock().unwrap() = options;
+    }
+
+    /// Attaches the handle through which configuration changes are applied, see for example [ThreadedExecutor::tuning_handle](crate::ThreadedExecutor::tuning_handle).
+    pub fn set_tuning_handle(&self, handle: TuningHandle) {
+        *self.state.tuning.lock().unwrap() = Some((handle, 0));
+    }
+
+    /// Returns whether a client paused execution.
+    pub fn is_paused(&self) -> bool {
+        self.state.paused.load(Ordering::Relaxed)
+    }
+
+    /// Sends the tree of a planning to all clients if one requested it, and returns whether it was sent.
+    pub fn publish_tree<D: Domain>(&self, mcts: &MCTS<D>) -> bool {
+        if !self
+            .state
+            .requested_trees
+            .lock()
+            .unwrap()
+            .remove(&mcts.agent())
+        {
+            return false;
+        }
+        let options = *self.state.options.lock().unwrap();
+        self.state.broadcast(&LiveReply::Tree {
+            agent: mcts.agent(),
+            tick: mcts.start_tick(),
+            tree: export_tree_nested(mcts, &options),
+        });
+        true
+    }
+
+    /// Applies the override requested for the agent of a decision, if any, and returns whether the task was replaced.
+    ///
+    /// The override is consumed even if no root task has the requested name.
+    pub fn apply_override<D: Domain>(&self, decision: &mut PendingDecision<D>) -> bool {
+        let name = match self
+            .state
+            .overrides
+            .lock()
+            .unwrap()
+            .remove(&decision.agent())
+        {
+            Some(name) => name,
+            None => return false,
+        };
+        let task = decision
+            .options()
+            .into_iter()
+            .map(|(task, _, _)| task)
+            .find(|task| format!("{task:?}") == name);
+        match task {
+            Some(task) => {
+                decision.override_task(task);
+                self.state.broadcast(&LiveReply::Overridden {
+                    agent: decision.agent(),
+                    tick: decision.tick(),
+                    task: &name,
+                });
+                true
+            }
+            None => {
+                let message = format!("{} has no task {name} to override", decision.agent());
+                log::warn!("{message}");
+                self.state
+                    .broadcast(&LiveReply::Error { message: &message });
+                false
+            }
+        }
+    }
+
+    /// Executes one task of a [SimpleExecutor] under the control of the clients, returns whether there are still tasks in the queue.
+    ///
+    /// While paused, nothing is executed.
+    /// Otherwise, configuration changes are applied first, and if the agent plans,
+    /// its tree is sent if requested and its decision overridden if requested.
+    pub fn step<D, S>(&self, executor: &mut SimpleExecutor<D, S>) -> bool
+    where
+        D: ExecutableDomain,
+        D::State: Clone,
+        S: ExecutorState<D> + ExecutorStateLocal<D>,
+    {
+        {
+            let mut tuning = self.state.tuning.lock().unwrap();
+            let (handle, version) = tuning.get_or_insert_with(|| {
+                let handle = TuningHandle::new(Tuning {
+                    mcts_config: executor.mcts_config().clone(),
+                    budget: None,
+                    step_budget: None,
+                });
+                (handle, 0)
+            });
+            if let Some(tuning) = handle.changes_since(version) {
+                executor.set_mcts_config(tuning.mcts_config);
+            }
+        }
+        if self.is_paused() {
+            return !executor.active_tasks().is_empty();
+        }
+        match executor.step_until_decision() {
+            DebugStep::Finished => false,
+            DebugStep::Executed => true,
+            DebugStep::Decision(mut decision) => {
+                self.publish_tree(decision.mcts());
+                self.apply_override(&mut decision);
+                executor.commit_decision(*decision);
+                true
+            }
+        }
+    }
+}
+
+impl Drop for LiveControlServer {
+    fn drop(&mut self) {
+        self.state.running.store(false, Ordering::Relaxed);
+    }
+}
+
+fn accept_client(stream: TcpStream, state: &ControlState) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(Duration::from_secs(1)))?;
+    stream.set_write_timeout(Some(Duration::from_millis(100)))?;
+    let client = tungstenite::accept(stream)
+        .map_err(|error| io::Error::new(io::ErrorKind::Other, error.to_string()))?;
+    // Commands are polled along with the other clients
+    client.get_ref().set_nonblocking(true)?;
+    state.clients.lock().unwrap().push(client);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::tests::{counter_mcts_config, CounterDomain, CounterExecutorState};
+    use serde_json::Value;
+
+    struct Client(WebSocket<TcpStream>);
+    impl Client {
+        fn connect(server: &LiveControlServer) -> Self {
+            let addr = server.local_addr();
+            let stream = TcpStream::connect(addr).unwrap();
+            let (client, _) = tungstenite::client(format!("ws://{addr}/"), stream).unwrap();
+            while server.client_count() == 0 {
+                thread::sleep(Duration::from_millis(5));
+            }
+            Self(client)
+        }
+
+        fn receive(&mut self) -> Value {
+            serde_json::from_str(self.0.read().unwrap().to_text().unwrap()).unwrap()
+        }
+
+        fn send(&mut self, command: &str) -> Value {
+            self.0.send(Message::text(command)).unwrap();
+            self.receive()
+        }
+    }
+
+    #[test]
+    fn commands_are_parsed() {
+        let command: LiveCommand =
+            serde_json::from_str(r#"{"type": "set_config", "visits": 10, "seed": 3}"#).unwrap();
+        assert_eq!(
+            command,
+            LiveCommand::SetConfig(ConfigChange {
+                visits: Some(10),
+                seed: Some(3),
+                ..Default::default()
+            })
+        );
+        let command: LiveCommand =
+            serde_json::from_str(r#"{"type": "override", "agent": 2, "task": "Add(1)"}"#).unwrap();
+        assert_eq!(
+            command,
+            LiveCommand::Override {
+                agent: AgentId(2),
+                task: "Add(1)".into()
+            }
+        );
+    }
+
+    #[test]
+    fn clients_control_a_simple_executor() {
+        let server = LiveControlServer::bind("127.0.0.1:0").unwrap();
+        let mut client = Client::connect(&server);
+        let mut executor_state = CounterExecutorState(0);
+        let mut executor =
+            SimpleExecutor::<CounterDomain, _>::new(counter_mcts_config(), &mut executor_state);
+
+        // Configuration changes need a controlled executor
+        assert_eq!(
+            client.send(r#"{"type": "set_config", "visits": 10}"#)["type"],
+            "error"
+        );
+        assert_eq!(client.send(r#"{"type": "pause"}"#)["type"], "ok");
+        assert!(server.step(&mut executor));
+        assert_eq!(executor.active_tasks().iter().next().unwrap().end, 0);
+        assert_eq!(client.send(r#"{"type": "jump"}"#)["type"], "error");
+        assert_eq!(
+            client.send(r#"{"type": "set_config", "visits": 0}"#)["type"],
+            "error"
+        );
+        assert_eq!(
+            client.send(r#"{"type": "set_config", "visits": 10}"#)["type"],
+            "ok"
+        );
+        assert_eq!(
+            client.send(r#"{"type": "request_tree", "agent": 0}"#)["type"],
+            "ok"
+        );
+        assert_eq!(
+            client.send(r#"{"type": "override", "agent": 0, "task": "Add(1)"}"#)["type"],
+            "ok"
+        );
+        assert_eq!(client.send(r#"{"type": "resume"}"#)["type"], "ok");
+
+        // The agent plans with the new configuration, its tree is sent and its decision overridden
+        assert!(server.step(&mut executor));
+        assert_eq!(executor.mcts_config().visits, 10);
+        let tree = client.receive();
+        assert_eq!(tree["type"], "tree");
+        assert_eq!(tree["agent"], 0);
+        assert_eq!(tree["tree"]["visits"], 10);
+        let overridden = client.receive();
+        assert_eq!(overridden["type"], "overridden");
+        assert_eq!(overridden["task"], "Add(1)");
+        assert!(server.step(&mut executor));
+        assert_eq!(*executor.state(), 1);
+    }
+}
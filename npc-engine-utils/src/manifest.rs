@@ -0,0 +1,126 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use npc_engine_core::{Domain, MCTSConfiguration, MCTS};
+use serde::{Deserialize, Serialize};
+
+/// A description of how a run or a search tree was produced, to keep recorded results attributable and reproducible.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RunManifest {
+    /// The version of the engine
+    pub engine_version: String,
+    /// The type name of the domain
+    pub domain: String,
+    /// The version of the domain, as given by the user
+    pub domain_version: String,
+    /// The configuration used for planning
+    pub config: MCTSConfiguration,
+    /// The seed of the run, if any
+    pub seed: Option<u64>,
+    /// The creation time of the manifest, in seconds since the Unix epoch
+    pub timestamp: u64,
+}
+
+impl RunManifest {
+    /// Creates a manifest for a run of domain `D` with the given configuration, whose seed is used.
+    pub fn new<D: Domain>(domain_version: &str, config: &MCTSConfiguration) -> Self {
+        Self {
+            engine_version: env!("CARGO_PKG_VERSION").into(),
+            domain: std::any::type_name::<D>().into(),
+            domain_version: domain_version.into(),
+            config: config.clone(),
+            seed: config.seed,
+            timestamp: unix_timestamp(),
+        }
+    }
+
+    /// Creates a manifest for a search tree, with its configuration and actual seed.
+    pub fn for_mcts<D: Domain>(mcts: &MCTS<D>, domain_version: &str) -> Self {
+        Self::new::<D>(domain_version, mcts.config()).with_seed(mcts.seed())
+    }
+
+    /// Sets the seed of the run.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Bundles the manifest with recorded data, for example a [Replay](crate::Replay) or a [TreeSnapshot](npc_engine_core::TreeSnapshot).
+    pub fn attach<T>(self, data: T) -> WithManifest<T> {
+        WithManifest {
+            manifest: self,
+            data,
+        }
+    }
+
+    /// Writes the manifest as JSON next to the file at `path`, with the extension `manifest.json`, and returns its path.
+    pub fn write_alongside(&self, path: impl AsRef<Path>) -> io::Result<PathBuf> {
+        let path = path.as_ref().with_extension("manifest.json");
+        let json = serde_json::to_string_pretty(self).expect("Manifests are always serializable");
+        fs::write(&path, json)?;
+        Ok(path)
+    }
+}
+
+/// Recorded data with the [RunManifest] describing how it was produced.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WithManifest<T> {
+    pub manifest: RunManifest,
+    pub data: T,
+}
+
+/// Returns the number of seconds since the Unix epoch.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+/// Returns the number of seconds since the Unix epoch, from the clock of JavaScript as the standard one is not available in browsers.
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+fn unix_timestamp() -> u64 {
+    (js_sys::Date::now() / 1000.) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::tests::{counter_mcts_config, CounterDomain};
+    use npc_engine_core::AgentId;
+
+    #[test]
+    fn manifest_describes_search() {
+        let mut mcts = MCTS::<CounterDomain>::new(0, AgentId(0), counter_mcts_config());
+        mcts.run();
+        let manifest = RunManifest::for_mcts(&mcts, "1.2");
+        assert_eq!(manifest.engine_version, env!("CARGO_PKG_VERSION"));
+        assert!(manifest.domain.ends_with("CounterDomain"));
+        assert_eq!(manifest.domain_version, "1.2");
+        assert_eq!(manifest.seed, Some(mcts.seed()));
+        assert_eq!(manifest.config.visits, counter_mcts_config().visits);
+        assert!(manifest.timestamp > 0);
+
+        let json = serde_json::to_string(&manifest.attach(mcts.node_count())).unwrap();
+        let restored: WithManifest<usize> = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.data, mcts.node_count());
+        assert_eq!(restored.manifest.domain_version, "1.2");
+
+        let path = std::env::temp_dir().join("npc_engine_manifest_test.json");
+        let manifest_path = restored.manifest.write_alongside(&path).unwrap();
+        assert!(manifest_path.ends_with("npc_engine_manifest_test.manifest.json"));
+        let written: RunManifest =
+            serde_json::from_str(&fs::read_to_string(&manifest_path).unwrap()).unwrap();
+        assert_eq!(written.seed, Some(mcts.seed()));
+        fs::remove_file(manifest_path).unwrap();
+    }
+}
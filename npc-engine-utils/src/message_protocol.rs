@@ -0,0 +1,464 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{
+    collections::BTreeMap,
+    io::{self, BufRead, BufReader, Write},
+    marker::PhantomData,
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use npc_engine_core::{
+    AgentId, DefaultPolicyEstimator, IdleTask, MCTSConfiguration, SnapshotDomain, Task,
+    TaskSnapshot, MCTS,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::export_plan;
+
+/// Overrides of the MCTS configuration of a [ClientMessage::PlanRequest], the default configuration of the host being used for missing fields.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProtocolConfig {
+    /// The number of visits
+    pub visits: Option<u32>,
+    /// The search depth in ticks
+    pub depth: Option<u32>,
+    /// The exploration factor
+    pub exploration: Option<f32>,
+    /// The discount half-life
+    pub discount_hl: Option<f32>,
+    /// The seed
+    pub seed: Option<u64>,
+}
+
+impl ProtocolConfig {
+    /// Applies the overrides to `config`.
+    pub fn apply(&self, config: &mut MCTSConfiguration) {
+        if let Some(visits) = self.visits {
+            config.visits = visits;
+        }
+        if let Some(depth) = self.depth {
+            config.depth = depth;
+        }
+        if let Some(exploration) = self.exploration {
+            config.exploration = exploration;
+        }
+        if let Some(discount_hl) = self.discount_hl {
+            config.discount_hl = discount_hl;
+        }
+        if self.seed.is_some() {
+            config.seed = self.seed;
+        }
+    }
+}
+
+/// A message from a client to a [MessageProtocol].
+///
+/// Messages are JSON objects whose `type` field gives their kind, in snake case, and whose other fields are flat,
+/// so that they map to plain C# classes, for example with Unity's `JsonUtility`.
+/// States and tasks are nested JSON documents carried as strings, so that clients serialize them with their own classes.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    /// Asks for the names of the domains served, answered by [HostMessage::Domains].
+    ListDomains,
+    /// Asks to plan for `agent` in `state`, answered by a [HostMessage::Decision], up to
+    /// `max_plan_steps` [HostMessage::PlanStep]s and a [HostMessage::PlanEnd], or by a [HostMessage::Error].
+    PlanRequest {
+        /// The identifier of the request, repeated in its responses
+        id: u64,
+        /// The name of the domain, as registered in the protocol
+        domain: String,
+        /// The planning agent
+        agent: u32,
+        /// The tick at which to plan
+        #[serde(default)]
+        tick: u64,
+        /// The state, as JSON
+        state: String,
+        /// Overrides of the default configuration
+        #[serde(default)]
+        config: ProtocolConfig,
+        /// The maximum number of steps of the plan to stream after the decision
+        #[serde(default)]
+        max_plan_steps: u32,
+    },
+}
+
+/// A message from a [MessageProtocol] to a client, see [ClientMessage] for the encoding.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HostMessage {
+    /// The names of the domains served
+    Domains { domains: Vec<String> },
+    /// The task chosen for a request
+    Decision {
+        /// The identifier of the request
+        id: u64,
+        /// The planning agent
+        agent: u32,
+        /// The chosen task, as a JSON [TaskSnapshot]
+        task: String,
+        /// The number of visits done by the search
+        visits: u32,
+        /// The expected value of the root of the search for the agent
+        value: f32,
+    },
+    /// A step of the plan following a decision, for any agent
+    PlanStep {
+        /// The identifier of the request
+        id: u64,
+        /// The agent doing the task
+        agent: u32,
+        /// The tick at which the task starts
+        tick: u64,
+        /// The task, as a JSON [TaskSnapshot]
+        task: String,
+        /// The duration of the task in ticks
+        duration: u64,
+        /// The number of visits of the task in the search
+        visits: u64,
+        /// The expected value of the task for its agent
+        expected_value: f32,
+    },
+    /// The end of the responses to a request
+    PlanEnd { id: u64 },
+    /// An invalid message or request, with the identifier of the request if known
+    Error { id: Option<u64>, message: String },
+}
+
+/// A domain registered in a [MessageProtocol], with its type erased.
+trait ProtocolDomain: Send + Sync {
+    /// Plans for the request and sends the responses, returns an error message if the request is invalid.
+    fn plan(&self, planning: Planning, send: &mut dyn FnMut(HostMessage)) -> Result<(), String>;
+}
+
+/// The parameters of a planning requested by a [ClientMessage::PlanRequest].
+struct Planning<'a> {
+    id: u64,
+    agent: AgentId,
+    tick: u64,
+    state: &'a str,
+    config: MCTSConfiguration,
+    max_plan_steps: usize,
+}
+
+struct Registered<D>(PhantomData<fn() -> D>);
+
+impl<D> ProtocolDomain for Registered<D>
+where
+    D: SnapshotDomain,
+    D::State: DeserializeOwned,
+    D::TaskSnapshot: Serialize,
+{
+    fn plan(&self, planning: Planning, send: &mut dyn FnMut(HostMessage)) -> Result<(), String> {
+        let Planning {
+            id,
+            agent,
+            tick,
+            state,
+            config,
+            max_plan_steps,
+        } = planning;
+        let state: D::State =
+            serde_json::from_str(state).map_err(|error| format!("invalid state: {error}"))?;
+        let mut mcts = MCTS::<D>::new_with_tasks(
+            state,
+            agent,
+            tick,
+            Default::default(),
+            config,
+            Box::new(DefaultPolicyEstimator {}),
+            None,
+        );
+        let task = mcts.run().unwrap_or_else(|| Box::new(IdleTask));
+        send(HostMessage::Decision {
+            id,
+            agent: agent.0,
+            task: task_json(&*task),
+            visits: mcts.visits_done(),
+            value: mcts.q_value_at_root(agent),
+        });
+        for step in export_plan(&mcts, max_plan_steps, task_json::<D>).steps {
+            send(HostMessage::PlanStep {
+                id,
+                agent: step.agent.0,
+                tick: step.tick,
+                task: step.task,
+                duration: step.duration,
+                visits: step.visits as u64,
+                expected_value: step.expected_value,
+            });
+        }
+        send(HostMessage::PlanEnd { id });
+        Ok(())
+    }
+}
+
+fn task_json<D>(task: &dyn Task<D>) -> String
+where
+    D: SnapshotDomain,
+    D::TaskSnapshot: Serialize,
+{
+    serde_json::to_string(&TaskSnapshot::new(task)).expect("Task snapshots are always serializable")
+}
+
+/// A transport-agnostic JSON message protocol to plan for the domains registered in it, for game clients such as Unity ones written in C#.
+///
+/// A transport delivers [ClientMessage]s to [handle_json](Self::handle_json) and sends back the [HostMessage]s it produces,
+/// [ProtocolHost] being a reference TCP transport with one message per line.
+pub struct MessageProtocol {
+    domains: BTreeMap<String, Box<dyn ProtocolDomain>>,
+    default_config: MCTSConfiguration,
+    max_visits: Option<u32>,
+}
+
+impl MessageProtocol {
+    /// Creates a protocol without domains, using `default_config` for requests without overrides.
+    pub fn new(default_config: MCTSConfiguration) -> Self {
+        Self {
+            domains: BTreeMap::new(),
+            default_config,
+            max_visits: None,
+        }
+    }
+
+    /// Registers domain `D` under `name`, replacing any domain with the same name.
+    pub fn register_domain<D>(&mut self, name: impl Into<String>)
+    where
+        D: SnapshotDomain,
+        D::State: DeserializeOwned,
+        D::TaskSnapshot: Serialize,
+    {
+        self.domains
+            .insert(name.into(), Box::new(Registered::<D>(PhantomData)));
+    }
+
+    /// Limits the number of visits of a planning, to protect the host from costly requests.
+    pub fn set_max_visits(&mut self, max_visits: Option<u32>) {
+        self.max_visits = max_visits;
+    }
+
+    /// Handles a message and sends its responses through `send`, in order.
+    pub fn handle(&self, message: ClientMessage, send: &mut dyn FnMut(HostMessage)) {
+        match message {
+            ClientMessage::ListDomains => send(HostMessage::Domains {
+                domains: self.domains.keys().cloned().collect(),
+            }),
+            ClientMessage::PlanRequest {
+                id,
+                domain,
+                agent,
+                tick,
+                state,
+                config,
+                max_plan_steps,
+            } => {
+                let error = |message: String| HostMessage::Error {
+                    id: Some(id),
+                    message,
+                };
+                let served = match self.domains.get(&domain) {
+                    Some(served) => served,
+                    None => return send(error(format!("unknown domain {domain}"))),
+                };
+                let mut mcts_config = self.default_config.clone();
+                config.apply(&mut mcts_config);
+                if let Some(max_visits) = self.max_visits {
+                    mcts_config.visits = mcts_config.visits.min(max_visits);
+                }
+                if let Err(config_error) = mcts_config.validate() {
+                    return send(error(format!("invalid configuration: {config_error}")));
+                }
+                let planning = Planning {
+                    id,
+                    agent: AgentId(agent),
+                    tick,
+                    state: &state,
+                    config: mcts_config,
+                    max_plan_steps: max_plan_steps as usize,
+                };
+                let result = served.plan(planning, send);
+                if let Err(message) = result {
+                    send(error(message));
+                }
+            }
+        }
+    }
+
+    /// Handles a message encoded as JSON and sends its responses, encoded as JSON, through `send`.
+    pub fn handle_json(&self, json: &str, send: &mut dyn FnMut(String)) {
+        let mut send_json = |message: HostMessage| {
+            send(serde_json::to_string(&message).expect("Messages are always serializable"))
+        };
+        match serde_json::from_str(json) {
+            Ok(message) => self.handle(message, &mut send_json),
+            Err(error) => send_json(HostMessage::Error {
+                id: None,
+                message: format!("invalid message: {error}"),
+            }),
+        }
+    }
+}
+
+/// A reference TCP transport of a [MessageProtocol], exchanging messages as lines of JSON.
+///
+/// Each client connection is served on its own thread, its requests being handled in order.
+/// The host stops accepting connections when dropped.
+pub struct ProtocolHost {
+    running: Arc<AtomicBool>,
+    local_addr: SocketAddr,
+}
+
+impl ProtocolHost {
+    /// Starts a host listening on the given address, accepting connections on a background thread.
+    pub fn bind(addr: impl ToSocketAddrs, protocol: MessageProtocol) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        let local_addr = listener.local_addr()?;
+        let running = Arc::new(AtomicBool::new(true));
+        let thread_running = running.clone();
+        let protocol = Arc::new(protocol);
+        thread::Builder::new()
+            .name("protocol-host".into())
+            .spawn(move || {
+                while thread_running.load(Ordering::Relaxed) {
+                    match listener.accept() {
+                        Ok((stream, _)) => {
+                            let protocol = protocol.clone();
+                            let spawned = thread::Builder::new()
+                                .name("protocol-client".into())
+                                .spawn(move || {
+                                    if let Err(error) = serve_client(stream, &protocol) {
+                                        log::debug!("Protocol client failed: {error}");
+                                    }
+                                });
+                            if let Err(error) = spawned {
+                                log::warn!("Protocol host cannot serve client: {error}");
+                            }
+                        }
+                        Err(error) if error.kind() == io::ErrorKind::WouldBlock => {
+                            thread::sleep(Duration::from_millis(20));
+                        }
+                        Err(error) => log::warn!("Protocol host cannot accept: {error}"),
+                    }
+                }
+            })?;
+        Ok(Self {
+            running,
+            local_addr,
+        })
+    }
+
+    /// Returns the address the host listens on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+impl Drop for ProtocolHost {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+    }
+}
+
+fn serve_client(stream: TcpStream, protocol: &MessageProtocol) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut result = Ok(());
+        protocol.handle_json(&line, &mut |json| {
+            if result.is_ok() {
+                result = writeln!(writer, "{json}");
+            }
+        });
+        result?;
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::tests::{counter_mcts_config, CounterDomain};
+
+    fn protocol() -> MessageProtocol {
+        let mut protocol = MessageProtocol::new(counter_mcts_config());
+        protocol.register_domain::<CounterDomain>("counter");
+        protocol
+    }
+
+    #[test]
+    fn plan_requests_are_answered() {
+        let protocol = protocol();
+        let mut responses = Vec::new();
+        let request = r#"{"type":"plan_request","id":7,"domain":"counter","agent":0,"state":"3","config":{"visits":30},"max_plan_steps":2}"#;
+        protocol.handle_json(request, &mut |json| {
+            responses.push(serde_json::from_str::<HostMessage>(&json).unwrap())
+        });
+        assert_eq!(responses.len(), 4);
+        match &responses[0] {
+            HostMessage::Decision {
+                id, task, visits, ..
+            } => {
+                assert_eq!((*id, *visits), (7, 30));
+                assert!(task.starts_with(r#"{"domain":"#));
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+        assert!(matches!(responses[1], HostMessage::PlanStep { id: 7, .. }));
+        assert_eq!(responses[3], HostMessage::PlanEnd { id: 7 });
+
+        let mut errors = Vec::new();
+        for request in [
+            r#"{"type":"plan_request","id":1,"domain":"chess","agent":0,"state":"3"}"#,
+            r#"{"type":"plan_request","id":2,"domain":"counter","agent":0,"state":"x"}"#,
+            r#"{"type":"plan_request","id":3,"domain":"counter","agent":0,"state":"3","config":{"visits":0}}"#,
+            r#"{"type":"dance"}"#,
+        ] {
+            protocol.handle_json(request, &mut |json| errors.push(json));
+        }
+        assert_eq!(errors.len(), 4);
+        assert!(errors[0].starts_with(r#"{"type":"error","id":1,"message":"unknown domain"#));
+        assert!(errors[3].contains(r#""id":null"#));
+    }
+
+    #[test]
+    fn host_serves_lines_of_json() {
+        let host = ProtocolHost::bind("127.0.0.1:0", protocol()).unwrap();
+        let mut stream = TcpStream::connect(host.local_addr()).unwrap();
+        writeln!(stream, r#"{{"type":"list_domains"}}"#).unwrap();
+        writeln!(
+            stream,
+            r#"{{"type":"plan_request","id":1,"domain":"counter","agent":0,"state":"0"}}"#
+        )
+        .unwrap();
+        let mut lines = BufReader::new(stream).lines();
+        let mut next = || serde_json::from_str::<HostMessage>(&lines.next().unwrap().unwrap());
+        assert_eq!(
+            next().unwrap(),
+            HostMessage::Domains {
+                domains: vec!["counter".into()]
+            }
+        );
+        assert!(matches!(
+            next().unwrap(),
+            HostMessage::Decision { id: 1, .. }
+        ));
+        assert_eq!(next().unwrap(), HostMessage::PlanEnd { id: 1 });
+    }
+}
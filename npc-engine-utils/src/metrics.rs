@@ -0,0 +1,134 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{fmt, time::Duration};
+
+use npc_engine_core::{AgentId, Domain, SearchProfile, Task, MCTS};
+
+/// Metrics of a finished planning, passed to [ExecutorState::on_planning_metrics](crate::ExecutorState::on_planning_metrics).
+///
+/// They allow to find out which agents are expensive to plan for, and why.
+pub struct PlanningMetrics<D: Domain> {
+    /// The agent that planned
+    pub agent: AgentId,
+    /// The tick at which planning started
+    pub start_tick: u64,
+    /// The tick at which the chosen task was committed
+    pub tick: u64,
+    /// The computation time spent planning
+    pub time: Duration,
+    /// The number of visits done
+    pub visits: u32,
+    /// The number of nodes of the search tree
+    pub node_count: usize,
+    /// The number of edges of the search tree
+    pub edge_count: usize,
+    /// The task that was committed
+    pub chosen_task: Box<dyn Task<D>>,
+    /// The estimated value of the root of the search tree for the agent
+    pub value: f32,
+    /// The calls and time spent in domain hooks
+    #[cfg(feature = "hook-timing")]
+    pub hook_timings: npc_engine_core::HookTimings,
+    /// The time spent in the phases of the search, if profiled, see [ExecutorState::profile_planning](crate::ExecutorState::profile_planning)
+    pub profile: Option<SearchProfile>,
+}
+impl<D: Domain> PlanningMetrics<D> {
+    /// Collects the metrics of a search whose `chosen_task` is committed at `tick`.
+    pub fn new(tick: u64, mcts: &MCTS<D>, chosen_task: Box<dyn Task<D>>) -> Self {
+        Self {
+            agent: mcts.agent(),
+            start_tick: mcts.start_tick(),
+            tick,
+            time: mcts.time(),
+            visits: mcts.visits_done(),
+            node_count: mcts.node_count(),
+            edge_count: mcts.edge_count(),
+            chosen_task,
+            value: mcts.q_value_at_root(mcts.agent()),
+            #[cfg(feature = "hook-timing")]
+            hook_timings: *mcts.hook_timings(),
+            profile: mcts.profile().copied(),
+        }
+    }
+}
+
+impl<D: Domain> fmt::Debug for PlanningMetrics<D> {
+    fn fmt(&self, f: &'_ mut fmt::Formatter) -> fmt::Result {
+        let mut f = f.debug_struct("PlanningMetrics");
+        f.field("agent", &self.agent)
+            .field("start_tick", &self.start_tick)
+            .field("tick", &self.tick)
+            .field("time", &self.time)
+            .field("visits", &self.visits)
+            .field("node_count", &self.node_count)
+            .field("edge_count", &self.edge_count)
+            .field("chosen_task", &self.chosen_task)
+            .field("value", &self.value);
+        #[cfg(feature = "hook-timing")]
+        f.field("hook_timings", &self.hook_timings);
+        f.field("profile", &self.profile);
+        f.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use npc_engine_core::ActiveTasks;
+
+    use super::*;
+    use crate::replay::tests::{counter_mcts_config, CounterDomain, CounterExecutorState};
+    use crate::{ExecutorState, ExecutorStateLocal, SimpleExecutor};
+
+    #[derive(Default)]
+    struct MetricsCollector(Vec<PlanningMetrics<CounterDomain>>, bool);
+    impl ExecutorStateLocal<CounterDomain> for MetricsCollector {
+        fn create_initial_state(&self) -> u32 {
+            0
+        }
+        fn init_task_queue(&self, state: &u32) -> ActiveTasks<CounterDomain> {
+            CounterExecutorState(*state).init_task_queue(state)
+        }
+    }
+    impl ExecutorState<CounterDomain> for MetricsCollector {
+        fn profile_planning(&self, _tick: u64, _agent: AgentId) -> bool {
+            self.1
+        }
+        fn on_planning_metrics(&mut self, metrics: &PlanningMetrics<CounterDomain>) {
+            self.0.push(PlanningMetrics {
+                chosen_task: metrics.chosen_task.clone(),
+                ..*metrics
+            });
+        }
+    }
+
+    #[test]
+    fn simple_executor_reports_planning_metrics() {
+        let mut collector = MetricsCollector::default();
+        let mut executor = SimpleExecutor::new(counter_mcts_config(), &mut collector);
+        for _ in 0..3 {
+            executor.step();
+        }
+        assert!(!collector.0.is_empty());
+        for metrics in &collector.0 {
+            assert_eq!(metrics.agent, AgentId(0));
+            assert_eq!(metrics.visits, counter_mcts_config().visits);
+            assert!(metrics.node_count > 1);
+            assert!(metrics.edge_count >= metrics.node_count - 1);
+            assert!(metrics.value > 0.);
+            assert!(metrics.profile.is_none());
+        }
+    }
+
+    #[test]
+    fn planning_metrics_include_requested_profiles() {
+        let mut collector = MetricsCollector(Vec::new(), true);
+        let mut executor = SimpleExecutor::new(counter_mcts_config(), &mut collector);
+        executor.step();
+        let profile = collector.0[0].profile.unwrap();
+        assert!(profile.total() > std::time::Duration::ZERO);
+        assert!(profile.total() <= collector.0[0].time);
+    }
+}
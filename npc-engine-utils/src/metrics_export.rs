@@ -0,0 +1,320 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt::Write as _,
+    io::{self, Read, Write},
+    mem,
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use npc_engine_core::{AgentId, Domain, MCTS};
+
+use crate::PlanningMetrics;
+
+/// The number of latest plannings per agent used to compute latency percentiles.
+const LATENCY_WINDOW: usize = 1024;
+
+/// The quantiles of planning latency that are exported.
+const LATENCY_QUANTILES: [f64; 4] = [0.5, 0.9, 0.99, 1.0];
+
+/// The name, the help and the value of a per-agent gauge.
+type AgentGauge = (&'static str, &'static str, fn(&AgentMetrics) -> usize);
+
+#[derive(Default)]
+struct AgentMetrics {
+    latencies: VecDeque<Duration>,
+    latency_sum: Duration,
+    plannings: u64,
+    node_count: usize,
+    edge_count: usize,
+    tree_memory: usize,
+}
+
+struct ExportedMetrics {
+    start: Instant,
+    steps: u64,
+    agents: BTreeMap<AgentId, AgentMetrics>,
+}
+
+/// Collects the health metrics of planners and renders them in the [Prometheus](https://prometheus.io) text format.
+///
+/// The collected metrics are the executor throughput, the per-agent planning latency percentiles over the latest plannings,
+/// and the sizes and memory usage of the latest search trees.
+/// Record plannings with [record_planning](Self::record_planning) from
+/// [on_planning_metrics](crate::ExecutorState::on_planning_metrics),
+/// tree memory with [record_tree](Self::record_tree) from [post_mcts_run_hook](crate::ExecutorState::post_mcts_run_hook)
+/// and steps with [record_steps](Self::record_steps).
+/// Clones share the same metrics, so that one can be served by a [MetricsServer].
+#[derive(Clone)]
+pub struct MetricsExporter {
+    metrics: Arc<Mutex<ExportedMetrics>>,
+}
+
+impl Default for MetricsExporter {
+    fn default() -> Self {
+        Self {
+            metrics: Arc::new(Mutex::new(ExportedMetrics {
+                start: Instant::now(),
+                steps: 0,
+                agents: BTreeMap::new(),
+            })),
+        }
+    }
+}
+
+impl MetricsExporter {
+    /// Records the metrics of a planning.
+    pub fn record_planning<D: Domain>(&self, metrics: &PlanningMetrics<D>) {
+        let mut exported = self.metrics.lock().unwrap();
+        let agent = exported.agents.entry(metrics.agent).or_default();
+        if agent.latencies.len() == LATENCY_WINDOW {
+            agent.latencies.pop_front();
+        }
+        agent.latencies.push_back(metrics.time);
+        agent.latency_sum += metrics.time;
+        agent.plannings += 1;
+        agent.node_count = metrics.node_count;
+        agent.edge_count = metrics.edge_count;
+    }
+
+    /// Records the estimated memory footprint of a search tree, tasks counted by their shallow size.
+    pub fn record_tree<D: Domain>(&self, mcts: &MCTS<D>) {
+        let tree_memory = mcts.size(mem::size_of_val);
+        let mut exported = self.metrics.lock().unwrap();
+        exported.agents.entry(mcts.agent()).or_default().tree_memory = tree_memory;
+    }
+
+    /// Records executed steps of an executor.
+    pub fn record_steps(&self, steps: u64) {
+        self.metrics.lock().unwrap().steps += steps;
+    }
+
+    /// Renders all metrics in the Prometheus text exposition format.
+    pub fn render_prometheus(&self) -> String {
+        let exported = self.metrics.lock().unwrap();
+        let mut text = String::new();
+        let header = |text: &mut String, name: &str, kind: &str, help: &str| {
+            writeln!(text, "# HELP {name} {help}").unwrap();
+            writeln!(text, "# TYPE {name} {kind}").unwrap();
+        };
+        header(
+            &mut text,
+            "npc_executor_steps_total",
+            "counter",
+            "Executed steps.",
+        );
+        writeln!(text, "npc_executor_steps_total {}", exported.steps).unwrap();
+        header(
+            &mut text,
+            "npc_executor_steps_per_second",
+            "gauge",
+            "Mean executed steps per second since the exporter was created.",
+        );
+        let elapsed = exported.start.elapsed().as_secs_f64();
+        let throughput = if elapsed > 0. {
+            exported.steps as f64 / elapsed
+        } else {
+            0.
+        };
+        writeln!(text, "npc_executor_steps_per_second {throughput}").unwrap();
+
+        header(
+            &mut text,
+            "npc_planning_latency_seconds",
+            "summary",
+            "Computation time of plannings, quantiles over the latest plannings.",
+        );
+        for (agent, metrics) in &exported.agents {
+            let mut latencies: Vec<_> = metrics.latencies.iter().copied().collect();
+            latencies.sort();
+            for quantile in LATENCY_QUANTILES {
+                if let Some(latency) = percentile(&latencies, quantile) {
+                    writeln!(
+                        text,
+                        "npc_planning_latency_seconds{{agent=\"{}\",quantile=\"{quantile}\"}} {}",
+                        agent.0,
+                        latency.as_secs_f64()
+                    )
+                    .unwrap();
+                }
+            }
+            writeln!(
+                text,
+                "npc_planning_latency_seconds_sum{{agent=\"{}\"}} {}",
+                agent.0,
+                metrics.latency_sum.as_secs_f64()
+            )
+            .unwrap();
+            writeln!(
+                text,
+                "npc_planning_latency_seconds_count{{agent=\"{}\"}} {}",
+                agent.0, metrics.plannings
+            )
+            .unwrap();
+        }
+
+        let gauges: [AgentGauge; 3] = [
+            (
+                "npc_tree_nodes",
+                "Number of nodes of the latest search tree.",
+                |metrics| metrics.node_count,
+            ),
+            (
+                "npc_tree_edges",
+                "Number of edges of the latest search tree.",
+                |metrics| metrics.edge_count,
+            ),
+            (
+                "npc_tree_memory_bytes",
+                "Estimated memory footprint of the latest search tree.",
+                |metrics| metrics.tree_memory,
+            ),
+        ];
+        for (name, help, value) in gauges {
+            header(&mut text, name, "gauge", help);
+            for (agent, metrics) in &exported.agents {
+                writeln!(text, "{name}{{agent=\"{}\"}} {}", agent.0, value(metrics)).unwrap();
+            }
+        }
+        text
+    }
+}
+
+/// Returns the nearest-rank percentile of sorted values.
+fn percentile(sorted: &[Duration], quantile: f64) -> Option<Duration> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (quantile * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+/// An HTTP server exposing the metrics of a [MetricsExporter] at `/metrics`, to be scraped by Prometheus
+/// or by an OpenTelemetry collector.
+///
+/// The server stops when dropped.
+pub struct MetricsServer {
+    running: Arc<AtomicBool>,
+    local_addr: SocketAddr,
+}
+
+impl MetricsServer {
+    /// Starts a server listening on the given address, serving requests on a background thread.
+    pub fn bind(addr: impl ToSocketAddrs, exporter: MetricsExporter) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        let local_addr = listener.local_addr()?;
+        let running = Arc::new(AtomicBool::new(true));
+        let thread_running = running.clone();
+        thread::Builder::new()
+            .name("metrics-server".into())
+            .spawn(move || {
+                while thread_running.load(Ordering::Relaxed) {
+                    match listener.accept() {
+                        Ok((stream, _)) => {
+                            if let Err(error) = handle_request(stream, &exporter) {
+                                log::debug!("Metrics server connection failed: {error}");
+                            }
+                        }
+                        Err(error) if error.kind() == io::ErrorKind::WouldBlock => {
+                            thread::sleep(Duration::from_millis(20));
+                        }
+                        Err(error) => log::warn!("Metrics server cannot accept: {error}"),
+                    }
+                }
+            })?;
+        Ok(Self {
+            running,
+            local_addr,
+        })
+    }
+
+    /// Returns the address the server listens on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+impl Drop for MetricsServer {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+    }
+}
+
+fn handle_request(mut stream: TcpStream, exporter: &MetricsExporter) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(Duration::from_secs(1)))?;
+    stream.set_write_timeout(Some(Duration::from_secs(1)))?;
+    let mut request = Vec::new();
+    let mut buffer = [0; 1024];
+    while !request.windows(4).any(|end| end == b"\r\n\r\n") {
+        let len = stream.read(&mut buffer)?;
+        if len == 0 || request.len() > 8192 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "malformed request",
+            ));
+        }
+        request.extend_from_slice(&buffer[..len]);
+    }
+    let request = String::from_utf8_lossy(&request);
+    let path = request.split_whitespace().nth(1).unwrap_or("/");
+    let (status, body) = if path == "/metrics" {
+        ("200 OK", exporter.render_prometheus())
+    } else {
+        ("404 Not Found", "not found".to_string())
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::tests::{counter_mcts_config, CounterDomain};
+
+    #[test]
+    fn exporter_renders_and_serves_metrics() {
+        let exporter = MetricsExporter::default();
+        for _ in 0..3 {
+            let mut mcts = MCTS::<CounterDomain>::new(0, AgentId(2), counter_mcts_config());
+            let task = mcts.run().unwrap();
+            exporter.record_planning(&PlanningMetrics::new(1, &mcts, task));
+            exporter.record_tree(&mcts);
+        }
+        exporter.record_steps(5);
+        let text = exporter.render_prometheus();
+        assert!(text.contains("npc_executor_steps_total 5\n"));
+        assert!(text.contains("npc_planning_latency_seconds{agent=\"2\",quantile=\"0.99\"}"));
+        assert!(text.contains("npc_planning_latency_seconds_count{agent=\"2\"} 3\n"));
+        assert!(text.contains("# TYPE npc_tree_memory_bytes gauge"));
+        assert!(!text.contains("npc_tree_memory_bytes{agent=\"2\"} 0\n"));
+
+        let latencies = [1, 2, 3, 4].map(Duration::from_secs);
+        assert_eq!(percentile(&latencies, 0.5), Some(latencies[1]));
+        assert_eq!(percentile(&latencies, 1.), Some(latencies[3]));
+        assert_eq!(percentile(&[], 0.5), None);
+
+        let server = MetricsServer::bind("127.0.0.1:0", exporter.clone()).unwrap();
+        let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+        write!(stream, "GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("npc_executor_steps_total 5"));
+    }
+}
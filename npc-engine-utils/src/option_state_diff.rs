@@ -18,7 +18,7 @@ pub trait OptionDiffDomain {
     type State: Clone;
     /// Returns either the `diff` if it is not `None`, or the `initial_state`.
     fn get_cur_state(
-        state_diff: StateDiffRef<Self::Domain>,
+        state_diff: StateDiffRef<'_, Self::Domain>,
     ) -> &<<Self as OptionDiffDomain>::Domain as Domain>::State {
         if let Some(diff) = state_diff.diff {
             diff
@@ -28,7 +28,7 @@ pub trait OptionDiffDomain {
     }
     /// Returns either the `diff` if it is not `None`, or copies the `initial_state` into the `diff` and returns it.
     fn get_cur_state_mut(
-        state_diff: StateDiffRefMut<Self::Domain>,
+        state_diff: StateDiffRefMut<'_, Self::Domain>,
     ) -> &mut <<Self as OptionDiffDomain>::Domain as Domain>::State {
         if let Some(diff) = state_diff.diff {
             diff
@@ -0,0 +1,467 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::hash::Hash;
+
+use crate::{Coord2D, HexCoord, HEX_DIRECTIONS};
+
+/// A path found by [astar], with its total cost.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Path<N> {
+    /// The nodes to go through, excluding the start and ending with the goal,
+    /// empty if both are the same
+    pub steps: Vec<N>,
+    pub cost: u32,
+}
+
+impl<N: Copy> Path<N> {
+    /// The first node to go to, if the goal is not reached already.
+    pub fn first(&self) -> Option<N> {
+        self.steps.first().copied()
+    }
+}
+
+/// Finds a cheapest path between two nodes with A*.
+///
+/// `successors` gives the nodes reachable in one step from a node, with the cost of these steps,
+/// and `heuristic` estimates the cost from a node to `goal`, without ever overestimating it.
+/// The nodes reachable from `start` must be finite, for instance those of a map or of a window of it,
+/// as the search only ends when the goal is found or all these nodes are explored.
+pub fn astar<N, I>(
+    start: N,
+    goal: N,
+    mut successors: impl FnMut(N) -> I,
+    heuristic: impl Fn(N) -> u32,
+) -> Option<Path<N>>
+where
+    N: Copy + Ord + Hash,
+    I: IntoIterator<Item = (N, u32)>,
+{
+    let mut heap = BinaryHeap::new();
+    let mut costs = HashMap::new();
+    let mut parents = HashMap::new();
+
+    heap.push(Reverse((heuristic(start), 0, start)));
+    costs.insert(start, 0);
+
+    while let Some(Reverse((_, cost, node))) = heap.pop() {
+        if node == goal {
+            let mut steps = Vec::new();
+            let mut node = goal;
+            while node != start {
+                steps.push(node);
+                node = parents[&node];
+            }
+            steps.reverse();
+            return Some(Path { steps, cost });
+        }
+
+        // Stale entry, a cheaper way to this node was found in the meantime
+        if costs.get(&node).map_or(false, |&best| best < cost) {
+            continue;
+        }
+
+        for (next, step) in successors(node) {
+            let next_cost = cost.saturating_add(step);
+            if costs.get(&next).map_or(true, |&best| next_cost < best) {
+                costs.insert(next, next_cost);
+                parents.insert(next, node);
+                heap.push(Reverse((
+                    next_cost.saturating_add(heuristic(next)),
+                    next_cost,
+                    next,
+                )));
+            }
+        }
+    }
+
+    None
+}
+
+/// Finds a shortest path on a square grid with 4-connectivity, through the cells for which `passable` holds.
+pub fn astar_grid(
+    start: Coord2D,
+    goal: Coord2D,
+    passable: impl Fn(Coord2D) -> bool,
+) -> Option<Path<Coord2D>> {
+    astar(
+        start,
+        goal,
+        |coord| grid_neighbors(coord, &passable),
+        |coord| coord.manhattan_dist(goal) as u32,
+    )
+}
+
+/// Finds a shortest path on a hex grid, through the cells for which `passable` holds.
+pub fn astar_hex(
+    start: HexCoord,
+    goal: HexCoord,
+    passable: impl Fn(HexCoord) -> bool,
+) -> Option<Path<HexCoord>> {
+    astar(
+        start,
+        goal,
+        |coord| hex_neighbors(coord, &passable),
+        |coord| coord.distance(goal) as u32,
+    )
+}
+
+fn grid_neighbors(
+    coord: Coord2D,
+    passable: &impl Fn(Coord2D) -> bool,
+) -> impl Iterator<Item = (Coord2D, u32)> + '_ {
+    [(0, -1), (1, 0), (0, 1), (-1, 0)]
+        .into_iter()
+        .map(move |(x, y)| coord + Coord2D::new(x, y))
+        .filter(move |next| passable(*next))
+        .map(|next| (next, 1))
+}
+
+fn hex_neighbors(
+    coord: HexCoord,
+    passable: &impl Fn(HexCoord) -> bool,
+) -> impl Iterator<Item = (HexCoord, u32)> + '_ {
+    HEX_DIRECTIONS
+        .into_iter()
+        .map(move |direction| coord.neighbor(direction))
+        .filter(move |next| passable(*next))
+        .map(|next| (next, 1))
+}
+
+/// The cheapest ways between a set of origins and all the nodes reachable from them,
+/// found by expanding the origins with Dijkstra's algorithm.
+///
+/// Expanded with the successors of nodes, the field tells the first step to take from the origin
+/// towards every node, as when an agent chooses among the places around it.
+/// Expanded with their predecessors, it tells the next step to take from every node towards the
+/// closest origin, a flow field leading any number of agents to common goals.
+/// On grids, where steps cost the same both ways, successors and predecessors are the same.
+#[derive(Clone, Debug)]
+pub struct FlowField<N> {
+    costs: HashMap<N, u32>,
+    parents: HashMap<N, N>,
+    firsts: HashMap<N, N>,
+}
+
+impl<N: Copy + Ord + Hash> FlowField<N> {
+    /// Expands the origins with `expand`, which gives the nodes adjacent to a node with the
+    /// cost of the steps between them. The reachable nodes must be finite, as for [astar].
+    pub fn new<I>(origins: impl IntoIterator<Item = N>, mut expand: impl FnMut(N) -> I) -> Self
+    where
+        I: IntoIterator<Item = (N, u32)>,
+    {
+        let mut heap = BinaryHeap::new();
+        let mut field = FlowField {
+            costs: HashMap::new(),
+            parents: HashMap::new(),
+            firsts: HashMap::new(),
+        };
+
+        for origin in origins {
+            field.costs.insert(origin, 0);
+            heap.push(Reverse((0, origin)));
+        }
+
+        while let Some(Reverse((cost, node))) = heap.pop() {
+            // Stale entry, a cheaper way to this node was found in the meantime
+            if field.costs.get(&node).map_or(false, |&best| best < cost) {
+                continue;
+            }
+
+            for (next, step) in expand(node) {
+                let next_cost = cost.saturating_add(step);
+                if field
+                    .costs
+                    .get(&next)
+                    .map_or(true, |&best| next_cost < best)
+                {
+                    let first = field.firsts.get(&node).copied().unwrap_or(next);
+                    field.costs.insert(next, next_cost);
+                    field.parents.insert(next, node);
+                    field.firsts.insert(next, first);
+                    heap.push(Reverse((next_cost, next)));
+                }
+            }
+        }
+
+        field
+    }
+
+    /// The cost between the closest origin and `node`, if it was reached.
+    pub fn cost(&self, node: N) -> Option<u32> {
+        self.costs.get(&node).copied()
+    }
+
+    /// The node from which `node` was reached, that is its next step back towards the closest origin.
+    /// None for origins and nodes that were not reached.
+    pub fn next_step(&self, node: N) -> Option<N> {
+        self.parents.get(&node).copied()
+    }
+
+    /// The first step from the closest origin on the way to `node`, which is `node` itself next to the origin.
+    /// None for origins and nodes that were not reached.
+    pub fn first_step(&self, node: N) -> Option<N> {
+        self.firsts.get(&node).copied()
+    }
+
+    /// The nodes from `node` to the closest origin, excluding `node` and ending with the origin, if `node` was reached.
+    pub fn path_from(&self, node: N) -> Option<Vec<N>> {
+        self.costs.get(&node)?;
+        let mut path = Vec::new();
+        let mut node = node;
+        while let Some(next) = self.next_step(node) {
+            path.push(next);
+            node = next;
+        }
+        Some(path)
+    }
+
+    /// All the nodes reached, origins included, with their costs, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (N, u32)> + '_ {
+        self.costs.iter().map(|(node, cost)| (*node, *cost))
+    }
+}
+
+impl FlowField<Coord2D> {
+    /// Builds the flow field towards `goals` on a square grid with 4-connectivity,
+    /// through the cells for which `passable` holds.
+    pub fn grid(
+        goals: impl IntoIterator<Item = Coord2D>,
+        passable: impl Fn(Coord2D) -> bool,
+    ) -> FlowField<Coord2D> {
+        FlowField::new(goals, |coord| grid_neighbors(coord, &passable))
+    }
+}
+
+impl FlowField<HexCoord> {
+    /// Builds the flow field towards `goals` on a hex grid, through the cells for which `passable` holds.
+    pub fn hex(
+        goals: impl IntoIterator<Item = HexCoord>,
+        passable: impl Fn(HexCoord) -> bool,
+    ) -> FlowField<HexCoord> {
+        FlowField::new(goals, |coord| hex_neighbors(coord, &passable))
+    }
+}
+
+/// A bounded cache of the paths between pairs of nodes, evicting the oldest entries first.
+///
+/// Tasks walking somewhere often search the same paths again, for instance when checking that they are still valid.
+/// Cached paths are kept until the domain tells the cache that the nodes they go through changed,
+/// so they may become longer than needed when new ways open, but never go through blocked nodes.
+#[derive(Clone, Debug)]
+pub struct PathCache<N> {
+    capacity: usize,
+    paths: HashMap<(N, N), Option<Path<N>>>,
+    order: VecDeque<(N, N)>,
+    hits: u64,
+    misses: u64,
+}
+
+impl<N: Copy + Hash + Eq> PathCache<N> {
+    /// Creates a cache holding at most `capacity` searches, failed ones included.
+    pub fn new(capacity: usize) -> Self {
+        PathCache {
+            capacity,
+            paths: HashMap::new(),
+            order: VecDeque::new(),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Returns the cached result of the search from `start` to `goal`, running `search` if there is none.
+    pub fn get_or_insert_with(
+        &mut self,
+        start: N,
+        goal: N,
+        search: impl FnOnce() -> Option<Path<N>>,
+    ) -> Option<Path<N>> {
+        if let Some(path) = self.paths.get(&(start, goal)) {
+            self.hits += 1;
+            return path.clone();
+        }
+        self.misses += 1;
+        let path = search();
+        if self.capacity > 0 {
+            while self.paths.len() >= self.capacity {
+                match self.order.pop_front() {
+                    Some(oldest) => {
+                        self.paths.remove(&oldest);
+                    }
+                    None => break,
+                }
+            }
+            self.paths.insert((start, goal), path.clone());
+            self.order.push_back((start, goal));
+        }
+        path
+    }
+
+    /// Forgets the paths starting at, ending at or going through `node`, and the failed searches,
+    /// which a change of `node` may have made possible.
+    pub fn invalidate_through(&mut self, node: N) {
+        self.paths.retain(|(start, _), path| match path {
+            Some(path) => *start != node && !path.steps.contains(&node),
+            None => false,
+        });
+        let paths = &self.paths;
+        self.order.retain(|key| paths.contains_key(key));
+    }
+
+    /// Forgets all the paths, for instance when the world changed as a whole.
+    pub fn clear(&mut self) {
+        self.paths.clear();
+        self.order.clear();
+    }
+
+    /// The number of searches cached, failed ones included.
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    /// The number of searches answered from the cache.
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// The number of searches that had to be run.
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    // The four neighbors of a tile of a 3 × 3 grid, stepping onto y = 0 costing 5
+    fn weighted_neighbors((x, y): (i32, i32)) -> impl Iterator<Item = ((i32, i32), u32)> {
+        [(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)]
+            .into_iter()
+            .filter(|(x, y)| (0..3).contains(x) && (0..3).contains(y))
+            .map(|(x, y)| ((x, y), if y == 0 { 5 } else { 1 }))
+    }
+
+    #[test]
+    fn astar_weighted() {
+        // The cheapest path goes around y = 0
+        let path = astar((0, 1), (2, 1), weighted_neighbors, |(x, y)| {
+            ((x - 2).abs() + (y - 1).abs()) as u32
+        })
+        .unwrap();
+        assert_eq!(path.cost, 2);
+        assert_eq!(path.steps, vec![(1, 1), (2, 1)]);
+    }
+
+    #[test]
+    fn astar_unreachable() {
+        assert_eq!(astar((0, 1), (5, 5), weighted_neighbors, |_| 0), None);
+        assert_eq!(
+            astar((0, 1), (0, 1), weighted_neighbors, |_| 0),
+            Some(Path {
+                steps: vec![],
+                cost: 0
+            })
+        );
+    }
+
+    #[test]
+    fn flow_field_weighted() {
+        let field = FlowField::new([(0, 1)], weighted_neighbors);
+        assert_eq!(field.cost((0, 1)), Some(0));
+        assert_eq!(field.first_step((0, 1)), None);
+        // Reaching (2, 0) through (2, 1) is cheaper than along y = 0
+        assert_eq!(field.cost((2, 0)), Some(7));
+        assert_eq!(field.next_step((2, 0)), Some((2, 1)));
+        assert_eq!(field.first_step((2, 0)), Some((1, 1)));
+        assert_eq!(field.cost((5, 5)), None);
+    }
+
+    // A 5 × 5 grid with a wall along x = 2, open at y = 4
+    fn passable(coord: Coord2D) -> bool {
+        (0..5).contains(&coord.x) && (0..5).contains(&coord.y) && (coord.x != 2 || coord.y == 4)
+    }
+
+    #[test]
+    fn astar_around_wall() {
+        let path = astar_grid(Coord2D::new(0, 0), Coord2D::new(4, 0), passable).unwrap();
+        assert_eq!(path.cost, 12);
+        assert_eq!(path.steps.len(), 12);
+        assert_eq!(path.steps.last(), Some(&Coord2D::new(4, 0)));
+        assert!(path.steps.iter().all(|coord| passable(*coord)));
+        assert_eq!(path.first().unwrap().manhattan_dist(Coord2D::new(0, 0)), 1);
+    }
+
+    #[test]
+    fn astar_same_and_unreachable() {
+        let start = Coord2D::new(1, 1);
+        assert_eq!(
+            astar_grid(start, start, passable),
+            Some(Path {
+                steps: vec![],
+                cost: 0
+            })
+        );
+        assert_eq!(astar_grid(start, Coord2D::new(7, 7), passable), None);
+    }
+
+    #[test]
+    fn astar_hex_matches_distance() {
+        let start = HexCoord::new(0, 0);
+        let goal = HexCoord::new(3, -2);
+        let path = astar_hex(start, goal, |coord| coord.distance(start) <= 4).unwrap();
+        assert_eq!(path.cost, start.distance(goal) as u32);
+    }
+
+    #[test]
+    fn flow_field() {
+        let goal = Coord2D::new(4, 0);
+        let field = FlowField::grid([goal], passable);
+        assert_eq!(field.cost(goal), Some(0));
+        assert_eq!(field.next_step(goal), None);
+        assert_eq!(field.cost(Coord2D::new(0, 0)), Some(12));
+        let path = field.path_from(Coord2D::new(0, 0)).unwrap();
+        assert_eq!(path.len(), 12);
+        assert_eq!(path.last(), Some(&goal));
+        assert_eq!(field.path_from(Coord2D::new(2, 0)), None);
+        let first = field.first_step(Coord2D::new(0, 0)).unwrap();
+        assert_eq!(first.manhattan_dist(goal), 1);
+        assert_eq!(field.iter().count(), 21);
+    }
+
+    #[test]
+    fn path_cache() {
+        let mut cache = PathCache::new(2);
+        let (a, b, c) = (Coord2D::new(0, 0), Coord2D::new(4, 0), Coord2D::new(1, 1));
+        let path = cache.get_or_insert_with(a, b, || astar_grid(a, b, passable));
+        assert_eq!(
+            cache.get_or_insert_with(a, b, || unreachable!()),
+            path.clone()
+        );
+        assert_eq!((cache.hits(), cache.misses()), (1, 1));
+
+        assert_eq!(cache.get_or_insert_with(a, c, || None), None);
+        assert_eq!(cache.len(), 2);
+        // The failed search goes, the path only if it goes through the node
+        cache.invalidate_through(Coord2D::new(2, 0));
+        assert_eq!(cache.len(), 1);
+        cache.invalidate_through(Coord2D::new(2, 4));
+        assert!(cache.is_empty());
+
+        cache.get_or_insert_with(a, b, || None);
+        cache.get_or_insert_with(a, c, || None);
+        cache.get_or_insert_with(b, c, || None);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.misses(), 5);
+        cache.get_or_insert_with(a, b, || None);
+        assert_eq!(cache.misses(), 6);
+    }
+}
@@ -0,0 +1,334 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+    fs, io,
+    path::Path,
+};
+
+use npc_engine_core::{AgentId, Domain, Task};
+
+use crate::{replay::execute_on_state, ExecutableDomain, Replay, SnapshotDomain, TaskSnapshot};
+
+/// A typed object of a PDDL problem, such as `agent0 - agent`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PddlObject {
+    /// The name of the object, or of the variable with its `?` in action parameters
+    pub name: String,
+    /// The type of the object
+    pub kind: String,
+}
+
+impl PddlObject {
+    /// Creates an object named `name` of type `kind`.
+    pub fn new(name: impl Into<String>, kind: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            kind: kind.into(),
+        }
+    }
+}
+
+/// The schema of a PDDL action, with its declared preconditions and effects.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PddlAction {
+    /// The name of the action
+    pub name: String,
+    /// The parameters of the action, such as `?who - agent`
+    pub parameters: Vec<PddlObject>,
+    /// The atoms that must hold, such as `(at ?who ?where)` or `(not (chopped ?tree))`
+    pub precondition: Vec<String>,
+    /// The atoms made true or, with `not`, false
+    pub effect: Vec<String>,
+}
+
+/// A domain whose states and tasks can be described in [PDDL](https://planning.wiki), for classical planners to check plans.
+///
+/// States are described by typed objects and ground facts, and tasks by ground actions.
+/// Declaring the schemas of the actions is optional, without them only problems and plans are exported.
+pub trait PddlDomain: Domain {
+    /// Returns the name of the domain in PDDL.
+    fn pddl_domain_name() -> &'static str;
+
+    /// Returns the predicates of the domain, such as `(at ?who - agent ?where - cell)`, none by default.
+    fn pddl_predicates() -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Returns the schemas of the actions of the domain, none by default.
+    fn pddl_actions() -> Vec<PddlAction> {
+        Vec::new()
+    }
+
+    /// Returns the objects of `state`.
+    fn pddl_objects(state: &Self::State) -> Vec<PddlObject>;
+
+    /// Returns the facts true in `state`, such as `(at agent0 cell3)`.
+    fn pddl_facts(state: &Self::State) -> Vec<String>;
+
+    /// Returns the ground action of `task` done by `agent` in `state`, such as `(chop agent0 tree1)`, None if it has no PDDL counterpart.
+    fn pddl_ground_action(
+        task: &dyn Task<Self>,
+        agent: AgentId,
+        state: &Self::State,
+    ) -> Option<String>;
+}
+
+/// Writes atoms as a conjunction, on one line.
+fn write_conjunction(out: &mut String, atoms: &[String]) {
+    match atoms {
+        [] => out.push_str("(and)"),
+        [atom] => out.push_str(atom),
+        atoms => write!(out, "(and {})", atoms.join(" ")).unwrap(),
+    }
+}
+
+/// Writes typed objects grouped by type, such as `agent0 agent1 - agent tree1 - tree`.
+fn write_typed(out: &mut String, objects: &[PddlObject]) {
+    let mut by_kind: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for object in objects {
+        by_kind.entry(&object.kind).or_default().push(&object.name);
+    }
+    let groups: Vec<_> = by_kind
+        .into_iter()
+        .map(|(kind, names)| format!("{} - {kind}", names.join(" ")))
+        .collect();
+    out.push_str(&groups.join(" "));
+}
+
+/// Exports the PDDL domain file of `D`, None if it declares no action.
+///
+/// The types are those of the parameters of the actions.
+pub fn export_pddl_domain<D: PddlDomain>() -> Option<String> {
+    let actions = D::pddl_actions();
+    if actions.is_empty() {
+        return None;
+    }
+    let types: BTreeSet<_> = actions
+        .iter()
+        .flat_map(|action| action.parameters.iter().map(|parameter| &parameter.kind))
+        .collect();
+    let mut out = String::new();
+    writeln!(out, "(define (domain {})", D::pddl_domain_name()).unwrap();
+    writeln!(
+        out,
+        "  (:requirements :strips :typing :negative-preconditions)"
+    )
+    .unwrap();
+    let types: Vec<_> = types.into_iter().map(String::as_str).collect();
+    writeln!(out, "  (:types {})", types.join(" ")).unwrap();
+    writeln!(out, "  (:predicates {})", D::pddl_predicates().join(" ")).unwrap();
+    for action in &actions {
+        writeln!(out, "  (:action {}", action.name).unwrap();
+        out.push_str("    :parameters (");
+        write_typed(&mut out, &action.parameters);
+        out.push_str(")\n    :precondition ");
+        write_conjunction(&mut out, &action.precondition);
+        out.push_str("\n    :effect ");
+        write_conjunction(&mut out, &action.effect);
+        out.push_str(")\n");
+    }
+    out.push_str(")\n");
+    Some(out)
+}
+
+/// Exports a PDDL problem named `name`, from `initial_state` to the facts of `goal_state`.
+///
+/// The objects are those of both states.
+pub fn export_pddl_problem<D: PddlDomain>(
+    name: &str,
+    initial_state: &D::State,
+    goal_state: &D::State,
+) -> String {
+    let objects: BTreeSet<_> = D::pddl_objects(initial_state)
+        .into_iter()
+        .chain(D::pddl_objects(goal_state))
+        .collect();
+    let objects: Vec<_> = objects.into_iter().collect();
+    let mut out = String::new();
+    writeln!(out, "(define (problem {name})").unwrap();
+    writeln!(out, "  (:domain {})", D::pddl_domain_name()).unwrap();
+    out.push_str("  (:objects ");
+    write_typed(&mut out, &objects);
+    out.push_str(")\n  (:init");
+    for fact in D::pddl_facts(initial_state) {
+        write!(out, "\n    {fact}").unwrap();
+    }
+    out.push_str(")\n  (:goal ");
+    write_conjunction(&mut out, &D::pddl_facts(goal_state));
+    out.push_str("))\n");
+    out
+}
+
+/// Exports the tasks of a replay as a sequential PDDL plan, one ground action per line, and returns it with the final state.
+///
+/// The tasks are re-executed to know the state in which each is done, without verifying the resulting states, see [play_replay](crate::play_replay).
+/// Each action is followed by a comment with its tick and agent.
+/// Idle and planning tasks, and tasks without PDDL counterpart, are exported as comments.
+pub fn export_pddl_plan<D>(replay: &Replay<D::State, D::TaskSnapshot>) -> (String, D::State)
+where
+    D: PddlDomain + ExecutableDomain + SnapshotDomain,
+    D::State: Clone,
+    D::TaskSnapshot: Clone,
+{
+    let mut out = String::new();
+    let mut state = replay.initial_state.clone();
+    for step in &replay.steps {
+        let is_domain_task = matches!(step.task, TaskSnapshot::Domain(_));
+        let task = step.task.clone().restore::<D>();
+        let action = if is_domain_task {
+            D::pddl_ground_action(&*task, step.agent, &state)
+        } else {
+            None
+        };
+        match action {
+            Some(action) => writeln!(out, "{action} ; T{} {}", step.tick, step.agent).unwrap(),
+            None => writeln!(out, "; T{} {}: no action", step.tick, step.agent).unwrap(),
+        }
+        execute_on_state(step.tick, step.agent, &*task, &mut state);
+    }
+    (out, state)
+}
+
+/// The PDDL files of a replay, see [from_replay](Self::from_replay).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PddlExport {
+    /// The domain file, if the domain declares its actions
+    pub domain: Option<String>,
+    /// The problem file, from the initial state to the final one
+    pub problem: String,
+    /// The plan file
+    pub plan: String,
+}
+
+impl PddlExport {
+    /// Exports a replay as the problem `name` of reaching its final state from its initial state, and the plan doing so.
+    pub fn from_replay<D>(name: &str, replay: &Replay<D::State, D::TaskSnapshot>) -> Self
+    where
+        D: PddlDomain + ExecutableDomain + SnapshotDomain,
+        D::State: Clone,
+        D::TaskSnapshot: Clone,
+    {
+        let (plan, final_state) = export_pddl_plan::<D>(replay);
+        Self {
+            domain: export_pddl_domain::<D>(),
+            problem: export_pddl_problem::<D>(name, &replay.initial_state, &final_state),
+            plan,
+        }
+    }
+
+    /// Writes the files in `dir` as `{name}-domain.pddl`, `{name}-problem.pddl` and `{name}.plan`.
+    pub fn save(&self, dir: impl AsRef<Path>, name: &str) -> io::Result<()> {
+        let dir = dir.as_ref();
+        if let Some(domain) = &self.domain {
+            fs::write(dir.join(format!("{name}-domain.pddl")), domain)?;
+        }
+        fs::write(dir.join(format!("{name}-problem.pddl")), &self.problem)?;
+        fs::write(dir.join(format!("{name}.plan")), &self.plan)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use npc_engine_core::{AgentId, MCTSConfiguration, Task};
+
+    use super::{PddlAction, PddlDomain, PddlExport, PddlObject};
+    use crate::{
+        replay::tests::{Add, CounterDomain},
+        Replay, ReplayStep, TaskSnapshot,
+    };
+
+    fn level(value: u32) -> String {
+        format!("n{value}")
+    }
+
+    impl PddlDomain for CounterDomain {
+        fn pddl_domain_name() -> &'static str {
+            "counter"
+        }
+
+        fn pddl_predicates() -> Vec<String> {
+            vec!["(count ?n - level)".into()]
+        }
+
+        fn pddl_actions() -> Vec<PddlAction> {
+            vec![PddlAction {
+                name: "add1".into(),
+                parameters: vec![
+                    PddlObject::new("?a", "level"),
+                    PddlObject::new("?b", "level"),
+                ],
+                precondition: vec!["(count ?a)".into(), "(not (count ?b))".into()],
+                effect: vec!["(not (count ?a))".into(), "(count ?b)".into()],
+            }]
+        }
+
+        fn pddl_objects(state: &u32) -> Vec<PddlObject> {
+            (0..=*state)
+                .map(|value| PddlObject::new(level(value), "level"))
+                .collect()
+        }
+
+        fn pddl_facts(state: &u32) -> Vec<String> {
+            vec![format!("(count {})", level(*state))]
+        }
+
+        fn pddl_ground_action(
+            task: &dyn Task<Self>,
+            _agent: AgentId,
+            state: &u32,
+        ) -> Option<String> {
+            // Only unit additions are declared
+            match task.downcast_ref::<Add>() {
+                Some(Add(1)) => Some(format!("(add1 {} {})", level(*state), level(state + 1))),
+                _ => None,
+            }
+        }
+    }
+
+    #[test]
+    fn replay_exports_domain_problem_and_plan() {
+        let step = |tick, task| ReplayStep {
+            tick,
+            agent: AgentId(0),
+            task,
+            state_hash: 0,
+        };
+        let replay = Replay {
+            mcts_config: MCTSConfiguration::default(),
+            initial_state: 0,
+            seeds: Vec::new(),
+            steps: vec![
+                step(1, TaskSnapshot::Idle),
+                step(2, TaskSnapshot::Domain(1)),
+                step(3, TaskSnapshot::Domain(2)),
+            ],
+        };
+        let export = PddlExport::from_replay::<CounterDomain>("count-to-3", &replay);
+
+        let domain = export.domain.unwrap();
+        assert!(domain.starts_with("(define (domain counter)"));
+        assert!(domain.contains("(:types level)"));
+        assert!(domain.contains(":parameters (?a ?b - level)"));
+        assert!(domain.contains(":precondition (and (count ?a) (not (count ?b)))"));
+
+        assert!(export.problem.contains("(:domain counter)"));
+        assert!(export.problem.contains("(:objects n0 n1 n2 n3 - level)"));
+        assert!(export.problem.contains("(:init\n    (count n0))"));
+        assert!(export.problem.contains("(:goal (count n3))"));
+
+        let plan: Vec<_> = export.plan.lines().collect();
+        assert_eq!(
+            plan,
+            [
+                "; T1 A0: no action",
+                "(add1 n0 n1) ; T2 A0",
+                "; T3 A0: no action"
+            ]
+        );
+    }
+}
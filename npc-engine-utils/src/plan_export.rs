@@ -0,0 +1,124 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use npc_engine_core::{AgentId, Context, Domain, Task, TaskDuration, MCTS};
+use serde::{Deserialize, Serialize};
+
+/// A step of an exported plan, see [export_plan].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlanStep<T> {
+    /// The agent doing the task
+    pub agent: AgentId,
+    /// The tick at which the task starts
+    pub tick: u64,
+    /// The task, as described by the caller of [export_plan]
+    pub task: T,
+    /// The duration of the task in ticks
+    pub duration: TaskDuration,
+    /// The number of visits of the task in the search
+    pub visits: usize,
+    /// The expected value of the task for its agent
+    pub expected_value: f32,
+}
+
+/// The principal variation of a search tree, i.e. the sequence of best tasks from its root.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlanExport<T> {
+    /// The agent the tree searched for
+    pub agent: AgentId,
+    /// The tick at the root of the tree
+    pub start_tick: u64,
+    /// The steps of all agents, in order
+    pub steps: Vec<PlanStep<T>>,
+}
+impl<T> PlanExport<T> {
+    /// Returns the steps of the agent the tree searched for.
+    pub fn agent_steps(&self) -> impl Iterator<Item = &PlanStep<T>> {
+        self.steps
+            .iter()
+            .filter(move |step| step.agent == self.agent)
+    }
+}
+
+/// Exports the principal variation of a search tree, describing each task with `describe`.
+///
+/// From the root, the best task of each node is followed, as chosen by [best_task_at_root](MCTS::best_task_at_root),
+/// until reaching a node without expanded tasks, a node already in the plan, or `max_steps` steps.
+/// To export task parameters, `describe` can for example return a [TaskSnapshot](npc_engine_core::TaskSnapshot).
+pub fn export_plan<D, T, F>(mcts: &MCTS<D>, max_steps: usize, describe: F) -> PlanExport<T>
+where
+    D: Domain,
+    F: Fn(&dyn Task<D>) -> T,
+{
+    let mut steps = Vec::new();
+    let mut path = vec![mcts.root_node()];
+    while steps.len() < max_steps {
+        let node = path.last().unwrap().clone();
+        let agent = node.agent();
+        let edges = match mcts.get_edges(&node) {
+            Some(edges) => edges,
+            None => break,
+        };
+        let task = match edges.best_task(agent, 0., mcts.min_max_range(agent)) {
+            Some(task) => task,
+            None => break,
+        };
+        let edge = edges.get_edge(&task).unwrap();
+        let edge = edge.lock().unwrap();
+        let ctx =
+            Context::with_state_and_diff(node.tick(), mcts.initial_state(), node.diff(), agent);
+        steps.push(PlanStep {
+            agent,
+            tick: node.tick(),
+            task: describe(&*task),
+            duration: task.duration(ctx),
+            visits: edge.visits(),
+            expected_value: edge.q_value(agent),
+        });
+        let child = edge.child();
+        if path.contains(&child) {
+            break;
+        }
+        path.push(child);
+    }
+    PlanExport {
+        agent: mcts.agent(),
+        start_tick: mcts.start_tick(),
+        steps,
+    }
+}
+
+/// Exports the principal variation of a search tree as JSON, tasks being described by their debug output.
+pub fn export_plan_json<D: Domain>(mcts: &MCTS<D>, max_steps: usize) -> String {
+    let plan = export_plan(mcts, max_steps, |task| format!("{task:?}"));
+    serde_json::to_string(&plan).expect("Exported plans are always serializable")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::tests::{counter_mcts_config, CounterDomain};
+
+    #[test]
+    fn plan_follows_best_tasks() {
+        let mut mcts = MCTS::<CounterDomain>::new(0, AgentId(0), counter_mcts_config());
+        let best = mcts.run().unwrap();
+        let plan = export_plan(&mcts, 10, |task| format!("{task:?}"));
+        assert_eq!(plan.agent, AgentId(0));
+        assert!(!plan.steps.is_empty());
+        assert!(plan.steps.len() <= 10);
+        assert_eq!(plan.steps[0].task, format!("{best:?}"));
+        assert_eq!(plan.steps[0].tick, 0);
+        for pair in plan.steps.windows(2) {
+            assert_eq!(pair[1].tick, pair[0].tick + pair[0].duration);
+        }
+        assert_eq!(plan.agent_steps().count(), plan.steps.len());
+        assert_eq!(export_plan(&mcts, 1, |_| ()).steps.len(), 1);
+
+        let json: serde_json::Value = serde_json::from_str(&export_plan_json(&mcts, 10)).unwrap();
+        assert_eq!(json["steps"].as_array().unwrap().len(), plan.steps.len());
+        assert!(json["steps"][0]["expected_value"].is_number());
+    }
+}
@@ -0,0 +1,94 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{collections::BTreeMap, num::NonZeroU64};
+
+use npc_engine_core::{AgentId, MCTSConfiguration};
+use serde::{Deserialize, Serialize};
+
+/// How often and how much an agent plans, its level of detail.
+///
+/// An agent decides when its planning task ends, so a longer planning task makes it plan less often.
+/// Giving less detail to agents far from the player allows large worlds to scale.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlanningDetail {
+    /// The number of visits of a planning, before adjustment by [ExecutorStateGlobal::planning_visits](crate::ExecutorStateGlobal::planning_visits)
+    pub visits: u32,
+    /// The duration of the planning task, in ticks
+    pub planning_task_duration: NonZeroU64,
+}
+impl PlanningDetail {
+    /// Creates a new planning detail, panics if `planning_task_duration` is 0.
+    pub fn new(visits: u32, planning_task_duration: u64) -> Self {
+        Self {
+            visits,
+            planning_task_duration: NonZeroU64::new(planning_task_duration)
+                .expect("Planning task duration must be non-zero"),
+        }
+    }
+}
+
+/// The planning details set for specific agents, others use the MCTS configuration.
+#[derive(Debug, Default)]
+pub(crate) struct PlanningDetails(BTreeMap<AgentId, PlanningDetail>);
+impl PlanningDetails {
+    /// Sets the planning detail of an agent, or resets it if `None`.
+    pub(crate) fn set(&mut self, agent: AgentId, detail: Option<PlanningDetail>) {
+        match detail {
+            Some(detail) => self.0.insert(agent, detail),
+            None => self.0.remove(&agent),
+        };
+    }
+
+    /// Gets the planning detail of an agent, falling back to `mcts_config`.
+    pub(crate) fn get(&self, agent: AgentId, mcts_config: &MCTSConfiguration) -> PlanningDetail {
+        self.0
+            .get(&agent)
+            .copied()
+            .unwrap_or_else(|| PlanningDetail {
+                visits: mcts_config.visits,
+                planning_task_duration: mcts_config
+                    .planning_task_duration
+                    .expect("Planning task must have non-zero duration"),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::tests::{trivial_mcts_config, TrivialExecutorState};
+    use crate::IncrementalExecutor;
+    use npc_engine_core::{PlanningBudget, PlanningTask};
+
+    #[test]
+    fn agents_plan_with_their_detail() {
+        let mut executor_state = TrivialExecutorState(3);
+        let mut executor = IncrementalExecutor::new(
+            trivial_mcts_config(),
+            PlanningBudget::Visits(1),
+            &mut executor_state,
+        );
+        let distant = PlanningDetail::new(1, 20);
+        executor.set_planning_details(|agent, _| (agent == AgentId(1)).then_some(distant));
+        executor.set_planning_detail(AgentId(0), Some(PlanningDetail::new(2, 3)));
+        executor.step();
+        let planning_end = |agent| {
+            executor
+                .active_tasks()
+                .iter()
+                .find(|task| task.agent == agent)
+                .filter(|task| task.task.downcast_ref::<PlanningTask>().is_some())
+                .map(|task| task.end)
+        };
+        assert_eq!(planning_end(AgentId(0)), Some(3));
+        assert_eq!(planning_end(AgentId(1)), Some(20));
+        assert_eq!(planning_end(AgentId(2)), Some(10));
+        // Agents 0 and 1 need 2 visits and finish after two slices, agent 2 needs 7
+        executor.step();
+        executor.step();
+        assert_eq!(executor.planning_agents_count(), 1);
+    }
+}
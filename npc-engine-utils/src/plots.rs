@@ -0,0 +1,305 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Write},
+};
+
+use npc_engine_core::{AgentId, Domain, MCTS};
+
+use crate::{
+    timeline::{escape_xml, task_hue},
+    SimulationData,
+};
+
+const WIDTH: f64 = 480.;
+const HEIGHT: f64 = 240.;
+const MARGIN: f64 = 40.;
+const BAR_HEIGHT: f64 = 18.;
+const LABEL_WIDTH: f64 = 160.;
+
+/// An SVG image, shown inline by [evcxr](https://github.com/evcxr/evcxr) notebooks and displayed as its SVG source.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Svg(pub String);
+
+impl Svg {
+    /// Shows the image in an evcxr notebook, called by evcxr when the image is the value of a cell.
+    pub fn evcxr_display(&self) {
+        println!(
+            "EVCXR_BEGIN_CONTENT image/svg+xml\n{}\nEVCXR_END_CONTENT",
+            self.0
+        );
+    }
+
+    /// Returns the SVG source.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Svg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+fn svg_header(width: f64, height: f64) -> String {
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" font-family=\"sans-serif\" font-size=\"11\">\n"
+    )
+}
+
+/// Renders horizontal bars of `(label, length, annotation)`, scaled to the longest bar.
+fn bar_chart(title: &str, bars: &[(String, f64, String)]) -> Svg {
+    let height = MARGIN + bars.len() as f64 * BAR_HEIGHT + 8.;
+    let mut svg = svg_header(WIDTH, height);
+    writeln!(
+        svg,
+        "<text x=\"4\" y=\"16\" font-weight=\"bold\">{}</text>",
+        escape_xml(title)
+    )
+    .unwrap();
+    let max = bars
+        .iter()
+        .map(|(_, length, _)| *length)
+        .fold(0., f64::max)
+        .max(f64::MIN_POSITIVE);
+    let bar_space = WIDTH - LABEL_WIDTH - MARGIN;
+    for (index, (label, length, annotation)) in bars.iter().enumerate() {
+        let y = MARGIN - 12. + index as f64 * BAR_HEIGHT;
+        let width = (length / max * bar_space).max(1.);
+        let label = escape_xml(label);
+        writeln!(
+            svg,
+            "<text x=\"4\" y=\"{:.1}\">{label}</text><rect x=\"{LABEL_WIDTH}\" y=\"{y:.1}\" width=\"{width:.1}\" height=\"{:.1}\" fill=\"hsl({}, 60%, 70%)\"><title>{label}: {}</title></rect><text x=\"{:.1}\" y=\"{:.1}\">{}</text>",
+            y + BAR_HEIGHT * 2. / 3.,
+            BAR_HEIGHT - 4.,
+            task_hue(&label),
+            escape_xml(annotation),
+            LABEL_WIDTH + width + 4.,
+            y + BAR_HEIGHT * 2. / 3.,
+            escape_xml(annotation)
+        )
+        .unwrap();
+    }
+    svg.push_str("</svg>\n");
+    Svg(svg)
+}
+
+/// Plots the visits of the tasks at the root of a search as bars, annotated with their expected values for the planning agent.
+///
+/// Tasks are sorted by decreasing visits.
+pub fn plot_root_tasks<D: Domain>(mcts: &MCTS<D>) -> Svg {
+    let agent = mcts.agent();
+    let mut tasks: Vec<_> = mcts
+        .get_edges(&mcts.root_node())
+        .into_iter()
+        .flat_map(|edges| edges.expanded_tasks())
+        .map(|(task, edge)| {
+            let edge = edge.lock().unwrap();
+            (format!("{task:?}"), edge.visits(), edge.q_value(agent))
+        })
+        .collect();
+    tasks.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let bars: Vec<_> = tasks
+        .into_iter()
+        .map(|(task, visits, value)| {
+            (
+                task,
+                visits as f64,
+                format!("{visits} visits, Q {value:.2}"),
+            )
+        })
+        .collect();
+    bar_chart(
+        &format!("Root tasks of {} at T{}", agent, mcts.start_tick()),
+        &bars,
+    )
+}
+
+/// Plots the number of nodes of a search tree by tick after the start of the search, to see how deep the search went.
+pub fn plot_tree_depths<D: Domain>(mcts: &MCTS<D>) -> Svg {
+    let mut counts = BTreeMap::new();
+    for (node, _) in mcts.nodes() {
+        *counts
+            .entry(node.tick().saturating_sub(mcts.start_tick()))
+            .or_insert(0usize) += 1;
+    }
+    let bars: Vec<_> = counts
+        .into_iter()
+        .map(|(depth, count)| (format!("+{depth}"), count as f64, count.to_string()))
+        .collect();
+    bar_chart(
+        &format!("Nodes by tick, {} nodes", mcts.node_count()),
+        &bars,
+    )
+}
+
+/// A named series of values over time, plotted by [plot_values].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ValueSeries {
+    /// The name of the series, shown in the legend
+    pub name: String,
+    /// The points, as ticks and values, sorted by tick
+    pub points: Vec<(f64, f64)>,
+}
+
+impl ValueSeries {
+    /// Creates a series named `name` from its points.
+    pub fn new(name: impl Into<String>, points: impl IntoIterator<Item = (f64, f64)>) -> Self {
+        Self {
+            name: name.into(),
+            points: points.into_iter().collect(),
+        }
+    }
+
+    /// Returns one series per agent of the values of the root of its plannings over ticks.
+    pub fn planning_values(data: &SimulationData) -> Vec<Self> {
+        let mut series: BTreeMap<AgentId, Vec<_>> = BTreeMap::new();
+        for row in &data.agent_rows {
+            series
+                .entry(row.agent)
+                .or_default()
+                .push((row.tick as f64, row.value as f64));
+        }
+        series
+            .into_iter()
+            .map(|(agent, points)| Self::new(agent.to_string(), points))
+            .collect()
+    }
+
+    /// Returns the series of the world summary value `name` over ticks.
+    pub fn world_values(data: &SimulationData, name: &str) -> Self {
+        let points = data
+            .world_rows
+            .iter()
+            .filter_map(|row| Some((row.tick as f64, *row.values.get(name)?)));
+        Self::new(name, points)
+    }
+}
+
+/// Plots series of values over time as lines, with the ranges of ticks and values on the axes.
+///
+/// Non-finite values are skipped.
+pub fn plot_values(title: &str, series: &[ValueSeries]) -> Svg {
+    let points = || {
+        series
+            .iter()
+            .flat_map(|series| series.points.iter())
+            .filter(|(x, y)| x.is_finite() && y.is_finite())
+    };
+    let range = |values: &mut dyn Iterator<Item = f64>| {
+        let (min, max) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), value| {
+            (min.min(value), max.max(value))
+        });
+        match (min.is_finite(), max - min > 0.) {
+            (false, _) => (0., 1.),
+            (true, false) => (min - 0.5, max + 0.5),
+            (true, true) => (min, max),
+        }
+    };
+    let (x_min, x_max) = range(&mut points().map(|(x, _)| *x));
+    let (y_min, y_max) = range(&mut points().map(|(_, y)| *y));
+    let (left, right, top, bottom) = (
+        MARGIN + 10.,
+        WIDTH - 100.,
+        MARGIN / 2. + 10.,
+        HEIGHT - MARGIN / 2.,
+    );
+    let to_x = |x: f64| left + (x - x_min) / (x_max - x_min) * (right - left);
+    let to_y = |y: f64| bottom - (y - y_min) / (y_max - y_min) * (bottom - top);
+
+    let mut svg = svg_header(WIDTH, HEIGHT);
+    writeln!(
+        svg,
+        "<text x=\"4\" y=\"16\" font-weight=\"bold\">{}</text>",
+        escape_xml(title)
+    )
+    .unwrap();
+    writeln!(
+        svg,
+        "<path d=\"M{left} {top} V{bottom} H{right}\" fill=\"none\" stroke=\"gray\"/>\n<text x=\"4\" y=\"{top}\">{}</text><text x=\"4\" y=\"{bottom}\">{}</text><text x=\"{left}\" y=\"{}\">{}</text><text x=\"{right}\" y=\"{}\" text-anchor=\"end\">{}</text>",
+        format_value(y_max),
+        format_value(y_min),
+        bottom + 14.,
+        format_value(x_min),
+        bottom + 14.,
+        format_value(x_max)
+    )
+    .unwrap();
+    for (index, series) in series.iter().enumerate() {
+        let name = escape_xml(&series.name);
+        let colour = format!("hsl({}, 70%, 45%)", (index * 137) % 360);
+        let path: Vec<_> = series
+            .points
+            .iter()
+            .filter(|(x, y)| x.is_finite() && y.is_finite())
+            .map(|(x, y)| format!("{:.1} {:.1}", to_x(*x), to_y(*y)))
+            .collect();
+        if !path.is_empty() {
+            writeln!(
+                svg,
+                "<polyline points=\"{}\" fill=\"none\" stroke=\"{colour}\" stroke-width=\"2\"><title>{name}</title></polyline>",
+                path.join(" ")
+            )
+            .unwrap();
+        }
+        writeln!(
+            svg,
+            "<text x=\"{:.1}\" y=\"{:.1}\" fill=\"{colour}\">{name}</text>",
+            right + 8.,
+            top + 10. + index as f64 * 14.
+        )
+        .unwrap();
+    }
+    svg.push_str("</svg>\n");
+    Svg(svg)
+}
+
+/// Formats an axis bound with at most 2 decimals.
+fn format_value(value: f64) -> String {
+    let text = format!("{value:.2}");
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        replay::tests::{counter_mcts_config, CounterDomain},
+        PlanningMetrics,
+    };
+
+    #[test]
+    fn searches_and_series_are_plotted() {
+        let mut mcts = MCTS::<CounterDomain>::new(0, AgentId(0), counter_mcts_config());
+        let task = mcts.run().unwrap();
+
+        let root_tasks = plot_root_tasks(&mcts);
+        assert!(root_tasks.as_str().starts_with("<svg"));
+        assert_eq!(root_tasks.as_str().matches("<rect").count(), 2);
+        assert!(root_tasks.as_str().contains("Add(1)"));
+        let depths = plot_tree_depths(&mcts);
+        assert!(depths.as_str().contains("<title>+0: 1</title>"));
+
+        let mut data = SimulationData::new("run", None);
+        data.record_planning(&PlanningMetrics::new(0, &mcts, task.clone()));
+        data.record_planning(&PlanningMetrics::new(4, &mcts, task));
+        data.record_world(0, [("counter", 0.)]);
+        data.record_world(4, [("counter", 3.)]);
+        let mut series = ValueSeries::planning_values(&data);
+        series.push(ValueSeries::world_values(&data, "counter"));
+        assert_eq!(series[0].points.len(), 2);
+        let values = plot_values("Values", &series);
+        assert_eq!(values.as_str().matches("<polyline").count(), 2);
+        assert!(values.as_str().contains(">A0</text>"));
+        assert_eq!(format_value(2.5), "2.5");
+        assert_eq!(format_value(4.), "4");
+
+        // Empty plots are valid images
+        assert!(plot_values("Nothing", &[]).as_str().ends_with("</svg>\n"));
+    }
+}
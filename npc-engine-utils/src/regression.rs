@@ -0,0 +1,252 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{
+    collections::BTreeMap,
+    panic::{self, AssertUnwindSafe},
+};
+
+use npc_engine_core::{AgentId, MCTSConfiguration, StateDiffRef};
+
+use crate::{DebugStep, ExecutableDomain, ExecutorState, ExecutorStateLocal, SimpleExecutor};
+
+/// The outcome of the simulation of one seed of a [RegressionReport].
+#[derive(Clone, Debug, PartialEq)]
+pub struct SeedOutcome {
+    /// The seed of the simulation
+    pub seed: u64,
+    /// The panic message, if the simulation panicked
+    pub panic: Option<String>,
+    /// The number of executed steps
+    pub steps: u64,
+    /// The tick of the last decision
+    pub last_tick: u64,
+    /// The current value of each agent in the final state
+    pub final_values: BTreeMap<AgentId, f32>,
+    /// The number of decisions of each agent
+    pub decisions: BTreeMap<AgentId, u64>,
+    /// The longest number of ticks each agent went without deciding, including before its first decision and after its last one
+    pub longest_wait: BTreeMap<AgentId, u64>,
+}
+
+impl SeedOutcome {
+    /// Returns the mean of the final values of the agents, or 0 if there is none.
+    pub fn mean_final_value(&self) -> f32 {
+        if self.final_values.is_empty() {
+            0.
+        } else {
+            self.final_values.values().sum::<f32>() / self.final_values.len() as f32
+        }
+    }
+}
+
+/// Aggregate properties of a domain simulated over many seeds, see [run_regression].
+///
+/// The `assert_*` methods panic with the offending seeds, so that behavioral regressions fail `cargo test`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RegressionReport {
+    /// The outcomes of all seeds, in order
+    pub outcomes: Vec<SeedOutcome>,
+}
+
+impl RegressionReport {
+    /// Returns the mean over the seeds that did not panic of their mean final value.
+    pub fn mean_final_value(&self) -> f32 {
+        let values: Vec<_> = self
+            .outcomes
+            .iter()
+            .filter(|outcome| outcome.panic.is_none())
+            .map(SeedOutcome::mean_final_value)
+            .collect();
+        if values.is_empty() {
+            0.
+        } else {
+            values.iter().sum::<f32>() / values.len() as f32
+        }
+    }
+
+    /// Asserts that no simulation panicked.
+    pub fn assert_no_panics(&self) -> &Self {
+        let panics: Vec<_> = self
+            .outcomes
+            .iter()
+            .filter_map(|outcome| {
+                outcome
+                    .panic
+                    .as_ref()
+                    .map(|message| format!("seed {}: {message}", outcome.seed))
+            })
+            .collect();
+        assert!(
+            panics.is_empty(),
+            "{} simulations panicked: {}",
+            panics.len(),
+            panics.join("; ")
+        );
+        self
+    }
+
+    /// Asserts that the mean final value over all seeds is above `threshold`.
+    pub fn assert_mean_value_above(&self, threshold: f32) -> &Self {
+        let mean = self.mean_final_value();
+        assert!(
+            mean > threshold,
+            "mean final value {mean} is not above {threshold}"
+        );
+        self
+    }
+
+    /// Asserts that no agent went more than `max_wait` ticks without deciding, in any simulation.
+    pub fn assert_no_starvation(&self, max_wait: u64) -> &Self {
+        let starving: Vec<_> = self
+            .outcomes
+            .iter()
+            .flat_map(|outcome| {
+                outcome
+                    .longest_wait
+                    .iter()
+                    .filter(|(_, wait)| **wait > max_wait)
+                    .map(move |(agent, wait)| {
+                        format!("seed {}: {agent} waited {wait} ticks", outcome.seed)
+                    })
+            })
+            .collect();
+        assert!(
+            starving.is_empty(),
+            "agents starved: {}",
+            starving.join("; ")
+        );
+        self
+    }
+}
+
+/// Simulates a domain with a [SimpleExecutor] once per seed, for at most `max_steps` steps,
+/// and collects the properties of each simulation, catching panics.
+///
+/// `create_executor_state` creates the executor state of the simulation of a given seed.
+pub fn run_regression<D, S, C>(
+    mcts_config: &MCTSConfiguration,
+    seeds: impl IntoIterator<Item = u64>,
+    max_steps: u64,
+    create_executor_state: C,
+) -> RegressionReport
+where
+    D: ExecutableDomain,
+    D::State: Clone,
+    S: ExecutorState<D> + ExecutorStateLocal<D>,
+    C: Fn(u64) -> S,
+{
+    let outcomes = seeds
+        .into_iter()
+        .map(|seed| {
+            let mut outcome = SeedOutcome {
+                seed,
+                panic: None,
+                steps: 0,
+                last_tick: 0,
+                final_values: BTreeMap::new(),
+                decisions: BTreeMap::new(),
+                longest_wait: BTreeMap::new(),
+            };
+            let mut mcts_config = mcts_config.clone();
+            mcts_config.seed = Some(seed);
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                simulate(&mut outcome, mcts_config, max_steps, &create_executor_state)
+            }));
+            if let Err(payload) = result {
+                outcome.panic = Some(
+                    payload
+                        .downcast_ref::<&str>()
+                        .map(|message| message.to_string())
+                        .or_else(|| payload.downcast_ref::<String>().cloned())
+                        .unwrap_or_else(|| "unknown panic".into()),
+                );
+            }
+            outcome
+        })
+        .collect();
+    RegressionReport { outcomes }
+}
+
+fn simulate<D, S, C>(
+    outcome: &mut SeedOutcome,
+    mcts_config: MCTSConfiguration,
+    max_steps: u64,
+    create_executor_state: &C,
+) where
+    D: ExecutableDomain,
+    D::State: Clone,
+    S: ExecutorState<D> + ExecutorStateLocal<D>,
+    C: Fn(u64) -> S,
+{
+    let mut executor_state = create_executor_state(outcome.seed);
+    let mut executor = SimpleExecutor::new(mcts_config, &mut executor_state);
+    let mut last_decisions: BTreeMap<AgentId, u64> = executor
+        .active_tasks()
+        .iter()
+        .map(|active_task| (active_task.agent, active_task.start))
+        .collect();
+    while outcome.steps < max_steps {
+        match executor.step_until_decision() {
+            DebugStep::Finished => break,
+            DebugStep::Executed => {}
+            DebugStep::Decision(decision) => {
+                let (agent, tick) = (decision.agent(), decision.tick());
+                let last = last_decisions.insert(agent, tick).unwrap_or(tick);
+                let wait = outcome.longest_wait.entry(agent).or_default();
+                *wait = (*wait).max(tick - last);
+                *outcome.decisions.entry(agent).or_default() += 1;
+                outcome.last_tick = outcome.last_tick.max(tick);
+                executor.commit_decision(*decision);
+            }
+        }
+        outcome.steps += 1;
+    }
+    let diff = Default::default();
+    for (agent, last) in last_decisions {
+        let wait = outcome.longest_wait.entry(agent).or_default();
+        *wait = (*wait).max(outcome.last_tick.saturating_sub(last));
+        let value = D::get_current_value(
+            outcome.last_tick,
+            StateDiffRef::<D>::new(executor.state(), &diff),
+            agent,
+        );
+        outcome.final_values.insert(agent, *value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::tests::{counter_mcts_config, CounterDomain, CounterExecutorState};
+
+    #[test]
+    fn regression_asserts_aggregate_properties() {
+        let report =
+            run_regression::<CounterDomain, _, _>(&counter_mcts_config(), 0..4, 20, |seed| {
+                CounterExecutorState(seed as u32)
+            });
+        assert_eq!(report.outcomes.len(), 4);
+        let outcome = &report.outcomes[2];
+        assert_eq!(outcome.panic, None);
+        assert_eq!(outcome.steps, 20);
+        assert_eq!(outcome.decisions[&AgentId(0)], 20);
+        assert_eq!(outcome.longest_wait[&AgentId(0)], 1);
+        assert!(outcome.final_values[&AgentId(0)] > 2.);
+        report
+            .assert_no_panics()
+            .assert_mean_value_above(10.)
+            .assert_no_starvation(1);
+
+        let starving = panic::catch_unwind(|| {
+            report.assert_no_starvation(0);
+        });
+        assert!(starving.is_err());
+        let too_demanding = panic::catch_unwind(|| {
+            report.assert_mean_value_above(1000.);
+        });
+        assert!(too_demanding.is_err());
+    }
+}
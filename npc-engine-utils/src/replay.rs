@@ -0,0 +1,389 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+};
+
+use npc_engine_core::{ActiveTask, AgentId, Context, ContextMut, MCTSConfiguration, Task, MCTS};
+use rustc_hash::FxHasher;
+use serde::{Deserialize, Serialize};
+
+use crate::{ExecutableDomain, SnapshotDomain, TaskSnapshot};
+
+/// Returns a hash of a state that is stable across runs and platforms.
+pub fn stable_state_hash<S: Hash>(state: &S) -> u64 {
+    let mut hasher = FxHasher::default();
+    state.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// One executed task of a [Replay], with the hash of the state after its execution.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReplayStep<T> {
+    pub tick: u64,
+    pub agent: AgentId,
+    pub task: TaskSnapshot<T>,
+    pub state_hash: u64,
+}
+
+/// The seed used by a planning of a [Replay].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReplaySeed {
+    pub tick: u64,
+    pub agent: AgentId,
+    pub seed: u64,
+}
+
+/// A record of a simulation, that can be re-played deterministically with [play_replay].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Replay<S, T> {
+    /// The configuration used for planning
+    pub mcts_config: MCTSConfiguration,
+    /// The state of the world at the beginning of the simulation
+    pub initial_state: S,
+    /// The seeds of all plannings, in order
+    pub seeds: Vec<ReplaySeed>,
+    /// All executed tasks, in order
+    pub steps: Vec<ReplayStep<T>>,
+}
+
+/// Records a simulation into a [Replay].
+///
+/// Call [record_execution](Self::record_execution) from [ExecutorState::post_action_execute_hook](crate::ExecutorState::post_action_execute_hook)
+/// and [record_planning](Self::record_planning) from [ExecutorState::post_mcts_run_hook](crate::ExecutorState::post_mcts_run_hook).
+pub struct ReplayRecorder<D>
+where
+    D: ExecutableDomain + SnapshotDomain,
+{
+    replay: Replay<D::State, D::TaskSnapshot>,
+}
+impl<D> ReplayRecorder<D>
+where
+    D: ExecutableDomain + SnapshotDomain,
+    D::State: Clone + Hash,
+{
+    /// Creates a new recorder for a simulation starting with `initial_state`.
+    pub fn new(initial_state: D::State, mcts_config: &MCTSConfiguration) -> Self {
+        Self {
+            replay: Replay {
+                mcts_config: mcts_config.clone(),
+                initial_state,
+                seeds: Vec::new(),
+                steps: Vec::new(),
+            },
+        }
+    }
+
+    /// Records the execution of a task in `state`, resulting in `diff`.
+    pub fn record_execution(
+        &mut self,
+        state: &D::State,
+        diff: &D::Diff,
+        active_task: &ActiveTask<D>,
+    ) {
+        let mut new_state = state.clone();
+        D::apply_diff(diff.clone(), &mut new_state);
+        self.replay.steps.push(ReplayStep {
+            tick: active_task.end,
+            agent: active_task.agent,
+            task: TaskSnapshot::new(&*active_task.task),
+            state_hash: stable_state_hash(&new_state),
+        });
+    }
+
+    /// Records the seed of a planning.
+    pub fn record_planning(&mut self, mcts: &MCTS<D>) {
+        self.replay.seeds.push(ReplaySeed {
+            tick: mcts.start_tick(),
+            agent: mcts.agent(),
+            seed: mcts.seed(),
+        });
+    }
+
+    /// Gets the replay recorded so far.
+    pub fn replay(&self) -> &Replay<D::State, D::TaskSnapshot> {
+        &self.replay
+    }
+
+    /// Consumes the recorder and returns the recorded replay.
+    pub fn into_replay(self) -> Replay<D::State, D::TaskSnapshot> {
+        self.replay
+    }
+}
+
+/// The reason why a replay diverged from its record.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReplayDivergence {
+    /// The task was not valid when re-played
+    InvalidTask,
+    /// The state after executing the task differs, with the recorded and actual hashes
+    StateMismatch { expected: u64, actual: u64 },
+}
+
+/// An error when re-playing a [Replay], giving the first step at which it diverged.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReplayError {
+    pub step: usize,
+    pub tick: u64,
+    pub agent: AgentId,
+    pub divergence: ReplayDivergence,
+}
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "replay diverged at step {} (T{}, {}): ",
+            self.step, self.tick, self.agent
+        )?;
+        match self.divergence {
+            ReplayDivergence::InvalidTask => write!(f, "task is not valid"),
+            ReplayDivergence::StateMismatch { expected, actual } => write!(
+                f,
+                "state hash is {actual:#018x} instead of {expected:#018x}"
+            ),
+        }
+    }
+}
+impl std::error::Error for ReplayError {}
+
+/// Executes `task` for `agent` at `tick` directly on `state`, returns whether it was valid.
+pub(crate) fn execute_on_state<D: ExecutableDomain>(
+    tick: u64,
+    agent: AgentId,
+    task: &dyn Task<D>,
+    state: &mut D::State,
+) -> bool {
+    let mut diff = D::Diff::default();
+    let ctx = Context::with_state_and_diff(tick, state, &diff, agent);
+    if !task.is_valid(ctx) {
+        return false;
+    }
+    let ctx = ContextMut::with_state_and_diff(tick, state, &mut diff, agent);
+    task.execute(ctx);
+    D::apply_diff(diff, state);
+    true
+}
+
+/// Re-plays a [Replay] by executing all its tasks in order, verifying the resulting state after each of them.
+///
+/// Returns the final state, or the first divergence from the record.
+pub fn play_replay<D>(replay: Replay<D::State, D::TaskSnapshot>) -> Result<D::State, ReplayError>
+where
+    D: ExecutableDomain + SnapshotDomain,
+    D::State: Hash,
+{
+    let mut state = replay.initial_state;
+    for (index, step) in replay.steps.into_iter().enumerate() {
+        let ReplayStep {
+            tick,
+            agent,
+            task,
+            state_hash,
+        } = step;
+        let error = |divergence| ReplayError {
+            step: index,
+            tick,
+            agent,
+            divergence,
+        };
+        let task = task.restore::<D>();
+        if !execute_on_state(tick, agent, &*task, &mut state) {
+            return Err(error(ReplayDivergence::InvalidTask));
+        }
+        let actual = stable_state_hash(&state);
+        if actual != state_hash {
+            return Err(error(ReplayDivergence::StateMismatch {
+                expected: state_hash,
+                actual,
+            }));
+        }
+    }
+    Ok(state)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::{ExecutorState, ExecutorStateLocal, SimpleExecutor};
+    use npc_engine_core::{
+        impl_task_boxed_methods, ActiveTasks, AgentValue, Behavior, Domain, StateDiffRef, Task,
+        TaskDuration,
+    };
+    use std::collections::{BTreeMap, BTreeSet};
+
+    pub(crate) struct CounterDomain;
+    impl Domain for CounterDomain {
+        type State = u32;
+        type Diff = Option<u32>;
+        type DisplayAction = ();
+
+        fn list_behaviors() -> &'static [&'static dyn Behavior<Self>] {
+            &[&CounterBehavior]
+        }
+
+        fn get_current_value(
+            _tick: u64,
+            state_diff: StateDiffRef<Self>,
+            _agent: AgentId,
+        ) -> AgentValue {
+            let state = state_diff.diff.unwrap_or(*state_diff.initial_state);
+            AgentValue::new(state as f32).unwrap()
+        }
+
+        fn update_visible_agents(
+            _start_tick: u64,
+            ctx: Context<Self>,
+            agents: &mut BTreeSet<AgentId>,
+        ) {
+            agents.insert(ctx.agent);
+        }
+
+        fn display_diff(state_diff: StateDiffRef<Self>) -> BTreeMap<String, String> {
+            state_diff
+                .diff
+                .iter()
+                .map(|counter| ("counter".to_string(), counter.to_string()))
+                .collect()
+        }
+    }
+    impl SnapshotDomain for CounterDomain {
+        type TaskSnapshot = u32;
+        fn snapshot_task(task: &dyn Task<Self>) -> Self::TaskSnapshot {
+            task.downcast_ref::<Add>().unwrap().0
+        }
+        fn restore_task(snapshot: Self::TaskSnapshot) -> Box<dyn Task<Self>> {
+            Box::new(Add(snapshot))
+        }
+    }
+
+    #[derive(Copy, Clone, Debug)]
+    struct CounterBehavior;
+    impl Behavior<CounterDomain> for CounterBehavior {
+        fn add_own_tasks(
+            &self,
+            _ctx: Context<CounterDomain>,
+            tasks: &mut Vec<Box<dyn Task<CounterDomain>>>,
+        ) {
+            tasks.push(Box::new(Add(1)));
+            tasks.push(Box::new(Add(2)));
+        }
+
+        fn is_valid(&self, _ctx: Context<CounterDomain>) -> bool {
+            true
+        }
+    }
+
+    #[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+    pub(crate) struct Add(pub u32);
+    impl Task<CounterDomain> for Add {
+        fn duration(&self, _ctx: Context<CounterDomain>) -> TaskDuration {
+            1
+        }
+        fn execute(&self, ctx: ContextMut<CounterDomain>) -> Option<Box<dyn Task<CounterDomain>>> {
+            let state = ctx.state_diff.diff.unwrap_or(*ctx.state_diff.initial_state);
+            *ctx.state_diff.diff = Some(state + self.0);
+            None
+        }
+        fn is_valid(&self, ctx: Context<CounterDomain>) -> bool {
+            ctx.state_diff.diff.unwrap_or(*ctx.state_diff.initial_state) < 100
+        }
+        fn display_action(&self) {}
+        impl_task_boxed_methods!(CounterDomain);
+    }
+
+    /// Executor state for the counter domain, with the given initial state
+    pub(crate) struct CounterExecutorState(pub u32);
+    impl ExecutorStateLocal<CounterDomain> for CounterExecutorState {
+        fn create_initial_state(&self) -> u32 {
+            self.0
+        }
+        fn init_task_queue(&self, _state: &u32) -> ActiveTasks<CounterDomain> {
+            [ActiveTask::new_idle(0, AgentId(0), AgentId(0))]
+                .into_iter()
+                .collect()
+        }
+    }
+    impl ExecutorState<CounterDomain> for CounterExecutorState {}
+
+    pub(crate) fn counter_mcts_config() -> MCTSConfiguration {
+        MCTSConfiguration {
+            allow_invalid_tasks: false,
+            visits: 20,
+            depth: 5,
+            exploration: 1.414,
+            discount_hl: 10.,
+            seed: Some(0),
+            planning_task_duration: None,
+        }
+    }
+
+    struct RecordingExecutorState(ReplayRecorder<CounterDomain>);
+    impl ExecutorStateLocal<CounterDomain> for RecordingExecutorState {
+        fn create_initial_state(&self) -> u32 {
+            self.0.replay().initial_state
+        }
+        fn init_task_queue(&self, state: &u32) -> ActiveTasks<CounterDomain> {
+            CounterExecutorState(*state).init_task_queue(state)
+        }
+    }
+    impl ExecutorState<CounterDomain> for RecordingExecutorState {
+        fn post_action_execute_hook(
+            &mut self,
+            state: &u32,
+            diff: &Option<u32>,
+            active_task: &ActiveTask<CounterDomain>,
+            _queue: &mut ActiveTasks<CounterDomain>,
+        ) {
+            self.0.record_execution(state, diff, active_task);
+        }
+        fn post_mcts_run_hook(
+            &mut self,
+            mcts: &MCTS<CounterDomain>,
+            _last_active_task: &ActiveTask<CounterDomain>,
+        ) {
+            self.0.record_planning(mcts);
+        }
+    }
+
+    /// Records a replay of the counter domain starting at 3, for `steps` steps.
+    pub(crate) fn record_counter_replay(steps: usize) -> Replay<u32, u32> {
+        let mcts_config = counter_mcts_config();
+        let mut executor_state = RecordingExecutorState(ReplayRecorder::new(3, &mcts_config));
+        let mut executor = SimpleExecutor::new(mcts_config, &mut executor_state);
+        for _ in 0..steps {
+            executor.step();
+        }
+        executor_state.0.into_replay()
+    }
+
+    #[test]
+    fn record_and_play_replay() {
+        let replay = record_counter_replay(10);
+        assert_eq!(replay.steps.len(), 10);
+        assert_eq!(replay.seeds.len(), 10);
+
+        // Re-playing from serialized form must give the same result
+        let replay: Replay<u32, u32> =
+            serde_json::from_str(&serde_json::to_string(&replay).unwrap()).unwrap();
+        let final_state = play_replay::<CounterDomain>(replay.clone()).unwrap();
+        assert!(final_state > 3);
+
+        // Changing a task must be detected
+        let mut altered_replay = replay;
+        let step = altered_replay
+            .steps
+            .iter_mut()
+            .find(|step| matches!(step.task, TaskSnapshot::Domain(_)))
+            .unwrap();
+        step.task = TaskSnapshot::Domain(7);
+        let error = play_replay::<CounterDomain>(altered_replay).unwrap_err();
+        assert!(matches!(
+            error.divergence,
+            ReplayDivergence::StateMismatch { .. }
+        ));
+    }
+}
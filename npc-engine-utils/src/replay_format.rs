@@ -0,0 +1,355 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{collections::BTreeMap, fmt, fs, hash::Hash, path::Path};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    play_replay, replay::execute_on_state, ExecutableDomain, Replay, ReplayError, SnapshotDomain,
+};
+
+/// The name of the replay interchange format, the `format` field of [ReplayFile].
+pub const REPLAY_FORMAT: &str = "npc-engine-replay";
+
+/// The version of the replay interchange format written by this crate, the `version` field of [ReplayFile].
+///
+/// Readers accept files of this version or older.
+pub const REPLAY_FORMAT_VERSION: u32 = 1;
+
+/// The first bytes of a binary replay file, followed by the version as a little-endian `u32`.
+pub const REPLAY_BINARY_MAGIC: &[u8; 4] = b"NPCR";
+
+/// A state of a [ReplayFile] stored along the steps, so that readers can seek without re-executing from the start.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ReplayKeyframe<S> {
+    /// The number of steps executed before this state
+    pub step: usize,
+    /// The tick of the last executed step, 0 before the first
+    pub tick: u64,
+    /// The state after executing `step` steps
+    pub state: S,
+}
+
+/// A [Replay] in the versioned interchange format, exchanged between games, the command-line tool, the web viewer and tests.
+///
+/// In JSON, a file is an object with the fields:
+/// - `format`: always [REPLAY_FORMAT],
+/// - `version`: the version of the format, see [REPLAY_FORMAT_VERSION],
+/// - `domain`: the name of the domain the states and tasks belong to,
+/// - `metadata`: free-form string values, such as the name of the game or the date of recording,
+/// - `replay`: the [Replay], with the MCTS configuration, the initial state, the seeds of all plannings
+///   and the executed tasks (`"idle"`, `{"planning": duration}` or `{"domain": snapshot}` with the task snapshot of the domain), each with the hash of the resulting state,
+/// - `keyframes`: optional [ReplayKeyframe]s.
+///
+/// With feature `bincode`, the same content is written in binary as [REPLAY_BINARY_MAGIC],
+/// the version as a little-endian `u32`, and the file encoded with [bincode](https://docs.rs/bincode) 1.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReplayFile<S, T> {
+    /// The name of the format, [REPLAY_FORMAT]
+    pub format: String,
+    /// The version of the format
+    pub version: u32,
+    /// The name of the domain
+    pub domain: String,
+    /// Free-form metadata
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+    /// The recorded simulation
+    pub replay: Replay<S, T>,
+    /// States at some steps, in order
+    #[serde(default = "Vec::new")]
+    pub keyframes: Vec<ReplayKeyframe<S>>,
+}
+
+/// An error when reading or writing a [ReplayFile].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReplayFormatError {
+    /// The file could not be read or written
+    Io(String),
+    /// The file extension is neither `json` nor `npcr`
+    UnknownFormat(String),
+    /// The content is not a replay file
+    NotAReplay,
+    /// The version of the file is newer than [REPLAY_FORMAT_VERSION]
+    UnsupportedVersion(u32),
+    /// The file is binary but feature `bincode` is disabled
+    BinaryUnsupported,
+    /// The file could not be parsed or encoded
+    Encoding(String),
+    /// The file is for another domain, with the expected and actual names
+    WrongDomain { expected: String, actual: String },
+}
+impl fmt::Display for ReplayFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayFormatError::Io(error) => write!(f, "cannot access replay: {error}"),
+            ReplayFormatError::UnknownFormat(path) => {
+                write!(f, "unknown format of replay file {path}")
+            }
+            ReplayFormatError::NotAReplay => write!(f, "not a replay file"),
+            ReplayFormatError::UnsupportedVersion(version) => write!(
+                f,
+                "replay format version {version} is newer than supported version {REPLAY_FORMAT_VERSION}"
+            ),
+            ReplayFormatError::BinaryUnsupported => {
+                write!(f, "binary replays need feature bincode")
+            }
+            ReplayFormatError::Encoding(error) => write!(f, "cannot encode replay: {error}"),
+            ReplayFormatError::WrongDomain { expected, actual } => {
+                write!(f, "replay is for domain {actual} instead of {expected}")
+            }
+        }
+    }
+}
+impl std::error::Error for ReplayFormatError {}
+
+/// The fields of a [ReplayFile] checked before parsing the rest.
+#[derive(Deserialize)]
+struct ReplayHeader {
+    format: String,
+    version: u32,
+}
+
+/// Checks the name and version of the format.
+fn check_header(format: &str, version: u32) -> Result<(), ReplayFormatError> {
+    if format != REPLAY_FORMAT {
+        Err(ReplayFormatError::NotAReplay)
+    } else if version > REPLAY_FORMAT_VERSION {
+        Err(ReplayFormatError::UnsupportedVersion(version))
+    } else {
+        Ok(())
+    }
+}
+
+impl<S, T> ReplayFile<S, T> {
+    /// Creates a file of the current version for `replay` of the domain `domain`, without metadata nor keyframes.
+    pub fn new(domain: impl Into<String>, replay: Replay<S, T>) -> Self {
+        Self {
+            format: REPLAY_FORMAT.to_string(),
+            version: REPLAY_FORMAT_VERSION,
+            domain: domain.into(),
+            metadata: BTreeMap::new(),
+            replay,
+            keyframes: Vec::new(),
+        }
+    }
+
+    /// Sets the metadata value `key`.
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    /// Checks that the file belongs to the domain `domain`.
+    pub fn check_domain(&self, domain: &str) -> Result<(), ReplayFormatError> {
+        if self.domain == domain {
+            Ok(())
+        } else {
+            Err(ReplayFormatError::WrongDomain {
+                expected: domain.to_string(),
+                actual: self.domain.clone(),
+            })
+        }
+    }
+
+    /// Returns the number of executed tasks.
+    pub fn step_count(&self) -> usize {
+        self.replay.steps.len()
+    }
+}
+
+impl<S, T> ReplayFile<S, T>
+where
+    S: Clone,
+    T: Clone,
+{
+    /// Re-executes the replay to store a keyframe every `interval` steps, replacing existing keyframes.
+    ///
+    /// The states are not verified, see [verify](Self::verify).
+    pub fn with_keyframes<D>(mut self, interval: usize) -> Self
+    where
+        D: ExecutableDomain + SnapshotDomain<State = S, TaskSnapshot = T>,
+    {
+        let interval = interval.max(1);
+        self.keyframes.clear();
+        let mut state = self.replay.initial_state.clone();
+        for (index, step) in self.replay.steps.iter().enumerate() {
+            let task = step.task.clone().restore::<D>();
+            execute_on_state(step.tick, step.agent, &*task, &mut state);
+            if (index + 1) % interval == 0 {
+                self.keyframes.push(ReplayKeyframe {
+                    step: index + 1,
+                    tick: step.tick,
+                    state: state.clone(),
+                });
+            }
+        }
+        self
+    }
+
+    /// Returns the state after executing the first `step` steps, starting from the last keyframe before it.
+    ///
+    /// Steps beyond the end of the replay are ignored.
+    pub fn state_at_step<D>(&self, step: usize) -> S
+    where
+        D: ExecutableDomain + SnapshotDomain<State = S, TaskSnapshot = T>,
+    {
+        let step = step.min(self.replay.steps.len());
+        let (start, state) = self
+            .keyframes
+            .iter()
+            .rev()
+            .find(|keyframe| keyframe.step <= step)
+            .map_or((0, &self.replay.initial_state), |keyframe| {
+                (keyframe.step, &keyframe.state)
+            });
+        let mut state = state.clone();
+        for step in &self.replay.steps[start..step] {
+            let task = step.task.clone().restore::<D>();
+            execute_on_state(step.tick, step.agent, &*task, &mut state);
+        }
+        state
+    }
+
+    /// Re-plays the replay, verifying the state after each step, and returns the final state.
+    pub fn verify<D>(&self) -> Result<S, ReplayError>
+    where
+        D: ExecutableDomain + SnapshotDomain<State = S, TaskSnapshot = T>,
+        S: Hash,
+    {
+        play_replay::<D>(self.replay.clone())
+    }
+}
+
+impl<S, T> ReplayFile<S, T>
+where
+    S: Serialize + DeserializeOwned,
+    T: Serialize + DeserializeOwned,
+{
+    /// Writes the file as pretty-printed JSON.
+    pub fn to_json_string(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Replays are always serializable")
+    }
+
+    /// Parses a file from JSON, after checking its format and version.
+    pub fn from_json_str(json: &str) -> Result<Self, ReplayFormatError> {
+        let header: ReplayHeader =
+            serde_json::from_str(json).map_err(|_| ReplayFormatError::NotAReplay)?;
+        check_header(&header.format, header.version)?;
+        serde_json::from_str(json).map_err(|error| ReplayFormatError::Encoding(error.to_string()))
+    }
+
+    /// Writes the file in binary, see [ReplayFile].
+    #[cfg(feature = "bincode")]
+    pub fn to_binary(&self) -> Vec<u8> {
+        let mut bytes = REPLAY_BINARY_MAGIC.to_vec();
+        bytes.extend_from_slice(&self.version.to_le_bytes());
+        bincode::serialize_into(&mut bytes, self).expect("Replays are always serializable");
+        bytes
+    }
+
+    /// Parses a file from binary, after checking its format and version.
+    #[cfg(feature = "bincode")]
+    pub fn from_binary(bytes: &[u8]) -> Result<Self, ReplayFormatError> {
+        if bytes.len() < 8 || &bytes[..4] != REPLAY_BINARY_MAGIC {
+            return Err(ReplayFormatError::NotAReplay);
+        }
+        let version = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+        check_header(REPLAY_FORMAT, version)?;
+        let file: Self = bincode::deserialize(&bytes[8..])
+            .map_err(|error| ReplayFormatError::Encoding(error.to_string()))?;
+        check_header(&file.format, file.version)?;
+        Ok(file)
+    }
+
+    /// Parses a file in either encoding, recognizing binary files by [REPLAY_BINARY_MAGIC].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ReplayFormatError> {
+        if bytes.starts_with(REPLAY_BINARY_MAGIC) {
+            #[cfg(feature = "bincode")]
+            return Self::from_binary(bytes);
+            #[cfg(not(feature = "bincode"))]
+            return Err(ReplayFormatError::BinaryUnsupported);
+        }
+        let json = std::str::from_utf8(bytes).map_err(|_| ReplayFormatError::NotAReplay)?;
+        Self::from_json_str(json)
+    }
+
+    /// Loads a file in either encoding.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ReplayFormatError> {
+        let bytes = fs::read(path).map_err(|error| ReplayFormatError::Io(error.to_string()))?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// Saves the file, in an encoding given by the extension of `path`: `json`, or `npcr` for binary with feature `bincode`.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ReplayFormatError> {
+        let path = path.as_ref();
+        let bytes = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => self.to_json_string().into_bytes(),
+            #[cfg(feature = "bincode")]
+            Some("npcr") => self.to_binary(),
+            #[cfg(not(feature = "bincode"))]
+            Some("npcr") => return Err(ReplayFormatError::BinaryUnsupported),
+            _ => return Err(ReplayFormatError::UnknownFormat(path.display().to_string())),
+        };
+        fs::write(path, bytes).map_err(|error| ReplayFormatError::Io(error.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::tests::{record_counter_replay, CounterDomain};
+
+    #[test]
+    fn replay_files_round_trip_and_seek() {
+        let replay = record_counter_replay(10);
+        let file = ReplayFile::new("counter", replay)
+            .with_metadata("game", "test")
+            .with_keyframes::<CounterDomain>(4);
+        assert_eq!(file.keyframes.len(), 2);
+        assert_eq!(file.keyframes[1].step, 8);
+        let final_state = file.verify::<CounterDomain>().unwrap();
+        assert_eq!(file.state_at_step::<CounterDomain>(10), final_state);
+        assert_eq!(file.state_at_step::<CounterDomain>(0), 3);
+        assert_eq!(
+            file.state_at_step::<CounterDomain>(9),
+            ReplayFile {
+                keyframes: Vec::new(),
+                ..file.clone()
+            }
+            .state_at_step::<CounterDomain>(9)
+        );
+
+        let json = file.to_json_string();
+        assert!(json.contains("\"format\": \"npc-engine-replay\""));
+        let read = ReplayFile::<u32, u32>::from_bytes(json.as_bytes()).unwrap();
+        assert_eq!(read.metadata["game"], "test");
+        assert_eq!(read.keyframes, file.keyframes);
+        assert!(read.check_domain("counter").is_ok());
+        assert!(read.check_domain("forest").is_err());
+        #[cfg(feature = "bincode")]
+        {
+            let read = ReplayFile::<u32, u32>::from_bytes(&file.to_binary()).unwrap();
+            assert_eq!(read.verify::<CounterDomain>().unwrap(), final_state);
+        }
+    }
+
+    #[test]
+    fn invalid_replay_files_are_rejected() {
+        let mut file = ReplayFile::new("counter", record_counter_replay(1));
+        file.version = REPLAY_FORMAT_VERSION + 1;
+        let json = serde_json::to_string(&file).unwrap();
+        assert_eq!(
+            ReplayFile::<u32, u32>::from_json_str(&json).unwrap_err(),
+            ReplayFormatError::UnsupportedVersion(REPLAY_FORMAT_VERSION + 1)
+        );
+        assert_eq!(
+            ReplayFile::<u32, u32>::from_json_str("{\"steps\": []}").unwrap_err(),
+            ReplayFormatError::NotAReplay
+        );
+        assert!(ReplayFile::<u32, u32>::from_bytes(b"NPCR\x02\0\0\0").is_err());
+    }
+}
@@ -0,0 +1,338 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{
+    any::Any,
+    fmt, fs, io,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+};
+
+use npc_engine_core::{
+    ActiveTaskSnapshot, AgentId, DefaultPolicyEstimator, Domain, MCTSConfiguration, SnapshotDomain,
+    Task, MCTS,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// An anomaly that happened during a planning.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanningAnomaly {
+    /// A domain hook panicked, with the panic message
+    Panic(String),
+    /// The search produced a NaN q-value for an agent at the root
+    NanValue(AgentId),
+}
+impl fmt::Display for PlanningAnomaly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlanningAnomaly::Panic(message) => write!(f, "planning panicked: {message}"),
+            PlanningAnomaly::NanValue(agent) => {
+                write!(f, "planning produced a NaN value for {agent}")
+            }
+        }
+    }
+}
+impl std::error::Error for PlanningAnomaly {}
+
+/// An anomaly caught by [run_capturing_repro], with the path of the written [PlanningRepro], if it could be written.
+#[derive(Debug)]
+pub struct CapturedAnomaly {
+    pub anomaly: PlanningAnomaly,
+    pub repro_path: Option<PathBuf>,
+}
+impl fmt::Display for CapturedAnomaly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.repro_path {
+            Some(path) => write!(f, "{}, repro written to {}", self.anomaly, path.display()),
+            None => write!(f, "{}, no repro written", self.anomaly),
+        }
+    }
+}
+impl std::error::Error for CapturedAnomaly {}
+
+/// Everything needed to re-run a planning deterministically: the seed, the configuration,
+/// the initial state and the active tasks at the root.
+///
+/// Repros are written by [run_capturing_repro] when a planning misbehaves,
+/// and can be loaded in a unit test and re-played with [replay](Self::replay).
+/// An eventual custom state value estimator is not part of the repro, as it cannot be serialized,
+/// replays use the [DefaultPolicyEstimator].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlanningRepro<S, T> {
+    /// The anomaly that happened, if any
+    pub anomaly: Option<PlanningAnomaly>,
+    /// The agent for which the search was done
+    pub agent: AgentId,
+    /// The tick at which the search started
+    pub start_tick: u64,
+    /// The configuration of the search, with its seed set
+    pub config: MCTSConfiguration,
+    /// The state at the beginning of the search
+    pub initial_state: S,
+    /// The active tasks of all agents at the root
+    pub tasks: Vec<ActiveTaskSnapshot<T>>,
+}
+
+impl<S, T> PlanningRepro<S, T> {
+    /// Captures a search, before it is run.
+    pub fn capture<D>(mcts: &MCTS<D>) -> Self
+    where
+        D: SnapshotDomain<State = S, TaskSnapshot = T>,
+        S: Clone,
+    {
+        let config = MCTSConfiguration {
+            seed: Some(mcts.seed()),
+            ..mcts.config().clone()
+        };
+        Self {
+            anomaly: None,
+            agent: mcts.agent(),
+            start_tick: mcts.start_tick(),
+            config,
+            initial_state: mcts.initial_state().clone(),
+            tasks: mcts
+                .root_node()
+                .active_tasks()
+                .iter()
+                .map(ActiveTaskSnapshot::new)
+                .collect(),
+        }
+    }
+
+    /// Re-creates the search, ready to run.
+    pub fn to_mcts<D>(&self) -> MCTS<D>
+    where
+        D: SnapshotDomain<State = S, TaskSnapshot = T>,
+        S: Clone,
+        T: Clone,
+    {
+        MCTS::new_with_tasks(
+            self.initial_state.clone(),
+            self.agent,
+            self.start_tick,
+            self.tasks
+                .iter()
+                .map(|task| task.clone().restore())
+                .collect(),
+            self.config.clone(),
+            Box::new(DefaultPolicyEstimator {}),
+            None,
+        )
+    }
+
+    /// Re-runs the search, returning its best task or the anomaly that happened.
+    pub fn replay<D>(&self) -> Result<Option<Box<dyn Task<D>>>, PlanningAnomaly>
+    where
+        D: SnapshotDomain<State = S, TaskSnapshot = T>,
+        S: Clone,
+        T: Clone,
+    {
+        run_checked(&mut self.to_mcts())
+    }
+}
+
+impl<S, T> PlanningRepro<S, T>
+where
+    S: Serialize + DeserializeOwned,
+    T: Serialize + DeserializeOwned,
+{
+    /// Writes the repro as JSON.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        fs::write(path, json)
+    }
+
+    /// Reads a repro written by [save](Self::save).
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let json = fs::read_to_string(path)?;
+        serde_json::from_str(&json)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+/// Runs a search, turning panics and NaN q-values at the root into anomalies.
+fn run_checked<D: Domain>(mcts: &mut MCTS<D>) -> Result<Option<Box<dyn Task<D>>>, PlanningAnomaly> {
+    let task = panic::catch_unwind(AssertUnwindSafe(|| mcts.run()))
+        .map_err(|payload| PlanningAnomaly::Panic(panic_message(payload)))?;
+    let root = mcts.root_node();
+    if let Some(edges) = mcts.get_edges(&root) {
+        for (_, edge) in edges.expanded_tasks() {
+            let edge = edge.lock().unwrap();
+            for agent in root.agents() {
+                if edge.q_value(agent).is_nan() {
+                    return Err(PlanningAnomaly::NanValue(agent));
+                }
+            }
+        }
+    }
+    Ok(task)
+}
+
+/// Runs a search and, if it panics or produces a NaN value, writes a [PlanningRepro] into `repro_dir`.
+///
+/// The repro is named after the tick, the agent and the seed of the search.
+pub fn run_capturing_repro<D>(
+    mcts: &mut MCTS<D>,
+    repro_dir: impl AsRef<Path>,
+) -> Result<Option<Box<dyn Task<D>>>, CapturedAnomaly>
+where
+    D: SnapshotDomain,
+    D::State: Clone + Serialize + DeserializeOwned,
+    D::TaskSnapshot: Serialize + DeserializeOwned,
+{
+    let mut repro = PlanningRepro::capture(mcts);
+    run_checked(mcts).map_err(|anomaly| {
+        let repro_dir = repro_dir.as_ref();
+        let path = repro_dir.join(format!(
+            "repro-T{}-{}-{:016x}.json",
+            repro.start_tick,
+            repro.agent,
+            mcts.seed()
+        ));
+        repro.anomaly = Some(anomaly.clone());
+        let repro_path = match fs::create_dir_all(repro_dir).and_then(|_| repro.save(&path)) {
+            Ok(()) => {
+                log::error!("{anomaly}, repro written to {}", path.display());
+                Some(path)
+            }
+            Err(error) => {
+                log::error!("{anomaly}, cannot write repro: {error}");
+                None
+            }
+        };
+        CapturedAnomaly {
+            anomaly,
+            repro_path,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use npc_engine_core::{
+        impl_task_boxed_methods, AgentValue, Behavior, Context, ContextMut, StateDiffRef,
+        TaskDuration,
+    };
+    use std::collections::BTreeSet;
+
+    /// A domain whose task panics when the counter reaches 13.
+    struct FaultyDomain;
+    impl Domain for FaultyDomain {
+        type State = u32;
+        type Diff = Option<u32>;
+        type DisplayAction = ();
+
+        fn list_behaviors() -> &'static [&'static dyn Behavior<Self>] {
+            &[&FaultyBehavior]
+        }
+
+        fn get_current_value(
+            _tick: u64,
+            state_diff: StateDiffRef<Self>,
+            _agent: AgentId,
+        ) -> AgentValue {
+            let state = state_diff.diff.unwrap_or(*state_diff.initial_state);
+            AgentValue::new(state as f32).unwrap()
+        }
+
+        fn update_visible_agents(
+            _start_tick: u64,
+            ctx: Context<Self>,
+            agents: &mut BTreeSet<AgentId>,
+        ) {
+            agents.insert(ctx.agent);
+        }
+    }
+    impl SnapshotDomain for FaultyDomain {
+        type TaskSnapshot = ();
+        fn snapshot_task(_task: &dyn Task<Self>) -> Self::TaskSnapshot {}
+        fn restore_task(_snapshot: Self::TaskSnapshot) -> Box<dyn Task<Self>> {
+            Box::new(Increment)
+        }
+    }
+
+    #[derive(Copy, Clone, Debug)]
+    struct FaultyBehavior;
+    impl Behavior<FaultyDomain> for FaultyBehavior {
+        fn add_own_tasks(
+            &self,
+            _ctx: Context<FaultyDomain>,
+            tasks: &mut Vec<Box<dyn Task<FaultyDomain>>>,
+        ) {
+            tasks.push(Box::new(Increment));
+        }
+
+        fn is_valid(&self, _ctx: Context<FaultyDomain>) -> bool {
+            true
+        }
+    }
+
+    #[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+    struct Increment;
+    impl Task<FaultyDomain> for Increment {
+        fn duration(&self, _ctx: Context<FaultyDomain>) -> TaskDuration {
+            1
+        }
+        fn execute(&self, ctx: ContextMut<FaultyDomain>) -> Option<Box<dyn Task<FaultyDomain>>> {
+            let state = ctx.state_diff.diff.unwrap_or(*ctx.state_diff.initial_state) + 1;
+            assert_ne!(state, 13, "unlucky counter");
+            *ctx.state_diff.diff = Some(state);
+            None
+        }
+        fn is_valid(&self, _ctx: Context<FaultyDomain>) -> bool {
+            true
+        }
+        fn display_action(&self) {}
+        impl_task_boxed_methods!(FaultyDomain);
+    }
+
+    #[test]
+    fn anomalies_are_captured_and_replayed() {
+        let config = MCTSConfiguration {
+            seed: None,
+            ..crate::replay::tests::counter_mcts_config()
+        };
+        let repro_dir = std::env::temp_dir().join("npc_engine_repro_test");
+
+        // A healthy search does not write a repro
+        let mut mcts = MCTS::<FaultyDomain>::new(0, AgentId(0), config.clone());
+        assert!(run_capturing_repro(&mut mcts, &repro_dir)
+            .unwrap()
+            .is_some());
+
+        // A panicking search does, with the seed that was randomly chosen
+        let mut mcts = MCTS::<FaultyDomain>::new(10, AgentId(0), config);
+        let captured = run_capturing_repro(&mut mcts, &repro_dir).unwrap_err();
+        assert!(
+            matches!(&captured.anomaly, PlanningAnomaly::Panic(message) if message.contains("unlucky"))
+        );
+        let repro_path = captured.repro_path.unwrap();
+        let repro = PlanningRepro::<u32, ()>::load(&repro_path).unwrap();
+        assert_eq!(repro.anomaly, Some(captured.anomaly.clone()));
+        assert_eq!(repro.config.seed, Some(mcts.seed()));
+        assert_eq!(repro.initial_state, 10);
+        assert_eq!(repro.tasks.len(), 1);
+
+        // Re-playing gives the same anomaly
+        assert_eq!(
+            repro.replay::<FaultyDomain>().unwrap_err(),
+            captured.anomaly
+        );
+        fs::remove_dir_all(repro_dir).unwrap();
+    }
+}
@@ -0,0 +1,215 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use npc_engine_core::{Domain, MCTSConfiguration, Task, MCTS};
+use rayon::prelude::*;
+
+/// The statistics of a root task after a root-parallel search, see [run_root_parallel].
+#[derive(Clone, Debug)]
+pub struct RootTaskStatistics<D: Domain> {
+    /// The task
+    pub task: Box<dyn Task<D>>,
+    /// The subtree that explored the task
+    pub subtree: usize,
+    /// The number of visits of the task
+    pub visits: usize,
+    /// The q-value of the task for the agent of the search
+    pub value: f32,
+}
+
+/// The merged result of a root-parallel search, see [run_root_parallel].
+#[derive(Clone, Debug)]
+pub struct RootParallelResult<D: Domain> {
+    /// The task with the highest value, None if no task was expanded
+    pub best_task: Option<Box<dyn Task<D>>>,
+    /// The statistics of all expanded root tasks, the best first
+    pub tasks: Vec<RootTaskStatistics<D>>,
+}
+
+impl<D: Domain> RootParallelResult<D> {
+    /// Returns the total number of visits over all subtrees.
+    pub fn visits(&self) -> usize {
+        self.tasks.iter().map(|task| task.visits).sum()
+    }
+}
+
+/// Searches the children of the root in parallel on `pool`, using up to `subtrees` independent trees.
+///
+/// The root tasks are distributed round-robin among the subtrees, and each subtree is searched by
+/// its own MCTS on its own thread, with a share of the visits of `config`, so that planning scales
+/// with cores without locking a shared tree.
+/// `create_mcts` creates an MCTS with the given configuration, whose seed is offset by the index of the subtree.
+/// The statistics of the root tasks are merged at the end, and the best task is the one with the highest value.
+pub fn run_root_parallel<D, F>(
+    config: &MCTSConfiguration,
+    subtrees: usize,
+    pool: &rayon::ThreadPool,
+    create_mcts: F,
+) -> RootParallelResult<D>
+where
+    D: Domain,
+    F: Fn(MCTSConfiguration) -> MCTS<D> + Sync,
+{
+    run_subtrees(config, subtrees, pool, create_mcts, |subtree| {
+        config.seed.map(|seed| seed.wrapping_add(subtree as u64))
+    })
+}
+
+/// Searches the children of the root in parallel as [run_root_parallel], with results bit-identical across runs with the same `seed`.
+///
+/// The partitioning of the root tasks only depends on `subtrees`, not on the number of threads of `pool`,
+/// each subtree is searched with its own seed derived from `seed` and its index, and ties between root tasks
+/// are broken by subtree and by the order of the tasks in the domain, so that lockstep simulations can plan in parallel.
+/// The domain must itself be deterministic, for example not iterate over randomly-seeded hash maps.
+pub fn run_root_parallel_deterministic<D, F>(
+    config: &MCTSConfiguration,
+    seed: u64,
+    subtrees: usize,
+    pool: &rayon::ThreadPool,
+    create_mcts: F,
+) -> RootParallelResult<D>
+where
+    D: Domain,
+    F: Fn(MCTSConfiguration) -> MCTS<D> + Sync,
+{
+    let mut config = config.clone();
+    config.seed = Some(seed);
+    run_subtrees(&config, subtrees, pool, create_mcts, |subtree| {
+        Some(subtree_seed(seed, subtree))
+    })
+}
+
+/// Derives the seed of a subtree with the SplitMix64 finalizer, so that the seeds of subtrees are uncorrelated.
+fn subtree_seed(seed: u64, subtree: usize) -> u64 {
+    let mut z = seed.wrapping_add((subtree as u64 + 1).wrapping_mul(0x9e3779b97f4a7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+fn run_subtrees<D, F>(
+    config: &MCTSConfiguration,
+    subtrees: usize,
+    pool: &rayon::ThreadPool,
+    create_mcts: F,
+    seed_of: impl Fn(usize) -> Option<u64> + Sync,
+) -> RootParallelResult<D>
+where
+    D: Domain,
+    F: Fn(MCTSConfiguration) -> MCTS<D> + Sync,
+{
+    let task_count = create_mcts(config.clone()).root_tasks().len();
+    let subtrees = subtrees.min(task_count).max(1);
+    let mut tasks: Vec<_> = pool.install(|| {
+        (0..subtrees)
+            .into_par_iter()
+            .flat_map_iter(|subtree| {
+                let mut config = config.clone();
+                config.visits = (config.visits / subtrees as u32).max(1);
+                config.seed = seed_of(subtree);
+                let mut mcts = create_mcts(config);
+                let mut kept = Vec::new();
+                mcts.retain_root_tasks(|index, task| {
+                    let keep = index % subtrees == subtree;
+                    if keep {
+                        kept.push(task.box_clone());
+                    }
+                    keep
+                });
+                mcts.run();
+                let mut statistics = root_statistics(&mcts, subtree);
+                // Order by the tasks of the domain rather than by the iteration order of the tree
+                statistics.sort_by_key(|statistics| {
+                    kept.iter().position(|task| task == &statistics.task)
+                });
+                statistics
+            })
+            .collect()
+    });
+    // Collecting preserves the order of subtrees, and sorting is stable
+    tasks.sort_by(|a, b| b.value.total_cmp(&a.value));
+    RootParallelResult {
+        best_task: tasks.first().map(|task| task.task.clone()),
+        tasks,
+    }
+}
+
+fn root_statistics<D: Domain>(mcts: &MCTS<D>, subtree: usize) -> Vec<RootTaskStatistics<D>> {
+    let agent = mcts.agent();
+    let edges = match mcts.get_edges(&mcts.root_node()) {
+        Some(edges) => edges,
+        None => return Vec::new(),
+    };
+    edges
+        .expanded_tasks()
+        .map(|(task, edge)| {
+            let edge = edge.lock().unwrap();
+            RootTaskStatistics {
+                task: task.clone(),
+                subtree,
+                visits: edge.visits(),
+                value: edge.q_value(agent),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use npc_engine_core::{AgentId, MCTS};
+
+    use super::{run_root_parallel, run_root_parallel_deterministic};
+    use crate::replay::tests::{counter_mcts_config, Add, CounterDomain};
+
+    #[test]
+    fn root_parallel_search_merges_subtrees() {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .build()
+            .unwrap();
+        let config = counter_mcts_config();
+        let result = run_root_parallel(&config, 4, &pool, |config| {
+            MCTS::<CounterDomain>::new(0, AgentId(0), config)
+        });
+        // Two root tasks, so two subtrees, each exploring a single task
+        assert_eq!(result.tasks.len(), 2);
+        assert_ne!(result.tasks[0].subtree, result.tasks[1].subtree);
+        assert_eq!(result.visits(), config.visits as usize);
+        let best = result.best_task.unwrap();
+        assert_eq!(best.downcast_ref::<Add>().unwrap().0, 2);
+    }
+
+    #[test]
+    fn deterministic_root_parallel_search_does_not_depend_on_threads() {
+        let mut config = counter_mcts_config();
+        config.seed = None;
+        let run = |threads| {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            let result = run_root_parallel_deterministic(&config, 42, 2, &pool, |config| {
+                MCTS::<CounterDomain>::new(0, AgentId(0), config)
+            });
+            result
+                .tasks
+                .iter()
+                .map(|task| {
+                    (
+                        task.task.downcast_ref::<Add>().unwrap().0,
+                        task.subtree,
+                        task.visits,
+                        task.value.to_bits(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        let reference = run(1);
+        assert_eq!(reference.len(), 2);
+        for threads in [1, 2, 4] {
+            assert_eq!(run(threads), reference);
+        }
+    }
+}
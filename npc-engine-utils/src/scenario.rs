@@ -0,0 +1,274 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt, fs,
+    path::Path,
+};
+
+use npc_engine_core::AgentId;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::Coord2D;
+
+/// An agent of a [Scenario], with domain-specific data `A`, for example its position and inventory.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ScenarioAgent<A> {
+    pub id: AgentId,
+    pub data: A,
+}
+
+/// A map made of rows of characters, one per tile, that scenarios can use as their map data.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GridMap {
+    pub rows: Vec<String>,
+}
+impl GridMap {
+    /// Returns the width of the widest row.
+    pub fn width(&self) -> usize {
+        self.rows
+            .iter()
+            .map(|row| row.chars().count())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Returns the number of rows.
+    pub fn height(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Returns the tile at `coord`, with y going down the rows, or None if outside of the map.
+    pub fn tile(&self, coord: Coord2D) -> Option<char> {
+        let x = usize::try_from(coord.x).ok()?;
+        let y = usize::try_from(coord.y).ok()?;
+        self.rows.get(y)?.chars().nth(x)
+    }
+
+    /// Returns the coordinates of all tiles equal to `tile`, row by row.
+    pub fn find(&self, tile: char) -> impl Iterator<Item = Coord2D> + '_ {
+        self.rows.iter().enumerate().flat_map(move |(y, row)| {
+            row.chars()
+                .enumerate()
+                .filter(move |(_, c)| *c == tile)
+                .map(move |(x, _)| Coord2D::new(x as i32, y as i32))
+        })
+    }
+}
+
+/// A description of the initial setup of a simulation, loaded from JSON or, with feature `ron`, RON.
+///
+/// Scenario crates choose the data of their agents `A` and of their map `M`, for example [GridMap].
+/// The value weights let scenarios tune their [get_current_value](npc_engine_core::Domain::get_current_value) without recompiling.
+///
+/// For example, in RON:
+/// ```ron
+/// (
+///     name: "duel",
+///     agents: [
+///         (id: (0), data: (x: 1, y: 1)),
+///         (id: (1), data: (x: 3, y: 1)),
+///     ],
+///     map: (rows: ["XXXXX", "X...X", "XXXXX"]),
+///     value_weights: { "wood": 1.0, "water": 0.5 },
+/// )
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario<A, M> {
+    /// the name of the scenario
+    #[serde(default)]
+    pub name: String,
+    /// the initial agents
+    pub agents: Vec<ScenarioAgent<A>>,
+    /// the map data
+    pub map: M,
+    /// named weights of the value function
+    #[serde(default)]
+    pub value_weights: BTreeMap<String, f32>,
+}
+
+/// An error when loading a [Scenario].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ScenarioError {
+    /// The file could not be read
+    Io(String),
+    /// The file extension is neither `json` nor `ron`
+    UnknownFormat(String),
+    /// The file could not be parsed
+    Parse(String),
+    /// Several agents have the same id
+    DuplicateAgent(AgentId),
+    /// A value weight is not finite
+    InvalidWeight(String),
+}
+impl fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScenarioError::Io(error) => write!(f, "cannot read scenario: {error}"),
+            ScenarioError::UnknownFormat(path) => {
+                write!(f, "unknown format of scenario file {path}")
+            }
+            ScenarioError::Parse(error) => write!(f, "cannot parse scenario: {error}"),
+            ScenarioError::DuplicateAgent(agent) => write!(f, "agent {agent} is defined twice"),
+            ScenarioError::InvalidWeight(name) => {
+                write!(f, "value weight {name} is not finite")
+            }
+        }
+    }
+}
+impl std::error::Error for ScenarioError {}
+
+impl<A, M> Scenario<A, M>
+where
+    A: DeserializeOwned,
+    M: DeserializeOwned,
+{
+    /// Parses and validates a scenario from JSON.
+    pub fn from_json_str(json: &str) -> Result<Self, ScenarioError> {
+        let scenario: Self =
+            serde_json::from_str(json).map_err(|error| ScenarioError::Parse(error.to_string()))?;
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    /// Parses and validates a scenario from RON.
+    #[cfg(feature = "ron")]
+    pub fn from_ron_str(ron: &str) -> Result<Self, ScenarioError> {
+        let scenario: Self =
+            ron::from_str(ron).map_err(|error| ScenarioError::Parse(error.to_string()))?;
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    /// Loads and validates a scenario from a file, whose format is given by its extension (`json`, or `ron` with feature `ron`).
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ScenarioError> {
+        let path = path.as_ref();
+        let read =
+            || fs::read_to_string(path).map_err(|error| ScenarioError::Io(error.to_string()));
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => Self::from_json_str(&read()?),
+            #[cfg(feature = "ron")]
+            Some("ron") => Self::from_ron_str(&read()?),
+            _ => Err(ScenarioError::UnknownFormat(path.display().to_string())),
+        }
+    }
+}
+
+impl<A, M> Scenario<A, M> {
+    /// Checks that agent ids are unique and value weights are finite.
+    pub fn validate(&self) -> Result<(), ScenarioError> {
+        let mut ids = BTreeSet::new();
+        for agent in &self.agents {
+            if !ids.insert(agent.id) {
+                return Err(ScenarioError::DuplicateAgent(agent.id));
+            }
+        }
+        for (name, weight) in &self.value_weights {
+            if !weight.is_finite() {
+                return Err(ScenarioError::InvalidWeight(name.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the ids of all agents.
+    pub fn agent_ids(&self) -> impl Iterator<Item = AgentId> + '_ {
+        self.agents.iter().map(|agent| agent.id)
+    }
+
+    /// Returns the data of an agent, if it exists.
+    pub fn agent(&self, id: AgentId) -> Option<&A> {
+        self.agents
+            .iter()
+            .find(|agent| agent.id == id)
+            .map(|agent| &agent.data)
+    }
+
+    /// Returns the value weight of the given name, or `default` if the scenario does not set it.
+    pub fn value_weight(&self, name: &str, default: f32) -> f32 {
+        self.value_weights.get(name).copied().unwrap_or(default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Position {
+        x: i32,
+        y: i32,
+    }
+
+    #[test]
+    fn scenario_loads_from_json() {
+        let scenario = Scenario::<Position, GridMap>::from_json_str(
+            r#"{
+                "name": "duel",
+                "agents": [
+                    { "id": 0, "data": { "x": 1, "y": 1 } },
+                    { "id": 1, "data": { "x": 3, "y": 1 } }
+                ],
+                "map": { "rows": ["XXXXX", "X.T.X", "XXXXX"] },
+                "value_weights": { "wood": 2.0 }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(scenario.name, "duel");
+        assert_eq!(
+            scenario.agent_ids().collect::<Vec<_>>(),
+            [AgentId(0), AgentId(1)]
+        );
+        assert_eq!(scenario.agent(AgentId(1)), Some(&Position { x: 3, y: 1 }));
+        assert_eq!(scenario.agent(AgentId(2)), None);
+        assert_eq!(scenario.value_weight("wood", 1.), 2.);
+        assert_eq!(scenario.value_weight("water", 1.), 1.);
+        assert_eq!((scenario.map.width(), scenario.map.height()), (5, 3));
+        assert_eq!(scenario.map.tile(Coord2D::new(2, 1)), Some('T'));
+        assert_eq!(scenario.map.tile(Coord2D::new(-1, 1)), None);
+        assert_eq!(
+            scenario.map.find('T').collect::<Vec<_>>(),
+            [Coord2D::new(2, 1)]
+        );
+
+        let error = Scenario::<Position, GridMap>::from_json_str(
+            r#"{
+                "agents": [
+                    { "id": 0, "data": { "x": 1, "y": 1 } },
+                    { "id": 0, "data": { "x": 3, "y": 1 } }
+                ],
+                "map": { "rows": [] }
+            }"#,
+        )
+        .unwrap_err();
+        assert_eq!(error, ScenarioError::DuplicateAgent(AgentId(0)));
+    }
+
+    #[cfg(feature = "ron")]
+    #[test]
+    fn scenario_loads_from_ron() {
+        let scenario = Scenario::<Position, GridMap>::from_ron_str(
+            r#"(
+                name: "duel",
+                agents: [
+                    (id: (0), data: (x: 1, y: 1)),
+                    (id: (1), data: (x: 3, y: 1)),
+                ],
+                map: (rows: ["XXXXX", "X...X", "XXXXX"]),
+                value_weights: { "wood": 1.0, "water": 0.5 },
+            )"#,
+        )
+        .unwrap();
+        assert_eq!(scenario.agents.len(), 2);
+        assert_eq!(scenario.agent(AgentId(0)), Some(&Position { x: 1, y: 1 }));
+        assert_eq!(scenario.value_weight("water", 1.), 0.5);
+        assert!(matches!(
+            Scenario::<Position, GridMap>::from_ron_str("(agents: [], map: (rows: []), size: 3)"),
+            Err(ScenarioError::Parse(_))
+        ));
+    }
+}
@@ -0,0 +1,63 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use npc_engine_core::AgentId;
+
+use crate::GlobalDomain;
+
+/// An ongoing planning waiting for computation in the [IncrementalExecutor](crate::IncrementalExecutor).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PlanningRequest {
+    /// The agent that plans
+    pub agent: AgentId,
+    /// The tick at which planning started
+    pub start_tick: u64,
+    /// The tick at which the planning task ends, and the plan is committed at the latest
+    pub end_tick: u64,
+    /// The number of visits done so far
+    pub visits_done: u32,
+}
+
+/// A policy deciding which agents get planned first when the computation per step is limited.
+///
+/// See [IncrementalExecutor::set_step_budget](crate::IncrementalExecutor::set_step_budget).
+pub trait SchedulingPolicy<D: GlobalDomain> {
+    /// Returns the priority of a planning at a given tick, the ones with the highest priority are planned first.
+    fn priority(&self, tick: u64, state: &D::GlobalState, request: &PlanningRequest) -> f32;
+}
+
+/// Plans first the agents whose planning task ends the soonest, this is the default policy.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct UrgentFirst;
+impl<D: GlobalDomain> SchedulingPolicy<D> for UrgentFirst {
+    fn priority(&self, tick: u64, _state: &D::GlobalState, request: &PlanningRequest) -> f32 {
+        -(request.end_tick.saturating_sub(tick) as f32)
+    }
+}
+
+/// Plans first the agents which have been waiting for a plan for the longest time.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LongestWaitingFirst;
+impl<D: GlobalDomain> SchedulingPolicy<D> for LongestWaitingFirst {
+    fn priority(&self, tick: u64, _state: &D::GlobalState, request: &PlanningRequest) -> f32 {
+        tick.saturating_sub(request.start_tick) as f32
+    }
+}
+
+/// Plans first the most important agents, as given by the domain, for example the closest to the player.
+#[derive(Clone, Copy, Debug)]
+pub struct ImportantFirst<F>(
+    /// Returns the importance of an agent in a given state
+    pub F,
+);
+impl<D, F> SchedulingPolicy<D> for ImportantFirst<F>
+where
+    D: GlobalDomain,
+    F: Fn(AgentId, &D::GlobalState) -> f32,
+{
+    fn priority(&self, _tick: u64, state: &D::GlobalState, request: &PlanningRequest) -> f32 {
+        (self.0)(request.agent, state)
+    }
+}
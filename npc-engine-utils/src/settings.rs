@@ -0,0 +1,288 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{fmt, fs, path::Path, time::Duration};
+
+use npc_engine_core::{ConfigError, DomainWithPlanningTask, MCTSConfiguration, PlanningBudget};
+use serde::{Deserialize, Serialize};
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::ThreadedExecutor;
+use crate::{ExecutorState, ExecutorStateGlobal, GlobalDomain, IncrementalExecutor};
+
+/// A planning budget as written in a settings file, see [PlanningBudget].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetSettings {
+    /// at most this number of visits
+    Visits(u32),
+    /// as many visits as fit in this number of milliseconds
+    TimeMs(u64),
+}
+impl From<BudgetSettings> for PlanningBudget {
+    fn from(budget: BudgetSettings) -> Self {
+        match budget {
+            BudgetSettings::Visits(visits) => PlanningBudget::Visits(visits),
+            BudgetSettings::TimeMs(ms) => PlanningBudget::Time(Duration::from_millis(ms)),
+        }
+    }
+}
+
+/// The settings of an executor that can be loaded from a file, all optional.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExecutorSettings {
+    /// the budget of each slice of planning of an [IncrementalExecutor]
+    pub budget: Option<BudgetSettings>,
+    /// the budget of each step of an [IncrementalExecutor], see [IncrementalExecutor::set_step_budget]
+    pub step_budget: Option<BudgetSettings>,
+    /// see [ThreadedExecutor::set_intention_sharing]
+    pub intention_sharing: bool,
+    /// see [ThreadedExecutor::set_coordination]
+    pub coordination: bool,
+    /// see [ThreadedExecutor::set_task_monitoring]
+    pub task_monitoring: bool,
+}
+impl ExecutorSettings {
+    /// Applies these settings to a [ThreadedExecutor], budgets being ignored.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn apply_to_threaded<D, S>(&self, executor: &mut ThreadedExecutor<D, S>)
+    where
+        D: DomainWithPlanningTask + GlobalDomain,
+        D::State: Clone + Send,
+        D::Diff: Send + Sync,
+        S: ExecutorState<D> + ExecutorStateGlobal<D>,
+    {
+        executor.set_intention_sharing(self.intention_sharing);
+        executor.set_coordination(self.coordination);
+        executor.set_task_monitoring(self.task_monitoring);
+    }
+
+    /// Applies these settings to an [IncrementalExecutor], keeping its budget if none is given.
+    pub fn apply_to_incremental<D, S>(&self, executor: &mut IncrementalExecutor<D, S>)
+    where
+        D: DomainWithPlanningTask + GlobalDomain,
+        D::State: Clone,
+        S: ExecutorState<D> + ExecutorStateGlobal<D>,
+    {
+        if let Some(budget) = self.budget {
+            executor.set_budget(budget.into());
+        }
+        executor.set_step_budget(self.step_budget.map(Into::into));
+        executor.set_intention_sharing(self.intention_sharing);
+        executor.set_coordination(self.coordination);
+        executor.set_task_monitoring(self.task_monitoring);
+    }
+}
+
+/// The settings of planning, an [MCTSConfiguration] and [ExecutorSettings], that can be loaded from JSON or TOML.
+///
+/// For example, in TOML:
+/// ```toml
+/// [mcts]
+/// allow_invalid_tasks = false
+/// visits = 1000
+/// depth = 10
+/// exploration = 1.414
+/// discount_hl = 17
+///
+/// [executor]
+/// budget = { time_ms = 2 }
+/// coordination = true
+/// ```
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PlannerSettings {
+    /// the configuration of the planners
+    pub mcts: MCTSConfiguration,
+    /// the settings of the executor, all defaulted if missing
+    #[serde(default)]
+    pub executor: ExecutorSettings,
+}
+
+/// An error when loading [PlannerSettings].
+#[derive(Clone, Debug, PartialEq)]
+pub enum SettingsError {
+    /// The file could not be read
+    Io(String),
+    /// The file extension is neither `json` nor `toml`
+    UnknownFormat(String),
+    /// The file could not be parsed
+    Parse(String),
+    /// The MCTS configuration is invalid
+    InvalidConfiguration(ConfigError),
+    /// A setting of the executor has an invalid value
+    Invalid {
+        field: &'static str,
+        reason: &'static str,
+    },
+}
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::Io(error) => write!(f, "cannot read settings: {error}"),
+            SettingsError::UnknownFormat(path) => {
+                write!(f, "unknown format of settings file {path}")
+            }
+            SettingsError::Parse(error) => write!(f, "cannot parse settings: {error}"),
+            SettingsError::InvalidConfiguration(error) => {
+                write!(f, "invalid MCTS configuration: {error}")
+            }
+            SettingsError::Invalid { field, reason } => {
+                write!(f, "invalid setting {field}: {reason}")
+            }
+        }
+    }
+}
+impl std::error::Error for SettingsError {}
+
+fn check(valid: bool, field: &'static str, reason: &'static str) -> Result<(), SettingsError> {
+    if valid {
+        Ok(())
+    } else {
+        Err(SettingsError::Invalid { field, reason })
+    }
+}
+
+fn check_budget(budget: Option<BudgetSettings>, field: &'static str) -> Result<(), SettingsError> {
+    let empty = matches!(
+        budget,
+        Some(BudgetSettings::Visits(0) | BudgetSettings::TimeMs(0))
+    );
+    check(!empty, field, "must not be zero")
+}
+
+impl PlannerSettings {
+    /// Parses and validates settings from JSON.
+    pub fn from_json_str(json: &str) -> Result<Self, SettingsError> {
+        let settings: Self =
+            serde_json::from_str(json).map_err(|error| SettingsError::Parse(error.to_string()))?;
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    /// Parses and validates settings from TOML.
+    #[cfg(feature = "toml")]
+    pub fn from_toml_str(toml: &str) -> Result<Self, SettingsError> {
+        let settings: Self =
+            toml::from_str(toml).map_err(|error| SettingsError::Parse(error.to_string()))?;
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    /// Loads and validates settings from a file, whose format is given by its extension (`json`, or `toml` with feature `toml`).
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SettingsError> {
+        let path = path.as_ref();
+        let read =
+            || fs::read_to_string(path).map_err(|error| SettingsError::Io(error.to_string()));
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => Self::from_json_str(&read()?),
+            #[cfg(feature = "toml")]
+            Some("toml") => Self::from_toml_str(&read()?),
+            _ => Err(SettingsError::UnknownFormat(path.display().to_string())),
+        }
+    }
+
+    /// Checks that the settings make sense, for example that planning does at least one visit.
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        self.mcts
+            .validate()
+            .map_err(SettingsError::InvalidConfiguration)?;
+        check_budget(self.executor.budget, "executor.budget")?;
+        check_budget(self.executor.step_budget, "executor.step_budget")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_load_from_json() {
+        let settings = PlannerSettings::from_json_str(
+            r#"{
+                "mcts": {
+                    "allow_invalid_tasks": false,
+                    "visits": 100,
+                    "depth": 10,
+                    "exploration": 1.414,
+                    "discount_hl": 17.0,
+                    "seed": 3
+                },
+                "executor": { "budget": { "visits": 10 }, "coordination": true }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(settings.mcts.visits, 100);
+        assert_eq!(settings.mcts.seed, Some(3));
+        assert_eq!(settings.mcts.planning_task_duration, None);
+        assert_eq!(settings.executor.budget, Some(BudgetSettings::Visits(10)));
+        assert!(settings.executor.coordination);
+        assert!(!settings.executor.intention_sharing);
+    }
+
+    #[test]
+    fn invalid_settings_are_rejected() {
+        let error = PlannerSettings::from_json_str(
+            r#"{ "mcts": { "allow_invalid_tasks": false, "visits": 0, "depth": 10, "exploration": 1.0, "discount_hl": 17.0 } }"#,
+        )
+        .unwrap_err();
+        assert_eq!(
+            error,
+            SettingsError::InvalidConfiguration(ConfigError::ZeroVisits)
+        );
+        let error = PlannerSettings::from_json_str(
+            r#"{ "mcts": { "allow_invalid_tasks": false, "visit": 10, "depth": 10, "exploration": 1.0, "discount_hl": 17.0 } }"#,
+        )
+        .unwrap_err();
+        assert!(matches!(error, SettingsError::Parse(_)));
+        assert!(matches!(
+            PlannerSettings::load("settings.yaml"),
+            Err(SettingsError::UnknownFormat(_))
+        ));
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn settings_load_from_toml() {
+        let settings = PlannerSettings::from_toml_str(
+            r#"
+            [mcts]
+            allow_invalid_tasks = true
+            visits = 1000
+            depth = 10
+            exploration = 1.414
+            discount_hl = 17
+
+            [executor]
+            step_budget = { time_ms = 2 }
+            "#,
+        )
+        .unwrap();
+        assert!(settings.mcts.allow_invalid_tasks);
+        assert_eq!(
+            settings.executor.step_budget.map(PlanningBudget::from),
+            Some(PlanningBudget::Time(Duration::from_millis(2)))
+        );
+        let error = PlannerSettings::from_toml_str(
+            r#"
+            [mcts]
+            allow_invalid_tasks = true
+            visits = 1000
+            depth = 10
+            exploration = 1.414
+            discount_hl = 17
+
+            [executor]
+            budget = { visits = 0 }
+            "#,
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid setting executor.budget: must not be zero"
+        );
+    }
+}
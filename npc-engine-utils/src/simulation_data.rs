@@ -0,0 +1,161 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{collections::BTreeMap, time::Duration};
+
+use npc_engine_core::{AgentId, Domain};
+
+use crate::PlanningMetrics;
+
+/// The metrics of one planning of an agent, a row of [SimulationData::agent_rows].
+#[derive(Clone, Debug, PartialEq)]
+pub struct AgentMetricsRow {
+    /// The label of the run
+    pub run: String,
+    /// The seed of the run, if any
+    pub seed: Option<u64>,
+    /// The tick at which the chosen task was committed
+    pub tick: u64,
+    /// The agent that planned
+    pub agent: AgentId,
+    /// The number of visits done
+    pub visits: u32,
+    /// The number of nodes of the search tree
+    pub node_count: usize,
+    /// The number of edges of the search tree
+    pub edge_count: usize,
+    /// The computation time spent planning
+    pub planning_time: Duration,
+    /// The estimated value of the root of the search tree for the agent
+    pub value: f32,
+    /// The committed task, in its debug format
+    pub task: String,
+}
+
+/// Named values summarizing the world at a tick, a row of [SimulationData::world_rows].
+#[derive(Clone, Debug, PartialEq)]
+pub struct WorldSummaryRow {
+    /// The label of the run
+    pub run: String,
+    /// The seed of the run, if any
+    pub seed: Option<u64>,
+    /// The tick of the summary
+    pub tick: u64,
+    /// The values, such as the number of remaining trees, by name
+    pub values: BTreeMap<String, f64>,
+}
+
+/// Rows of per-tick agent metrics and world summaries of one or several simulations, to be analyzed as tables.
+///
+/// Record plannings from [ExecutorState::on_planning_metrics](crate::ExecutorState::on_planning_metrics)
+/// and world summaries from [ExecutorStateGlobal::post_step_hook](crate::ExecutorStateGlobal::post_step_hook), and
+/// [extend](Self::extend) the data of the runs of a batch together.
+/// The `npc-engine-arrow` crate writes these rows as Arrow and Parquet files.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SimulationData {
+    /// The label of the run recorded next
+    pub run: String,
+    /// The seed of the run recorded next, if any
+    pub seed: Option<u64>,
+    /// One row per planning
+    pub agent_rows: Vec<AgentMetricsRow>,
+    /// One row per recorded world summary
+    pub world_rows: Vec<WorldSummaryRow>,
+}
+
+impl SimulationData {
+    /// Creates empty data for the run `run` with `seed`.
+    pub fn new(run: impl Into<String>, seed: Option<u64>) -> Self {
+        Self {
+            run: run.into(),
+            seed,
+            ..Default::default()
+        }
+    }
+
+    /// Records the metrics of a planning.
+    pub fn record_planning<D: Domain>(&mut self, metrics: &PlanningMetrics<D>) {
+        self.agent_rows.push(AgentMetricsRow {
+            run: self.run.clone(),
+            seed: self.seed,
+            tick: metrics.tick,
+            agent: metrics.agent,
+            visits: metrics.visits,
+            node_count: metrics.node_count,
+            edge_count: metrics.edge_count,
+            planning_time: metrics.time,
+            value: metrics.value,
+            task: format!("{:?}", metrics.chosen_task),
+        });
+    }
+
+    /// Records a summary of the world at `tick`.
+    pub fn record_world<'a>(
+        &mut self,
+        tick: u64,
+        values: impl IntoIterator<Item = (&'a str, f64)>,
+    ) {
+        self.world_rows.push(WorldSummaryRow {
+            run: self.run.clone(),
+            seed: self.seed,
+            tick,
+            values: values
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value))
+                .collect(),
+        });
+    }
+
+    /// Appends the rows of `other`, typically another run of the same batch.
+    pub fn extend(&mut self, other: SimulationData) {
+        self.agent_rows.extend(other.agent_rows);
+        self.world_rows.extend(other.world_rows);
+    }
+
+    /// Returns the names of the values of the world summaries, sorted.
+    pub fn world_columns(&self) -> Vec<&str> {
+        let mut columns: Vec<_> = self
+            .world_rows
+            .iter()
+            .flat_map(|row| row.values.keys().map(String::as_str))
+            .collect();
+        columns.sort_unstable();
+        columns.dedup();
+        columns
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use npc_engine_core::MCTS;
+
+    use super::*;
+    use crate::replay::tests::{counter_mcts_config, Add, CounterDomain};
+
+    #[test]
+    fn plannings_and_world_summaries_are_recorded() {
+        let mut mcts = MCTS::<CounterDomain>::new(0, AgentId(0), counter_mcts_config());
+        let task = mcts.run().unwrap();
+        let mut data = SimulationData::new("baseline", Some(1));
+        data.record_planning(&PlanningMetrics::new(0, &mcts, task));
+        data.record_world(0, [("counter", 0.)]);
+        let mut other = SimulationData::new("tuned", Some(2));
+        other.record_planning(&PlanningMetrics::new(3, &mcts, Box::new(Add(1))));
+        other.record_world(3, [("counter", 1.), ("agents", 1.)]);
+        data.extend(other);
+
+        assert_eq!(data.agent_rows.len(), 2);
+        let row = &data.agent_rows[0];
+        assert_eq!(
+            (row.run.as_str(), row.seed, row.tick),
+            ("baseline", Some(1), 0)
+        );
+        assert_eq!(row.visits, counter_mcts_config().visits);
+        assert_eq!(data.agent_rows[1].task, "Add(1)");
+        assert_eq!(data.world_rows[1].run, "tuned");
+        assert_eq!(data.world_rows[1].values["agents"], 1.);
+        assert_eq!(data.world_columns(), ["agents", "counter"]);
+    }
+}
@@ -0,0 +1,66 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::collections::HashMap;
+
+use npc_engine_core::{ActiveTask, ActiveTasks, AgentId};
+pub use npc_engine_core::{ActiveTaskSnapshot, SnapshotDomain, TaskSnapshot};
+use serde::{Deserialize, Serialize};
+
+/// The serializable state of an executor, allowing to save and restore a simulation.
+///
+/// It contains the world state, the tick, and all in-flight tasks,
+/// including the planning tasks of agents that are planning.
+/// The search trees of ongoing plannings are not stored: when restoring the executor,
+/// the plannings are started anew and their results are committed as scheduled.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExecutorSnapshot<S, T> {
+    /// The tick of the next step
+    pub tick: u64,
+    /// The state of the world
+    pub state: S,
+    /// The queue of active tasks
+    pub queue: Vec<ActiveTaskSnapshot<T>>,
+    /// The last tasks chosen by planning, for each agent
+    pub task_history: Vec<ActiveTaskSnapshot<T>>,
+}
+impl<S, T> ExecutorSnapshot<S, T> {
+    /// Creates a snapshot from the parts of an executor.
+    pub(crate) fn new<D: SnapshotDomain<TaskSnapshot = T>>(
+        tick: u64,
+        state: S,
+        queue: &ActiveTasks<D>,
+        task_history: &HashMap<AgentId, ActiveTask<D>>,
+    ) -> Self {
+        let mut task_history: Vec<_> = task_history.values().collect();
+        task_history.sort_by_key(|active_task| active_task.agent);
+        Self {
+            tick,
+            state,
+            queue: queue.iter().map(ActiveTaskSnapshot::new).collect(),
+            task_history: task_history
+                .into_iter()
+                .map(ActiveTaskSnapshot::new)
+                .collect(),
+        }
+    }
+
+    /// Re-creates the queue and task history of an executor.
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn restore<D: SnapshotDomain<TaskSnapshot = T>>(
+        self,
+    ) -> (u64, S, ActiveTasks<D>, HashMap<AgentId, ActiveTask<D>>) {
+        let queue = self.queue.into_iter().map(|task| task.restore()).collect();
+        let task_history = self
+            .task_history
+            .into_iter()
+            .map(|task| {
+                let active_task: ActiveTask<D> = task.restore();
+                (active_task.agent, active_task)
+            })
+            .collect();
+        (self.tick, self.state, queue, task_history)
+    }
+}
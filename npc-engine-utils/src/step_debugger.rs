@@ -0,0 +1,161 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::io::{self, BufRead, Write};
+
+use npc_engine_core::{StateDiffRef, Task};
+
+use crate::{
+    plot_tree_in_tmp, DebugStep, ExecutableDomain, ExecutorState, ExecutorStateLocal,
+    PendingDecision, SimpleExecutor,
+};
+
+const HELP: &str = "Commands:
+  <enter>, c   commit the chosen task
+  <number>     commit the task of the option with this number instead
+  o            show the options explored by the planner
+  s            show the current state
+  p            plot the search tree in the temporary directory
+  q            quit";
+
+fn write_decision<D: ExecutableDomain, W: Write>(
+    output: &mut W,
+    decision: &PendingDecision<D>,
+) -> io::Result<()> {
+    writeln!(
+        output,
+        "T{} {} chooses {:?} after {} visits",
+        decision.tick(),
+        decision.agent(),
+        decision.task(),
+        decision.mcts().visits_done()
+    )
+}
+
+fn write_options<D: ExecutableDomain, W: Write>(
+    output: &mut W,
+    decision: &PendingDecision<D>,
+) -> io::Result<()> {
+    for (index, (task, visits, q_value)) in decision.options().iter().enumerate() {
+        writeln!(
+            output,
+            "  {index}: {task:?} visits: {visits} q-value: {q_value:.3}"
+        )?;
+    }
+    Ok(())
+}
+
+/// Runs an interactive step debugger on an executor, advancing one agent decision at a time.
+///
+/// For each decision, commands are read line by line from `input` until the decision is committed,
+/// either as chosen by the planner or overridden with another explored task.
+/// Type `h` for the list of commands.
+/// Returns when there are no more tasks to execute, or when the command `q` is read or `input` is exhausted,
+/// in which case the current decision is committed as chosen.
+pub fn run_step_debugger<D, S, R, W>(
+    executor: &mut SimpleExecutor<D, S>,
+    input: R,
+    mut output: W,
+) -> io::Result<()>
+where
+    D: ExecutableDomain,
+    D::State: Clone,
+    S: ExecutorState<D> + ExecutorStateLocal<D>,
+    R: BufRead,
+    W: Write,
+{
+    let mut lines = input.lines();
+    loop {
+        let mut decision = match executor.step_until_decision() {
+            DebugStep::Finished => {
+                writeln!(output, "No more tasks to execute.")?;
+                return Ok(());
+            }
+            DebugStep::Executed => continue,
+            DebugStep::Decision(decision) => *decision,
+        };
+        write_decision(&mut output, &decision)?;
+        loop {
+            write!(output, "> ")?;
+            output.flush()?;
+            let line = match lines.next() {
+                Some(line) => line?,
+                None => "q".into(),
+            };
+            let command = line.trim();
+            match command {
+                "" | "c" => break,
+                "o" => write_options(&mut output, &decision)?,
+                "s" => {
+                    let diff = D::Diff::default();
+                    let state_diff = StateDiffRef::new(executor.state(), &diff);
+                    writeln!(output, "{}", D::get_state_description(state_diff))?;
+                }
+                "p" => {
+                    let file_name = format!("T{}-{}", decision.tick(), decision.agent());
+                    plot_tree_in_tmp(decision.mcts(), "step-debugger", &file_name)?;
+                    writeln!(output, "Search tree written to {file_name}.dot")?;
+                }
+                "q" => {
+                    executor.commit_decision(decision);
+                    return Ok(());
+                }
+                _ => {
+                    let chosen: Option<Box<dyn Task<D>>> = command
+                        .parse::<usize>()
+                        .ok()
+                        .and_then(|index| decision.options().into_iter().nth(index))
+                        .map(|(task, _, _)| task);
+                    if let Some(task) = chosen {
+                        writeln!(output, "Overriding with {task:?}")?;
+                        decision.override_task(task);
+                        break;
+                    }
+                    writeln!(output, "{HELP}")?;
+                }
+            }
+        }
+        executor.commit_decision(decision);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::tests::{counter_mcts_config, Add, CounterExecutorState};
+
+    #[test]
+    fn step_debugger_overrides_decisions() {
+        let mut executor_state = CounterExecutorState(3);
+        let mut executor = SimpleExecutor::new(counter_mcts_config(), &mut executor_state);
+        let mut output = Vec::new();
+        let input = "o\nx\n0\n\nq\n".as_bytes();
+        run_step_debugger(&mut executor, input, &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("T0 A0 chooses"));
+        assert!(output.contains("visits:"));
+        assert!(output.contains("Commands:"));
+        assert!(output.contains("Overriding with"));
+        assert_eq!(executor.active_tasks().len(), 1);
+        let active_task = executor.active_tasks().iter().next().unwrap();
+        assert!(active_task.task.downcast_ref::<Add>().is_some());
+    }
+
+    #[test]
+    fn step_decision_can_be_overridden() {
+        let mut executor_state = CounterExecutorState(3);
+        let mut executor = SimpleExecutor::new(counter_mcts_config(), &mut executor_state);
+        let decision = match executor.step_until_decision() {
+            DebugStep::Decision(decision) => decision,
+            _ => panic!("Initial idle task should lead to a decision"),
+        };
+        let mut decision = *decision;
+        assert_eq!(decision.options().len(), 2);
+        decision.override_task(Box::new(Add(5)));
+        executor.commit_decision(decision);
+        executor.step();
+        assert_eq!(*executor.state(), 8);
+    }
+}
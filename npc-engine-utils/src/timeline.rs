@@ -0,0 +1,262 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    hash::{Hash, Hasher},
+};
+
+use npc_engine_core::{ActiveTask, AgentId, Domain};
+use rustc_hash::FxHasher;
+use serde::{Deserialize, Serialize};
+
+/// How a task of a [Timeline] ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpanOutcome {
+    /// The task was executed
+    Completed,
+    /// The task was not valid any more when it ended
+    Failed,
+}
+
+/// A task of an agent over a range of ticks.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimelineSpan {
+    /// The tick at which the task started
+    pub start: u64,
+    /// The tick at which the task ended
+    pub end: u64,
+    /// The task, as displayed by its `Debug` implementation
+    pub task: String,
+    /// How the task ended
+    pub outcome: SpanOutcome,
+}
+
+/// The tasks of an agent, in order.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AgentTimeline {
+    /// The agent
+    pub agent: AgentId,
+    /// Its tasks, ordered by start tick
+    pub spans: Vec<TimelineSpan>,
+}
+
+impl AgentTimeline {
+    /// Returns the ranges of ticks between `start` and `end` during which the agent had no task.
+    pub fn idle_gaps(&self, start: u64, end: u64) -> Vec<(u64, u64)> {
+        let mut gaps = Vec::new();
+        let mut tick = start;
+        for span in &self.spans {
+            if span.start > tick {
+                gaps.push((tick, span.start.min(end)));
+            }
+            tick = tick.max(span.end);
+        }
+        if tick < end {
+            gaps.push((tick, end));
+        }
+        gaps.retain(|(start, end)| start < end);
+        gaps
+    }
+}
+
+/// A Gantt-style timeline of the tasks of all agents over a recorded run, see [TimelineRecorder].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Timeline {
+    /// The timelines of the agents, ordered by agent
+    pub agents: Vec<AgentTimeline>,
+}
+
+impl Timeline {
+    /// Returns the timeline of an agent, if it did any task.
+    pub fn agent(&self, agent: AgentId) -> Option<&AgentTimeline> {
+        self.agents.iter().find(|timeline| timeline.agent == agent)
+    }
+
+    /// Returns the first and last ticks covered by tasks, or None if the timeline is empty.
+    pub fn tick_range(&self) -> Option<(u64, u64)> {
+        let spans = self.agents.iter().flat_map(|timeline| &timeline.spans);
+        let start = spans.clone().map(|span| span.start).min()?;
+        let end = spans.map(|span| span.end).max()?;
+        Some((start, end))
+    }
+
+    /// Returns the timeline in JSON, for external timeline viewers.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("Timelines are always serializable")
+    }
+
+    /// Renders the timeline as an SVG image, one row per agent and `tick_width` pixels per tick.
+    ///
+    /// Tasks are coloured by name, failed tasks are outlined in red, and hovering a task shows its name and ticks.
+    pub fn to_svg(&self, tick_width: u32) -> String {
+        const ROW_HEIGHT: u32 = 24;
+        const LABEL_WIDTH: u32 = 60;
+        let (start, end) = self.tick_range().unwrap_or((0, 0));
+        let tick_width = tick_width.max(1);
+        let width = LABEL_WIDTH + (end - start) as u32 * tick_width;
+        let height = ROW_HEIGHT * self.agents.len() as u32;
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" font-family=\"sans-serif\" font-size=\"11\">\n"
+        );
+        for (row, timeline) in self.agents.iter().enumerate() {
+            let y = row as u32 * ROW_HEIGHT;
+            writeln!(
+                svg,
+                "<text x=\"4\" y=\"{}\">{}</text>",
+                y + ROW_HEIGHT * 2 / 3,
+                timeline.agent
+            )
+            .unwrap();
+            for span in &timeline.spans {
+                let x = LABEL_WIDTH + (span.start - start) as u32 * tick_width;
+                let span_width = ((span.end - span.start) as u32 * tick_width).max(1);
+                let stroke = match span.outcome {
+                    SpanOutcome::Completed => "none",
+                    SpanOutcome::Failed => "red",
+                };
+                let task = escape_xml(&span.task);
+                writeln!(
+                    svg,
+                    "<rect x=\"{x}\" y=\"{}\" width=\"{span_width}\" height=\"{}\" fill=\"hsl({}, 60%, 70%)\" stroke=\"{stroke}\"><title>{task} T{}-{}</title></rect>",
+                    y + 2,
+                    ROW_HEIGHT - 4,
+                    task_hue(&span.task),
+                    span.start,
+                    span.end
+                )
+                .unwrap();
+            }
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+/// Returns a stable hue for a task name, so that the same tasks have the same colour.
+pub(crate) fn task_hue(task: &str) -> u64 {
+    // Ignore parameters, so that tasks of the same kind have the same colour
+    let kind = task
+        .split(|c: char| !c.is_alphanumeric())
+        .next()
+        .unwrap_or(task);
+    let mut hasher = FxHasher::default();
+    kind.hash(&mut hasher);
+    hasher.finish() % 360
+}
+
+pub(crate) fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Records the tasks of all agents of a run into a [Timeline].
+///
+/// Call [record_completed](Self::record_completed) from [on_task_completed](crate::ExecutorState::on_task_completed)
+/// and [record_failed](Self::record_failed) from [on_task_failed](crate::ExecutorState::on_task_failed).
+#[derive(Default)]
+pub struct TimelineRecorder {
+    spans: BTreeMap<AgentId, Vec<TimelineSpan>>,
+}
+
+impl TimelineRecorder {
+    /// Records a task that was executed.
+    pub fn record_completed<D: Domain>(&mut self, active_task: &ActiveTask<D>) {
+        self.record(active_task, SpanOutcome::Completed);
+    }
+
+    /// Records a task that failed.
+    pub fn record_failed<D: Domain>(&mut self, active_task: &ActiveTask<D>) {
+        self.record(active_task, SpanOutcome::Failed);
+    }
+
+    fn record<D: Domain>(&mut self, active_task: &ActiveTask<D>, outcome: SpanOutcome) {
+        self.spans
+            .entry(active_task.agent)
+            .or_default()
+            .push(TimelineSpan {
+                start: active_task.start,
+                end: active_task.end,
+                task: format!("{:?}", active_task.task),
+                outcome,
+            });
+    }
+
+    /// Returns the timeline recorded so far.
+    pub fn timeline(&self) -> Timeline {
+        Timeline {
+            agents: self
+                .spans
+                .iter()
+                .map(|(agent, spans)| {
+                    let mut spans = spans.clone();
+                    spans.sort_by_key(|span| (span.start, span.end));
+                    AgentTimeline {
+                        agent: *agent,
+                        spans,
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::tests::{counter_mcts_config, CounterDomain, CounterExecutorState};
+    use crate::{ExecutorState, ExecutorStateLocal, SimpleExecutor};
+    use npc_engine_core::ActiveTasks;
+
+    struct TimelineExecutorState(TimelineRecorder);
+    impl ExecutorStateLocal<CounterDomain> for TimelineExecutorState {
+        fn create_initial_state(&self) -> u32 {
+            0
+        }
+        fn init_task_queue(&self, state: &u32) -> ActiveTasks<CounterDomain> {
+            CounterExecutorState(*state).init_task_queue(state)
+        }
+    }
+    impl ExecutorState<CounterDomain> for TimelineExecutorState {
+        fn on_task_completed(&mut self, active_task: &ActiveTask<CounterDomain>) {
+            self.0.record_completed(active_task);
+        }
+        fn on_task_failed(&mut self, active_task: &ActiveTask<CounterDomain>) {
+            self.0.record_failed(active_task);
+        }
+    }
+
+    #[test]
+    fn timeline_records_and_renders_tasks() {
+        let mut executor_state = TimelineExecutorState(TimelineRecorder::default());
+        let mut executor = SimpleExecutor::new(counter_mcts_config(), &mut executor_state);
+        for _ in 0..5 {
+            executor.step();
+        }
+        let timeline = executor_state.0.timeline();
+        assert_eq!(timeline.agents.len(), 1);
+        let agent = timeline.agent(AgentId(0)).unwrap();
+        assert_eq!(agent.spans.len(), 5);
+        assert!(agent.spans[1..]
+            .iter()
+            .all(|span| span.task.starts_with("Add") && span.outcome == SpanOutcome::Completed));
+        let (start, end) = timeline.tick_range().unwrap();
+        assert!(agent.idle_gaps(start, end).is_empty());
+        assert_eq!(agent.idle_gaps(start, end + 3), vec![(end, end + 3)]);
+
+        let restored: Timeline = serde_json::from_str(&timeline.to_json()).unwrap();
+        assert_eq!(restored, timeline);
+        let svg = timeline.to_svg(10);
+        assert!(svg.starts_with("<svg"));
+        assert_eq!(svg.matches("<rect").count(), 5);
+        assert!(svg.contains("<title>Add("));
+        assert_eq!(escape_xml("<a & \"b\">"), "&lt;a &amp; &quot;b&quot;&gt;");
+        assert_eq!(task_hue("Add(1)"), task_hue("Add(2)"));
+    }
+}
@@ -0,0 +1,298 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::collections::BTreeMap;
+
+use npc_engine_core::{diff_changes, AgentId, DiffChange, Domain, Node, MCTS};
+use serde::Serialize;
+
+/// Options selecting the part of a search tree to export, see [export_tree_json].
+#[derive(Clone, Copy, Debug)]
+pub struct TreeExportOptions {
+    /// The maximum depth of exported nodes, the root being at depth 0
+    pub max_depth: usize,
+    /// The minimum number of visits of an edge for its branch to be exported
+    pub min_visits: usize,
+}
+impl Default for TreeExportOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: 8,
+            min_visits: 1,
+        }
+    }
+}
+
+/// The layout of an exported tree.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TreeLayout {
+    /// Each node contains its children, see [NestedTreeNode]
+    Nested,
+    /// A list of nodes referring to their parent by index, see [FlatTreeNode]
+    Flat,
+}
+
+/// The statistics of an exported node and of the edge leading to it.
+#[derive(Clone, Debug, Serialize)]
+pub struct ExportedNodeStats {
+    /// The agent who decides in this node
+    pub agent: AgentId,
+    /// The tick of this node
+    pub tick: u64,
+    /// The number of visits of this node
+    pub visits: usize,
+    /// The estimated value of this node for its agent, if it was expanded
+    pub value: Option<f32>,
+    /// The current values of the agents in this node
+    pub current_values: BTreeMap<AgentId, f32>,
+    /// The task leading to this node, None for the root
+    pub task: Option<String>,
+    /// The q-value of the task leading to this node for the agent of the parent, None for the root
+    pub task_value: Option<f32>,
+    /// The state changes compared to the parent, see [Domain::display_diff]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub changes: Vec<DiffChange>,
+}
+
+/// A node of an exported tree in the [nested](TreeLayout::Nested) layout.
+#[derive(Clone, Debug, Serialize)]
+pub struct NestedTreeNode {
+    /// The statistics of this node
+    #[serde(flatten)]
+    pub stats: ExportedNodeStats,
+    /// The exported children, the most visited first
+    pub children: Vec<NestedTreeNode>,
+}
+
+/// A node of an exported tree in the [flat](TreeLayout::Flat) layout.
+#[derive(Clone, Debug, Serialize)]
+pub struct FlatTreeNode {
+    /// The index of the parent in the list of nodes, None for the root
+    pub parent: Option<usize>,
+    /// The statistics of this node
+    #[serde(flatten)]
+    pub stats: ExportedNodeStats,
+}
+
+/// A child reachable from a node through an edge, with the statistics of that edge.
+struct ExportedChild<D: Domain> {
+    node: Node<D>,
+    task: String,
+    visits: usize,
+    task_value: f32,
+    changes: Vec<DiffChange>,
+}
+
+fn node_stats<D: Domain>(
+    mcts: &MCTS<D>,
+    node: &Node<D>,
+    edge: Option<&ExportedChild<D>>,
+) -> ExportedNodeStats {
+    let edges = mcts.get_edges(node);
+    ExportedNodeStats {
+        agent: node.agent(),
+        tick: node.tick(),
+        visits: edge.map_or_else(
+            || edges.map_or(0, |edges| edges.child_visits()),
+            |edge| edge.visits,
+        ),
+        value: edges.and_then(|edges| edges.q_value((0, 0.), node.agent())),
+        current_values: node
+            .current_values()
+            .iter()
+            .map(|(agent, value)| (*agent, **value))
+            .collect(),
+        task: edge.map(|edge| edge.task.clone()),
+        task_value: edge.map(|edge| edge.task_value),
+        changes: edge.map_or_else(Vec::new, |edge| edge.changes.clone()),
+    }
+}
+
+/// Returns the children of a node kept by `options`, the most visited first.
+fn exported_children<D: Domain>(
+    mcts: &MCTS<D>,
+    node: &Node<D>,
+    path: &[Node<D>],
+    options: &TreeExportOptions,
+) -> Vec<ExportedChild<D>> {
+    if path.len() >= options.max_depth {
+        return Vec::new();
+    }
+    let edges = match mcts.get_edges(node) {
+        Some(edges) => edges,
+        None => return Vec::new(),
+    };
+    let mut children: Vec<_> = edges
+        .expanded_tasks()
+        .filter_map(|(task, edge)| {
+            let edge = edge.lock().unwrap();
+            let child = edge.child();
+            // Skip less-visited branches and transpositions leading back to the current path
+            (edge.visits() >= options.min_visits && !path.contains(&child) && child != *node).then(
+                || ExportedChild {
+                    changes: diff_changes(mcts.initial_state(), node, &child),
+                    node: child,
+                    task: format!("{task:?}"),
+                    visits: edge.visits(),
+                    task_value: edge.q_value(node.agent()),
+                },
+            )
+        })
+        .collect();
+    children.sort_by(|a, b| b.visits.cmp(&a.visits).then_with(|| a.task.cmp(&b.task)));
+    children
+}
+
+fn export_nested<D: Domain>(
+    mcts: &MCTS<D>,
+    node: &Node<D>,
+    edge: Option<&ExportedChild<D>>,
+    path: &mut Vec<Node<D>>,
+    options: &TreeExportOptions,
+) -> NestedTreeNode {
+    let stats = node_stats(mcts, node, edge);
+    let children = exported_children(mcts, node, path, options);
+    path.push(node.clone());
+    let children = children
+        .iter()
+        .map(|child| export_nested(mcts, &child.node, Some(child), path, options))
+        .collect();
+    path.pop();
+    NestedTreeNode { stats, children }
+}
+
+fn export_flat<D: Domain>(
+    mcts: &MCTS<D>,
+    node: &Node<D>,
+    edge: Option<&ExportedChild<D>>,
+    parent: Option<usize>,
+    path: &mut Vec<Node<D>>,
+    options: &TreeExportOptions,
+    nodes: &mut Vec<FlatTreeNode>,
+) {
+    let index = nodes.len();
+    nodes.push(FlatTreeNode {
+        parent,
+        stats: node_stats(mcts, node, edge),
+    });
+    let children = exported_children(mcts, node, path, options);
+    path.push(node.clone());
+    for child in &children {
+        export_flat(
+            mcts,
+            &child.node,
+            Some(child),
+            Some(index),
+            path,
+            options,
+            nodes,
+        );
+    }
+    path.pop();
+}
+
+/// Exports the search tree in the [nested](TreeLayout::Nested) layout.
+///
+/// Nodes reachable through several paths are exported once per path.
+pub fn export_tree_nested<D: Domain>(
+    mcts: &MCTS<D>,
+    options: &TreeExportOptions,
+) -> NestedTreeNode {
+    export_nested(mcts, &mcts.root_node(), None, &mut Vec::new(), options)
+}
+
+/// Exports the search tree in the [flat](TreeLayout::Flat) layout, in depth-first order from the root.
+///
+/// Nodes reachable through several paths are exported once per path.
+pub fn export_tree_flat<D: Domain>(
+    mcts: &MCTS<D>,
+    options: &TreeExportOptions,
+) -> Vec<FlatTreeNode> {
+    let mut nodes = Vec::new();
+    export_flat(
+        mcts,
+        &mcts.root_node(),
+        None,
+        None,
+        &mut Vec::new(),
+        options,
+        &mut nodes,
+    );
+    nodes
+}
+
+/// Exports the search tree as compact JSON, for example to feed a browser-based tree explorer.
+pub fn export_tree_json<D: Domain>(
+    mcts: &MCTS<D>,
+    options: &TreeExportOptions,
+    layout: TreeLayout,
+) -> String {
+    match layout {
+        TreeLayout::Nested => serde_json::to_string(&export_tree_nested(mcts, options)),
+        TreeLayout::Flat => serde_json::to_string(&export_tree_flat(mcts, options)),
+    }
+    .expect("Exported trees are always serializable")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::tests::{counter_mcts_config, CounterDomain};
+
+    fn count_nested(node: &NestedTreeNode) -> usize {
+        1 + node.children.iter().map(count_nested).sum::<usize>()
+    }
+
+    #[test]
+    fn tree_export_layouts_agree() {
+        let mut mcts = MCTS::<CounterDomain>::new(0, AgentId(0), counter_mcts_config());
+        mcts.run();
+        let options = TreeExportOptions {
+            max_depth: 3,
+            min_visits: 1,
+        };
+        let nested = export_tree_nested(&mcts, &options);
+        let flat = export_tree_flat(&mcts, &options);
+        assert_eq!(count_nested(&nested), flat.len());
+        assert!(flat.len() > 1);
+        assert_eq!(flat[0].parent, None);
+        assert_eq!(nested.stats.visits, counter_mcts_config().visits as usize);
+        assert!(nested.stats.task.is_none());
+        for (index, node) in flat.iter().enumerate().skip(1) {
+            let parent = node.parent.unwrap();
+            assert!(parent < index);
+            assert!(node.stats.task.as_ref().unwrap().starts_with("Add"));
+            assert_eq!(node.stats.changes.len(), 1);
+        }
+
+        // Pruning keeps fewer nodes
+        let pruned = export_tree_flat(
+            &mcts,
+            &TreeExportOptions {
+                max_depth: 3,
+                min_visits: 5,
+            },
+        );
+        assert!(pruned.len() < flat.len());
+        let root_only = export_tree_flat(
+            &mcts,
+            &TreeExportOptions {
+                max_depth: 0,
+                min_visits: 1,
+            },
+        );
+        assert_eq!(root_only.len(), 1);
+
+        let json = export_tree_json(&mcts, &options, TreeLayout::Nested);
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            value["children"].as_array().unwrap().len(),
+            nested.children.len()
+        );
+        assert!(value["current_values"].is_object());
+        assert!(value.get("changes").is_none());
+        assert!(value["children"][0]["changes"].is_array());
+    }
+}
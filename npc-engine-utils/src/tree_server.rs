@@ -0,0 +1,266 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use npc_engine_core::{ActiveTask, AgentId, Domain, MCTS};
+use serde::Serialize;
+use tungstenite::{Message, WebSocket};
+
+use crate::{export_tree_nested, TreeExportOptions};
+
+/// The bundled web page exploring trees and listing decisions.
+const TREE_SERVER_PAGE: &str = include_str!("tree_server.html");
+
+/// A message streamed to the WebSocket clients of a [TreeServer].
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum LiveMessage<'a> {
+    Tree {
+        agent: AgentId,
+        tick: u64,
+    },
+    Decision {
+        agent: AgentId,
+        start: u64,
+        end: u64,
+        task: &'a str,
+    },
+}
+
+#[derive(Default)]
+struct ServerState {
+    options: Mutex<TreeExportOptions>,
+    trees: Mutex<BTreeMap<AgentId, String>>,
+    clients: Mutex<Vec<WebSocket<TcpStream>>>,
+    running: AtomicBool,
+}
+impl ServerState {
+    fn broadcast(&self, message: &LiveMessage) {
+        let json = serde_json::to_string(message).expect("Live messages are always serializable");
+        // Forget clients that cannot be written to
+        self.clients
+            .lock()
+            .unwrap()
+            .retain_mut(|client| client.send(Message::text(json.clone())).is_ok());
+    }
+}
+
+/// An HTTP and WebSocket server to watch the reasoning of agents live, for example while playing the game.
+///
+/// The server exposes:
+/// - `/`, a small web page to explore the latest trees and follow decisions,
+/// - `/trees`, the list of agents having a published tree, in JSON,
+/// - `/trees/<agent>`, the latest tree of an agent, as exported by [export_tree_nested],
+/// - `/ws`, a WebSocket streaming a JSON message when a tree or a decision is published.
+///
+/// Trees and decisions are published by calling [publish_tree](Self::publish_tree) and
+/// [publish_decision](Self::publish_decision), typically from
+/// [post_mcts_run_hook](crate::ExecutorState::post_mcts_run_hook) and
+/// [on_task_chosen](crate::ExecutorState::on_task_chosen).
+/// The server stops when dropped.
+pub struct TreeServer {
+    state: Arc<ServerState>,
+    local_addr: SocketAddr,
+}
+
+impl TreeServer {
+    /// Starts a server listening on the given address, serving requests on a background thread.
+    pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        let local_addr = listener.local_addr()?;
+        let state = Arc::new(ServerState::default());
+        state.running.store(true, Ordering::Relaxed);
+        let thread_state = state.clone();
+        thread::Builder::new()
+            .name("tree-server".into())
+            .spawn(move || {
+                while thread_state.running.load(Ordering::Relaxed) {
+                    match listener.accept() {
+                        Ok((stream, _)) => {
+                            if let Err(error) = handle_connection(stream, &thread_state) {
+                                log::debug!("Tree server connection failed: {error}");
+                            }
+                        }
+                        Err(error) if error.kind() == io::ErrorKind::WouldBlock => {
+                            thread::sleep(Duration::from_millis(20));
+                        }
+                        Err(error) => log::warn!("Tree server cannot accept: {error}"),
+                    }
+                }
+            })?;
+        Ok(Self { state, local_addr })
+    }
+
+    /// Returns the address the server listens on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Sets the part of the trees to publish, see [TreeExportOptions].
+    pub fn set_export_options(&self, options: TreeExportOptions) {
+        *self.state.options.lock().unwrap() = options;
+    }
+
+    /// Returns the number of connected WebSocket clients.
+    pub fn client_count(&self) -> usize {
+        self.state.clients.lock().unwrap().len()
+    }
+
+    /// Publishes the tree of a planning, replacing the previous one of its agent.
+    pub fn publish_tree<D: Domain>(&self, mcts: &MCTS<D>) {
+        let options = *self.state.options.lock().unwrap();
+        let json = serde_json::to_string(&export_tree_nested(mcts, &options))
+            .expect("Exported trees are always serializable");
+        self.state.trees.lock().unwrap().insert(mcts.agent(), json);
+        self.state.broadcast(&LiveMessage::Tree {
+            agent: mcts.agent(),
+            tick: mcts.start_tick(),
+        });
+    }
+
+    /// Publishes a task chosen by an agent.
+    pub fn publish_decision<D: Domain>(&self, active_task: &ActiveTask<D>) {
+        let task = format!("{:?}", active_task.task);
+        self.state.broadcast(&LiveMessage::Decision {
+            agent: active_task.agent,
+            start: active_task.start,
+            end: active_task.end,
+            task: &task,
+        });
+    }
+}
+
+impl Drop for TreeServer {
+    fn drop(&mut self) {
+        self.state.running.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Returns the head of the request on the stream and its length in bytes including the blank line, without consuming it.
+fn peek_request_head(stream: &TcpStream) -> io::Result<(String, usize)> {
+    let mut buffer = [0; 4096];
+    loop {
+        let len = stream.peek(&mut buffer)?;
+        if let Some(end) = buffer[..len].windows(4).position(|end| end == b"\r\n\r\n") {
+            return Ok((
+                String::from_utf8_lossy(&buffer[..end]).into_owned(),
+                end + 4,
+            ));
+        }
+        if len == 0 || len == buffer.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "malformed request",
+            ));
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+fn handle_connection(mut stream: TcpStream, state: &ServerState) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(Duration::from_secs(1)))?;
+    stream.set_write_timeout(Some(Duration::from_millis(100)))?;
+    let (head, head_len) = peek_request_head(&stream)?;
+    let path = head.split_whitespace().nth(1).unwrap_or("/").to_string();
+    if path == "/ws" {
+        let client = tungstenite::accept(stream)
+            .map_err(|error| io::Error::new(io::ErrorKind::Other, error.to_string()))?;
+        state.clients.lock().unwrap().push(client);
+        return Ok(());
+    }
+    // Consume the request before answering
+    let mut head_bytes = vec![0; head_len];
+    io::Read::read_exact(&mut stream, &mut head_bytes)?;
+    let (status, content_type, body) = match path.as_str() {
+        "/" => ("200 OK", "text/html", TREE_SERVER_PAGE.to_string()),
+        "/trees" => {
+            let agents: Vec<_> = state.trees.lock().unwrap().keys().copied().collect();
+            (
+                "200 OK",
+                "application/json",
+                serde_json::to_string(&agents).expect("Agents are always serializable"),
+            )
+        }
+        _ => {
+            let tree = path
+                .strip_prefix("/trees/")
+                .and_then(|agent| agent.parse().ok())
+                .and_then(|agent| state.trees.lock().unwrap().get(&AgentId(agent)).cloned());
+            match tree {
+                Some(tree) => ("200 OK", "application/json", tree),
+                None => ("404 Not Found", "text/plain", "not found".to_string()),
+            }
+        }
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::tests::{counter_mcts_config, CounterDomain};
+    use std::io::Read;
+
+    fn get(addr: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn tree_server_serves_trees_and_streams_decisions() {
+        let server = TreeServer::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr();
+        assert!(get(addr, "/").contains("<html"));
+        assert!(get(addr, "/trees").ends_with("[]"));
+        assert!(get(addr, "/trees/0").starts_with("HTTP/1.1 404"));
+
+        let stream = TcpStream::connect(addr).unwrap();
+        let (mut client, _) = tungstenite::client(format!("ws://{addr}/ws"), stream).unwrap();
+        while server.client_count() == 0 {
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        let mut mcts = MCTS::<CounterDomain>::new(0, AgentId(0), counter_mcts_config());
+        let task = mcts.run().unwrap();
+        server.publish_tree(&mcts);
+        let message: serde_json::Value =
+            serde_json::from_str(client.read().unwrap().to_text().unwrap()).unwrap();
+        assert_eq!(message["type"], "tree");
+        assert_eq!(message["agent"], 0);
+        assert!(get(addr, "/trees").ends_with("[0]"));
+        let response = get(addr, "/trees/0");
+        let tree: serde_json::Value =
+            serde_json::from_str(response.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        assert_eq!(tree["visits"], counter_mcts_config().visits);
+
+        server.publish_decision(&ActiveTask::new_with_end(0, 1, AgentId(0), task));
+        let message: serde_json::Value =
+            serde_json::from_str(client.read().unwrap().to_text().unwrap()).unwrap();
+        assert_eq!(message["type"], "decision");
+        assert_eq!(message["end"], 1);
+        assert!(message["task"].as_str().unwrap().starts_with("Add"));
+    }
+}
@@ -0,0 +1,136 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::sync::{Arc, Mutex};
+
+use npc_engine_core::{MCTSConfiguration, PlanningBudget};
+
+/// The parameters of an executor that can be changed while it runs, through a [TuningHandle].
+#[derive(Clone, Debug)]
+pub struct Tuning {
+    /// The MCTS configuration of new plannings
+    pub mcts_config: MCTSConfiguration,
+    /// The computation granted to each planning per step, only used by the [IncrementalExecutor](crate::IncrementalExecutor)
+    pub budget: Option<PlanningBudget>,
+    /// The computation shared by all plannings per step, only used by the [IncrementalExecutor](crate::IncrementalExecutor)
+    pub step_budget: Option<PlanningBudget>,
+}
+
+#[derive(Debug)]
+struct VersionedTuning {
+    version: u64,
+    tuning: Tuning,
+}
+
+/// A shared handle to change the parameters of an executor while it runs.
+///
+/// It can be cloned and sent to other threads, for example to let a game dial the AI quality
+/// up or down from its graphics settings.
+/// Changes are applied at the beginning of the next step of the executor, and affect the plannings started after.
+#[derive(Clone, Debug)]
+pub struct TuningHandle(Arc<Mutex<VersionedTuning>>);
+impl TuningHandle {
+    pub(crate) fn new(tuning: Tuning) -> Self {
+        Self(Arc::new(Mutex::new(VersionedTuning { version: 0, tuning })))
+    }
+
+    /// Gets a copy of the current parameters.
+    pub fn get(&self) -> Tuning {
+        self.0.lock().unwrap().tuning.clone()
+    }
+
+    /// Changes the parameters through `f`.
+    pub fn update<F: FnOnce(&mut Tuning)>(&self, f: F) {
+        let mut versioned = self.0.lock().unwrap();
+        f(&mut versioned.tuning);
+        versioned.version += 1;
+    }
+
+    /// Sets the number of visits of new plannings.
+    pub fn set_visits(&self, visits: u32) {
+        self.update(|tuning| tuning.mcts_config.visits = visits);
+    }
+
+    /// Sets the search depth of new plannings.
+    pub fn set_depth(&self, depth: u32) {
+        self.update(|tuning| tuning.mcts_config.depth = depth);
+    }
+
+    /// Sets the exploration factor of new plannings.
+    pub fn set_exploration(&self, exploration: f32) {
+        self.update(|tuning| tuning.mcts_config.exploration = exploration);
+    }
+
+    /// Returns the parameters if they changed since `version`, and updates it.
+    pub(crate) fn changes_since(&self, version: &mut u64) -> Option<Tuning> {
+        let versioned = self.0.lock().unwrap();
+        (versioned.version != *version).then(|| {
+            *version = versioned.version;
+            versioned.tuning.clone()
+        })
+    }
+}
+
+/// The end of a [TuningHandle] held by an executor.
+#[derive(Debug)]
+pub(crate) struct TuningListener {
+    handle: TuningHandle,
+    version: u64,
+}
+impl TuningListener {
+    /// Creates a handle initialized with `tuning`, or returns the existing one.
+    pub(crate) fn handle(
+        listener: &mut Option<Self>,
+        tuning: impl FnOnce() -> Tuning,
+    ) -> TuningHandle {
+        listener
+            .get_or_insert_with(|| Self {
+                handle: TuningHandle::new(tuning()),
+                version: 0,
+            })
+            .handle
+            .clone()
+    }
+
+    /// Returns the parameters if they changed since the last call.
+    pub(crate) fn poll(&mut self) -> Option<Tuning> {
+        self.handle.changes_since(&mut self.version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::tests::{trivial_mcts_config, TrivialExecutorState};
+    use crate::IncrementalExecutor;
+
+    #[test]
+    fn tuning_applies_at_next_step() {
+        let mut executor_state = TrivialExecutorState(2);
+        let mut executor = IncrementalExecutor::new(
+            trivial_mcts_config(),
+            PlanningBudget::Visits(100),
+            &mut executor_state,
+        );
+        let handle = executor.tuning_handle();
+        assert_eq!(
+            handle.get().mcts_config.visits,
+            trivial_mcts_config().visits
+        );
+        assert_eq!(handle.get().budget, Some(PlanningBudget::Visits(100)));
+        let other_thread_handle = handle.clone();
+        std::thread::spawn(move || {
+            other_thread_handle.set_visits(50);
+            other_thread_handle.update(|tuning| tuning.budget = Some(PlanningBudget::Visits(1)));
+        })
+        .join()
+        .unwrap();
+        assert_eq!(handle.get().mcts_config.visits, 50);
+        // Planning starts with the new parameters and, with 1 visit per step, is still ongoing
+        executor.step();
+        executor.step();
+        assert_eq!(executor.planning_agents_count(), 2);
+    }
+}
@@ -0,0 +1,342 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    hash::Hash,
+    ops::Deref,
+    sync::Arc,
+};
+
+/// A read-only view of an external world, such as an ECS world (hecs, legion, bevy), used as the [State](npc_engine_core::Domain::State) of a domain.
+///
+/// Cloning a view shares the world instead of copying it, so that a planning only costs a reference count increment,
+/// while its [Diff](npc_engine_core::Domain::Diff), typically a [WorldDiff], records the changes made by tasks on top of the world.
+/// To use it, implement [WorldQuery] on the world for the components the domain reads,
+/// and [WorldWrite] for those its tasks change.
+///
+/// The world is changed between plannings through [write](Self::write), which copies it only if a planning still holds a view.
+/// With a [GlobalDomain](crate::GlobalDomain), both the global and the local states are views of the same world:
+/// ```
+/// # use std::collections::{BTreeMap, BTreeSet};
+/// # use npc_engine_core::{AgentId, AgentValue, Behavior, Context, Domain, StateDiffRef};
+/// use npc_engine_utils::{GlobalDomain, WorldDiff, WorldView};
+///
+/// /// The amount of wood of each entity
+/// type World = BTreeMap<u32, u32>;
+///
+/// struct ForestDomain;
+/// impl Domain for ForestDomain {
+///     type State = WorldView<World>;
+///     type Diff = WorldDiff<u32, u32>;
+///     // ..
+/// #     type DisplayAction = ();
+/// #     fn list_behaviors() -> &'static [&'static dyn Behavior<Self>] { &[] }
+/// #     fn get_current_value(_tick: u64, state_diff: StateDiffRef<Self>, agent: AgentId) -> AgentValue {
+/// #         let wood = state_diff.diff.get(&**state_diff.initial_state, &agent.0).unwrap_or(0);
+/// #         AgentValue::new(wood as f32).unwrap()
+/// #     }
+/// #     fn update_visible_agents(_start_tick: u64, _ctx: Context<Self>, _agents: &mut BTreeSet<AgentId>) {}
+/// }
+/// impl GlobalDomain for ForestDomain {
+///     type GlobalState = WorldView<World>;
+///     fn derive_local_state(world: &WorldView<World>, _agent: AgentId) -> WorldView<World> {
+///         world.clone()
+///     }
+///     fn apply(world: &mut WorldView<World>, _local_state: &WorldView<World>, diff: &Self::Diff) {
+///         diff.apply(world.write());
+///     }
+/// }
+/// ```
+pub struct WorldView<W>(Arc<W>);
+
+impl<W> WorldView<W> {
+    /// Creates a view of `world`.
+    pub fn new(world: W) -> Self {
+        Self(Arc::new(world))
+    }
+
+    /// Creates a view of a world already shared with the rest of the application.
+    pub fn from_shared(world: Arc<W>) -> Self {
+        Self(world)
+    }
+
+    /// Returns the world mutably, if no other view shares it.
+    pub fn get_mut(&mut self) -> Option<&mut W> {
+        Arc::get_mut(&mut self.0)
+    }
+
+    /// Returns whether other views share this world, for example because a planning is ongoing.
+    pub fn is_shared(&self) -> bool {
+        Arc::strong_count(&self.0) > 1
+    }
+
+    /// Returns whether both views share the same world.
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        Arc::ptr_eq(&this.0, &other.0)
+    }
+}
+
+impl<W: Clone> WorldView<W> {
+    /// Returns the world mutably, copying it first if other views share it.
+    ///
+    /// Ongoing plannings keep seeing the world as it was when they started.
+    pub fn write(&mut self) -> &mut W {
+        Arc::make_mut(&mut self.0)
+    }
+}
+
+impl<W> Clone for WorldView<W> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<W> Deref for WorldView<W> {
+    type Target = W;
+
+    fn deref(&self) -> &W {
+        &self.0
+    }
+}
+
+impl<W> fmt::Debug for WorldView<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // External worlds often do not implement Debug, and are too large to print anyway
+        write!(f, "WorldView<{}>", std::any::type_name::<W>())
+    }
+}
+
+/// Read access to a component of an external world, indexed by `K`, typically an entity.
+pub trait WorldQuery<K, V> {
+    /// Returns the value of the component for `key`, None if it has none.
+    fn get_component(&self, key: &K) -> Option<V>;
+}
+
+/// Write access to a component of an external world, indexed by `K`, typically an entity.
+pub trait WorldWrite<K, V> {
+    /// Sets the value of the component for `key`, removes it if `value` is None.
+    fn set_component(&mut self, key: K, value: Option<V>);
+}
+
+impl<K: Ord, V: Clone> WorldQuery<K, V> for BTreeMap<K, V> {
+    fn get_component(&self, key: &K) -> Option<V> {
+        self.get(key).cloned()
+    }
+}
+
+impl<K: Ord, V> WorldWrite<K, V> for BTreeMap<K, V> {
+    fn set_component(&mut self, key: K, value: Option<V>) {
+        match value {
+            Some(value) => self.insert(key, value),
+            None => self.remove(&key),
+        };
+    }
+}
+
+impl<K: Eq + Hash, V: Clone> WorldQuery<K, V> for HashMap<K, V> {
+    fn get_component(&self, key: &K) -> Option<V> {
+        self.get(key).cloned()
+    }
+}
+
+impl<K: Eq + Hash, V> WorldWrite<K, V> for HashMap<K, V> {
+    fn set_component(&mut self, key: K, value: Option<V>) {
+        match value {
+            Some(value) => self.insert(key, value),
+            None => self.remove(&key),
+        };
+    }
+}
+
+/// The changes made by tasks to a component of a world seen through a [WorldView], to be used as [Diff](npc_engine_core::Domain::Diff).
+///
+/// Reads go through the changes first and fall back to the world, so that tasks never copy the world.
+/// A domain with several changing components can use a tuple or a struct of such diffs.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct WorldDiff<K: Ord, V>(BTreeMap<K, Option<V>>);
+
+impl<K: Ord, V> Default for WorldDiff<K, V> {
+    fn default() -> Self {
+        Self(BTreeMap::new())
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> WorldDiff<K, V> {
+    /// Returns the value of the component for `key`, as changed by the diff or otherwise as in `world`.
+    pub fn get<W: WorldQuery<K, V> + ?Sized>(&self, world: &W, key: &K) -> Option<V> {
+        match self.0.get(key) {
+            Some(value) => value.clone(),
+            None => world.get_component(key),
+        }
+    }
+
+    /// Sets the value of the component for `key`.
+    pub fn set(&mut self, key: K, value: V) {
+        self.0.insert(key, Some(value));
+    }
+
+    /// Removes the component for `key`.
+    pub fn remove(&mut self, key: K) {
+        self.0.insert(key, None);
+    }
+
+    /// Returns the number of changed components.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns whether no component was changed.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the changes, None values being removals, ordered by key.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, Option<&V>)> {
+        self.0.iter().map(|(key, value)| (key, value.as_ref()))
+    }
+
+    /// Applies the changes to `world`.
+    pub fn apply<W: WorldWrite<K, V> + ?Sized>(&self, world: &mut W) {
+        for (key, value) in &self.0 {
+            world.set_component(key.clone(), value.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use npc_engine_core::{
+        impl_task_boxed_methods, AgentId, AgentValue, Behavior, Context, ContextMut, Domain,
+        MCTSConfiguration, StateDiffRef, Task, TaskDuration, MCTS,
+    };
+
+    use super::{WorldDiff, WorldView};
+
+    /// The amount of wood of each agent
+    type World = BTreeMap<u32, u32>;
+
+    struct ForestDomain;
+    impl Domain for ForestDomain {
+        type State = WorldView<World>;
+        type Diff = WorldDiff<u32, u32>;
+        type DisplayAction = ();
+
+        fn list_behaviors() -> &'static [&'static dyn Behavior<Self>] {
+            &[&ChopBehavior]
+        }
+
+        fn get_current_value(
+            _tick: u64,
+            state_diff: StateDiffRef<Self>,
+            agent: AgentId,
+        ) -> AgentValue {
+            let wood = state_diff
+                .diff
+                .get(&**state_diff.initial_state, &agent.0)
+                .unwrap_or(0);
+            AgentValue::new(wood as f32).unwrap()
+        }
+
+        fn update_visible_agents(
+            _start_tick: u64,
+            ctx: Context<Self>,
+            agents: &mut BTreeSet<AgentId>,
+        ) {
+            agents.insert(ctx.agent);
+        }
+    }
+
+    #[derive(Copy, Clone, Debug)]
+    struct ChopBehavior;
+    impl Behavior<ForestDomain> for ChopBehavior {
+        fn add_own_tasks(
+            &self,
+            _ctx: Context<ForestDomain>,
+            tasks: &mut Vec<Box<dyn Task<ForestDomain>>>,
+        ) {
+            tasks.push(Box::new(Chop(1)));
+            tasks.push(Box::new(Chop(2)));
+        }
+
+        fn is_valid(&self, _ctx: Context<ForestDomain>) -> bool {
+            true
+        }
+    }
+
+    #[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+    struct Chop(u32);
+    impl Task<ForestDomain> for Chop {
+        fn duration(&self, _ctx: Context<ForestDomain>) -> TaskDuration {
+            1
+        }
+        fn execute(&self, ctx: ContextMut<ForestDomain>) -> Option<Box<dyn Task<ForestDomain>>> {
+            let key = ctx.agent.0;
+            let wood = ctx
+                .state_diff
+                .diff
+                .get(&**ctx.state_diff.initial_state, &key)
+                .unwrap_or(0);
+            ctx.state_diff.diff.set(key, wood + self.0);
+            None
+        }
+        fn is_valid(&self, _ctx: Context<ForestDomain>) -> bool {
+            true
+        }
+        fn display_action(&self) -> <ForestDomain as Domain>::DisplayAction {}
+        impl_task_boxed_methods!(ForestDomain);
+    }
+
+    #[test]
+    fn diffs_read_through_to_the_world() {
+        let world: World = [(0, 3), (1, 5)].into_iter().collect();
+        let mut diff = WorldDiff::default();
+        assert_eq!(diff.get(&world, &0), Some(3));
+        diff.set(0, 4);
+        diff.remove(1);
+        diff.set(2, 1);
+        assert_eq!(diff.get(&world, &0), Some(4));
+        assert_eq!(diff.get(&world, &1), None);
+        assert_eq!(diff.len(), 3);
+
+        let mut view = WorldView::new(world);
+        diff.apply(view.write());
+        let expected: World = [(0, 4), (2, 1)].into_iter().collect();
+        assert_eq!(*view, expected);
+    }
+
+    #[test]
+    fn planning_shares_the_world() {
+        let config = MCTSConfiguration {
+            allow_invalid_tasks: false,
+            visits: 20,
+            depth: 5,
+            exploration: 1.414,
+            discount_hl: 15.,
+            seed: Some(0),
+            planning_task_duration: None,
+        };
+        let mut view = WorldView::new([(0, 3)].into_iter().collect::<World>());
+        let mut mcts = MCTS::<ForestDomain>::new(view.clone(), AgentId(0), config);
+        assert!(WorldView::ptr_eq(mcts.initial_state(), &view));
+        let best = mcts.run().unwrap();
+        assert_eq!(best.downcast_ref::<Chop>().unwrap().0, 2);
+        // The world is not copied by the search
+        assert!(WorldView::ptr_eq(mcts.initial_state(), &view));
+
+        // Writing while planning copies the world, the planning keeps its own
+        assert!(view.is_shared());
+        view.write().insert(0, 10);
+        assert!(!WorldView::ptr_eq(mcts.initial_state(), &view));
+        assert_eq!(mcts.initial_state()[&0], 3);
+        drop(mcts);
+        assert!(!view.is_shared());
+        view.get_mut().unwrap().insert(0, 11);
+        assert_eq!(view[&0], 11);
+    }
+}
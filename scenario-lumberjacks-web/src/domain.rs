@@ -0,0 +1,236 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use npc_engine_core::{
+    impl_task_boxed_methods, AgentId, AgentValue, Behavior, Context, ContextMut, Domain,
+    SnapshotDomain, StateDiffRef, Task, TaskDuration,
+};
+use npc_engine_utils::{Coord2D, Direction, DirectionConverterYDown, OptionDiffDomain, DIRECTIONS};
+use serde::{Deserialize, Serialize};
+
+/// A lumberjack, with the wood it has collected.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lumberjack {
+    pub position: Coord2D,
+    pub wood: u32,
+}
+
+/// A rectangular forest, in which lumberjacks move and chop trees.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct State {
+    pub width: i32,
+    pub height: i32,
+    /// Whether there is a tree, row by row
+    pub trees: Vec<bool>,
+    pub lumberjacks: BTreeMap<AgentId, Lumberjack>,
+}
+
+impl State {
+    fn index(&self, position: Coord2D) -> Option<usize> {
+        (position.x >= 0 && position.x < self.width && position.y >= 0 && position.y < self.height)
+            .then(|| (position.y * self.width + position.x) as usize)
+    }
+
+    /// Returns whether there is a tree at `position`.
+    pub fn is_tree(&self, position: Coord2D) -> bool {
+        self.index(position)
+            .map_or(false, |index| self.trees[index])
+    }
+
+    /// Returns whether a lumberjack can move to `position`.
+    pub fn is_free(&self, position: Coord2D) -> bool {
+        self.index(position).is_some()
+            && !self.is_tree(position)
+            && self
+                .lumberjacks
+                .values()
+                .all(|lumberjack| lumberjack.position != position)
+    }
+
+    /// Returns the lumberjack at `position`, if any.
+    pub fn lumberjack_at(&self, position: Coord2D) -> Option<AgentId> {
+        self.lumberjacks
+            .iter()
+            .find_map(|(agent, lumberjack)| (lumberjack.position == position).then_some(*agent))
+    }
+
+    /// Returns the number of remaining trees.
+    pub fn tree_count(&self) -> usize {
+        self.trees.iter().filter(|tree| **tree).count()
+    }
+
+    /// Returns the forest as text, one line per row, with a digit for each lumberjack and a `T` for each tree.
+    pub fn render(&self) -> String {
+        let mut text = String::new();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let position = Coord2D::new(x, y);
+                let cell = if let Some(agent) = self.lumberjack_at(position) {
+                    char::from_digit(agent.0 % 10, 10).unwrap()
+                } else if self.is_tree(position) {
+                    'T'
+                } else {
+                    '.'
+                };
+                text.push(cell);
+            }
+            text.push('\n');
+        }
+        text
+    }
+}
+
+pub struct LumberjacksDomain;
+
+impl LumberjacksDomain {
+    /// The name of the domain in replay files, see [ReplayFile](npc_engine_utils::ReplayFile).
+    pub const REPLAY_DOMAIN: &'static str = "lumberjacks-web";
+}
+
+impl Domain for LumberjacksDomain {
+    type State = State;
+    type Diff = Option<State>;
+    type DisplayAction = ();
+
+    fn list_behaviors() -> &'static [&'static dyn Behavior<Self>] {
+        &[&LumberjackBehavior]
+    }
+
+    fn get_current_value(_tick: u64, state_diff: StateDiffRef<Self>, agent: AgentId) -> AgentValue {
+        let state = LumberjacksDomain::get_cur_state(state_diff);
+        let wood = state
+            .lumberjacks
+            .get(&agent)
+            .map_or(0, |lumberjack| lumberjack.wood);
+        AgentValue::new(wood as f32).unwrap()
+    }
+
+    fn update_visible_agents(_start_tick: u64, ctx: Context<Self>, agents: &mut BTreeSet<AgentId>) {
+        let state = LumberjacksDomain::get_cur_state(ctx.state_diff);
+        agents.extend(state.lumberjacks.keys());
+    }
+}
+
+/// The serializable form of the tasks of lumberjacks.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LumberjackTask {
+    Move(Direction),
+    Chop(Direction),
+}
+
+impl SnapshotDomain for LumberjacksDomain {
+    type TaskSnapshot = LumberjackTask;
+
+    fn snapshot_task(task: &dyn Task<Self>) -> LumberjackTask {
+        if let Some(Move(direction)) = task.downcast_ref::<Move>() {
+            LumberjackTask::Move(*direction)
+        } else if let Some(Chop(direction)) = task.downcast_ref::<Chop>() {
+            LumberjackTask::Chop(*direction)
+        } else {
+            panic!("Unknown task {task:?}")
+        }
+    }
+
+    fn restore_task(snapshot: LumberjackTask) -> Box<dyn Task<Self>> {
+        match snapshot {
+            LumberjackTask::Move(direction) => Box::new(Move(direction)),
+            LumberjackTask::Chop(direction) => Box::new(Chop(direction)),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct LumberjackBehavior;
+
+impl Behavior<LumberjacksDomain> for LumberjackBehavior {
+    fn add_own_tasks(
+        &self,
+        ctx: Context<LumberjacksDomain>,
+        tasks: &mut Vec<Box<dyn Task<LumberjacksDomain>>>,
+    ) {
+        for direction in DIRECTIONS {
+            let tasks_in_direction: [Box<dyn Task<LumberjacksDomain>>; 2] =
+                [Box::new(Move(direction)), Box::new(Chop(direction))];
+            tasks.extend(
+                tasks_in_direction
+                    .into_iter()
+                    .filter(|task| task.is_valid(ctx)),
+            );
+        }
+    }
+
+    fn is_valid(&self, ctx: Context<LumberjacksDomain>) -> bool {
+        LumberjacksDomain::get_cur_state(ctx.state_diff)
+            .lumberjacks
+            .contains_key(&ctx.agent)
+    }
+}
+
+/// Returns the position next to the agent in `direction`.
+fn target(state: &State, agent: AgentId, direction: Direction) -> Coord2D {
+    DirectionConverterYDown::apply(direction, state.lumberjacks[&agent].position)
+}
+
+/// Moves to the next cell in a direction.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Move(pub Direction);
+
+impl Task<LumberjacksDomain> for Move {
+    fn duration(&self, _ctx: Context<LumberjacksDomain>) -> TaskDuration {
+        1
+    }
+
+    fn execute(
+        &self,
+        ctx: ContextMut<LumberjacksDomain>,
+    ) -> Option<Box<dyn Task<LumberjacksDomain>>> {
+        let state = LumberjacksDomain::get_cur_state_mut(ctx.state_diff);
+        let position = target(state, ctx.agent, self.0);
+        state.lumberjacks.get_mut(&ctx.agent).unwrap().position = position;
+        None
+    }
+
+    fn is_valid(&self, ctx: Context<LumberjacksDomain>) -> bool {
+        let state = LumberjacksDomain::get_cur_state(ctx.state_diff);
+        state.is_free(target(state, ctx.agent, self.0))
+    }
+
+    fn display_action(&self) -> <LumberjacksDomain as Domain>::DisplayAction {}
+
+    impl_task_boxed_methods!(LumberjacksDomain);
+}
+
+/// Chops the tree on the next cell in a direction, collecting one wood.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Chop(pub Direction);
+
+impl Task<LumberjacksDomain> for Chop {
+    fn duration(&self, _ctx: Context<LumberjacksDomain>) -> TaskDuration {
+        1
+    }
+
+    fn execute(
+        &self,
+        ctx: ContextMut<LumberjacksDomain>,
+    ) -> Option<Box<dyn Task<LumberjacksDomain>>> {
+        let state = LumberjacksDomain::get_cur_state_mut(ctx.state_diff);
+        let position = target(state, ctx.agent, self.0);
+        let index = state.index(position).unwrap();
+        state.trees[index] = false;
+        state.lumberjacks.get_mut(&ctx.agent).unwrap().wood += 1;
+        None
+    }
+
+    fn is_valid(&self, ctx: Context<LumberjacksDomain>) -> bool {
+        let state = LumberjacksDomain::get_cur_state(ctx.state_diff);
+        state.is_tree(target(state, ctx.agent, self.0))
+    }
+
+    fn display_action(&self) -> <LumberjacksDomain as Domain>::DisplayAction {}
+
+    impl_task_boxed_methods!(LumberjacksDomain);
+}
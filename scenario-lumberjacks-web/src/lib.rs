@@ -0,0 +1,208 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+//! A minimal lumberjacks demo of the NPC engine, running in the browser through WebAssembly.
+//!
+//! Lumberjacks move in a forest and chop trees, each planning to collect as much wood as possible.
+//! The [Demo] is driven from JavaScript, see `www/index.html`, and also runs natively.
+
+use npc_engine_core::{ActiveTask, ActiveTasks, AgentId, IdleTask, MCTSConfiguration, MCTS};
+use npc_engine_utils::{Coord2D, ExecutionQueue, ExecutorState, ReplayFile, ReplayRecorder};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use wasm_bindgen::prelude::*;
+
+mod domain;
+mod replay_viewer;
+pub use domain::*;
+pub use replay_viewer::*;
+
+const WIDTH: i32 = 12;
+const HEIGHT: i32 = 8;
+const LUMBERJACK_COUNT: u32 = 2;
+const TREE_PROBABILITY: f64 = 0.3;
+
+/// Records the executed tasks into the replay of the demo.
+struct DemoExecutorState<'a>(&'a mut ReplayRecorder<LumberjacksDomain>);
+impl ExecutorState<LumberjacksDomain> for DemoExecutorState<'_> {
+    fn post_action_execute_hook(
+        &mut self,
+        state: &State,
+        diff: &Option<State>,
+        active_task: &ActiveTask<LumberjacksDomain>,
+        _queue: &mut ActiveTasks<LumberjacksDomain>,
+    ) {
+        self.0.record_execution(state, diff, active_task);
+    }
+}
+
+/// A forest with lumberjacks, stepped and rendered from JavaScript.
+#[wasm_bindgen]
+pub struct Demo {
+    mcts_config: MCTSConfiguration,
+    state: State,
+    queue: ExecutionQueue<LumberjacksDomain>,
+    tick: u64,
+    recorder: ReplayRecorder<LumberjacksDomain>,
+}
+
+#[wasm_bindgen]
+impl Demo {
+    /// Creates a random forest from `seed`, with lumberjacks in its left column.
+    #[wasm_bindgen(constructor)]
+    pub fn new(seed: u32) -> Demo {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed as u64);
+        let mut state = State {
+            width: WIDTH,
+            height: HEIGHT,
+            trees: (0..WIDTH * HEIGHT)
+                .map(|index| index % WIDTH != 0 && rng.gen_bool(TREE_PROBABILITY))
+                .collect(),
+            lumberjacks: Default::default(),
+        };
+        for id in 0..LUMBERJACK_COUNT {
+            let lumberjack = Lumberjack {
+                position: Coord2D::new(0, (id as i32 * 2 + 1) % HEIGHT),
+                wood: 0,
+            };
+            state.lumberjacks.insert(AgentId(id), lumberjack);
+        }
+        let mut queue = ExecutionQueue::new(Default::default());
+        for agent in state.lumberjacks.keys() {
+            queue.queue_task(0, *agent, Box::new(IdleTask), &state);
+        }
+        let mcts_config = MCTSConfiguration {
+            allow_invalid_tasks: false,
+            visits: 300,
+            depth: 12,
+            exploration: 1.414,
+            discount_hl: 8.,
+            seed: Some(seed as u64),
+            planning_task_duration: None,
+        };
+        let recorder = ReplayRecorder::new(state.clone(), &mcts_config);
+        Demo {
+            mcts_config,
+            state,
+            queue,
+            tick: 0,
+            recorder,
+        }
+    }
+
+    /// Executes the tasks ending at the current tick, plans for the agents without a subsequent task, and advances the tick.
+    pub fn step(&mut self) {
+        let mut executor_state = DemoExecutorState(&mut self.recorder);
+        loop {
+            match self.queue.task_queue().iter().next() {
+                Some(active_task) if active_task.end <= self.tick => {}
+                _ => break,
+            }
+            let active_task = self.queue.pop_first_task();
+            let active_agent = active_task.agent;
+            let tick = active_task.end;
+            let (diff, new_task) =
+                self.queue
+                    .execute_task(&active_task, &self.state, &mut executor_state, |_| {});
+            if let Some(diff) = diff {
+                self.state = diff;
+            }
+            let new_task = new_task.unwrap_or_else(|| {
+                let mut mcts = MCTS::<LumberjacksDomain>::new_with_tasks(
+                    self.state.clone(),
+                    active_agent,
+                    tick,
+                    self.queue.task_queue().clone(),
+                    self.mcts_config.clone(),
+                    executor_state.create_state_value_estimator(),
+                    None,
+                );
+                let task = mcts.run().unwrap_or_else(|| Box::new(IdleTask));
+                executor_state.0.record_planning(&mcts);
+                task
+            });
+            self.queue.queue_chosen_task(
+                tick,
+                active_agent,
+                new_task,
+                &self.state,
+                &mut executor_state,
+            );
+        }
+        self.tick += 1;
+    }
+
+    /// Returns the forest as text, one line per row.
+    pub fn render(&self) -> String {
+        self.state.render()
+    }
+
+    /// Returns the replay of the steps so far, as a JSON replay file with a keyframe every 16 steps.
+    pub fn replay_json(&self) -> String {
+        ReplayFile::new(
+            LumberjacksDomain::REPLAY_DOMAIN,
+            self.recorder.replay().clone(),
+        )
+        .with_metadata("game", "lumberjacks-web")
+        .with_keyframes::<LumberjacksDomain>(16)
+        .to_json_string()
+    }
+
+    /// Returns the current tick.
+    pub fn tick(&self) -> u32 {
+        self.tick as u32
+    }
+
+    /// Returns the wood collected by all lumberjacks.
+    pub fn wood(&self) -> u32 {
+        self.state
+            .lumberjacks
+            .values()
+            .map(|lumberjack| lumberjack.wood)
+            .sum()
+    }
+
+    /// Returns the number of remaining trees.
+    pub fn trees(&self) -> u32 {
+        self.state.tree_count() as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Demo, ReplayViewer};
+
+    #[test]
+    fn lumberjacks_collect_wood() {
+        let mut demo = Demo::new(0);
+        let trees = demo.trees();
+        assert!(trees > 0);
+        for _ in 0..40 {
+            demo.step();
+        }
+        assert_eq!(demo.tick(), 40);
+        assert!(demo.wood() > 0);
+        // Every chopped tree becomes wood
+        assert_eq!(demo.trees() + demo.wood(), trees);
+        assert_eq!(demo.render().lines().count(), super::HEIGHT as usize);
+    }
+
+    #[test]
+    fn replays_are_viewed() {
+        let mut demo = Demo::new(1);
+        for _ in 0..20 {
+            demo.step();
+        }
+        let mut viewer = ReplayViewer::new(&demo.replay_json()).unwrap();
+        assert!(viewer.step_count() > 0);
+        assert_eq!(viewer.tick(), 0);
+        assert_eq!(viewer.render(), Demo::new(1).render());
+        viewer.seek(usize::MAX);
+        assert_eq!(viewer.step(), viewer.step_count());
+        assert_eq!(viewer.render(), demo.render());
+        assert!(viewer.last_task().is_some());
+        assert!(ReplayViewer::new("{}").is_err());
+    }
+}
@@ -0,0 +1,70 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use npc_engine_utils::ReplayFile;
+use wasm_bindgen::prelude::*;
+
+use crate::{LumberjackTask, LumberjacksDomain, State};
+
+/// A viewer of a replay file of this demo, seeking to any step from JavaScript.
+#[wasm_bindgen]
+pub struct ReplayViewer {
+    file: ReplayFile<State, LumberjackTask>,
+    step: usize,
+    state: State,
+}
+
+#[wasm_bindgen]
+impl ReplayViewer {
+    /// Loads a JSON replay file, such as one returned by [Demo::replay_json](crate::Demo::replay_json), at its first step.
+    #[wasm_bindgen(constructor)]
+    pub fn new(json: &str) -> Result<ReplayViewer, String> {
+        let file: ReplayFile<State, LumberjackTask> =
+            ReplayFile::from_json_str(json).map_err(|error| error.to_string())?;
+        file.check_domain(LumberjacksDomain::REPLAY_DOMAIN)
+            .map_err(|error| error.to_string())?;
+        let state = file.replay.initial_state.clone();
+        Ok(ReplayViewer {
+            file,
+            step: 0,
+            state,
+        })
+    }
+
+    /// Returns the number of executed tasks in the replay.
+    pub fn step_count(&self) -> usize {
+        self.file.step_count()
+    }
+
+    /// Shows the state after the first `step` executed tasks.
+    pub fn seek(&mut self, step: usize) {
+        self.step = step.min(self.file.step_count());
+        self.state = self.file.state_at_step::<LumberjacksDomain>(self.step);
+    }
+
+    /// Returns the step shown.
+    pub fn step(&self) -> usize {
+        self.step
+    }
+
+    /// Returns the tick of the last executed task shown, 0 at the first step.
+    pub fn tick(&self) -> u32 {
+        match self.step {
+            0 => 0,
+            step => self.file.replay.steps[step - 1].tick as u32,
+        }
+    }
+
+    /// Returns the forest shown as text, one line per row.
+    pub fn render(&self) -> String {
+        self.state.render()
+    }
+
+    /// Returns the task executed last, in its debug format, if any.
+    pub fn last_task(&self) -> Option<String> {
+        let step = self.file.replay.steps.get(self.step.checked_sub(1)?)?;
+        Some(format!("{} {:?}", step.agent, step.task))
+    }
+}
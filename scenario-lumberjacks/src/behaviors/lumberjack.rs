@@ -3,16 +3,15 @@
  *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
  */
 
-use std::cmp::Ordering;
-use std::collections::BinaryHeap;
 use std::fmt;
 
 use npc_engine_core::{Behavior, Context, Task};
-use npc_engine_utils::DIRECTIONS;
+use npc_engine_utils::{FlowField, DIRECTIONS};
 
 use crate::{
-    apply_direction, config, from_direction, Barrier, Chop, Lumberjacks, Map2D, Move, Plant,
-    Refill, Wait, Water, WorldState,
+    apply_direction, climb, config, from_direction, walking_steps, Accept, Barrier, Bridge, Build,
+    Chop, Cross, Deliver, Deposit, Eat, Extinguish, Fetch, Found, Lumberjacks, Move, MoveTo, Offer,
+    Plant, Refill, Repair, Rest, Sell, Wait, Water, WorldState, WORLD_AGENT_ID,
 };
 
 pub struct Lumberjack;
@@ -23,146 +22,9 @@ impl fmt::Display for Lumberjack {
     }
 }
 
-#[derive(Copy, Clone, Eq, PartialEq)]
-struct WavefrontState {
-    cost: u16,
-    position: (usize, usize),
-}
-
-impl Ord for WavefrontState {
-    fn cmp(&self, other: &WavefrontState) -> Ordering {
-        other
-            .cost
-            .cmp(&self.cost)
-            .then_with(|| self.position.cmp(&other.position))
-    }
-}
-
-impl PartialOrd for WavefrontState {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
-    }
-}
-
-fn wavefront_expansion(
-    pathfindable: impl Fn(usize, usize) -> bool,
-    (start_x, start_y): (usize, usize),
-    radius: usize,
-) -> Map2D<u16> {
-    let mut heap = BinaryHeap::new();
-
-    let extent = radius * 2 + 1;
-    let mut gradient = Map2D::new_square(extent, u16::MAX);
-
-    heap.push(WavefrontState {
-        cost: 0,
-        position: (start_x, start_y),
-    });
-
-    while let Some(WavefrontState {
-        cost,
-        position: (x, y),
-    }) = heap.pop()
-    {
-        let gradient_value = gradient
-            .get_mut(x + radius - start_x, y + radius - start_y)
-            .unwrap();
-
-        if *gradient_value <= cost {
-            continue;
-        }
-
-        *gradient_value = cost;
-
-        // X lower bound
-        if x + radius >= start_x + 1 && pathfindable(x - 1, y) {
-            heap.push(WavefrontState {
-                cost: cost + 1,
-                position: (x - 1, y),
-            });
-        }
-
-        // X upper bound
-        if x < start_x + radius && pathfindable(x + 1, y) {
-            heap.push(WavefrontState {
-                cost: cost + 1,
-                position: (x + 1, y),
-            });
-        }
-
-        // Y lower bound
-        if y + radius >= start_y + 1 && pathfindable(x, y - 1) {
-            heap.push(WavefrontState {
-                cost: cost + 1,
-                position: (x, y - 1),
-            });
-        }
-
-        // Y upper bound
-        if y < start_y + radius && pathfindable(x, y + 1) {
-            heap.push(WavefrontState {
-                cost: cost + 1,
-                position: (x, y + 1),
-            });
-        }
-    }
-
-    gradient
-}
-
-fn wavefront_pathfind(
-    gradient: &Map2D<u16>,
-    (agent_x, agent_y): (usize, usize),
-    (mut target_x, mut target_y): (usize, usize),
-) -> Option<Vec<(usize, usize)>> {
-    assert_eq!(gradient.width() % 2, 1);
-    assert_eq!(gradient.width(), gradient.height());
-
-    let radius = (gradient.width() - 1) / 2;
-
-    let mut path = vec![(target_x, target_y)];
-
-    while target_x != agent_x || target_y != agent_y {
-        let mut best_cost = u16::MAX;
-        let (mut best_x, mut best_y) = (0, 0);
-
-        let mut update_best = |x, y| {
-            if let Some(&cost) = gradient.get(x + radius - agent_x, y + radius - agent_y) {
-                if cost < best_cost {
-                    best_cost = cost;
-                    best_x = x;
-                    best_y = y;
-                }
-            }
-        };
-
-        if target_x > 0 {
-            update_best(target_x - 1, target_y);
-        }
-        update_best(target_x + 1, target_y);
-
-        if target_y > 0 {
-            update_best(target_x, target_y - 1);
-        }
-        update_best(target_x, target_y + 1);
-
-        if best_cost != u16::MAX {
-            path.push((best_x, best_y));
-            target_x = best_x;
-            target_y = best_y;
-        } else {
-            return None;
-        }
-    }
-
-    path.reverse();
-
-    Some(path)
-}
-
 impl Behavior<Lumberjacks> for Lumberjack {
-    fn is_valid(&self, _ctx: Context<Lumberjacks>) -> bool {
-        true
+    fn is_valid(&self, ctx: Context<Lumberjacks>) -> bool {
+        ctx.agent != WORLD_AGENT_ID && ctx.state_diff.predator_kind(ctx.agent).is_none()
     }
     fn add_own_tasks(
         &self,
@@ -175,49 +37,20 @@ impl Behavior<Lumberjacks> for Lumberjack {
         if let Some((x, y)) = state_diff.find_agent(agent) {
             if config().agents.tasks {
                 // Movement
-                let mut wavefront = None;
+                let field = FlowField::new([(x, y)], |from| walking_steps(&state_diff, from));
 
                 state_diff.points_of_interest(|target_x, target_y| {
-                    if wavefront.is_none() {
-                        wavefront = Some(wavefront_expansion(
-                            |x: usize, y: usize| {
-                                state_diff
-                                    .get_tile(x as _, y as _)
-                                    .map(|tile| tile.is_pathfindable())
-                                    .unwrap_or_default()
-                            },
-                            (x as _, y as _),
-                            config().agents.snapshot_radius,
-                        ));
-                    }
-
-                    let wavefront = wavefront.as_mut().unwrap();
-
-                    if !(target_x == x && target_y == y) {
-                        if let Some(path) = wavefront_pathfind(
-                            wavefront,
-                            (x as _, y as _),
-                            (target_x as _, target_y as _),
-                        ) {
-                            let task = Move {
-                                path: path
-                                    .windows(2)
-                                    .map(|slice| {
-                                        let start = slice[0];
-                                        let end = slice[1];
-                                        from_direction(
-                                            (start.0 as _, start.1 as _),
-                                            (end.0 as _, end.1 as _),
-                                        )
-                                    })
-                                    .collect(),
-                                x: target_x as _,
-                                y: target_y as _,
-                            };
-
-                            if task.is_valid(ctx) {
-                                tasks.push(Box::new(task));
-                            }
+                    if let Some((first_x, first_y)) = field.first_step((target_x, target_y)) {
+                        if state_diff
+                            .get_tile(first_x, first_y)
+                            .map(|tile| tile.is_walkable())
+                            .unwrap_or(false)
+                        {
+                            tasks.push(Box::new(MoveTo {
+                                x: target_x,
+                                y: target_y,
+                                direction: from_direction((x, y), (first_x, first_y)),
+                            }));
                         }
                     }
                 });
@@ -229,6 +62,7 @@ impl Behavior<Lumberjacks> for Lumberjack {
                         .get_tile(adjacent.0, adjacent.1)
                         .map(|tile| tile.is_walkable())
                         .unwrap_or(false)
+                        && climb(&state_diff, (x, y), adjacent).is_some()
                     {
                         tasks.push(Box::new(Move {
                             path: vec![direction],
@@ -246,6 +80,24 @@ impl Behavior<Lumberjacks> for Lumberjack {
                 }
             }
 
+            // Tools
+            if config().features.tools {
+                if Repair.is_valid(ctx) {
+                    tasks.push(Box::new(Repair));
+                }
+                if Fetch.is_valid(ctx) {
+                    tasks.push(Box::new(Fetch));
+                }
+                if Deposit.is_valid(ctx) {
+                    tasks.push(Box::new(Deposit));
+                }
+            }
+
+            // Market
+            if config().features.market && Sell.is_valid(ctx) {
+                tasks.push(Box::new(Sell));
+            }
+
             // Barriers
             if config().features.barriers && state_diff.get_inventory(agent) > 0 {
                 for direction in DIRECTIONS {
@@ -255,19 +107,72 @@ impl Behavior<Lumberjacks> for Lumberjack {
                 }
             }
 
-            // Watering
-            if config().features.watering {
+            // Bridges
+            for direction in DIRECTIONS {
+                if (Cross { direction }).is_valid(ctx) {
+                    tasks.push(Box::new(Cross { direction }));
+                }
+                if config().features.bridges && (Bridge { direction }).is_valid(ctx) {
+                    tasks.push(Box::new(Bridge { direction }));
+                }
+            }
+
+            // Construction
+            if config().features.construction {
+                for direction in DIRECTIONS {
+                    if (Found { direction }).is_valid(ctx) {
+                        tasks.push(Box::new(Found { direction }));
+                    }
+                    if (Deliver { direction }).is_valid(ctx) {
+                        tasks.push(Box::new(Deliver { direction }));
+                    }
+                    if (Build { direction }).is_valid(ctx) {
+                        tasks.push(Box::new(Build { direction }));
+                    }
+                }
+            }
+
+            // Watering and firefighting
+            if config().features.watering || config().features.fire_spread {
                 if state_diff.get_water(agent) {
                     for direction in DIRECTIONS {
-                        if (Water { direction }.is_valid(ctx)) {
+                        if config().features.watering && (Water { direction }.is_valid(ctx)) {
                             tasks.push(Box::new(Water { direction }));
                         }
+                        if config().features.fire_spread && (Extinguish { direction }.is_valid(ctx))
+                        {
+                            tasks.push(Box::new(Extinguish { direction }));
+                        }
                     }
                 } else if Refill.is_valid(ctx) {
                     tasks.push(Box::new(Refill))
                 }
             }
 
+            // Trading
+            if config().features.trading {
+                for direction in DIRECTIONS {
+                    if (Offer { direction }).is_valid(ctx) {
+                        tasks.push(Box::new(Offer { direction }));
+                    }
+                    if (Accept { direction }).is_valid(ctx) {
+                        tasks.push(Box::new(Accept { direction }));
+                    }
+                }
+            }
+
+            // Needs
+            if Rest.is_valid(ctx) {
+                tasks.push(Box::new(Rest));
+            }
+            if config().features.needs {
+                for direction in DIRECTIONS {
+                    if (Eat { direction }).is_valid(ctx) {
+                        tasks.push(Box::new(Eat { direction }));
+                    }
+                }
+            }
+
             // Planting
             if config().features.planting && state_diff.get_inventory(agent) > 0 {
                 for direction in DIRECTIONS {
@@ -5,6 +5,10 @@
 
 mod human;
 mod lumberjack;
+mod predator;
+mod world;
 
 pub use human::*;
 pub use lumberjack::*;
+pub use predator::*;
+pub use world::*;
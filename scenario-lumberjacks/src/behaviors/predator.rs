@@ -0,0 +1,73 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::fmt;
+
+use macroquad::color::Color;
+use npc_engine_core::{Behavior, Context, Task};
+use npc_engine_utils::DIRECTIONS;
+use serde::{Deserialize, Serialize};
+
+use crate::{config, Flee, Hunt, Lumberjacks, Roam, Wait, WorldState};
+
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PredatorKind {
+    Bear,
+    Wolf,
+}
+
+impl PredatorKind {
+    /// The wood a hunt of this predator makes a lumberjack drop.
+    pub fn bite(&self) -> isize {
+        match self {
+            PredatorKind::Bear => config().predators.bear_bite,
+            PredatorKind::Wolf => config().predators.wolf_bite,
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            PredatorKind::Bear => Color::from_rgba(128, 64, 0, 255),
+            PredatorKind::Wolf => Color::from_rgba(128, 128, 128, 255),
+        }
+    }
+}
+
+pub struct Predator;
+
+impl fmt::Display for Predator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Predator")
+    }
+}
+
+impl Behavior<Lumberjacks> for Predator {
+    fn is_valid(&self, ctx: Context<Lumberjacks>) -> bool {
+        ctx.state_diff.predator_kind(ctx.agent).is_some()
+    }
+
+    fn add_own_tasks(
+        &self,
+        ctx: Context<Lumberjacks>,
+        tasks: &mut Vec<Box<dyn Task<Lumberjacks>>>,
+    ) {
+        for direction in DIRECTIONS {
+            if (Hunt { direction }).is_valid(ctx) {
+                tasks.push(Box::new(Hunt { direction }));
+            }
+            if (Flee { direction }).is_valid(ctx) {
+                tasks.push(Box::new(Flee { direction }));
+            }
+            if (Roam { direction }).is_valid(ctx) {
+                tasks.push(Box::new(Roam { direction }));
+            }
+        }
+
+        if config().features.waiting || tasks.is_empty() {
+            tasks.push(Box::new(Wait));
+        }
+    }
+}
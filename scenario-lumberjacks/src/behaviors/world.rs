@@ -0,0 +1,268 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use npc_engine_core::{AgentId, Behavior, Context, Task};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+use crate::{
+    config, EventConfig, EventKind, Lumberjacks, Regrow, Reprice, Spread, Tile, WorldGlobalState,
+};
+
+/// The world agent, which is not on the map and regrows trees when the regrowth feature is enabled,
+/// spreads fires when the fire spread feature is, and sets the wood price when the market one is.
+/// It also plays the scripted events of the configuration.
+pub const WORLD_AGENT_ID: AgentId = AgentId(u32::MAX);
+
+pub struct WorldBehavior;
+
+/// What the scripted events of a turn did to the agents.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Surprises {
+    /// The agents that arrived, in the order of their ids
+    pub arrived: Vec<AgentId>,
+    /// The agents that see a tile the events changed, whose plans may not hold anymore
+    pub affected: BTreeSet<AgentId>,
+}
+
+impl WorldBehavior {
+    /// Applies the events of `turn` to the world.
+    ///
+    /// Unlike the tasks of the world agent, the events are never simulated by the planners,
+    /// so they surprise the agents, who must replan.
+    pub fn apply_events(
+        world: &mut WorldGlobalState,
+        events: &[EventConfig],
+        turn: usize,
+        seed: u64,
+    ) -> Surprises {
+        let mut rng = StdRng::seed_from_u64(seed ^ turn as u64);
+        let mut surprises = Surprises::default();
+        let mut changed = Vec::new();
+        for event in events.iter().filter(|event| event.turn == turn) {
+            match event.kind {
+                EventKind::Storm { fraction } => {
+                    let fallen = world.storm(fraction, &mut rng);
+                    println!("turn {}: a storm knocked down {} trees", turn, fallen.len());
+                    changed.extend(fallen);
+                }
+                EventKind::Arrival { agents } => {
+                    let mut empty = world
+                        .map
+                        .iter()
+                        .filter(|(_, _, tile)| *tile == Tile::Empty)
+                        .map(|(x, y, _)| (x, y))
+                        .collect::<Vec<_>>();
+                    empty.shuffle(&mut rng);
+                    for (x, y) in empty.into_iter().take(agents) {
+                        surprises.arrived.push(world.add_agent(x, y, None));
+                    }
+                    println!("turn {}: {} lumberjacks arrived", turn, agents);
+                }
+                EventKind::Wildfire { fires } => {
+                    let ignited = world.ignite(fires, &mut rng);
+                    println!(
+                        "turn {}: a wildfire set {} trees on fire",
+                        turn,
+                        ignited.len()
+                    );
+                    changed.extend(ignited);
+                }
+            }
+        }
+
+        // An agent sees the tiles of its planning window
+        let radius = config().agents.snapshot_radius;
+        surprises.affected = world
+            .map
+            .iter()
+            .filter_map(|(x, y, tile)| match tile {
+                Tile::Agent(agent) => Some((agent, x, y)),
+                _ => None,
+            })
+            .filter(|(agent, x, y)| {
+                !surprises.arrived.contains(agent)
+                    && changed.iter().any(|(other_x, other_y)| {
+                        x.abs_diff(*other_x) <= radius && y.abs_diff(*other_y) <= radius
+                    })
+            })
+            .map(|(agent, _, _)| agent)
+            .collect();
+
+        surprises
+    }
+}
+
+impl fmt::Display for WorldBehavior {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "World")
+    }
+}
+
+impl Behavior<Lumberjacks> for WorldBehavior {
+    fn is_valid(&self, ctx: Context<Lumberjacks>) -> bool {
+        ctx.agent == WORLD_AGENT_ID
+    }
+
+    fn add_own_tasks(
+        &self,
+        _ctx: Context<Lumberjacks>,
+        tasks: &mut Vec<Box<dyn Task<Lumberjacks>>>,
+    ) {
+        if config().features.regrowth {
+            tasks.push(Box::new(Regrow));
+        }
+        if config().features.fire_spread {
+            tasks.push(Box::new(Spread));
+        }
+        if config().features.market {
+            tasks.push(Box::new(Reprice));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU8;
+
+    use npc_engine_core::{
+        AgentId, Behavior, Context, ContextMut, StateDiffRef, StateDiffRefMut, Task,
+    };
+    use npc_engine_utils::GlobalDomain;
+
+    use super::{Surprises, WorldBehavior, WORLD_AGENT_ID};
+    use crate::{
+        testing, EventConfig, EventKind, Lumberjacks, Market, Regrow, Reprice, Spread, Tile,
+        WorldDiff, WorldGlobalState, WorldState,
+    };
+
+    fn tree(height: u8) -> Tile {
+        Tile::Tree(NonZeroU8::new(height).unwrap())
+    }
+
+    // Executes a task of the world agent, returning the tiles of the map and the market after it
+    fn execute(world: &WorldGlobalState, task: &dyn Task<Lumberjacks>) -> (Vec<Tile>, Market) {
+        let state = Lumberjacks::derive_local_state(world, WORLD_AGENT_ID);
+        let mut diff = WorldDiff::default();
+        let ctx = ContextMut::new(0, StateDiffRefMut::new(&state, &mut diff), WORLD_AGENT_ID);
+        assert!(task.execute(ctx).is_none());
+        let state_diff = StateDiffRef::<Lumberjacks>::new(&state, &diff);
+        let tiles = (0..world.map.height as isize)
+            .flat_map(|y| (0..world.map.width as isize).map(move |x| (x, y)))
+            .map(|(x, y)| state_diff.get_tile(x, y).unwrap())
+            .collect();
+        (tiles, state_diff.get_market())
+    }
+
+    #[test]
+    fn world_agent_has_the_enabled_tasks() {
+        let world = testing::world(vec![vec![Tile::Empty; 3]; 3]);
+        let state = Lumberjacks::derive_local_state(&world, WORLD_AGENT_ID);
+        let diff = WorldDiff::default();
+        let ctx = Context::new(0, StateDiffRef::new(&state, &diff), WORLD_AGENT_ID);
+        assert!(WorldBehavior.is_valid(ctx));
+        let mut tasks = Vec::new();
+        WorldBehavior.add_own_tasks(ctx, &mut tasks);
+        let expected: Vec<Box<dyn Task<Lumberjacks>>> =
+            vec![Box::new(Regrow), Box::new(Spread), Box::new(Reprice)];
+        assert_eq!(tasks, expected);
+    }
+
+    #[test]
+    fn regrow_grows_trees_up_to_the_tree_height() {
+        let world = testing::world(vec![vec![tree(1), tree(3), Tile::Empty]]);
+        let (tiles, _) = execute(&world, &Regrow);
+        assert_eq!(tiles, vec![tree(2), tree(3), Tile::Empty]);
+    }
+
+    #[test]
+    fn spread_ignites_adjacent_trees_and_burns_out() {
+        let world = testing::world(vec![
+            vec![tree(1), Tile::Empty, Tile::Empty],
+            vec![Tile::Empty, Tile::Empty, tree(2)],
+            vec![Tile::Empty, Tile::Empty, Tile::Fire],
+        ]);
+        let (tiles, _) = execute(&world, &Spread);
+        assert_eq!(
+            tiles,
+            vec![
+                tree(1),
+                Tile::Empty,
+                Tile::Empty,
+                Tile::Empty,
+                Tile::Empty,
+                Tile::Fire,
+                Tile::Empty,
+                Tile::Empty,
+                Tile::Empty,
+            ]
+        );
+    }
+
+    #[test]
+    fn reprice_follows_the_wood_sold() {
+        let mut world = testing::world(vec![vec![Tile::Empty; 3]; 3]);
+        // nothing sold raises the price, and much sold lowers it down to the minimum
+        world.market = Market { price: 2, sold: 0 };
+        assert_eq!(execute(&world, &Reprice).1, Market { price: 3, sold: 0 });
+        world.market = Market { price: 2, sold: 6 };
+        assert_eq!(execute(&world, &Reprice).1, Market { price: 1, sold: 0 });
+    }
+
+    #[test]
+    fn storm_knocks_down_trees_at_its_turn() {
+        let mut world = testing::world(vec![vec![
+            Tile::Agent(AgentId(0)),
+            tree(2),
+            Tile::Empty,
+            Tile::Empty,
+            Tile::Empty,
+            tree(1),
+            Tile::Empty,
+            Tile::Empty,
+            Tile::Empty,
+            Tile::Agent(AgentId(1)),
+        ]]);
+        let events = [EventConfig {
+            turn: 3,
+            kind: EventKind::Storm { fraction: 1. },
+        }];
+        let surprises = WorldBehavior::apply_events(&mut world, &events, 2, 0);
+        assert_eq!(surprises, Surprises::default());
+        assert_eq!(world.map.tree_count(), 2);
+        // only the first agent sees a tree fall
+        let surprises = WorldBehavior::apply_events(&mut world, &events, 3, 0);
+        assert_eq!(world.map.tree_count(), 0);
+        assert!(surprises.arrived.is_empty());
+        assert_eq!(
+            surprises.affected.into_iter().collect::<Vec<_>>(),
+            vec![AgentId(0)]
+        );
+    }
+
+    #[test]
+    fn arrival_adds_agents_on_empty_tiles() {
+        let mut world = testing::world(vec![vec![
+            Tile::Agent(AgentId(0)),
+            Tile::Empty,
+            Tile::Empty,
+        ]]);
+        let events = [EventConfig {
+            turn: 0,
+            kind: EventKind::Arrival { agents: 2 },
+        }];
+        let surprises = WorldBehavior::apply_events(&mut world, &events, 0, 0);
+        assert_eq!(surprises.arrived, vec![AgentId(1), AgentId(2)]);
+        assert!(surprises.affected.is_empty());
+        assert_eq!(world.inventory.0.len(), 3);
+        for agent in surprises.arrived {
+            assert!(world.find_agent(agent).is_some());
+        }
+    }
+}
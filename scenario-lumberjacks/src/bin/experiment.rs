@@ -11,7 +11,7 @@ use std::process::{Command, Stdio};
 use clap::{App, Arg};
 use serde_json::Value;
 
-use lumberjacks::Experiment;
+use lumberjacks::{Experiment, BATCH_METRICS_HEADER};
 
 fn override_config(value: &mut Value, mut _override: Value) {
     match (value, &mut _override) {
@@ -151,14 +151,32 @@ fn main() {
 
                 serde_json::to_writer(child.stdin.take().unwrap(), &config).unwrap();
 
-                child
+                (name, child)
             })
             .collect::<Vec<_>>(),
         _ => unreachable!("Trial is not a valid json object!"),
     };
 
+    let names = children
+        .iter()
+        .map(|(name, _)| name.clone())
+        .collect::<Vec<_>>();
     children
         .into_iter()
-        .fold(Ok(()), |res, mut child| res.and(child.wait().map(|_| ())))
+        .try_for_each(|(_, mut child)| child.wait().map(|_| ()))
         .expect("some experiment didn't exit successfully");
+
+    // Concatenate the metrics of all trials, for analysing the sweep as a whole
+    let mut metrics = format!("trial,{}\n", BATCH_METRICS_HEADER);
+    for name in names {
+        let path = format!("{}/{}/metrics.csv", output, name);
+        match fs::read_to_string(&path) {
+            Ok(csv) => csv
+                .lines()
+                .skip(1)
+                .for_each(|line| metrics.push_str(&format!("{},{}\n", name, line))),
+            Err(e) => println!("Cannot read metrics {}: {}", path, e),
+        }
+    }
+    fs::write(format!("{}/metrics.csv", output), metrics).unwrap();
 }
@@ -3,18 +3,20 @@
  *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
  */
 
-use std::{env, f32, fs};
+use std::collections::BTreeMap;
+use std::{env, f32, fs, process};
 
-use ggez::conf::{WindowMode, WindowSetup};
-use ggez::graphics::Image;
-use ggez::ContextBuilder;
 use image::{png::PngDecoder, ImageDecoder};
+use macroquad::texture::Texture2D;
 
 use npc_engine_core::graphviz::set_graph_output_depth;
 use rand::{thread_rng, RngCore};
 use rayon::prelude::*;
 
-use lumberjacks::{batch, config, name, output_path, GameState, SPRITE_SIZE};
+use lumberjacks::{
+    batch, config, dump_batch_metrics, dump_team_summary, name, output_path, replay_path,
+    run_window, GameState, Graphics, ReplayViewer, SPRITE_SIZE,
+};
 
 const ASSETS: &[(&str, &[u8])] = &[
     (
@@ -113,6 +115,25 @@ const ASSETS: &[(&str, &[u8])] = &[
     ("Well", include_bytes!("../../assets/Well.png")),
 ];
 
+fn load_assets(_graphics: Graphics) -> BTreeMap<String, Texture2D> {
+    ASSETS
+        .iter()
+        .map(|(name, bytes)| {
+            let png = PngDecoder::new(<&[u8]>::clone(bytes)).unwrap();
+            let (width, height) = png.dimensions();
+
+            let mut rgba = vec![0u8; png.total_bytes() as _];
+
+            png.read_image(&mut rgba).unwrap();
+
+            (
+                (*name).to_owned(),
+                Texture2D::from_rgba8(width as _, height as _, &rgba),
+            )
+        })
+        .collect()
+}
+
 fn main() {
     env_logger::init();
 
@@ -133,11 +154,37 @@ fn main() {
     serde_json::to_writer_pretty(file, &info).unwrap();
 
     set_graph_output_depth(config().analytics.graphs_depth);
-    if batch() {
-        (0..config().batch.runs).into_par_iter().for_each(|run| {
+    if let Some(path) = replay_path() {
+        let viewer = match ReplayViewer::load(path) {
+            Ok(viewer) => viewer,
+            Err(e) => {
+                println!("{}", e);
+                process::exit(2);
+            }
+        };
+
+        let size = (
+            (2 * config().display.padding.0 + viewer.width()) as f32 * SPRITE_SIZE,
+            (2 * config().display.padding.1 + viewer.height()) as f32 * SPRITE_SIZE,
+        );
+        run_window(
+            format!("{} (replay)", name()),
+            size,
+            viewer,
+            |viewer, graphics| {
+                for (name, texture) in load_assets(graphics) {
+                    viewer.add_asset(name, texture);
+                }
+            },
+            |_, _| (),
+        );
+    } else if batch() {
+        let runs = (0..config().batch.runs).into_par_iter().map(|run| {
             let seed = config()
-                .mcts
+                .batch
                 .seed
+                .map(|seed| seed.wrapping_add(run as u64))
+                .or(config().mcts.seed)
                 .unwrap_or_else(|| thread_rng().next_u64());
             let mut state = GameState::new(config().display.interactive, Some(run), seed);
 
@@ -150,54 +197,54 @@ fn main() {
                 state.update(None);
             }
             state.dump_result();
+            state.save_state(&format!("{}state.json", state.output_dir()));
+            let winner = if config().features.teams {
+                state.dump_teams()
+            } else {
+                None
+            };
+            (winner, state.run_metrics())
         });
+        let (winners, metrics): (Vec<_>, Vec<_>) = runs.unzip();
+
+        dump_batch_metrics(&metrics);
+        if config().features.teams {
+            dump_team_summary(winners);
+        }
     } else {
         let seed = config()
             .mcts
             .seed
             .unwrap_or_else(|| thread_rng().next_u64());
-        let mut state = GameState::new(config().display.interactive, None, seed);
+        let state = GameState::new(config().display.interactive, None, seed);
         state.dump_run();
 
-        // Create game context
-        let (mut ctx, mut events) = ContextBuilder::new("lumberjacks", "Sven Knobloch")
-            .window_setup(WindowSetup {
-                title: name().clone(),
-                vsync: true,
-                ..Default::default()
-            })
-            .window_mode(WindowMode::default().dimensions(
-                (2 * config().display.padding.0 + state.width()) as f32 * SPRITE_SIZE,
-                (2 * config().display.padding.1 + state.height()) as f32 * SPRITE_SIZE,
-            ))
-            .build()
-            .unwrap();
-
-        // Load assets
-        for (name, bytes) in ASSETS {
-            let png = PngDecoder::new(<&[u8]>::clone(bytes)).unwrap();
-            let (width, height) = png.dimensions();
-
-            let mut rgba = vec![0u8; png.total_bytes() as _];
-
-            png.read_image(&mut rgba).unwrap();
-
-            state.add_asset(
-                (*name).to_owned(),
-                Image::from_rgba8(&mut ctx, width as _, height as _, &rgba).unwrap(),
-            );
-        }
-
-        // Screenshot of initial state
-        let dir = state.output_dir();
-        state.screenshot(&mut ctx, &format!("{}/start.png", dir));
-
-        // Run game
-        ggez::event::run(&mut ctx, &mut events, &mut state).unwrap();
-
-        // Screenshot of final state
-        state.screenshot(&mut ctx, &format!("{}/result.png", dir));
-
-        state.dump_result();
+        let size = (
+            (2 * config().display.padding.0 + state.width()) as f32 * SPRITE_SIZE,
+            (2 * config().display.padding.1 + state.height()) as f32 * SPRITE_SIZE,
+        );
+        run_window(
+            name().clone(),
+            size,
+            state,
+            |state, graphics| {
+                // Load assets
+                for (name, texture) in load_assets(graphics) {
+                    state.add_asset(name, texture);
+                }
+
+                // Screenshot of initial state
+                state.screenshot(graphics, &format!("{}/start.png", state.output_dir()));
+            },
+            |state, graphics| {
+                // Screenshot of final state
+                state.screenshot(graphics, &format!("{}/result.png", state.output_dir()));
+
+                state.dump_result();
+                if config().features.teams {
+                    state.dump_teams();
+                }
+            },
+        );
     }
 }
@@ -0,0 +1,59 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use macroquad::math::Rect;
+
+const MIN_ZOOM: f32 = 0.25;
+const MAX_ZOOM: f32 = 4.;
+
+/// The part of the world shown in the window, which can be panned and zoomed.
+#[derive(Clone, Copy, Debug)]
+pub struct Camera {
+    pub x: f32,
+    pub y: f32,
+    pub zoom: f32,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Camera {
+            x: 0.,
+            y: 0.,
+            zoom: 1.,
+        }
+    }
+}
+
+impl Camera {
+    // The screen coordinates seen through the camera, for a window showing `screen` at rest
+    pub fn view(&self, screen: Rect) -> Rect {
+        Rect::new(
+            screen.x + self.x,
+            screen.y + self.y,
+            screen.w / self.zoom,
+            screen.h / self.zoom,
+        )
+    }
+
+    // The world coordinates under a point of the window
+    pub fn to_world(&self, x: f32, y: f32) -> (f32, f32) {
+        (self.x + x / self.zoom, self.y + y / self.zoom)
+    }
+
+    // Drags the world by a distance in window pixels
+    pub fn pan(&mut self, dx: f32, dy: f32) {
+        self.x -= dx / self.zoom;
+        self.y -= dy / self.zoom;
+    }
+
+    // Zooms in for positive steps and out for negative ones, keeping the world under the point
+    // (x, y) of the window in place
+    pub fn zoom(&mut self, steps: f32, x: f32, y: f32) {
+        let (world_x, world_y) = self.to_world(x, y);
+        self.zoom = (self.zoom * 1.25f32.powf(steps)).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.x = world_x - x / self.zoom;
+        self.y = world_y - y / self.zoom;
+    }
+}
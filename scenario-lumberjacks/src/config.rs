@@ -15,18 +15,24 @@ use serde_json::Value;
 use npc_engine_core::{AgentId, StateDiffRef};
 
 use crate::fitnesses;
-use crate::Lumberjacks;
+use crate::{Lumberjacks, Personality, TeamStrategy};
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct BatchConfig {
     #[serde(default)]
     pub runs: usize,
+    /// The seed of the first run, the following runs counting up from it, random if unset
+    #[serde(default)]
+    pub seed: Option<u64>,
 }
 
 impl Default for BatchConfig {
     fn default() -> Self {
-        BatchConfig { runs: 1 }
+        BatchConfig {
+            runs: 1,
+            seed: None,
+        }
     }
 }
 
@@ -34,6 +40,47 @@ impl Default for BatchConfig {
 #[serde(rename_all = "kebab-case")]
 pub enum GeneratorType {
     File { path: String },
+    Procedural(ProceduralConfig),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct ProceduralConfig {
+    pub width: usize,
+    pub height: usize,
+    /// If none, the seed of the run is used, so that every run of a batch has its own map
+    pub seed: Option<u64>,
+    pub forest_density: f32,
+    pub rivers: usize,
+    pub clearings: usize,
+    pub clearing_radius: usize,
+    pub agents: usize,
+    pub wells: usize,
+    pub bushes: usize,
+    /// Cones of higher ground, the rivers keeping to the ground level
+    pub hills: usize,
+    pub hill_height: u8,
+    pub hill_radius: usize,
+}
+
+impl Default for ProceduralConfig {
+    fn default() -> Self {
+        ProceduralConfig {
+            width: 24,
+            height: 16,
+            seed: None,
+            forest_density: 0.3,
+            rivers: 1,
+            clearings: 3,
+            clearing_radius: 2,
+            agents: 2,
+            wells: 1,
+            bushes: 0,
+            hills: 0,
+            hill_height: 3,
+            hill_radius: 4,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -57,11 +104,15 @@ pub struct AgentsConfig {
     pub snapshot_radius: usize,
     pub tasks: bool,
     pub plan_others: bool,
+    /// The agent controlled by the player, if any, outside of batch mode
+    pub human: Option<usize>,
     #[serde(
         deserialize_with = "behavior_deserializer",
         serialize_with = "behavior_serializer"
     )]
     pub(crate) behaviors: Behaviors,
+    /// The personalities of the lumberjacks, by agent index, the others being neutral
+    pub personalities: HashMap<usize, Personality>,
 }
 
 impl fmt::Debug for AgentsConfig {
@@ -71,6 +122,7 @@ impl fmt::Debug for AgentsConfig {
             .field("snapshot_radius", &self.snapshot_radius)
             .field("tasks", &self.tasks)
             .field("plan_others", &self.plan_others)
+            .field("human", &self.human)
             .field(
                 "behaviors",
                 &self
@@ -79,6 +131,7 @@ impl fmt::Debug for AgentsConfig {
                     .map(|(k, (v, _))| (*k, v.clone()))
                     .collect::<HashMap<_, _>>(),
             )
+            .field("personalities", &self.personalities)
             .finish()
     }
 }
@@ -134,7 +187,9 @@ impl Default for AgentsConfig {
             snapshot_radius: 10,
             tasks: false,
             plan_others: true,
+            human: None,
             behaviors: Default::default(),
+            personalities: Default::default(),
         }
     }
 }
@@ -142,11 +197,28 @@ impl Default for AgentsConfig {
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(default, rename_all = "kebab-case")]
 pub struct ActionWeightsConfig {
+    pub accept: f32,
     pub barrier: f32,
+    pub bridge: f32,
+    pub build: f32,
     pub chop: f32,
+    pub cross: f32,
+    pub deliver: f32,
+    pub deposit: f32,
+    pub eat: f32,
+    pub extinguish: f32,
+    pub fetch: f32,
+    pub flee: f32,
+    pub found: f32,
+    pub hunt: f32,
     pub r#move: f32,
+    pub offer: f32,
     pub plant: f32,
     pub refill: f32,
+    pub repair: f32,
+    pub rest: f32,
+    pub roam: f32,
+    pub sell: f32,
     pub wait: f32,
     pub water: f32,
 }
@@ -154,17 +226,54 @@ pub struct ActionWeightsConfig {
 impl Default for ActionWeightsConfig {
     fn default() -> Self {
         ActionWeightsConfig {
+            accept: 10.,
             barrier: 1.,
+            bridge: 1.,
+            build: 20.,
             chop: 20.,
+            cross: 10.,
+            deliver: 10.,
+            deposit: 20.,
+            eat: 10.,
+            extinguish: 20.,
+            fetch: 20.,
+            flee: 20.,
+            found: 1.,
+            hunt: 20.,
             r#move: 10.,
+            offer: 5.,
             plant: 1.,
             refill: 20.,
+            repair: 5.,
+            rest: 10.,
+            roam: 10.,
+            sell: 10.,
             wait: 1.,
             water: 20.,
         }
     }
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct PredatorsConfig {
+    pub bear_bite: isize,
+    pub wolf_bite: isize,
+    pub fire_radius: usize,
+    pub fire_penalty: f32,
+}
+
+impl Default for PredatorsConfig {
+    fn default() -> Self {
+        PredatorsConfig {
+            bear_bite: 2,
+            wolf_bite: 1,
+            fire_radius: 2,
+            fire_penalty: 5.,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(default, rename_all = "kebab-case")]
 pub struct MCTSConfig {
@@ -193,10 +302,243 @@ impl Default for MCTSConfig {
 #[serde(default, rename_all = "kebab-case")]
 pub struct FeaturesConfig {
     pub barriers: bool,
+    pub bridges: bool,
     pub teamwork: bool,
     pub watering: bool,
     pub planting: bool,
     pub waiting: bool,
+    pub regrowth: bool,
+    pub tools: bool,
+    pub trading: bool,
+    pub teams: bool,
+    pub day_night: bool,
+    pub construction: bool,
+    pub needs: bool,
+    pub fire_spread: bool,
+    pub market: bool,
+    pub terrain: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct EventConfig {
+    pub turn: usize,
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EventKind {
+    /// Knocks down each tree with the given probability
+    Storm { fraction: f32 },
+    /// Adds lumberjacks on random empty tiles
+    Arrival { agents: usize },
+    /// Sets random trees on fire
+    Wildfire { fires: usize },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct TeamsConfig {
+    pub orange: TeamStrategy,
+    pub yellow: TeamStrategy,
+}
+
+impl Default for TeamsConfig {
+    fn default() -> Self {
+        TeamsConfig {
+            orange: TeamStrategy::Shared,
+            yellow: TeamStrategy::Shared,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct ConstructionConfig {
+    pub cost: usize,
+    pub hut_value: f32,
+}
+
+impl Default for ConstructionConfig {
+    fn default() -> Self {
+        ConstructionConfig {
+            cost: 3,
+            hut_value: 5.,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct MarketConfig {
+    pub period: u64,
+    pub initial_price: u32,
+    pub min_price: u32,
+    pub max_price: u32,
+    /// The wood to sell in a period to lower the price by one coin
+    pub elasticity: u32,
+}
+
+impl Default for MarketConfig {
+    fn default() -> Self {
+        MarketConfig {
+            period: 5,
+            initial_price: 2,
+            min_price: 1,
+            max_price: 4,
+            elasticity: 5,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct FireConfig {
+    pub period: u64,
+    pub penalty: f32,
+}
+
+impl Default for FireConfig {
+    fn default() -> Self {
+        FireConfig {
+            period: 5,
+            penalty: 2.,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct TerrainConfig {
+    /// The largest difference of elevation that can be walked in one step
+    pub max_slope: u8,
+    /// The extra ticks it takes to climb one level of elevation
+    pub climb_cost: u64,
+}
+
+impl Default for TerrainConfig {
+    fn default() -> Self {
+        TerrainConfig {
+            max_slope: 1,
+            climb_cost: 1,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct NeedsConfig {
+    pub hunger_threshold: u64,
+    pub hunger_penalty: f32,
+    pub tiredness_threshold: u64,
+    pub tiredness_penalty: f32,
+}
+
+impl Default for NeedsConfig {
+    fn default() -> Self {
+        NeedsConfig {
+            hunger_threshold: 20,
+            hunger_penalty: 0.5,
+            tiredness_threshold: 30,
+            tiredness_penalty: 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct DayNightConfig {
+    pub day: u64,
+    pub night: u64,
+    pub energy: usize,
+}
+
+impl Default for DayNightConfig {
+    fn default() -> Self {
+        DayNightConfig {
+            day: 12,
+            night: 6,
+            energy: 8,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct TradingConfig {
+    pub price: usize,
+}
+
+impl Default for TradingConfig {
+    fn default() -> Self {
+        TradingConfig { price: 1 }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct ToolsConfig {
+    pub durability: u8,
+    pub capacity: usize,
+    pub repair_cost: usize,
+}
+
+impl Default for ToolsConfig {
+    fn default() -> Self {
+        ToolsConfig {
+            durability: 10,
+            capacity: 5,
+            repair_cost: 1,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct PlantingConfig {
+    pub forecast: f32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct RegrowthConfig {
+    pub period: u64,
+}
+
+impl Default for RegrowthConfig {
+    fn default() -> Self {
+        RegrowthConfig { period: 10 }
+    }
+}
+
+/// The weights of the terms of the value function of lumberjacks.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct ValueConfig {
+    /// Per wood carried and stored
+    pub wood: f32,
+    /// Per tile to the closest tree, as a penalty
+    pub distance: f32,
+    /// Per predator nearby, weighted by how close it is, as a penalty
+    pub safety: f32,
+    /// Per wood still standing around
+    pub sustainability: f32,
+    /// Per coin earned at the market
+    pub coins: f32,
+}
+
+impl Default for ValueConfig {
+    fn default() -> Self {
+        ValueConfig {
+            wood: 1.,
+            distance: 0.,
+            safety: 0.,
+            sustainability: 0.,
+            coins: 0.5,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -206,6 +548,7 @@ pub struct AnalyticsConfig {
     pub heatmaps: bool,
     pub graphs: bool,
     pub serialization: bool,
+    pub replay: bool,
     pub screenshot: bool,
     pub performance: bool,
     pub graphs_depth: usize,
@@ -217,6 +560,7 @@ impl Default for AnalyticsConfig {
             heatmaps: Default::default(),
             graphs: Default::default(),
             serialization: Default::default(),
+            replay: Default::default(),
             screenshot: Default::default(),
             performance: Default::default(),
             graphs_depth: get_graph_output_depth(),
@@ -261,6 +605,34 @@ pub struct Config {
     #[serde(default = "Default::default")]
     pub features: FeaturesConfig,
     #[serde(default = "Default::default")]
+    pub predators: PredatorsConfig,
+    #[serde(default = "Default::default")]
+    pub regrowth: RegrowthConfig,
+    #[serde(default = "Default::default")]
+    pub planting: PlantingConfig,
+    #[serde(default = "Default::default")]
+    pub tools: ToolsConfig,
+    #[serde(default = "Default::default")]
+    pub trading: TradingConfig,
+    #[serde(default = "Default::default")]
+    pub teams: TeamsConfig,
+    #[serde(default = "Default::default")]
+    pub day_night: DayNightConfig,
+    #[serde(default = "Default::default")]
+    pub construction: ConstructionConfig,
+    #[serde(default = "Default::default")]
+    pub needs: NeedsConfig,
+    #[serde(default = "Default::default")]
+    pub value: ValueConfig,
+    #[serde(default = "Default::default")]
+    pub fire: FireConfig,
+    #[serde(default = "Default::default")]
+    pub market: MarketConfig,
+    #[serde(default = "Default::default")]
+    pub terrain: TerrainConfig,
+    #[serde(default = "Default::default")]
+    pub events: Vec<EventConfig>,
+    #[serde(default = "Default::default")]
     pub analytics: AnalyticsConfig,
     #[serde(default = "Default::default")]
     pub display: DisplayConfig,
@@ -0,0 +1,76 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::fmt;
+
+use macroquad::input::KeyCode;
+
+use crate::{config, PredatorKind, Tile};
+
+/// What the map editor paints on the tile under the mouse.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Brush {
+    Empty,
+    Tree,
+    Impassable,
+    Well,
+    Water,
+    Bridge,
+    Shed,
+    Fire,
+    Bush,
+    Lumberjack,
+    Predator(PredatorKind),
+}
+
+impl Brush {
+    /// The brushes, selected by the number keys in that order, then minus and equals.
+    const KEYS: &'static [(KeyCode, Brush)] = &[
+        (KeyCode::Key1, Brush::Empty),
+        (KeyCode::Key2, Brush::Tree),
+        (KeyCode::Key3, Brush::Impassable),
+        (KeyCode::Key4, Brush::Well),
+        (KeyCode::Key5, Brush::Water),
+        (KeyCode::Key6, Brush::Bridge),
+        (KeyCode::Key7, Brush::Shed),
+        (KeyCode::Key8, Brush::Fire),
+        (KeyCode::Key9, Brush::Lumberjack),
+        (KeyCode::Key0, Brush::Predator(PredatorKind::Bear)),
+        (KeyCode::Minus, Brush::Predator(PredatorKind::Wolf)),
+        (KeyCode::Equal, Brush::Bush),
+    ];
+
+    pub fn from_key(keycode: KeyCode) -> Option<Brush> {
+        Brush::KEYS
+            .iter()
+            .find(|(key, _)| *key == keycode)
+            .map(|(_, brush)| *brush)
+    }
+
+    /// The tile painted by this brush, none for agents which need a new id.
+    pub fn tile(&self) -> Option<Tile> {
+        match self {
+            Brush::Empty => Some(Tile::Empty),
+            Brush::Tree => Some(Tile::Tree(config().map.tree_height)),
+            Brush::Impassable => Some(Tile::Impassable),
+            Brush::Well => Some(Tile::Well),
+            Brush::Water => Some(Tile::Water),
+            Brush::Bridge => Some(Tile::Bridge),
+            Brush::Shed => Some(Tile::Shed),
+            Brush::Fire => Some(Tile::Fire),
+            Brush::Bush => Some(Tile::Bush),
+            Brush::Lumberjack | Brush::Predator(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Brush {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Brush::Predator(kind) => write!(f, "{:?}", kind),
+            brush => write!(f, "{:?}", brush),
+        }
+    }
+}
@@ -3,9 +3,23 @@
  *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
  */
 
-use crate::{Lumberjacks, WorldState};
+use crate::{config, Lumberjacks, WorldState};
 use npc_engine_core::{AgentId, StateDiffRef};
 
 pub(crate) fn minimalist(state: StateDiffRef<Lumberjacks>, _agent: AgentId) -> f32 {
     -(state.trees().len() as f32)
 }
+
+pub(crate) fn predator(state: StateDiffRef<Lumberjacks>, agent: AgentId) -> f32 {
+    let near_fire = state.find_agent(agent).map_or(false, |(x, y)| {
+        state
+            .distance_to_fire(x, y, config().predators.fire_radius)
+            .is_some()
+    });
+    state.get_food(agent) as f32
+        - if near_fire {
+            config().predators.fire_penalty
+        } else {
+            0.
+        }
+}
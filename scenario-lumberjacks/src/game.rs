@@ -7,26 +7,34 @@ use std::collections::BTreeMap;
 
 use std::fs;
 use std::process;
+use std::time::Duration;
 
-use ggez::event;
-use ggez::event::EventHandler;
-use ggez::graphics::{Image, Text};
-use ggez::input::keyboard::KeyCode;
-use ggez::{graphics, input::keyboard};
-use ggez::{Context, GameResult};
+use macroquad::camera::set_default_camera;
+use macroquad::input::{
+    get_keys_pressed, is_key_down, is_mouse_button_down, mouse_position, mouse_wheel, KeyCode,
+    MouseButton,
+};
+use macroquad::math::Rect;
+use macroquad::texture::Texture2D;
+use macroquad::window::{clear_background, screen_height, screen_width};
 use npc_engine_core::ContextMut;
 use npc_engine_core::MCTSConfiguration;
-use npc_engine_core::{AgentId, Task, MCTS};
-use npc_engine_utils::GlobalDomain;
+use npc_engine_core::{
+    ActiveTask, AgentId, DefaultPolicyEstimator, IdleTask, StateDiffRef, Task, MCTS,
+};
+use npc_engine_utils::{Direction, GlobalDomain};
 
 use crate::WorldDiff;
 use crate::{
-    agency_metric_hook, branching_metric_hook, config, diff_memory_metric_hook,
-    features_metric_hook, graph_hook, heatmap_hook, islands_metric_hook,
-    node_edges_count_metric_hook, output_path, screenshot, screenshot_hook, time_metric_hook,
-    total_memory_metric_hook, working_dir, world_serialization_hook, AgentInventory, GeneratorType,
-    Lumberjacks, PostMCTSHookArgs, PostMCTSHookFn, PostWorldHookArgs, PostWorldHookFn,
-    PreWorldHookArgs, PreWorldHookFn, TileMap, WorldGlobalState, SPRITE_SIZE,
+    agency_metric_hook, apply_direction, background, batch, branching_metric_hook, config,
+    diff_memory_metric_hook, draw_label, features_metric_hook, graph_hook, heatmap_hook, is_night,
+    islands_metric_hook, load_path, map_origin, node_edges_count_metric_hook, output_path,
+    predicted_path, replay_hook, screenshot, screenshot_hook, set_view, time_metric_hook,
+    total_memory_metric_hook, working_dir, world_serialization_hook, AgentInventory, Brush, Camera,
+    Chop, DebugLayers, EventConfig, Frame, GeneratorType, Graphics, Lumberjacks, Move, Personality,
+    PostMCTSHookArgs, PostMCTSHookFn, PostWorldHookArgs, PostWorldHookFn, PreWorldHookArgs,
+    PreWorldHookFn, PredatorKind, RunMetrics, SavedState, Team, Tile, TileMap, Wait, WorldBehavior,
+    WorldGlobalState, WorldState, SPRITE_SIZE,
 };
 
 type PerWorldHooks = Vec<Box<dyn FnMut(PreWorldHookArgs)>>;
@@ -46,48 +54,42 @@ pub struct GameState {
     pre_world_hooks: PerWorldHooks,
     post_world_hooks: PostWorldHooks,
     post_mcts_hooks: PostMCTSHooks,
-    assets: BTreeMap<String, Image>,
+    assets: BTreeMap<String, Texture2D>,
+    // The brush of the map editor, if it is open
+    editor: Option<Brush>,
+    // The last turn of the agents busy with a slow task
+    busy: BTreeMap<AgentId, usize>,
+    // The task commanded by the player for their agent, until it is its turn
+    human_task: Option<Box<dyn Task<Lumberjacks>>>,
+    // The view of the map, and the debug layers drawn over it
+    camera: Camera,
+    layers: DebugLayers,
+    // Where the mouse was in the last frame, to drag the map
+    mouse: (f32, f32),
+    // The path each agent expected to walk when it last planned, for the plan layer
+    plans: BTreeMap<AgentId, Vec<(isize, isize)>>,
+    // The total time spent planning, and how many times
+    planning_time: Duration,
+    plannings: u32,
 }
 
 impl GameState {
     pub fn new(interactive: bool, run: Option<usize>, seed: u64) -> Self {
-        let mut agents = Vec::new();
-
-        let inventory = Default::default();
-        let map = match &config().map.generator {
-            GeneratorType::File { path, .. } => {
-                let file = match fs::OpenOptions::new().read(true).open(format!(
-                    "{}/{}",
-                    working_dir(),
-                    path
-                )) {
-                    Ok(file) => file,
-                    Err(e) => {
-                        println!("Cannot open map file {}: {}", path, e);
-                        process::exit(2);
-                    }
-                };
-
-                TileMap::from_io(&mut agents, &file)
+        let saved = match load_path().map(|path| SavedState::load(path)) {
+            Some(Ok(saved)) => saved,
+            Some(Err(e)) => {
+                println!("{}", e);
+                process::exit(2);
             }
+            None => SavedState {
+                turn: 0,
+                current_agent: 0,
+                world: GameState::generate_world(seed),
+                busy: BTreeMap::new(),
+            },
         };
-        agents.sort();
-
-        let mut world = WorldGlobalState {
-            actions: Default::default(),
-            inventory,
-            map,
-        };
-
-        for agent in &agents {
-            world.inventory.0.insert(
-                *agent,
-                AgentInventory {
-                    wood: 0,
-                    water: false,
-                },
-            );
-        }
+        // The agents are all those with an inventory, in order
+        let agents = saved.world.inventory.0.keys().copied().collect();
 
         let config = MCTSConfiguration {
             allow_invalid_tasks: false,
@@ -98,15 +100,22 @@ impl GameState {
             seed: Some(seed),
             ..Default::default()
         };
+        // Every personality must leave its agent with a planner that makes sense
+        for (agent, personality) in &crate::config().agents.personalities {
+            if let Err(e) = personality.planner_config(&config).validate() {
+                println!("invalid personality of agent {}: {}", agent, e);
+                process::exit(2);
+            }
+        }
         let objectives = BTreeMap::new();
 
         let mut state = GameState {
             interactive,
             seed,
-            current_agent: 0,
+            current_agent: saved.current_agent,
             run,
-            turn: 0,
-            world,
+            turn: saved.turn,
+            world: saved.world,
             agents,
             objectives,
             config,
@@ -114,6 +123,15 @@ impl GameState {
             post_world_hooks: Default::default(),
             post_mcts_hooks: Default::default(),
             assets: Default::default(),
+            editor: None,
+            busy: saved.busy,
+            human_task: None,
+            camera: Default::default(),
+            layers: Default::default(),
+            mouse: (0., 0.),
+            plans: BTreeMap::new(),
+            planning_time: Duration::ZERO,
+            plannings: 0,
         };
 
         state.register_hooks();
@@ -121,6 +139,49 @@ impl GameState {
         state
     }
 
+    // Creates the world from the configured map generator
+    fn generate_world(seed: u64) -> WorldGlobalState {
+        let mut agents = Vec::new();
+        let mut predators = BTreeMap::new();
+
+        let inventory = Default::default();
+        let map = match &config().map.generator {
+            GeneratorType::File { path, .. } => {
+                let file = match fs::OpenOptions::new().read(true).open(format!(
+                    "{}/{}",
+                    working_dir(),
+                    path
+                )) {
+                    Ok(file) => file,
+                    Err(e) => {
+                        println!("Cannot open map file {}: {}", path, e);
+                        process::exit(2);
+                    }
+                };
+
+                TileMap::from_io(&mut agents, &mut predators, &file)
+            }
+            GeneratorType::Procedural(generator) => {
+                TileMap::generate(&mut agents, generator, generator.seed.unwrap_or(seed))
+            }
+        };
+        agents.sort();
+
+        let mut world = WorldGlobalState {
+            actions: Default::default(),
+            inventory,
+            map,
+            predators,
+            market: Default::default(),
+        };
+
+        for agent in &agents {
+            world.inventory.0.insert(*agent, AgentInventory::initial());
+        }
+
+        world
+    }
+
     pub fn register_pre_world_hook(&mut self, f: PreWorldHookFn) {
         self.pre_world_hooks.push(f);
     }
@@ -152,6 +213,10 @@ impl GameState {
             self.register_pre_world_hook(world_serialization_hook());
         }
 
+        if config().analytics.replay {
+            self.register_post_mcts_hook(replay_hook());
+        }
+
         if config().analytics.screenshot {
             self.register_pre_world_hook(screenshot_hook());
         }
@@ -214,6 +279,133 @@ impl GameState {
         serde_json::to_writer_pretty(file, &self.world).unwrap();
     }
 
+    // Writes the score of every team and returns the winner, if any
+    pub fn dump_teams(&self) -> Option<Team> {
+        let winner = self.world.winning_team();
+
+        let info = serde_json::json!({
+            "scores": self.world.team_scores(),
+            "winner": winner,
+        });
+
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .truncate(true)
+            .write(true)
+            .open(format!("{}teams.json", self.output_dir()))
+            .unwrap();
+
+        serde_json::to_writer_pretty(file, &info).unwrap();
+
+        winner
+    }
+
+    // Paints the tile at map coordinates with the editor brush, adding or removing agents as needed
+    fn paint(&mut self, x: isize, y: isize, brush: Brush) {
+        if x < 0 || y < 0 || x as usize >= self.width() || y as usize >= self.height() {
+            return;
+        }
+        let (x, y) = (x as usize, y as usize);
+
+        if let Tile::Agent(agent) = self.world.map[(x, y)] {
+            self.agents.retain(|other| *other != agent);
+            self.world.inventory.0.remove(&agent);
+            self.world.predators.remove(&agent);
+            self.world.actions.remove(&agent);
+            self.objectives.remove(&agent);
+        }
+
+        match (brush.tile(), brush) {
+            (Some(tile), _) => self.world.map[(x, y)] = tile,
+            (None, Brush::Predator(kind)) => self.add_agent(x, y, Some(kind)),
+            (None, _) => self.add_agent(x, y, None),
+        }
+
+        if self.current_agent >= self.agents.len() {
+            self.current_agent = 0;
+        }
+    }
+
+    // Places a new agent with the next free id on the tile
+    fn add_agent(&mut self, x: usize, y: usize, predator: Option<PredatorKind>) {
+        let agent = self.world.add_agent(x, y, predator);
+        self.agents.push(agent);
+    }
+
+    // Lets the world agent apply the scripted events of the current turn, the agents they
+    // surprised drop what they were doing and those that arrived join the turn order
+    fn apply_events(&mut self, events: &[EventConfig]) {
+        let surprises = WorldBehavior::apply_events(&mut self.world, events, self.turn, self.seed);
+        for agent in &surprises.affected {
+            self.objectives.remove(agent);
+            self.busy.remove(agent);
+            self.plans.remove(agent);
+        }
+        self.agents.extend(surprises.arrived);
+    }
+
+    // Paints the tile under the mouse, given in screen coordinates
+    fn paint_at(&mut self, x: f32, y: f32) {
+        if let Some(brush) = self.editor {
+            let (x, y) = self.camera.to_world(x, y);
+            self.paint(
+                (x / SPRITE_SIZE).floor() as isize - config().display.padding.0 as isize,
+                (y / SPRITE_SIZE).floor() as isize - config().display.padding.1 as isize,
+                brush,
+            );
+        }
+    }
+
+    pub fn save_map(&self, path: &str) {
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .truncate(true)
+            .write(true)
+            .open(path)
+            .unwrap();
+
+        self.world.map.to_io(&self.world.predators, file);
+        println!("Saved map to {}", path);
+    }
+
+    pub fn run_metrics(&self) -> RunMetrics {
+        RunMetrics {
+            run: self.run.unwrap_or_default(),
+            seed: self.seed,
+            wood: self.world.team_scores().values().sum(),
+            trees: self.world.map.tree_count(),
+            planning_time: self.planning_time / self.plannings.max(1),
+        }
+    }
+
+    pub fn save_state(&self, path: &str) {
+        SavedState {
+            turn: self.turn,
+            current_agent: self.current_agent,
+            world: self.world.clone(),
+            busy: self.busy.clone(),
+        }
+        .save(path);
+        println!("Saved state to {}", path);
+    }
+
+    // Resumes the game from a saved state, the ongoing objectives are lost
+    pub fn load_state(&mut self, path: &str) {
+        match SavedState::load(path) {
+            Ok(saved) => {
+                self.agents = saved.world.inventory.0.keys().copied().collect();
+                self.turn = saved.turn;
+                self.current_agent = saved.current_agent;
+                self.world = saved.world;
+                self.busy = saved.busy;
+                self.objectives.clear();
+                self.human_task = None;
+                println!("Loaded state from {}", path);
+            }
+            Err(e) => println!("{}", e),
+        }
+    }
+
     pub fn output_dir(&self) -> String {
         format!(
             "{}/{}/",
@@ -222,12 +414,12 @@ impl GameState {
         )
     }
 
-    pub fn screenshot(&self, ctx: &mut Context, path: &str) {
-        screenshot(ctx, &self.world, &self.assets, path);
+    pub fn screenshot(&self, graphics: Graphics, path: &str) {
+        screenshot(graphics, &self.world, &self.assets, path);
     }
 
-    pub fn add_asset(&mut self, name: String, image: Image) {
-        self.assets.insert(name, image);
+    pub fn add_asset(&mut self, name: String, texture: Texture2D) {
+        self.assets.insert(name, texture);
     }
 
     pub fn width(&self) -> usize {
@@ -242,19 +434,80 @@ impl GameState {
         self.turn
     }
 
-    pub fn update(&mut self, mut ctx: Option<&mut Context>) {
+    // The agent controlled by the player, not in batch mode as nobody is there to play
+    fn human(&self) -> Option<AgentId> {
+        config()
+            .agents
+            .human
+            .filter(|_| !batch())
+            .map(|index| AgentId(index as u32))
+    }
+
+    // Whether the game waits for the player to command their agent
+    fn awaiting_human(&self) -> bool {
+        self.editor.is_none()
+            && self.human_task.is_none()
+            && self.agents.get(self.current_agent).map_or(false, |agent| {
+                Some(*agent) == self.human()
+                    && self
+                        .busy
+                        .get(agent)
+                        .map_or(true, |until| *until < self.turn)
+            })
+    }
+
+    // Turns a key into a task of the player's agent: arrows walk, or chop a tree in the way, and
+    // space waits
+    fn human_command(&self, keycode: KeyCode) -> Option<Box<dyn Task<Lumberjacks>>> {
+        let direction = match keycode {
+            KeyCode::Up => Direction::Up,
+            KeyCode::Down => Direction::Down,
+            KeyCode::Left => Direction::Left,
+            KeyCode::Right => Direction::Right,
+            KeyCode::Space => return Some(Box::new(Wait)),
+            _ => return None,
+        };
+
+        let agent = self.agents[self.current_agent];
+        let state = Lumberjacks::derive_local_state(&self.world, agent);
+        let diff = WorldDiff::default();
+        let ctx =
+            npc_engine_core::Context::with_state_and_diff(self.turn as u64, &state, &diff, agent);
+
+        let (x, y) = ctx.state_diff.find_agent(agent)?;
+        let (x, y) = apply_direction(direction, x, y);
+        let tasks: Vec<Box<dyn Task<Lumberjacks>>> = vec![
+            Box::new(Move {
+                path: vec![direction],
+                x: x as _,
+                y: y as _,
+            }),
+            Box::new(Chop { direction }),
+        ];
+        tasks.into_iter().find(|task| task.is_valid(ctx))
+    }
+
+    pub fn update(&mut self, graphics: Option<Graphics>) {
+        if self.awaiting_human() {
+            return;
+        }
+
+        // Start of turn, scripted events happen before the hooks see the world
+        if self.current_agent == 0 {
+            self.apply_events(&config().events);
+        }
+
         let turn = self.turn;
         let run = self.run;
         let assets = &self.assets;
 
-        // Start of turn
         if self.current_agent == 0 {
             let world = &self.world;
 
             self.pre_world_hooks.iter_mut().for_each(|f| {
                 f(PreWorldHookArgs {
                     run,
-                    ctx: &mut ctx,
+                    graphics,
                     assets,
                     turn,
                     world,
@@ -264,92 +517,273 @@ impl GameState {
 
         let world = &mut self.world;
         let agent = self.agents[self.current_agent];
-        let initial_state = Lumberjacks::derive_local_state(world, agent);
-        // FIXME: re-introduce objectives as tasks
-        let mut mcts = MCTS::new(initial_state, agent, self.config.clone());
-
-        println!("planning start, turn {} {:?}", turn, agent);
-        let objective = mcts.run().unwrap();
-        println!("planning end");
-
-        self.post_mcts_hooks.iter_mut().for_each(|f| {
-            f(PostMCTSHookArgs {
-                run,
-                ctx: &mut ctx,
-                assets,
-                turn,
-                world,
+        if self.busy.get(&agent).map_or(true, |until| *until < turn) {
+            let initial_state = Lumberjacks::derive_local_state(world, agent);
+            let objective = if let Some(objective) = self.human_task.take() {
+                self.plans.remove(&agent);
+                objective
+            } else {
+                // An agent walking somewhere keeps going, the planner only checks the way is still open.
+                // This cannot loop: the task ends this turn, the planner only forces it on the root if
+                // it is still valid and otherwise chooses among all tasks, and MoveTo turns into an
+                // IdleTask once its target is reached or cannot be anymore, which is dropped here.
+                let tasks = self
+                    .objectives
+                    .remove(&agent)
+                    .filter(|objective| objective.downcast_ref::<IdleTask>().is_none())
+                    .map(|objective| {
+                        ActiveTask::new_with_end(turn as u64, turn as u64, agent, objective)
+                    })
+                    .into_iter()
+                    .collect();
+                let mut mcts = MCTS::new_with_tasks(
+                    initial_state.clone(),
+                    agent,
+                    turn as u64,
+                    tasks,
+                    Personality::of(agent).planner_config(&self.config),
+                    Box::new(DefaultPolicyEstimator {}),
+                    None,
+                );
+
+                println!("planning start, turn {} {:?}", turn, agent);
+                let objective = mcts.run().unwrap();
+                println!("planning end");
+                self.planning_time += mcts.time();
+                self.plannings += 1;
+                if self.layers.plans {
+                    self.plans.insert(agent, predicted_path(&mcts, agent));
+                }
+
+                self.post_mcts_hooks.iter_mut().for_each(|f| {
+                    f(PostMCTSHookArgs {
+                        run,
+                        graphics,
+                        assets,
+                        turn,
+                        world,
+                        agent,
+                        mcts: &mcts,
+                        objective: objective.clone(),
+                    })
+                });
+
+                objective
+            };
+
+            // Slow tasks keep the agent busy for the following turns
+            let duration = objective.duration(npc_engine_core::Context::new(
+                turn as u64,
+                StateDiffRef::new(&initial_state, &WorldDiff::default()),
                 agent,
-                mcts: &mcts,
-                objective: objective.clone(),
-            })
-        });
+            ));
+            if duration > 0 {
+                self.busy.insert(agent, turn + duration as usize);
+            }
 
-        let mut diff = WorldDiff::default();
-        let mcts_ctx = ContextMut::with_state_and_diff(0, mcts.initial_state(), &mut diff, agent);
-        let new_objective = objective.execute(mcts_ctx);
-        Lumberjacks::apply(world, mcts.initial_state(), &diff);
-        world.actions.insert(agent, objective.display_action());
-        new_objective.map(|objective| self.objectives.insert(agent, objective));
-
-        self.post_world_hooks.iter_mut().for_each(|f| {
-            f(PostWorldHookArgs {
-                run,
-                ctx: &mut ctx,
-                assets,
-                turn,
-                world,
-                objectives: &self.objectives,
-            })
-        });
+            let mut diff = WorldDiff::default();
+            let mcts_ctx =
+                ContextMut::with_state_and_diff(turn as u64, &initial_state, &mut diff, agent);
+            let new_objective = objective.execute(mcts_ctx);
+            Lumberjacks::apply(world, &initial_state, &diff);
+            world.actions.insert(agent, objective.display_action());
+            new_objective.map(|objective| self.objectives.insert(agent, objective));
+
+            self.post_world_hooks.iter_mut().for_each(|f| {
+                f(PostWorldHookArgs {
+                    run,
+                    graphics,
+                    assets,
+                    turn,
+                    world,
+                    objectives: &self.objectives,
+                })
+            });
+        }
 
         self.current_agent += 1;
 
         if self.current_agent == self.agents.len() {
             self.current_agent = 0;
             self.turn += 1;
+
+            // Every agent acts once per turn, which lasts one tick in planning
+            if config().features.regrowth && self.turn as u64 % config().regrowth.period == 0 {
+                self.world.regrow_trees();
+            }
+            if config().features.fire_spread && self.turn as u64 % config().fire.period == 0 {
+                self.world.spread_fire();
+            }
+            if config().features.market && self.turn as u64 % config().market.period == 0 {
+                self.world.market = self.world.market.repriced();
+            }
+        }
+    }
+
+    // Status line at the top of the window
+    fn status(&self) -> String {
+        let world = &self.world;
+        if let Some(brush) = self.editor {
+            format!("Editor: {} (1-9, 0, -, =: brush, S: save, E: play)", brush)
+        } else if self.awaiting_human() {
+            format!(
+                "Turn: {}, your move (arrows: walk or chop, space: wait)",
+                self.turn
+            )
+        } else if config().features.market {
+            format!(
+                "Turn: {}, wood sells for {} coins",
+                self.turn, world.market.price
+            )
+        } else if is_night(self.turn as u64) {
+            format!("Turn: {} (night)", self.turn)
+        } else if config().features.teams {
+            let scores = world.team_scores();
+            format!(
+                "Turn: {}, orange: {}, yellow: {}",
+                self.turn,
+                scores.get(&Team::Orange).unwrap_or(&0),
+                scores.get(&Team::Yellow).unwrap_or(&0)
+            )
+        } else {
+            format!("Turn: {}", self.turn)
+        }
+    }
+
+    // Handles a key press, returns false to quit
+    fn key_down(&mut self, keycode: KeyCode) -> bool {
+        match (keycode, self.editor) {
+            (KeyCode::Escape, _) => return false,
+            (KeyCode::E, None) if self.interactive => self.editor = Some(Brush::Tree),
+            (KeyCode::E, Some(_)) => self.editor = None,
+            (KeyCode::S, Some(_)) => self.save_map(&format!("{}map.png", self.output_dir())),
+            (KeyCode::F5, None) => self.save_state(&format!("{}state.json", self.output_dir())),
+            (KeyCode::F9, None) => self.load_state(&format!("{}state.json", self.output_dir())),
+            (KeyCode::F1, _) => self.layers.plans = !self.layers.plans,
+            (KeyCode::F2, _) => self.layers.points_of_interest = !self.layers.points_of_interest,
+            (KeyCode::F3, _) => self.layers.windows = !self.layers.windows,
+            (KeyCode::Home, _) => self.camera = Default::default(),
+            (keycode, None) if self.awaiting_human() => {
+                self.human_task = self.human_command(keycode);
+            }
+            (keycode, Some(_)) => {
+                if let Some(brush) = Brush::from_key(keycode) {
+                    self.editor = Some(brush);
+                }
+            }
+            _ => (),
+        }
+        true
+    }
+
+    // Paints with the left button, pans with the right one and zooms with the wheel
+    fn handle_mouse(&mut self) {
+        let (x, y) = mouse_position();
+        if is_mouse_button_down(MouseButton::Left) {
+            self.paint_at(x, y);
+        }
+        if is_mouse_button_down(MouseButton::Right) {
+            self.camera.pan(x - self.mouse.0, y - self.mouse.1);
+        }
+        self.mouse = (x, y);
+
+        // The wheel moves by a platform-dependent amount, so only its direction counts
+        let (_, wheel) = mouse_wheel();
+        if wheel != 0. {
+            self.camera.zoom(wheel.signum(), x, y);
         }
     }
 }
 
-impl EventHandler for GameState {
-    fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
-        match config().turns {
-            Some(turns) if self.turn >= turns => {
-                event::quit(ctx);
-                return Ok(());
+impl Frame for GameState {
+    fn update(&mut self, graphics: Graphics) -> bool {
+        for keycode in get_keys_pressed() {
+            if !self.key_down(keycode) {
+                return false;
             }
+        }
+        self.handle_mouse();
+
+        match config().turns {
+            Some(turns) if self.turn >= turns => return false,
             _ => (),
         }
 
-        if keyboard::is_key_pressed(ctx, KeyCode::Return) || !self.interactive {
-            GameState::update(self, Some(ctx));
+        if self.editor.is_some() || self.agents.is_empty() {
+            return true;
+        }
+
+        // With a player, the game runs by itself and waits for them at their agent's turn
+        if is_key_down(KeyCode::Enter) || !self.interactive || self.human().is_some() {
+            GameState::update(self, Some(graphics));
         }
 
-        Ok(())
+        true
     }
 
-    fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
-        let world = &self.world;
+    fn draw(&mut self, _graphics: Graphics) {
+        // The background darkens at night
+        let light = if is_night(self.turn as u64) { 0.4 } else { 1. };
+        clear_background(background(light));
+
+        draw_label(&self.status(), 5.0 * SPRITE_SIZE, 0.0 * SPRITE_SIZE);
 
-        graphics::clear(
-            ctx,
-            graphics::Color::new(
-                config().display.background.0,
-                config().display.background.1,
-                config().display.background.2,
-                1.,
-            ),
+        set_view(
+            self.camera
+                .view(Rect::new(0., 0., screen_width(), screen_height())),
+        );
+        self.world.draw(&self.assets);
+        self.layers.draw(
+            map_origin(),
+            &self.world,
+            &self.plans,
+            self.agents.get(self.current_agent).copied(),
         );
+        set_default_camera();
+    }
+}
 
-        graphics::draw(
-            ctx,
-            &Text::new(format!("Turn: {}", self.turn)),
-            ([5.0 * SPRITE_SIZE, 0.0 * SPRITE_SIZE], graphics::WHITE),
-        )
-        .unwrap();
-        world.draw(ctx, &self.assets);
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU8;
+
+    use npc_engine_core::AgentId;
+
+    use super::GameState;
+    use crate::{testing, EventConfig, EventKind, Tile, Wait};
+
+    #[test]
+    fn surprised_agents_replan_and_arrivals_join_the_turn() {
+        testing::init();
+        let mut state = GameState::new(false, None, 0);
+        let mut row = vec![Tile::Empty; 10];
+        row[0] = Tile::Agent(AgentId(0));
+        row[1] = Tile::Tree(NonZeroU8::new(1).unwrap());
+        row[8] = Tile::Agent(AgentId(1));
+        state.world = testing::world(vec![row]);
+        state.agents = vec![AgentId(0), AgentId(1)];
+        for agent in &state.agents {
+            state.objectives.insert(*agent, Box::new(Wait));
+            state.busy.insert(*agent, 5);
+        }
 
-        graphics::present(ctx)
+        let events = [
+            EventConfig {
+                turn: 0,
+                kind: EventKind::Storm { fraction: 1. },
+            },
+            EventConfig {
+                turn: 0,
+                kind: EventKind::Arrival { agents: 1 },
+            },
+        ];
+        state.apply_events(&events);
+
+        // the agent next to the fallen tree plans again on its turn, the other keeps going
+        assert!(!state.objectives.contains_key(&AgentId(0)));
+        assert!(!state.busy.contains_key(&AgentId(0)));
+        assert!(state.objectives.contains_key(&AgentId(1)));
+        assert!(state.busy.contains_key(&AgentId(1)));
+        assert_eq!(state.agents, vec![AgentId(0), AgentId(1), AgentId(2)]);
     }
 }
@@ -0,0 +1,149 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use npc_engine_core::AgentId;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::{config, ProceduralConfig, Tile, TileMap};
+
+impl TileMap {
+    /// Generates a random map: a forest of the given density, crossed by rivers flowing
+    /// from the top to the bottom and with clearings where the agents, wells and bushes are placed.
+    /// Hills raise the ground afterwards, so that they do not change the rest of the map of a seed.
+    pub fn generate(agents: &mut Vec<AgentId>, generator: &ProceduralConfig, seed: u64) -> Self {
+        let ProceduralConfig {
+            width,
+            height,
+            forest_density,
+            rivers,
+            clearings,
+            clearing_radius,
+            hills,
+            hill_height,
+            hill_radius,
+            ..
+        } = *generator;
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        let mut tiles = (0..height)
+            .map(|_| {
+                (0..width)
+                    .map(|_| {
+                        if rng.gen_bool(forest_density.clamp(0., 1.) as f64) {
+                            Tile::Tree(config().map.tree_height)
+                        } else {
+                            Tile::Empty
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        // Rivers meander down the map, one column left or right at most per row
+        for _ in 0..rivers {
+            let mut x = rng.gen_range(0, width as isize);
+            for row in tiles.iter_mut() {
+                row[x as usize] = Tile::Water;
+                x = (x + rng.gen_range(-1, 2)).clamp(0, width as isize - 1);
+            }
+        }
+
+        // Clearings are disks of empty tiles
+        let mut centers = Vec::new();
+        for _ in 0..clearings {
+            let (cx, cy) = (
+                rng.gen_range(0, width as isize),
+                rng.gen_range(0, height as isize),
+            );
+            let radius = clearing_radius as isize;
+            for y in (cy - radius).max(0)..=(cy + radius).min(height as isize - 1) {
+                for x in (cx - radius).max(0)..=(cx + radius).min(width as isize - 1) {
+                    if (x - cx).pow(2) + (y - cy).pow(2) <= radius.pow(2)
+                        && tiles[y as usize][x as usize] != Tile::Water
+                    {
+                        tiles[y as usize][x as usize] = Tile::Empty;
+                    }
+                }
+            }
+            centers.push((cx, cy));
+        }
+
+        // Wells, bushes then agents go to empty tiles, around the clearings if there are any
+        let mut place = |tiles: &mut Vec<Vec<Tile>>, tile: Tile| {
+            for _ in 0..width * height {
+                let (x, y) = match centers.get(rng.gen_range(0, centers.len().max(1))) {
+                    Some((cx, cy)) => {
+                        let radius = clearing_radius as isize;
+                        (
+                            cx + rng.gen_range(-radius, radius + 1),
+                            cy + rng.gen_range(-radius, radius + 1),
+                        )
+                    }
+                    None => (
+                        rng.gen_range(0, width as isize),
+                        rng.gen_range(0, height as isize),
+                    ),
+                };
+                if x >= 0
+                    && x < width as isize
+                    && y >= 0
+                    && y < height as isize
+                    && tiles[y as usize][x as usize] == Tile::Empty
+                {
+                    tiles[y as usize][x as usize] = tile;
+                    return;
+                }
+            }
+            panic!(
+                "not enough empty tiles to place {:?} on the generated map",
+                tile
+            );
+        };
+        for _ in 0..generator.wells {
+            place(&mut tiles, Tile::Well);
+        }
+        for _ in 0..generator.bushes {
+            place(&mut tiles, Tile::Bush);
+        }
+        for _ in 0..generator.agents {
+            // The actual agent ids are assigned below
+            place(&mut tiles, Tile::Agent(AgentId(u32::MAX)));
+        }
+
+        // Agents are numbered in reading order, as when loading a map from a file
+        for row in tiles.iter_mut() {
+            for tile in row.iter_mut() {
+                if let Tile::Agent(_) = tile {
+                    let agent = AgentId(agents.len() as u32);
+                    agents.push(agent);
+                    *tile = Tile::Agent(agent);
+                }
+            }
+        }
+
+        // Hills are cones, the highest one wins where they overlap
+        let mut elevation = vec![vec![0u8; width]; height];
+        for _ in 0..hills {
+            let (cx, cy) = (
+                rng.gen_range(0, width as isize),
+                rng.gen_range(0, height as isize),
+            );
+            let radius = hill_radius.max(1) as f32;
+            for (y, row) in elevation.iter_mut().enumerate() {
+                for (x, level) in row.iter_mut().enumerate() {
+                    let distance =
+                        ((x as isize - cx).pow(2) as f32 + (y as isize - cy).pow(2) as f32).sqrt();
+                    let raised = (hill_height as f32 * (1. - distance / radius)).round();
+                    if raised > 0. && tiles[y][x] != Tile::Water {
+                        *level = (*level).max(raised as u8);
+                    }
+                }
+            }
+        }
+
+        TileMap::from_rows(width, height, tiles).with_elevation(elevation)
+    }
+}
@@ -0,0 +1,105 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use image::imageops;
+use image::{ImageBuffer, Rgba, RgbaImage};
+use macroquad::prelude::*;
+
+use crate::{config, SPRITE_SIZE};
+
+/// The height of the text drawn in the window, in pixels.
+pub const FONT_SIZE: f32 = 16.;
+
+/// Proof that a window is open, which drawing needs; batch runs never get one.
+#[derive(Clone, Copy, Debug)]
+pub struct Graphics(());
+
+/// What a window shows, updated then drawn once per frame.
+pub trait Frame {
+    /// Handles the input and advances the simulation, returns false to close the window.
+    fn update(&mut self, graphics: Graphics) -> bool;
+    fn draw(&mut self, graphics: Graphics);
+}
+
+/// Opens a window of the given size in pixels and runs `frame` in it until it closes.
+///
+/// `setup` runs once the window is open and `exit` once it is closed, both still able to draw.
+pub fn run_window<F: Frame + 'static>(
+    title: String,
+    size: (f32, f32),
+    mut frame: F,
+    setup: impl FnOnce(&mut F, Graphics) + 'static,
+    exit: impl FnOnce(&mut F, Graphics) + 'static,
+) {
+    let conf = macroquad::conf::Conf {
+        miniquad_conf: macroquad::window::Conf {
+            window_title: title,
+            window_width: size.0 as i32,
+            window_height: size.1 as i32,
+            ..Default::default()
+        },
+        default_filter_mode: FilterMode::Nearest,
+        ..Default::default()
+    };
+    macroquad::Window::from_config(conf, async move {
+        let graphics = Graphics(());
+        // Closing the window ends the loop, so that exit still runs
+        prevent_quit();
+        setup(&mut frame, graphics);
+        while !is_quit_requested() && frame.update(graphics) {
+            frame.draw(graphics);
+            next_frame().await;
+        }
+        exit(&mut frame, graphics);
+    });
+}
+
+/// The background colour, darker by `light`.
+pub fn background(light: f32) -> Color {
+    let (r, g, b) = config().display.background;
+    Color::new(r * light, g * light, b * light, 1.)
+}
+
+/// The position of the top-left tile of the map, right of and below the padding.
+pub fn map_origin() -> (f32, f32) {
+    (
+        config().display.padding.0 as f32 * SPRITE_SIZE,
+        config().display.padding.1 as f32 * SPRITE_SIZE,
+    )
+}
+
+/// Draws white text whose top-left corner is at (x, y).
+pub fn draw_label(text: &str, x: f32, y: f32) {
+    let dimensions = measure_text(text, None, FONT_SIZE as u16, 1.);
+    draw_text(text, x, y + dimensions.offset_y, FONT_SIZE, WHITE);
+}
+
+/// Shows `view` in the whole window, with y pointing down.
+pub fn set_view(view: Rect) {
+    // A display rect maps its top to the bottom of the screen, so it is flipped
+    set_camera(&Camera2D::from_display_rect(Rect::new(
+        view.x,
+        view.y + view.h,
+        view.w,
+        -view.h,
+    )));
+}
+
+/// Draws off-screen with `draw` into an image of the size of the window.
+pub fn capture(_graphics: Graphics, draw: impl FnOnce()) -> RgbaImage {
+    let (width, height) = (screen_width() as u32, screen_height() as u32);
+    let target = render_target(width, height);
+    let mut camera = Camera2D::from_display_rect(Rect::new(0., 0., width as f32, height as f32));
+    camera.render_target = Some(target.clone());
+    set_camera(&camera);
+    draw();
+    set_default_camera();
+
+    // The render target is read from its bottom row up
+    let image = target.texture.get_texture_data();
+    let image: RgbaImage =
+        ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, image.bytes).unwrap();
+    imageops::flip_vertical(&image)
+}
@@ -6,19 +6,22 @@
 use std::collections::BTreeMap;
 use std::{f32, fs};
 
-use ggez::graphics;
-use ggez::graphics::{Canvas, Color, DrawMode, Mesh, Rect};
 use image::png::PngEncoder;
-use image::{ColorType, ImageBuffer, Rgba};
+use image::ColorType;
+use macroquad::color::{Color, BLACK};
+use macroquad::shapes::draw_rectangle;
+use macroquad::window::clear_background;
 use npc_engine_core::StateDiffRef;
 
-use crate::{output_path, PostMCTSHookArgs, PostMCTSHookFn, WorldState, SPRITE_SIZE};
+use crate::{
+    capture, map_origin, output_path, PostMCTSHookArgs, PostMCTSHookFn, WorldState, SPRITE_SIZE,
+};
 
 // TODO
 pub fn heatmap_hook() -> PostMCTSHookFn {
     Box::new(
         move |PostMCTSHookArgs {
-                  ctx,
+                  graphics,
                   assets,
                   run,
                   turn,
@@ -27,7 +30,7 @@ pub fn heatmap_hook() -> PostMCTSHookFn {
                   mcts,
                   ..
               }| {
-            if let Some(ctx) = ctx {
+            if let Some(graphics) = graphics {
                 struct HeatMapEntry {
                     visits: usize,
                     score: f32,
@@ -69,19 +72,10 @@ pub fn heatmap_hook() -> PostMCTSHookFn {
 
                 // Heatmap
                 {
-                    let canvas = Canvas::with_window_size(ctx).unwrap();
-                    graphics::set_canvas(ctx, Some(&canvas));
-                    graphics::clear(ctx, graphics::BLACK);
-
-                    let rect = Mesh::new_rectangle(
-                        ctx,
-                        DrawMode::fill(),
-                        Rect::new(0 as f32, 0 as f32, SPRITE_SIZE, SPRITE_SIZE),
-                        graphics::WHITE,
-                    )
-                    .unwrap();
+                    let image = capture(graphics, || {
+                        clear_background(BLACK);
 
-                    world.with_map_coordinates(ctx, |ctx| {
+                        let (origin_x, origin_y) = map_origin();
                         for (&(x, y), entry) in &positions {
                             let visits = entry.visits as f32 / max_visits as f32;
 
@@ -102,30 +96,18 @@ pub fn heatmap_hook() -> PostMCTSHookFn {
                             red /= max;
 
                             if visits > f32::EPSILON && scores > f32::EPSILON {
-                                graphics::draw(
-                                    ctx,
-                                    &rect,
-                                    (
-                                        [x as f32 * SPRITE_SIZE, y as f32 * SPRITE_SIZE],
-                                        Color::new(red, green, 0., visits),
-                                    ),
-                                )
-                                .unwrap();
+                                draw_rectangle(
+                                    origin_x + x as f32 * SPRITE_SIZE,
+                                    origin_y + y as f32 * SPRITE_SIZE,
+                                    SPRITE_SIZE,
+                                    SPRITE_SIZE,
+                                    Color::new(red, green, 0., visits),
+                                );
                             }
                         }
-                    });
-
-                    world.draw(ctx, assets);
 
-                    graphics::present(ctx).unwrap();
-                    let image = canvas.image();
-
-                    let width = image.width() as u32;
-                    let height = image.height() as u32;
-                    let image_data: ImageBuffer<Rgba<u8>, _> =
-                        ImageBuffer::from_raw(width, height, image.to_rgba8(ctx).unwrap()).unwrap();
-
-                    let flipped_image_data = image::imageops::flip_vertical(&image_data);
+                        world.draw(assets);
+                    });
 
                     fs::create_dir_all(format!(
                         "{}/{}/heatmaps/agent{}/",
@@ -148,7 +130,7 @@ pub fn heatmap_hook() -> PostMCTSHookFn {
                         .unwrap();
 
                     PngEncoder::new(file)
-                        .encode(&flipped_image_data, width, height, ColorType::Rgba8)
+                        .encode(&image, image.width(), image.height(), ColorType::Rgba8)
                         .unwrap();
                 }
             }
@@ -6,56 +6,94 @@
 use std::collections::BTreeMap;
 use std::mem;
 
-use ggez::graphics::{draw, Image, Text, DEFAULT_FONT_SCALE, WHITE};
-use ggez::Context;
-use serde::Serialize;
+use macroquad::color::WHITE;
+use macroquad::shapes::draw_rectangle;
+use macroquad::texture::{draw_texture, Texture2D};
+use serde::{Deserialize, Serialize};
 
 use npc_engine_core::AgentId;
 
-use crate::SPRITE_SIZE;
+use crate::{config, draw_label, PredatorKind, FONT_SIZE, SPRITE_SIZE};
 
-#[derive(Clone, Default, Debug, PartialEq, Eq, Serialize)]
+#[derive(Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Inventory(pub BTreeMap<AgentId, AgentInventory>);
 
-#[derive(Clone, Default, Debug, PartialEq, Eq, Serialize, Hash)]
+#[derive(Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize, Hash)]
 pub struct AgentInventory {
     pub wood: isize,
     pub water: bool,
+    pub food: isize,
+    pub axe: u8,
+    pub stored: isize,
+    pub offer: Option<AgentId>,
+    pub energy: isize,
+    pub last_meal: u64,
+    pub last_rest: u64,
+    #[serde(default)]
+    pub coins: isize,
 }
 
-impl Inventory {
-    pub fn draw(&self, ctx: &mut Context, assets: &BTreeMap<String, Image>) {
-        let mut agents = self
-            .0
-            .iter()
-            .map(|(k, v)| (*k, v.wood, v.water))
-            .collect::<Vec<(AgentId, isize, bool)>>();
+impl AgentInventory {
+    // What an agent carries when it enters the game
+    pub fn initial() -> Self {
+        AgentInventory {
+            wood: 0,
+            water: false,
+            food: 0,
+            axe: config().tools.durability,
+            stored: 0,
+            offer: None,
+            energy: config().day_night.energy as isize,
+            last_meal: 0,
+            last_rest: 0,
+            coins: 0,
+        }
+    }
+}
 
-        agents.sort_by_key(|(k, ..)| *k);
+impl Inventory {
+    pub fn draw(
+        &self,
+        assets: &BTreeMap<String, Texture2D>,
+        predators: &BTreeMap<AgentId, PredatorKind>,
+    ) {
+        for (i, (agent, inventory)) in self.0.iter().enumerate() {
+            let y = i as f32 * SPRITE_SIZE;
+            let text_y = y + (SPRITE_SIZE - FONT_SIZE) / 2.;
+            if let Some(kind) = predators.get(agent) {
+                draw_rectangle(0., y, SPRITE_SIZE, SPRITE_SIZE, kind.color());
+                draw_label(&format!(":{}", inventory.food), SPRITE_SIZE, text_y);
+                continue;
+            }
 
-        for (i, (agent, wood, water)) in agents.iter().enumerate() {
             let sprite_name = if agent.0 % 2 == 0 {
                 "OrangeRight".to_owned()
             } else {
                 "YellowRight".to_owned()
             };
 
-            draw(
-                ctx,
-                assets.get(&sprite_name).unwrap(),
-                ([0 as f32 * SPRITE_SIZE, i as f32 * SPRITE_SIZE], WHITE),
-            )
-            .unwrap();
+            draw_texture(assets.get(&sprite_name).unwrap(), 0., y, WHITE);
 
-            draw(
-                ctx,
-                &Text::new(format!(":{}, {}", wood, water)),
-                ([
-                    SPRITE_SIZE,
-                    i as f32 * SPRITE_SIZE + (SPRITE_SIZE - DEFAULT_FONT_SCALE) / 2.,
-                ],),
-            )
-            .unwrap();
+            draw_label(
+                &(if config().features.tools {
+                    format!(
+                        ":{} (+{} stored), {}, axe {}",
+                        inventory.wood, inventory.stored, inventory.water, inventory.axe
+                    )
+                } else {
+                    format!(":{}, {}", inventory.wood, inventory.water)
+                } + &if config().features.day_night {
+                    format!(", energy {}", inventory.energy)
+                } else {
+                    String::new()
+                } + &if config().features.market {
+                    format!(", {} coins", inventory.coins)
+                } else {
+                    String::new()
+                }),
+                SPRITE_SIZE,
+                text_y,
+            );
         }
     }
 }
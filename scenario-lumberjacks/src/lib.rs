@@ -6,41 +6,58 @@
 use std::mem::MaybeUninit;
 use std::path::PathBuf;
 use std::sync::Once;
-use std::{fs, io, mem, process};
+use std::{fs, io, mem, process, ptr};
 
 use clap::{App, Arg};
 
 use serde_json::Value;
 
 mod behaviors;
+mod camera;
 mod config;
+mod editor;
 mod fitnesses;
 mod game;
+mod generator;
 mod graph;
+mod gui;
 mod heatmap;
 mod hooks;
 mod inventory;
 mod lumberjacks_domain;
 mod metrics;
+mod overlay;
+mod personality;
+mod replay;
 mod screenshot;
 mod serialization;
 mod tasks;
+mod teams;
+#[cfg(test)]
+mod testing;
 mod tilemap;
 mod util;
 mod world;
 
 pub use behaviors::*;
+pub use camera::*;
 pub use config::*;
+pub use editor::*;
 pub use game::*;
 pub use graph::*;
+pub use gui::*;
 pub use heatmap::*;
 pub use hooks::*;
 pub use inventory::*;
 pub use lumberjacks_domain::*;
 pub use metrics::*;
+pub use overlay::*;
+pub use personality::*;
+pub use replay::*;
 pub use screenshot::*;
 pub use serialization::*;
 pub use tasks::*;
+pub use teams::*;
 pub use tilemap::*;
 pub use util::*;
 pub use world::*;
@@ -51,6 +68,8 @@ static mut WORKING_DIR: MaybeUninit<String> = MaybeUninit::uninit();
 static mut OUTPUT_PATH: MaybeUninit<String> = MaybeUninit::uninit();
 static mut NAME: MaybeUninit<String> = MaybeUninit::uninit();
 static mut BATCH: MaybeUninit<bool> = MaybeUninit::uninit();
+static mut LOAD: MaybeUninit<Option<String>> = MaybeUninit::uninit();
+static mut REPLAY: MaybeUninit<Option<String>> = MaybeUninit::uninit();
 
 unsafe fn init() {
     INIT.call_once(|| {
@@ -98,6 +117,24 @@ unsafe fn init() {
                     .long("batch")
                     .help("Enables batch mode"),
             )
+            .arg(
+                Arg::with_name("load")
+                    .required(false)
+                    .takes_value(true)
+                    .value_name("file")
+                    .short("l")
+                    .long("load")
+                    .help("Starts from a saved state instead of the configured map"),
+            )
+            .arg(
+                Arg::with_name("replay")
+                    .required(false)
+                    .takes_value(true)
+                    .value_name("file")
+                    .short("r")
+                    .long("replay")
+                    .help("Plays back a recorded run instead of simulating"),
+            )
             .arg(
                 Arg::with_name("set")
                     .required(false)
@@ -138,6 +175,10 @@ unsafe fn init() {
 
         BATCH = MaybeUninit::new(matches.is_present("batch"));
 
+        LOAD = MaybeUninit::new(matches.value_of("load").map(str::to_owned));
+
+        REPLAY = MaybeUninit::new(matches.value_of("replay").map(str::to_owned));
+
         CONFIG = MaybeUninit::new({
             let mut json: Value = match config_path {
                 "-" => {
@@ -207,32 +248,32 @@ unsafe fn init() {
 pub fn name() -> &'static String {
     unsafe {
         init();
-        // Safe to transmute, initialized
-        mem::transmute(&NAME)
+        // Safe to dereference, initialized
+        (*ptr::addr_of!(NAME)).assume_init_ref()
     }
 }
 
 pub fn config() -> &'static Config {
     unsafe {
         init();
-        // Safe to transmute, initialized
-        mem::transmute(&CONFIG)
+        // Safe to dereference, initialized
+        (*ptr::addr_of!(CONFIG)).assume_init_ref()
     }
 }
 
 pub fn working_dir() -> &'static String {
     unsafe {
         init();
-        // Safe to transmute, initialized
-        mem::transmute(&WORKING_DIR)
+        // Safe to dereference, initialized
+        (*ptr::addr_of!(WORKING_DIR)).assume_init_ref()
     }
 }
 
 pub fn output_path() -> &'static String {
     unsafe {
         init();
-        // Safe to transmute, initialized
-        mem::transmute(&OUTPUT_PATH)
+        // Safe to dereference, initialized
+        (*ptr::addr_of!(OUTPUT_PATH)).assume_init_ref()
     }
 }
 
@@ -243,3 +284,19 @@ pub fn batch() -> bool {
         mem::transmute(BATCH)
     }
 }
+
+pub fn load_path() -> Option<&'static String> {
+    unsafe {
+        init();
+        // Safe to dereference, initialized
+        (*ptr::addr_of!(LOAD)).assume_init_ref().as_ref()
+    }
+}
+
+pub fn replay_path() -> Option<&'static String> {
+    unsafe {
+        init();
+        // Safe to dereference, initialized
+        (*ptr::addr_of!(REPLAY)).assume_init_ref().as_ref()
+    }
+}
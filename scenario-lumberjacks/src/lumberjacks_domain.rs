@@ -9,8 +9,9 @@ use npc_engine_core::{AgentId, AgentValue, Behavior, Context, Domain, StateDiffR
 use npc_engine_utils::GlobalDomain;
 
 use crate::{
-    config, Action, AgentInventory, Human, InventorySnapshot, Lumberjack, Tile, TileMapSnapshot,
-    WorldDiff, WorldGlobalState, WorldLocalState, WorldState,
+    config, fitnesses, Action, AgentInventory, Human, InventorySnapshot, Lumberjack, Personality,
+    Predator, Team, TeamStrategy, TileMapSnapshot, WorldBehavior, WorldDiff, WorldGlobalState,
+    WorldLocalState, WorldState, WORLD_AGENT_ID,
 };
 
 pub struct Lumberjacks;
@@ -21,14 +22,111 @@ impl Domain for Lumberjacks {
     type DisplayAction = Action;
 
     fn list_behaviors() -> &'static [&'static dyn Behavior<Self>] {
-        &[&Human, &Lumberjack]
+        &[&Human, &Lumberjack, &Predator, &WorldBehavior]
     }
 
-    fn get_current_value(_tick: u64, state_diff: StateDiffRef<Self>, agent: AgentId) -> AgentValue {
-        let value = if let Some((_, f)) = config().agents.behaviors.get(&(agent.0 as usize)) {
+    fn get_current_value(tick: u64, state_diff: StateDiffRef<Self>, agent: AgentId) -> AgentValue {
+        let value = if agent == WORLD_AGENT_ID {
+            0.
+        } else if state_diff.predator_kind(agent).is_some() {
+            fitnesses::predator(state_diff, agent)
+        } else if let Some((_, f)) = config().agents.behaviors.get(&(agent.0 as usize)) {
             f(state_diff, agent)
         } else {
-            state_diff.get_inventory(agent) as f32
+            let wood =
+                if config().features.teams && Team::of(agent).strategy() == TeamStrategy::Shared {
+                    state_diff.team_score(Team::of(agent))
+                } else {
+                    state_diff.get_inventory(agent) + state_diff.get_stored(agent)
+                };
+            // neglected needs cost value for every tick past their threshold
+            let needs = if config().features.needs {
+                config().needs.hunger_penalty
+                    * state_diff
+                        .hunger(tick, agent)
+                        .saturating_sub(config().needs.hunger_threshold)
+                        as f32
+                    + config().needs.tiredness_penalty
+                        * state_diff
+                            .tiredness(tick, agent)
+                            .saturating_sub(config().needs.tiredness_threshold)
+                            as f32
+            } else {
+                0.
+            };
+            let weights = &config().value;
+            let personality = Personality::of(agent);
+            // cooperative lumberjacks are glad of the wood of the others too
+            let others = if personality.cooperation != 0. {
+                state_diff
+                    .initial_state
+                    .inventory
+                    .0
+                    .keys()
+                    .filter(|other| **other != agent && state_diff.predator_kind(**other).is_none())
+                    .map(|other| state_diff.get_inventory(*other) + state_diff.get_stored(*other))
+                    .sum::<usize>() as f32
+            } else {
+                0.
+            };
+            let position = state_diff.find_agent(agent);
+            // staying close to the trees keeps the next wood within reach
+            let distance = match position {
+                Some((x, y)) if weights.distance != 0. => state_diff
+                    .trees()
+                    .iter()
+                    .map(|(tx, ty)| (tx - x).abs() + (ty - y).abs())
+                    .min()
+                    .unwrap_or(0) as f32,
+                _ => 0.,
+            };
+            // predators in view are a threat, the more so the closer they are
+            let threat = match position {
+                Some((x, y)) if weights.safety != 0. => {
+                    let radius = config().agents.horizon_radius as isize;
+                    state_diff
+                        .find_nearby_agents(x, y, config().agents.horizon_radius)
+                        .into_iter()
+                        .filter(|other| state_diff.predator_kind(*other).is_some())
+                        .filter_map(|other| state_diff.find_agent(other))
+                        .map(|(px, py)| (radius + 1 - (px - x).abs().max((py - y).abs())).max(0))
+                        .sum::<isize>() as f32
+                }
+                _ => 0.,
+            };
+            // fires in view threaten the forest and everyone around
+            let fires = if config().features.fire_spread {
+                config().fire.penalty * state_diff.fires().len() as f32
+            } else {
+                0.
+            };
+            // standing wood is worth keeping for later when the trees regrow
+            let standing_wood = if weights.sustainability != 0. {
+                weights.sustainability * state_diff.standing_wood() as f32
+            } else {
+                0.
+            };
+            // young trees are a forecast of the wood they will grow into
+            let future_growth = if config().features.planting && config().planting.forecast != 0. {
+                config().planting.forecast * state_diff.future_growth() as f32
+            } else {
+                0.
+            };
+            // huts in view are worth their value to anyone
+            let huts = if config().features.construction && config().construction.hut_value != 0. {
+                config().construction.hut_value * state_diff.huts() as f32
+            } else {
+                0.
+            };
+            personality.greed
+                * (weights.wood * wood as f32 + weights.coins * state_diff.get_coins(agent) as f32)
+                + personality.cooperation * others
+                - needs
+                - weights.distance * distance
+                - personality.caution * (weights.safety * threat + fires)
+                + standing_wood
+                + future_growth
+                + huts
         };
         AgentValue::new(value).unwrap()
     }
@@ -37,19 +135,20 @@ impl Domain for Lumberjacks {
         let Context {
             state_diff, agent, ..
         } = ctx;
-        if let Some((x, y)) = state_diff.find_agent(agent) {
+        if agent == WORLD_AGENT_ID {
+            // The world agent is not on the map, it keeps the agents already visible
+        } else if let Some((x, y)) = state_diff.find_agent(agent) {
             if config().agents.plan_others {
-                agents.extend(
-                    state_diff
-                        .find_nearby_agents(x, y, config().agents.horizon_radius)
-                        .into_iter(),
-                );
+                agents.extend(state_diff.find_nearby_agents(x, y, config().agents.horizon_radius));
             } else {
                 agents.insert(agent);
             }
         } else {
             unreachable!("{:?}", state_diff);
         }
+        if config().features.regrowth || config().features.fire_spread || config().features.market {
+            agents.insert(WORLD_AGENT_ID);
+        }
     }
 }
 
@@ -57,62 +156,76 @@ impl GlobalDomain for Lumberjacks {
     type GlobalState = WorldGlobalState;
 
     fn derive_local_state(state: &Self::GlobalState, agent: AgentId) -> Self::State {
-        let (x, y) = state.find_agent(agent).unwrap();
-
-        let top = y - config().agents.snapshot_radius as isize;
-        let left = x - config().agents.snapshot_radius as isize;
-
-        let mut map = TileMapSnapshot {
-            top,
-            left,
-            tiles: (0..(config().agents.snapshot_radius * 2 + 1))
-                .map(|_| {
-                    (0..(config().agents.snapshot_radius * 2 + 1))
-                        .map(|_| Tile::Empty)
-                        .collect::<Vec<_>>()
-                        .into_boxed_slice()
-                })
-                .collect::<Vec<_>>()
-                .into_boxed_slice(),
+        // The world agent is not on the map, it looks at its centre
+        let (x, y) = if agent == WORLD_AGENT_ID {
+            (
+                (state.map.width / 2) as isize,
+                (state.map.height / 2) as isize,
+            )
+        } else {
+            state.find_agent(agent).unwrap()
         };
 
-        for y in 0..(config().agents.snapshot_radius * 2 + 1) {
-            for x in 0..(config().agents.snapshot_radius * 2 + 1) {
-                let src_y = top + y as isize;
-                let src_x = left + x as isize;
-
-                if (src_y >= 0 && src_y < state.map.height as isize)
-                    && (src_x >= 0 && src_x < state.map.width as isize)
-                {
-                    map.tiles[y][x] =
-                        state.map.tiles[(top + y as isize) as usize][(left + x as isize) as usize];
-                } else {
-                    map.tiles[y][x] = Tile::Impassable;
-                }
-            }
-        }
+        // Agents plan on a local window of the map, however large the map is
+        let map = TileMapSnapshot {
+            top: y - config().agents.snapshot_radius as isize,
+            left: x - config().agents.snapshot_radius as isize,
+            tiles: state.map.window(x, y, config().agents.snapshot_radius),
+            elevation: state
+                .map
+                .elevation_window(x, y, config().agents.snapshot_radius),
+        };
 
         WorldLocalState {
             inventory: InventorySnapshot(state.inventory.0.clone()),
             map,
+            predators: state.predators.clone(),
+            market: state.market,
         }
     }
 
     fn apply(state: &mut Self::GlobalState, snapshot: &Self::State, diff: &Self::Diff) {
-        for (agent, AgentInventory { wood, water }) in &diff.inventory.0 {
+        for (
+            agent,
+            AgentInventory {
+                wood,
+                water,
+                food,
+                axe,
+                stored,
+                offer,
+                energy,
+                last_meal,
+                last_rest,
+                coins,
+            },
+        ) in &diff.inventory.0
+        {
             if let Some(inventory) = state.inventory.0.get_mut(agent) {
                 inventory.wood += *wood;
                 inventory.water = *water;
+                inventory.food += *food;
+                inventory.axe = *axe;
+                inventory.stored += *stored;
+                inventory.offer = *offer;
+                inventory.energy += *energy;
+                inventory.last_meal = *last_meal;
+                inventory.last_rest = *last_rest;
+                inventory.coins += *coins;
             }
         }
 
+        if let Some(market) = diff.market {
+            state.market = market;
+        }
+
         for ((x, y), tile) in &diff.map.tiles {
             let dest_y = y + snapshot.map.top;
             let dest_x = x + snapshot.map.left;
             if (dest_y >= 0 && dest_y < state.map.height as isize)
                 && (dest_x >= 0 && dest_x < state.map.width as isize)
             {
-                state.map.tiles[dest_y as usize][dest_x as usize] = *tile;
+                state.map[(dest_x as usize, dest_y as usize)] = *tile;
             }
         }
     }
@@ -185,3 +298,70 @@ impl GlobalDomain for Lumberjacks {
     }
     */
 }
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU8;
+
+    use npc_engine_core::{AgentId, Domain, StateDiffRef};
+    use npc_engine_utils::GlobalDomain;
+
+    use crate::{testing, Lumberjacks, Tile, WorldDiff, WORLD_AGENT_ID};
+
+    const AGENT: AgentId = AgentId(0);
+
+    fn tree(height: u8) -> Tile {
+        Tile::Tree(NonZeroU8::new(height).unwrap())
+    }
+
+    // A lumberjack in the middle of a clearing, with a tree next to it
+    fn clearing(tree: Tile) -> Vec<Vec<Tile>> {
+        let mut rows = vec![vec![Tile::Empty; 5]; 5];
+        rows[1][1] = tree;
+        rows[2][2] = Tile::Agent(AGENT);
+        rows
+    }
+
+    fn value(rows: Vec<Vec<Tile>>, agent: AgentId) -> f32 {
+        let world = testing::world(rows);
+        let state = Lumberjacks::derive_local_state(&world, AGENT);
+        let diff = WorldDiff::default();
+        Lumberjacks::get_current_value(0, StateDiffRef::new(&state, &diff), agent).into_inner()
+    }
+
+    #[test]
+    fn standing_wood_is_worth_its_height() {
+        assert_eq!(value(clearing(tree(3)), AGENT), 3.);
+        assert_eq!(value(clearing(Tile::Empty), AGENT), 0.);
+    }
+
+    #[test]
+    fn young_trees_are_worth_their_growth() {
+        // the forecast of one half per level to grow comes on top of the standing wood
+        assert_eq!(value(clearing(tree(1)), AGENT), 1. + 0.5 * 2.);
+        assert_eq!(value(clearing(tree(2)), AGENT), 2. + 0.5 * 1.);
+    }
+
+    #[test]
+    fn huts_are_worth_their_value() {
+        assert_eq!(value(clearing(Tile::Hut), AGENT), 5.);
+    }
+
+    #[test]
+    fn world_agent_has_no_value() {
+        assert_eq!(value(clearing(tree(1)), WORLD_AGENT_ID), 0.);
+    }
+
+    #[test]
+    fn world_agent_sees_the_centre_of_the_map() {
+        let world = testing::world(clearing(tree(1)));
+        let state = Lumberjacks::derive_local_state(&world, WORLD_AGENT_ID);
+        assert_eq!((state.map.left, state.map.top), (0, 0));
+        assert_eq!(state.map.tiles[1][1], tree(1));
+        assert_eq!(state.map.tiles[2][2], Tile::Agent(AGENT));
+        assert_eq!(
+            state.inventory.0.keys().copied().collect::<Vec<_>>(),
+            vec![AGENT]
+        );
+    }
+}
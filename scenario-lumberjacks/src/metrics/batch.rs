@@ -0,0 +1,48 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::fs;
+use std::io::Write;
+use std::time::Duration;
+
+use crate::output_path;
+
+pub const BATCH_METRICS_HEADER: &str = "run,seed,wood,trees,planning-time-ms";
+
+/// The outcome of one run of a batch, a row of `metrics.csv`.
+pub struct RunMetrics {
+    pub run: usize,
+    pub seed: u64,
+    /// The wood carried and stored by all lumberjacks
+    pub wood: usize,
+    /// The trees still standing, saplings included
+    pub trees: usize,
+    /// The average time of a planning step
+    pub planning_time: Duration,
+}
+
+/// Writes the metrics of every run of a batch to `metrics.csv`, in the output directory.
+pub fn dump_batch_metrics(metrics: &[RunMetrics]) {
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(true)
+        .write(true)
+        .open(format!("{}/metrics.csv", output_path()))
+        .unwrap();
+
+    writeln!(file, "{}", BATCH_METRICS_HEADER).unwrap();
+    for metrics in metrics {
+        writeln!(
+            file,
+            "{},{},{},{},{:.3}",
+            metrics.run,
+            metrics.seed,
+            metrics.wood,
+            metrics.trees,
+            metrics.planning_time.as_secs_f64() * 1000.,
+        )
+        .unwrap();
+    }
+}
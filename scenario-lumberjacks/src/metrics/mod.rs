@@ -4,11 +4,13 @@
  */
 
 mod agency;
+mod batch;
 mod features;
 mod islands;
 mod performance;
 
 pub use agency::*;
+pub use batch::*;
 pub use features::*;
 pub use islands::*;
 pub use performance::*;
@@ -0,0 +1,114 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::collections::BTreeMap;
+
+use macroquad::color::Color;
+use macroquad::shapes::{draw_rectangle, draw_rectangle_lines};
+use npc_engine_core::{AgentId, StateDiffRef, MCTS};
+
+use crate::{config, Lumberjacks, WorldGlobalState, WorldState, SPRITE_SIZE};
+
+/// The debug layers drawn over the map, each toggled with a function key.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DebugLayers {
+    /// The path each agent expects to walk in its last plan (F1)
+    pub plans: bool,
+    /// The points of interest, that agents walk next to (F2)
+    pub points_of_interest: bool,
+    /// The window of the map the next agent plans on (F3)
+    pub windows: bool,
+}
+
+// The positions the planner expects an agent to go through, following the best task of every
+// agent down the search tree
+pub fn predicted_path(mcts: &MCTS<Lumberjacks>, agent: AgentId) -> Vec<(isize, isize)> {
+    let mut path = Vec::new();
+    let mut node = mcts.root_node();
+
+    // Linked nodes can form cycles, so the walk stops at the planning depth
+    for _ in 0..config().mcts.depth {
+        let edge = mcts.get_edges(&node).and_then(|edges| {
+            edges
+                .best_task(node.agent(), 0., mcts.min_max_range(node.agent()))
+                .and_then(|task| edges.get_edge(&task))
+        });
+        match edge {
+            Some(edge) => node = edge.lock().unwrap().child(),
+            None => break,
+        }
+
+        let position = StateDiffRef::new(mcts.initial_state(), node.diff()).find_agent(agent);
+        if let Some(position) = position {
+            if path.last() != Some(&position) {
+                path.push(position);
+            }
+        }
+    }
+
+    path
+}
+
+impl DebugLayers {
+    // Draws the enabled layers over the map, whose top-left tile is at origin
+    pub fn draw(
+        &self,
+        (origin_x, origin_y): (f32, f32),
+        world: &WorldGlobalState,
+        plans: &BTreeMap<AgentId, Vec<(isize, isize)>>,
+        next_agent: Option<AgentId>,
+    ) {
+        if self.points_of_interest {
+            world
+                .map
+                .iter()
+                .filter(|(_, _, tile)| tile.is_point_of_interest())
+                .for_each(|(x, y, _)| {
+                    draw_rectangle_lines(
+                        origin_x + x as f32 * SPRITE_SIZE,
+                        origin_y + y as f32 * SPRITE_SIZE,
+                        SPRITE_SIZE,
+                        SPRITE_SIZE,
+                        2.,
+                        Color::from_rgba(255, 255, 255, 160),
+                    );
+                });
+        }
+
+        if self.plans {
+            for (agent, path) in plans {
+                let color = if agent.0 % 2 == 0 {
+                    Color::from_rgba(255, 128, 0, 200)
+                } else {
+                    Color::from_rgba(255, 224, 0, 200)
+                };
+                for (x, y) in path {
+                    draw_rectangle(
+                        origin_x + (*x as f32 + 1. / 3.) * SPRITE_SIZE,
+                        origin_y + (*y as f32 + 1. / 3.) * SPRITE_SIZE,
+                        SPRITE_SIZE / 3.,
+                        SPRITE_SIZE / 3.,
+                        color,
+                    );
+                }
+            }
+        }
+
+        if self.windows {
+            if let Some((x, y)) = next_agent.and_then(|agent| world.find_agent(agent)) {
+                let radius = config().agents.snapshot_radius as isize;
+                let extent = (2 * radius + 1) as f32 * SPRITE_SIZE;
+                draw_rectangle_lines(
+                    origin_x + (x - radius) as f32 * SPRITE_SIZE,
+                    origin_y + (y - radius) as f32 * SPRITE_SIZE,
+                    extent,
+                    extent,
+                    3.,
+                    Color::from_rgba(0, 192, 255, 220),
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,55 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use npc_engine_core::{AgentId, MCTSConfiguration};
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+
+/// The character of a lumberjack, which scales the terms of its value function
+/// and how much its planner explores.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Personality {
+    /// Scales the worth of the own wood and coins
+    pub greed: f32,
+    /// Scales the penalties for the predators and fires in view
+    pub caution: f32,
+    /// The worth of each wood carried or stored by the other lumberjacks
+    pub cooperation: f32,
+    /// Scales the exploration factor of the planner
+    pub exploration: f32,
+}
+
+impl Default for Personality {
+    fn default() -> Self {
+        Personality {
+            greed: 1.,
+            caution: 1.,
+            cooperation: 0.,
+            exploration: 1.,
+        }
+    }
+}
+
+impl Personality {
+    /// The personality of an agent, the neutral one if none is configured for it.
+    pub fn of(agent: AgentId) -> Self {
+        config()
+            .agents
+            .personalities
+            .get(&(agent.0 as usize))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Derives the configuration of the planner of an agent with this personality from the shared one.
+    pub fn planner_config(&self, base: &MCTSConfiguration) -> MCTSConfiguration {
+        MCTSConfiguration {
+            exploration: base.exploration * self.exploration,
+            ..base.clone()
+        }
+    }
+}
@@ -0,0 +1,219 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+
+use macroquad::color::WHITE;
+use macroquad::input::{
+    get_keys_pressed, is_mouse_button_down, mouse_position, KeyCode, MouseButton,
+};
+use macroquad::shapes::draw_rectangle;
+use macroquad::texture::Texture2D;
+use macroquad::time::get_time;
+use macroquad::window::{clear_background, screen_height, screen_width};
+use npc_engine_core::AgentId;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    background, draw_label, is_night, output_path, Frame, Graphics, PostMCTSHookArgs,
+    PostMCTSHookFn, WorldGlobalState, SPRITE_SIZE,
+};
+
+/// One planning step of a recorded run: the world an agent planned in, its chosen task and the
+/// value it expected from it.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ReplayFrame {
+    pub turn: usize,
+    pub agent: AgentId,
+    pub task: String,
+    pub value: f32,
+    pub world: WorldGlobalState,
+}
+
+// Records every planning step as a line of replay.jsonl
+pub fn replay_hook() -> PostMCTSHookFn {
+    let mut file = None;
+    Box::new(
+        move |PostMCTSHookArgs {
+                  run,
+                  turn,
+                  world,
+                  agent,
+                  mcts,
+                  objective,
+                  ..
+              }| {
+            let file = file.get_or_insert_with(|| {
+                let dir = format!(
+                    "{}/{}/",
+                    output_path(),
+                    run.map(|n| n.to_string()).unwrap_or_default(),
+                );
+                fs::create_dir_all(&dir).unwrap();
+
+                fs::OpenOptions::new()
+                    .create(true)
+                    .write(true)
+                    .truncate(true)
+                    .open(format!("{}replay.jsonl", dir))
+                    .unwrap()
+            });
+
+            let frame = ReplayFrame {
+                turn,
+                agent,
+                task: format!("{:?}", objective),
+                value: mcts.q_value_at_root(agent),
+                world: world.clone(),
+            };
+            serde_json::to_writer(&mut *file, &frame).unwrap();
+            writeln!(file).unwrap();
+        },
+    )
+}
+
+/// Plays back a recorded run, frame by frame.
+pub struct ReplayViewer {
+    frames: Vec<ReplayFrame>,
+    current: usize,
+    playing: bool,
+    // When playback shows the next frame, in seconds
+    next_step: f64,
+    assets: BTreeMap<String, Texture2D>,
+}
+
+impl ReplayViewer {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let file = fs::OpenOptions::new()
+            .read(true)
+            .open(path)
+            .map_err(|e| format!("Cannot open replay {}: {}", path, e))?;
+
+        let frames = BufReader::new(file)
+            .lines()
+            .map(|line| {
+                let line = line.map_err(|e| format!("Cannot read replay {}: {}", path, e))?;
+                serde_json::from_str(&line)
+                    .map_err(|e| format!("Cannot read replay {}: {}", path, e))
+            })
+            .collect::<Result<Vec<ReplayFrame>, _>>()?;
+
+        if frames.is_empty() {
+            return Err(format!("Replay {} has no frames", path));
+        }
+
+        Ok(ReplayViewer {
+            frames,
+            current: 0,
+            playing: false,
+            next_step: 0.,
+            assets: Default::default(),
+        })
+    }
+
+    pub fn add_asset(&mut self, name: String, texture: Texture2D) {
+        self.assets.insert(name, texture);
+    }
+
+    pub fn width(&self) -> usize {
+        self.frames[0].world.map.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.frames[0].world.map.height
+    }
+
+    fn select(&mut self, frame: isize) {
+        self.current = frame.clamp(0, self.frames.len() as isize - 1) as usize;
+    }
+
+    // The timeline is the bottom row of the window, scrubbing selects the frame under the mouse
+    fn scrub(&mut self, x: f32, y: f32) {
+        if y >= screen_height() - SPRITE_SIZE {
+            self.select((x / screen_width() * self.frames.len() as f32) as isize);
+        }
+    }
+
+    // Handles a key press, returns false to quit
+    fn key_down(&mut self, keycode: KeyCode) -> bool {
+        let current = self.current as isize;
+        match keycode {
+            KeyCode::Escape => return false,
+            KeyCode::Space => self.playing = !self.playing,
+            KeyCode::Left => self.select(current - 1),
+            KeyCode::Right => self.select(current + 1),
+            KeyCode::PageUp => self.select(current - 10),
+            KeyCode::PageDown => self.select(current + 10),
+            KeyCode::Home => self.select(0),
+            KeyCode::End => self.select(self.frames.len() as isize),
+            _ => (),
+        }
+        true
+    }
+}
+
+impl Frame for ReplayViewer {
+    fn update(&mut self, _graphics: Graphics) -> bool {
+        for keycode in get_keys_pressed() {
+            if !self.key_down(keycode) {
+                return false;
+            }
+        }
+        if is_mouse_button_down(MouseButton::Left) {
+            let (x, y) = mouse_position();
+            self.scrub(x, y);
+        }
+
+        // Playback shows a few frames per second
+        let now = get_time();
+        while self.next_step <= now {
+            self.next_step += 0.25;
+            if self.playing {
+                if self.current + 1 < self.frames.len() {
+                    self.current += 1;
+                } else {
+                    self.playing = false;
+                }
+            }
+        }
+
+        true
+    }
+
+    fn draw(&mut self, _graphics: Graphics) {
+        let frame = &self.frames[self.current];
+
+        let light = if is_night(frame.turn as u64) { 0.4 } else { 1. };
+        clear_background(background(light));
+
+        draw_label(
+            &format!(
+                "Replay {}/{}{}, turn {}, {:?}: {} (value {:.2})",
+                self.current + 1,
+                self.frames.len(),
+                if self.playing { "" } else { " (paused)" },
+                frame.turn,
+                frame.agent,
+                frame.task,
+                frame.value,
+            ),
+            5.0 * SPRITE_SIZE,
+            0.0 * SPRITE_SIZE,
+        );
+        frame.world.draw(&self.assets);
+
+        let progress = (self.current + 1) as f32 / self.frames.len() as f32;
+        draw_rectangle(
+            0.,
+            screen_height() - SPRITE_SIZE / 4.,
+            screen_width() * progress,
+            SPRITE_SIZE / 4.,
+            WHITE,
+        );
+    }
+}
@@ -7,43 +7,25 @@ use std::collections::BTreeMap;
 use std::fs;
 use std::path::PathBuf;
 
-use ggez::graphics;
-use ggez::graphics::{Canvas, Image};
-use ggez::Context;
 use image::png::PngEncoder;
-use image::{ColorType, ImageBuffer, Rgba};
+use image::ColorType;
+use macroquad::texture::Texture2D;
+use macroquad::window::clear_background;
 
-use crate::{config, output_path, PreWorldHookArgs, PreWorldHookFn, WorldGlobalState};
+use crate::{
+    background, capture, output_path, Graphics, PreWorldHookArgs, PreWorldHookFn, WorldGlobalState,
+};
 
 pub fn screenshot(
-    ctx: &mut Context,
+    graphics: Graphics,
     world: &WorldGlobalState,
-    assets: &BTreeMap<String, Image>,
+    assets: &BTreeMap<String, Texture2D>,
     path: &str,
 ) {
-    let canvas = Canvas::with_window_size(ctx).unwrap();
-    graphics::set_canvas(ctx, Some(&canvas));
-    graphics::clear(
-        ctx,
-        graphics::Color::new(
-            config().display.background.0,
-            config().display.background.1,
-            config().display.background.2,
-            1.,
-        ),
-    );
-
-    world.draw(ctx, assets);
-
-    graphics::present(ctx).unwrap();
-    let image = canvas.image();
-
-    let width = image.width() as u32;
-    let height = image.height() as u32;
-    let image_data: ImageBuffer<Rgba<u8>, _> =
-        ImageBuffer::from_raw(width, height, image.to_rgba8(ctx).unwrap()).unwrap();
-
-    let flipped_image_data = image::imageops::flip_vertical(&image_data);
+    let image = capture(graphics, || {
+        clear_background(background(1.));
+        world.draw(assets);
+    });
 
     let dir = {
         let mut path = PathBuf::from(path);
@@ -60,7 +42,7 @@ pub fn screenshot(
         .unwrap();
 
     PngEncoder::new(file)
-        .encode(&flipped_image_data, width, height, ColorType::Rgba8)
+        .encode(&image, image.width(), image.height(), ColorType::Rgba8)
         .unwrap();
 }
 
@@ -68,15 +50,15 @@ pub fn screenshot_hook() -> PreWorldHookFn {
     Box::new(
         |PreWorldHookArgs {
              world,
-             ctx,
+             graphics,
              assets,
              run,
              turn,
              ..
          }| {
-            if let Some(ctx) = ctx {
+            if let Some(graphics) = graphics {
                 screenshot(
-                    ctx,
+                    graphics,
                     world,
                     assets,
                     &format!(
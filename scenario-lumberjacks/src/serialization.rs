@@ -3,9 +3,49 @@
  *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
  */
 
+use std::collections::BTreeMap;
 use std::fs;
 
-use crate::{output_path, PreWorldHookArgs, PreWorldHookFn};
+use npc_engine_core::AgentId;
+use serde::{Deserialize, Serialize};
+
+use crate::{output_path, PreWorldHookArgs, PreWorldHookFn, WorldGlobalState};
+
+/// Everything needed to resume a game: the world, the turn and agent it is at and the agents busy
+/// with slow tasks.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SavedState {
+    pub turn: usize,
+    #[serde(default)]
+    pub current_agent: usize,
+    pub world: WorldGlobalState,
+    #[serde(default)]
+    pub busy: BTreeMap<AgentId, usize>,
+}
+
+impl SavedState {
+    pub fn save(&self, path: &str) {
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .truncate(true)
+            .write(true)
+            .open(path)
+            .unwrap();
+
+        serde_json::to_writer_pretty(file, self).unwrap();
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let file = fs::OpenOptions::new()
+            .read(true)
+            .open(path)
+            .map_err(|e| format!("Cannot open saved state {}: {}", path, e))?;
+
+        serde_json::from_reader(file)
+            .map_err(|e| format!("Cannot read saved state {}: {}", path, e))
+    }
+}
 
 pub fn world_serialization_hook() -> PreWorldHookFn {
     Box::new(
@@ -0,0 +1,67 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::hash::Hash;
+
+use npc_engine_core::{
+    impl_task_boxed_methods, Context, ContextMut, Domain, IdleTask, Task, TaskDuration,
+};
+use npc_engine_utils::Direction;
+
+use crate::{apply_direction, config, Action, Lumberjacks, Tile, WorldState, WorldStateMut};
+
+/// Builds a bridge over an adjacent water tile, using one wood.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Bridge {
+    pub direction: Direction,
+}
+
+impl Task<Lumberjacks> for Bridge {
+    fn weight(&self, _ctx: Context<Lumberjacks>) -> f32 {
+        config().action_weights.bridge
+    }
+
+    fn duration(&self, _ctx: Context<Lumberjacks>) -> TaskDuration {
+        0
+    }
+
+    fn execute(&self, ctx: ContextMut<Lumberjacks>) -> Option<Box<dyn Task<Lumberjacks>>> {
+        let ContextMut {
+            mut state_diff,
+            agent,
+            ..
+        } = ctx;
+        state_diff.increment_time();
+
+        if let Some((x, y)) = state_diff.find_agent(agent) {
+            let (x, y) = apply_direction(self.direction, x, y);
+            state_diff.set_tile(x, y, Tile::Bridge);
+            state_diff.decrement_inventory(agent);
+
+            Some(Box::new(IdleTask))
+        } else {
+            unreachable!()
+        }
+    }
+
+    fn display_action(&self) -> <Lumberjacks as Domain>::DisplayAction {
+        Action::Bridge(self.direction)
+    }
+
+    fn is_valid(&self, ctx: Context<Lumberjacks>) -> bool {
+        let Context {
+            state_diff, agent, ..
+        } = ctx;
+        if let Some((x, y)) = state_diff.find_agent(agent) {
+            let (x, y) = apply_direction(self.direction, x, y);
+            state_diff.get_inventory(agent) > 0
+                && matches!(state_diff.get_tile(x, y), Some(Tile::Water))
+        } else {
+            unreachable!()
+        }
+    }
+
+    impl_task_boxed_methods!(Lumberjacks);
+}
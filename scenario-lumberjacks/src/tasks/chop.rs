@@ -7,17 +7,25 @@ use std::hash::Hash;
 use std::num::NonZeroU8;
 
 use npc_engine_core::{
-    impl_task_boxed_methods, Context, ContextMut, Domain, IdleTask, Task, TaskDuration,
+    impl_task_boxed_methods, AgentId, Context, ContextMut, Domain, IdleTask, StateDiffRef, Task,
+    TaskDuration,
 };
 use npc_engine_utils::{Direction, DIRECTIONS};
 
-use crate::{apply_direction, config, Action, Lumberjacks, Tile, WorldState, WorldStateMut};
+use crate::{
+    apply_direction, config, is_night, Action, Lumberjacks, Tile, WorldState, WorldStateMut,
+};
 
 // SAFETY: this is safe as 1 is non-zero. This is actually a work-around the fact
 // that Option::unwrap() is currently not const, but we need a constant in the match arm below.
 // See the related Rust issue: https://github.com/rust-lang/rust/issues/67441
 const NON_ZERO_U8_1: NonZeroU8 = unsafe { NonZeroU8::new_unchecked(1) };
 
+// Whether the agent can carry more wood, always true without tools
+fn has_capacity(state_diff: StateDiffRef<Lumberjacks>, agent: AgentId) -> bool {
+    !config().features.tools || state_diff.get_inventory(agent) < config().tools.capacity
+}
+
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub struct Chop {
     pub direction: Direction,
@@ -53,11 +61,22 @@ impl Task<Lumberjacks> for Chop {
                 _ => return Some(Box::new(IdleTask)),
             }
 
+            if config().features.tools {
+                let durability = state_diff.get_axe(agent);
+                state_diff.set_axe(agent, durability.saturating_sub(1));
+            }
+
+            if config().features.day_night {
+                state_diff.add_energy(agent, -1);
+            }
+
             if config().features.teamwork {
                 for direction in DIRECTIONS {
                     let (x, y) = apply_direction(direction, x, y);
                     if let Some(Tile::Agent(agent)) = state_diff.get_tile(x, y) {
-                        state_diff.increment_inventory(agent);
+                        if has_capacity(*state_diff, agent) {
+                            state_diff.increment_inventory(agent);
+                        }
                     }
                 }
             } else {
@@ -76,11 +95,19 @@ impl Task<Lumberjacks> for Chop {
 
     fn is_valid(&self, ctx: Context<Lumberjacks>) -> bool {
         let Context {
-            state_diff, agent, ..
+            tick,
+            state_diff,
+            agent,
         } = ctx;
+        // It is too dark to chop at night, and chopping takes energy
+        if config().features.day_night && (is_night(tick) || state_diff.get_energy(agent) == 0) {
+            return false;
+        }
         if let Some((x, y)) = state_diff.find_agent(agent) {
             let (x, y) = apply_direction(self.direction, x, y);
-            matches!(state_diff.get_tile(x, y), Some(Tile::Tree(_)))
+            let equipped = !config().features.tools
+                || (state_diff.get_axe(agent) > 0 && has_capacity(state_diff, agent));
+            equipped && matches!(state_diff.get_tile(x, y), Some(Tile::Tree(_)))
         } else {
             unreachable!("Could not find agent on map!")
         }
@@ -0,0 +1,181 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::hash::Hash;
+
+use npc_engine_core::{
+    impl_task_boxed_methods, Context, ContextMut, Domain, IdleTask, Task, TaskDuration,
+};
+use npc_engine_utils::Direction;
+
+use crate::{apply_direction, config, Action, Lumberjacks, Tile, WorldState, WorldStateMut};
+
+/// Lays out a construction site for a hut on an adjacent empty tile, when carrying wood to deliver to it.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Found {
+    pub direction: Direction,
+}
+
+impl Task<Lumberjacks> for Found {
+    fn weight(&self, _ctx: Context<Lumberjacks>) -> f32 {
+        config().action_weights.found
+    }
+
+    fn duration(&self, _ctx: Context<Lumberjacks>) -> TaskDuration {
+        0
+    }
+
+    fn execute(&self, ctx: ContextMut<Lumberjacks>) -> Option<Box<dyn Task<Lumberjacks>>> {
+        let ContextMut {
+            mut state_diff,
+            agent,
+            ..
+        } = ctx;
+        state_diff.increment_time();
+
+        if let Some((x, y)) = state_diff.find_agent(agent) {
+            let (x, y) = apply_direction(self.direction, x, y);
+            state_diff.set_tile(x, y, Tile::Site(0));
+
+            Some(Box::new(IdleTask))
+        } else {
+            unreachable!()
+        }
+    }
+
+    fn display_action(&self) -> <Lumberjacks as Domain>::DisplayAction {
+        Action::Found(self.direction)
+    }
+
+    fn is_valid(&self, ctx: Context<Lumberjacks>) -> bool {
+        let Context {
+            state_diff, agent, ..
+        } = ctx;
+        if let Some((x, y)) = state_diff.find_agent(agent) {
+            let (x, y) = apply_direction(self.direction, x, y);
+            state_diff.get_inventory(agent) > 0
+                && matches!(state_diff.get_tile(x, y), Some(Tile::Empty))
+        } else {
+            unreachable!()
+        }
+    }
+
+    impl_task_boxed_methods!(Lumberjacks);
+}
+
+/// Delivers one carried wood to an adjacent construction site, which any lumberjack can do.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Deliver {
+    pub direction: Direction,
+}
+
+impl Task<Lumberjacks> for Deliver {
+    fn weight(&self, _ctx: Context<Lumberjacks>) -> f32 {
+        config().action_weights.deliver
+    }
+
+    fn duration(&self, _ctx: Context<Lumberjacks>) -> TaskDuration {
+        0
+    }
+
+    fn execute(&self, ctx: ContextMut<Lumberjacks>) -> Option<Box<dyn Task<Lumberjacks>>> {
+        let ContextMut {
+            mut state_diff,
+            agent,
+            ..
+        } = ctx;
+        state_diff.increment_time();
+
+        if let Some((x, y)) = state_diff.find_agent(agent) {
+            let (x, y) = apply_direction(self.direction, x, y);
+            match state_diff.get_tile_ref_mut(x, y) {
+                Some(Tile::Site(wood)) => *wood = wood.saturating_add(1),
+                _ => return Some(Box::new(IdleTask)),
+            }
+            state_diff.decrement_inventory(agent);
+
+            Some(Box::new(IdleTask))
+        } else {
+            unreachable!()
+        }
+    }
+
+    fn display_action(&self) -> <Lumberjacks as Domain>::DisplayAction {
+        Action::Deliver(self.direction)
+    }
+
+    fn is_valid(&self, ctx: Context<Lumberjacks>) -> bool {
+        let Context {
+            state_diff, agent, ..
+        } = ctx;
+        if let Some((x, y)) = state_diff.find_agent(agent) {
+            let (x, y) = apply_direction(self.direction, x, y);
+            state_diff.get_inventory(agent) > 0
+                && matches!(
+                    state_diff.get_tile(x, y),
+                    Some(Tile::Site(wood)) if (wood as usize) < config().construction.cost
+                )
+        } else {
+            unreachable!()
+        }
+    }
+
+    impl_task_boxed_methods!(Lumberjacks);
+}
+
+/// Turns an adjacent construction site that received all its wood into a hut.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Build {
+    pub direction: Direction,
+}
+
+impl Task<Lumberjacks> for Build {
+    fn weight(&self, _ctx: Context<Lumberjacks>) -> f32 {
+        config().action_weights.build
+    }
+
+    fn duration(&self, _ctx: Context<Lumberjacks>) -> TaskDuration {
+        0
+    }
+
+    fn execute(&self, ctx: ContextMut<Lumberjacks>) -> Option<Box<dyn Task<Lumberjacks>>> {
+        let ContextMut {
+            mut state_diff,
+            agent,
+            ..
+        } = ctx;
+        state_diff.increment_time();
+
+        if let Some((x, y)) = state_diff.find_agent(agent) {
+            let (x, y) = apply_direction(self.direction, x, y);
+            state_diff.set_tile(x, y, Tile::Hut);
+
+            Some(Box::new(IdleTask))
+        } else {
+            unreachable!()
+        }
+    }
+
+    fn display_action(&self) -> <Lumberjacks as Domain>::DisplayAction {
+        Action::Build(self.direction)
+    }
+
+    fn is_valid(&self, ctx: Context<Lumberjacks>) -> bool {
+        let Context {
+            state_diff, agent, ..
+        } = ctx;
+        if let Some((x, y)) = state_diff.find_agent(agent) {
+            let (x, y) = apply_direction(self.direction, x, y);
+            matches!(
+                state_diff.get_tile(x, y),
+                Some(Tile::Site(wood)) if wood as usize >= config().construction.cost
+            )
+        } else {
+            unreachable!()
+        }
+    }
+
+    impl_task_boxed_methods!(Lumberjacks);
+}
@@ -0,0 +1,77 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::hash::Hash;
+
+use npc_engine_core::{
+    impl_task_boxed_methods, AgentId, Context, ContextMut, Domain, IdleTask, StateDiffRef, Task,
+    TaskDuration,
+};
+use npc_engine_utils::Direction;
+
+use crate::{apply_direction, config, Action, Lumberjacks, Tile, WorldState, WorldStateMut};
+
+/// Walks over the bridge tiles in a direction, up to the walkable tile on the other side.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Cross {
+    pub direction: Direction,
+}
+
+impl Cross {
+    // Returns the tile reached on the other side of the bridge, if any
+    fn destination(
+        &self,
+        state_diff: StateDiffRef<Lumberjacks>,
+        agent: AgentId,
+    ) -> Option<(isize, isize)> {
+        let (mut x, mut y) = state_diff.find_agent(agent).unwrap();
+        let mut on_bridge = false;
+        loop {
+            (x, y) = apply_direction(self.direction, x, y);
+            match state_diff.get_tile(x, y) {
+                Some(Tile::Bridge) => on_bridge = true,
+                Some(tile) if on_bridge && tile.is_walkable() => return Some((x, y)),
+                _ => return None,
+            }
+        }
+    }
+}
+
+impl Task<Lumberjacks> for Cross {
+    fn weight(&self, _ctx: Context<Lumberjacks>) -> f32 {
+        config().action_weights.cross
+    }
+
+    fn duration(&self, _ctx: Context<Lumberjacks>) -> TaskDuration {
+        0
+    }
+
+    fn execute(&self, ctx: ContextMut<Lumberjacks>) -> Option<Box<dyn Task<Lumberjacks>>> {
+        let ContextMut {
+            mut state_diff,
+            agent,
+            ..
+        } = ctx;
+        state_diff.increment_time();
+
+        if let Some((_x, _y)) = self.destination(*state_diff, agent) {
+            let (x, y) = state_diff.find_agent(agent).unwrap();
+            state_diff.set_tile(x, y, Tile::Empty);
+            state_diff.set_tile(_x, _y, Tile::Agent(agent));
+        }
+
+        Some(Box::new(IdleTask))
+    }
+
+    fn display_action(&self) -> <Lumberjacks as Domain>::DisplayAction {
+        Action::Cross(self.direction)
+    }
+
+    fn is_valid(&self, ctx: Context<Lumberjacks>) -> bool {
+        self.destination(ctx.state_diff, ctx.agent).is_some()
+    }
+
+    impl_task_boxed_methods!(Lumberjacks);
+}
@@ -0,0 +1,52 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::hash::Hash;
+
+use npc_engine_core::{
+    impl_task_boxed_methods, Context, ContextMut, Domain, IdleTask, Task, TaskDuration,
+};
+
+use crate::{config, Action, Lumberjacks, WorldState, WorldStateMut};
+
+/// Stores all the carried wood of a lumberjack in an adjacent shed.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Deposit;
+
+impl Task<Lumberjacks> for Deposit {
+    fn weight(&self, _ctx: Context<Lumberjacks>) -> f32 {
+        config().action_weights.deposit
+    }
+
+    fn duration(&self, _ctx: Context<Lumberjacks>) -> TaskDuration {
+        0
+    }
+
+    fn execute(&self, ctx: ContextMut<Lumberjacks>) -> Option<Box<dyn Task<Lumberjacks>>> {
+        let ContextMut {
+            mut state_diff,
+            agent,
+            ..
+        } = ctx;
+        state_diff.increment_time();
+
+        state_diff.store_wood(agent);
+
+        Some(Box::new(IdleTask))
+    }
+
+    fn display_action(&self) -> <Lumberjacks as Domain>::DisplayAction {
+        Action::Deposit
+    }
+
+    fn is_valid(&self, ctx: Context<Lumberjacks>) -> bool {
+        let Context {
+            state_diff, agent, ..
+        } = ctx;
+        state_diff.get_inventory(agent) > 0 && state_diff.is_next_to_shed(agent)
+    }
+
+    impl_task_boxed_methods!(Lumberjacks);
+}
@@ -0,0 +1,62 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::hash::Hash;
+
+use npc_engine_core::{
+    impl_task_boxed_methods, Context, ContextMut, Domain, IdleTask, Task, TaskDuration,
+};
+use npc_engine_utils::Direction;
+
+use crate::{apply_direction, config, Action, Lumberjacks, Tile, WorldState, WorldStateMut};
+
+/// Eats berries from an adjacent bush, which resets the hunger of a lumberjack.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Eat {
+    pub direction: Direction,
+}
+
+impl Task<Lumberjacks> for Eat {
+    fn weight(&self, _ctx: Context<Lumberjacks>) -> f32 {
+        config().action_weights.eat
+    }
+
+    fn duration(&self, _ctx: Context<Lumberjacks>) -> TaskDuration {
+        0
+    }
+
+    fn execute(&self, ctx: ContextMut<Lumberjacks>) -> Option<Box<dyn Task<Lumberjacks>>> {
+        let ContextMut {
+            tick,
+            mut state_diff,
+            agent,
+        } = ctx;
+        state_diff.increment_time();
+        state_diff.set_last_meal(agent, tick);
+
+        Some(Box::new(IdleTask))
+    }
+
+    fn display_action(&self) -> <Lumberjacks as Domain>::DisplayAction {
+        Action::Eat(self.direction)
+    }
+
+    fn is_valid(&self, ctx: Context<Lumberjacks>) -> bool {
+        let Context {
+            tick,
+            state_diff,
+            agent,
+        } = ctx;
+        if let Some((x, y)) = state_diff.find_agent(agent) {
+            let (x, y) = apply_direction(self.direction, x, y);
+            state_diff.hunger(tick, agent) > 0
+                && matches!(state_diff.get_tile(x, y), Some(Tile::Bush))
+        } else {
+            unreachable!()
+        }
+    }
+
+    impl_task_boxed_methods!(Lumberjacks);
+}
@@ -0,0 +1,52 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::hash::Hash;
+
+use npc_engine_core::{
+    impl_task_boxed_methods, Context, ContextMut, Domain, IdleTask, Task, TaskDuration,
+};
+
+use crate::{config, Action, Lumberjacks, WorldState, WorldStateMut};
+
+/// Fetches a new axe from an adjacent shed.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Fetch;
+
+impl Task<Lumberjacks> for Fetch {
+    fn weight(&self, _ctx: Context<Lumberjacks>) -> f32 {
+        config().action_weights.fetch
+    }
+
+    fn duration(&self, _ctx: Context<Lumberjacks>) -> TaskDuration {
+        0
+    }
+
+    fn execute(&self, ctx: ContextMut<Lumberjacks>) -> Option<Box<dyn Task<Lumberjacks>>> {
+        let ContextMut {
+            mut state_diff,
+            agent,
+            ..
+        } = ctx;
+        state_diff.increment_time();
+
+        state_diff.set_axe(agent, config().tools.durability);
+
+        Some(Box::new(IdleTask))
+    }
+
+    fn display_action(&self) -> <Lumberjacks as Domain>::DisplayAction {
+        Action::Fetch
+    }
+
+    fn is_valid(&self, ctx: Context<Lumberjacks>) -> bool {
+        let Context {
+            state_diff, agent, ..
+        } = ctx;
+        state_diff.get_axe(agent) < config().tools.durability && state_diff.is_next_to_shed(agent)
+    }
+
+    impl_task_boxed_methods!(Lumberjacks);
+}
@@ -0,0 +1,108 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::hash::Hash;
+
+use npc_engine_core::{
+    impl_task_boxed_methods, Context, ContextMut, Domain, IdleTask, Task, TaskDuration,
+};
+use npc_engine_utils::{Direction, DIRECTIONS};
+
+use crate::{apply_direction, config, Action, Lumberjacks, Tile, WorldState, WorldStateMut};
+
+/// The task of the world agent, letting every fire ignite the adjacent trees and then burn out
+/// every fire period.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Spread;
+
+impl Task<Lumberjacks> for Spread {
+    fn duration(&self, _ctx: Context<Lumberjacks>) -> TaskDuration {
+        config().fire.period
+    }
+
+    fn execute(&self, ctx: ContextMut<Lumberjacks>) -> Option<Box<dyn Task<Lumberjacks>>> {
+        let ContextMut { mut state_diff, .. } = ctx;
+
+        let fires = state_diff.fires();
+        for &(x, y) in &fires {
+            for direction in DIRECTIONS {
+                let (x, y) = apply_direction(direction, x, y);
+                if matches!(state_diff.get_tile(x, y), Some(Tile::Tree(_))) {
+                    state_diff.set_tile(x, y, Tile::Fire);
+                }
+            }
+        }
+        for (x, y) in fires {
+            state_diff.set_tile(x, y, Tile::Empty);
+        }
+
+        None
+    }
+
+    fn display_action(&self) -> <Lumberjacks as Domain>::DisplayAction {
+        Action::Wait
+    }
+
+    fn is_valid(&self, _ctx: Context<Lumberjacks>) -> bool {
+        true
+    }
+
+    impl_task_boxed_methods!(Lumberjacks);
+}
+
+/// Puts out an adjacent fire with the water carried.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Extinguish {
+    pub direction: Direction,
+}
+
+impl Task<Lumberjacks> for Extinguish {
+    fn weight(&self, _ctx: Context<Lumberjacks>) -> f32 {
+        config().action_weights.extinguish
+    }
+
+    fn duration(&self, _ctx: Context<Lumberjacks>) -> TaskDuration {
+        0
+    }
+
+    fn execute(&self, ctx: ContextMut<Lumberjacks>) -> Option<Box<dyn Task<Lumberjacks>>> {
+        let ContextMut {
+            mut state_diff,
+            agent,
+            ..
+        } = ctx;
+        state_diff.increment_time();
+
+        if let Some((x, y)) = state_diff.find_agent(agent) {
+            state_diff.set_water(agent, false);
+
+            let (x, y) = apply_direction(self.direction, x, y);
+            state_diff.set_tile(x, y, Tile::Empty);
+
+            Some(Box::new(IdleTask))
+        } else {
+            unreachable!("Failed to find agent on map");
+        }
+    }
+
+    fn display_action(&self) -> <Lumberjacks as Domain>::DisplayAction {
+        Action::Extinguish(self.direction)
+    }
+
+    fn is_valid(&self, ctx: Context<Lumberjacks>) -> bool {
+        let Context {
+            state_diff, agent, ..
+        } = ctx;
+        state_diff.get_water(agent)
+            && if let Some((x, y)) = state_diff.find_agent(agent) {
+                let (x, y) = apply_direction(self.direction, x, y);
+                matches!(state_diff.get_tile(x, y), Some(Tile::Fire))
+            } else {
+                unreachable!("Failed to find agent on map");
+            }
+    }
+
+    impl_task_boxed_methods!(Lumberjacks);
+}
@@ -0,0 +1,79 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::hash::Hash;
+
+use npc_engine_core::{
+    impl_task_boxed_methods, Context, ContextMut, Domain, IdleTask, Task, TaskDuration,
+};
+use npc_engine_utils::Direction;
+
+use crate::{apply_direction, climb, config, Action, Lumberjacks, Tile, WorldState, WorldStateMut};
+
+/// Moves a predator away from the closest fire within its fire radius.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Flee {
+    pub direction: Direction,
+}
+
+impl Task<Lumberjacks> for Flee {
+    fn weight(&self, _ctx: Context<Lumberjacks>) -> f32 {
+        config().action_weights.flee
+    }
+
+    fn duration(&self, _ctx: Context<Lumberjacks>) -> TaskDuration {
+        0
+    }
+
+    fn execute(&self, ctx: ContextMut<Lumberjacks>) -> Option<Box<dyn Task<Lumberjacks>>> {
+        let ContextMut {
+            mut state_diff,
+            agent,
+            ..
+        } = ctx;
+        state_diff.increment_time();
+
+        if let Some((x, y)) = state_diff.find_agent(agent) {
+            let (_x, _y) = apply_direction(self.direction, x, y);
+            state_diff.set_tile(x, y, Tile::Empty);
+            state_diff.set_tile(_x, _y, Tile::Agent(agent));
+
+            Some(Box::new(IdleTask))
+        } else {
+            unreachable!()
+        }
+    }
+
+    fn display_action(&self) -> <Lumberjacks as Domain>::DisplayAction {
+        Action::Flee(self.direction)
+    }
+
+    fn is_valid(&self, ctx: Context<Lumberjacks>) -> bool {
+        let Context {
+            state_diff, agent, ..
+        } = ctx;
+        if let Some((x, y)) = state_diff.find_agent(agent) {
+            let radius = config().predators.fire_radius;
+            let (_x, _y) = apply_direction(self.direction, x, y);
+            match state_diff.distance_to_fire(x, y, radius) {
+                Some(distance) => {
+                    state_diff
+                        .get_tile(_x, _y)
+                        .map(|tile| tile.is_walkable())
+                        .unwrap_or(false)
+                        && climb(&state_diff, (x, y), (_x, _y)).is_some()
+                        && state_diff
+                            .distance_to_fire(_x, _y, radius + 1)
+                            .map_or(true, |new_distance| new_distance > distance)
+                }
+                None => false,
+            }
+        } else {
+            unreachable!()
+        }
+    }
+
+    impl_task_boxed_methods!(Lumberjacks);
+}
@@ -0,0 +1,75 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::hash::Hash;
+
+use npc_engine_core::{
+    impl_task_boxed_methods, Context, ContextMut, Domain, IdleTask, Task, TaskDuration,
+};
+use npc_engine_utils::Direction;
+
+use crate::{apply_direction, config, Action, Lumberjacks, Tile, WorldState, WorldStateMut};
+
+/// Attacks an adjacent lumberjack, which drops some of its wood, feeding the predator.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Hunt {
+    pub direction: Direction,
+}
+
+impl Task<Lumberjacks> for Hunt {
+    fn weight(&self, _ctx: Context<Lumberjacks>) -> f32 {
+        config().action_weights.hunt
+    }
+
+    fn duration(&self, _ctx: Context<Lumberjacks>) -> TaskDuration {
+        0
+    }
+
+    fn execute(&self, ctx: ContextMut<Lumberjacks>) -> Option<Box<dyn Task<Lumberjacks>>> {
+        let ContextMut {
+            mut state_diff,
+            agent,
+            ..
+        } = ctx;
+        state_diff.increment_time();
+
+        if let Some((x, y)) = state_diff.find_agent(agent) {
+            let (x, y) = apply_direction(self.direction, x, y);
+            if let Some(Tile::Agent(prey)) = state_diff.get_tile(x, y) {
+                let bite = state_diff.predator_kind(agent).unwrap().bite();
+                let dropped = bite.min(state_diff.get_inventory(prey) as isize);
+                for _ in 0..dropped {
+                    state_diff.decrement_inventory(prey);
+                }
+                state_diff.increment_food(agent);
+            }
+
+            Some(Box::new(IdleTask))
+        } else {
+            unreachable!("Could not find agent on map!")
+        }
+    }
+
+    fn display_action(&self) -> <Lumberjacks as Domain>::DisplayAction {
+        Action::Hunt(self.direction)
+    }
+
+    fn is_valid(&self, ctx: Context<Lumberjacks>) -> bool {
+        let Context {
+            state_diff, agent, ..
+        } = ctx;
+        if let Some((x, y)) = state_diff.find_agent(agent) {
+            let (x, y) = apply_direction(self.direction, x, y);
+            matches!(
+                state_diff.get_tile(x, y),
+                Some(Tile::Agent(prey)) if state_diff.predator_kind(prey).is_none()
+            )
+        } else {
+            unreachable!("Could not find agent on map!")
+        }
+    }
+
+    impl_task_boxed_methods!(Lumberjacks);
+}
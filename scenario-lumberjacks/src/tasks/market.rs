@@ -0,0 +1,82 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::hash::Hash;
+
+use npc_engine_core::{
+    impl_task_boxed_methods, Context, ContextMut, Domain, IdleTask, Task, TaskDuration,
+};
+
+use crate::{config, Action, Lumberjacks, WorldState, WorldStateMut};
+
+/// Sells all the carried wood of a lumberjack at the current market price.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Sell;
+
+impl Task<Lumberjacks> for Sell {
+    fn weight(&self, _ctx: Context<Lumberjacks>) -> f32 {
+        config().action_weights.sell
+    }
+
+    fn duration(&self, _ctx: Context<Lumberjacks>) -> TaskDuration {
+        0
+    }
+
+    fn execute(&self, ctx: ContextMut<Lumberjacks>) -> Option<Box<dyn Task<Lumberjacks>>> {
+        let ContextMut {
+            mut state_diff,
+            agent,
+            ..
+        } = ctx;
+        state_diff.increment_time();
+
+        state_diff.sell_wood(agent);
+
+        Some(Box::new(IdleTask))
+    }
+
+    fn display_action(&self) -> <Lumberjacks as Domain>::DisplayAction {
+        Action::Sell
+    }
+
+    fn is_valid(&self, ctx: Context<Lumberjacks>) -> bool {
+        let Context {
+            state_diff, agent, ..
+        } = ctx;
+        state_diff.get_inventory(agent) > 0
+    }
+
+    impl_task_boxed_methods!(Lumberjacks);
+}
+
+/// The task of the world agent, moving the wood price along with the wood sold every market
+/// period.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Reprice;
+
+impl Task<Lumberjacks> for Reprice {
+    fn duration(&self, _ctx: Context<Lumberjacks>) -> TaskDuration {
+        config().market.period
+    }
+
+    fn execute(&self, ctx: ContextMut<Lumberjacks>) -> Option<Box<dyn Task<Lumberjacks>>> {
+        let ContextMut { mut state_diff, .. } = ctx;
+
+        let market = state_diff.get_market().repriced();
+        state_diff.set_market(market);
+
+        None
+    }
+
+    fn display_action(&self) -> <Lumberjacks as Domain>::DisplayAction {
+        Action::Wait
+    }
+
+    fn is_valid(&self, _ctx: Context<Lumberjacks>) -> bool {
+        true
+    }
+
+    impl_task_boxed_methods!(Lumberjacks);
+}
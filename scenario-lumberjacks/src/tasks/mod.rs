@@ -4,17 +4,47 @@
  */
 
 mod barrier;
+mod bridge;
 mod chop;
+mod construction;
+mod cross;
+mod deposit;
+mod eat;
+mod fetch;
+mod fire;
+mod flee;
+mod hunt;
+mod market;
 mod r#move;
 mod plant;
 mod refill;
+mod regrow;
+mod repair;
+mod rest;
+mod roam;
+mod trade;
 mod wait;
 mod water;
 
 pub use barrier::*;
+pub use bridge::*;
 pub use chop::*;
+pub use construction::*;
+pub use cross::*;
+pub use deposit::*;
+pub use eat::*;
+pub use fetch::*;
+pub use fire::*;
+pub use flee::*;
+pub use hunt::*;
+pub use market::*;
 pub use plant::*;
 pub use r#move::*;
 pub use refill::*;
+pub use regrow::*;
+pub use repair::*;
+pub use rest::*;
+pub use roam::*;
+pub use trade::*;
 pub use wait::*;
 pub use water::*;
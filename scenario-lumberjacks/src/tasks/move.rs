@@ -8,9 +8,64 @@ use std::hash::Hash;
 use npc_engine_core::{
     impl_task_boxed_methods, Context, ContextMut, Domain, IdleTask, Task, TaskDuration,
 };
-use npc_engine_utils::Direction;
+use npc_engine_utils::{astar, Direction, Path, DIRECTIONS};
 
-use crate::{apply_direction, config, Action, Lumberjacks, Tile, WorldState, WorldStateMut};
+use crate::{
+    apply_direction, config, from_direction, is_night, Action, Lumberjacks, Tile, WorldState,
+    WorldStateMut,
+};
+
+/// The extra ticks it takes to step between two adjacent tiles because of the climb,
+/// or none if the slope between them is too steep to be walked.
+/// The ground counts as flat unless the terrain feature is enabled.
+pub fn climb(
+    state: &impl WorldState,
+    from: (isize, isize),
+    to: (isize, isize),
+) -> Option<TaskDuration> {
+    if !config().features.terrain {
+        return Some(0);
+    }
+    let from = state.get_elevation(from.0, from.1)?;
+    let to = state.get_elevation(to.0, to.1)?;
+    if from.abs_diff(to) > config().terrain.max_slope {
+        None
+    } else {
+        Some(to.saturating_sub(from) as TaskDuration * config().terrain.climb_cost)
+    }
+}
+
+/// The steps of a path from a tile, with their cost in ticks, through tiles that may be
+/// temporarily blocked by other agents.
+pub fn walking_steps(
+    state: &impl WorldState,
+    from: (isize, isize),
+) -> impl Iterator<Item = ((isize, isize), u32)> + '_ {
+    DIRECTIONS.into_iter().filter_map(move |direction| {
+        let to = apply_direction(direction, from.0, from.1);
+        let pathfindable = state
+            .get_tile(to.0, to.1)
+            .map(|tile| tile.is_pathfindable())
+            .unwrap_or(false);
+        if pathfindable {
+            climb(state, from, to).map(|ticks| (to, 1 + ticks as u32))
+        } else {
+            None
+        }
+    })
+}
+
+// The climb of the agent stepping in the given direction
+fn walk_climb(ctx: Context<Lumberjacks>, direction: Direction) -> TaskDuration {
+    let Context {
+        state_diff, agent, ..
+    } = ctx;
+    if let Some((x, y)) = state_diff.find_agent(agent) {
+        climb(&state_diff, (x, y), apply_direction(direction, x, y)).unwrap_or(0)
+    } else {
+        unreachable!()
+    }
+}
 
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub struct Move {
@@ -24,8 +79,10 @@ impl Task<Lumberjacks> for Move {
         config().action_weights.r#move
     }
 
-    fn duration(&self, _ctx: Context<Lumberjacks>) -> TaskDuration {
-        0
+    fn duration(&self, ctx: Context<Lumberjacks>) -> TaskDuration {
+        // Walking in the dark takes an extra tick, and so does every level climbed
+        let night = if is_night(ctx.tick) { 1 } else { 0 };
+        night + walk_climb(ctx, *self.path.first().unwrap())
     }
 
     fn execute(&self, ctx: ContextMut<Lumberjacks>) -> Option<Box<dyn Task<Lumberjacks>>> {
@@ -71,6 +128,7 @@ impl Task<Lumberjacks> for Move {
         } = ctx;
         if let Some((mut x, mut y)) = state_diff.find_agent(agent) {
             self.path.iter().enumerate().all(|(idx, direction)| {
+                let from = (x, y);
                 let tmp = apply_direction(*direction, x, y);
                 x = tmp.0;
                 y = tmp.1;
@@ -84,6 +142,7 @@ impl Task<Lumberjacks> for Move {
                         }
                     })
                     .unwrap_or(false)
+                    && climb(&state_diff, from, (x, y)).is_some()
             })
         } else {
             unreachable!()
@@ -92,3 +151,104 @@ impl Task<Lumberjacks> for Move {
 
     impl_task_boxed_methods!(Lumberjacks);
 }
+
+/// Walks to a tile, one step at a time, along the quickest path to it.
+///
+/// The path is searched again after each step, so the task follows the world as it changes and
+/// stays valid as long as the tile can be reached.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct MoveTo {
+    pub x: isize,
+    pub y: isize,
+    /// The first step of the path
+    pub direction: Direction,
+}
+
+fn path(
+    state: &impl WorldState,
+    start: (isize, isize),
+    goal: (isize, isize),
+) -> Option<Path<(isize, isize)>> {
+    astar(
+        start,
+        goal,
+        |from| walking_steps(state, from),
+        |(x, y)| ((x - goal.0).abs() + (y - goal.1).abs()) as u32,
+    )
+}
+
+// Whether the agent can take the step right away
+fn can_step(state: &impl WorldState, from: (isize, isize), (x, y): (isize, isize)) -> bool {
+    state
+        .get_tile(x, y)
+        .map(|tile| tile.is_walkable())
+        .unwrap_or(false)
+        && climb(state, from, (x, y)).is_some()
+}
+
+impl MoveTo {
+    /// Returns the task of walking from `start` to the tile `(x, y)`, if there is a path.
+    pub fn new(state: &impl WorldState, start: (isize, isize), x: isize, y: isize) -> Option<Self> {
+        let first = path(state, start, (x, y))?.first()?;
+        can_step(state, start, first).then(|| MoveTo {
+            x,
+            y,
+            direction: from_direction(start, first),
+        })
+    }
+}
+
+impl Task<Lumberjacks> for MoveTo {
+    fn weight(&self, _ctx: Context<Lumberjacks>) -> f32 {
+        config().action_weights.r#move
+    }
+
+    fn duration(&self, ctx: Context<Lumberjacks>) -> TaskDuration {
+        // Walking in the dark takes an extra tick, and so does every level climbed
+        let night = if is_night(ctx.tick) { 1 } else { 0 };
+        night + walk_climb(ctx, self.direction)
+    }
+
+    fn execute(&self, ctx: ContextMut<Lumberjacks>) -> Option<Box<dyn Task<Lumberjacks>>> {
+        let ContextMut {
+            mut state_diff,
+            agent,
+            ..
+        } = ctx;
+        state_diff.increment_time();
+
+        if let Some((x, y)) = state_diff.find_agent(agent) {
+            let (x_, y_) = apply_direction(self.direction, x, y);
+            state_diff.set_tile(x, y, Tile::Empty);
+            state_diff.set_tile(x_, y_, Tile::Agent(agent));
+
+            // Keep walking until the target is reached or cannot be anymore
+            match MoveTo::new(&*state_diff, (x_, y_), self.x, self.y) {
+                Some(task) => Some(Box::new(task)),
+                None => Some(Box::new(IdleTask)),
+            }
+        } else {
+            unreachable!()
+        }
+    }
+
+    fn display_action(&self) -> <Lumberjacks as Domain>::DisplayAction {
+        Action::Walk(self.direction)
+    }
+
+    fn is_valid(&self, ctx: Context<Lumberjacks>) -> bool {
+        let Context {
+            state_diff, agent, ..
+        } = ctx;
+        if let Some((x, y)) = state_diff.find_agent(agent) {
+            // The first step must be free, the rest of the way may be blocked by agents for now
+            let next = apply_direction(self.direction, x, y);
+            can_step(&state_diff, (x, y), next)
+                && path(&state_diff, next, (self.x, self.y)).is_some()
+        } else {
+            unreachable!()
+        }
+    }
+
+    impl_task_boxed_methods!(Lumberjacks);
+}
@@ -13,6 +13,7 @@ use npc_engine_utils::Direction;
 
 use crate::{apply_direction, config, Action, Lumberjacks, Tile, WorldState, WorldStateMut};
 
+/// Plants a sapling using one carried wood, an investment that only pays off once it has grown.
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub struct Plant {
     pub direction: Direction,
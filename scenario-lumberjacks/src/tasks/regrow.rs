@@ -0,0 +1,45 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::hash::Hash;
+
+use npc_engine_core::{impl_task_boxed_methods, Context, ContextMut, Domain, Task, TaskDuration};
+
+use crate::{config, Action, Lumberjacks, WorldState, WorldStateMut};
+
+/// The task of the world agent, growing all trees by one level every regrowth period.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Regrow;
+
+impl Task<Lumberjacks> for Regrow {
+    fn duration(&self, _ctx: Context<Lumberjacks>) -> TaskDuration {
+        config().regrowth.period
+    }
+
+    fn execute(&self, ctx: ContextMut<Lumberjacks>) -> Option<Box<dyn Task<Lumberjacks>>> {
+        let ContextMut { mut state_diff, .. } = ctx;
+
+        for (x, y) in state_diff.trees() {
+            if let Some(tile) = state_diff.get_tile(x, y) {
+                let regrown = tile.regrown();
+                if regrown != tile {
+                    state_diff.set_tile(x, y, regrown);
+                }
+            }
+        }
+
+        None
+    }
+
+    fn display_action(&self) -> <Lumberjacks as Domain>::DisplayAction {
+        Action::Wait
+    }
+
+    fn is_valid(&self, _ctx: Context<Lumberjacks>) -> bool {
+        true
+    }
+
+    impl_task_boxed_methods!(Lumberjacks);
+}
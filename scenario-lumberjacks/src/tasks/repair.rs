@@ -0,0 +1,56 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::hash::Hash;
+
+use npc_engine_core::{
+    impl_task_boxed_methods, Context, ContextMut, Domain, IdleTask, Task, TaskDuration,
+};
+
+use crate::{config, Action, Lumberjacks, WorldState, WorldStateMut};
+
+/// Repairs the axe of a lumberjack to full durability, using some of its carried wood.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Repair;
+
+impl Task<Lumberjacks> for Repair {
+    fn weight(&self, _ctx: Context<Lumberjacks>) -> f32 {
+        config().action_weights.repair
+    }
+
+    fn duration(&self, _ctx: Context<Lumberjacks>) -> TaskDuration {
+        0
+    }
+
+    fn execute(&self, ctx: ContextMut<Lumberjacks>) -> Option<Box<dyn Task<Lumberjacks>>> {
+        let ContextMut {
+            mut state_diff,
+            agent,
+            ..
+        } = ctx;
+        state_diff.increment_time();
+
+        for _ in 0..config().tools.repair_cost {
+            state_diff.decrement_inventory(agent);
+        }
+        state_diff.set_axe(agent, config().tools.durability);
+
+        Some(Box::new(IdleTask))
+    }
+
+    fn display_action(&self) -> <Lumberjacks as Domain>::DisplayAction {
+        Action::Repair
+    }
+
+    fn is_valid(&self, ctx: Context<Lumberjacks>) -> bool {
+        let Context {
+            state_diff, agent, ..
+        } = ctx;
+        state_diff.get_axe(agent) < config().tools.durability
+            && state_diff.get_inventory(agent) >= config().tools.repair_cost
+    }
+
+    impl_task_boxed_methods!(Lumberjacks);
+}
@@ -0,0 +1,59 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::hash::Hash;
+
+use npc_engine_core::{
+    impl_task_boxed_methods, Context, ContextMut, Domain, IdleTask, Task, TaskDuration,
+};
+
+use crate::{config, is_night, Action, Lumberjacks, WorldState, WorldStateMut};
+
+/// Sleeps, restoring the energy of a lumberjack and resetting its tiredness.
+/// Resting is only possible at night, unless the needs of lumberjacks are enabled.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Rest;
+
+impl Task<Lumberjacks> for Rest {
+    fn weight(&self, _ctx: Context<Lumberjacks>) -> f32 {
+        config().action_weights.rest
+    }
+
+    fn duration(&self, _ctx: Context<Lumberjacks>) -> TaskDuration {
+        0
+    }
+
+    fn execute(&self, ctx: ContextMut<Lumberjacks>) -> Option<Box<dyn Task<Lumberjacks>>> {
+        let ContextMut {
+            tick,
+            mut state_diff,
+            agent,
+        } = ctx;
+        state_diff.increment_time();
+        state_diff.set_last_rest(agent, tick);
+
+        let energy = state_diff.get_energy(agent);
+        state_diff.add_energy(agent, (config().day_night.energy - energy) as isize);
+
+        Some(Box::new(IdleTask))
+    }
+
+    fn display_action(&self) -> <Lumberjacks as Domain>::DisplayAction {
+        Action::Rest
+    }
+
+    fn is_valid(&self, ctx: Context<Lumberjacks>) -> bool {
+        let Context {
+            tick,
+            state_diff,
+            agent,
+        } = ctx;
+        let tired = config().features.needs && state_diff.tiredness(tick, agent) > 0;
+        (is_night(tick) || config().features.needs)
+            && (state_diff.get_energy(agent) < config().day_night.energy || tired)
+    }
+
+    impl_task_boxed_methods!(Lumberjacks);
+}
@@ -0,0 +1,137 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::hash::Hash;
+
+use npc_engine_core::{
+    impl_task_boxed_methods, AgentId, Context, ContextMut, Domain, IdleTask, StateDiffRef, Task,
+    TaskDuration,
+};
+use npc_engine_utils::Direction;
+
+use crate::{
+    apply_direction, config, Action, Lumberjacks, Tile, WorldState, WorldStateMut, WORLD_AGENT_ID,
+};
+
+// Returns the adjacent lumberjack in a direction, if any
+fn trader(
+    state_diff: StateDiffRef<Lumberjacks>,
+    agent: AgentId,
+    direction: Direction,
+) -> Option<AgentId> {
+    let (x, y) = state_diff.find_agent(agent).unwrap();
+    let (x, y) = apply_direction(direction, x, y);
+    match state_diff.get_tile(x, y) {
+        Some(Tile::Agent(other))
+            if other != WORLD_AGENT_ID && state_diff.predator_kind(other).is_none() =>
+        {
+            Some(other)
+        }
+        _ => None,
+    }
+}
+
+// Whether the buyer can pay for the water of the seller
+fn can_trade(state_diff: StateDiffRef<Lumberjacks>, buyer: AgentId, seller: AgentId) -> bool {
+    !state_diff.get_water(buyer)
+        && state_diff.get_water(seller)
+        && state_diff.get_inventory(buyer) >= config().trading.price
+}
+
+/// Offers wood to an adjacent lumberjack in exchange for its water, the trade happens if it accepts.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Offer {
+    pub direction: Direction,
+}
+
+impl Task<Lumberjacks> for Offer {
+    fn weight(&self, _ctx: Context<Lumberjacks>) -> f32 {
+        config().action_weights.offer
+    }
+
+    fn duration(&self, _ctx: Context<Lumberjacks>) -> TaskDuration {
+        0
+    }
+
+    fn execute(&self, ctx: ContextMut<Lumberjacks>) -> Option<Box<dyn Task<Lumberjacks>>> {
+        let ContextMut {
+            mut state_diff,
+            agent,
+            ..
+        } = ctx;
+        state_diff.increment_time();
+
+        let seller = trader(*state_diff, agent, self.direction);
+        state_diff.set_offer(agent, seller);
+
+        Some(Box::new(IdleTask))
+    }
+
+    fn display_action(&self) -> <Lumberjacks as Domain>::DisplayAction {
+        Action::Offer(self.direction)
+    }
+
+    fn is_valid(&self, ctx: Context<Lumberjacks>) -> bool {
+        let Context {
+            state_diff, agent, ..
+        } = ctx;
+        trader(state_diff, agent, self.direction).map_or(false, |seller| {
+            state_diff.get_offer(agent) != Some(seller) && can_trade(state_diff, agent, seller)
+        })
+    }
+
+    impl_task_boxed_methods!(Lumberjacks);
+}
+
+/// Accepts the offer of an adjacent lumberjack, giving it water in exchange for wood.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Accept {
+    pub direction: Direction,
+}
+
+impl Task<Lumberjacks> for Accept {
+    fn weight(&self, _ctx: Context<Lumberjacks>) -> f32 {
+        config().action_weights.accept
+    }
+
+    fn duration(&self, _ctx: Context<Lumberjacks>) -> TaskDuration {
+        0
+    }
+
+    fn execute(&self, ctx: ContextMut<Lumberjacks>) -> Option<Box<dyn Task<Lumberjacks>>> {
+        let ContextMut {
+            mut state_diff,
+            agent,
+            ..
+        } = ctx;
+        state_diff.increment_time();
+
+        if let Some(buyer) = trader(*state_diff, agent, self.direction) {
+            if state_diff.get_offer(buyer) == Some(agent) && can_trade(*state_diff, buyer, agent) {
+                state_diff.transfer_wood(buyer, agent, config().trading.price);
+                state_diff.set_water(agent, false);
+                state_diff.set_water(buyer, true);
+                state_diff.set_offer(buyer, None);
+            }
+        }
+
+        Some(Box::new(IdleTask))
+    }
+
+    fn display_action(&self) -> <Lumberjacks as Domain>::DisplayAction {
+        Action::Accept(self.direction)
+    }
+
+    fn is_valid(&self, ctx: Context<Lumberjacks>) -> bool {
+        let Context {
+            state_diff, agent, ..
+        } = ctx;
+        trader(state_diff, agent, self.direction).map_or(false, |buyer| {
+            state_diff.get_offer(buyer) == Some(agent) && can_trade(state_diff, buyer, agent)
+        })
+    }
+
+    impl_task_boxed_methods!(Lumberjacks);
+}
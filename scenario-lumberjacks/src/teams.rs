@@ -0,0 +1,109 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::collections::BTreeMap;
+use std::fs;
+
+use npc_engine_core::AgentId;
+use serde::{Deserialize, Serialize};
+
+use crate::{config, output_path, WorldGlobalState};
+
+/// One of the two lumberjack companies, matching the colors of the sprites.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Team {
+    Orange,
+    Yellow,
+}
+
+impl Team {
+    pub fn of(agent: AgentId) -> Self {
+        if agent.0 % 2 == 0 {
+            Team::Orange
+        } else {
+            Team::Yellow
+        }
+    }
+
+    pub fn strategy(&self) -> TeamStrategy {
+        match self {
+            Team::Orange => config().teams.orange,
+            Team::Yellow => config().teams.yellow,
+        }
+    }
+}
+
+/// What the members of a team plan for.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TeamStrategy {
+    /// Maximize the wood of the whole team
+    Shared,
+    /// Maximize the own wood, the team score only being reported
+    Selfish,
+}
+
+impl WorldGlobalState {
+    /// The wood, carried and stored, of every team.
+    pub fn team_scores(&self) -> BTreeMap<Team, usize> {
+        let mut scores = BTreeMap::new();
+        for (agent, inventory) in &self.inventory.0 {
+            if !self.predators.contains_key(agent) {
+                *scores.entry(Team::of(*agent)).or_default() +=
+                    (inventory.wood + inventory.stored) as usize;
+            }
+        }
+        scores
+    }
+
+    /// The team with the strictly highest score, if any.
+    pub fn winning_team(&self) -> Option<Team> {
+        let scores = self.team_scores();
+        let best = scores.values().max()?;
+        let mut leaders = scores.iter().filter(|(_, score)| *score == best);
+        match (leaders.next(), leaders.next()) {
+            (Some((team, _)), None) => Some(*team),
+            _ => None,
+        }
+    }
+}
+
+/// Writes and prints which team strategy won over the runs of a batch.
+pub fn dump_team_summary(winners: Vec<Option<Team>>) {
+    let wins = |team| {
+        winners
+            .iter()
+            .filter(|winner| **winner == Some(team))
+            .count()
+    };
+    let ties = winners.iter().filter(|winner| winner.is_none()).count();
+
+    let info = serde_json::json!({
+        "runs": winners.len(),
+        "orange": { "strategy": Team::Orange.strategy(), "wins": wins(Team::Orange) },
+        "yellow": { "strategy": Team::Yellow.strategy(), "wins": wins(Team::Yellow) },
+        "ties": ties,
+    });
+
+    println!(
+        "Teams over {} runs: orange ({:?}) won {}, yellow ({:?}) won {}, {} ties",
+        winners.len(),
+        Team::Orange.strategy(),
+        wins(Team::Orange),
+        Team::Yellow.strategy(),
+        wins(Team::Yellow),
+        ties
+    );
+
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(true)
+        .write(true)
+        .open(format!("{}/teams.json", output_path()))
+        .unwrap();
+
+    serde_json::to_writer_pretty(file, &info).unwrap();
+}
@@ -0,0 +1,65 @@
+/*
+ *  SPDX-License-Identifier: Apache-2.0 OR MIT
+ *  © 2020-2022 ETH Zurich and other contributors, see AUTHORS.txt for details
+ */
+
+use std::mem::MaybeUninit;
+
+use serde_json::json;
+
+use crate::{AgentInventory, Inventory, Tile, TileMap, WorldGlobalState};
+use crate::{BATCH, CONFIG, INIT, LOAD, NAME, OUTPUT_PATH, REPLAY, WORKING_DIR};
+
+/// Initializes the globals for the unit tests, which have no command line to read them from.
+///
+/// Agents see two tiles around them, and the world agent features and the value terms
+/// they are tested with are enabled.
+pub(crate) fn init() {
+    unsafe {
+        INIT.call_once(|| {
+            NAME = MaybeUninit::new("Lumberjacks".to_owned());
+            OUTPUT_PATH = MaybeUninit::new(".".to_owned());
+            WORKING_DIR = MaybeUninit::new(".".to_owned());
+            BATCH = MaybeUninit::new(true);
+            LOAD = MaybeUninit::new(None);
+            REPLAY = MaybeUninit::new(None);
+            CONFIG = MaybeUninit::new(
+                serde_json::from_value(json!({
+                    "map": { "generator": { "procedural": {} } },
+                    "agents": { "horizon-radius": 2, "snapshot-radius": 2 },
+                    "features": {
+                        "regrowth": true,
+                        "fire-spread": true,
+                        "market": true,
+                        "planting": true,
+                        "construction": true,
+                    },
+                    "value": { "sustainability": 1. },
+                    "planting": { "forecast": 0.5 },
+                }))
+                .unwrap(),
+            );
+        })
+    }
+}
+
+/// A world of the given rows of tiles, with an empty inventory for each agent on them.
+pub(crate) fn world(rows: Vec<Vec<Tile>>) -> WorldGlobalState {
+    init();
+    let map = TileMap::from_rows(rows[0].len(), rows.len(), rows);
+    let inventory = Inventory(
+        map.iter()
+            .filter_map(|(_, _, tile)| match tile {
+                Tile::Agent(agent) => Some((agent, AgentInventory::default())),
+                _ => None,
+            })
+            .collect(),
+    );
+    WorldGlobalState {
+        actions: Default::default(),
+        inventory,
+        map,
+        predators: Default::default(),
+        market: Default::default(),
+    }
+}
@@ -7,25 +7,89 @@ use std::collections::hash_map::DefaultHasher;
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::hash::{Hash, Hasher};
 use std::mem;
+use std::ops::{Index, IndexMut};
 use std::{char, num::NonZeroU8};
 use std::{fmt, io};
 
-use ggez::graphics::{draw, Image, WHITE};
-use ggez::Context;
-use image::png::PngDecoder;
+use image::png::{PngDecoder, PngEncoder};
 use image::{ColorType, ImageDecoder};
+use macroquad::color::{Color, WHITE};
+use macroquad::shapes::draw_rectangle;
+use macroquad::texture::{draw_texture, Texture2D};
 use npc_engine_core::AgentId;
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-use crate::{config, Action, SPRITE_SIZE};
+use crate::{config, Action, PredatorKind, SPRITE_SIZE};
 
-#[derive(Clone, Serialize)]
-#[serde(rename_all = "kebab-case")]
+/// The side of the square chunks the tiles of a map are stored in.
+pub const CHUNK_SIZE: usize = 16;
+
+/// The map of the whole world, stored in chunks so that large maps keep the tiles of
+/// a local window close in memory.
+/// Tiles are indexed by `(x, y)` and the map serializes as rows of tiles.
+/// The elevation of the ground is a separate layer, chunked alike, and is not changed by the agents.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(from = "TileRows", into = "TileRows")]
 pub struct TileMap {
     pub width: usize,
     pub height: usize,
-    pub tiles: Box<[Box<[Tile]>]>,
+    chunks: Box<[Box<[Tile]>]>,
+    elevation: Box<[Box<[u8]>]>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct TileRows {
+    width: usize,
+    height: usize,
+    tiles: Vec<Vec<Tile>>,
+    /// Left out for flat maps
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    elevation: Vec<Vec<u8>>,
+}
+
+impl From<TileRows> for TileMap {
+    fn from(rows: TileRows) -> Self {
+        TileMap::from_rows(rows.width, rows.height, rows.tiles).with_elevation(rows.elevation)
+    }
+}
+
+impl From<TileMap> for TileRows {
+    fn from(map: TileMap) -> Self {
+        let flat = map
+            .elevation
+            .iter()
+            .all(|chunk| chunk.iter().all(|&h| h == 0));
+        TileRows {
+            width: map.width,
+            height: map.height,
+            tiles: map.rows().collect(),
+            elevation: if flat {
+                Vec::new()
+            } else {
+                (0..map.height)
+                    .map(|y| (0..map.width).map(|x| map.elevation_at(x, y)).collect())
+                    .collect()
+            },
+        }
+    }
+}
+
+impl Index<(usize, usize)> for TileMap {
+    type Output = Tile;
+
+    fn index(&self, (x, y): (usize, usize)) -> &Tile {
+        let (chunk, index) = self.locate(x, y);
+        &self.chunks[chunk][index]
+    }
+}
+
+impl IndexMut<(usize, usize)> for TileMap {
+    fn index_mut(&mut self, (x, y): (usize, usize)) -> &mut Tile {
+        let (chunk, index) = self.locate(x, y);
+        &mut self.chunks[chunk][index]
+    }
 }
 
 impl fmt::Debug for TileMap {
@@ -35,13 +99,17 @@ impl fmt::Debug for TileMap {
 }
 
 impl TileMap {
-    pub fn from_io(agents: &mut Vec<AgentId>, read: impl io::Read) -> Self {
+    pub fn from_io(
+        agents: &mut Vec<AgentId>,
+        predators: &mut BTreeMap<AgentId, PredatorKind>,
+        read: impl io::Read,
+    ) -> Self {
         let decoder = PngDecoder::new(read).expect("failed to parse value as png");
         let (width, height) = decoder.dimensions();
         let width = width as usize;
         let height = height as usize;
 
-        let tiles = match decoder.color_type() {
+        let rows = match decoder.color_type() {
             ColorType::Rgba8 => {
                 let mut data = vec![0; decoder.total_bytes() as _];
                 decoder.read_image(&mut data).expect("failed to read image");
@@ -56,6 +124,25 @@ impl TileMap {
                                     agents.push(agent);
                                     Tile::Agent(agent)
                                 }
+                                (128, 64, 0, 255) => {
+                                    let agent = AgentId(agents.len() as u32);
+                                    agents.push(agent);
+                                    predators.insert(agent, PredatorKind::Bear);
+                                    Tile::Agent(agent)
+                                }
+                                (128, 128, 128, 255) => {
+                                    let agent = AgentId(agents.len() as u32);
+                                    agents.push(agent);
+                                    predators.insert(agent, PredatorKind::Wolf);
+                                    Tile::Agent(agent)
+                                }
+                                (255, 0, 0, 255) => Tile::Fire,
+                                (0, 128, 255, 255) => Tile::Water,
+                                (160, 96, 32, 255) => Tile::Bridge,
+                                (255, 128, 0, 255) => Tile::Shed,
+                                (192, 160, 96, 255) => Tile::Site(0),
+                                (128, 0, 64, 255) => Tile::Hut,
+                                (96, 160, 0, 255) => Tile::Bush,
                                 (0, 0, 0, 255) => Tile::Impassable,
                                 (0, 0, 255, 255) => Tile::Well,
                                 (_, _, _, 0) => Tile::Empty,
@@ -65,61 +152,262 @@ impl TileMap {
                                 ),
                             })
                             .collect::<Vec<_>>()
-                            .into_boxed_slice()
                     })
                     .collect::<Vec<_>>()
-                    .into_boxed_slice()
             }
             _ => panic!("unsupported png color format"),
         };
 
-        TileMap {
+        TileMap::from_rows(width, height, rows)
+    }
+
+    /// Creates a map from its rows of tiles, from the top to the bottom.
+    pub fn from_rows(width: usize, height: usize, rows: Vec<Vec<Tile>>) -> Self {
+        let chunks_x = (width + CHUNK_SIZE - 1) / CHUNK_SIZE;
+        let chunks_y = (height + CHUNK_SIZE - 1) / CHUNK_SIZE;
+        let mut map = TileMap {
             width,
             height,
-            tiles,
+            chunks: (0..chunks_x * chunks_y)
+                .map(|_| vec![Tile::Empty; CHUNK_SIZE * CHUNK_SIZE].into_boxed_slice())
+                .collect(),
+            elevation: (0..chunks_x * chunks_y)
+                .map(|_| vec![0; CHUNK_SIZE * CHUNK_SIZE].into_boxed_slice())
+                .collect(),
+        };
+        for (y, row) in rows.into_iter().enumerate() {
+            for (x, tile) in row.into_iter().enumerate() {
+                map[(x, y)] = tile;
+            }
         }
+        map
     }
 
-    #[allow(clippy::needless_borrow)] // because clippy is buggy in its analysis here
-    pub fn draw(
-        &self,
-        ctx: &mut Context,
-        assets: &BTreeMap<String, Image>,
-        actions: &BTreeMap<AgentId, Action>,
-    ) {
-        self.tiles
-            .iter()
+    /// Returns this map with the given rows of elevation, from the top to the bottom.
+    /// Missing rows and columns are at ground level.
+    pub fn with_elevation(mut self, rows: Vec<Vec<u8>>) -> Self {
+        for (y, row) in rows.into_iter().enumerate() {
+            for (x, elevation) in row.into_iter().enumerate() {
+                self.set_elevation(x, y, elevation);
+            }
+        }
+        self
+    }
+
+    // The chunk holding a tile, and the index of the tile within the chunk
+    fn locate(&self, x: usize, y: usize) -> (usize, usize) {
+        assert!(
+            x < self.width && y < self.height,
+            "tile ({}, {}) is outside the map",
+            x,
+            y
+        );
+        let chunks_x = (self.width + CHUNK_SIZE - 1) / CHUNK_SIZE;
+        (
+            (y / CHUNK_SIZE) * chunks_x + x / CHUNK_SIZE,
+            (y % CHUNK_SIZE) * CHUNK_SIZE + x % CHUNK_SIZE,
+        )
+    }
+
+    /// The tile at the given position, if it is within the map.
+    pub fn get(&self, x: isize, y: isize) -> Option<Tile> {
+        if x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height {
+            Some(self[(x as usize, y as usize)])
+        } else {
+            None
+        }
+    }
+
+    /// The elevation of the ground at the given position, if it is within the map.
+    pub fn elevation(&self, x: isize, y: isize) -> Option<u8> {
+        if x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height {
+            Some(self.elevation_at(x as usize, y as usize))
+        } else {
+            None
+        }
+    }
+
+    fn elevation_at(&self, x: usize, y: usize) -> u8 {
+        let (chunk, index) = self.locate(x, y);
+        self.elevation[chunk][index]
+    }
+
+    pub fn set_elevation(&mut self, x: usize, y: usize, elevation: u8) {
+        let (chunk, index) = self.locate(x, y);
+        self.elevation[chunk][index] = elevation;
+    }
+
+    /// All tiles with their positions, in reading order.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, usize, Tile)> + '_ {
+        (0..self.height).flat_map(move |y| (0..self.width).map(move |x| (x, y, self[(x, y)])))
+    }
+
+    /// The rows of tiles, from the top to the bottom.
+    pub fn rows(&self) -> impl Iterator<Item = Vec<Tile>> + '_ {
+        (0..self.height).map(move |y| (0..self.width).map(|x| self[(x, y)]).collect())
+    }
+
+    /// All tiles, in no particular order.
+    pub fn tiles_mut(&mut self) -> impl Iterator<Item = &mut Tile> + '_ {
+        let (width, height) = (self.width, self.height);
+        let chunks_x = (width + CHUNK_SIZE - 1) / CHUNK_SIZE;
+        self.chunks
+            .iter_mut()
             .enumerate()
-            .flat_map(|(row_id, row)| {
-                row.iter()
+            .flat_map(move |(chunk, tiles)| {
+                let (left, top) = (
+                    (chunk % chunks_x) * CHUNK_SIZE,
+                    (chunk / chunks_x) * CHUNK_SIZE,
+                );
+                tiles
+                    .iter_mut()
                     .enumerate()
-                    .map(move |(col_id, tile)| (row_id, col_id, tile))
+                    .filter_map(move |(index, tile)| {
+                        let (x, y) = (left + index % CHUNK_SIZE, top + index / CHUNK_SIZE);
+                        if x < width && y < height {
+                            Some(tile)
+                        } else {
+                            None
+                        }
+                    })
             })
-            .for_each(|(row, col, tile)| {
-                let sprite = match tile {
-                    Tile::Agent(agent) if actions.contains_key(&agent) => Some(format!(
-                        "{}{}",
-                        if agent.0 % 2 == 0 { "Orange" } else { "Yellow" },
-                        actions.get(&agent).unwrap().sprite_name(),
-                    )),
-                    tile => tile.sprite(),
-                };
-
-                if let Some(sprite) = sprite {
-                    draw(
-                        ctx,
-                        assets.get(&sprite).unwrap(),
-                        ([col as f32 * SPRITE_SIZE, row as f32 * SPRITE_SIZE], WHITE),
-                    )
-                    .unwrap();
+    }
+
+    /// Extracts the square window of the given radius around a position, tiles outside
+    /// the map being impassable. Only the chunks overlapping the window are visited.
+    pub fn window(&self, x: isize, y: isize, radius: usize) -> Box<[Box<[Tile]>]> {
+        self.window_of(&self.chunks, Tile::Impassable, x, y, radius)
+    }
+
+    /// Extracts the elevation of the same window as `window`, at ground level outside the map.
+    pub fn elevation_window(&self, x: isize, y: isize, radius: usize) -> Box<[Box<[u8]>]> {
+        self.window_of(&self.elevation, 0, x, y, radius)
+    }
+
+    fn window_of<T: Copy>(
+        &self,
+        chunks: &[Box<[T]>],
+        outside: T,
+        x: isize,
+        y: isize,
+        radius: usize,
+    ) -> Box<[Box<[T]>]> {
+        let size = radius * 2 + 1;
+        let (left, top) = (x - radius as isize, y - radius as isize);
+        let mut window = vec![vec![outside; size].into_boxed_slice(); size];
+        let chunk = CHUNK_SIZE as isize;
+        let chunks_x = (self.width + CHUNK_SIZE - 1) / CHUNK_SIZE;
+        let (min_x, min_y) = (left.max(0), top.max(0));
+        let max_x = (left + size as isize).min(self.width as isize);
+        let max_y = (top + size as isize).min(self.height as isize);
+        let mut cy = min_y / chunk * chunk;
+        while cy < max_y {
+            let mut cx = min_x / chunk * chunk;
+            while cx < max_x {
+                let tiles = &chunks[(cy / chunk) as usize * chunks_x + (cx / chunk) as usize];
+                for y in cy.max(min_y)..(cy + chunk).min(max_y) {
+                    for x in cx.max(min_x)..(cx + chunk).min(max_x) {
+                        window[(y - top) as usize][(x - left) as usize] =
+                            tiles[((y - cy) * chunk + (x - cx)) as usize];
+                    }
                 }
-            });
+                cx += chunk;
+            }
+            cy += chunk;
+        }
+        window.into_boxed_slice()
     }
 
-    pub fn tree_count(&self) -> usize {
-        self.tiles
+    /// Writes the map in the PNG format read by `from_io`.
+    /// Trees are saved at full height, construction sites without their wood,
+    /// and barriers, which are not part of the format, as empty tiles.
+    pub fn to_io(&self, predators: &BTreeMap<AgentId, PredatorKind>, write: impl io::Write) {
+        let data = self
             .iter()
-            .flat_map(|cols| cols.iter().filter(|&&tile| matches!(tile, Tile::Tree(_))))
+            .flat_map(|(_, _, tile)| match tile {
+                Tile::Tree(_) => [0, 255, 0, 255],
+                Tile::Agent(agent) => match predators.get(&agent) {
+                    Some(PredatorKind::Bear) => [128, 64, 0, 255],
+                    Some(PredatorKind::Wolf) => [128, 128, 128, 255],
+                    None => [255, 255, 255, 255],
+                },
+                Tile::Fire => [255, 0, 0, 255],
+                Tile::Water => [0, 128, 255, 255],
+                Tile::Bridge => [160, 96, 32, 255],
+                Tile::Shed => [255, 128, 0, 255],
+                Tile::Site(_) => [192, 160, 96, 255],
+                Tile::Hut => [128, 0, 64, 255],
+                Tile::Bush => [96, 160, 0, 255],
+                Tile::Impassable => [0, 0, 0, 255],
+                Tile::Well => [0, 0, 255, 255],
+                Tile::Barrier | Tile::Empty => [0, 0, 0, 0],
+            })
+            .collect::<Vec<_>>();
+
+        PngEncoder::new(write)
+            .encode(
+                &data,
+                self.width as u32,
+                self.height as u32,
+                ColorType::Rgba8,
+            )
+            .expect("failed to write png");
+    }
+
+    // Draws the map with its top-left tile at origin
+    pub fn draw(
+        &self,
+        (origin_x, origin_y): (f32, f32),
+        assets: &BTreeMap<String, Texture2D>,
+        actions: &BTreeMap<AgentId, Action>,
+        predators: &BTreeMap<AgentId, PredatorKind>,
+    ) {
+        self.iter().for_each(|(col, row, tile)| {
+            let (x, y) = (
+                origin_x + col as f32 * SPRITE_SIZE,
+                origin_y + row as f32 * SPRITE_SIZE,
+            );
+
+            // Higher ground is lighter, under whatever stands on it
+            let elevation = self.elevation_at(col, row);
+            if elevation > 0 {
+                draw_rectangle(
+                    x,
+                    y,
+                    SPRITE_SIZE,
+                    SPRITE_SIZE,
+                    Color::new(1., 1., 1., (elevation as f32 * 0.1).min(0.6)),
+                );
+            }
+
+            // Predators and fire have no sprites, so they are drawn as coloured tiles
+            let color = match tile {
+                Tile::Agent(agent) => predators.get(&agent).map(PredatorKind::color),
+                tile => tile.color(),
+            };
+            if let Some(color) = color {
+                draw_rectangle(x, y, SPRITE_SIZE, SPRITE_SIZE, color);
+                return;
+            }
+
+            let sprite = match tile {
+                Tile::Agent(agent) if actions.contains_key(&agent) => Some(format!(
+                    "{}{}",
+                    if agent.0 % 2 == 0 { "Orange" } else { "Yellow" },
+                    actions.get(&agent).unwrap().sprite_name(),
+                )),
+                tile => tile.sprite(),
+            };
+
+            if let Some(sprite) = sprite {
+                draw_texture(assets.get(&sprite).unwrap(), x, y, WHITE);
+            }
+        });
+    }
+
+    pub fn tree_count(&self) -> usize {
+        self.iter()
+            .filter(|(_, _, tile)| matches!(tile, Tile::Tree(_)))
             .count()
     }
 
@@ -135,7 +423,7 @@ impl TileMap {
                     (0..patch_size)
                         .fold(DefaultHasher::default(), |hasher, y| {
                             (0..patch_size).fold(hasher, |mut hasher, x| {
-                                self.tiles[offset_y + y][offset_x + x].hash(&mut hasher);
+                                self[(offset_x + x, offset_y + y)].hash(&mut hasher);
                                 hasher
                             })
                         })
@@ -152,6 +440,7 @@ pub struct TileMapSnapshot {
     pub top: isize,
     pub left: isize,
     pub tiles: Box<[Box<[Tile]>]>,
+    pub elevation: Box<[Box<[u8]>]>,
 }
 
 impl fmt::Debug for TileMapSnapshot {
@@ -166,6 +455,13 @@ impl fmt::Debug for TileMapSnapshot {
                     Tile::Barrier => 'B',
                     Tile::Impassable => 'X',
                     Tile::Well => 'W',
+                    Tile::Fire => 'F',
+                    Tile::Water => '~',
+                    Tile::Bridge => '=',
+                    Tile::Shed => 'S',
+                    Tile::Site(_) => 'c',
+                    Tile::Hut => 'H',
+                    Tile::Bush => '*',
                 });
             }
             string.push('\n');
@@ -175,7 +471,7 @@ impl fmt::Debug for TileMapSnapshot {
     }
 }
 
-#[derive(Copy, Clone, Debug, Default, Hash, PartialEq, Eq, Serialize)]
+#[derive(Copy, Clone, Debug, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub enum Tile {
     Tree(NonZeroU8),
@@ -183,6 +479,15 @@ pub enum Tile {
     Impassable,
     Barrier,
     Well,
+    Fire,
+    Water,
+    Bridge,
+    Shed,
+    /// A construction site, with the wood delivered so far
+    Site(u8),
+    Hut,
+    /// A berry bush, where lumberjacks eat
+    Bush,
     #[default]
     Empty,
 }
@@ -199,12 +504,42 @@ impl Tile {
             Tile::Barrier => Some("WoodenBarrier".to_owned()),
             Tile::Impassable => Some("ImpassableRock".to_owned()),
             Tile::Well => Some("Well".to_owned()),
-            Tile::Empty => None,
+            Tile::Fire
+            | Tile::Water
+            | Tile::Bridge
+            | Tile::Shed
+            | Tile::Site(_)
+            | Tile::Hut
+            | Tile::Bush
+            | Tile::Empty => None,
+        }
+    }
+
+    /// Returns this tile after a regrowth step, in which trees grow by one level up to the tree height of the map.
+    pub fn regrown(self) -> Tile {
+        match self {
+            Tile::Tree(height) if height < config().map.tree_height => {
+                Tile::Tree(NonZeroU8::new(height.get() + 1).unwrap())
+            }
+            tile => tile,
+        }
+    }
+
+    fn color(&self) -> Option<Color> {
+        match self {
+            Tile::Fire => Some(Color::from_rgba(255, 96, 0, 255)),
+            Tile::Water => Some(Color::from_rgba(0, 96, 192, 255)),
+            Tile::Bridge => Some(Color::from_rgba(160, 96, 32, 255)),
+            Tile::Shed => Some(Color::from_rgba(96, 64, 32, 255)),
+            Tile::Site(_) => Some(Color::from_rgba(192, 160, 96, 255)),
+            Tile::Hut => Some(Color::from_rgba(128, 0, 64, 255)),
+            Tile::Bush => Some(Color::from_rgba(96, 160, 0, 255)),
+            _ => None,
         }
     }
 
     pub fn is_impassable(&self) -> bool {
-        matches!(self, Tile::Impassable)
+        matches!(self, Tile::Impassable | Tile::Water)
     }
 
     pub fn is_walkable(&self) -> bool {
@@ -216,11 +551,22 @@ impl Tile {
     }
 
     pub fn is_support(&self) -> bool {
-        matches!(self, Tile::Impassable | Tile::Barrier)
+        matches!(
+            self,
+            Tile::Impassable | Tile::Barrier | Tile::Water | Tile::Shed | Tile::Site(_) | Tile::Hut
+        )
     }
 
     pub fn is_point_of_interest(&self) -> bool {
-        matches!(self, Tile::Tree(_) | Tile::Well)
+        match self {
+            Tile::Tree(_) | Tile::Well | Tile::Bridge => true,
+            Tile::Shed => config().features.tools,
+            Tile::Site(_) => config().features.construction,
+            Tile::Bush => config().features.needs,
+            Tile::Water => config().features.bridges,
+            Tile::Fire => config().features.fire_spread,
+            _ => false,
+        }
     }
 }
 
@@ -8,17 +8,25 @@ use std::f32;
 use std::fmt;
 use std::hash::{Hash, Hasher};
 
-use ggez::graphics::Color;
+use macroquad::color::Color;
 use npc_engine_utils::Coord2D;
 use npc_engine_utils::Direction;
 use npc_engine_utils::DirectionConverterYDown;
 use num_traits::{AsPrimitive, PrimInt};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use npc_engine_core::AgentId;
 
+use crate::config;
+
 pub const SPRITE_SIZE: f32 = 32.;
 
+/// Whether it is night at the given tick, always false without the day/night cycle.
+pub fn is_night(tick: u64) -> bool {
+    let day_night = &config().day_night;
+    config().features.day_night && tick % (day_night.day + day_night.night) >= day_night.day
+}
+
 #[derive(Clone, Serialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct Map2D<T> {
@@ -100,7 +108,7 @@ pub fn agent_color(agent: AgentId) -> Color {
     let mut hasher = DefaultHasher::default();
     agent.0.hash(&mut hasher);
     let bytes: [u8; 8] = hasher.finish().to_ne_bytes();
-    Color::from_rgb(bytes[5], bytes[6], bytes[7])
+    Color::from_rgba(bytes[5], bytes[6], bytes[7], 255)
 }
 
 pub fn apply_direction(direction: Direction, x: isize, y: isize) -> (isize, isize) {
@@ -114,7 +122,7 @@ pub fn from_direction(start: (isize, isize), end: (isize, isize)) -> Direction {
     DirectionConverterYDown::from(start, end)
 }
 
-#[derive(Copy, Clone, Debug, Default, Serialize)]
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub enum Action {
     Walk(Direction),
@@ -123,6 +131,23 @@ pub enum Action {
     Plant(Direction),
     Water(Direction),
     Refill,
+    Roam(Direction),
+    Hunt(Direction),
+    Flee(Direction),
+    Bridge(Direction),
+    Cross(Direction),
+    Repair,
+    Fetch,
+    Deposit,
+    Offer(Direction),
+    Accept(Direction),
+    Rest,
+    Found(Direction),
+    Deliver(Direction),
+    Build(Direction),
+    Eat(Direction),
+    Extinguish(Direction),
+    Sell,
     #[default]
     Wait,
 }
@@ -153,11 +178,58 @@ impl Action {
             Action::Plant(Direction::Down) => "DownBarrier",
             Action::Plant(Direction::Left) => "LeftBarrier",
             Action::Plant(Direction::Right) => "RightBarrier",
-            Action::Refill => "Right",
-            Action::Water(Direction::Up) => "TopBarrier",
-            Action::Water(Direction::Down) => "DownBarrier",
-            Action::Water(Direction::Left) => "LeftBarrier",
-            Action::Water(Direction::Right) => "RightBarrier",
+            Action::Refill
+            | Action::Repair
+            | Action::Fetch
+            | Action::Deposit
+            | Action::Rest
+            | Action::Sell => "Right",
+            Action::Offer(Direction::Up)
+            | Action::Accept(Direction::Up)
+            | Action::Eat(Direction::Up) => "Top",
+            Action::Offer(Direction::Down)
+            | Action::Accept(Direction::Down)
+            | Action::Eat(Direction::Down) => "Down",
+            Action::Offer(Direction::Left)
+            | Action::Accept(Direction::Left)
+            | Action::Eat(Direction::Left) => "Left",
+            Action::Offer(Direction::Right)
+            | Action::Accept(Direction::Right)
+            | Action::Eat(Direction::Right) => "Right",
+            Action::Water(Direction::Up) | Action::Extinguish(Direction::Up) => "TopBarrier",
+            Action::Water(Direction::Down) | Action::Extinguish(Direction::Down) => "DownBarrier",
+            Action::Water(Direction::Left) | Action::Extinguish(Direction::Left) => "LeftBarrier",
+            Action::Water(Direction::Right) | Action::Extinguish(Direction::Right) => {
+                "RightBarrier"
+            }
+            Action::Roam(Direction::Up) | Action::Flee(Direction::Up) => "Top",
+            Action::Roam(Direction::Down) | Action::Flee(Direction::Down) => "Down",
+            Action::Roam(Direction::Left) | Action::Flee(Direction::Left) => "Left",
+            Action::Roam(Direction::Right) | Action::Flee(Direction::Right) => "Right",
+            Action::Found(Direction::Up)
+            | Action::Deliver(Direction::Up)
+            | Action::Build(Direction::Up) => "TopBarrier",
+            Action::Found(Direction::Down)
+            | Action::Deliver(Direction::Down)
+            | Action::Build(Direction::Down) => "DownBarrier",
+            Action::Found(Direction::Left)
+            | Action::Deliver(Direction::Left)
+            | Action::Build(Direction::Left) => "LeftBarrier",
+            Action::Found(Direction::Right)
+            | Action::Deliver(Direction::Right)
+            | Action::Build(Direction::Right) => "RightBarrier",
+            Action::Bridge(Direction::Up) => "TopBarrier",
+            Action::Bridge(Direction::Down) => "DownBarrier",
+            Action::Bridge(Direction::Left) => "LeftBarrier",
+            Action::Bridge(Direction::Right) => "RightBarrier",
+            Action::Cross(Direction::Up) => "Top",
+            Action::Cross(Direction::Down) => "Down",
+            Action::Cross(Direction::Left) => "Left",
+            Action::Cross(Direction::Right) => "Right",
+            Action::Hunt(Direction::Up) => "TopChopping",
+            Action::Hunt(Direction::Down) => "DownChopping",
+            Action::Hunt(Direction::Left) => "LeftChopping",
+            Action::Hunt(Direction::Right) => "RightChopping",
         }
     }
 }
@@ -4,67 +4,167 @@
  */
 
 use std::collections::{BTreeMap, BTreeSet};
-use std::usize;
 
-use ggez::graphics;
-use ggez::graphics::Image;
-use ggez::Context;
+use macroquad::texture::Texture2D;
 use npc_engine_core::{AgentId, StateDiffRef, StateDiffRefMut};
 use npc_engine_utils::DIRECTIONS;
-use serde::Serialize;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    apply_direction, config, Action, AgentInventory, Inventory, InventoryDiff, InventorySnapshot,
-    Lumberjacks, Tile, TileMap, TileMapDiff, TileMapSnapshot, SPRITE_SIZE,
+    apply_direction, config, map_origin, Action, AgentInventory, Inventory, InventoryDiff,
+    InventorySnapshot, Lumberjacks, PredatorKind, Team, Tile, TileMap, TileMapDiff,
+    TileMapSnapshot, WORLD_AGENT_ID,
 };
 
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "kebab-case")]
 pub struct WorldGlobalState {
     pub actions: BTreeMap<AgentId, Action>,
     pub inventory: Inventory,
     pub map: TileMap,
+    pub predators: BTreeMap<AgentId, PredatorKind>,
+    #[serde(default)]
+    pub market: Market,
 }
 
-impl WorldGlobalState {
-    pub fn draw(&self, ctx: &mut Context, assets: &BTreeMap<String, Image>) {
-        let screen = graphics::screen_coordinates(ctx);
+/// The wood market, whose price falls with the wood sold and recovers when none is.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Market {
+    /// The coins paid per wood
+    pub price: u32,
+    /// The wood sold since the price last changed
+    pub sold: u32,
+}
+
+impl Default for Market {
+    fn default() -> Self {
+        Market {
+            price: config().market.initial_price,
+            sold: 0,
+        }
+    }
+}
+
+impl Market {
+    // The market after a pricing step of the world agent: every elasticity wood sold lowers
+    // the price by a coin, and it rises by one when none was
+    pub fn repriced(self) -> Self {
+        let market = &config().market;
+        let price = if self.sold == 0 {
+            self.price + 1
+        } else {
+            let elasticity = market.elasticity.max(1);
+            self.price
+                .saturating_sub((self.sold + elasticity - 1) / elasticity)
+        };
+        Market {
+            price: price.clamp(market.min_price, market.max_price),
+            sold: 0,
+        }
+    }
+}
 
+impl WorldGlobalState {
+    pub fn draw(&self, assets: &BTreeMap<String, Texture2D>) {
         if config().display.inventory {
-            self.inventory.draw(ctx, assets);
+            self.inventory.draw(assets, &self.predators);
         }
-        self.with_map_coordinates(ctx, |ctx| {
-            self.map.draw(ctx, assets, &self.actions);
-        });
-        graphics::set_screen_coordinates(ctx, screen).unwrap();
-    }
-
-    // Draw to non-padding area
-    pub fn with_map_coordinates(&self, ctx: &mut Context, f: impl Fn(&mut Context)) {
-        let screen = graphics::screen_coordinates(ctx);
-        graphics::set_screen_coordinates(
-            ctx,
-            graphics::Rect {
-                x: screen.x - config().display.padding.0 as f32 * SPRITE_SIZE,
-                y: screen.y - config().display.padding.1 as f32 * SPRITE_SIZE,
-                w: screen.w,
-                h: screen.h,
-            },
-        )
-        .unwrap();
-        f(ctx);
-        graphics::set_screen_coordinates(ctx, screen).unwrap();
+        self.map
+            .draw(map_origin(), assets, &self.actions, &self.predators);
     }
 
-    pub fn find_agent(&self, agent: AgentId) -> Option<(isize, isize)> {
-        for y in 0..self.map.height {
-            for x in 0..self.map.width {
-                if self.map.tiles[y][x] == Tile::Agent(agent) {
-                    return Some((x as isize, y as isize));
+    // Applies a regrowth step of the world agent to the whole map
+    pub fn regrow_trees(&mut self) {
+        for tile in self.map.tiles_mut() {
+            *tile = tile.regrown();
+        }
+    }
+
+    // Lets every fire ignite the adjacent trees and then burn out, returning how many trees caught fire
+    pub fn spread_fire(&mut self) -> usize {
+        let fires = self
+            .map
+            .iter()
+            .filter(|(_, _, tile)| *tile == Tile::Fire)
+            .map(|(x, y, _)| (x as isize, y as isize))
+            .collect::<Vec<_>>();
+        let mut ignited = 0;
+        for &(x, y) in &fires {
+            for direction in DIRECTIONS {
+                let (x, y) = apply_direction(direction, x, y);
+                if let Some(Tile::Tree(_)) = self.map.get(x, y) {
+                    self.map[(x as usize, y as usize)] = Tile::Fire;
+                    ignited += 1;
                 }
             }
         }
-        None
+        for (x, y) in fires {
+            self.map[(x as usize, y as usize)] = Tile::Empty;
+        }
+        ignited
+    }
+
+    // Sets the given number of random trees on fire, returning their positions
+    pub fn ignite(&mut self, fires: usize, rng: &mut impl Rng) -> Vec<(usize, usize)> {
+        let trees = self
+            .map
+            .iter()
+            .filter(|(_, _, tile)| matches!(tile, Tile::Tree(_)))
+            .map(|(x, y, _)| (x, y))
+            .collect::<Vec<_>>();
+        let ignited = trees
+            .choose_multiple(rng, fires)
+            .copied()
+            .collect::<Vec<_>>();
+        for &(x, y) in &ignited {
+            self.map[(x, y)] = Tile::Fire;
+        }
+        ignited
+    }
+
+    // Knocks down each tree with the given probability, returning the positions of those that fell
+    pub fn storm(&mut self, fraction: f32, rng: &mut impl Rng) -> Vec<(usize, usize)> {
+        let trees = self
+            .map
+            .iter()
+            .filter(|(_, _, tile)| matches!(tile, Tile::Tree(_)))
+            .map(|(x, y, _)| (x, y))
+            .collect::<Vec<_>>();
+        let fallen = trees
+            .into_iter()
+            .filter(|_| rng.gen_bool(fraction.clamp(0., 1.) as f64))
+            .collect::<Vec<_>>();
+        for &(x, y) in &fallen {
+            self.map[(x, y)] = Tile::Empty;
+        }
+        fallen
+    }
+
+    // Places a new agent with the next free id on the tile, returning its id
+    pub fn add_agent(&mut self, x: usize, y: usize, predator: Option<PredatorKind>) -> AgentId {
+        let agent = self
+            .inventory
+            .0
+            .keys()
+            .last()
+            .map_or(AgentId(0), |agent| AgentId(agent.0 + 1));
+        debug_assert_ne!(agent, WORLD_AGENT_ID);
+        self.inventory.0.insert(agent, AgentInventory::initial());
+        if let Some(kind) = predator {
+            self.predators.insert(agent, kind);
+        }
+        self.map[(x, y)] = Tile::Agent(agent);
+        agent
+    }
+
+    pub fn find_agent(&self, agent: AgentId) -> Option<(isize, isize)> {
+        self.map
+            .iter()
+            .find(|(_, _, tile)| *tile == Tile::Agent(agent))
+            .map(|(x, y, _)| (x as isize, y as isize))
     }
 }
 
@@ -72,6 +172,8 @@ impl WorldGlobalState {
 pub struct WorldLocalState {
     pub inventory: InventorySnapshot,
     pub map: TileMapSnapshot,
+    pub predators: BTreeMap<AgentId, PredatorKind>,
+    pub market: Market,
 }
 
 #[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
@@ -79,6 +181,7 @@ pub struct WorldDiff {
     pub ctr: usize,
     pub inventory: InventoryDiff,
     pub map: TileMapDiff,
+    pub market: Option<Market>,
 }
 
 impl WorldDiff {
@@ -89,11 +192,29 @@ impl WorldDiff {
 
 pub trait WorldState {
     fn get_tile(&self, x: isize, y: isize) -> Option<Tile>;
+    fn get_elevation(&self, x: isize, y: isize) -> Option<u8>;
     fn find_agent(&self, agent: AgentId) -> Option<(isize, isize)>;
     fn get_inventory(&self, agent: AgentId) -> usize;
     fn get_total_inventory(&self) -> usize;
     fn get_water(&self, agent: AgentId) -> bool;
+    fn get_food(&self, agent: AgentId) -> usize;
+    fn get_stored(&self, agent: AgentId) -> usize;
+    fn get_axe(&self, agent: AgentId) -> u8;
+    fn get_offer(&self, agent: AgentId) -> Option<AgentId>;
+    fn get_coins(&self, agent: AgentId) -> usize;
+    fn get_market(&self) -> Market;
+    fn team_score(&self, team: Team) -> usize;
+    fn get_energy(&self, agent: AgentId) -> usize;
+    fn hunger(&self, tick: u64, agent: AgentId) -> u64;
+    fn tiredness(&self, tick: u64, agent: AgentId) -> u64;
+    fn is_next_to_shed(&self, agent: AgentId) -> bool;
+    fn predator_kind(&self, agent: AgentId) -> Option<PredatorKind>;
+    fn distance_to_fire(&self, x: isize, y: isize, radius: usize) -> Option<usize>;
     fn trees(&self) -> BTreeSet<(isize, isize)>;
+    fn fires(&self) -> BTreeSet<(isize, isize)>;
+    fn standing_wood(&self) -> usize;
+    fn future_growth(&self) -> usize;
+    fn huts(&self) -> usize;
     fn points_of_interest(&self, f: impl FnMut(isize, isize));
     fn find_nearby_agents(&self, x: isize, y: isize, radius: usize) -> Vec<AgentId>;
 }
@@ -119,6 +240,21 @@ impl WorldState for StateDiffRef<'_, Lumberjacks> {
         }
     }
 
+    fn get_elevation(&self, x: isize, y: isize) -> Option<u8> {
+        let map = &self.initial_state.map;
+        let (x, y) = (x - map.left, y - map.top);
+
+        if x >= 0
+            && x < (config().agents.snapshot_radius * 2 + 1) as isize
+            && y >= 0
+            && y < (config().agents.snapshot_radius * 2 + 1) as isize
+        {
+            Some(map.elevation[y as usize][x as usize])
+        } else {
+            None
+        }
+    }
+
     fn trees(&self) -> BTreeSet<(isize, isize)> {
         let initial_state = self.initial_state;
         let mut set = BTreeSet::new();
@@ -134,6 +270,50 @@ impl WorldState for StateDiffRef<'_, Lumberjacks> {
         set
     }
 
+    fn fires(&self) -> BTreeSet<(isize, isize)> {
+        let initial_state = self.initial_state;
+        let extent = config().agents.snapshot_radius as isize * 2 + 1;
+        (initial_state.map.top..initial_state.map.top + extent)
+            .flat_map(|y| {
+                (initial_state.map.left..initial_state.map.left + extent).map(move |x| (x, y))
+            })
+            .filter(|(x, y)| matches!(self.get_tile(*x, *y), Some(Tile::Fire)))
+            .collect()
+    }
+
+    fn standing_wood(&self) -> usize {
+        self.trees()
+            .into_iter()
+            .map(|(x, y)| match self.get_tile(x, y) {
+                Some(Tile::Tree(height)) => height.get() as usize,
+                _ => 0,
+            })
+            .sum()
+    }
+
+    // The wood that the trees have yet to grow until they reach the tree height of the map
+    fn future_growth(&self) -> usize {
+        let tree_height = config().map.tree_height.get() as usize;
+        self.trees()
+            .into_iter()
+            .map(|(x, y)| match self.get_tile(x, y) {
+                Some(Tile::Tree(height)) => tree_height.saturating_sub(height.get() as usize),
+                _ => 0,
+            })
+            .sum()
+    }
+
+    fn huts(&self) -> usize {
+        let initial_state = self.initial_state;
+        let extent = config().agents.snapshot_radius as isize * 2 + 1;
+        (initial_state.map.top..initial_state.map.top + extent)
+            .flat_map(|y| {
+                (initial_state.map.left..initial_state.map.left + extent).map(move |x| (x, y))
+            })
+            .filter(|(x, y)| matches!(self.get_tile(*x, *y), Some(Tile::Hut)))
+            .count()
+    }
+
     fn points_of_interest(&self, mut f: impl FnMut(isize, isize)) {
         let (start_x, end_x, start_y, end_y) = {
             let extent = config().agents.snapshot_radius as isize * 2 + 1;
@@ -233,6 +413,172 @@ impl WorldState for StateDiffRef<'_, Lumberjacks> {
             })
     }
 
+    fn get_food(&self, agent: AgentId) -> usize {
+        let initial_state = self.initial_state;
+        let diff = self.diff;
+        (initial_state.inventory.0.get(&agent).unwrap().food
+            + diff
+                .inventory
+                .0
+                .get(&agent)
+                .map(|inv| inv.food)
+                .unwrap_or(0)) as usize
+    }
+
+    fn get_stored(&self, agent: AgentId) -> usize {
+        let initial_state = self.initial_state;
+        let diff = self.diff;
+        (initial_state.inventory.0.get(&agent).unwrap().stored
+            + diff
+                .inventory
+                .0
+                .get(&agent)
+                .map(|inv| inv.stored)
+                .unwrap_or(0)) as usize
+    }
+
+    fn get_axe(&self, agent: AgentId) -> u8 {
+        let initial_state = self.initial_state;
+        let diff = self.diff;
+        diff.inventory
+            .0
+            .get(&agent)
+            .map(|inv| inv.axe)
+            .unwrap_or_else(|| {
+                initial_state
+                    .inventory
+                    .0
+                    .get(&agent)
+                    .map(|inv| inv.axe)
+                    .unwrap_or_default()
+            })
+    }
+
+    fn get_coins(&self, agent: AgentId) -> usize {
+        let initial_state = self.initial_state;
+        let diff = self.diff;
+        (initial_state.inventory.0.get(&agent).unwrap().coins
+            + diff
+                .inventory
+                .0
+                .get(&agent)
+                .map(|inv| inv.coins)
+                .unwrap_or(0)) as usize
+    }
+
+    fn get_market(&self) -> Market {
+        self.diff.market.unwrap_or(self.initial_state.market)
+    }
+
+    fn get_offer(&self, agent: AgentId) -> Option<AgentId> {
+        let initial_state = self.initial_state;
+        let diff = self.diff;
+        diff.inventory
+            .0
+            .get(&agent)
+            .map(|inv| inv.offer)
+            .unwrap_or_else(|| {
+                initial_state
+                    .inventory
+                    .0
+                    .get(&agent)
+                    .and_then(|inv| inv.offer)
+            })
+    }
+
+    fn get_energy(&self, agent: AgentId) -> usize {
+        let initial_state = self.initial_state;
+        let diff = self.diff;
+        (initial_state.inventory.0.get(&agent).unwrap().energy
+            + diff
+                .inventory
+                .0
+                .get(&agent)
+                .map(|inv| inv.energy)
+                .unwrap_or(0)) as usize
+    }
+
+    // Ticks since the last meal
+    fn hunger(&self, tick: u64, agent: AgentId) -> u64 {
+        let last_meal = self
+            .diff
+            .inventory
+            .0
+            .get(&agent)
+            .map(|inv| inv.last_meal)
+            .unwrap_or_else(|| {
+                self.initial_state
+                    .inventory
+                    .0
+                    .get(&agent)
+                    .unwrap()
+                    .last_meal
+            });
+        tick.saturating_sub(last_meal)
+    }
+
+    // Ticks since the last rest
+    fn tiredness(&self, tick: u64, agent: AgentId) -> u64 {
+        let last_rest = self
+            .diff
+            .inventory
+            .0
+            .get(&agent)
+            .map(|inv| inv.last_rest)
+            .unwrap_or_else(|| {
+                self.initial_state
+                    .inventory
+                    .0
+                    .get(&agent)
+                    .unwrap()
+                    .last_rest
+            });
+        tick.saturating_sub(last_rest)
+    }
+
+    fn team_score(&self, team: Team) -> usize {
+        self.initial_state
+            .inventory
+            .0
+            .keys()
+            .filter(|agent| Team::of(**agent) == team && self.predator_kind(**agent).is_none())
+            .map(|agent| self.get_inventory(*agent) + self.get_stored(*agent))
+            .sum()
+    }
+
+    fn is_next_to_shed(&self, agent: AgentId) -> bool {
+        if let Some((x, y)) = self.find_agent(agent) {
+            DIRECTIONS.iter().any(|direction| {
+                let (x, y) = apply_direction(*direction, x, y);
+                matches!(self.get_tile(x, y), Some(Tile::Shed))
+            })
+        } else {
+            false
+        }
+    }
+
+    fn predator_kind(&self, agent: AgentId) -> Option<PredatorKind> {
+        self.initial_state.predators.get(&agent).copied()
+    }
+
+    // Manhattan distance to the closest fire within radius, if any
+    fn distance_to_fire(&self, x: isize, y: isize, radius: usize) -> Option<usize> {
+        let radius = radius as isize;
+        let mut closest = None;
+        for dy in -radius..=radius {
+            for dx in -radius..=radius {
+                let distance = (dx.abs() + dy.abs()) as usize;
+                if distance as isize <= radius
+                    && closest.map_or(true, |closest| distance < closest)
+                    && matches!(self.get_tile(x + dx, y + dy), Some(Tile::Fire))
+                {
+                    closest = Some(distance);
+                }
+            }
+        }
+        closest
+    }
+
     fn find_nearby_agents(&self, x: isize, y: isize, radius: usize) -> Vec<AgentId> {
         let mut vec = Vec::new();
 
@@ -255,6 +601,46 @@ pub trait WorldStateMut {
     fn increment_inventory(&mut self, agent: AgentId);
     fn decrement_inventory(&mut self, agent: AgentId);
     fn set_water(&mut self, agent: AgentId, value: bool);
+    fn increment_food(&mut self, agent: AgentId);
+    fn set_axe(&mut self, agent: AgentId, durability: u8);
+    fn store_wood(&mut self, agent: AgentId);
+    fn set_offer(&mut self, agent: AgentId, seller: Option<AgentId>);
+    fn transfer_wood(&mut self, from: AgentId, to: AgentId, amount: usize);
+    fn add_energy(&mut self, agent: AgentId, amount: isize);
+    fn set_last_meal(&mut self, agent: AgentId, tick: u64);
+    fn set_last_rest(&mut self, agent: AgentId, tick: u64);
+    fn sell_wood(&mut self, agent: AgentId);
+    fn set_market(&mut self, market: Market);
+}
+
+// Returns the inventory diff of an agent, creating it if needed.
+// This is cumbersome because the diff has a real diff for the wood, the stored wood,
+// the food, the energy and the coins (+= diff.wood), but for the water, the axe, the offer and the
+// ticks of the last meal and rest it is an override (= diff.water), so we need to fetch
+// these from the snapshot when we create a new inventory diff
+fn inventory_diff_mut<'a>(
+    state_diff: &'a mut StateDiffRefMut<'_, Lumberjacks>,
+    agent: AgentId,
+) -> &'a mut AgentInventory {
+    let snapshot = state_diff.initial_state.inventory.0.get(&agent);
+    let water = snapshot.map(|inv| inv.water).unwrap_or_default();
+    let axe = snapshot.map(|inv| inv.axe).unwrap_or_default();
+    let offer = snapshot.and_then(|inv| inv.offer);
+    let last_meal = snapshot.map(|inv| inv.last_meal).unwrap_or_default();
+    let last_rest = snapshot.map(|inv| inv.last_rest).unwrap_or_default();
+    state_diff
+        .diff
+        .inventory
+        .0
+        .entry(agent)
+        .or_insert_with(|| AgentInventory {
+            water,
+            axe,
+            offer,
+            last_meal,
+            last_rest,
+            ..Default::default()
+        })
 }
 
 impl WorldStateMut for StateDiffRefMut<'_, Lumberjacks> {
@@ -305,48 +691,68 @@ impl WorldStateMut for StateDiffRefMut<'_, Lumberjacks> {
     }
 
     fn increment_inventory(&mut self, agent: AgentId) {
-        let snapshot = self.initial_state;
-        // this is cumbersome because the diff has a real diff for the tree (+= diff.tree)
-        // but for the water it is an override (= diff.water), so we need to fetch the
-        // water from the snapshot when we create a new inventory diff
-        self.diff
-            .inventory
-            .0
-            .entry(agent)
-            .or_insert_with(|| AgentInventory {
-                wood: 0,
-                water: snapshot
-                    .inventory
-                    .0
-                    .get(&agent)
-                    .map(|inv| inv.water)
-                    .unwrap_or_default(),
-            })
-            .wood += 1;
+        inventory_diff_mut(self, agent).wood += 1;
     }
 
     fn decrement_inventory(&mut self, agent: AgentId) {
-        let snapshot = self.initial_state;
-        // this is cumbersome because the diff has a real diff for the tree (+= diff.tree)
-        // but for the water it is an override (= diff.water), so we need to fetch the
-        // water from the snapshot when we create a new inventory diff
-        self.diff
-            .inventory
-            .0
-            .entry(agent)
-            .or_insert_with(|| AgentInventory {
-                wood: 0,
-                water: snapshot
-                    .inventory
-                    .0
-                    .get(&agent)
-                    .map(|inv| inv.water)
-                    .unwrap_or_default(),
-            })
-            .wood -= 1;
+        inventory_diff_mut(self, agent).wood -= 1;
     }
 
     fn set_water(&mut self, agent: AgentId, value: bool) {
-        self.diff.inventory.0.entry(agent).or_default().water = value;
+        inventory_diff_mut(self, agent).water = value;
+    }
+
+    fn increment_food(&mut self, agent: AgentId) {
+        inventory_diff_mut(self, agent).food += 1;
+    }
+
+    fn set_axe(&mut self, agent: AgentId, durability: u8) {
+        inventory_diff_mut(self, agent).axe = durability;
+    }
+
+    fn set_offer(&mut self, agent: AgentId, seller: Option<AgentId>) {
+        inventory_diff_mut(self, agent).offer = seller;
+    }
+
+    fn transfer_wood(&mut self, from: AgentId, to: AgentId, amount: usize) {
+        inventory_diff_mut(self, from).wood -= amount as isize;
+        inventory_diff_mut(self, to).wood += amount as isize;
+    }
+
+    fn add_energy(&mut self, agent: AgentId, amount: isize) {
+        inventory_diff_mut(self, agent).energy += amount;
+    }
+
+    fn set_last_meal(&mut self, agent: AgentId, tick: u64) {
+        inventory_diff_mut(self, agent).last_meal = tick;
+    }
+
+    fn set_last_rest(&mut self, agent: AgentId, tick: u64) {
+        inventory_diff_mut(self, agent).last_rest = tick;
+    }
+
+    fn sell_wood(&mut self, agent: AgentId) {
+        let wood = self.get_inventory(agent);
+        let mut market = self.get_market();
+        market.sold += wood as u32;
+        let inventory = inventory_diff_mut(self, agent);
+        inventory.wood -= wood as isize;
+        inventory.coins += (wood * market.price as usize) as isize;
+        self.set_market(market);
+    }
+
+    fn set_market(&mut self, market: Market) {
+        self.diff.market = if market == self.initial_state.market {
+            None
+        } else {
+            Some(market)
+        };
+    }
+
+    fn store_wood(&mut self, agent: AgentId) {
+        let wood = self.get_inventory(agent) as isize;
+        let inventory = inventory_diff_mut(self, agent);
+        inventory.wood -= wood;
+        inventory.stored += wood;
     }
 }